@@ -0,0 +1,219 @@
+//! Async variant of the coupler based on `futures`.
+//!
+//! This module is only available with the `futures` feature.
+//!
+//! The `AsyncCoupler` shares the coupler with async readers and writers
+//! of the communication modules. Every cycle wakes up the tasks that
+//! wait for received data, so the message processing does not
+//! block any thread.
+
+use super::*;
+use crate::ur20_fbc_mod_tcp::Coupler;
+use futures::io::{AsyncRead, AsyncWrite};
+use std::{
+    collections::HashMap,
+    io,
+    pin::Pin,
+    sync::{Arc, Mutex, MutexGuard},
+    task::{Context, Poll, Waker},
+};
+
+#[derive(Debug, Default)]
+struct Wakers(HashMap<usize, Vec<Waker>>);
+
+/// A coupler that can be shared with async tasks.
+#[derive(Debug, Clone)]
+pub struct AsyncCoupler {
+    coupler: Arc<Mutex<Coupler>>,
+    wakers: Arc<Mutex<Wakers>>,
+}
+
+fn lock<T>(m: &Mutex<T>) -> MutexGuard<'_, T> {
+    m.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+impl AsyncCoupler {
+    pub fn new(coupler: Coupler) -> Self {
+        AsyncCoupler {
+            coupler: Arc::new(Mutex::new(coupler)),
+            wakers: Arc::new(Mutex::new(Wakers::default())),
+        }
+    }
+
+    /// Gives access to the coupler (e.g. to set outputs).
+    ///
+    /// Do not hold the guard across an `.await`.
+    pub fn coupler(&self) -> MutexGuard<'_, Coupler> {
+        lock(&self.coupler)
+    }
+
+    /// Processes the data of a cycle (see `Coupler::next`)
+    /// and wakes up all waiting readers.
+    pub async fn next(
+        &mut self,
+        process_input: &[u16],
+        process_output: &[u16],
+    ) -> Result<Vec<u16>> {
+        let res = self.coupler().next(process_input, process_output);
+        let wakers: Vec<_> = lock(&self.wakers).0.drain().flat_map(|(_, w)| w).collect();
+        for w in wakers {
+            w.wake();
+        }
+        res
+    }
+
+    /// Returns an async reader for a communication module.
+    pub fn reader(&self, module_nr: usize) -> Option<AsyncComReader> {
+        self.coupler().reader(module_nr)?;
+        Some(AsyncComReader {
+            module_nr,
+            inner: self.clone(),
+        })
+    }
+
+    /// Returns an async writer for a communication module.
+    pub fn writer(&self, module_nr: usize) -> Option<AsyncComWriter> {
+        self.coupler().writer(module_nr)?;
+        Some(AsyncComWriter {
+            module_nr,
+            inner: self.clone(),
+        })
+    }
+}
+
+fn unknown_module() -> io::Error {
+    io::Error::new(io::ErrorKind::NotFound, "no communication module")
+}
+
+/// Reads the received data of a communication module.
+///
+/// A read is pending until the next cycle delivered new data.
+#[derive(Debug)]
+pub struct AsyncComReader {
+    module_nr: usize,
+    inner: AsyncCoupler,
+}
+
+impl AsyncRead for AsyncComReader {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        let mut c = self.inner.coupler();
+        let reader = c.reader(self.module_nr).ok_or_else(unknown_module)?;
+        match reader.read(buf)? {
+            0 if !buf.is_empty() => {
+                lock(&self.inner.wakers)
+                    .0
+                    .entry(self.module_nr)
+                    .or_default()
+                    .push(cx.waker().clone());
+                Poll::Pending
+            }
+            n => Poll::Ready(Ok(n)),
+        }
+    }
+}
+
+/// Writes data that is sent by a communication module.
+///
+/// The data is queued and transmitted within the next cycles.
+#[derive(Debug)]
+pub struct AsyncComWriter {
+    module_nr: usize,
+    inner: AsyncCoupler,
+}
+
+impl AsyncWrite for AsyncComWriter {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        _: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let mut c = self.inner.coupler();
+        let writer = c.writer(self.module_nr).ok_or_else(unknown_module)?;
+        Poll::Ready(writer.write(buf))
+    }
+    fn poll_flush(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let mut c = self.inner.coupler();
+        let writer = c.writer(self.module_nr).ok_or_else(unknown_module)?;
+        Poll::Ready(writer.flush())
+    }
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.poll_flush(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::ur20_1com_232_485_422::{OperatingMode, ProcessDataLength};
+    use crate::ur20_fbc_mod_tcp::CouplerConfig;
+    use futures::{executor::block_on, future, io::AsyncReadExt, io::AsyncWriteExt};
+    use num_traits::ToPrimitive;
+    use std::future::Future;
+
+    fn coupler() -> Coupler {
+        Coupler::new(&CouplerConfig {
+            modules: vec![ModuleType::UR20_4DI_P, ModuleType::UR20_1COM_232_485_422],
+            offsets: vec![0xFFFF, 0x0000, 0x8000, 0x0010],
+            params: vec![
+                vec![0; 4],
+                vec![
+                    ProcessDataLength::EightBytes.to_u16().unwrap(),
+                    OperatingMode::RS232.to_u16().unwrap(),
+                    0,
+                    0,
+                    0,
+                    0,
+                    0,
+                    0,
+                    0,
+                    0,
+                ],
+            ],
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn no_reader_and_writer_for_other_modules() {
+        let c = AsyncCoupler::new(coupler());
+        assert!(c.reader(0).is_none());
+        assert!(c.writer(2).is_none());
+        assert!(c.reader(1).is_some());
+        assert!(c.writer(1).is_some());
+    }
+
+    #[test]
+    fn pending_read_is_woken_up_by_next_cycle() {
+        let mut c = AsyncCoupler::new(coupler());
+        let mut reader = c.reader(1).unwrap();
+        let mut buf = [0; 3];
+        let mut read = reader.read(&mut buf);
+        block_on(future::poll_fn(|cx| {
+            assert!(Pin::new(&mut read).poll(cx).is_pending());
+            Poll::Ready(())
+        }));
+        assert_eq!(c.wakers.lock().unwrap().0[&1].len(), 1);
+
+        // initialize the message processor
+        let output = block_on(c.next(&[0; 5], &[0; 4])).unwrap();
+        let output = block_on(c.next(&[0; 5], &output)).unwrap();
+        assert!(c.wakers.lock().unwrap().0.is_empty());
+        // receive "abc"
+        block_on(c.next(&[0x0000, 0x0389, 0x6261, 0x0063, 0x0000], &output)).unwrap();
+        assert_eq!(block_on(read).unwrap(), 3);
+        assert_eq!(&buf, b"abc");
+    }
+
+    #[test]
+    fn write_data() {
+        let c = AsyncCoupler::new(coupler());
+        let mut writer = c.writer(1).unwrap();
+        block_on(writer.write_all(b"hello")).unwrap();
+        block_on(writer.flush()).unwrap();
+    }
+}
@@ -0,0 +1,189 @@
+//! Groups of digital channels that represent a number.
+//!
+//! Some field devices use several digital signals for a single value,
+//! e.g. a selector switch with a BCD output on four input channels.
+
+use super::*;
+
+/// Greatest number of channels within a group.
+pub const MAX_GROUP_SIZE: usize = 32;
+
+/// How the number is encoded into the channel states.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BitEncoding {
+    /// Dual number.
+    Binary,
+    /// Binary coded decimal digits (four channels per digit).
+    Bcd,
+}
+
+/// Named group of digital channels.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BitGroup {
+    /// A unique name of the group (e.g. `recipe_selector`).
+    pub name: String,
+    /// The channels beginning with the least significant bit.
+    pub channels: Vec<Address>,
+    pub encoding: BitEncoding,
+}
+
+impl BitGroup {
+    pub fn new(name: &str, channels: Vec<Address>, encoding: BitEncoding) -> Self {
+        BitGroup {
+            name: name.into(),
+            channels,
+            encoding,
+        }
+    }
+
+    /// Greatest number that can be represented by the group.
+    pub fn max_value(&self) -> u32 {
+        let n = self.channels.len();
+        match self.encoding {
+            BitEncoding::Binary if n >= 32 => u32::MAX,
+            BitEncoding::Binary => (1 << n) - 1,
+            BitEncoding::Bcd => 10u32.saturating_pow((n / 4) as u32) - 1,
+        }
+    }
+
+    /// Decodes the number based on the channel values of all modules.
+    ///
+    /// Returns `None` if a channel has no valid value
+    /// or a BCD digit is greater than nine.
+    pub fn decode(&self, values: &[Vec<ChannelValue>]) -> Option<u32> {
+        let mut raw = 0u32;
+        for (i, addr) in self.channels.iter().enumerate() {
+            match values.get(addr.module).and_then(|m| m.get(addr.channel)) {
+                Some(ChannelValue::Bit(true)) => raw |= 1 << i,
+                Some(ChannelValue::Bit(false)) => {}
+                _ => return None,
+            }
+        }
+        match self.encoding {
+            BitEncoding::Binary => Some(raw),
+            BitEncoding::Bcd => {
+                let mut v = 0;
+                for d in (0..self.channels.len() / 4).rev() {
+                    let digit = (raw >> (d * 4)) & 0xF;
+                    if digit > 9 {
+                        return None;
+                    }
+                    v = v * 10 + digit;
+                }
+                Some(v)
+            }
+        }
+    }
+
+    /// Encodes a number into the states of the channels.
+    pub fn encode(&self, value: u32) -> Result<Vec<(Address, bool)>> {
+        if value > self.max_value() {
+            return Err(Error::ChannelValue);
+        }
+        let raw = match self.encoding {
+            BitEncoding::Binary => value,
+            BitEncoding::Bcd => {
+                let mut raw = 0;
+                let mut v = value;
+                for d in 0..self.channels.len() / 4 {
+                    raw |= (v % 10) << (d * 4);
+                    v /= 10;
+                }
+                raw
+            }
+        };
+        Ok(self
+            .channels
+            .iter()
+            .enumerate()
+            .map(|(i, addr)| (*addr, raw & (1 << i) != 0))
+            .collect())
+    }
+
+    pub(crate) fn validate(&self) -> Result<()> {
+        let n = self.channels.len();
+        if n == 0 || n > MAX_GROUP_SIZE {
+            return Err(Error::ChannelParameter);
+        }
+        if self.encoding == BitEncoding::Bcd && n % 4 != 0 {
+            return Err(Error::ChannelParameter);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::ChannelValue::*;
+
+    fn channels(n: usize) -> Vec<Address> {
+        (0..n)
+            .map(|channel| Address { module: 0, channel })
+            .collect()
+    }
+
+    #[test]
+    fn decode_binary_and_bcd() {
+        let mut g = BitGroup::new("selector", channels(8), BitEncoding::Binary);
+        let values = vec![vec![
+            Bit(true),
+            Bit(false),
+            Bit(false),
+            Bit(true),
+            Bit(false),
+            Bit(true),
+            Bit(false),
+            Bit(false),
+        ]];
+        assert_eq!(g.decode(&values), Some(0x29));
+        g.encoding = BitEncoding::Bcd;
+        assert_eq!(g.decode(&values), Some(29));
+        assert_eq!(g.decode(&[vec![Bit(true); 8]]), Option::None);
+        let mut values = values;
+        values[0][3] = Disabled;
+        assert_eq!(g.decode(&values), Option::None);
+        assert_eq!(g.decode(&[]), Option::None);
+    }
+
+    #[test]
+    fn encode_binary_and_bcd() {
+        let mut g = BitGroup::new("display", channels(8), BitEncoding::Bcd);
+        assert_eq!(g.max_value(), 99);
+        let bits: Vec<_> = g.encode(42).unwrap().into_iter().map(|(_, b)| b).collect();
+        assert_eq!(
+            bits,
+            vec![false, true, false, false, false, false, true, false]
+        );
+        assert_eq!(g.encode(100), Err(Error::ChannelValue));
+        g.encoding = BitEncoding::Binary;
+        assert_eq!(g.max_value(), 255);
+        let values = vec![g
+            .encode(200)
+            .unwrap()
+            .into_iter()
+            .map(|(_, b)| Bit(b))
+            .collect()];
+        assert_eq!(g.decode(&values), Some(200));
+        assert_eq!(g.encode(256), Err(Error::ChannelValue));
+        let g = BitGroup::new("all", channels(32), BitEncoding::Binary);
+        assert_eq!(g.max_value(), u32::MAX);
+    }
+
+    #[test]
+    fn validate_groups() {
+        assert!(BitGroup::new("a", channels(4), BitEncoding::Bcd)
+            .validate()
+            .is_ok());
+        assert!(BitGroup::new("a", channels(6), BitEncoding::Bcd)
+            .validate()
+            .is_err());
+        assert!(BitGroup::new("a", vec![], BitEncoding::Binary)
+            .validate()
+            .is_err());
+        assert!(BitGroup::new("a", channels(33), BitEncoding::Binary)
+            .validate()
+            .is_err());
+    }
+}
@@ -0,0 +1,123 @@
+//! Field calibration of analog channels.
+
+use super::*;
+
+/// Linear correction of an analog channel value
+/// (`calibrated = raw * gain + offset`).
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Calibration {
+    pub offset: f32,
+    pub gain: f32,
+}
+
+impl Default for Calibration {
+    fn default() -> Self {
+        Calibration {
+            offset: 0.0,
+            gain: 1.0,
+        }
+    }
+}
+
+impl Calibration {
+    pub fn new(offset: f32, gain: f32) -> Result<Self> {
+        if !offset.is_finite() || !gain.is_finite() || gain == 0.0 {
+            return Err(Error::ChannelParameter);
+        }
+        Ok(Calibration { offset, gain })
+    }
+
+    /// Converts a raw value into a calibrated value.
+    pub fn apply(&self, v: &ChannelValue) -> ChannelValue {
+        match *v {
+            ChannelValue::Decimal32(x) => ChannelValue::Decimal32(x * self.gain + self.offset),
+            ref x => x.clone(),
+        }
+    }
+
+    /// Converts a calibrated value back into a raw value.
+    pub fn revert(&self, v: &ChannelValue) -> ChannelValue {
+        match *v {
+            ChannelValue::Decimal32(x) => ChannelValue::Decimal32((x - self.offset) / self.gain),
+            ref x => x.clone(),
+        }
+    }
+}
+
+/// The calibration of a single channel.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ChannelCalibration {
+    pub address: Address,
+    pub calibration: Calibration,
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::ChannelValue::*;
+
+    #[test]
+    fn create_calibration() {
+        assert!(Calibration::new(0.5, 1.1).is_ok());
+        assert!(Calibration::new(0.5, 0.0).is_err());
+        assert!(Calibration::new(f32::NAN, 1.0).is_err());
+        assert!(Calibration::new(0.0, f32::INFINITY).is_err());
+    }
+
+    #[test]
+    fn apply_and_revert() {
+        let c = Calibration::new(1.0, 2.0).unwrap();
+        assert_eq!(c.apply(&Decimal32(3.0)), Decimal32(7.0));
+        assert_eq!(c.revert(&Decimal32(7.0)), Decimal32(3.0));
+        assert_eq!(c.apply(&Bit(true)), Bit(true));
+        assert_eq!(c.revert(&Disabled), Disabled);
+        let d = Calibration::default();
+        assert_eq!(d.apply(&Decimal32(3.0)), Decimal32(3.0));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serialize_channel_calibration() {
+        use serde_test::{assert_tokens, Token};
+        let c = ChannelCalibration {
+            address: Address {
+                module: 1,
+                channel: 2,
+            },
+            calibration: Calibration::new(0.5, 2.0).unwrap(),
+        };
+        assert_tokens(
+            &c,
+            &[
+                Token::Struct {
+                    name: "ChannelCalibration",
+                    len: 2,
+                },
+                Token::Str("address"),
+                Token::Struct {
+                    name: "Address",
+                    len: 2,
+                },
+                Token::Str("module"),
+                Token::U64(1),
+                Token::Str("channel"),
+                Token::U64(2),
+                Token::StructEnd,
+                Token::Str("calibration"),
+                Token::Struct {
+                    name: "Calibration",
+                    len: 2,
+                },
+                Token::Str("offset"),
+                Token::F32(0.5),
+                Token::Str("gain"),
+                Token::F32(2.0),
+                Token::StructEnd,
+                Token::StructEnd,
+            ],
+        );
+    }
+}
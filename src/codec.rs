@@ -0,0 +1,488 @@
+//! Conversion between raw register values and physical values.
+//!
+//! These are the conversions that are used by the module implementations.
+//! They can be used for ad-hoc register access as well.
+
+use super::*;
+
+/// Encodes a current or voltage value (mA or V) into a register value.
+///
+/// Values outside of the range are not clamped;
+/// `Disabled` channels are encoded as `0`.
+pub fn analog_ui_value_to_u16(v: f32, range: &AnalogUIRange, format: &DataFormat) -> u16 {
+    let factor = format.factor();
+    use crate::AnalogUIRange::*;
+
+    #[rustfmt::skip]
+    let v = match *range {
+        mA0To20       => factor * v / 20.0 ,
+        mA4To20       => factor * (v - 4.0) / 16.0 ,
+        V0To10        |
+        VMinus10To10  => factor * v / 10.0 ,
+        V0To5         |
+        VMinus5To5    => factor * v / 5.0 ,
+        V1To5         => factor * (v - 1.0) / 4.0 ,
+        V2To10        => factor * (v - 2.0) / 8.0 ,
+        Disabled      => 0.0,
+    };
+    v as i16 as u16
+}
+
+/// Decodes a register value into a current or voltage value (mA or V).
+///
+/// The register is interpreted as a signed 16-Bit value
+/// scaled by the factor of the data format (S5 or S7).
+/// Returns `None` for `Disabled` channels.
+#[rustfmt::skip]
+pub fn u16_to_analog_ui_value(
+    data: u16,
+    range: &AnalogUIRange,
+    format: &DataFormat,
+) -> Option<f32> {
+    let factor = format.factor();
+    use crate::AnalogUIRange::*;
+    let data = f32::from(data as i16);
+
+    match *range {
+        mA0To20         => Some(data * 20.0 / factor),
+        mA4To20         => Some(data * 16.0 / factor + 4.0),
+        V0To10          |
+        VMinus10To10    => Some(data * 10.0 / factor),
+        V0To5           |
+        VMinus5To5      => Some(data * 5.0 / factor),
+        V1To5           => Some(data * 4.0 / factor + 1.0),
+        V2To10          => Some(data * 8.0 / factor + 2.0),
+        Disabled        => None,
+    }
+}
+
+/// Decodes a register value into a current value (mA).
+///
+/// Returns `None` for `Disabled` channels.
+#[rustfmt::skip]
+pub fn u16_to_analog_i_value(data: u16, range: &AnalogIRange, format: &DataFormat) -> Option<f32> {
+    let factor = format.factor();
+    use crate::AnalogIRange::*;
+    let data = f32::from(data as i16);
+
+    match *range {
+        mA0To20  => Some(data * 20.0 / factor),
+        mA4To20  => Some(data * 16.0 / factor + 4.0),
+        Disabled => None,
+    }
+}
+
+/// Encodes a current value (mA) into a register value.
+///
+/// Values outside of the range are not clamped;
+/// `Disabled` channels are encoded as `0`.
+pub fn analog_i_value_to_u16(v: f32, range: &AnalogIRange, format: &DataFormat) -> u16 {
+    let factor = format.factor();
+    use crate::AnalogIRange::*;
+
+    #[rustfmt::skip]
+    let v = match *range {
+        mA0To20  => factor * v / 20.0,
+        mA4To20  => factor * (v - 4.0) / 16.0,
+        Disabled => 0.0,
+    };
+    v as i16 as u16
+}
+
+/// Linear scaling of an analog current or voltage channel.
+///
+/// It is used to interpret the register value of a channel
+/// in another data format than the parameterized one.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AnalogScaling {
+    /// The parameterized data format.
+    pub format: DataFormat,
+    /// The value of the register value `0` (e.g. `4.0` for `mA4To20`).
+    pub offset: f32,
+}
+
+impl AnalogScaling {
+    /// Returns `None` for `Disabled` channels.
+    pub fn ui(range: &AnalogUIRange, format: &DataFormat) -> Option<Self> {
+        u16_to_analog_ui_value(0, range, format).map(|offset| AnalogScaling {
+            format: format.clone(),
+            offset,
+        })
+    }
+
+    /// Returns `None` for `Disabled` channels.
+    pub fn i(range: &AnalogIRange, format: &DataFormat) -> Option<Self> {
+        u16_to_analog_i_value(0, range, format).map(|offset| AnalogScaling {
+            format: format.clone(),
+            offset,
+        })
+    }
+
+    /// Converts a value that was decoded with the parameterized
+    /// data format into the value of the same register in `format`.
+    pub fn convert(&self, v: f32, format: &DataFormat) -> f32 {
+        (v - self.offset) * self.format.factor() / format.factor() + self.offset
+    }
+
+    /// Converts a value in `format` into the value that
+    /// has to be encoded with the parameterized data format.
+    pub fn revert(&self, v: f32, format: &DataFormat) -> f32 {
+        (v - self.offset) * format.factor() / self.format.factor() + self.offset
+    }
+}
+
+/// Smallest register value that is reported for an overload
+/// (and negated the greatest value for an underload).
+const OVERLOAD: i16 = 0x7F00;
+
+/// Decodes a register value into a current or voltage value (mA or V)
+/// and detects values outside of the measurement range.
+///
+/// The modules report values beyond the overshoot range
+/// as `0x7F00` and above (`0x8100` and below for an underload).
+/// Returns `None` for `Disabled` channels.
+pub fn u16_to_checked_analog_ui_value(
+    data: u16,
+    range: &AnalogUIRange,
+    format: &DataFormat,
+) -> Option<AnalogValue> {
+    use crate::AnalogUIRange::*;
+    let v = u16_to_analog_ui_value(data, range, format)?;
+    let live_zero = matches!(*range, mA4To20 | V1To5 | V2To10);
+    Some(check_analog_value(data, v, live_zero))
+}
+
+/// Decodes a register value into a current value (mA)
+/// and detects values outside of the measurement range
+/// (see `u16_to_checked_analog_ui_value`).
+pub fn u16_to_checked_analog_i_value(
+    data: u16,
+    range: &AnalogIRange,
+    format: &DataFormat,
+) -> Option<AnalogValue> {
+    let v = u16_to_analog_i_value(data, range, format)?;
+    let live_zero = *range == AnalogIRange::mA4To20;
+    Some(check_analog_value(data, v, live_zero))
+}
+
+fn check_analog_value(data: u16, v: f32, live_zero: bool) -> AnalogValue {
+    let data = data as i16;
+    if data >= OVERLOAD {
+        AnalogValue::Overload
+    } else if data <= -OVERLOAD {
+        if live_zero {
+            AnalogValue::WireBreak
+        } else {
+            AnalogValue::Underload
+        }
+    } else {
+        AnalogValue::InRange(v)
+    }
+}
+
+/// Decodes a register value of an RTD channel.
+///
+/// Temperature sensors report a signed value in 0.1 °C;
+/// resistance ranges are scaled to Ohm (`0x6C00` is the end of the range).
+/// Returns `None` for `Disabled` channels.
+#[rustfmt::skip]
+pub fn u16_to_rtd_value(data: u16, range: &RtdRange) -> Option<f32> {
+    use crate::RtdRange::*;
+
+    match *range {
+        PT100  |
+        PT200  |
+        PT500  |
+        PT1000 |
+        NI100  |
+        NI120  |
+        NI200  |
+        NI500  |
+        NI1000 |
+        Cu10   => {
+            Some(f32::from(data as i16) / 10.0)
+        }
+        R40   |
+        R80   |
+        R150  |
+        R300  |
+        R500  |
+        R1000 |
+        R2000 |
+        R4000 => {
+            let n = match *range {
+                R40   => 40.0,
+                R80   => 80.0,
+                R150  => 150.0,
+                R300  => 300.0,
+                R500  => 500.0,
+                R1000 => 1000.0,
+                R2000 => 2000.0,
+                R4000 => 4000.0,
+                _ => {
+                    unreachable!()
+                }
+            };
+            let d = n * u32::from(data) as f32 / 0x6C00 as f32;
+            Some(d)
+        }
+        Disabled => None
+    }
+}
+
+/// Converts a raw thermocouple module value into a temperature or voltage.
+///
+/// Temperatures are reported as signed value in 0.1 °C;
+/// voltage ranges are scaled to `±0x6C00` and converted into `V`.
+/// Returns `None` for `Disabled` channels.
+#[rustfmt::skip]
+pub fn u16_to_tc_value(data: u16, range: &TcRange) -> Option<f32> {
+    use crate::TcRange::*;
+
+    let mv = match *range {
+        J | K | N | R | S | T | B | C | E | L | U => {
+            return Some(f32::from(data as i16) / 10.0);
+        }
+        mV15   => 15.625,
+        mV31   => 31.25,
+        mV62   => 62.5,
+        mV125  => 125.0,
+        mV250  => 250.0,
+        mV500  => 500.0,
+        mV1000 => 1000.0,
+        Disabled => return None,
+    };
+    Some(mv * f32::from(data as i16) / f32::from(S7_FACTOR) / 1000.0)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_u16_to_analog_ui_value() {
+        assert_eq!(
+            u16_to_analog_ui_value(0x3600, &AnalogUIRange::mA0To20, &DataFormat::S7),
+            Some(10.0)
+        );
+        assert_eq!(
+            u16_to_analog_ui_value(0x2000, &AnalogUIRange::mA0To20, &DataFormat::S5),
+            Some(10.0)
+        );
+
+        assert_eq!(
+            u16_to_analog_ui_value(0x3600, &AnalogUIRange::mA4To20, &DataFormat::S7),
+            Some(12.0)
+        );
+        assert_eq!(
+            u16_to_analog_ui_value(0x2000, &AnalogUIRange::mA4To20, &DataFormat::S5),
+            Some(12.0)
+        );
+
+        assert_eq!(
+            u16_to_analog_ui_value(0x3600, &AnalogUIRange::V0To10, &DataFormat::S7),
+            Some(5.0)
+        );
+        assert_eq!(
+            u16_to_analog_ui_value(0x2000, &AnalogUIRange::V0To10, &DataFormat::S5),
+            Some(5.0)
+        );
+
+        assert_eq!(
+            u16_to_analog_ui_value(0x3600, &AnalogUIRange::VMinus10To10, &DataFormat::S7),
+            Some(5.0)
+        );
+        assert_eq!(
+            u16_to_analog_ui_value(0x2000, &AnalogUIRange::VMinus10To10, &DataFormat::S5),
+            Some(5.0)
+        );
+
+        assert_eq!(
+            u16_to_analog_ui_value(0x3600, &AnalogUIRange::V2To10, &DataFormat::S7),
+            Some(6.0)
+        );
+        assert_eq!(
+            u16_to_analog_ui_value(0x2000, &AnalogUIRange::V2To10, &DataFormat::S5),
+            Some(6.0)
+        );
+
+        assert_eq!(
+            u16_to_analog_ui_value(0x3600, &AnalogUIRange::V1To5, &DataFormat::S7),
+            Some(3.0)
+        );
+        assert_eq!(
+            u16_to_analog_ui_value(0x2000, &AnalogUIRange::V1To5, &DataFormat::S5),
+            Some(3.0)
+        );
+
+        assert_eq!(
+            u16_to_analog_ui_value(0x3600, &AnalogUIRange::V0To5, &DataFormat::S7),
+            Some(2.5)
+        );
+        assert_eq!(
+            u16_to_analog_ui_value(0x2000, &AnalogUIRange::V0To5, &DataFormat::S5),
+            Some(2.5)
+        );
+
+        assert_eq!(
+            u16_to_analog_ui_value(0x3600, &AnalogUIRange::VMinus5To5, &DataFormat::S7),
+            Some(2.5)
+        );
+        assert_eq!(
+            u16_to_analog_ui_value(0xCA00, &AnalogUIRange::VMinus5To5, &DataFormat::S7),
+            Some(-2.5)
+        );
+        assert_eq!(
+            u16_to_analog_ui_value(0x2000, &AnalogUIRange::VMinus5To5, &DataFormat::S5),
+            Some(2.5)
+        );
+        assert_eq!(
+            u16_to_analog_ui_value(0xE000, &AnalogUIRange::VMinus5To5, &DataFormat::S5),
+            Some(-2.5)
+        );
+        assert_eq!(
+            u16_to_analog_ui_value(0xE000, &AnalogUIRange::Disabled, &DataFormat::S5),
+            None
+        );
+    }
+
+    #[test]
+    fn test_u16_to_checked_analog_value() {
+        use crate::AnalogValue::*;
+        let s7 = DataFormat::S7;
+        assert_eq!(
+            u16_to_checked_analog_ui_value(0x3600, &AnalogUIRange::mA0To20, &s7),
+            Some(InRange(10.0))
+        );
+        assert_eq!(
+            u16_to_checked_analog_ui_value(0x7EFF, &AnalogUIRange::V0To10, &s7),
+            Some(InRange(0x7EFF as f32 * 10.0 / 27_648.0))
+        );
+        assert_eq!(
+            u16_to_checked_analog_ui_value(0x7F00, &AnalogUIRange::V0To10, &s7),
+            Some(Overload)
+        );
+        assert_eq!(
+            u16_to_checked_analog_ui_value(0x8000, &AnalogUIRange::VMinus10To10, &s7),
+            Some(Underload)
+        );
+        assert_eq!(
+            u16_to_checked_analog_ui_value(0x8000, &AnalogUIRange::V1To5, &s7),
+            Some(WireBreak)
+        );
+        assert_eq!(
+            u16_to_checked_analog_ui_value(0x8000, &AnalogUIRange::Disabled, &s7),
+            None
+        );
+        assert_eq!(
+            u16_to_checked_analog_i_value(0x8100, &AnalogIRange::mA4To20, &s7),
+            Some(WireBreak)
+        );
+        assert_eq!(
+            u16_to_checked_analog_i_value(0x8100, &AnalogIRange::mA0To20, &s7),
+            Some(Underload)
+        );
+        assert_eq!(
+            u16_to_checked_analog_i_value(0x7FFF, &AnalogIRange::mA4To20, &DataFormat::S5),
+            Some(Overload)
+        );
+    }
+
+    #[test]
+    fn test_u16_to_analog_i_value() {
+        assert_eq!(
+            u16_to_analog_i_value(0x3600, &AnalogIRange::mA0To20, &DataFormat::S7),
+            Some(10.0)
+        );
+        assert_eq!(
+            u16_to_analog_i_value(0x4000, &AnalogIRange::mA4To20, &DataFormat::S5),
+            Some(20.0)
+        );
+        assert_eq!(
+            u16_to_analog_i_value(0x6C00, &AnalogIRange::Disabled, &DataFormat::S7),
+            None
+        );
+    }
+
+    #[test]
+    fn test_analog_ui_value_to_u16() {
+        assert_eq!(
+            analog_ui_value_to_u16(10.0, &AnalogUIRange::mA0To20, &DataFormat::S7),
+            0x3600
+        );
+    }
+
+    #[test]
+    fn test_negative_analog_ui_value_to_u16() {
+        assert_eq!(
+            analog_ui_value_to_u16(-10.0, &AnalogUIRange::VMinus10To10, &DataFormat::S7),
+            0x9400
+        );
+        assert_eq!(
+            analog_ui_value_to_u16(-5.0, &AnalogUIRange::VMinus5To5, &DataFormat::S5),
+            0xC000
+        );
+        assert_eq!(
+            analog_ui_value_to_u16(-2.5, &AnalogUIRange::VMinus10To10, &DataFormat::S7),
+            0xE500
+        );
+        assert_eq!(
+            u16_to_analog_ui_value(
+                analog_ui_value_to_u16(-7.5, &AnalogUIRange::VMinus10To10, &DataFormat::S7),
+                &AnalogUIRange::VMinus10To10,
+                &DataFormat::S7
+            ),
+            Some(-7.5)
+        );
+    }
+
+    #[test]
+    fn test_analog_i_value_to_u16() {
+        assert_eq!(
+            analog_i_value_to_u16(10.0, &AnalogIRange::mA0To20, &DataFormat::S7),
+            0x3600
+        );
+        assert_eq!(
+            analog_i_value_to_u16(20.0, &AnalogIRange::mA4To20, &DataFormat::S5),
+            0x4000
+        );
+        assert_eq!(
+            analog_i_value_to_u16(5.0, &AnalogIRange::Disabled, &DataFormat::S5),
+            0
+        );
+    }
+
+    #[test]
+    fn test_u16_to_rtd_value() {
+        assert_eq!(u16_to_rtd_value(0x00FA, &RtdRange::PT100), Some(25.0));
+        assert_eq!(u16_to_rtd_value(0xFF9C, &RtdRange::PT100), Some(-10.0));
+        assert_eq!(u16_to_rtd_value(0x3600, &RtdRange::R300), Some(150.0));
+        assert_eq!(u16_to_rtd_value(0x3600, &RtdRange::Disabled), None);
+    }
+
+    #[test]
+    fn test_u16_to_tc_value() {
+        assert_eq!(u16_to_tc_value(250, &TcRange::K), Some(25.0));
+        assert_eq!(u16_to_tc_value(0xFF38, &TcRange::J), Some(-20.0));
+        assert_eq!(u16_to_tc_value(0x6C00, &TcRange::mV1000), Some(1.0));
+        assert_eq!(u16_to_tc_value(0x9400, &TcRange::mV500), Some(-0.5));
+        assert_eq!(u16_to_tc_value(0x3600, &TcRange::mV125), Some(0.0625));
+        assert_eq!(u16_to_tc_value(123, &TcRange::Disabled), None);
+    }
+
+    #[test]
+    fn convert_analog_data_format() {
+        let s = AnalogScaling::ui(&AnalogUIRange::mA4To20, &DataFormat::S5).unwrap();
+        assert_eq!(s.offset, 4.0);
+        let s5 = u16_to_analog_ui_value(0x3600, &AnalogUIRange::mA4To20, &DataFormat::S5).unwrap();
+        let s7 = u16_to_analog_ui_value(0x3600, &AnalogUIRange::mA4To20, &DataFormat::S7).unwrap();
+        assert_eq!(s.convert(s5, &DataFormat::S7), s7);
+        assert_eq!(s.revert(s7, &DataFormat::S7), s5);
+        assert_eq!(s.convert(s5, &DataFormat::S5), s5);
+        let s = AnalogScaling::i(&AnalogIRange::mA0To20, &DataFormat::S7).unwrap();
+        assert_eq!(s.convert(20.0, &DataFormat::S5), 20.0 * 27_648.0 / 16_384.0);
+        assert!(AnalogScaling::ui(&AnalogUIRange::Disabled, &DataFormat::S7).is_none());
+        assert!(AnalogScaling::i(&AnalogIRange::Disabled, &DataFormat::S5).is_none());
+    }
+}
@@ -0,0 +1,171 @@
+//! Generation of Rust source code from a station description.
+//!
+//! The generated constants can be used instead of labels,
+//! so channel references are checked at compile time.
+//! A typical `build.rs` looks like this:
+//!
+//! ```rust,no_run
+//! # fn station() -> ur20::station::StationDescription { unimplemented!() }
+//! let out = std::path::Path::new(&std::env::var("OUT_DIR").unwrap()).join("channels.rs");
+//! ur20::codegen::write_address_constants(&station(), &out).unwrap();
+//! ```
+//!
+//! The constants are then included with
+//! `include!(concat!(env!("OUT_DIR"), "/channels.rs"));`.
+
+use super::*;
+use crate::station::{ChannelDescription, StationDescription};
+use std::{collections::HashSet, fmt::Write, fs, path::Path};
+
+/// Converts a channel label into the name of a constant
+/// (e.g. `pump 1` becomes `PUMP_1`).
+fn const_name(label: &str) -> Result<String> {
+    let mut name: String = label
+        .trim()
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_uppercase()
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    if name.is_empty() || name.chars().all(|c| c == '_') {
+        return Err(Error::Address);
+    }
+    if name.starts_with(|c: char| c.is_ascii_digit()) {
+        name.insert(0, '_');
+    }
+    Ok(name)
+}
+
+/// Generates an `Address` constant for each labeled channel
+/// (including the virtual channels).
+///
+/// Channels without label are skipped.
+/// If two labels result in the same constant name,
+/// `Error::Address` is returned.
+pub fn address_constants(station: &StationDescription) -> Result<String> {
+    let modules = station.modules.iter().map(|m| &m.channels[..]);
+    let virtual_channels: Vec<ChannelDescription> = station
+        .virtual_channels
+        .iter()
+        .map(|v| v.channel.clone())
+        .collect();
+
+    let mut names = HashSet::new();
+    let mut src = String::from("// Generated by ur20::codegen. Do not edit.\n");
+    for (module, channels) in modules
+        .chain(std::iter::once(&virtual_channels[..]))
+        .enumerate()
+    {
+        for (channel, desc) in channels.iter().enumerate() {
+            let label = match desc.label {
+                Some(ref l) => l,
+                None => continue,
+            };
+            let name = const_name(label)?;
+            if !names.insert(name.clone()) {
+                return Err(Error::Address);
+            }
+            writeln!(src).unwrap();
+            if let Some(ref d) = desc.description {
+                for line in d.lines() {
+                    writeln!(src, "/// {}", line).unwrap();
+                }
+            }
+            writeln!(
+                src,
+                "pub const {}: ::ur20::Address = ::ur20::Address {{ module: {}, channel: {} }};",
+                name, module, channel
+            )
+            .unwrap();
+        }
+    }
+    Ok(src)
+}
+
+/// Writes the generated constants (see `address_constants`) into a file.
+pub fn write_address_constants<P: AsRef<Path>>(
+    station: &StationDescription,
+    path: P,
+) -> Result<()> {
+    fs::write(path, address_constants(station)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::station::{Expression, ModuleDescription, VirtualChannel};
+
+    fn channel(label: &str, description: Option<&str>) -> ChannelDescription {
+        ChannelDescription {
+            label: Some(label.into()),
+            description: description.map(Into::into),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn convert_labels_into_const_names() {
+        assert_eq!(const_name("pump_1").unwrap(), "PUMP_1");
+        assert_eq!(const_name(" Tank level (m) ").unwrap(), "TANK_LEVEL__M_");
+        assert_eq!(const_name("1st_valve").unwrap(), "_1ST_VALVE");
+        assert!(const_name("").is_err());
+        assert!(const_name("--").is_err());
+    }
+
+    #[test]
+    fn generate_address_constants() {
+        let station = StationDescription {
+            modules: vec![
+                ModuleDescription {
+                    module_type: ModuleType::UR20_4DI_P,
+                    params: vec![0; 4],
+                    channels: vec![
+                        ChannelDescription::default(),
+                        channel("pump_1", Some("Pump 1\nrunning")),
+                    ],
+                },
+                ModuleDescription {
+                    module_type: ModuleType::UR20_4DO_P,
+                    params: vec![0; 4],
+                    channels: vec![channel("valve", None)],
+                },
+            ],
+            virtual_channels: vec![VirtualChannel {
+                channel: channel("pumps", None),
+                expression: Expression::Sum(vec![]),
+            }],
+        };
+        let src = address_constants(&station).unwrap();
+        assert_eq!(
+            src,
+            "// Generated by ur20::codegen. Do not edit.\n\
+             \n\
+             /// Pump 1\n\
+             /// running\n\
+             pub const PUMP_1: ::ur20::Address = ::ur20::Address { module: 0, channel: 1 };\n\
+             \n\
+             pub const VALVE: ::ur20::Address = ::ur20::Address { module: 1, channel: 0 };\n\
+             \n\
+             pub const PUMPS: ::ur20::Address = ::ur20::Address { module: 2, channel: 0 };\n"
+        );
+    }
+
+    #[test]
+    fn reject_duplicate_const_names() {
+        let station = StationDescription {
+            modules: vec![ModuleDescription {
+                module_type: ModuleType::UR20_4DI_P,
+                params: vec![0; 4],
+                channels: vec![channel("pump 1", None), channel("pump-1", None)],
+            }],
+            virtual_channels: vec![],
+        };
+        assert_eq!(address_constants(&station).err(), Some(Error::Address));
+    }
+}
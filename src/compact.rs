@@ -0,0 +1,232 @@
+//! Compact digital process image.
+//!
+//! Besides the packed process images, the coupler offers
+//! the states of all digital channels in a compact form
+//! (`ADDR_COMPACT_DIGITAL_INPUT_DATA` and `ADDR_COMPACT_DIGITAL_OUTPUT_DATA`).
+//! The channels of the digital modules are arranged without gaps
+//! in the order of the modules, 16 channels per register
+//! (channel 0 of the first module is bit 0 of the first register).
+//!
+//! Clients that only need the digital states can read these areas
+//! instead of the full process image.
+//!
+//! ```rust
+//! # use ur20::{compact::CompactDigitalImage, ChannelValue, ModuleType};
+//! let img = CompactDigitalImage::new(&[
+//!     ModuleType::UR20_4DI_P,
+//!     ModuleType::UR20_4AI_UI_12,
+//!     ModuleType::UR20_4DO_P,
+//! ]);
+//! assert_eq!(img.input_len(), 1);
+//!
+//! let inputs = img.decode_inputs(&[0b0010]).unwrap();
+//! assert_eq!(inputs[0][1], ChannelValue::Bit(true));
+//! assert!(inputs[1].is_empty());
+//!
+//! let out = vec![vec![], vec![], vec![ChannelValue::Bit(true); 4]];
+//! assert_eq!(img.encode_outputs(&out).unwrap(), vec![0b1111]);
+//! ```
+
+use super::*;
+use crate::util::{set_bit_16, test_bit_16};
+
+/// The position of the digital channels within the compact image.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompactDigitalImage {
+    module_count: usize,
+    /// Module position and number of channels of the digital input modules.
+    inputs: Vec<(usize, usize)>,
+    /// Module position and number of channels of the digital output modules.
+    outputs: Vec<(usize, usize)>,
+}
+
+/// Returns `true` if the module has plain digital channels.
+fn is_digital(t: &ModuleType) -> bool {
+    match *t {
+        ModuleType::UR20_2DI_P_TS | ModuleType::UR20_4DI_P_TS => false,
+        _ => matches!(
+            ModuleCategory::from(t.clone()),
+            ModuleCategory::DI | ModuleCategory::DO | ModuleCategory::RO
+        ),
+    }
+}
+
+fn channel_count(channels: &[(usize, usize)]) -> usize {
+    channels.iter().map(|(_, cnt)| cnt).sum()
+}
+
+impl CompactDigitalImage {
+    /// Creates the image layout of a station with the given modules.
+    pub fn new(modules: &[ModuleType]) -> Self {
+        let mut inputs = vec![];
+        let mut outputs = vec![];
+        for (i, t) in modules.iter().enumerate().filter(|(_, t)| is_digital(t)) {
+            let entry = (i, t.channel_count());
+            match ModuleCategory::from(t.clone()) {
+                ModuleCategory::DI => inputs.push(entry),
+                _ => outputs.push(entry),
+            }
+        }
+        CompactDigitalImage {
+            module_count: modules.len(),
+            inputs,
+            outputs,
+        }
+    }
+
+    /// Number of registers of the compact input image.
+    pub fn input_len(&self) -> usize {
+        channel_count(&self.inputs).div_ceil(16)
+    }
+
+    /// Number of registers of the compact output image.
+    pub fn output_len(&self) -> usize {
+        channel_count(&self.outputs).div_ceil(16)
+    }
+
+    /// Maps the compact input image into values.
+    ///
+    /// There is one entry per module (like `Coupler::inputs`);
+    /// the entries of modules without digital inputs are empty.
+    pub fn decode_inputs(&self, data: &[u16]) -> Result<Vec<Vec<ChannelValue>>> {
+        decode(&self.inputs, self.module_count, data)
+    }
+
+    /// Maps the compact output image into values.
+    ///
+    /// There is one entry per module (like `Coupler::outputs`);
+    /// the entries of modules without digital outputs are empty.
+    pub fn decode_outputs(&self, data: &[u16]) -> Result<Vec<Vec<ChannelValue>>> {
+        decode(&self.outputs, self.module_count, data)
+    }
+
+    /// Maps output values (one entry per module) into the compact output image.
+    ///
+    /// The values of each digital output module follow the contract of
+    /// `ProcessModbusTcpData::process_output_values`;
+    /// the entries of all other modules are ignored.
+    pub fn encode_outputs(&self, values: &[Vec<ChannelValue>]) -> Result<Vec<u16>> {
+        if values.len() != self.module_count {
+            return Err(Error::ChannelValue);
+        }
+        let mut data = vec![0; self.output_len()];
+        let mut bit = 0;
+        for &(m, cnt) in &self.outputs {
+            let v = &values[m];
+            if !v.is_empty() && v.len() != cnt {
+                return Err(Error::ChannelValue);
+            }
+            for ch in 0..cnt {
+                match v.get(ch) {
+                    Some(ChannelValue::Bit(true)) => {
+                        data[bit / 16] = set_bit_16(data[bit / 16], bit % 16);
+                    }
+                    Some(ChannelValue::Bit(false))
+                    | Some(ChannelValue::Disabled)
+                    | Some(ChannelValue::None)
+                    | None => {}
+                    Some(_) => {
+                        return Err(Error::ChannelValue);
+                    }
+                }
+                bit += 1;
+            }
+        }
+        Ok(data)
+    }
+}
+
+fn decode(
+    channels: &[(usize, usize)],
+    module_count: usize,
+    data: &[u16],
+) -> Result<Vec<Vec<ChannelValue>>> {
+    if data.len() != channel_count(channels).div_ceil(16) {
+        return Err(Error::BufferLength);
+    }
+    let mut res = vec![vec![]; module_count];
+    let mut bit = 0;
+    for &(m, cnt) in channels {
+        res[m] = (bit..bit + cnt)
+            .map(|b| ChannelValue::Bit(test_bit_16(data[b / 16], b % 16)))
+            .collect();
+        bit += cnt;
+    }
+    Ok(res)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::ChannelValue::*;
+
+    fn image() -> CompactDigitalImage {
+        CompactDigitalImage::new(&[
+            ModuleType::UR20_16DI_P,
+            ModuleType::UR20_4DO_P,
+            ModuleType::UR20_4AO_UI_16,
+            ModuleType::UR20_4DI_P_TS,
+            ModuleType::UR20_4DI_P,
+            ModuleType::UR20_16DO_P,
+        ])
+    }
+
+    #[test]
+    fn image_len() {
+        let img = image();
+        assert_eq!(img.input_len(), 2);
+        assert_eq!(img.output_len(), 2);
+        assert_eq!(CompactDigitalImage::new(&[]).input_len(), 0);
+    }
+
+    #[test]
+    fn decode_compact_inputs() {
+        let img = image();
+        assert!(img.decode_inputs(&[0]).is_err());
+        let values = img.decode_inputs(&[0x8001, 0b0100]).unwrap();
+        assert_eq!(values.len(), 6);
+        assert_eq!(values[0].len(), 16);
+        assert_eq!(values[0][0], Bit(true));
+        assert_eq!(values[0][15], Bit(true));
+        assert!(values[1].is_empty());
+        assert!(values[3].is_empty());
+        assert_eq!(
+            values[4],
+            vec![Bit(false), Bit(false), Bit(true), Bit(false)]
+        );
+    }
+
+    #[test]
+    fn decode_compact_outputs() {
+        let values = image().decode_outputs(&[0x0031, 0x0007]).unwrap();
+        assert_eq!(
+            values[1],
+            vec![Bit(true), Bit(false), Bit(false), Bit(false)]
+        );
+        assert_eq!(values[5][0], Bit(true));
+        assert_eq!(values[5][1], Bit(true));
+        assert_eq!(values[5][11], Bit(false));
+        assert_eq!(values[5][14], Bit(true));
+    }
+
+    #[test]
+    fn encode_compact_outputs() {
+        let img = image();
+        let mut values = vec![vec![]; 6];
+        assert_eq!(img.encode_outputs(&values).unwrap(), vec![0, 0]);
+        values[1] = vec![Bit(true), None, Disabled, Bit(true)];
+        values[2] = vec![Decimal32(1.0); 4];
+        let mut ch = vec![Bit(false); 16];
+        ch[0] = Bit(true);
+        ch[12] = Bit(true);
+        ch[15] = Bit(true);
+        values[5] = ch;
+        assert_eq!(img.encode_outputs(&values).unwrap(), vec![0x0019, 0x0009]);
+        values[1] = vec![Bit(true)];
+        assert!(img.encode_outputs(&values).is_err());
+        values[1] = vec![Decimal32(1.0); 4];
+        assert!(img.encode_outputs(&values).is_err());
+        assert!(img.encode_outputs(&values[..5]).is_err());
+    }
+}
@@ -0,0 +1,289 @@
+//! Declarative station configuration files.
+//!
+//! A station is described by the list of its modules (TOML or JSON).
+//! The parameters of a module are given either as raw `registers`
+//! or as typed module `parameters` and `channels` parameters.
+//! Omitted parameters keep their default value:
+//!
+//! ```rust
+//! # use ur20::{config::StationConfig, ModuleType};
+//! let cfg = StationConfig::from_toml(r#"
+//!     [[modules]]
+//!     module = "UR20-4AI-UI-16"
+//!     parameters = { frequency_suppression = "Hz50" }
+//!     channels = [
+//!         { measurement_range = "mA4To20" },
+//!         { measurement_range = "V0To10", data_format = "S5" },
+//!     ]
+//!
+//!     [[modules]]
+//!     module = "UR20-4DO-P"
+//!     registers = [0, 1, 0, 0]
+//! "#).unwrap();
+//! let station = cfg.station_description().unwrap();
+//! assert_eq!(station.modules[0].module_type, ModuleType::UR20_4AI_UI_16);
+//! assert_eq!(station.modules[0].params, vec![1, 1, 1, 0, 2, 1, 8, 1, 8]);
+//! assert_eq!(station.modules[1].params, vec![0, 1, 0, 0]);
+//! ```
+//!
+//! Typed parameters are supported by the digital input and output
+//! modules, the analog modules with a generic implementation and the
+//! RTD and TC modules. All other modules need their `registers`
+//! (unless they have no parameters at all).
+
+use super::*;
+use crate::{
+    station::{ModuleDescription, StationDescription},
+    ur20_fbc_mod_tcp::{
+        module_from_parameter_data, ModbusParameterRegisterCount, ProcessModbusTcpData,
+        ToModbusParameterData,
+    },
+};
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use std::{fs, path::Path};
+
+/// The modules of a station in the order of their physical position.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct StationConfig {
+    pub modules: Vec<ModuleConfig>,
+}
+
+/// The configuration of a single module.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ModuleConfig {
+    /// Module name or order number (e.g. `UR20-4DI-P`).
+    pub module: String,
+    /// Raw parameter registers.
+    #[serde(default)]
+    pub registers: Option<Vec<u16>>,
+    /// Typed module parameters.
+    #[serde(default)]
+    pub parameters: Option<Value>,
+    /// Typed channel parameters (beginning at channel `0`).
+    #[serde(default)]
+    pub channels: Vec<Value>,
+}
+
+fn config_error<E: fmt::Display>(err: E) -> Error {
+    Error::Config(err.to_string())
+}
+
+impl StationConfig {
+    pub fn from_toml(s: &str) -> Result<Self> {
+        toml::from_str(s).map_err(config_error)
+    }
+
+    pub fn from_json(s: &str) -> Result<Self> {
+        serde_json::from_str(s).map_err(config_error)
+    }
+
+    /// Reads a configuration file.
+    ///
+    /// Files with the extension `json` are parsed as JSON,
+    /// all other files as TOML.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let s = fs::read_to_string(path)?;
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("json") => Self::from_json(&s),
+            _ => Self::from_toml(&s),
+        }
+    }
+
+    /// Creates the station description with the raw parameter registers.
+    pub fn station_description(&self) -> Result<StationDescription> {
+        let modules = self
+            .modules
+            .iter()
+            .map(|m| {
+                let module_type: ModuleType = m.module.parse()?;
+                let params = m.parameter_registers(&module_type)?;
+                module_from_parameter_data(&module_type, &params)?;
+                Ok(ModuleDescription::new(module_type, params))
+            })
+            .collect::<Result<_>>()?;
+        Ok(StationDescription {
+            modules,
+            virtual_channels: vec![],
+        })
+    }
+
+    /// Creates the module instances.
+    pub fn modules(&self) -> Result<Vec<Box<dyn ProcessModbusTcpData>>> {
+        self.station_description()?
+            .modules
+            .iter()
+            .map(|m| module_from_parameter_data(&m.module_type, &m.params))
+            .collect()
+    }
+}
+
+impl ModuleConfig {
+    fn has_typed_parameters(&self) -> bool {
+        self.parameters.is_some() || !self.channels.is_empty()
+    }
+
+    /// Returns the parameter registers of the module.
+    fn parameter_registers(&self, t: &ModuleType) -> Result<Vec<u16>> {
+        if let Some(ref registers) = self.registers {
+            if self.has_typed_parameters() {
+                return Err(Error::Config(format!(
+                    "{}: use either registers or typed parameters",
+                    self.module
+                )));
+            }
+            if registers.len() != t.param_register_count() as usize {
+                return Err(Error::RegisterCount);
+            }
+            return Ok(registers.clone());
+        }
+        match self.typed_parameter_registers(t)? {
+            Some(registers) => Ok(registers),
+            None if t.param_register_count() == 0 && !self.has_typed_parameters() => Ok(vec![]),
+            None => Err(Error::Config(format!(
+                "{}: typed parameters are not supported, use registers",
+                self.module
+            ))),
+        }
+    }
+
+    fn typed_parameter_registers(&self, t: &ModuleType) -> Result<Option<Vec<u16>>> {
+        let data = if ur20_di_generic::MODULE_TYPES.contains(t) {
+            let mut m = ur20_di_generic::Mod::new(t.clone())?;
+            self.no_module_parameters()?;
+            channel_parameters(&self.channels, &mut m.ch_params)?;
+            m.to_modbus_parameter_data()
+        } else if ur20_do_generic::MODULE_TYPES.contains(t) {
+            let mut m = ur20_do_generic::Mod::new(t.clone())?;
+            self.no_module_parameters()?;
+            channel_parameters(&self.channels, &mut m.ch_params)?;
+            m.to_modbus_parameter_data()
+        } else if ur20_ai_ui_generic::MODULE_TYPES.contains(t) {
+            let mut m = ur20_ai_ui_generic::Mod::new(t.clone())?;
+            module_parameters(&self.parameters, &mut m.mod_params)?;
+            channel_parameters(&self.channels, &mut m.ch_params)?;
+            m.to_modbus_parameter_data()
+        } else if ur20_ai_i_generic::MODULE_TYPES.contains(t) {
+            let mut m = ur20_ai_i_generic::Mod::new(t.clone())?;
+            module_parameters(&self.parameters, &mut m.mod_params)?;
+            channel_parameters(&self.channels, &mut m.ch_params)?;
+            m.to_modbus_parameter_data()
+        } else if ur20_ao_ui_generic::MODULE_TYPES.contains(t) {
+            let mut m = ur20_ao_ui_generic::Mod::new(t.clone())?;
+            self.no_module_parameters()?;
+            channel_parameters(&self.channels, &mut m.ch_params)?;
+            m.to_modbus_parameter_data()
+        } else if *t == ModuleType::UR20_4AI_RTD_DIAG {
+            let mut m = ur20_4ai_rtd_diag::Mod::default();
+            module_parameters(&self.parameters, &mut m.mod_params)?;
+            channel_parameters(&self.channels, &mut m.ch_params)?;
+            m.to_modbus_parameter_data()
+        } else if *t == ModuleType::UR20_4AI_TC_DIAG {
+            let mut m = ur20_4ai_tc_diag::Mod::default();
+            module_parameters(&self.parameters, &mut m.mod_params)?;
+            channel_parameters(&self.channels, &mut m.ch_params)?;
+            m.to_modbus_parameter_data()
+        } else {
+            return Ok(None);
+        };
+        Ok(Some(data))
+    }
+
+    fn no_module_parameters(&self) -> Result<()> {
+        match self.parameters {
+            Some(_) => Err(Error::Config(format!(
+                "{}: the module has no module parameters",
+                self.module
+            ))),
+            None => Ok(()),
+        }
+    }
+}
+
+fn module_parameters<T: DeserializeOwned>(value: &Option<Value>, params: &mut T) -> Result<()> {
+    if let Some(v) = value {
+        *params = serde_json::from_value(v.clone()).map_err(config_error)?;
+    }
+    Ok(())
+}
+
+fn channel_parameters<T: DeserializeOwned>(values: &[Value], params: &mut [T]) -> Result<()> {
+    if values.len() > params.len() {
+        return Err(Error::Config(format!(
+            "parameters of {} channels given, but the module has {} configurable channels",
+            values.len(),
+            params.len()
+        )));
+    }
+    for (p, v) in params.iter_mut().zip(values) {
+        *p = serde_json::from_value(v.clone()).map_err(config_error)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn load_json_configuration() {
+        let cfg = StationConfig::from_json(
+            r#"{
+                "modules": [
+                    { "module": "UR20-PF-I" },
+                    { "module": "1315170000", "channels": [{}, { "input_delay": "ms10" }] },
+                    {
+                        "module": "UR20-4AI-RTD-DIAG",
+                        "parameters": { "temperature_unit": "Fahrenheit" },
+                        "channels": [{
+                            "measurement_range": "PT100",
+                            "limit_value_monitoring": true,
+                            "high_limit_value": 1000,
+                            "low_limit_value": -200
+                        }]
+                    }
+                ]
+            }"#,
+        )
+        .unwrap();
+        let station = cfg.station_description().unwrap();
+        assert!(station.modules[0].params.is_empty());
+        assert_eq!(station.modules[1].module_type, ModuleType::UR20_4DI_P);
+        assert_eq!(station.modules[1].params, vec![2, 3, 2, 2]);
+        let rtd = &station.modules[2].params;
+        assert_eq!(rtd.len(), 29);
+        assert_eq!(rtd[0], 1);
+        assert_eq!(rtd[1..8], [0, 0, 2, 0, 1, 1000, 0xFF38]);
+        let modules = cfg.modules().unwrap();
+        assert_eq!(modules.len(), 3);
+        assert_eq!(modules[2].module_type(), ModuleType::UR20_4AI_RTD_DIAG);
+    }
+
+    #[test]
+    fn reject_invalid_configurations() {
+        let invalid = [
+            "modules = [{ module = \"UR20-XYZ\" }]",
+            "modules = [{ module = \"UR20-4DO-P\", unknown = 1 }]",
+            "modules = [{ module = \"UR20-4DO-P\", registers = [0, 0] }]",
+            "modules = [{ module = \"UR20-4DO-P\", registers = [0, 0, 0, 0], channels = [{}] }]",
+            "modules = [{ module = \"UR20-4DO-P\", parameters = {} }]",
+            "modules = [{ module = \"UR20-4DO-P\", channels = [{}, {}, {}, {}, {}] }]",
+            "modules = [{ module = \"UR20-4AI-UI-16\", channels = [{ measurement_range = \"mA5\" }] }]",
+            "modules = [{ module = \"UR20-1SSI\" }]",
+            "modules = [{ module = \"UR20-1SSI\", channels = [{}] }]",
+            "modules = [{ module = \"UR20-4DI-P\", registers = [9, 9, 9, 9] }]",
+        ];
+        for s in &invalid {
+            let res = StationConfig::from_toml(s).and_then(|c| c.station_description());
+            assert!(res.is_err(), "{}", s);
+        }
+        assert!(matches!(
+            StationConfig::from_toml("modules = 1"),
+            Err(Error::Config(_))
+        ));
+    }
+}
@@ -0,0 +1,204 @@
+//! Exchange of station descriptions with the vendor configuration tools.
+//!
+//! The native project formats of the configuration tools are not
+//! documented, so the station is exchanged as a CSV table
+//! (separated by `;`) with a header line and one line per module:
+//!
+//! | Column         | Content                                              |
+//! |----------------|------------------------------------------------------|
+//! | `slot`         | position of the module (beginning at `1`)            |
+//! | `order_number` | article number of the module (empty if unknown)      |
+//! | `module`       | module name (e.g. `UR20-4DI-P`)                      |
+//! | `parameters`   | parameter registers (decimal, separated by spaces)   |
+//!
+//! ```rust
+//! # use ur20::{csv, station::*, ModuleType};
+//! let station = StationDescription {
+//!     modules: vec![ModuleDescription::new(ModuleType::UR20_4DI_P, vec![2, 2, 2, 2])],
+//!     virtual_channels: vec![],
+//! };
+//! assert_eq!(
+//!     csv::station_to_csv(&station),
+//!     "slot;order_number;module;parameters\r\n1;1315170000;UR20-4DI-P;2 2 2 2\r\n"
+//! );
+//! assert_eq!(csv::station_from_csv(&csv::station_to_csv(&station)).unwrap(), station);
+//! ```
+
+use super::*;
+use crate::{
+    station::{ModuleDescription, StationDescription},
+    ur20_fbc_mod_tcp::ModbusParameterRegisterCount,
+};
+use std::{fs, path::Path};
+
+/// Column separator
+const SEPARATOR: char = ';';
+
+/// Line ending (the tools are running on Windows).
+const LINE_END: &str = "\r\n";
+
+const HEADER: [&str; 4] = ["slot", "order_number", "module", "parameters"];
+
+/// Returns the module name as used by the vendor (e.g. `UR20-4DI-P`).
+fn module_name(t: &ModuleType) -> String {
+    format!("{:?}", t).replace('_', "-")
+}
+
+/// Exports the modules and their parameters of a station.
+///
+/// Channel descriptions and virtual channels are not exported.
+pub fn station_to_csv(station: &StationDescription) -> String {
+    let mut csv = HEADER.join(&SEPARATOR.to_string());
+    csv.push_str(LINE_END);
+    for (i, m) in station.modules.iter().enumerate() {
+        let params: Vec<_> = m.params.iter().map(ToString::to_string).collect();
+        let line = [
+            (i + 1).to_string(),
+            m.module_type.order_number().unwrap_or_default().to_string(),
+            module_name(&m.module_type),
+            params.join(" "),
+        ];
+        csv.push_str(&line.join(&SEPARATOR.to_string()));
+        csv.push_str(LINE_END);
+    }
+    csv
+}
+
+/// Writes the exported station (see `station_to_csv`) into a file.
+pub fn write_station_csv<P: AsRef<Path>>(station: &StationDescription, path: P) -> Result<()> {
+    fs::write(path, station_to_csv(station))?;
+    Ok(())
+}
+
+/// Imports the modules and their parameters of a station
+/// (see `station_to_csv`).
+///
+/// A module is identified by its order number or,
+/// if the number is empty or unknown, by its name.
+/// Empty lines are ignored. Use `StationDescription::coupler_config`
+/// to get the configuration of the coupler.
+pub fn station_from_csv(csv: &str) -> Result<StationDescription> {
+    let mut lines = csv.lines().map(str::trim).filter(|l| !l.is_empty());
+    let header: Vec<_> = lines
+        .next()
+        .ok_or(Error::DataLength)?
+        .split(SEPARATOR)
+        .map(str::trim)
+        .collect();
+    if header != HEADER {
+        return Err(Error::DataLength);
+    }
+    let modules = lines
+        .enumerate()
+        .map(|(i, line)| {
+            let cols: Vec<_> = line.split(SEPARATOR).map(str::trim).collect();
+            if cols.len() != HEADER.len() {
+                return Err(Error::DataLength);
+            }
+            if cols[0].parse::<usize>().ok() != Some(i + 1) {
+                return Err(Error::Address);
+            }
+            let module_type = cols[1].parse::<ModuleType>().or_else(|_| cols[2].parse())?;
+            let params = cols[3]
+                .split_whitespace()
+                .map(|p| p.parse().map_err(|_| Error::ChannelParameter))
+                .collect::<Result<Vec<u16>>>()?;
+            if params.len() != module_type.param_register_count() as usize {
+                return Err(Error::RegisterCount);
+            }
+            Ok(ModuleDescription::new(module_type, params))
+        })
+        .collect::<Result<_>>()?;
+    Ok(StationDescription {
+        modules,
+        virtual_channels: vec![],
+    })
+}
+
+/// Reads an exported station (see `station_from_csv`) from a file.
+pub fn read_station_csv<P: AsRef<Path>>(path: P) -> Result<StationDescription> {
+    station_from_csv(&fs::read_to_string(path)?)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn export_station() {
+        let station = StationDescription {
+            modules: vec![
+                ModuleDescription::new(ModuleType::UR20_PF_I, vec![]),
+                ModuleDescription::new(ModuleType::UR20_4DO_P, vec![0, 1, 0, 0]),
+                ModuleDescription::new(ModuleType::UR20_2PWM_PN_0_5A, vec![100, 0, 0, 100, 2, 500]),
+            ],
+            virtual_channels: vec![],
+        };
+        assert_eq!(
+            station_to_csv(&station),
+            "slot;order_number;module;parameters\r\n\
+             1;1334740000;UR20-PF-I;\r\n\
+             2;1315220000;UR20-4DO-P;0 1 0 0\r\n\
+             3;;UR20-2PWM-PN-0-5A;100 0 0 100 2 500\r\n"
+        );
+        assert_eq!(
+            station_to_csv(&StationDescription::default()),
+            "slot;order_number;module;parameters\r\n"
+        );
+    }
+
+    #[test]
+    fn import_station() {
+        let csv = "slot;order_number;module;parameters\r\n\
+                   1;1334740000;UR20-PF-I;\r\n\
+                   \r\n\
+                   2; 1315220000 ;UR20-4DO-P;0 1  0 0\r\n\
+                   3;;UR20-2PWM-PN-0-5A;100 0 0 100 2 500\r\n\
+                   4;1234567890;UR20-4DI-P;0 0 0 0";
+        let station = station_from_csv(csv).unwrap();
+        assert_eq!(
+            station.modules,
+            vec![
+                ModuleDescription::new(ModuleType::UR20_PF_I, vec![]),
+                ModuleDescription::new(ModuleType::UR20_4DO_P, vec![0, 1, 0, 0]),
+                ModuleDescription::new(ModuleType::UR20_2PWM_PN_0_5A, vec![100, 0, 0, 100, 2, 500]),
+                ModuleDescription::new(ModuleType::UR20_4DI_P, vec![0; 4]),
+            ]
+        );
+        assert_eq!(
+            station_from_csv(&station_to_csv(&station)).unwrap(),
+            station
+        );
+        assert!(station_from_csv("slot;order_number;module;parameters")
+            .unwrap()
+            .modules
+            .is_empty());
+    }
+
+    #[test]
+    fn import_invalid_station() {
+        let header = "slot;order_number;module;parameters\n";
+        assert_eq!(station_from_csv(""), Err(Error::DataLength));
+        assert_eq!(
+            station_from_csv("slot;module;parameters\n1;UR20-PF-I;"),
+            Err(Error::DataLength)
+        );
+        let invalid = [
+            ("1;;UR20-PF-I", Error::DataLength),
+            ("2;;UR20-PF-I;", Error::Address),
+            ("1;;UR20-XYZ;", Error::UnknownModule),
+            ("1;;UR20-4DO-P;0 1 x 0", Error::ChannelParameter),
+            ("1;;UR20-4DO-P;0 70000 0 0", Error::ChannelParameter),
+            ("1;;UR20-4DO-P;0 0 0", Error::RegisterCount),
+        ];
+        for (line, err) in &invalid {
+            assert_eq!(
+                station_from_csv(&format!("{}{}", header, line))
+                    .err()
+                    .as_ref(),
+                Some(err)
+            );
+        }
+    }
+}
@@ -0,0 +1,353 @@
+//! Module diagnosis data.
+//!
+//! The coupler provides a diagnosis block for each slot.
+//! The block starts with a module header
+//! followed by the channel error flags and the error causes of
+//! the first eight channels:
+//!
+//! | Byte   | Content                             |
+//! |--------|-------------------------------------|
+//! | 0      | module error flags                  |
+//! | 1 - 3  | reserved                            |
+//! | 4 - 5  | channel error flags (one per channel) |
+//! | 6 - 7  | reserved                            |
+//! | 8 - 15 | error causes of channel 0 - 7       |
+
+use super::*;
+use crate::util::*;
+use std::{
+    collections::{BTreeMap, HashMap},
+    time::{Duration, Instant},
+};
+
+/// Number of bytes of a diagnosis block.
+pub const DIAGNOSIS_BYTE_COUNT: usize = 16;
+
+const CAUSE_OFFSET: usize = 8;
+
+/// The cause of a channel error.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ChannelDiagnosis {
+    ConfigurationError,
+    ShortCircuit,
+    Undervoltage,
+    Overvoltage,
+    Overload,
+    Overtemperature,
+    WireBreak,
+    LimitExceeded,
+    /// The module reported an error without a specific cause.
+    Unspecified,
+}
+
+const CAUSES: [ChannelDiagnosis; 8] = [
+    ChannelDiagnosis::ConfigurationError,
+    ChannelDiagnosis::ShortCircuit,
+    ChannelDiagnosis::Undervoltage,
+    ChannelDiagnosis::Overvoltage,
+    ChannelDiagnosis::Overload,
+    ChannelDiagnosis::Overtemperature,
+    ChannelDiagnosis::WireBreak,
+    ChannelDiagnosis::LimitExceeded,
+];
+
+/// Decoded diagnosis data of a module.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ModuleDiagnosis {
+    pub module_error: bool,
+    pub internal_error: bool,
+    pub external_error: bool,
+    pub channel_error: bool,
+    pub supply_voltage_missing: bool,
+    pub parameter_error: bool,
+    /// The error causes of each channel.
+    pub channels: Vec<Vec<ChannelDiagnosis>>,
+}
+
+impl ModuleDiagnosis {
+    /// Decodes the raw diagnosis data of a module
+    /// with the given number of channels.
+    pub fn from_raw_data(data: &[u8], channel_count: usize) -> Result<Self> {
+        if data.len() < DIAGNOSIS_BYTE_COUNT || channel_count > 16 {
+            return Err(Error::BufferLength);
+        }
+        let flags = u16::from(data[4]) | (u16::from(data[5]) << 8);
+        let channels = (0..channel_count)
+            .map(|ch| {
+                if !test_bit_16(flags, ch) {
+                    return vec![];
+                }
+                let mut causes = vec![];
+                if CAUSE_OFFSET + ch < DIAGNOSIS_BYTE_COUNT {
+                    let c = data[CAUSE_OFFSET + ch];
+                    for (bit, cause) in CAUSES.iter().enumerate() {
+                        if test_bit(c, bit) {
+                            causes.push(*cause);
+                        }
+                    }
+                }
+                if causes.is_empty() {
+                    vec![ChannelDiagnosis::Unspecified]
+                } else {
+                    causes
+                }
+            })
+            .collect();
+        Ok(ModuleDiagnosis {
+            module_error: test_bit(data[0], 0),
+            internal_error: test_bit(data[0], 1),
+            external_error: test_bit(data[0], 2),
+            channel_error: test_bit(data[0], 3),
+            supply_voltage_missing: test_bit(data[0], 4),
+            parameter_error: test_bit(data[0], 7),
+            channels,
+        })
+    }
+
+    /// Returns `true` if the module reports no error at all.
+    pub fn is_ok(&self) -> bool {
+        !(self.module_error
+            || self.internal_error
+            || self.external_error
+            || self.channel_error
+            || self.supply_voltage_missing
+            || self.parameter_error)
+            && self.channels.iter().all(|c| c.is_empty())
+    }
+}
+
+/// The error causes of a single channel.
+pub type ChannelDiagnostics = Vec<ChannelDiagnosis>;
+
+/// Modules with channel specific diagnostic settings.
+///
+/// The coupler reports all error causes of a channel.
+/// Implementations drop the causes whose monitoring
+/// is disabled by the channel parameters.
+pub trait Diagnostics: Module {
+    /// Decodes the diagnosis block of the module
+    /// (`DIAGNOSIS_BYTE_COUNT / 2` registers).
+    fn process_diagnostic_data(&self, data: &[u16]) -> Result<Vec<ChannelDiagnostics>> {
+        filter_channel_diagnostics(&self.module_type(), data, |_, _| true)
+    }
+}
+
+/// Decodes the error causes of each channel and keeps the causes
+/// that are accepted by `monitored(channel, cause)`.
+pub(crate) fn filter_channel_diagnostics<F>(
+    module_type: &ModuleType,
+    data: &[u16],
+    monitored: F,
+) -> Result<Vec<ChannelDiagnostics>>
+where
+    F: Fn(usize, ChannelDiagnosis) -> bool,
+{
+    let d = ModuleDiagnosis::from_raw_data(&u16_to_u8(data), module_type.channel_count())?;
+    Ok(d.channels
+        .into_iter()
+        .enumerate()
+        .map(|(ch, causes)| causes.into_iter().filter(|c| monitored(ch, *c)).collect())
+        .collect())
+}
+
+/// A (coalesced) change of the diagnosis of a module.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiagnosisEvent {
+    /// Module position (beginning at `0`)
+    pub module: usize,
+    /// The latest diagnosis.
+    pub diagnosis: ModuleDiagnosis,
+    /// Number of changes that are summarized by this event.
+    pub count: usize,
+    /// Time of the first change.
+    pub first: Instant,
+    /// Time of the last change.
+    pub last: Instant,
+}
+
+/// Limits the rate of diagnosis events per module.
+///
+/// Changes within the interval are coalesced into a single event
+/// that is emitted as soon as the interval has elapsed.
+#[derive(Debug, Default)]
+pub(crate) struct DiagnosisThrottle {
+    interval: Option<Duration>,
+    pending: BTreeMap<usize, DiagnosisEvent>,
+    last_emitted: HashMap<usize, Instant>,
+}
+
+impl DiagnosisThrottle {
+    pub fn set_interval(&mut self, interval: Option<Duration>) {
+        self.interval = interval;
+    }
+
+    /// Records a changed diagnosis of a module.
+    pub fn push(&mut self, module: usize, diagnosis: ModuleDiagnosis, now: Instant) {
+        let ev = self.pending.entry(module).or_insert(DiagnosisEvent {
+            module,
+            diagnosis: ModuleDiagnosis::default(),
+            count: 0,
+            first: now,
+            last: now,
+        });
+        ev.diagnosis = diagnosis;
+        ev.count += 1;
+        ev.last = now;
+    }
+
+    /// Returns the events whose interval has elapsed.
+    pub fn flush(&mut self, now: Instant) -> Vec<DiagnosisEvent> {
+        let interval = self.interval;
+        let last_emitted = &self.last_emitted;
+        let ready: Vec<_> = self
+            .pending
+            .keys()
+            .filter(|m| match (interval, last_emitted.get(m)) {
+                (Some(i), Some(t)) => now.duration_since(*t) >= i,
+                _ => true,
+            })
+            .cloned()
+            .collect();
+        ready
+            .into_iter()
+            .filter_map(|m| {
+                self.last_emitted.insert(m, now);
+                self.pending.remove(&m)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn decode_diagnosis_without_errors() {
+        let d = ModuleDiagnosis::from_raw_data(&[0; 16], 4).unwrap();
+        assert!(d.is_ok());
+        assert_eq!(d.channels, vec![vec![]; 4]);
+        assert!(ModuleDiagnosis::from_raw_data(&[0; 15], 4).is_err());
+        assert!(ModuleDiagnosis::from_raw_data(&[0; 16], 17).is_err());
+    }
+
+    #[test]
+    fn filter_unmonitored_causes() {
+        let mut data = [0; 8];
+        data[2] = 0b0011;
+        data[4] = 0b0100_0000_0100_0010;
+        let all = filter_channel_diagnostics(&ModuleType::UR20_4AI_UI_16_DIAG, &data, |_, _| true)
+            .unwrap();
+        assert_eq!(
+            all,
+            vec![
+                vec![ChannelDiagnosis::ShortCircuit, ChannelDiagnosis::WireBreak],
+                vec![ChannelDiagnosis::WireBreak],
+                vec![],
+                vec![],
+            ]
+        );
+        let filtered =
+            filter_channel_diagnostics(&ModuleType::UR20_4AI_UI_16_DIAG, &data, |ch, c| {
+                ch == 0 && c != ChannelDiagnosis::WireBreak
+            })
+            .unwrap();
+        assert_eq!(
+            filtered,
+            vec![vec![ChannelDiagnosis::ShortCircuit], vec![], vec![], vec![]]
+        );
+        assert!(
+            filter_channel_diagnostics(&ModuleType::UR20_4AI_UI_16_DIAG, &[0; 7], |_, _| true)
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn decode_channel_causes() {
+        let mut data = [0; 16];
+        data[0] = 0b1000_1001;
+        data[4] = 0b0000_0101;
+        data[5] = 0b1000_0000;
+        data[8] = 0b0100_0000;
+        data[10] = 0b0000_1100;
+        let d = ModuleDiagnosis::from_raw_data(&data, 16).unwrap();
+        assert!(!d.is_ok());
+        assert!(d.module_error);
+        assert!(!d.internal_error);
+        assert!(d.channel_error);
+        assert!(d.parameter_error);
+        assert_eq!(d.channels[0], vec![ChannelDiagnosis::WireBreak]);
+        assert!(d.channels[1].is_empty());
+        assert_eq!(
+            d.channels[2],
+            vec![
+                ChannelDiagnosis::Undervoltage,
+                ChannelDiagnosis::Overvoltage
+            ]
+        );
+        assert_eq!(d.channels[15], vec![ChannelDiagnosis::Unspecified]);
+    }
+
+    #[test]
+    fn emit_each_change_without_interval() {
+        let mut t = DiagnosisThrottle::default();
+        let now = Instant::now();
+        t.push(1, ModuleDiagnosis::default(), now);
+        t.push(1, ModuleDiagnosis::default(), now);
+        let events = t.flush(now);
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].count, 2);
+        t.push(1, ModuleDiagnosis::default(), now);
+        assert_eq!(t.flush(now).len(), 1);
+        assert!(t.flush(now).is_empty());
+    }
+
+    #[test]
+    fn coalesce_changes_within_interval() {
+        let mut t = DiagnosisThrottle::default();
+        t.set_interval(Some(Duration::from_secs(1)));
+        let start = Instant::now();
+        let at = |ms| start + Duration::from_millis(ms);
+        let error = ModuleDiagnosis {
+            module_error: true,
+            ..Default::default()
+        };
+
+        t.push(0, error.clone(), at(0));
+        t.push(2, error.clone(), at(0));
+        let events = t.flush(at(0));
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].module, 0);
+        assert_eq!(events[1].module, 2);
+
+        t.push(0, ModuleDiagnosis::default(), at(100));
+        t.push(0, error.clone(), at(200));
+        t.push(0, ModuleDiagnosis::default(), at(300));
+        assert!(t.flush(at(300)).is_empty());
+        t.push(2, ModuleDiagnosis::default(), at(400));
+        assert!(t.flush(at(999)).is_empty());
+
+        let events = t.flush(at(1000));
+        assert_eq!(
+            events,
+            vec![
+                DiagnosisEvent {
+                    module: 0,
+                    diagnosis: ModuleDiagnosis::default(),
+                    count: 3,
+                    first: at(100),
+                    last: at(300),
+                },
+                DiagnosisEvent {
+                    module: 2,
+                    diagnosis: ModuleDiagnosis::default(),
+                    count: 1,
+                    first: at(400),
+                    last: at(400),
+                },
+            ]
+        );
+        assert!(t.flush(at(5000)).is_empty());
+    }
+}
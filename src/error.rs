@@ -1,9 +1,11 @@
+use crate::{Address, ModuleType};
 use std::{fmt, io};
 
 /// UR20 specific errors.
 #[derive(Debug, PartialEq)]
 pub enum Error {
     UnknownModule,
+    UnknownModuleSuggestion(ModuleType),
     UnknownCategory,
     BufferLength,
     SequenceNumber,
@@ -13,7 +15,31 @@ pub enum Error {
     ChannelValue,
     ModuleOffset,
     Address,
-    Io(String), // TODO
+    Checksum,
+    Capacity,
+    /// The addressed channel belongs to a module without outputs.
+    NotAnOutput {
+        address: Address,
+        module_type: ModuleType,
+    },
+    /// The recorded data belongs to a station with another configuration.
+    StationMismatch {
+        /// Fingerprint of the recorded configuration.
+        recorded: u16,
+        /// Fingerprint of the configuration to replay with.
+        actual: u16,
+    },
+    /// The channel does not have the requested type.
+    ChannelType,
+    /// The operation needs the output image read back from the coupler.
+    ReadbackRequired,
+    /// Invalid station configuration file.
+    Config(String),
+    /// I/O error (of the data buffer of a module if there is one).
+    Io {
+        module: Option<usize>,
+        source: String,
+    },
 }
 
 #[rustfmt::skip]
@@ -21,6 +47,9 @@ impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match *self {
             Error::UnknownModule    => write!(f, "unknown module type"),
+            Error::UnknownModuleSuggestion(ref t) => {
+                write!(f, "unknown module type (did you mean {:?}?)", t)
+            }
             Error::UnknownCategory  => write!(f, "unknown module category"),
             Error::BufferLength     => write!(f, "invalid buffer length"),
             Error::SequenceNumber   => write!(f, "invalid sequence number"),
@@ -30,7 +59,25 @@ impl fmt::Display for Error {
             Error::ChannelValue     => write!(f, "invalid channel value(s)"),
             Error::ModuleOffset     => write!(f, "invalid module offset"),
             Error::Address          => write!(f, "invalid module address"),
-            Error::Io(ref err)      => write!(f, "I/O error: {}", err),
+            Error::Checksum         => write!(f, "invalid checksum"),
+            Error::Capacity         => write!(f, "capacity exceeded"),
+            Error::NotAnOutput { ref address, ref module_type } => write!(
+                f,
+                "channel {} of module {} ({:?}) is not an output",
+                address.channel, address.module, module_type
+            ),
+            Error::StationMismatch { recorded, actual } => write!(
+                f,
+                "station fingerprint {:04X} does not match the recorded one ({:04X})",
+                actual, recorded
+            ),
+            Error::ChannelType      => write!(f, "invalid channel type"),
+            Error::ReadbackRequired => write!(f, "output readback required"),
+            Error::Config(ref err)  => write!(f, "invalid configuration: {}", err),
+            Error::Io { module: Some(m), ref source } => {
+                write!(f, "I/O error of module {}: {}", m, source)
+            }
+            Error::Io { ref source, .. } => write!(f, "I/O error: {}", source),
         }
     }
 }
@@ -39,7 +86,8 @@ impl fmt::Display for Error {
 impl ::std::error::Error for Error {
     fn description(&self) -> &str {
         match *self {
-            Error::UnknownModule    => "unknown module type",
+            Error::UnknownModule    |
+            Error::UnknownModuleSuggestion(_) => "unknown module type",
             Error::UnknownCategory  => "unknown module category",
             Error::BufferLength     => "invalid buffer length",
             Error::SequenceNumber   => "invalid sequence number",
@@ -49,13 +97,44 @@ impl ::std::error::Error for Error {
             Error::ChannelValue     => "invalid channel value(s)",
             Error::ModuleOffset     => "invalid module offset",
             Error::Address          => "invalid module address",
-            Error::Io(ref err)      => err
+            Error::Checksum         => "invalid checksum",
+            Error::Capacity         => "capacity exceeded",
+            Error::NotAnOutput{..}  => "channel is not an output",
+            Error::StationMismatch{..} => "station configuration mismatch",
+            Error::ChannelType      => "invalid channel type",
+            Error::ReadbackRequired => "output readback required",
+            Error::Config(_)        => "invalid configuration",
+            Error::Io { ref source, .. } => source
+        }
+    }
+}
+
+impl Error {
+    /// Creates an I/O error without a module context.
+    pub(crate) fn io<S: Into<String>>(source: S) -> Self {
+        Error::Io {
+            module: None,
+            source: source.into(),
         }
     }
 }
 
 impl From<io::Error> for Error {
     fn from(e: io::Error) -> Self {
-        Error::Io(format!("{}", e))
+        Error::io(e.to_string())
+    }
+}
+
+/// Adds the number of the affected module to I/O errors.
+pub(crate) trait ModuleContext<T> {
+    fn module_context(self, module: usize) -> Result<T, Error>;
+}
+
+impl<T> ModuleContext<T> for io::Result<T> {
+    fn module_context(self, module: usize) -> Result<T, Error> {
+        self.map_err(|e| Error::Io {
+            module: Some(module),
+            source: e.to_string(),
+        })
     }
 }
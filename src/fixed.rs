@@ -0,0 +1,407 @@
+//! A coupler with a fixed capacity for small stations.
+//!
+//! `FixedCoupler` keeps all modules and channel values in arrays
+//! whose size is bounded at compile time (`M` modules with up to
+//! `C` channels each), so processing a cycle does not allocate.
+//! It supports modules with plain digital and analog channels and
+//! uses the conversions of the `codec` module.
+
+use super::*;
+use crate::ur20_fbc_mod_tcp::{
+    to_register_address, ModuleOffset, ADDR_PACKED_PROCESS_INPUT_DATA,
+    ADDR_PACKED_PROCESS_OUTPUT_DATA,
+};
+use std::cmp;
+
+/// The conversion of an analog channel.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub enum ChannelCodec {
+    #[default]
+    Disabled,
+    /// Current or voltage (see `codec::u16_to_analog_ui_value`).
+    AnalogUI(AnalogUIRange, DataFormat),
+    /// Temperature or resistance (see `codec::u16_to_rtd_value`).
+    Rtd(RtdRange),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    DigitalIn,
+    DigitalOut,
+    AnalogIn,
+    AnalogOut,
+}
+
+impl Kind {
+    fn of(module_type: &ModuleType) -> Option<Kind> {
+        use crate::ModuleType::*;
+        match *module_type {
+            UR20_4DI_P => Some(Kind::DigitalIn),
+            UR20_4DO_P | UR20_16DO_P | UR20_4RO_CO_255 => Some(Kind::DigitalOut),
+            UR20_4AI_UI_12 | UR20_4AI_UI_16_DIAG | UR20_8AI_I_16_DIAG_HD | UR20_4AI_RTD_DIAG => {
+                Some(Kind::AnalogIn)
+            }
+            UR20_4AO_UI_16 | UR20_4AO_UI_16_DIAG => Some(Kind::AnalogOut),
+            _ => None,
+        }
+    }
+
+    fn is_input(self) -> bool {
+        self == Kind::DigitalIn || self == Kind::AnalogIn
+    }
+}
+
+#[derive(Debug)]
+struct Slot<const C: usize> {
+    module_type: ModuleType,
+    kind: Kind,
+    offset: ModuleOffset,
+    codecs: [ChannelCodec; C],
+    values: [ChannelValue; C],
+}
+
+impl<const C: usize> Slot<C> {
+    fn channel_count(&self) -> usize {
+        self.module_type.channel_count()
+    }
+
+    /// Byte position of the module data within the image.
+    fn byte_offset(&self) -> Result<usize> {
+        let (offset, base) = if self.kind.is_input() {
+            (self.offset.input, ADDR_PACKED_PROCESS_INPUT_DATA)
+        } else {
+            (self.offset.output, ADDR_PACKED_PROCESS_OUTPUT_DATA)
+        };
+        let (register, bit) = to_register_address(offset.ok_or(Error::ModuleOffset)?);
+        if register < base || (bit != 0 && bit != 8) {
+            return Err(Error::ModuleOffset);
+        }
+        Ok((register - base) as usize * 2 + bit / 8)
+    }
+}
+
+/// A coupler for at most `M` modules with at most `C` channels each.
+#[derive(Debug)]
+pub struct FixedCoupler<const M: usize, const C: usize> {
+    slots: [Option<Slot<C>>; M],
+    len: usize,
+}
+
+impl<const M: usize, const C: usize> Default for FixedCoupler<M, C> {
+    fn default() -> Self {
+        FixedCoupler {
+            slots: std::array::from_fn(|_| None),
+            len: 0,
+        }
+    }
+}
+
+impl<const M: usize, const C: usize> FixedCoupler<M, C> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Number of modules.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Appends a module and returns its position.
+    ///
+    /// Analog channels are disabled until a `ChannelCodec` is set.
+    pub fn add_module(&mut self, module_type: ModuleType, offset: ModuleOffset) -> Result<usize> {
+        let kind = Kind::of(&module_type).ok_or(Error::UnknownModule)?;
+        if self.len == M || module_type.channel_count() > C {
+            return Err(Error::Capacity);
+        }
+        let slot = Slot {
+            module_type,
+            kind,
+            offset,
+            codecs: std::array::from_fn(|_| ChannelCodec::Disabled),
+            values: std::array::from_fn(|_| ChannelValue::None),
+        };
+        slot.byte_offset()?;
+        self.slots[self.len] = Some(slot);
+        self.len += 1;
+        Ok(self.len - 1)
+    }
+
+    pub fn module_type(&self, module: usize) -> Option<&ModuleType> {
+        self.slot(module).map(|s| &s.module_type)
+    }
+
+    /// Sets the conversion of an analog channel.
+    pub fn set_channel_codec(&mut self, addr: &Address, codec: ChannelCodec) -> Result<()> {
+        let slot = self.slot_mut(addr)?;
+        if slot.kind != Kind::AnalogIn && slot.kind != Kind::AnalogOut {
+            return Err(Error::ChannelParameter);
+        }
+        slot.codecs[addr.channel] = codec;
+        Ok(())
+    }
+
+    /// The current value of a channel.
+    ///
+    /// Input values are updated by `next`,
+    /// output values are the ones that were set last.
+    pub fn get(&self, addr: &Address) -> Option<&ChannelValue> {
+        self.slot(addr.module)
+            .filter(|s| addr.channel < s.channel_count())
+            .map(|s| &s.values[addr.channel])
+    }
+
+    /// Sets the value of an output channel.
+    pub fn set_output(&mut self, addr: &Address, value: ChannelValue) -> Result<()> {
+        let slot = self.slot_mut(addr)?;
+        let valid = matches!(
+            (slot.kind, &value),
+            (Kind::DigitalOut, ChannelValue::Bit(_))
+                | (Kind::AnalogOut, ChannelValue::Decimal32(_))
+                | (Kind::DigitalOut, ChannelValue::Disabled)
+                | (Kind::AnalogOut, ChannelValue::Disabled)
+        );
+        if !valid {
+            return Err(Error::ChannelValue);
+        }
+        slot.values[addr.channel] = value;
+        Ok(())
+    }
+
+    /// Decodes the process input data and
+    /// writes the output values into the process output data.
+    ///
+    /// Bytes of the output image that do not belong
+    /// to an output module are left untouched.
+    pub fn next(&mut self, process_input: &[u16], process_output: &mut [u16]) -> Result<()> {
+        for slot in self.slots.iter_mut().flatten() {
+            let start = slot.byte_offset()?;
+            let cnt = slot.channel_count();
+            match slot.kind {
+                Kind::DigitalIn => {
+                    for ch in 0..cnt {
+                        let byte = read_byte(process_input, start + ch / 8)?;
+                        slot.values[ch] = ChannelValue::Bit(byte & (1 << (ch % 8)) != 0);
+                    }
+                }
+                Kind::AnalogIn => {
+                    for ch in 0..cnt {
+                        let word = read_word(process_input, start + ch * 2)?;
+                        let v = match slot.codecs[ch] {
+                            ChannelCodec::Disabled => None,
+                            ChannelCodec::AnalogUI(ref range, ref format) => {
+                                codec::u16_to_analog_ui_value(word, range, format)
+                            }
+                            ChannelCodec::Rtd(ref range) => codec::u16_to_rtd_value(word, range),
+                        };
+                        slot.values[ch] = v
+                            .map(ChannelValue::Decimal32)
+                            .unwrap_or(ChannelValue::Disabled);
+                    }
+                }
+                Kind::DigitalOut => {
+                    for byte_nr in 0..cnt.div_ceil(8) {
+                        let mut byte = 0;
+                        for ch in byte_nr * 8..cmp::min(cnt, byte_nr * 8 + 8) {
+                            if slot.values[ch] == ChannelValue::Bit(true) {
+                                byte |= 1 << (ch % 8);
+                            }
+                        }
+                        write_byte(process_output, start + byte_nr, byte)?;
+                    }
+                }
+                Kind::AnalogOut => {
+                    for ch in 0..cnt {
+                        let word = match (&slot.values[ch], &slot.codecs[ch]) {
+                            (ChannelValue::Decimal32(v), ChannelCodec::AnalogUI(range, format)) => {
+                                codec::analog_ui_value_to_u16(*v, range, format)
+                            }
+                            _ => 0,
+                        };
+                        write_word(process_output, start + ch * 2, word)?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn slot(&self, module: usize) -> Option<&Slot<C>> {
+        self.slots.get(module).and_then(Option::as_ref)
+    }
+
+    fn slot_mut(&mut self, addr: &Address) -> Result<&mut Slot<C>> {
+        match self.slots.get_mut(addr.module).and_then(Option::as_mut) {
+            Some(slot) if addr.channel < slot.channel_count() => Ok(slot),
+            _ => Err(Error::Address),
+        }
+    }
+}
+
+fn read_byte(image: &[u16], idx: usize) -> Result<u8> {
+    let word = image.get(idx / 2).ok_or(Error::BufferLength)?;
+    Ok(if idx % 2 == 0 {
+        *word as u8
+    } else {
+        (*word >> 8) as u8
+    })
+}
+
+fn write_byte(image: &mut [u16], idx: usize, byte: u8) -> Result<()> {
+    let word = image.get_mut(idx / 2).ok_or(Error::BufferLength)?;
+    *word = if idx % 2 == 0 {
+        (*word & 0xFF00) | u16::from(byte)
+    } else {
+        (*word & 0x00FF) | (u16::from(byte) << 8)
+    };
+    Ok(())
+}
+
+fn read_word(image: &[u16], idx: usize) -> Result<u16> {
+    if idx % 2 != 0 {
+        return Err(Error::ModuleOffset);
+    }
+    image.get(idx / 2).cloned().ok_or(Error::BufferLength)
+}
+
+fn write_word(image: &mut [u16], idx: usize, word: u16) -> Result<()> {
+    if idx % 2 != 0 {
+        return Err(Error::ModuleOffset);
+    }
+    *image.get_mut(idx / 2).ok_or(Error::BufferLength)? = word;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::ur20_fbc_mod_tcp::to_bit_address;
+
+    fn offset(input: Option<u16>, output: Option<u16>) -> ModuleOffset {
+        ModuleOffset { input, output }
+    }
+
+    #[test]
+    fn reject_modules_beyond_capacity() {
+        let mut c = FixedCoupler::<1, 4>::new();
+        assert!(c.is_empty());
+        assert_eq!(
+            c.add_module(ModuleType::UR20_16DO_P, offset(None, Some(0x8000)))
+                .err()
+                .unwrap(),
+            Error::Capacity
+        );
+        assert_eq!(
+            c.add_module(ModuleType::UR20_4DI_P, offset(Some(0x0000), None))
+                .unwrap(),
+            0
+        );
+        assert_eq!(
+            c.add_module(ModuleType::UR20_4DO_P, offset(None, Some(0x8000)))
+                .err()
+                .unwrap(),
+            Error::Capacity
+        );
+        assert_eq!(c.len(), 1);
+    }
+
+    #[test]
+    fn reject_unsupported_modules() {
+        let mut c = FixedCoupler::<2, 4>::new();
+        assert_eq!(
+            c.add_module(ModuleType::UR20_2FCNT_100, offset(Some(0), Some(0x8000)))
+                .err()
+                .unwrap(),
+            Error::UnknownModule
+        );
+    }
+
+    #[test]
+    fn process_digital_and_analog_modules() {
+        let mut c = FixedCoupler::<4, 4>::new();
+        c.add_module(ModuleType::UR20_4DI_P, offset(Some(0x0000), None))
+            .unwrap();
+        c.add_module(
+            ModuleType::UR20_4AI_UI_12,
+            offset(Some(to_bit_address(0x0001, 0)), None),
+        )
+        .unwrap();
+        c.add_module(ModuleType::UR20_4DO_P, offset(None, Some(0x8000)))
+            .unwrap();
+        c.add_module(ModuleType::UR20_4RO_CO_255, offset(None, Some(0x8008)))
+            .unwrap();
+        c.set_channel_codec(
+            &Address {
+                module: 1,
+                channel: 0,
+            },
+            ChannelCodec::AnalogUI(AnalogUIRange::V0To10, DataFormat::S7),
+        )
+        .unwrap();
+        assert!(c
+            .set_channel_codec(
+                &Address {
+                    module: 0,
+                    channel: 0
+                },
+                ChannelCodec::Disabled
+            )
+            .is_err());
+        c.set_output(
+            &Address {
+                module: 2,
+                channel: 1,
+            },
+            ChannelValue::Bit(true),
+        )
+        .unwrap();
+        c.set_output(
+            &Address {
+                module: 3,
+                channel: 3,
+            },
+            ChannelValue::Bit(true),
+        )
+        .unwrap();
+        assert!(c
+            .set_output(
+                &Address {
+                    module: 0,
+                    channel: 0
+                },
+                ChannelValue::Bit(true)
+            )
+            .is_err());
+
+        let mut out = [0xFFFF, 0xABCD];
+        c.next(&[0b0101, 0x6C00, 0, 0, 0], &mut out).unwrap();
+        assert_eq!(out, [0x0802, 0xABCD]);
+        assert_eq!(
+            c.get(&Address {
+                module: 0,
+                channel: 2
+            }),
+            Some(&ChannelValue::Bit(true))
+        );
+        assert_eq!(
+            c.get(&Address {
+                module: 1,
+                channel: 0
+            }),
+            Some(&ChannelValue::Decimal32(10.0))
+        );
+        assert_eq!(
+            c.get(&Address {
+                module: 1,
+                channel: 1
+            }),
+            Some(&ChannelValue::Disabled)
+        );
+        assert!(c.next(&[0], &mut out).is_err());
+    }
+}
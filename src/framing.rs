@@ -0,0 +1,279 @@
+//! Framing of serial protocols (delimiters, checksums and escaping).
+//!
+//! A `FrameCodec` describes the framing of a protocol and
+//! a `FrameDecoder` splits a byte stream (e.g. the data read
+//! from a communication module) into frames.
+
+use super::*;
+use crate::util::crc16;
+use std::io::Read;
+
+/// The checksum that is appended to the payload.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Checksum {
+    None,
+    /// XOR of all bytes.
+    Xor,
+    /// Longitudinal redundancy check (two's complement of the sum).
+    Lrc,
+    /// CRC-16 as used by Modbus (little endian).
+    Crc16,
+}
+
+impl Checksum {
+    fn calculate(&self, data: &[u8]) -> Vec<u8> {
+        match *self {
+            Checksum::None => vec![],
+            Checksum::Xor => vec![data.iter().fold(0, |acc, x| acc ^ x)],
+            Checksum::Lrc => vec![data
+                .iter()
+                .fold(0u8, |acc, x| acc.wrapping_add(*x))
+                .wrapping_neg()],
+            Checksum::Crc16 => crc16(data).to_le_bytes().to_vec(),
+        }
+    }
+}
+
+/// The framing of a protocol.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FrameCodec {
+    /// Start delimiter (e.g. `STX`).
+    pub start: Option<u8>,
+    /// End delimiter (e.g. `ETX`).
+    pub end: u8,
+    pub checksum: Checksum,
+    /// Transmit the checksum as upper case hex digits.
+    pub hex_checksum: bool,
+    /// Escape byte: delimiters and the escape byte itself
+    /// are sent as the escape byte followed by the byte XOR `0x20`.
+    pub escape: Option<u8>,
+}
+
+pub const STX: u8 = 0x02;
+pub const ETX: u8 = 0x03;
+
+impl FrameCodec {
+    /// Framing with `STX` and `ETX` without checksum.
+    pub fn stx_etx() -> Self {
+        FrameCodec {
+            start: Some(STX),
+            end: ETX,
+            checksum: Checksum::None,
+            hex_checksum: false,
+            escape: None,
+        }
+    }
+
+    fn is_special(&self, b: u8) -> bool {
+        Some(b) == self.start || b == self.end || Some(b) == self.escape
+    }
+
+    fn checksum(&self, payload: &[u8]) -> Vec<u8> {
+        let sum = self.checksum.calculate(payload);
+        if self.hex_checksum {
+            sum.iter()
+                .flat_map(|b| format!("{:02X}", b).into_bytes())
+                .collect()
+        } else {
+            sum
+        }
+    }
+
+    fn checksum_len(&self) -> usize {
+        let len = match self.checksum {
+            Checksum::None => 0,
+            Checksum::Xor | Checksum::Lrc => 1,
+            Checksum::Crc16 => 2,
+        };
+        if self.hex_checksum {
+            len * 2
+        } else {
+            len
+        }
+    }
+
+    /// Creates a frame that contains the payload.
+    pub fn encode(&self, payload: &[u8]) -> Vec<u8> {
+        let mut frame = vec![];
+        frame.extend(self.start);
+        for b in payload.iter().chain(&self.checksum(payload)) {
+            match self.escape {
+                Some(esc) if self.is_special(*b) => {
+                    frame.push(esc);
+                    frame.push(b ^ 0x20);
+                }
+                _ => frame.push(*b),
+            }
+        }
+        frame.push(self.end);
+        frame
+    }
+
+    /// Extracts and verifies the payload of a frame.
+    pub fn decode(&self, frame: &[u8]) -> Result<Vec<u8>> {
+        let mut data = frame;
+        if let Some(start) = self.start {
+            if data.first() != Some(&start) {
+                return Err(Error::DataLength);
+            }
+            data = &data[1..];
+        }
+        match data.split_last() {
+            Some((end, rest)) if *end == self.end => data = rest,
+            _ => return Err(Error::DataLength),
+        }
+        let mut content = vec![];
+        let mut iter = data.iter();
+        while let Some(b) = iter.next() {
+            if Some(*b) == self.escape {
+                let b = iter.next().ok_or(Error::DataLength)?;
+                content.push(b ^ 0x20);
+            } else {
+                content.push(*b);
+            }
+        }
+        let len = self.checksum_len();
+        if content.len() < len {
+            return Err(Error::DataLength);
+        }
+        let sum = content.split_off(content.len() - len);
+        if sum != self.checksum(&content) {
+            return Err(Error::Checksum);
+        }
+        Ok(content)
+    }
+}
+
+/// Splits a byte stream into frames.
+#[derive(Debug, Clone)]
+pub struct FrameDecoder {
+    pub codec: FrameCodec,
+    buf: Vec<u8>,
+}
+
+impl FrameDecoder {
+    pub fn new(codec: FrameCodec) -> Self {
+        FrameDecoder { codec, buf: vec![] }
+    }
+
+    /// Adds received data.
+    pub fn push(&mut self, data: &[u8]) {
+        self.buf.extend_from_slice(data);
+    }
+
+    /// Adds all data that is currently available from the reader.
+    pub fn read_from(&mut self, reader: &mut dyn Read) -> Result<()> {
+        let mut buf = [0; 64];
+        loop {
+            let len = reader.read(&mut buf)?;
+            if len == 0 {
+                return Ok(());
+            }
+            self.push(&buf[..len]);
+        }
+    }
+
+    /// Returns the payload of the next complete frame.
+    ///
+    /// Data in front of the start delimiter is dropped.
+    pub fn next_frame(&mut self) -> Option<Result<Vec<u8>>> {
+        if let Some(start) = self.codec.start {
+            let pos = match self.buf.iter().position(|b| *b == start) {
+                Some(pos) => pos,
+                None => {
+                    self.buf.clear();
+                    return None;
+                }
+            };
+            self.buf.drain(..pos);
+        }
+        let end = self.buf.iter().position(|b| *b == self.codec.end)?;
+        let frame: Vec<_> = self.buf.drain(..=end).collect();
+        Some(self.codec.decode(&frame))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn calculate_checksums() {
+        assert_eq!(Checksum::None.calculate(b"abc"), Vec::<u8>::new());
+        assert_eq!(Checksum::Xor.calculate(&[0x01, 0x02, 0x04]), vec![0x07]);
+        assert_eq!(
+            Checksum::Lrc.calculate(&[0x11, 0x03, 0x00, 0x6B, 0x00, 0x03]),
+            vec![0x7E]
+        );
+        assert_eq!(
+            Checksum::Crc16.calculate(&[0x01, 0x03, 0x00, 0x00, 0x00, 0x0A]),
+            vec![0xC5, 0xCD]
+        );
+    }
+
+    #[test]
+    fn encode_and_decode_stx_etx() {
+        let codec = FrameCodec::stx_etx();
+        let frame = codec.encode(b"hello");
+        assert_eq!(frame, b"\x02hello\x03");
+        assert_eq!(codec.decode(&frame).unwrap(), b"hello");
+        assert!(codec.decode(b"hello\x03").is_err());
+        assert!(codec.decode(b"\x02hello").is_err());
+    }
+
+    #[test]
+    fn encode_and_decode_with_hex_checksum() {
+        let codec = FrameCodec {
+            start: Some(b':'),
+            end: b'\n',
+            checksum: Checksum::Lrc,
+            hex_checksum: true,
+            escape: None,
+        };
+        let frame = codec.encode(&[0x11, 0x03, 0x00, 0x6B, 0x00, 0x03]);
+        assert_eq!(&frame[frame.len() - 3..], b"7E\n");
+        assert_eq!(
+            codec.decode(&frame).unwrap(),
+            vec![0x11, 0x03, 0x00, 0x6B, 0x00, 0x03]
+        );
+        let mut invalid = frame.clone();
+        invalid[1] = 0x12;
+        assert_eq!(codec.decode(&invalid).err().unwrap(), Error::Checksum);
+    }
+
+    #[test]
+    fn escape_special_bytes() {
+        let mut codec = FrameCodec::stx_etx();
+        codec.escape = Some(0x10);
+        codec.checksum = Checksum::Xor;
+        let payload = [0x01, STX, 0x10, ETX];
+        let frame = codec.encode(&payload);
+        assert_eq!(
+            frame,
+            vec![STX, 0x01, 0x10, 0x22, 0x10, 0x30, 0x10, 0x23, 0x10, 0x30, ETX]
+        );
+        assert_eq!(codec.decode(&frame).unwrap(), payload);
+    }
+
+    #[test]
+    fn split_stream_into_frames() {
+        let mut codec = FrameCodec::stx_etx();
+        codec.checksum = Checksum::Xor;
+        let mut dec = FrameDecoder::new(codec.clone());
+        assert!(dec.next_frame().is_none());
+        let mut stream = b"garbage".to_vec();
+        stream.extend(codec.encode(b"one"));
+        stream.extend(codec.encode(b"two"));
+        let (a, b) = stream.split_at(14);
+        dec.push(a);
+        assert_eq!(dec.next_frame().unwrap().unwrap(), b"one");
+        assert!(dec.next_frame().is_none());
+        let mut reader = b;
+        dec.read_from(&mut reader).unwrap();
+        assert_eq!(dec.next_frame().unwrap().unwrap(), b"two");
+        assert!(dec.next_frame().is_none());
+        dec.push(b"\x02x\x00\x03");
+        assert_eq!(dec.next_frame().unwrap().err().unwrap(), Error::Checksum);
+    }
+}
@@ -0,0 +1,189 @@
+//! Typed access to the output channels of a coupler.
+//!
+//! A handle checks the module category once when it is created,
+//! so the values that are passed to it always fit the channel:
+//!
+//! ```rust
+//! # use ur20::{ur20_fbc_mod_tcp::{Coupler, CouplerConfig}, Address, ModuleType};
+//! let cfg = CouplerConfig {
+//!     modules: vec![ModuleType::UR20_4DO_P],
+//!     offsets: vec![0x8000, 0xFFFF],
+//!     params: vec![vec![0; 4]],
+//! };
+//! let mut c = Coupler::new(&cfg).unwrap();
+//! let addr = Address { module: 0, channel: 2 };
+//! c.digital_output(&addr).unwrap().set(true).unwrap();
+//! assert!(c.analog_output(&addr).is_err());
+//! ```
+
+use super::*;
+use crate::{error::ModuleContext, ur20_fbc_mod_tcp::Coupler};
+
+/// Handle of a digital or relay output channel.
+#[derive(Debug)]
+pub struct DigitalOutputHandle<'a> {
+    coupler: &'a mut Coupler,
+    address: Address,
+}
+
+impl DigitalOutputHandle<'_> {
+    pub fn address(&self) -> &Address {
+        &self.address
+    }
+
+    /// Switches the output on the next cycle.
+    pub fn set(&mut self, v: bool) -> Result<()> {
+        self.coupler.set_output(&self.address, ChannelValue::Bit(v))
+    }
+}
+
+/// Handle of an analog output channel.
+#[derive(Debug)]
+pub struct AnalogOutputHandle<'a> {
+    coupler: &'a mut Coupler,
+    address: Address,
+}
+
+impl AnalogOutputHandle<'_> {
+    pub fn address(&self) -> &Address {
+        &self.address
+    }
+
+    /// Sets the output value (mA or V) on the next cycle.
+    pub fn set(&mut self, v: f32) -> Result<()> {
+        if !v.is_finite() {
+            return Err(Error::ChannelValue);
+        }
+        self.coupler
+            .set_output(&self.address, ChannelValue::Decimal32(v))
+    }
+}
+
+/// Handle of a serial communication module.
+#[derive(Debug)]
+pub struct SerialHandle<'a> {
+    coupler: &'a mut Coupler,
+    module: usize,
+}
+
+impl SerialHandle<'_> {
+    pub fn module(&self) -> usize {
+        self.module
+    }
+
+    /// Buffers the data that is sent with the next cycles.
+    pub fn write(&mut self, data: &[u8]) -> Result<()> {
+        self.coupler
+            .writer(self.module)
+            .ok_or(Error::ChannelType)?
+            .write_all(data)
+            .module_context(self.module)
+    }
+}
+
+impl Coupler {
+    /// Returns a handle of a digital or relay output channel.
+    pub fn digital_output(&mut self, addr: &Address) -> Result<DigitalOutputHandle<'_>> {
+        match self.category(addr)? {
+            ModuleCategory::DO | ModuleCategory::RO => Ok(DigitalOutputHandle {
+                coupler: self,
+                address: *addr,
+            }),
+            _ => Err(Error::ChannelType),
+        }
+    }
+
+    /// Returns a handle of an analog output channel.
+    pub fn analog_output(&mut self, addr: &Address) -> Result<AnalogOutputHandle<'_>> {
+        match self.category(addr)? {
+            ModuleCategory::AO => Ok(AnalogOutputHandle {
+                coupler: self,
+                address: *addr,
+            }),
+            _ => Err(Error::ChannelType),
+        }
+    }
+
+    /// Returns a handle of a serial communication module.
+    pub fn serial(&mut self, module_nr: usize) -> Result<SerialHandle<'_>> {
+        let addr = Address {
+            module: module_nr,
+            channel: 0,
+        };
+        self.category(&addr)?;
+        if self.writer(module_nr).is_none() {
+            return Err(Error::ChannelType);
+        }
+        Ok(SerialHandle {
+            coupler: self,
+            module: module_nr,
+        })
+    }
+
+    fn category(&self, addr: &Address) -> Result<ModuleCategory> {
+        match self.module_type(addr.module) {
+            Some(t) if addr.channel < t.channel_count() => Ok(ModuleCategory::from(t)),
+            _ => Err(Error::Address),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::ur20_fbc_mod_tcp::CouplerConfig;
+
+    fn coupler() -> Coupler {
+        let cfg = CouplerConfig {
+            modules: vec![
+                ModuleType::UR20_4DO_P,
+                ModuleType::UR20_4AO_UI_16,
+                ModuleType::UR20_1COM_232_485_422,
+                ModuleType::UR20_4DI_P,
+            ],
+            offsets: vec![
+                0x8000, 0xFFFF, 0x8010, 0xFFFF, 0x8050, 0x0000, 0xFFFF, 0x0080,
+            ],
+            params: vec![vec![0; 4], vec![0; 12], vec![0; 10], vec![0; 4]],
+        };
+        Coupler::new(&cfg).unwrap()
+    }
+
+    #[test]
+    fn access_outputs_by_handles() {
+        let mut c = coupler();
+        let addr = |module, channel| Address { module, channel };
+
+        c.digital_output(&addr(0, 1)).unwrap().set(true).unwrap();
+        c.analog_output(&addr(1, 3)).unwrap().set(5.0).unwrap();
+        assert_eq!(
+            c.analog_output(&addr(1, 3)).unwrap().set(f32::NAN),
+            Err(Error::ChannelValue)
+        );
+        assert_eq!(
+            c.pending_writes().get(&addr(0, 1)),
+            Some(&ChannelValue::Bit(true))
+        );
+        assert_eq!(
+            c.pending_writes().get(&addr(1, 3)),
+            Some(&ChannelValue::Decimal32(5.0))
+        );
+        let mut s = c.serial(2).unwrap();
+        assert_eq!(s.module(), 2);
+        s.write(b"abc").unwrap();
+
+        assert_eq!(
+            c.digital_output(&addr(1, 0)).err(),
+            Some(Error::ChannelType)
+        );
+        assert_eq!(
+            c.digital_output(&addr(3, 0)).err(),
+            Some(Error::ChannelType)
+        );
+        assert_eq!(c.analog_output(&addr(0, 0)).err(), Some(Error::ChannelType));
+        assert_eq!(c.serial(0).err(), Some(Error::ChannelType));
+        assert_eq!(c.digital_output(&addr(0, 4)).err(), Some(Error::Address));
+        assert_eq!(c.serial(4).err(), Some(Error::Address));
+    }
+}
@@ -0,0 +1,82 @@
+//! Heartbeat output for downstream monitors.
+
+use super::*;
+use std::time::Duration;
+
+/// How the heartbeat is signaled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HeartbeatMode {
+    /// Toggle a digital output on each cycle.
+    Toggle,
+    /// Write a rolling counter (`0..=max`) to an analog output.
+    Counter { max: u16 },
+}
+
+/// A channel that changes its value on each cycle.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Heartbeat {
+    /// The output channel.
+    pub address: Address,
+    pub mode: HeartbeatMode,
+    /// The maximum expected time between two cycles.
+    pub max_cycle_time: Duration,
+    state: u16,
+}
+
+impl Heartbeat {
+    pub fn new(address: Address, mode: HeartbeatMode, max_cycle_time: Duration) -> Self {
+        Heartbeat {
+            address,
+            mode,
+            max_cycle_time,
+            state: 0,
+        }
+    }
+
+    /// Returns the value for the next cycle.
+    pub fn next_value(&mut self) -> ChannelValue {
+        match self.mode {
+            HeartbeatMode::Toggle => {
+                self.state = (self.state + 1) % 2;
+                ChannelValue::Bit(self.state == 1)
+            }
+            HeartbeatMode::Counter { max } => {
+                self.state = if self.state >= max { 0 } else { self.state + 1 };
+                ChannelValue::Decimal32(f32::from(self.state))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::ChannelValue::*;
+
+    #[test]
+    fn toggle() {
+        let addr = Address {
+            module: 0,
+            channel: 0,
+        };
+        let mut hb = Heartbeat::new(addr, HeartbeatMode::Toggle, Duration::from_secs(1));
+        assert_eq!(hb.next_value(), Bit(true));
+        assert_eq!(hb.next_value(), Bit(false));
+        assert_eq!(hb.next_value(), Bit(true));
+    }
+
+    #[test]
+    fn rolling_counter() {
+        let addr = Address {
+            module: 0,
+            channel: 0,
+        };
+        let mode = HeartbeatMode::Counter { max: 2 };
+        let mut hb = Heartbeat::new(addr, mode, Duration::from_secs(1));
+        assert_eq!(hb.next_value(), Decimal32(1.0));
+        assert_eq!(hb.next_value(), Decimal32(2.0));
+        assert_eq!(hb.next_value(), Decimal32(0.0));
+        assert_eq!(hb.next_value(), Decimal32(1.0));
+    }
+}
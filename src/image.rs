@@ -0,0 +1,190 @@
+//! Raw process images for the exchange with other processes.
+//!
+//! A serialized image starts with the number of input and output
+//! registers (one register each) followed by the input and the output
+//! registers. All registers use the same byte order that has to be
+//! agreed on by both sides.
+//!
+//! ```rust
+//! # use ur20::{image::ProcessImage, ur20_fbc_mod_tcp::RegisterFormat};
+//! let img = ProcessImage {
+//!     input: vec![0x0102],
+//!     output: vec![],
+//! };
+//! let bytes = img.to_bytes(&RegisterFormat::BigEndian);
+//! assert_eq!(bytes, vec![0, 1, 0, 0, 0x01, 0x02]);
+//! assert_eq!(ProcessImage::from_bytes(&bytes, &RegisterFormat::BigEndian).unwrap(), img);
+//! ```
+
+use super::*;
+use crate::ur20_fbc_mod_tcp::RegisterFormat;
+use byteorder::{BigEndian, ByteOrder, LittleEndian};
+use std::cmp;
+
+/// Number of header registers of a serialized image.
+const HEADER_LEN: usize = 2;
+
+/// The register content of the packed process input and output data.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ProcessImage {
+    pub input: Vec<u16>,
+    pub output: Vec<u16>,
+}
+
+impl ProcessImage {
+    /// Serializes the image with the given byte order.
+    ///
+    /// # Panics
+    ///
+    /// Panics if an image has more than `u16::MAX` registers.
+    pub fn to_bytes(&self, format: &RegisterFormat) -> Vec<u8> {
+        let mut words = Vec::with_capacity(HEADER_LEN + self.input.len() + self.output.len());
+        words.push(register_count(&self.input));
+        words.push(register_count(&self.output));
+        words.extend(&self.input);
+        words.extend(&self.output);
+        let mut bytes = vec![0; words.len() * 2];
+        match *format {
+            RegisterFormat::LittleEndian => LittleEndian::write_u16_into(&words, &mut bytes),
+            RegisterFormat::BigEndian => BigEndian::write_u16_into(&words, &mut bytes),
+        }
+        bytes
+    }
+
+    /// Deserializes an image that was created by `to_bytes`
+    /// with the same byte order.
+    pub fn from_bytes(bytes: &[u8], format: &RegisterFormat) -> Result<Self> {
+        if bytes.len() % 2 != 0 || bytes.len() < HEADER_LEN * 2 {
+            return Err(Error::BufferLength);
+        }
+        let mut words = vec![0; bytes.len() / 2];
+        match *format {
+            RegisterFormat::LittleEndian => LittleEndian::read_u16_into(bytes, &mut words),
+            RegisterFormat::BigEndian => BigEndian::read_u16_into(bytes, &mut words),
+        }
+        let input_len = words[0] as usize;
+        let output_len = words[1] as usize;
+        if words.len() != HEADER_LEN + input_len + output_len {
+            return Err(Error::BufferLength);
+        }
+        let (input, output) = words[HEADER_LEN..].split_at(input_len);
+        Ok(ProcessImage {
+            input: input.to_vec(),
+            output: output.to_vec(),
+        })
+    }
+}
+
+/// The input or the output part of a process image.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ImageArea {
+    Input,
+    Output,
+}
+
+/// A difference between two process images
+/// (see `Coupler::diff_images`).
+#[derive(Debug, Clone, PartialEq)]
+pub enum ChannelDiff {
+    /// The decoded values of a channel differ.
+    Channel {
+        area: ImageArea,
+        address: Address,
+        a: ChannelValue,
+        b: ChannelValue,
+    },
+    /// A register that does not belong to a module differs
+    /// (`None` if the register is missing in one of the images).
+    Word {
+        area: ImageArea,
+        index: usize,
+        a: Option<u16>,
+        b: Option<u16>,
+    },
+}
+
+/// Compares the registers of two images word by word.
+pub fn diff_words(area: ImageArea, a: &[u16], b: &[u16]) -> Vec<ChannelDiff> {
+    (0..cmp::max(a.len(), b.len()))
+        .map(|index| (index, a.get(index).cloned(), b.get(index).cloned()))
+        .filter(|(_, a, b)| a != b)
+        .map(|(index, a, b)| ChannelDiff::Word { area, index, a, b })
+        .collect()
+}
+
+fn register_count(data: &[u16]) -> u16 {
+    assert!(data.len() <= u16::MAX as usize, "process image too large");
+    data.len() as u16
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn serialize_with_explicit_byte_order() {
+        let img = ProcessImage {
+            input: vec![0x1234, 0xABCD],
+            output: vec![0x00FF],
+        };
+        assert_eq!(
+            img.to_bytes(&RegisterFormat::LittleEndian),
+            vec![2, 0, 1, 0, 0x34, 0x12, 0xCD, 0xAB, 0xFF, 0x00]
+        );
+        assert_eq!(
+            img.to_bytes(&RegisterFormat::BigEndian),
+            vec![0, 2, 0, 1, 0x12, 0x34, 0xAB, 0xCD, 0x00, 0xFF]
+        );
+        assert_eq!(
+            ProcessImage::default().to_bytes(&RegisterFormat::BigEndian),
+            vec![0; 4]
+        );
+    }
+
+    #[test]
+    fn deserialize_with_explicit_byte_order() {
+        let img = ProcessImage {
+            input: vec![1, 2, 3],
+            output: vec![0x8000],
+        };
+        for format in &[RegisterFormat::LittleEndian, RegisterFormat::BigEndian] {
+            let bytes = img.to_bytes(format);
+            assert_eq!(ProcessImage::from_bytes(&bytes, format).unwrap(), img);
+        }
+        let bytes = img.to_bytes(&RegisterFormat::LittleEndian);
+        assert!(ProcessImage::from_bytes(&bytes, &RegisterFormat::BigEndian).is_err());
+    }
+
+    #[test]
+    fn deserialize_invalid_data() {
+        let format = RegisterFormat::LittleEndian;
+        assert!(ProcessImage::from_bytes(&[], &format).is_err());
+        assert!(ProcessImage::from_bytes(&[0, 0, 0], &format).is_err());
+        assert!(ProcessImage::from_bytes(&[1, 0, 0, 0], &format).is_err());
+        assert!(ProcessImage::from_bytes(&[0, 0, 0, 0, 1, 0], &format).is_err());
+        assert!(ProcessImage::from_bytes(&[0, 0, 0, 0], &format).is_ok());
+    }
+
+    #[test]
+    fn compare_words() {
+        assert!(diff_words(ImageArea::Input, &[1, 2], &[1, 2]).is_empty());
+        assert_eq!(
+            diff_words(ImageArea::Output, &[1, 2], &[1, 3, 4]),
+            vec![
+                ChannelDiff::Word {
+                    area: ImageArea::Output,
+                    index: 1,
+                    a: Some(2),
+                    b: Some(3)
+                },
+                ChannelDiff::Word {
+                    area: ImageArea::Output,
+                    index: 2,
+                    a: None,
+                    b: Some(4)
+                },
+            ]
+        );
+    }
+}
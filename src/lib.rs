@@ -1,5 +1,9 @@
 // Copyright (c) 2017 - 2018 slowtec GmbH <markus.kohlhase@slowtec.de>
 
+// The parameter parsers of the modules start with the default
+// parameters and decode one field after the other.
+#![allow(clippy::field_reassign_with_default)]
+
 extern crate byteorder;
 #[macro_use]
 extern crate num_derive;
@@ -7,24 +11,81 @@ extern crate num_traits;
 #[macro_use]
 extern crate lazy_static;
 
-use std::{fmt::Debug, result, str::FromStr};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::{
+    convert::TryFrom,
+    fmt::{self, Debug},
+    result,
+    str::FromStr,
+};
 
+#[macro_use]
+mod macros;
+
+#[cfg(feature = "futures")]
+pub mod async_coupler;
+pub mod bitfield;
+pub mod calibration;
+pub mod codec;
+pub mod codegen;
+pub mod compact;
+#[cfg(feature = "config")]
+pub mod config;
+pub mod csv;
+pub mod diagnosis;
 mod error;
-
+pub mod fixed;
+pub mod framing;
+pub mod handle;
+pub mod heartbeat;
+pub mod image;
+pub mod lint;
+#[cfg(feature = "modbus-rtu")]
+pub mod modbus_rtu;
+#[cfg(feature = "uom")]
+pub mod quantity;
+pub mod reproduction;
+pub mod rules;
+
+#[cfg(feature = "shm")]
+pub mod shm;
+pub mod simulator;
+pub mod station;
+pub mod task;
+#[cfg(feature = "tcp")]
+pub mod tcp;
+pub mod templates;
+#[cfg(feature = "testing")]
+pub mod testing;
 pub mod ur20_16do_p;
 pub mod ur20_1com_232_485_422;
+pub mod ur20_1ssi;
 pub mod ur20_2fcnt_100;
+pub mod ur20_2pwm_pn;
+pub mod ur20_3em_230v_ac;
 pub mod ur20_4ai_rtd_diag;
+pub mod ur20_4ai_tc_diag;
 pub mod ur20_4ai_ui_12;
 pub mod ur20_4ai_ui_16_diag;
 pub mod ur20_4ao_ui_16;
 pub mod ur20_4ao_ui_16_diag;
+pub mod ur20_4com_io_link;
 pub mod ur20_4di_p;
 pub mod ur20_4do_p;
 pub mod ur20_4ro_co_255;
 pub mod ur20_8ai_i_16_diag_hd;
+pub mod ur20_ai_i_generic;
+pub mod ur20_ai_ui_generic;
+pub mod ur20_ao_ui_generic;
+pub mod ur20_di_generic;
+pub mod ur20_di_ts;
+pub mod ur20_do_generic;
 pub mod ur20_fbc_mod_tcp;
+pub mod ur20_pf;
+pub mod ur20_ro_generic;
 pub(crate) mod util;
+pub mod voting;
 
 pub use crate::error::*;
 
@@ -32,7 +93,14 @@ const S5_FACTOR: u16 = 16_384;
 const S7_FACTOR: u16 = 27_648;
 
 use crate::ur20_1com_232_485_422::{ProcessInput as RsIn, ProcessOutput as RsOut};
-use crate::ur20_2fcnt_100::{ProcessInput as FcntIn, ProcessOutput as FcntOut};
+use crate::ur20_1ssi::ProcessInput as SsiIn;
+use crate::ur20_2fcnt_100::{
+    Command as FcntCommand, ProcessInput as FcntIn, ProcessOutput as FcntOut,
+};
+use crate::ur20_2pwm_pn::ProcessOutput as PwmOut;
+use crate::ur20_3em_230v_ac::ProcessInput as EmIn;
+use crate::ur20_4com_io_link::PortInput as IoLinkIn;
+use crate::ur20_di_ts::ProcessInput as TsIn;
 
 /// Data type used by the module channels.
 #[derive(Debug, Clone, PartialEq)]
@@ -49,6 +117,16 @@ pub enum ChannelValue {
     FcntIn(FcntIn),
     /// Special output data used by 2FCNT-100
     FcntOut(FcntOut),
+    /// Special input data used by 1SSI
+    SsiIn(SsiIn),
+    /// Special output data used by 2PWM-PN
+    PwmOut(PwmOut),
+    /// Special input data used by 3EM-230V-AC
+    EmIn(EmIn),
+    /// Special input data used by 4COM-IO-LINK
+    IoLinkIn(IoLinkIn),
+    /// Special input data used by 2DI-P-TS and 4DI-P-TS
+    TsIn(TsIn),
     /// Raw binary data.
     Bytes(Vec<u8>),
     /// The channel is currently disabled.
@@ -57,8 +135,57 @@ pub enum ChannelValue {
     None,
 }
 
+/// The quality of the values of a module.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Quality {
+    /// The values were read from the process image.
+    Good,
+    /// The module data is not part of the process image.
+    Unavailable,
+}
+
+/// A measured current or voltage that may be out of range.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AnalogValue {
+    /// A valid measurement (including the overshoot range).
+    InRange(f32),
+    /// The value is above the measurement range.
+    Overload,
+    /// The value is below the measurement range.
+    Underload,
+    /// The value of a live zero range (e.g. `4..20 mA`)
+    /// is below the measurement range.
+    WireBreak,
+}
+
+/// The result of the limit value monitoring of a channel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LimitStatus {
+    /// The value is within the limits.
+    Ok,
+    /// The value is above the high limit value.
+    AboveHigh,
+    /// The value is below the low limit value.
+    BelowLow,
+}
+
+impl LimitStatus {
+    /// Compares a raw process value with the limit values
+    /// (both in the register format of the measurement range).
+    pub fn from_raw_value(value: i16, low: i16, high: i16) -> Self {
+        if value > high {
+            LimitStatus::AboveHigh
+        } else if value < low {
+            LimitStatus::BelowLow
+        } else {
+            LimitStatus::Ok
+        }
+    }
+}
+
 /// A fieldbus independend channel address.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Address {
     /// Module position (beginning at `0`)
     pub module: usize,
@@ -66,12 +193,110 @@ pub struct Address {
     pub channel: usize,
 }
 
+impl Address {
+    /// Addresses a field of a structured channel value
+    /// (e.g. the `frequency` of a frequency counter channel).
+    pub fn sub(&self, field: &str) -> SubAddress {
+        SubAddress {
+            address: *self,
+            sub: field.into(),
+        }
+    }
+}
+
+/// The address of a field within a structured channel value.
+///
+/// See `ChannelValue::fields` for the available fields.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct SubAddress {
+    pub address: Address,
+    /// Name of the field
+    pub sub: String,
+}
+
+/// Physical unit of a channel value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Unit {
+    /// Milliampere (mA)
+    MilliAmpere,
+    /// Volt (V)
+    Volt,
+    /// Ohm (Ω)
+    Ohm,
+    /// Degree Celsius (°C)
+    Celsius,
+    /// Degree Fahrenheit (°F)
+    Fahrenheit,
+    /// Kelvin (K)
+    Kelvin,
+}
+
+/// Meta data of a module channel.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ChannelInfo {
+    /// The unit of the channel value (if there is any).
+    pub unit: Option<Unit>,
+    /// The configured measurement or output range (e.g. `mA4To20`).
+    pub range: Option<String>,
+    /// A short name of the signal (e.g. `pump_1`).
+    pub label: Option<String>,
+    /// A longer description of the signal.
+    pub description: Option<String>,
+}
+
 type Result<T> = result::Result<T, Error>;
 
+// Couplers and modules are moved into other threads or tasks,
+// so these bounds are checked at compile time.
+const _: fn() = || {
+    fn send<T: Send>() {}
+    fn send_sync<T: Send + Sync>() {}
+
+    send::<ur20_fbc_mod_tcp::Coupler>();
+    send::<simulator::SimulatedCoupler>();
+    send_sync::<ur20_fbc_mod_tcp::Observer>();
+    send_sync::<fixed::FixedCoupler<1, 1>>();
+    send_sync::<ur20_1com_232_485_422::MessageProcessor>();
+
+    send_sync::<ur20_16do_p::Mod>();
+    send_sync::<ur20_1com_232_485_422::Mod>();
+    send_sync::<ur20_1ssi::Mod>();
+    send_sync::<ur20_2pwm_pn::Mod>();
+    send_sync::<ur20_3em_230v_ac::Mod>();
+    send_sync::<ur20_4com_io_link::Mod>();
+    send_sync::<ur20_2fcnt_100::Mod>();
+    send_sync::<ur20_4ai_rtd_diag::Mod>();
+    send_sync::<ur20_4ai_tc_diag::Mod>();
+    send_sync::<ur20_4ai_ui_12::Mod>();
+    send_sync::<ur20_4ai_ui_16_diag::Mod>();
+    send_sync::<ur20_4ao_ui_16::Mod>();
+    send_sync::<ur20_4ao_ui_16_diag::Mod>();
+    send_sync::<ur20_4di_p::Mod>();
+    send_sync::<ur20_4do_p::Mod>();
+    send_sync::<ur20_4ro_co_255::Mod>();
+    send_sync::<ur20_8ai_i_16_diag_hd::Mod>();
+    send_sync::<ur20_ai_i_generic::Mod>();
+    send_sync::<ur20_ai_ui_generic::Mod>();
+    send_sync::<ur20_ao_ui_generic::Mod>();
+    send_sync::<ur20_di_generic::Mod>();
+    send_sync::<ur20_di_ts::Mod>();
+    send_sync::<ur20_do_generic::Mod>();
+    send_sync::<ur20_pf::Mod>();
+    send_sync::<ur20_ro_generic::Mod>();
+
+    send_sync::<ChannelValue>();
+    send_sync::<Error>();
+};
+
 /// A generic description of modules.
 pub trait Module: Debug {
     /// Get concrete i/o module type.
     fn module_type(&self) -> ModuleType;
+    /// Get the meta data of all channels.
+    fn channel_info(&self) -> Vec<ChannelInfo> {
+        vec![ChannelInfo::default(); self.module_type().channel_count()]
+    }
 }
 
 /// Describes the general class of a module.
@@ -104,6 +329,7 @@ pub enum ModuleCategory {
 /// Describes the concrete module type.
 #[allow(non_camel_case_types)]
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum ModuleType {
     // Digital input modules
     UR20_4DI_P,
@@ -190,7 +416,8 @@ pub enum ModuleType {
 }
 
 /// Describes how the data should be interpreted.
-#[derive(Debug, Clone, PartialEq, Eq, FromPrimitive, ToPrimitive)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, FromPrimitive, ToPrimitive)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum DataFormat {
     /// Siemens S5 format
     S5 = 0,
@@ -198,6 +425,66 @@ pub enum DataFormat {
     S7 = 1,
 }
 
+impl AnalogUIRange {
+    /// The unit of values within this range.
+    pub fn unit(&self) -> Option<Unit> {
+        use crate::AnalogUIRange::*;
+        match *self {
+            mA0To20 | mA4To20 => Some(Unit::MilliAmpere),
+            V0To10 | VMinus10To10 | V0To5 | VMinus5To5 | V1To5 | V2To10 => Some(Unit::Volt),
+            Disabled => None,
+        }
+    }
+}
+
+impl AnalogIRange {
+    /// The unit of values within this range.
+    pub fn unit(&self) -> Option<Unit> {
+        match *self {
+            AnalogIRange::mA0To20 | AnalogIRange::mA4To20 => Some(Unit::MilliAmpere),
+            AnalogIRange::Disabled => None,
+        }
+    }
+}
+
+impl RtdRange {
+    /// The unit of values within this range.
+    pub fn unit(&self, temperature_unit: &TemperatureUnit) -> Option<Unit> {
+        use crate::RtdRange::*;
+        match *self {
+            PT100 | PT200 | PT500 | PT1000 | NI100 | NI120 | NI200 | NI500 | NI1000 | Cu10 => {
+                Some(temperature_unit.into())
+            }
+            R40 | R80 | R150 | R300 | R500 | R1000 | R2000 | R4000 => Some(Unit::Ohm),
+            Disabled => None,
+        }
+    }
+}
+
+impl TcRange {
+    /// The unit of values within this range.
+    ///
+    /// Values of the voltage ranges are reported in `V`.
+    pub fn unit(&self, temperature_unit: &TemperatureUnit) -> Option<Unit> {
+        use crate::TcRange::*;
+        match *self {
+            J | K | N | R | S | T | B | C | E | L | U => Some(temperature_unit.into()),
+            mV15 | mV31 | mV62 | mV125 | mV250 | mV500 | mV1000 => Some(Unit::Volt),
+            Disabled => None,
+        }
+    }
+}
+
+impl From<&TemperatureUnit> for Unit {
+    fn from(u: &TemperatureUnit) -> Self {
+        match *u {
+            TemperatureUnit::Celsius => Unit::Celsius,
+            TemperatureUnit::Fahrenheit => Unit::Fahrenheit,
+            TemperatureUnit::Kelvin => Unit::Kelvin,
+        }
+    }
+}
+
 impl DataFormat {
     fn factor(&self) -> f32 {
         f32::from(match *self {
@@ -209,7 +496,8 @@ impl DataFormat {
 
 /// Analog input or output range (current and voltage).
 #[allow(non_camel_case_types)]
-#[derive(Debug, Clone, PartialEq, Eq, FromPrimitive, ToPrimitive)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, FromPrimitive, ToPrimitive)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum AnalogUIRange {
     /// 0mA ... 20mA
     mA0To20 = 0,
@@ -233,7 +521,8 @@ pub enum AnalogUIRange {
 
 /// Analog input or output range (current only).
 #[allow(non_camel_case_types)]
-#[derive(Debug, Clone, PartialEq, Eq, FromPrimitive, ToPrimitive)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, FromPrimitive, ToPrimitive)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum AnalogIRange {
     /// 0mA ... 20mA
     mA0To20 = 0,
@@ -244,7 +533,8 @@ pub enum AnalogIRange {
 }
 
 /// Resistor value range.
-#[derive(Debug, Clone, PartialEq, Eq, FromPrimitive, ToPrimitive)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, FromPrimitive, ToPrimitive)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum RtdRange {
     /// -200 ... 850 Degree Celsius
     PT100 = 0,
@@ -286,9 +576,64 @@ pub enum RtdRange {
     Disabled = 18,
 }
 
+/// Thermocouple type or voltage range.
+#[allow(non_camel_case_types)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, FromPrimitive, ToPrimitive)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum TcRange {
+    /// -210 ... 1200 Degree Celsius
+    J = 0,
+    /// -270 ... 1372 Degree Celsius
+    K = 1,
+    /// -270 ... 1300 Degree Celsius
+    N = 2,
+    /// -50 ... 1768 Degree Celsius
+    R = 3,
+    /// -50 ... 1768 Degree Celsius
+    S = 4,
+    /// -270 ... 400 Degree Celsius
+    T = 5,
+    /// 0 ... 1820 Degree Celsius
+    B = 6,
+    /// 0 ... 2315 Degree Celsius
+    C = 7,
+    /// -270 ... 1000 Degree Celsius
+    E = 8,
+    /// -200 ... 900 Degree Celsius
+    L = 9,
+    /// -200 ... 600 Degree Celsius
+    U = 10,
+    /// -15.625 ... 15.625 mV
+    mV15 = 11,
+    /// -31.25 ... 31.25 mV
+    mV31 = 12,
+    /// -62.5 ... 62.5 mV
+    mV62 = 13,
+    /// -125 ... 125 mV
+    mV125 = 14,
+    /// -250 ... 250 mV
+    mV250 = 15,
+    /// -500 ... 500 mV
+    mV500 = 16,
+    /// -1000 ... 1000 mV
+    mV1000 = 17,
+    /// Disabled
+    Disabled = 18,
+}
+
+/// Compensation of the cold junction temperature of a thermocouple.
+#[rustfmt::skip]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, FromPrimitive, ToPrimitive)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum ColdJunctionCompensation {
+    Internal = 0,
+    External = 1,
+}
+
 /// The unit a temperature value is represented in.
 #[rustfmt::skip]
-#[derive(Debug, Clone, PartialEq, Eq, FromPrimitive, ToPrimitive)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, FromPrimitive, ToPrimitive)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum TemperatureUnit {
     Celsius    = 0,
     Fahrenheit = 1,
@@ -297,7 +642,8 @@ pub enum TemperatureUnit {
 
 /// Describes how the resistor is physically conneted.
 #[rustfmt::skip]
-#[derive(Debug, Clone, PartialEq, Eq, FromPrimitive, ToPrimitive)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, FromPrimitive, ToPrimitive)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum ConnectionType {
     TwoWire   = 0,
     ThreeWire = 1,
@@ -307,7 +653,8 @@ pub enum ConnectionType {
 /// Time to convert a signal.
 #[rustfmt::skip]
 #[allow(non_camel_case_types)]
-#[derive(Debug, Clone, PartialEq, Eq, FromPrimitive, ToPrimitive)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, FromPrimitive, ToPrimitive)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum ConversionTime {
     ms240 = 0,
     ms130 = 1,
@@ -320,7 +667,8 @@ pub enum ConversionTime {
 /// Filter signals by defining a minimal duration.
 #[rustfmt::skip]
 #[allow(non_camel_case_types)]
-#[derive(Debug, Clone, PartialEq, Eq, FromPrimitive, ToPrimitive)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, FromPrimitive, ToPrimitive)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum InputFilter {
     us5    = 0,
     us11   = 1,
@@ -344,7 +692,8 @@ pub enum InputFilter {
 /// Time to delay a signal.
 #[rustfmt::skip]
 #[allow(non_camel_case_types)]
-#[derive(Debug, Clone, PartialEq, Eq, FromPrimitive, ToPrimitive)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, FromPrimitive, ToPrimitive)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum InputDelay {
     no    = 0,
     us300 = 1, // not at PROFIBUS-DP
@@ -357,7 +706,8 @@ pub enum InputDelay {
 /// Frequency suppression.
 #[rustfmt::skip]
 #[allow(non_camel_case_types)]
-#[derive(Debug, Clone, PartialEq, Eq, FromPrimitive, ToPrimitive)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, FromPrimitive, ToPrimitive)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum FrequencySuppression {
     Disabled  = 0,
     Hz50      = 1,
@@ -367,84 +717,46 @@ pub enum FrequencySuppression {
 
 impl ModuleType {
     pub fn try_from_u32(id: u32) -> Result<Self> {
-        use crate::ModuleType::*;
+        MODULE_IDS
+            .iter()
+            .find(|(x, _)| *x == id)
+            .map(|(_, t)| t.clone())
+            .ok_or(Error::UnknownModule)
+    }
 
-        let t = match id {
-            0x0009_1F84 => UR20_4DI_P,
-            0x001B_1F84 => UR20_4DI_P_3W,
-            0x0013_1FC1 => UR20_8DI_P_2W,
-            0x000A_1FC1 => UR20_8DI_P_3W,
-            0x0003_1FC1 => UR20_8DI_P_3W_HD,
-            0x0004_9FC2 => UR20_16DI_P,
-            0x0005_9FC2 => UR20_16DI_P_PLC_INT,
-            0x0F01_4700 => UR20_2DI_P_TS,
-            0x0F02_4700 => UR20_4DI_P_TS,
-            0x0001_1F84 => UR20_4DI_N,
-            0x0002_1FC1 => UR20_8DI_N_3W,
-            0x000C_9FC2 => UR20_16DI_N,
-            0x000D_9FC2 => UR20_16DI_N_PLC_INT,
-            0x0016_9F84 => UR20_4DI_2W_230V_AC,
-
-            0x0101_2FA0 => UR20_4DO_P,
-            0x0105_2FA0 => UR20_4DO_P_2A,
-            0x0115_2FC8 => UR20_4DO_PN_2A,
-            0x0102_2FC8 => UR20_8DO_P,
-            0x0119_2FC8 => UR20_8DO_P_2W_HD,
-            0x0103_AFD0 => UR20_16DO_P,
-            0x0104_AFD0 => UR20_16DO_P_PLC_INT,
-            0x010A_2FA0 => UR20_4DO_N,
-            0x010B_2FA0 => UR20_4DO_N_2A,
-            0x010C_2FC8 => UR20_8DO_N,
-            0x010D_AFD0 => UR20_16DO_N,
-            0x010E_AFD0 => UR20_16DO_N_PLC_INT,
-            0x0107_2FA0 => UR20_4RO_SSR_255,
-            0x0106_2FA0 => UR20_4RO_CO_255,
-
-            0x0908_4880 => UR20_2PWM_PN_0_5A,
-            0x0909_4880 => UR20_2PWM_PN_2A,
-
-            0x0401_15C4 => UR20_4AI_UI_16,
-            0x0402_1544 => UR20_4AI_UI_16_DIAG,
-            0x041E_1544 => UR20_4AI_UI_DIF_16_DIAG,
-            0x0413_15C4 => UR20_4AI_UI_16_HD,
-            0x0414_1544 => UR20_4AI_UI_16_DIAG_HD,
-            0x0411_15C4 => UR20_4AI_UI_12,
-            0x0404_15C5 => UR20_8AI_I_16_HD,
-            0x0405_1545 => UR20_8AI_I_16_DIAG_HD,
-            0x0409_15C5 => UR20_8AI_I_PLC_INT,
-            0x041C_1544 => UR20_4AI_R_HS_16_DIAG,
-            0x041B_356D => UR20_2AI_SG_24_DIAG,
-            0x0418_356D => UR20_3EM_230V_AC,
-
-            0x0502_25E0 => UR20_4AO_UI_16,
-            0x0506_25E0 => UR20_4AO_UI_16_M,
-            0x0501_2560 => UR20_4AO_UI_16_DIAG,
-            0x0505_2560 => UR20_4AO_UI_16_M_DIAG,
-            0x0504_25E0 => UR20_4AO_UI_16_HD,
-            0x0503_2560 => UR20_4AO_UI_16_DIAG_HD,
-
-            0x08C1_3800 => UR20_1CNT_100_1DO,
-            0x08C3_3800 => UR20_2CNT_100,
-            0x08C4_3801 => UR20_1CNT_500,
-            0x0881_28EE => UR20_2FCNT_100,
-
-            0x09C1_7880 => UR20_1SSI,
-            0x0E41_3FED => UR20_1COM_232_485_422,
-            0x0BC1_E800 => UR20_1COM_SAI_PRO,
-            0x0E81_276D => UR20_4COM_IO_LINK,
-
-            0x0406_1544 => UR20_4AI_RTD_DIAG,
-            0x0407_1544 => UR20_4AI_TC_DIAG,
-
-            0x1801_9F43 => UR20_PF_O_1DI_SIL,
-            0x1803_9F43 => UR20_PF_O_2DI_SIL,
-            0x1802_9F43 => UR20_PF_O_2DI_DELAY_SIL,
+    /// Like `try_from_u32` but ignores the revision bits of the ID
+    /// (`MODULE_ID_REVISION_MASK`) if there is no exact match.
+    ///
+    /// The returned flag is `true` if the ID matched exactly.
+    pub fn try_from_u32_tolerant(id: u32) -> Result<(Self, bool)> {
+        if let Ok(t) = Self::try_from_u32(id) {
+            return Ok((t, true));
+        }
+        MODULE_IDS
+            .iter()
+            .find(|(x, _)| x & !MODULE_ID_REVISION_MASK == id & !MODULE_ID_REVISION_MASK)
+            .map(|(_, t)| (t.clone(), false))
+            .ok_or(Error::UnknownModule)
+    }
 
-            _ => {
-                return Err(Error::UnknownModule);
-            }
-        };
-        Ok(t)
+    /// Returns the ID that is reported by the coupler.
+    pub fn to_u32(&self) -> Option<u32> {
+        MODULE_IDS
+            .iter()
+            .find(|(_, t)| t == self)
+            .map(|(id, _)| *id)
+    }
+
+    /// Returns the Weidmüller article number of the module.
+    ///
+    /// Only the standard modules of the u-remote catalogue are covered
+    /// (digital, relay and analog I/O, the serial communication module
+    /// and the power feed modules); all other types return `None`.
+    pub fn order_number(&self) -> Option<&'static str> {
+        ORDER_NUMBERS
+            .iter()
+            .find(|(_, t)| t == self)
+            .map(|(nr, _)| *nr)
     }
 
     /// Returns the number of channels for a specific module type.
@@ -473,6 +785,8 @@ impl ModuleType {
             UR20_2CNT_100           |
             UR20_2FCNT_100          => 2,
 
+            UR20_3EM_230V_AC        => 3,
+
             UR20_4DI_P              |
             UR20_4DI_P_3W           |
             UR20_4DI_P_TS           |
@@ -511,8 +825,7 @@ impl ModuleType {
             UR20_8DO_N              |
             UR20_8AI_I_16_HD        |
             UR20_8AI_I_16_DIAG_HD   |
-            UR20_8AI_I_PLC_INT      |
-            UR20_3EM_230V_AC        => 8,
+            UR20_8AI_I_PLC_INT      => 8,
 
             UR20_16DI_P             |
             UR20_16DI_P_PLC_INT     |
@@ -532,7 +845,11 @@ impl FromStr for ModuleType {
     type Err = Error;
     fn from_str(s: &str) -> result::Result<Self, Self::Err> {
         use crate::ModuleType::*;
-        let t = match &*s.to_uppercase().replace("-","_") {
+        if let Some(t) = module_type_by_order_number(s) {
+            return Ok(t);
+        }
+        let name = normalize_module_name(s);
+        let t = match &*name {
             "UR20_4DI_P"               => UR20_4DI_P,
             "UR20_4DI_P_3W"            => UR20_4DI_P_3W,
             "UR20_8DI_P_2W"            => UR20_8DI_P_2W,
@@ -607,13 +924,229 @@ impl FromStr for ModuleType {
             "UR20_PF_O_2DI_DELAY_SIL"  => UR20_PF_O_2DI_DELAY_SIL,
 
             _ => {
-                return Err(Error::UnknownModule);
+                return Err(match similar_module_type(&name) {
+                    Some(t) => Error::UnknownModuleSuggestion(t),
+                    None    => Error::UnknownModule,
+                });
             }
         };
         Ok(t)
     }
 }
 
+const MODULE_TYPES: &[ModuleType] = &[
+    ModuleType::UR20_4DI_P,
+    ModuleType::UR20_4DI_P_3W,
+    ModuleType::UR20_8DI_P_2W,
+    ModuleType::UR20_8DI_P_3W,
+    ModuleType::UR20_8DI_P_3W_HD,
+    ModuleType::UR20_16DI_P,
+    ModuleType::UR20_16DI_P_PLC_INT,
+    ModuleType::UR20_2DI_P_TS,
+    ModuleType::UR20_4DI_P_TS,
+    ModuleType::UR20_4DI_N,
+    ModuleType::UR20_8DI_N_3W,
+    ModuleType::UR20_16DI_N,
+    ModuleType::UR20_16DI_N_PLC_INT,
+    ModuleType::UR20_4DI_2W_230V_AC,
+    ModuleType::UR20_4DO_P,
+    ModuleType::UR20_4DO_P_2A,
+    ModuleType::UR20_4DO_PN_2A,
+    ModuleType::UR20_8DO_P,
+    ModuleType::UR20_8DO_P_2W_HD,
+    ModuleType::UR20_16DO_P,
+    ModuleType::UR20_16DO_P_PLC_INT,
+    ModuleType::UR20_4DO_N,
+    ModuleType::UR20_4DO_N_2A,
+    ModuleType::UR20_8DO_N,
+    ModuleType::UR20_16DO_N,
+    ModuleType::UR20_16DO_N_PLC_INT,
+    ModuleType::UR20_4RO_SSR_255,
+    ModuleType::UR20_4RO_CO_255,
+    ModuleType::UR20_2PWM_PN_0_5A,
+    ModuleType::UR20_2PWM_PN_2A,
+    ModuleType::UR20_4AI_UI_16,
+    ModuleType::UR20_4AI_UI_16_DIAG,
+    ModuleType::UR20_4AI_UI_DIF_16_DIAG,
+    ModuleType::UR20_4AI_UI_16_HD,
+    ModuleType::UR20_4AI_UI_16_DIAG_HD,
+    ModuleType::UR20_4AI_UI_12,
+    ModuleType::UR20_8AI_I_16_HD,
+    ModuleType::UR20_8AI_I_16_DIAG_HD,
+    ModuleType::UR20_8AI_I_PLC_INT,
+    ModuleType::UR20_4AI_R_HS_16_DIAG,
+    ModuleType::UR20_2AI_SG_24_DIAG,
+    ModuleType::UR20_3EM_230V_AC,
+    ModuleType::UR20_4AO_UI_16,
+    ModuleType::UR20_4AO_UI_16_M,
+    ModuleType::UR20_4AO_UI_16_DIAG,
+    ModuleType::UR20_4AO_UI_16_M_DIAG,
+    ModuleType::UR20_4AO_UI_16_HD,
+    ModuleType::UR20_4AO_UI_16_DIAG_HD,
+    ModuleType::UR20_1CNT_100_1DO,
+    ModuleType::UR20_2CNT_100,
+    ModuleType::UR20_1CNT_500,
+    ModuleType::UR20_2FCNT_100,
+    ModuleType::UR20_1SSI,
+    ModuleType::UR20_1COM_232_485_422,
+    ModuleType::UR20_1COM_SAI_PRO,
+    ModuleType::UR20_4COM_IO_LINK,
+    ModuleType::UR20_4AI_RTD_DIAG,
+    ModuleType::UR20_4AI_TC_DIAG,
+    ModuleType::UR20_PF_I,
+    ModuleType::UR20_PF_O,
+    ModuleType::UR20_PF_O_1DI_SIL,
+    ModuleType::UR20_PF_O_2DI_SIL,
+    ModuleType::UR20_PF_O_2DI_DELAY_SIL,
+];
+
+/// Bits of a module ID that differ between firmware revisions.
+pub const MODULE_ID_REVISION_MASK: u32 = 0x0000_00FF;
+
+/// Module IDs as reported by the coupler.
+#[rustfmt::skip]
+const MODULE_IDS: &[(u32, ModuleType)] = &[
+    (0x0009_1F84, ModuleType::UR20_4DI_P),
+    (0x001B_1F84, ModuleType::UR20_4DI_P_3W),
+    (0x0013_1FC1, ModuleType::UR20_8DI_P_2W),
+    (0x000A_1FC1, ModuleType::UR20_8DI_P_3W),
+    (0x0003_1FC1, ModuleType::UR20_8DI_P_3W_HD),
+    (0x0004_9FC2, ModuleType::UR20_16DI_P),
+    (0x0005_9FC2, ModuleType::UR20_16DI_P_PLC_INT),
+    (0x0F01_4700, ModuleType::UR20_2DI_P_TS),
+    (0x0F02_4700, ModuleType::UR20_4DI_P_TS),
+    (0x0001_1F84, ModuleType::UR20_4DI_N),
+    (0x0002_1FC1, ModuleType::UR20_8DI_N_3W),
+    (0x000C_9FC2, ModuleType::UR20_16DI_N),
+    (0x000D_9FC2, ModuleType::UR20_16DI_N_PLC_INT),
+    (0x0016_9F84, ModuleType::UR20_4DI_2W_230V_AC),
+
+    (0x0101_2FA0, ModuleType::UR20_4DO_P),
+    (0x0105_2FA0, ModuleType::UR20_4DO_P_2A),
+    (0x0115_2FC8, ModuleType::UR20_4DO_PN_2A),
+    (0x0102_2FC8, ModuleType::UR20_8DO_P),
+    (0x0119_2FC8, ModuleType::UR20_8DO_P_2W_HD),
+    (0x0103_AFD0, ModuleType::UR20_16DO_P),
+    (0x0104_AFD0, ModuleType::UR20_16DO_P_PLC_INT),
+    (0x010A_2FA0, ModuleType::UR20_4DO_N),
+    (0x010B_2FA0, ModuleType::UR20_4DO_N_2A),
+    (0x010C_2FC8, ModuleType::UR20_8DO_N),
+    (0x010D_AFD0, ModuleType::UR20_16DO_N),
+    (0x010E_AFD0, ModuleType::UR20_16DO_N_PLC_INT),
+    (0x0107_2FA0, ModuleType::UR20_4RO_SSR_255),
+    (0x0106_2FA0, ModuleType::UR20_4RO_CO_255),
+
+    (0x0908_4880, ModuleType::UR20_2PWM_PN_0_5A),
+    (0x0909_4880, ModuleType::UR20_2PWM_PN_2A),
+
+    (0x0401_15C4, ModuleType::UR20_4AI_UI_16),
+    (0x0402_1544, ModuleType::UR20_4AI_UI_16_DIAG),
+    (0x041E_1544, ModuleType::UR20_4AI_UI_DIF_16_DIAG),
+    (0x0413_15C4, ModuleType::UR20_4AI_UI_16_HD),
+    (0x0414_1544, ModuleType::UR20_4AI_UI_16_DIAG_HD),
+    (0x0411_15C4, ModuleType::UR20_4AI_UI_12),
+    (0x0404_15C5, ModuleType::UR20_8AI_I_16_HD),
+    (0x0405_1545, ModuleType::UR20_8AI_I_16_DIAG_HD),
+    (0x0409_15C5, ModuleType::UR20_8AI_I_PLC_INT),
+    (0x041C_1544, ModuleType::UR20_4AI_R_HS_16_DIAG),
+    (0x041B_356D, ModuleType::UR20_2AI_SG_24_DIAG),
+    (0x0418_356D, ModuleType::UR20_3EM_230V_AC),
+
+    (0x0502_25E0, ModuleType::UR20_4AO_UI_16),
+    (0x0506_25E0, ModuleType::UR20_4AO_UI_16_M),
+    (0x0501_2560, ModuleType::UR20_4AO_UI_16_DIAG),
+    (0x0505_2560, ModuleType::UR20_4AO_UI_16_M_DIAG),
+    (0x0504_25E0, ModuleType::UR20_4AO_UI_16_HD),
+    (0x0503_2560, ModuleType::UR20_4AO_UI_16_DIAG_HD),
+
+    (0x08C1_3800, ModuleType::UR20_1CNT_100_1DO),
+    (0x08C3_3800, ModuleType::UR20_2CNT_100),
+    (0x08C4_3801, ModuleType::UR20_1CNT_500),
+    (0x0881_28EE, ModuleType::UR20_2FCNT_100),
+
+    (0x09C1_7880, ModuleType::UR20_1SSI),
+    (0x0E41_3FED, ModuleType::UR20_1COM_232_485_422),
+    (0x0BC1_E800, ModuleType::UR20_1COM_SAI_PRO),
+    (0x0E81_276D, ModuleType::UR20_4COM_IO_LINK),
+
+    (0x0406_1544, ModuleType::UR20_4AI_RTD_DIAG),
+    (0x0407_1544, ModuleType::UR20_4AI_TC_DIAG),
+
+    (0x1801_9F43, ModuleType::UR20_PF_O_1DI_SIL),
+    (0x1803_9F43, ModuleType::UR20_PF_O_2DI_SIL),
+    (0x1802_9F43, ModuleType::UR20_PF_O_2DI_DELAY_SIL),
+];
+
+/// Weidmüller order numbers of the standard modules
+/// (see `ModuleType::order_number`).
+#[rustfmt::skip]
+const ORDER_NUMBERS: &[(&str, ModuleType)] = &[
+    ("1315170000", ModuleType::UR20_4DI_P),
+    ("1315180000", ModuleType::UR20_4DI_P_3W),
+    ("1315190000", ModuleType::UR20_8DI_P_2W),
+    ("1394400000", ModuleType::UR20_8DI_P_3W),
+    ("1315200000", ModuleType::UR20_16DI_P),
+    ("1315210000", ModuleType::UR20_16DI_P_PLC_INT),
+    ("1315220000", ModuleType::UR20_4DO_P),
+    ("1315230000", ModuleType::UR20_4DO_P_2A),
+    ("1315240000", ModuleType::UR20_8DO_P),
+    ("1315250000", ModuleType::UR20_16DO_P),
+    ("1315270000", ModuleType::UR20_16DO_P_PLC_INT),
+    ("1315540000", ModuleType::UR20_4RO_SSR_255),
+    ("1315550000", ModuleType::UR20_4RO_CO_255),
+    ("1315620000", ModuleType::UR20_4AI_UI_16),
+    ("1315630000", ModuleType::UR20_4AI_UI_16_DIAG),
+    ("1315650000", ModuleType::UR20_4AO_UI_16),
+    ("1315660000", ModuleType::UR20_4AO_UI_16_DIAG),
+    ("1315700000", ModuleType::UR20_4AI_RTD_DIAG),
+    ("1315710000", ModuleType::UR20_4AI_TC_DIAG),
+    ("1315760000", ModuleType::UR20_1COM_232_485_422),
+    ("1334740000", ModuleType::UR20_PF_I),
+    ("1334750000", ModuleType::UR20_PF_O),
+];
+
+/// Maximum edit distance of a name to be considered as a typo.
+const MAX_NAME_DISTANCE: usize = 2;
+
+fn module_type_by_order_number(s: &str) -> Option<ModuleType> {
+    let nr: String = s.chars().filter(|c| !c.is_whitespace()).collect();
+    if nr.is_empty() || !nr.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    ORDER_NUMBERS
+        .iter()
+        .find(|(n, _)| *n == nr)
+        .map(|(_, t)| t.clone())
+}
+
+/// Converts names like `ur20 4di p` or `4DI-P` into `UR20_4DI_P`.
+fn normalize_module_name(s: &str) -> String {
+    let mut name = String::new();
+    for c in s.trim().chars() {
+        if c.is_ascii_alphanumeric() {
+            name.push(c.to_ascii_uppercase());
+        } else if !name.is_empty() && !name.ends_with('_') {
+            name.push('_');
+        }
+    }
+    if name.ends_with('_') {
+        name.pop();
+    }
+    if !name.starts_with("UR20_") {
+        name.insert_str(0, "UR20_");
+    }
+    name
+}
+
+fn similar_module_type(name: &str) -> Option<ModuleType> {
+    MODULE_TYPES
+        .iter()
+        .map(|t| (util::edit_distance(name, &format!("{:?}", t)), t))
+        .filter(|(d, _)| *d <= MAX_NAME_DISTANCE)
+        .min_by_key(|(d, _)| *d)
+        .map(|(_, t)| t.clone())
+}
+
 #[rustfmt::skip]
 impl FromStr for ModuleCategory {
     type Err = Error;
@@ -639,11 +1172,11 @@ impl FromStr for ModuleCategory {
 }
 
 #[rustfmt::skip]
-impl Into<ModuleCategory> for ModuleType {
-    fn into(self) -> ModuleCategory {
+impl From<ModuleType> for ModuleCategory {
+    fn from(val: ModuleType) -> Self {
         use crate::ModuleType::*;
         use crate::ModuleCategory::*;
-        match self {
+        match val {
             UR20_4DI_P              |
             UR20_4DI_P_3W           |
             UR20_8DI_P_2W           |
@@ -719,11 +1252,418 @@ impl Into<ModuleCategory> for ModuleType {
     }
 }
 
+impl Unit {
+    /// Converts a value into another unit of the same quantity.
+    fn convert(self, v: f32, to: Unit) -> Option<f32> {
+        use crate::Unit::*;
+        let kelvin = match self {
+            Celsius => v + 273.15,
+            Fahrenheit => (v - 32.0) * 5.0 / 9.0 + 273.15,
+            Kelvin => v,
+            _ if self == to => return Some(v),
+            _ => return None,
+        };
+        match to {
+            Celsius => Some(kelvin - 273.15),
+            Fahrenheit => Some((kelvin - 273.15) * 9.0 / 5.0 + 32.0),
+            Kelvin => Some(kelvin),
+            _ => None,
+        }
+    }
+}
+
+#[rustfmt::skip]
+impl fmt::Display for Unit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let u = match *self {
+            Unit::MilliAmpere => "mA",
+            Unit::Volt        => "V",
+            Unit::Ohm         => "Ω",
+            Unit::Celsius     => "°C",
+            Unit::Fahrenheit  => "°F",
+            Unit::Kelvin      => "K",
+        };
+        f.write_str(u)
+    }
+}
+
+impl ChannelValue {
+    /// Displays the value including the unit of the given channel.
+    pub fn display<'a>(&'a self, info: &'a ChannelInfo) -> DisplayWithUnit<'a> {
+        DisplayWithUnit {
+            value: self,
+            unit: info.unit.as_ref(),
+        }
+    }
+
+    /// Converts the value of a channel for a channel of a similar module.
+    ///
+    /// This helps to keep application code when a module is replaced
+    /// by one of another type or generation:
+    ///
+    /// - a bit becomes `0.0` or `1.0` if the target channel has a unit,
+    /// - a decimal `0.0` or `1.0` becomes a bit if the target channel has none,
+    /// - a decimal value is converted from the unit of the `source`
+    ///   channel into the unit of the `target` channel.
+    ///
+    /// All other values are returned unchanged.
+    pub fn coerce(&self, source: &ChannelInfo, target: &ChannelInfo) -> Result<ChannelValue> {
+        match (self, source.unit, target.unit) {
+            (ChannelValue::Bit(b), _, Some(_)) => {
+                Ok(ChannelValue::Decimal32(if *b { 1.0 } else { 0.0 }))
+            }
+            (ChannelValue::Decimal32(v), _, None) => {
+                if *v == 0.0 {
+                    Ok(ChannelValue::Bit(false))
+                } else if (*v - 1.0).abs() < f32::EPSILON {
+                    Ok(ChannelValue::Bit(true))
+                } else {
+                    Err(Error::ChannelValue)
+                }
+            }
+            (ChannelValue::Decimal32(v), Some(from), Some(to)) => from
+                .convert(*v, to)
+                .map(ChannelValue::Decimal32)
+                .ok_or(Error::ChannelValue),
+            (v, _, _) => Ok(v.clone()),
+        }
+    }
+
+    /// Returns the state if the value is a `Bit`.
+    pub fn as_bit(&self) -> Option<bool> {
+        match *self {
+            ChannelValue::Bit(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    /// Returns the number if the value is a `Decimal32`.
+    pub fn as_decimal(&self) -> Option<f32> {
+        match *self {
+            ChannelValue::Decimal32(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    /// Returns the data if the value is `Bytes`.
+    pub fn as_bytes(&self) -> Option<&[u8]> {
+        match *self {
+            ChannelValue::Bytes(ref v) => Some(v),
+            _ => None,
+        }
+    }
+}
+
+/// Helper to display a [`ChannelValue`] with its unit.
+///
+/// Use [`ChannelValue::display`] to create it.
+#[derive(Debug)]
+pub struct DisplayWithUnit<'a> {
+    value: &'a ChannelValue,
+    unit: Option<&'a Unit>,
+}
+
+impl<'a> fmt::Display for DisplayWithUnit<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match (self.value, self.unit) {
+            (ChannelValue::Decimal32(v), Some(unit)) => {
+                fmt_decimal(*v, f)?;
+                write!(f, " {}", unit)
+            }
+            (v, _) => fmt::Display::fmt(v, f),
+        }
+    }
+}
+
+fn fmt_decimal(v: f32, f: &mut fmt::Formatter) -> fmt::Result {
+    match f.precision() {
+        Some(p) => write!(f, "{:.*}", p, v),
+        None => write!(f, "{:?}", v),
+    }
+}
+
+fn fmt_byte_count(cnt: usize, f: &mut fmt::Formatter) -> fmt::Result {
+    if cnt == 1 {
+        write!(f, "1 byte")
+    } else {
+        write!(f, "{} bytes", cnt)
+    }
+}
+
+impl ChannelValue {
+    /// Returns the fields of a structured value as plain values.
+    ///
+    /// - `FcntIn`: `frequency` (Hz), `period` (s), `count`, `active`
+    /// - `FcntOut`: `duration` (s), `start`, `stop`
+    /// - `SsiIn`: `position`, `error`, `supply_error`
+    /// - `PwmOut`: `duty_cycle` (`0.0 ... 1.0`), `frequency` (Hz)
+    /// - `EmIn`: `voltage` (V), `current` (A), `power` (W), `energy` (Wh),
+    ///   `frequency` (Hz), `phase_failure`, `overcurrent`
+    /// - `IoLinkIn`: `connected`, `valid`, `diagnosis`
+    /// - `TsIn`: `state`, `edges` (number of edges)
+    ///
+    /// All other values have no fields.
+    pub fn fields(&self) -> Vec<(&'static str, ChannelValue)> {
+        use crate::ChannelValue::*;
+        let opt = |v: Option<f32>| v.map(Decimal32).unwrap_or(ChannelValue::None);
+        match *self {
+            FcntIn(ref v) => vec![
+                ("frequency", opt(v.hertz())),
+                ("period", opt(v.duration.map(|d| d.as_secs_f32()))),
+                ("count", Decimal32(v.count as f32)),
+                ("active", Bit(v.active)),
+            ],
+            FcntOut(ref v) => vec![
+                ("duration", Decimal32(v.duration.as_secs_f32())),
+                ("start", Bit(v.command == Some(FcntCommand::Start))),
+                ("stop", Bit(v.command == Some(FcntCommand::Stop))),
+            ],
+            SsiIn(ref v) => vec![
+                ("position", Decimal32(v.position as f32)),
+                ("error", Bit(v.error)),
+                ("supply_error", Bit(v.supply_error)),
+            ],
+            PwmOut(ref v) => vec![
+                ("duty_cycle", Decimal32(v.duty_cycle)),
+                ("frequency", opt(v.frequency.map(f32::from))),
+            ],
+            EmIn(ref v) => vec![
+                ("voltage", Decimal32(v.voltage)),
+                ("current", Decimal32(v.current)),
+                ("power", Decimal32(v.power)),
+                ("energy", Decimal32(v.energy as f32)),
+                ("frequency", Decimal32(v.frequency)),
+                ("phase_failure", Bit(v.phase_failure)),
+                ("overcurrent", Bit(v.overcurrent)),
+            ],
+            IoLinkIn(ref v) => vec![
+                ("connected", Bit(v.connected)),
+                ("valid", Bit(v.valid)),
+                ("diagnosis", Bit(v.diagnosis)),
+            ],
+            TsIn(ref v) => vec![
+                ("state", Bit(v.state)),
+                ("edges", Decimal32(v.edges.len() as f32)),
+            ],
+            _ => vec![],
+        }
+    }
+
+    /// Returns a single field of a structured value.
+    pub fn field(&self, name: &str) -> Option<ChannelValue> {
+        self.fields()
+            .into_iter()
+            .find(|(n, _)| *n == name)
+            .map(|(_, v)| v)
+    }
+
+    /// Sets a single field of a structured output value.
+    ///
+    /// Setting `start` or `stop` to `false` clears the command
+    /// if it is the current one.
+    pub fn set_field(&mut self, name: &str, value: ChannelValue) -> Result<()> {
+        use crate::ChannelValue::*;
+        match (self, name, value) {
+            (FcntOut(ref mut v), "duration", Decimal32(secs)) => {
+                if !secs.is_finite() || secs < 0.0 {
+                    return Err(Error::ChannelValue);
+                }
+                v.duration = std::time::Duration::from_secs_f32(secs);
+            }
+            (FcntOut(ref mut v), cmd @ ("start" | "stop"), Bit(on)) => {
+                let c = if cmd == "start" {
+                    FcntCommand::Start
+                } else {
+                    FcntCommand::Stop
+                };
+                if on {
+                    v.command = Some(c);
+                } else if v.command == Some(c) {
+                    v.command = Option::None;
+                }
+            }
+            (FcntOut(_), "duration" | "start" | "stop", _) => return Err(Error::ChannelValue),
+            (PwmOut(ref mut v), "duty_cycle", Decimal32(d)) => {
+                if !(0.0..=1.0).contains(&d) {
+                    return Err(Error::ChannelValue);
+                }
+                v.duty_cycle = d;
+            }
+            (PwmOut(ref mut v), "frequency", Decimal32(f)) => {
+                if !(1.0..=f32::from(ur20_2pwm_pn::MAX_FREQUENCY)).contains(&f) {
+                    return Err(Error::ChannelValue);
+                }
+                v.frequency = Some(f.round() as u16);
+            }
+            (PwmOut(ref mut v), "frequency", None) => {
+                v.frequency = Option::None;
+            }
+            (PwmOut(_), "duty_cycle" | "frequency", _) => return Err(Error::ChannelValue),
+            _ => return Err(Error::Address),
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for ChannelValue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ChannelValue::Bit(v) => write!(f, "{}", v),
+            ChannelValue::Decimal32(v) => fmt_decimal(v, f),
+            ChannelValue::ComRsIn(ref v) => fmt_byte_count(v.data.len(), f),
+            ChannelValue::ComRsOut(ref v) => fmt_byte_count(v.data.len(), f),
+            ChannelValue::FcntIn(ref v) => match v.hertz() {
+                Some(hz) => {
+                    fmt_decimal(hz, f)?;
+                    write!(f, " Hz")
+                }
+                None => write!(f, "no signal"),
+            },
+            ChannelValue::FcntOut(ref v) => match v.command {
+                Some(ref cmd) => write!(f, "{:?} ({:?})", cmd, v.duration),
+                None => write!(f, "{:?}", v.duration),
+            },
+            ChannelValue::SsiIn(ref v) => {
+                write!(f, "{}", v.position)?;
+                if v.error || v.supply_error {
+                    write!(f, " (error)")?;
+                }
+                Ok(())
+            }
+            ChannelValue::PwmOut(ref v) => {
+                fmt_decimal(v.duty_cycle * 100.0, f)?;
+                write!(f, " %")?;
+                if let Some(hz) = v.frequency {
+                    write!(f, " ({} Hz)", hz)?;
+                }
+                Ok(())
+            }
+            ChannelValue::EmIn(ref v) => {
+                fmt_decimal(v.voltage, f)?;
+                write!(f, " V, ")?;
+                fmt_decimal(v.current, f)?;
+                write!(f, " A, ")?;
+                fmt_decimal(v.power, f)?;
+                write!(f, " W")
+            }
+            ChannelValue::IoLinkIn(ref v) => {
+                fmt_byte_count(v.data.len(), f)?;
+                if !v.connected {
+                    write!(f, " (disconnected)")?;
+                } else if v.diagnosis {
+                    write!(f, " (diagnosis)")?;
+                }
+                Ok(())
+            }
+            ChannelValue::TsIn(ref v) => {
+                write!(f, "{}", v.state)?;
+                match v.edges.len() {
+                    0 => Ok(()),
+                    1 => write!(f, " (1 edge)"),
+                    n => write!(f, " ({} edges)", n),
+                }
+            }
+            ChannelValue::Bytes(ref v) => fmt_byte_count(v.len(), f),
+            ChannelValue::Disabled => write!(f, "disabled"),
+            ChannelValue::None => write!(f, "none"),
+        }
+    }
+}
+
+impl From<bool> for ChannelValue {
+    fn from(v: bool) -> Self {
+        ChannelValue::Bit(v)
+    }
+}
+
+impl From<f32> for ChannelValue {
+    fn from(v: f32) -> Self {
+        ChannelValue::Decimal32(v)
+    }
+}
+
+impl From<&[u8]> for ChannelValue {
+    fn from(v: &[u8]) -> Self {
+        ChannelValue::Bytes(v.to_vec())
+    }
+}
+
+impl TryFrom<ChannelValue> for bool {
+    type Error = Error;
+    fn try_from(v: ChannelValue) -> Result<Self> {
+        match v {
+            ChannelValue::Bit(v) => Ok(v),
+            _ => Err(Error::ChannelValue),
+        }
+    }
+}
+
+impl TryFrom<ChannelValue> for f32 {
+    type Error = Error;
+    fn try_from(v: ChannelValue) -> Result<Self> {
+        match v {
+            ChannelValue::Decimal32(v) => Ok(v),
+            _ => Err(Error::ChannelValue),
+        }
+    }
+}
+
+impl TryFrom<ChannelValue> for Vec<u8> {
+    type Error = Error;
+    fn try_from(v: ChannelValue) -> Result<Self> {
+        match v {
+            ChannelValue::Bytes(v) => Ok(v),
+            _ => Err(Error::ChannelValue),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
 
     use super::*;
 
+    #[test]
+    fn convert_channel_values() {
+        assert_eq!(ChannelValue::from(true), ChannelValue::Bit(true));
+        assert_eq!(ChannelValue::from(2.5), ChannelValue::Decimal32(2.5));
+        assert_eq!(
+            ChannelValue::from(&[1, 2][..]),
+            ChannelValue::Bytes(vec![1, 2])
+        );
+        assert_eq!(bool::try_from(ChannelValue::Bit(true)), Ok(true));
+        assert_eq!(f32::try_from(ChannelValue::Decimal32(2.5)), Ok(2.5));
+        assert_eq!(
+            Vec::<u8>::try_from(ChannelValue::Bytes(vec![3])),
+            Ok(vec![3])
+        );
+        assert_eq!(
+            bool::try_from(ChannelValue::Decimal32(1.0)),
+            Err(Error::ChannelValue)
+        );
+        assert_eq!(
+            f32::try_from(ChannelValue::Disabled),
+            Err(Error::ChannelValue)
+        );
+        assert_eq!(
+            Vec::<u8>::try_from(ChannelValue::None),
+            Err(Error::ChannelValue)
+        );
+    }
+
+    #[test]
+    fn access_channel_values() {
+        assert_eq!(ChannelValue::Bit(true).as_bit(), Some(true));
+        assert_eq!(ChannelValue::Decimal32(1.0).as_bit(), Option::None);
+        assert_eq!(ChannelValue::Decimal32(2.5).as_decimal(), Some(2.5));
+        assert_eq!(ChannelValue::Bit(false).as_decimal(), Option::None);
+        assert_eq!(
+            ChannelValue::Bytes(vec![1, 2]).as_bytes(),
+            Some(&[1, 2][..])
+        );
+        assert_eq!(ChannelValue::Disabled.as_bytes(), Option::None);
+    }
+
     #[test]
     fn module_by_u32_id() {
         assert_eq!(
@@ -736,6 +1676,35 @@ mod tests {
         );
     }
 
+    #[test]
+    fn module_by_u32_id_with_other_revision() {
+        assert!(ModuleType::try_from_u32(0x0505_2561).is_err());
+        assert_eq!(
+            ModuleType::try_from_u32_tolerant(0x0505_2560).unwrap(),
+            (ModuleType::UR20_4AO_UI_16_M_DIAG, true)
+        );
+        assert_eq!(
+            ModuleType::try_from_u32_tolerant(0x0505_2561).unwrap(),
+            (ModuleType::UR20_4AO_UI_16_M_DIAG, false)
+        );
+        assert_eq!(
+            ModuleType::try_from_u32_tolerant(0x0505_2660)
+                .err()
+                .unwrap(),
+            Error::UnknownModule
+        );
+    }
+
+    #[test]
+    fn module_to_u32_id() {
+        assert_eq!(ModuleType::UR20_4DI_P.to_u32(), Some(0x0009_1F84));
+        assert_eq!(ModuleType::UR20_PF_I.to_u32(), None);
+        for (id, t) in MODULE_IDS {
+            assert_eq!(ModuleType::try_from_u32(*id).unwrap(), *t);
+            assert_eq!(t.to_u32(), Some(*id));
+        }
+    }
+
     #[test]
     fn module_by_str_id() {
         assert_eq!(
@@ -756,6 +1725,250 @@ mod tests {
         );
     }
 
+    #[test]
+    fn module_by_alternative_str_id() {
+        assert_eq!(
+            "ur20 4di p".parse::<ModuleType>().unwrap(),
+            ModuleType::UR20_4DI_P
+        );
+        assert_eq!(
+            "Ur20-4Di_p".parse::<ModuleType>().unwrap(),
+            ModuleType::UR20_4DI_P
+        );
+        assert_eq!(
+            " 4DI-P ".parse::<ModuleType>().unwrap(),
+            ModuleType::UR20_4DI_P
+        );
+        assert_eq!(
+            "UR20-2PWM-PN-0.5A".parse::<ModuleType>().unwrap(),
+            ModuleType::UR20_2PWM_PN_0_5A
+        );
+        assert_eq!(
+            "UR20 -- 16DO-P-PLC-INT".parse::<ModuleType>().unwrap(),
+            ModuleType::UR20_16DO_P_PLC_INT
+        );
+    }
+
+    #[test]
+    fn module_by_order_number() {
+        assert_eq!(
+            "1315170000".parse::<ModuleType>().unwrap(),
+            ModuleType::UR20_4DI_P
+        );
+        assert_eq!(
+            "1315760000".parse::<ModuleType>().unwrap(),
+            ModuleType::UR20_1COM_232_485_422
+        );
+        assert_eq!(
+            "1234567890".parse::<ModuleType>().err().unwrap(),
+            Error::UnknownModule
+        );
+    }
+
+    #[test]
+    fn order_numbers() {
+        for (nr, t) in ORDER_NUMBERS {
+            assert_eq!(t.order_number(), Some(*nr));
+            assert_eq!(nr.parse::<ModuleType>().unwrap(), *t);
+        }
+        let mut numbers: Vec<_> = ORDER_NUMBERS.iter().map(|(nr, _)| nr).collect();
+        numbers.sort();
+        numbers.dedup();
+        assert_eq!(numbers.len(), ORDER_NUMBERS.len());
+        assert_eq!(ModuleType::UR20_1CNT_500.order_number(), None);
+    }
+
+    #[test]
+    fn module_by_str_id_with_typo() {
+        assert_eq!(
+            "UR20-4DI-PP".parse::<ModuleType>().err().unwrap(),
+            Error::UnknownModuleSuggestion(ModuleType::UR20_4DI_P)
+        );
+        assert_eq!(
+            "UR20-1COM-232-458-422".parse::<ModuleType>().err().unwrap(),
+            Error::UnknownModuleSuggestion(ModuleType::UR20_1COM_232_485_422)
+        );
+        assert_eq!(
+            "UR20-4AI-RTD".parse::<ModuleType>().err().unwrap(),
+            Error::UnknownModule
+        );
+    }
+
+    #[test]
+    fn display_channel_values() {
+        assert_eq!(ChannelValue::Bit(true).to_string(), "true");
+        assert_eq!(ChannelValue::Decimal32(20.0).to_string(), "20.0");
+        assert_eq!(format!("{:.2}", ChannelValue::Decimal32(3.3)), "3.30");
+        assert_eq!(ChannelValue::Bytes(vec![0; 5]).to_string(), "5 bytes");
+        assert_eq!(ChannelValue::Bytes(vec![0]).to_string(), "1 byte");
+        let edge = ur20_di_ts::Edge {
+            sequence: 0,
+            rising: true,
+            timestamp: std::time::Duration::from_millis(1),
+        };
+        let ts = TsIn {
+            state: true,
+            edges: vec![edge; 2],
+        };
+        assert_eq!(ChannelValue::TsIn(ts).to_string(), "true (2 edges)");
+        assert_eq!(ChannelValue::Disabled.to_string(), "disabled");
+        assert_eq!(ChannelValue::None.to_string(), "none");
+    }
+
+    #[test]
+    fn display_channel_values_with_unit() {
+        let info = ChannelInfo {
+            unit: Some(Unit::MilliAmpere),
+            ..Default::default()
+        };
+        assert_eq!(
+            ChannelValue::Decimal32(20.0).display(&info).to_string(),
+            "20.0 mA"
+        );
+        assert_eq!(
+            format!("{:.1}", ChannelValue::Decimal32(-3.25).display(&info)),
+            "-3.2 mA"
+        );
+        assert_eq!(ChannelValue::Bit(false).display(&info).to_string(), "false");
+        assert_eq!(
+            ChannelValue::Disabled.display(&info).to_string(),
+            "disabled"
+        );
+        let info = ChannelInfo::default();
+        assert_eq!(
+            ChannelValue::Decimal32(1.5).display(&info).to_string(),
+            "1.5"
+        );
+        let info = ChannelInfo {
+            unit: Some(Unit::Celsius),
+            ..Default::default()
+        };
+        assert_eq!(
+            ChannelValue::Decimal32(21.5).display(&info).to_string(),
+            "21.5 °C"
+        );
+    }
+
+    #[test]
+    fn fields_of_structured_values() {
+        use crate::ur20_2fcnt_100::{Command, ProcessOutput};
+        use std::time::Duration;
+        let ssi = ChannelValue::SsiIn(ur20_1ssi::ProcessInput {
+            position: 1234,
+            error: false,
+            supply_error: true,
+        });
+        assert_eq!(ssi.fields().len(), 3);
+        assert_eq!(ssi.field("position"), Some(ChannelValue::Decimal32(1234.0)));
+        assert_eq!(ssi.field("supply_error"), Some(ChannelValue::Bit(true)));
+        assert!(ChannelValue::Bit(true).fields().is_empty());
+
+        let mut out = ChannelValue::FcntOut(ProcessOutput::default());
+        out.set_field("stop", ChannelValue::Bit(true)).unwrap();
+        out.set_field("start", ChannelValue::Bit(false)).unwrap();
+        assert_eq!(out.field("stop"), Some(ChannelValue::Bit(true)));
+        out.set_field("duration", ChannelValue::Decimal32(2.0))
+            .unwrap();
+        assert_eq!(
+            out,
+            ChannelValue::FcntOut(ProcessOutput {
+                duration: Duration::from_secs(2),
+                command: Some(Command::Stop),
+            })
+        );
+        out.set_field("stop", ChannelValue::Bit(false)).unwrap();
+        assert_eq!(out.field("stop"), Some(ChannelValue::Bit(false)));
+        assert!(out
+            .set_field("duration", ChannelValue::Decimal32(-1.0))
+            .is_err());
+        assert!(ssi
+            .clone()
+            .set_field("error", ChannelValue::Bit(true))
+            .is_err());
+
+        let mut pwm = ChannelValue::PwmOut(ur20_2pwm_pn::ProcessOutput::default());
+        assert_eq!(pwm.field("frequency"), Some(ChannelValue::None));
+        pwm.set_field("duty_cycle", ChannelValue::Decimal32(0.25))
+            .unwrap();
+        pwm.set_field("frequency", ChannelValue::Decimal32(50.0))
+            .unwrap();
+        assert_eq!(pwm.to_string(), "25.0 % (50 Hz)");
+        pwm.set_field("frequency", ChannelValue::None).unwrap();
+        assert_eq!(pwm.to_string(), "25.0 %");
+        assert!(pwm
+            .set_field("duty_cycle", ChannelValue::Decimal32(1.5))
+            .is_err());
+        assert!(pwm
+            .set_field("frequency", ChannelValue::Decimal32(0.0))
+            .is_err());
+    }
+
+    #[test]
+    fn coerce_channel_values() {
+        let digital = ChannelInfo::default();
+        let info = |unit| ChannelInfo {
+            unit: Some(unit),
+            ..Default::default()
+        };
+        assert_eq!(
+            ChannelValue::Bit(true)
+                .coerce(&digital, &info(Unit::Volt))
+                .unwrap(),
+            ChannelValue::Decimal32(1.0)
+        );
+        assert_eq!(
+            ChannelValue::Decimal32(0.0)
+                .coerce(&info(Unit::Volt), &digital)
+                .unwrap(),
+            ChannelValue::Bit(false)
+        );
+        assert!(ChannelValue::Decimal32(0.5)
+            .coerce(&info(Unit::Volt), &digital)
+            .is_err());
+        assert_eq!(
+            ChannelValue::Decimal32(100.0)
+                .coerce(&info(Unit::Celsius), &info(Unit::Fahrenheit))
+                .unwrap(),
+            ChannelValue::Decimal32(212.0)
+        );
+        assert_eq!(
+            ChannelValue::Decimal32(5.0)
+                .coerce(&info(Unit::Volt), &info(Unit::Volt))
+                .unwrap(),
+            ChannelValue::Decimal32(5.0)
+        );
+        assert!(ChannelValue::Decimal32(5.0)
+            .coerce(&info(Unit::Volt), &info(Unit::MilliAmpere))
+            .is_err());
+        assert_eq!(
+            ChannelValue::Bit(true).coerce(&digital, &digital).unwrap(),
+            ChannelValue::Bit(true)
+        );
+        assert_eq!(
+            ChannelValue::Disabled
+                .coerce(&info(Unit::Volt), &digital)
+                .unwrap(),
+            ChannelValue::Disabled
+        );
+    }
+
+    #[test]
+    fn units_of_ranges() {
+        assert_eq!(AnalogUIRange::mA4To20.unit(), Some(Unit::MilliAmpere));
+        assert_eq!(AnalogUIRange::V2To10.unit(), Some(Unit::Volt));
+        assert_eq!(AnalogUIRange::Disabled.unit(), None);
+        assert_eq!(AnalogIRange::mA0To20.unit(), Some(Unit::MilliAmpere));
+        assert_eq!(
+            RtdRange::PT100.unit(&TemperatureUnit::Kelvin),
+            Some(Unit::Kelvin)
+        );
+        assert_eq!(
+            RtdRange::R4000.unit(&TemperatureUnit::Kelvin),
+            Some(Unit::Ohm)
+        );
+        assert_eq!(RtdRange::Disabled.unit(&TemperatureUnit::Celsius), None);
+    }
+
     #[test]
     fn category_by_str_id() {
         assert_eq!(
@@ -0,0 +1,209 @@
+//! Detection of suspicious station configurations.
+//!
+//! The checks catch common configuration mistakes
+//! before the parameters are downloaded to the modules.
+//! A lint is not necessarily an error.
+
+use super::*;
+use crate::{
+    station::StationDescription,
+    ur20_1com_232_485_422::{DataBits, FlowControl},
+    ur20_fbc_mod_tcp::{module_from_parameter_data, FromModbusParameterData},
+};
+use std::collections::BTreeMap;
+
+/// A suspicious setting.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Lint {
+    /// The parameters can't be decoded.
+    InvalidParameters(usize),
+    /// Channel diagnostics are enabled,
+    /// but the limit values are not monitored.
+    DiagnosticsWithoutLimitMonitoring(Address),
+    /// The substitute value is outside of the output range.
+    SubstituteValueOutOfRange { address: Address, value: f32 },
+    /// Flow control is enabled for seven data bits.
+    FlowControlWithSevenDataBits(Address),
+    /// Several channels have the same label.
+    DuplicateLabel {
+        label: String,
+        addresses: Vec<Address>,
+    },
+}
+
+/// Checks a station description.
+///
+/// The lints are ordered by the module number.
+/// Duplicate labels (including virtual channels) follow at the end.
+pub fn lint<'a>(station: &'a StationDescription) -> Vec<Lint> {
+    let mut lints = vec![];
+    for (nr, m) in station.modules.iter().enumerate() {
+        if module_from_parameter_data(&m.module_type, &m.params).is_err() {
+            lints.push(Lint::InvalidParameters(nr));
+            continue;
+        }
+        lint_module(nr, &m.module_type, &m.params, &mut lints);
+    }
+    let mut labels: BTreeMap<&str, Vec<Address>> = BTreeMap::new();
+    let mut add_label = |label: &'a Option<String>, module, channel| {
+        if let Some(label) = label {
+            labels
+                .entry(label)
+                .or_default()
+                .push(Address { module, channel });
+        }
+    };
+    for (module, m) in station.modules.iter().enumerate() {
+        for (channel, c) in m.channels.iter().enumerate() {
+            add_label(&c.label, module, channel);
+        }
+    }
+    for (channel, v) in station.virtual_channels.iter().enumerate() {
+        add_label(&v.channel.label, station.modules.len(), channel);
+    }
+    for (label, addresses) in labels {
+        if addresses.len() > 1 {
+            lints.push(Lint::DuplicateLabel {
+                label: label.into(),
+                addresses,
+            });
+        }
+    }
+    lints
+}
+
+fn lint_module(module: usize, t: &ModuleType, params: &[u16], lints: &mut Vec<Lint>) {
+    let addr = |channel| Address { module, channel };
+    if ur20_ao_ui_generic::MODULE_TYPES.contains(t) {
+        if let Ok(m) = ur20_ao_ui_generic::Mod::from_modbus_parameter_data(t.clone(), params) {
+            for (i, p) in m.ch_params.iter().enumerate() {
+                let value = p.substitute_value;
+                if output_range(&p.output_range)
+                    .is_some_and(|(min, max)| value < min || value > max)
+                {
+                    lints.push(Lint::SubstituteValueOutOfRange {
+                        address: addr(i),
+                        value,
+                    });
+                }
+            }
+        }
+        return;
+    }
+    match *t {
+        ModuleType::UR20_4AI_RTD_DIAG => {
+            if let Ok(m) = ur20_4ai_rtd_diag::Mod::from_modbus_parameter_data(params) {
+                for (i, p) in m.ch_params.iter().enumerate() {
+                    if p.channel_diagnostics && !p.limit_value_monitoring {
+                        lints.push(Lint::DiagnosticsWithoutLimitMonitoring(addr(i)));
+                    }
+                }
+            }
+        }
+        ModuleType::UR20_4AI_TC_DIAG => {
+            if let Ok(m) = ur20_4ai_tc_diag::Mod::from_modbus_parameter_data(params) {
+                for (i, p) in m.ch_params.iter().enumerate() {
+                    if p.channel_diagnostics && !p.limit_value_monitoring {
+                        lints.push(Lint::DiagnosticsWithoutLimitMonitoring(addr(i)));
+                    }
+                }
+            }
+        }
+        ModuleType::UR20_1COM_232_485_422 => {
+            if let Ok(m) = ur20_1com_232_485_422::Mod::from_modbus_parameter_data(params) {
+                for (i, p) in m.ch_params.iter().enumerate() {
+                    if p.flow_control != FlowControl::None && p.data_bits == DataBits::SevenBits {
+                        lints.push(Lint::FlowControlWithSevenDataBits(addr(i)));
+                    }
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Smallest and greatest value of an output range (mA or V).
+fn output_range(range: &AnalogUIRange) -> Option<(f32, f32)> {
+    use crate::AnalogUIRange::*;
+    match *range {
+        mA0To20 => Some((0.0, 20.0)),
+        mA4To20 => Some((4.0, 20.0)),
+        V0To10 => Some((0.0, 10.0)),
+        VMinus10To10 => Some((-10.0, 10.0)),
+        V0To5 => Some((0.0, 5.0)),
+        VMinus5To5 => Some((-5.0, 5.0)),
+        V1To5 => Some((1.0, 5.0)),
+        V2To10 => Some((2.0, 10.0)),
+        Disabled => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::{
+        station::{ChannelDescription, ModuleDescription},
+        ur20_fbc_mod_tcp::ToModbusParameterData,
+    };
+
+    fn labeled(label: &str) -> ChannelDescription {
+        ChannelDescription {
+            label: Some(label.into()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn lint_station() {
+        let mut ao = ur20_ao_ui_generic::Mod::new(ModuleType::UR20_4AO_UI_16).unwrap();
+        ao.ch_params[0].output_range = AnalogUIRange::mA4To20;
+        ao.ch_params[0].substitute_value = 2.0;
+        ao.ch_params[1].output_range = AnalogUIRange::V0To10;
+        ao.ch_params[1].substitute_value = 10.0;
+        let mut rtd = ur20_4ai_rtd_diag::Mod::default();
+        rtd.ch_params[2].channel_diagnostics = true;
+        rtd.ch_params[3].channel_diagnostics = true;
+        rtd.ch_params[3].limit_value_monitoring = true;
+        let mut com = ur20_1com_232_485_422::Mod::default();
+        com.ch_params[0].data_bits = DataBits::SevenBits;
+        com.ch_params[0].flow_control = FlowControl::XON_XOFF;
+
+        let mut di = ModuleDescription::new(ModuleType::UR20_4DI_P, vec![0; 4]);
+        di.channels = vec![labeled("pump"), labeled("valve"), labeled("pump")];
+        let station = StationDescription {
+            modules: vec![
+                di,
+                ModuleDescription::new(ModuleType::UR20_4AO_UI_16, ao.to_modbus_parameter_data()),
+                ModuleDescription::new(
+                    ModuleType::UR20_4AI_RTD_DIAG,
+                    rtd.to_modbus_parameter_data(),
+                ),
+                ModuleDescription::new(
+                    ModuleType::UR20_1COM_232_485_422,
+                    com.to_modbus_parameter_data(),
+                ),
+                ModuleDescription::new(ModuleType::UR20_4DO_P, vec![9; 4]),
+            ],
+            virtual_channels: vec![],
+        };
+        let addr = |module, channel| Address { module, channel };
+        assert_eq!(
+            lint(&station),
+            vec![
+                Lint::SubstituteValueOutOfRange {
+                    address: addr(1, 0),
+                    value: 2.0
+                },
+                Lint::DiagnosticsWithoutLimitMonitoring(addr(2, 2)),
+                Lint::FlowControlWithSevenDataBits(addr(3, 0)),
+                Lint::InvalidParameters(4),
+                Lint::DuplicateLabel {
+                    label: "pump".into(),
+                    addresses: vec![addr(0, 0), addr(0, 2)],
+                },
+            ]
+        );
+        assert!(lint(&StationDescription::default()).is_empty());
+    }
+}
@@ -0,0 +1,19 @@
+/// Defines the module types that share a generic implementation
+/// together with their number of parameter registers.
+///
+/// It generates the public `MODULE_TYPES` list and a private
+/// `param_register_count` function.
+macro_rules! make_variants {
+    ($($t:ident => $param_cnt:expr,)*) => {
+        /// All module types that are covered by this implementation.
+        pub const MODULE_TYPES: &[ModuleType] = &[$(ModuleType::$t,)*];
+
+        /// Number of parameter registers of a module type.
+        fn param_register_count(module_type: &ModuleType) -> Option<usize> {
+            match *module_type {
+                $(ModuleType::$t => Some($param_cnt),)*
+                _ => None,
+            }
+        }
+    };
+}
@@ -0,0 +1,347 @@
+//! Modbus RTU master on top of a serial communication module.
+
+use super::*;
+pub use crate::util::crc16;
+use crate::{ur20_1com_232_485_422::SystemClock, ur20_fbc_mod_tcp::Coupler};
+use std::time::Duration;
+
+const READ_HOLDING_REGISTERS: u8 = 0x03;
+const READ_INPUT_REGISTERS: u8 = 0x04;
+const WRITE_SINGLE_REGISTER: u8 = 0x06;
+const WRITE_MULTIPLE_REGISTERS: u8 = 0x10;
+
+/// A Modbus RTU master that talks over a communication module.
+///
+/// All calls block and run coupler cycles through the `cycle` callback
+/// (e.g. by calling `next()`) until the response was received.
+#[derive(Debug, Clone)]
+pub struct RtuMaster {
+    /// Module position of the communication module.
+    pub module_nr: usize,
+    /// Maximum time to wait for a response.
+    pub timeout: Duration,
+    /// Number of cycles to wait before a request is sent
+    /// (approximates the inter-frame gap).
+    pub gap_cycles: usize,
+}
+
+impl RtuMaster {
+    pub fn new(module_nr: usize) -> Self {
+        RtuMaster {
+            module_nr,
+            timeout: Duration::from_secs(1),
+            gap_cycles: 1,
+        }
+    }
+
+    pub fn read_holding_registers<F>(
+        &self,
+        c: &mut Coupler,
+        slave: u8,
+        addr: u16,
+        count: u16,
+        cycle: F,
+    ) -> Result<Vec<u16>>
+    where
+        F: FnMut(&mut Coupler) -> Result<()>,
+    {
+        self.read_registers(c, slave, READ_HOLDING_REGISTERS, addr, count, cycle)
+    }
+
+    pub fn read_input_registers<F>(
+        &self,
+        c: &mut Coupler,
+        slave: u8,
+        addr: u16,
+        count: u16,
+        cycle: F,
+    ) -> Result<Vec<u16>>
+    where
+        F: FnMut(&mut Coupler) -> Result<()>,
+    {
+        self.read_registers(c, slave, READ_INPUT_REGISTERS, addr, count, cycle)
+    }
+
+    pub fn write_single_register<F>(
+        &self,
+        c: &mut Coupler,
+        slave: u8,
+        addr: u16,
+        value: u16,
+        cycle: F,
+    ) -> Result<()>
+    where
+        F: FnMut(&mut Coupler) -> Result<()>,
+    {
+        let mut pdu = vec![WRITE_SINGLE_REGISTER];
+        pdu.extend_from_slice(&addr.to_be_bytes());
+        pdu.extend_from_slice(&value.to_be_bytes());
+        let res = self.transaction(c, slave, &pdu, cycle)?;
+        if res != pdu[1..] {
+            return Err(Error::DataLength);
+        }
+        Ok(())
+    }
+
+    pub fn write_multiple_registers<F>(
+        &self,
+        c: &mut Coupler,
+        slave: u8,
+        addr: u16,
+        values: &[u16],
+        cycle: F,
+    ) -> Result<()>
+    where
+        F: FnMut(&mut Coupler) -> Result<()>,
+    {
+        if values.is_empty() || values.len() > 123 {
+            return Err(Error::DataLength);
+        }
+        let mut pdu = vec![WRITE_MULTIPLE_REGISTERS];
+        pdu.extend_from_slice(&addr.to_be_bytes());
+        pdu.extend_from_slice(&(values.len() as u16).to_be_bytes());
+        pdu.push((values.len() * 2) as u8);
+        for v in values {
+            pdu.extend_from_slice(&v.to_be_bytes());
+        }
+        let res = self.transaction(c, slave, &pdu, cycle)?;
+        if res != pdu[1..5] {
+            return Err(Error::DataLength);
+        }
+        Ok(())
+    }
+
+    fn read_registers<F>(
+        &self,
+        c: &mut Coupler,
+        slave: u8,
+        function: u8,
+        addr: u16,
+        count: u16,
+        cycle: F,
+    ) -> Result<Vec<u16>>
+    where
+        F: FnMut(&mut Coupler) -> Result<()>,
+    {
+        if count == 0 || count > 125 {
+            return Err(Error::DataLength);
+        }
+        let mut pdu = vec![function];
+        pdu.extend_from_slice(&addr.to_be_bytes());
+        pdu.extend_from_slice(&count.to_be_bytes());
+        let res = self.transaction(c, slave, &pdu, cycle)?;
+        if res.len() != 1 + count as usize * 2 || res[0] as usize != count as usize * 2 {
+            return Err(Error::DataLength);
+        }
+        Ok(res[1..]
+            .chunks(2)
+            .map(|x| u16::from_be_bytes([x[0], x[1]]))
+            .collect())
+    }
+
+    /// Sends a request and returns the response data
+    /// without slave address, function code and CRC.
+    fn transaction<F>(
+        &self,
+        c: &mut Coupler,
+        slave: u8,
+        pdu: &[u8],
+        mut cycle: F,
+    ) -> Result<Vec<u8>>
+    where
+        F: FnMut(&mut Coupler) -> Result<()>,
+    {
+        for _ in 0..self.gap_cycles {
+            cycle(c)?;
+        }
+        // drop stale data of previous transactions
+        let mut stale = [0; 64];
+        while c
+            .reader(self.module_nr)
+            .ok_or(Error::Address)?
+            .read(&mut stale)?
+            > 0
+        {}
+
+        c.writer(self.module_nr)
+            .ok_or(Error::Address)?
+            .write_all(&encode_frame(slave, pdu))?;
+
+        let clock = SystemClock;
+        let mut frame = vec![0; 2];
+        c.read_exact_timeout(self.module_nr, &mut frame, self.timeout, &clock, &mut cycle)?;
+        let function = frame[1];
+        let remaining = if function == pdu[0] | 0x80 {
+            3
+        } else {
+            match function {
+                READ_HOLDING_REGISTERS | READ_INPUT_REGISTERS => {
+                    let mut cnt = [0];
+                    c.read_exact_timeout(
+                        self.module_nr,
+                        &mut cnt,
+                        self.timeout,
+                        &clock,
+                        &mut cycle,
+                    )?;
+                    frame.push(cnt[0]);
+                    cnt[0] as usize + 2
+                }
+                WRITE_SINGLE_REGISTER | WRITE_MULTIPLE_REGISTERS => 6,
+                _ => return Err(Error::DataLength),
+            }
+        };
+        let start = frame.len();
+        frame.resize(start + remaining, 0);
+        c.read_exact_timeout(
+            self.module_nr,
+            &mut frame[start..],
+            self.timeout,
+            &clock,
+            &mut cycle,
+        )?;
+        let (res_slave, data) = decode_frame(&frame)?;
+        if res_slave != slave || data[0] & 0x7F != pdu[0] {
+            return Err(Error::SequenceNumber);
+        }
+        if data[0] & 0x80 != 0 {
+            return Err(Error::io(format!("Modbus exception 0x{:02X}", data[1])));
+        }
+        Ok(data[1..].to_vec())
+    }
+}
+
+/// Creates an RTU frame (slave address, PDU, CRC).
+pub fn encode_frame(slave: u8, pdu: &[u8]) -> Vec<u8> {
+    let mut frame = vec![slave];
+    frame.extend_from_slice(pdu);
+    let crc = crc16(&frame);
+    frame.extend_from_slice(&crc.to_le_bytes());
+    frame
+}
+
+/// Verifies the CRC of an RTU frame and returns the slave address and the PDU.
+pub fn decode_frame(frame: &[u8]) -> Result<(u8, &[u8])> {
+    if frame.len() < 4 {
+        return Err(Error::DataLength);
+    }
+    let (data, crc) = frame.split_at(frame.len() - 2);
+    if crc16(data).to_le_bytes() != crc {
+        return Err(Error::Checksum);
+    }
+    Ok((data[0], &data[1..]))
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::{ur20_1com_232_485_422::*, ur20_fbc_mod_tcp::CouplerConfig, util::u8_to_u16};
+    use num_traits::ToPrimitive;
+    use std::collections::VecDeque;
+
+    #[test]
+    fn calculate_crc() {
+        assert_eq!(crc16(&[0x01, 0x03, 0x00, 0x00, 0x00, 0x0A]), 0xCDC5);
+        assert_eq!(
+            encode_frame(0x01, &[0x03, 0x00, 0x00, 0x00, 0x0A]),
+            vec![0x01, 0x03, 0x00, 0x00, 0x00, 0x0A, 0xC5, 0xCD]
+        );
+    }
+
+    #[test]
+    fn decode_frames() {
+        let frame = encode_frame(0x11, &[0x06, 0x00, 0x01, 0x00, 0x03]);
+        assert_eq!(
+            decode_frame(&frame).unwrap(),
+            (0x11, &[0x06, 0x00, 0x01, 0x00, 0x03][..])
+        );
+        let mut invalid = frame.clone();
+        invalid[2] = 0xFF;
+        assert_eq!(decode_frame(&invalid).err().unwrap(), Error::Checksum);
+        assert_eq!(decode_frame(&frame[..3]).err().unwrap(), Error::DataLength);
+    }
+
+    fn com_coupler() -> Coupler {
+        #[rustfmt::skip]
+        let cfg = CouplerConfig {
+            modules: vec![ModuleType::UR20_1COM_232_485_422],
+            offsets: vec![0x8000, 0x0000],
+            params: vec![vec![
+                ProcessDataLength::EightBytes.to_u16().unwrap(),
+                OperatingMode::RS485.to_u16().unwrap(),
+                0, 0, 0, 0, 0, 0, 0, 0,
+            ]],
+        };
+        Coupler::new(&cfg).unwrap()
+    }
+
+    /// Splits the response into process input frames of the module.
+    fn input_frames(response: &[u8]) -> VecDeque<Vec<u16>> {
+        let mut frames: VecDeque<_> = vec![vec![0; 4]; 3].into();
+        for (i, chunk) in response.chunks(6).enumerate() {
+            let rx_cnt = (i % 4) as u16;
+            let mut words = vec![((chunk.len() as u16) << 8) | 0b1110_0001 | (rx_cnt << 3)];
+            words.extend(u8_to_u16(chunk));
+            words.resize(4, 0);
+            frames.push_back(words);
+        }
+        frames
+    }
+
+    #[test]
+    fn read_holding_registers() {
+        let mut c = com_coupler();
+        let response = encode_frame(0x01, &[0x03, 0x04, 0x12, 0x34, 0xAB, 0xCD]);
+        let mut frames = input_frames(&response);
+        let mut out = vec![0; 4];
+        let cycle = |c: &mut Coupler| {
+            let input = frames.pop_front().unwrap_or_else(|| vec![0; 4]);
+            out = c.next(&input, &out)?;
+            Ok(())
+        };
+        let master = RtuMaster::new(0);
+        assert_eq!(
+            master
+                .read_holding_registers(&mut c, 0x01, 0x0000, 2, cycle)
+                .unwrap(),
+            vec![0x1234, 0xABCD]
+        );
+    }
+
+    #[test]
+    fn handle_exceptions() {
+        let mut c = com_coupler();
+        let response = encode_frame(0x01, &[0x86, 0x02]);
+        let mut frames = input_frames(&response);
+        let mut out = vec![0; 4];
+        let cycle = |c: &mut Coupler| {
+            let input = frames.pop_front().unwrap_or_else(|| vec![0; 4]);
+            out = c.next(&input, &out)?;
+            Ok(())
+        };
+        let master = RtuMaster::new(0);
+        assert_eq!(
+            master
+                .write_single_register(&mut c, 0x01, 0x0001, 7, cycle)
+                .err()
+                .unwrap(),
+            Error::io("Modbus exception 0x02")
+        );
+    }
+
+    #[test]
+    fn time_out_without_response() {
+        let mut c = com_coupler();
+        let mut out = vec![0; 4];
+        let cycle = |c: &mut Coupler| {
+            out = c.next(&[0; 4], &out)?;
+            Ok(())
+        };
+        let mut master = RtuMaster::new(0);
+        master.timeout = Duration::from_millis(10);
+        assert!(master
+            .read_input_registers(&mut c, 0x01, 0x0000, 1, cycle)
+            .is_err());
+    }
+}
@@ -0,0 +1,135 @@
+//! Typed physical quantities based on the `uom` crate.
+//!
+//! This module is only available with the `uom` feature.
+
+use super::*;
+use crate::ur20_fbc_mod_tcp::Coupler;
+use uom::si::{
+    electric_current::milliampere,
+    electric_potential::volt,
+    electrical_resistance::ohm,
+    f32::{ElectricCurrent, ElectricPotential, ElectricalResistance, ThermodynamicTemperature},
+    thermodynamic_temperature::{degree_celsius, degree_fahrenheit, kelvin},
+};
+
+/// The value of an analog channel as a physical quantity.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Quantity {
+    Current(ElectricCurrent),
+    Potential(ElectricPotential),
+    Resistance(ElectricalResistance),
+    Temperature(ThermodynamicTemperature),
+}
+
+impl Quantity {
+    /// Creates a quantity from a value in the given unit.
+    pub fn new(v: f32, unit: Unit) -> Self {
+        match unit {
+            Unit::MilliAmpere => Quantity::Current(ElectricCurrent::new::<milliampere>(v)),
+            Unit::Volt => Quantity::Potential(ElectricPotential::new::<volt>(v)),
+            Unit::Ohm => Quantity::Resistance(ElectricalResistance::new::<ohm>(v)),
+            Unit::Celsius => {
+                Quantity::Temperature(ThermodynamicTemperature::new::<degree_celsius>(v))
+            }
+            Unit::Fahrenheit => {
+                Quantity::Temperature(ThermodynamicTemperature::new::<degree_fahrenheit>(v))
+            }
+            Unit::Kelvin => Quantity::Temperature(ThermodynamicTemperature::new::<kelvin>(v)),
+        }
+    }
+
+    /// Returns the value in the given unit.
+    ///
+    /// Returns `None` if the unit does not belong to the quantity
+    /// (e.g. a current in `V`).
+    pub fn value(&self, unit: Unit) -> Option<f32> {
+        match (self, unit) {
+            (Quantity::Current(q), Unit::MilliAmpere) => Some(q.get::<milliampere>()),
+            (Quantity::Potential(q), Unit::Volt) => Some(q.get::<volt>()),
+            (Quantity::Resistance(q), Unit::Ohm) => Some(q.get::<ohm>()),
+            (Quantity::Temperature(q), Unit::Celsius) => Some(q.get::<degree_celsius>()),
+            (Quantity::Temperature(q), Unit::Fahrenheit) => Some(q.get::<degree_fahrenheit>()),
+            (Quantity::Temperature(q), Unit::Kelvin) => Some(q.get::<kelvin>()),
+            _ => None,
+        }
+    }
+}
+
+impl ChannelValue {
+    /// Returns the value as quantity if the channel has a unit.
+    pub fn to_quantity(&self, info: &ChannelInfo) -> Option<Quantity> {
+        match (self, info.unit) {
+            (ChannelValue::Decimal32(v), Some(unit)) => Some(Quantity::new(*v, unit)),
+            _ => None,
+        }
+    }
+
+    /// Creates the value of a channel from a quantity.
+    pub fn from_quantity(q: &Quantity, info: &ChannelInfo) -> Result<ChannelValue> {
+        info.unit
+            .and_then(|unit| q.value(unit))
+            .map(ChannelValue::Decimal32)
+            .ok_or(Error::ChannelValue)
+    }
+}
+
+impl Coupler {
+    /// Returns the current value of an analog input as quantity.
+    pub fn input_quantity(&self, addr: &Address) -> Option<Quantity> {
+        let info = self.channel_info(addr)?;
+        self.inputs()
+            .get(addr.module)
+            .and_then(|m| m.get(addr.channel))
+            .and_then(|v| v.to_quantity(&info))
+    }
+
+    /// Sets the value of an analog output by a quantity.
+    ///
+    /// The quantity is converted into the unit of the channel.
+    pub fn set_output_quantity(&mut self, addr: &Address, q: &Quantity) -> Result<()> {
+        let info = self.channel_info(addr).ok_or(Error::Address)?;
+        let value = ChannelValue::from_quantity(q, &info)?;
+        self.set_output(addr, value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn convert_values_into_quantities() {
+        let info = ChannelInfo {
+            unit: Some(Unit::Celsius),
+            ..Default::default()
+        };
+        let q = ChannelValue::Decimal32(100.0).to_quantity(&info).unwrap();
+        assert!((q.value(Unit::Kelvin).unwrap() - 373.15).abs() < 0.01);
+        assert!(q.value(Unit::Volt).is_none());
+        assert!(ChannelValue::Bit(true).to_quantity(&info).is_none());
+        assert!(ChannelValue::Decimal32(1.0)
+            .to_quantity(&ChannelInfo::default())
+            .is_none());
+    }
+
+    #[test]
+    fn convert_quantities_into_values() {
+        let info = ChannelInfo {
+            unit: Some(Unit::MilliAmpere),
+            ..Default::default()
+        };
+        let q = Quantity::Current(ElectricCurrent::new::<uom::si::electric_current::ampere>(
+            0.012,
+        ));
+        match ChannelValue::from_quantity(&q, &info).unwrap() {
+            ChannelValue::Decimal32(v) => assert!((v - 12.0).abs() < 0.001),
+            v => panic!("unexpected value {:?}", v),
+        }
+        let q = Quantity::new(5.0, Unit::Volt);
+        assert_eq!(
+            ChannelValue::from_quantity(&q, &info).err().unwrap(),
+            Error::ChannelValue
+        );
+    }
+}
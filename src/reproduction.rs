@@ -0,0 +1,420 @@
+//! Captured register data to reproduce issues.
+//!
+//! A `Reproduction` contains the configuration registers of a station
+//! and a sequence of process images. It can be anonymized, written as
+//! plain text (e.g. to attach it to a bug report) and replayed with
+//! a `Coupler`.
+//!
+//! The text format contains a fingerprint of the station configuration
+//! and optionally a checksum for each cycle, so corrupted files and
+//! files of another station are rejected instead of being analyzed.
+
+use super::*;
+use crate::ur20_fbc_mod_tcp::{
+    module_from_parameter_data, offsets_of_process_data, to_register_address, Coupler,
+    CouplerConfig, ADDR_PACKED_PROCESS_INPUT_DATA, ADDR_PACKED_PROCESS_OUTPUT_DATA,
+};
+
+/// Consistency check of the recorded cycles.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RecordChecksum {
+    /// The cycles are written without checksum.
+    None,
+    /// Each cycle is followed by the CRC-16 (Modbus) of its data.
+    Crc16,
+}
+
+/// Captured register data of a station.
+#[derive(Debug, Clone)]
+pub struct Reproduction {
+    pub config: CouplerConfig,
+    /// Pairs of process input and process output data (one per cycle).
+    pub cycles: Vec<(Vec<u16>, Vec<u16>)>,
+    /// Checksum that is written with each cycle.
+    pub checksum: RecordChecksum,
+}
+
+/// Calculates a fingerprint of the station configuration
+/// (module IDs, offsets and parameters).
+pub fn fingerprint(config: &CouplerConfig) -> u16 {
+    let mut words = vec![];
+    for m in &config.modules {
+        let id = m.to_u32().unwrap_or(0);
+        words.push((id >> 16) as u16);
+        words.push(id as u16);
+    }
+    words.extend_from_slice(&config.offsets);
+    for p in &config.params {
+        words.push(p.len() as u16);
+        words.extend_from_slice(p);
+    }
+    util::crc16(&util::u16_to_u8(&words))
+}
+
+fn record_checksum(input: &[u16], output: &[u16]) -> u16 {
+    let mut bytes = util::u16_to_u8(input);
+    bytes.extend(util::u16_to_u8(output));
+    util::crc16(&bytes)
+}
+
+impl Reproduction {
+    pub fn new(config: CouplerConfig) -> Self {
+        Reproduction {
+            config,
+            cycles: vec![],
+            checksum: RecordChecksum::None,
+        }
+    }
+
+    /// Fingerprint of the recorded station configuration.
+    pub fn fingerprint(&self) -> u16 {
+        fingerprint(&self.config)
+    }
+
+    /// Adds the process data of a cycle.
+    pub fn push(&mut self, process_input: &[u16], process_output: &[u16]) {
+        self.cycles
+            .push((process_input.to_vec(), process_output.to_vec()));
+    }
+
+    /// Returns a copy without user data.
+    ///
+    /// The payload of communication modules (see
+    /// `ProcessModbusTcpData::process_input_payload`) is replaced by zeros.
+    /// Status and length bytes are kept, so the structure of
+    /// the data stays the same.
+    pub fn anonymize(&self) -> Result<Self> {
+        let mut res = self.clone();
+        let offsets = offsets_of_process_data(&self.config.offsets);
+        for ((t, params), offset) in self
+            .config
+            .modules
+            .iter()
+            .zip(&self.config.params)
+            .zip(offsets)
+        {
+            let m = module_from_parameter_data(t, params)?;
+            for (input, output) in &mut res.cycles {
+                if let Some(o) = offset.input {
+                    let start = byte_offset(o, ADDR_PACKED_PROCESS_INPUT_DATA)?;
+                    for r in m.process_input_payload() {
+                        clear_bytes(input, start + r.start, start + r.end);
+                    }
+                }
+                if let Some(o) = offset.output {
+                    let start = byte_offset(o, ADDR_PACKED_PROCESS_OUTPUT_DATA)?;
+                    for r in m.process_output_payload() {
+                        clear_bytes(output, start + r.start, start + r.end);
+                    }
+                }
+            }
+        }
+        Ok(res)
+    }
+
+    /// Runs all cycles and returns the input values of each cycle.
+    pub fn replay(&self) -> Result<Vec<Vec<Vec<ChannelValue>>>> {
+        let mut c = Coupler::new(&self.config)?;
+        let mut res = vec![];
+        for (input, output) in &self.cycles {
+            c.next(input, output)?;
+            res.push(c.inputs().clone());
+        }
+        Ok(res)
+    }
+
+    /// Like `replay` but refuses to run if the recorded data
+    /// does not belong to a station with the given configuration.
+    pub fn replay_with(&self, config: &CouplerConfig) -> Result<Vec<Vec<Vec<ChannelValue>>>> {
+        let recorded = self.fingerprint();
+        let actual = fingerprint(config);
+        if recorded != actual {
+            return Err(Error::StationMismatch { recorded, actual });
+        }
+        self.replay()
+    }
+}
+
+fn byte_offset(offset: u16, base: u16) -> Result<usize> {
+    let (register, bit) = to_register_address(offset);
+    if register < base {
+        return Err(Error::ModuleOffset);
+    }
+    Ok((register - base) as usize * 2 + bit / 8)
+}
+
+fn clear_bytes(image: &mut [u16], start: usize, end: usize) {
+    for idx in start..end {
+        if let Some(word) = image.get_mut(idx / 2) {
+            *word &= if idx % 2 == 0 { 0xFF00 } else { 0x00FF };
+        }
+    }
+}
+
+fn fmt_words(f: &mut fmt::Formatter, words: &[u16]) -> fmt::Result {
+    for w in words {
+        write!(f, " {:04X}", w)?;
+    }
+    Ok(())
+}
+
+/// The text format has one entry per line:
+///
+/// ```text
+/// fingerprint 1A30
+/// modules UR20_4DI_P UR20_4DO_P
+/// offsets FFFF 0000 8000 FFFF
+/// params 0000 0000 0000 0000
+/// params 0000 0000 0000 0000
+/// cycle 0001 / 0000 crc D801
+/// ```
+///
+/// There is one `params` line per module and one `cycle` line
+/// (input data / output data) per cycle.
+/// The `fingerprint` header and the `crc` of each cycle are optional
+/// when reading, but are checked if they are present.
+impl fmt::Display for Reproduction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "fingerprint {:04X}", self.fingerprint())?;
+        write!(f, "modules")?;
+        for m in &self.config.modules {
+            write!(f, " {:?}", m)?;
+        }
+        writeln!(f)?;
+        write!(f, "offsets")?;
+        fmt_words(f, &self.config.offsets)?;
+        writeln!(f)?;
+        for p in &self.config.params {
+            write!(f, "params")?;
+            fmt_words(f, p)?;
+            writeln!(f)?;
+        }
+        for (input, output) in &self.cycles {
+            write!(f, "cycle")?;
+            fmt_words(f, input)?;
+            write!(f, " /")?;
+            fmt_words(f, output)?;
+            if self.checksum == RecordChecksum::Crc16 {
+                write!(f, " crc {:04X}", record_checksum(input, output))?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+fn parse_words<'a, I: Iterator<Item = &'a str>>(words: I) -> Result<Vec<u16>> {
+    words
+        .map(|w| u16::from_str_radix(w, 16).map_err(|_| Error::DataLength))
+        .collect()
+}
+
+impl FromStr for Reproduction {
+    type Err = Error;
+    fn from_str(s: &str) -> result::Result<Self, Self::Err> {
+        let mut cfg = CouplerConfig {
+            modules: vec![],
+            offsets: vec![],
+            params: vec![],
+        };
+        let mut cycles = vec![];
+        let mut header = None;
+        let mut checksum = RecordChecksum::None;
+        for line in s.lines().map(str::trim).filter(|l| !l.is_empty()) {
+            let mut items = line.split_whitespace();
+            match items.next() {
+                Some("fingerprint") => {
+                    header = Some(parse_words(items)?);
+                }
+                Some("modules") => {
+                    cfg.modules = items.map(str::parse).collect::<Result<_>>()?;
+                }
+                Some("offsets") => {
+                    cfg.offsets = parse_words(items)?;
+                }
+                Some("params") => {
+                    cfg.params.push(parse_words(items)?);
+                }
+                Some("cycle") => {
+                    let mut items: Vec<_> = items.collect();
+                    let crc = match items.iter().position(|x| *x == "crc") {
+                        Some(pos) if pos + 2 == items.len() => {
+                            let crc = parse_words(items.drain(pos..).skip(1))?;
+                            Some(crc[0])
+                        }
+                        Some(_) => return Err(Error::DataLength),
+                        None => None,
+                    };
+                    let sep = items
+                        .iter()
+                        .position(|x| *x == "/")
+                        .ok_or(Error::DataLength)?;
+                    let input = parse_words(items[..sep].iter().cloned())?;
+                    let output = parse_words(items[sep + 1..].iter().cloned())?;
+                    if let Some(crc) = crc {
+                        if crc != record_checksum(&input, &output) {
+                            return Err(Error::Checksum);
+                        }
+                        checksum = RecordChecksum::Crc16;
+                    }
+                    cycles.push((input, output));
+                }
+                _ => return Err(Error::DataLength),
+            }
+        }
+        if let Some(header) = header {
+            let actual = fingerprint(&cfg);
+            if header.len() != 1 || header[0] != actual {
+                return Err(Error::Checksum);
+            }
+        }
+        Ok(Reproduction {
+            config: cfg,
+            cycles,
+            checksum,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::ur20_1com_232_485_422::{OperatingMode, ProcessDataLength};
+    use num_traits::ToPrimitive;
+
+    fn reproduction() -> Reproduction {
+        #[rustfmt::skip]
+        let cfg = CouplerConfig {
+            modules: vec![
+                ModuleType::UR20_4DI_P,
+                ModuleType::UR20_1COM_232_485_422,
+            ],
+            offsets: vec![
+                0xFFFF, 0x0000,
+                0x8000, 0x0010,
+            ],
+            params: vec![
+                vec![0; 4],
+                vec![
+                    ProcessDataLength::EightBytes.to_u16().unwrap(),
+                    OperatingMode::RS232.to_u16().unwrap(),
+                    0, 0, 0, 0, 0, 0, 0, 0,
+                ],
+            ],
+        };
+        let mut r = Reproduction::new(cfg);
+        r.push(
+            &[0x0005, 0x0301, 0x6261, 0x0063, 0x0000],
+            &[0x0000, 0x0000, 0x0000, 0x0000],
+        );
+        r.push(
+            &[0x0001, 0x0000, 0x0000, 0x0000, 0x0000],
+            &[0x0209, 0x6968, 0x0000, 0x0000],
+        );
+        r
+    }
+
+    #[test]
+    fn anonymize_user_data() {
+        let r = reproduction().anonymize().unwrap();
+        assert_eq!(r.cycles[0].0, vec![0x0005, 0x0301, 0x0000, 0x0000, 0x0000]);
+        assert_eq!(r.cycles[1].1, vec![0x0209, 0x0000, 0x0000, 0x0000]);
+        assert_eq!(r.cycles[1].0, reproduction().cycles[1].0);
+    }
+
+    #[test]
+    fn anonymize_io_link_data() {
+        let cfg = CouplerConfig {
+            modules: vec![ModuleType::UR20_4COM_IO_LINK],
+            offsets: vec![0x8000, 0x0000],
+            params: vec![vec![0; 24]],
+        };
+        let mut r = Reproduction::new(cfg);
+        r.push(&[0xFFFF; 26], &[0xFFFF; 24]);
+        let r = r.anonymize().unwrap();
+        let mut input = vec![0; 26];
+        input[..3].copy_from_slice(&[0xFFFF; 3]);
+        let mut output = vec![0; 24];
+        output[..3].copy_from_slice(&[0xFFFF; 3]);
+        assert_eq!(r.cycles[0], (input, output));
+    }
+
+    #[test]
+    fn write_and_read_text_format() {
+        let r = reproduction();
+        let txt = r.to_string();
+        assert!(txt.starts_with(&format!(
+            "fingerprint {:04X}\nmodules UR20_4DI_P UR20_1COM_232_485_422\noffsets FFFF 0000 8000 0010\n",
+            r.fingerprint()
+        )));
+        assert!(txt.contains("cycle 0005 0301 6261 0063 0000 / 0000 0000 0000 0000\n"));
+        let parsed: Reproduction = txt.parse().unwrap();
+        assert_eq!(parsed.config.modules, r.config.modules);
+        assert_eq!(parsed.config.offsets, r.config.offsets);
+        assert_eq!(parsed.config.params, r.config.params);
+        assert_eq!(parsed.cycles, r.cycles);
+        assert!("cycle 0000".parse::<Reproduction>().is_err());
+        assert!("foo".parse::<Reproduction>().is_err());
+    }
+
+    #[test]
+    fn read_text_format_without_header() {
+        let txt = reproduction().to_string();
+        let txt = txt.lines().skip(1).collect::<Vec<_>>().join("\n");
+        let parsed: Reproduction = txt.parse().unwrap();
+        assert_eq!(parsed.cycles, reproduction().cycles);
+        assert_eq!(parsed.checksum, RecordChecksum::None);
+    }
+
+    #[test]
+    fn reject_text_format_with_wrong_fingerprint() {
+        let txt = reproduction().to_string();
+        let txt = txt.replace("offsets FFFF 0000 8000 0010", "offsets FFFF 0000 8000 0012");
+        assert_eq!(txt.parse::<Reproduction>().err(), Some(Error::Checksum));
+    }
+
+    #[test]
+    fn write_and_read_cycles_with_checksum() {
+        let mut r = reproduction();
+        r.checksum = RecordChecksum::Crc16;
+        let txt = r.to_string();
+        assert!(txt.contains("cycle 0005 0301 6261 0063 0000 / 0000 0000 0000 0000 crc "));
+        let parsed: Reproduction = txt.parse().unwrap();
+        assert_eq!(parsed.checksum, RecordChecksum::Crc16);
+        assert_eq!(parsed.cycles, r.cycles);
+
+        let corrupted = txt.replace("6261", "6262");
+        assert_eq!(
+            corrupted.parse::<Reproduction>().err(),
+            Some(Error::Checksum)
+        );
+        let truncated = txt.replace(" crc ", " crc\n");
+        assert!(truncated.parse::<Reproduction>().is_err());
+    }
+
+    #[test]
+    fn refuse_replay_with_another_station() {
+        let r = reproduction();
+        assert_eq!(r.replay_with(&r.config).unwrap().len(), 2);
+        let mut cfg = r.config.clone();
+        cfg.params[0][1] = 1;
+        match r.replay_with(&cfg).err() {
+            Some(Error::StationMismatch { recorded, actual }) => {
+                assert_eq!(recorded, r.fingerprint());
+                assert_eq!(actual, fingerprint(&cfg));
+                assert_ne!(recorded, actual);
+            }
+            e => panic!("unexpected result: {:?}", e),
+        }
+    }
+
+    #[test]
+    fn replay_cycles() {
+        let values = reproduction().anonymize().unwrap().replay().unwrap();
+        assert_eq!(values.len(), 2);
+        assert_eq!(values[0][0][0], ChannelValue::Bit(true));
+        assert_eq!(values[0][0][2], ChannelValue::Bit(true));
+        assert_eq!(values[1][0][2], ChannelValue::Bit(false));
+    }
+}
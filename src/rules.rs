@@ -0,0 +1,208 @@
+//! Data driven interlock rules.
+//!
+//! A rule requires an output state as long as all of its conditions
+//! are met. If several active rules refer to the same output,
+//! the rule with the highest priority wins
+//! (on equal priorities the first one in the list).
+//!
+//! A `RuleSet` is a `CyclicTask`, so it is evaluated on each cycle:
+//!
+//! ```rust,no_run
+//! # use ur20::{rules::*, ur20_fbc_mod_tcp::Coupler, Address};
+//! # fn setup(coupler: &mut Coupler) {
+//! let rules = RuleSet {
+//!     rules: vec![Rule {
+//!         name: "stop pump on low level".into(),
+//!         priority: 10,
+//!         conditions: vec![Condition::Below(Address { module: 2, channel: 0 }, 1.5)],
+//!         output: Address { module: 1, channel: 0 },
+//!         state: OutputState::Bit(false),
+//!     }],
+//! };
+//! coupler.add_task(Box::new(rules));
+//! # }
+//! ```
+
+use super::*;
+use crate::task::{CyclicTask, IoAccess};
+
+/// A condition over an input channel.
+///
+/// Conditions over unknown channels or channels
+/// with an unexpected value type are never met.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Condition {
+    /// The digital input is on.
+    On(Address),
+    /// The digital input is off.
+    Off(Address),
+    /// The analog input is above the limit.
+    Above(Address, f32),
+    /// The analog input is below the limit.
+    Below(Address, f32),
+}
+
+impl Condition {
+    pub fn is_met(&self, io: &dyn IoAccess) -> bool {
+        match *self {
+            Condition::On(ref a) => io.input(a) == Some(&ChannelValue::Bit(true)),
+            Condition::Off(ref a) => io.input(a) == Some(&ChannelValue::Bit(false)),
+            Condition::Above(ref a, limit) => match io.input(a) {
+                Some(ChannelValue::Decimal32(v)) => *v > limit,
+                _ => false,
+            },
+            Condition::Below(ref a, limit) => match io.input(a) {
+                Some(ChannelValue::Decimal32(v)) => *v < limit,
+                _ => false,
+            },
+        }
+    }
+}
+
+/// The required state of an output channel.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum OutputState {
+    Bit(bool),
+    Decimal32(f32),
+}
+
+impl From<OutputState> for ChannelValue {
+    fn from(s: OutputState) -> Self {
+        match s {
+            OutputState::Bit(v) => ChannelValue::Bit(v),
+            OutputState::Decimal32(v) => ChannelValue::Decimal32(v),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Rule {
+    pub name: String,
+    /// Rules with a higher priority win.
+    pub priority: u8,
+    /// All conditions must be met.
+    pub conditions: Vec<Condition>,
+    pub output: Address,
+    pub state: OutputState,
+}
+
+impl Rule {
+    pub fn is_active(&self, io: &dyn IoAccess) -> bool {
+        self.conditions.iter().all(|c| c.is_met(io))
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct RuleSet {
+    pub rules: Vec<Rule>,
+}
+
+impl RuleSet {
+    /// Returns the winning rule of each output with an active rule.
+    pub fn evaluate(&self, io: &dyn IoAccess) -> Vec<&Rule> {
+        let mut res: Vec<&Rule> = vec![];
+        for r in self.rules.iter().filter(|r| r.is_active(io)) {
+            match res.iter_mut().find(|x| x.output == r.output) {
+                Some(x) => {
+                    if r.priority > x.priority {
+                        *x = r;
+                    }
+                }
+                None => res.push(r),
+            }
+        }
+        res
+    }
+}
+
+impl CyclicTask for RuleSet {
+    fn scan(&mut self, io: &mut dyn IoAccess) {
+        let required: Vec<_> = self
+            .evaluate(io)
+            .into_iter()
+            .map(|r| (r.output, r.state))
+            .collect();
+        for (addr, state) in required {
+            // Invalid output addresses are ignored.
+            let _ = io.set_output(&addr, state.into());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::ur20_fbc_mod_tcp::{Coupler, CouplerConfig};
+
+    fn addr(module: usize, channel: usize) -> Address {
+        Address { module, channel }
+    }
+
+    fn rule(name: &str, priority: u8, conditions: Vec<Condition>, state: bool) -> Rule {
+        Rule {
+            name: name.into(),
+            priority,
+            conditions,
+            output: addr(1, 0),
+            state: OutputState::Bit(state),
+        }
+    }
+
+    fn coupler() -> Coupler {
+        let cfg = CouplerConfig {
+            modules: vec![ModuleType::UR20_4DI_P, ModuleType::UR20_4DO_P],
+            offsets: vec![0xFFFF, 0x0000, 0x8000, 0xFFFF],
+            params: vec![vec![0; 4], vec![0; 4]],
+        };
+        Coupler::new(&cfg).unwrap()
+    }
+
+    #[test]
+    fn highest_priority_wins() {
+        let mut c = coupler();
+        c.add_task(Box::new(RuleSet {
+            rules: vec![
+                rule("start", 1, vec![Condition::On(addr(0, 0))], true),
+                rule(
+                    "emergency stop",
+                    9,
+                    vec![Condition::On(addr(0, 0)), Condition::Off(addr(0, 1))],
+                    false,
+                ),
+                rule("other start", 1, vec![Condition::On(addr(0, 2))], true),
+            ],
+        }));
+        assert_eq!(c.next(&[0b0000], &[0]).unwrap(), vec![0b0000]);
+        assert_eq!(c.next(&[0b0011], &[0]).unwrap(), vec![0b0001]);
+        assert_eq!(c.next(&[0b0001], &[1]).unwrap(), vec![0b0000]);
+        assert_eq!(c.next(&[0b0100], &[0]).unwrap(), vec![0b0001]);
+    }
+
+    #[test]
+    fn rules_without_active_conditions_do_not_change_outputs() {
+        let mut c = coupler();
+        c.add_task(Box::new(RuleSet {
+            rules: vec![rule("stop", 1, vec![Condition::On(addr(0, 3))], false)],
+        }));
+        c.set_output(&addr(1, 0), ChannelValue::Bit(true)).unwrap();
+        assert_eq!(c.next(&[0b0000], &[0]).unwrap(), vec![0b0001]);
+        assert_eq!(c.next(&[0b1000], &[1]).unwrap(), vec![0b0000]);
+    }
+
+    #[test]
+    fn conditions_over_invalid_channels_are_never_met() {
+        let mut c = coupler();
+        c.add_task(Box::new(RuleSet {
+            rules: vec![
+                rule("a", 1, vec![Condition::On(addr(5, 0))], true),
+                rule("b", 1, vec![Condition::Above(addr(0, 0), 0.0)], true),
+            ],
+        }));
+        assert_eq!(c.next(&[0b1111], &[0]).unwrap(), vec![0b0000]);
+    }
+}
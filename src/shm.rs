@@ -0,0 +1,184 @@
+//! Export of the process image into shared memory.
+//!
+//! The latest process image is written into a memory mapped file
+//! (e.g. below `/dev/shm`), so other local processes can read the
+//! I/O data without a network connection.
+//!
+//! The region starts with a header followed by the serialized
+//! image (see `ProcessImage::to_bytes`). All values are little endian:
+//!
+//! | Offset | Size | Content                                          |
+//! |--------|------|--------------------------------------------------|
+//! | 0      | 4    | sequence number (odd while an image is written)  |
+//! | 4      | 2    | CRC-16 (Modbus) of the serialized image          |
+//! | 6      | 2    | reserved                                         |
+//! | 8      | ...  | serialized image                                 |
+//!
+//! A reader copies the region, and accepts the copy if the sequence
+//! number is even, unchanged after copying and the checksum matches.
+//!
+//! ```rust,no_run
+//! # use ur20::{image::ProcessImage, shm::SharedImageWriter};
+//! let mut w = SharedImageWriter::create("/dev/shm/ur20", 512).unwrap();
+//! w.write(&ProcessImage {
+//!     input: vec![0x0102],
+//!     output: vec![],
+//! })
+//! .unwrap();
+//! ```
+
+use super::*;
+use crate::{image::ProcessImage, ur20_fbc_mod_tcp::RegisterFormat};
+use byteorder::{ByteOrder, LittleEndian};
+use memmap2::{Mmap, MmapMut};
+use std::{
+    fs::OpenOptions,
+    path::Path,
+    sync::atomic::{fence, Ordering},
+};
+
+/// Size of the header in bytes.
+pub const HEADER_SIZE: usize = 8;
+
+/// Size of the image header (register counts) in bytes.
+const IMAGE_HEADER_SIZE: usize = 4;
+
+/// Publishes process images into a memory mapped file.
+#[derive(Debug)]
+pub struct SharedImageWriter {
+    map: MmapMut,
+    seq: u32,
+}
+
+impl SharedImageWriter {
+    /// Creates (or truncates) the file with space
+    /// for `capacity` input and output registers.
+    ///
+    /// The file initially contains an empty image.
+    pub fn create<P: AsRef<Path>>(path: P, capacity: usize) -> Result<Self> {
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(path)?;
+        file.set_len((HEADER_SIZE + IMAGE_HEADER_SIZE + capacity * 2) as u64)?;
+        // The file is only modified through this mapping.
+        let map = unsafe { MmapMut::map_mut(&file)? };
+        let mut w = SharedImageWriter { map, seq: 0 };
+        w.write(&ProcessImage::default())?;
+        Ok(w)
+    }
+
+    /// Number of registers that fit into the region.
+    pub fn capacity(&self) -> usize {
+        (self.map.len() - HEADER_SIZE - IMAGE_HEADER_SIZE) / 2
+    }
+
+    /// Sequence number of the last written image.
+    pub fn sequence_number(&self) -> u32 {
+        self.seq
+    }
+
+    /// Writes an image and increments the sequence number.
+    pub fn write(&mut self, img: &ProcessImage) -> Result<()> {
+        if img.input.len() + img.output.len() > self.capacity() {
+            return Err(Error::Capacity);
+        }
+        let bytes = img.to_bytes(&RegisterFormat::LittleEndian);
+        self.set_sequence_number(self.seq.wrapping_add(1));
+        fence(Ordering::Release);
+        LittleEndian::write_u16(&mut self.map[4..6], util::crc16(&bytes));
+        self.map[HEADER_SIZE..HEADER_SIZE + bytes.len()].copy_from_slice(&bytes);
+        fence(Ordering::Release);
+        self.set_sequence_number(self.seq.wrapping_add(1));
+        Ok(())
+    }
+
+    fn set_sequence_number(&mut self, seq: u32) {
+        self.seq = seq;
+        LittleEndian::write_u32(&mut self.map[0..4], seq);
+    }
+}
+
+/// Reads process images that are published by a `SharedImageWriter`.
+#[derive(Debug)]
+pub struct SharedImageReader {
+    map: Mmap,
+}
+
+impl SharedImageReader {
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let file = OpenOptions::new().read(true).open(path)?;
+        // Concurrent modifications are detected by `read`.
+        let map = unsafe { Mmap::map(&file)? };
+        if map.len() < HEADER_SIZE + IMAGE_HEADER_SIZE {
+            return Err(Error::BufferLength);
+        }
+        Ok(SharedImageReader { map })
+    }
+
+    /// Returns the sequence number and the latest image.
+    ///
+    /// Fails with `Error::SequenceNumber` if the image
+    /// was changed while reading, so the caller should retry.
+    pub fn read(&self) -> Result<(u32, ProcessImage)> {
+        let seq = LittleEndian::read_u32(&self.map[0..4]);
+        fence(Ordering::Acquire);
+        let data = self.map.to_vec();
+        fence(Ordering::Acquire);
+        if seq % 2 != 0 || LittleEndian::read_u32(&self.map[0..4]) != seq {
+            return Err(Error::SequenceNumber);
+        }
+        let crc = LittleEndian::read_u16(&data[4..6]);
+        let img = &data[HEADER_SIZE..];
+        let len = IMAGE_HEADER_SIZE
+            + 2 * (LittleEndian::read_u16(&img[0..2]) as usize
+                + LittleEndian::read_u16(&img[2..4]) as usize);
+        if len > img.len() {
+            return Err(Error::BufferLength);
+        }
+        if util::crc16(&img[..len]) != crc {
+            return Err(Error::Checksum);
+        }
+        let img = ProcessImage::from_bytes(&img[..len], &RegisterFormat::LittleEndian)?;
+        Ok((seq, img))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use std::{env, fs};
+
+    #[test]
+    fn write_and_read_images() {
+        let path = env::temp_dir().join(format!("ur20-shm-test-{}", std::process::id()));
+        let mut w = SharedImageWriter::create(&path, 4).unwrap();
+        assert_eq!(w.capacity(), 4);
+        let r = SharedImageReader::open(&path).unwrap();
+        assert_eq!(r.read().unwrap(), (2, ProcessImage::default()));
+
+        let img = ProcessImage {
+            input: vec![1, 2, 3],
+            output: vec![0x8000],
+        };
+        w.write(&img).unwrap();
+        assert_eq!(w.sequence_number(), 4);
+        assert_eq!(r.read().unwrap(), (4, img));
+
+        let too_large = ProcessImage {
+            input: vec![0; 5],
+            output: vec![],
+        };
+        assert_eq!(w.write(&too_large).err(), Some(Error::Capacity));
+        assert_eq!(w.sequence_number(), 4);
+
+        w.map[HEADER_SIZE + IMAGE_HEADER_SIZE] = 9;
+        assert_eq!(r.read().err(), Some(Error::Checksum));
+        w.map[0] = 3;
+        assert_eq!(r.read().err(), Some(Error::SequenceNumber));
+        fs::remove_file(&path).unwrap();
+    }
+}
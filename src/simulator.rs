@@ -0,0 +1,961 @@
+//! Simulation of a Modbus TCP coupler for tests of application logic.
+//!
+//! The simulation can inject faults into the input images
+//! to test error handling and recovery. The faults are derived
+//! from a seed, so a test run can be reproduced.
+//!
+//! A `ProcessImageBuilder` creates input images from channel values:
+//!
+//! ```rust
+//! # use ur20::{simulator::ProcessImageBuilder, ur20_fbc_mod_tcp::CouplerConfig, *};
+//! let cfg = CouplerConfig {
+//!     modules: vec![ModuleType::UR20_4DI_P, ModuleType::UR20_4AI_UI_16],
+//!     offsets: vec![0xFFFF, 0x0000, 0xFFFF, 0x0010],
+//!     params: vec![vec![0; 4], vec![0, 1, 2, 1, 2, 1, 2, 1, 2]],
+//! };
+//! let input = ProcessImageBuilder::new(&cfg)
+//!     .unwrap()
+//!     .set_input(&Address { module: 0, channel: 1 }, ChannelValue::Bit(true))
+//!     .unwrap()
+//!     .set_input(&Address { module: 1, channel: 0 }, ChannelValue::Decimal32(5.0))
+//!     .unwrap()
+//!     .build();
+//! assert_eq!(input, vec![0b10, 0x3600, 0, 0, 0]);
+//! ```
+
+use super::*;
+use crate::ur20_fbc_mod_tcp::*;
+use std::cmp;
+#[cfg(feature = "tcp")]
+use std::io::{self, Read, Write};
+
+/// Probabilities (`0.0 ... 1.0`) of faults within a cycle.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FaultConfig {
+    /// Start value of the pseudo random sequence.
+    pub seed: u64,
+    /// A random bit of the input image is inverted.
+    pub bit_flip: f32,
+    /// The input image is cut off at a random length.
+    pub truncated_read: f32,
+    /// A random module delivers the input data of the previous cycle.
+    pub delayed_module: f32,
+    /// A random module delivers only zeros.
+    pub module_dropout: f32,
+}
+
+/// A fault that was injected into an input image.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Fault {
+    BitFlip { register: usize, bit: usize },
+    TruncatedRead { len: usize },
+    DelayedModule(usize),
+    ModuleDropout(usize),
+}
+
+/// Xorshift pseudo random numbers.
+#[derive(Debug)]
+struct Rng(u64);
+
+impl Rng {
+    fn new(seed: u64) -> Self {
+        // the state must not be zero
+        Rng(seed ^ 0x9E37_79B9_7F4A_7C15)
+    }
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+    fn chance(&mut self, p: f32) -> bool {
+        p > 0.0 && (self.next() >> 40) as f32 / (1u64 << 24) as f32 <= p
+    }
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+}
+
+#[derive(Debug)]
+struct FaultInjection {
+    cfg: FaultConfig,
+    rng: Rng,
+    /// input image of the previous cycle
+    last_input: Vec<u16>,
+    /// faults of the last cycle
+    faults: Vec<Fault>,
+}
+
+/// A simulated station that holds the process images of a coupler.
+#[derive(Debug)]
+pub struct SimulatedCoupler {
+    /// used to decode the output image
+    decoder: Coupler,
+    modules: Vec<ModuleType>,
+    offsets: Vec<ModuleOffset>,
+    input: Vec<u16>,
+    output: Vec<u16>,
+    /// output channels that are wired to input channels
+    loopbacks: Vec<(Address, Address)>,
+    /// first bit and number of bits of the module input data
+    input_bits: Vec<Option<(usize, usize)>>,
+    faults: Option<FaultInjection>,
+}
+
+impl SimulatedCoupler {
+    pub fn new(cfg: &CouplerConfig) -> Result<Self> {
+        let decoder = Coupler::new(cfg)?;
+        let input = vec![0; decoder.process_input_len()];
+        let output = vec![0; decoder.process_output_len()];
+        let offsets = offsets_of_process_data(&cfg.offsets);
+        let mut input_bits = vec![];
+        for ((t, params), offset) in cfg.modules.iter().zip(&cfg.params).zip(&offsets) {
+            let m = module_from_parameter_data(t, params)?;
+            input_bits.push(match (offset.input, m.process_input_byte_count()) {
+                (Some(o), cnt) if cnt > 0 => {
+                    let (register, bit) = to_register_address(o);
+                    let idx = register.saturating_sub(ADDR_PACKED_PROCESS_INPUT_DATA) as usize;
+                    Some((idx * 16 + bit, cnt * 8))
+                }
+                _ => None,
+            });
+        }
+        Ok(SimulatedCoupler {
+            decoder,
+            modules: cfg.modules.clone(),
+            offsets,
+            input,
+            output,
+            loopbacks: vec![],
+            input_bits,
+            faults: None,
+        })
+    }
+
+    /// Enables (or disables) the fault injection.
+    ///
+    /// The faults only affect the input images that are passed
+    /// to the coupler by `cycle`, not the state of the simulation.
+    pub fn set_faults(&mut self, cfg: Option<FaultConfig>) {
+        self.faults = cfg.map(|cfg| FaultInjection {
+            rng: Rng::new(cfg.seed),
+            cfg,
+            last_input: self.input.clone(),
+            faults: vec![],
+        });
+    }
+
+    /// Returns the faults that were injected within the last cycle.
+    pub fn injected_faults(&self) -> &[Fault] {
+        self.faults.as_ref().map(|f| &f.faults[..]).unwrap_or(&[])
+    }
+
+    /// Returns the input image with injected faults.
+    fn faulty_input(&mut self) -> Vec<u16> {
+        let mut input = self.input.clone();
+        let f = match self.faults {
+            Some(ref mut f) => f,
+            None => return input,
+        };
+        f.faults.clear();
+        let modules: Vec<_> = self
+            .input_bits
+            .iter()
+            .enumerate()
+            .filter_map(|(nr, bits)| bits.map(|b| (nr, b)))
+            .collect();
+        let bit_cnt = input.len() * 16;
+        if !modules.is_empty() && f.rng.chance(f.cfg.delayed_module) {
+            let (nr, (start, len)) = modules[f.rng.below(modules.len())];
+            for b in start..cmp::min(start + len, bit_cnt) {
+                set_image_bit(&mut input, b, image_bit(&f.last_input, b));
+            }
+            f.faults.push(Fault::DelayedModule(nr));
+        }
+        if !modules.is_empty() && f.rng.chance(f.cfg.module_dropout) {
+            let (nr, (start, len)) = modules[f.rng.below(modules.len())];
+            for b in start..cmp::min(start + len, bit_cnt) {
+                set_image_bit(&mut input, b, false);
+            }
+            f.faults.push(Fault::ModuleDropout(nr));
+        }
+        if bit_cnt > 0 && f.rng.chance(f.cfg.bit_flip) {
+            let b = f.rng.below(bit_cnt);
+            let v = image_bit(&input, b);
+            set_image_bit(&mut input, b, !v);
+            f.faults.push(Fault::BitFlip {
+                register: b / 16,
+                bit: b % 16,
+            });
+        }
+        if !input.is_empty() && f.rng.chance(f.cfg.truncated_read) {
+            let len = f.rng.below(input.len());
+            input.truncate(len);
+            f.faults.push(Fault::TruncatedRead { len });
+        }
+        f.last_input = self.input.clone();
+        input
+    }
+
+    /// The register content of `ADDR_PACKED_PROCESS_INPUT_DATA`.
+    pub fn process_input(&self) -> &[u16] {
+        &self.input
+    }
+
+    /// The register content of `ADDR_PACKED_PROCESS_OUTPUT_DATA`.
+    pub fn process_output(&self) -> &[u16] {
+        &self.output
+    }
+
+    /// Sets a digital input channel.
+    pub fn set_input(&mut self, addr: &Address, value: bool) -> Result<()> {
+        let (idx, bit) = self.input_bit(addr)?;
+        if value {
+            self.input[idx] |= 1 << bit;
+        } else {
+            self.input[idx] &= !(1 << bit);
+        }
+        Ok(())
+    }
+
+    /// Returns the register index and the bit number of a digital input.
+    fn input_bit(&self, addr: &Address) -> Result<(usize, usize)> {
+        let m = self.modules.get(addr.module).ok_or(Error::Address)?;
+        if addr.channel >= m.channel_count() {
+            return Err(Error::Address);
+        }
+        if ModuleCategory::from(m.clone()) != ModuleCategory::DI {
+            return Err(Error::ChannelValue);
+        }
+        let offset = self.offsets[addr.module].input.ok_or(Error::ModuleOffset)?;
+        let (register, bit) = to_register_address(offset + addr.channel as u16);
+        let idx = register.saturating_sub(ADDR_PACKED_PROCESS_INPUT_DATA) as usize;
+        if idx >= self.input.len() {
+            return Err(Error::ModuleOffset);
+        }
+        Ok((idx, bit))
+    }
+
+    /// Wires an output channel to a digital input channel.
+    ///
+    /// The input follows the output each time an output image is written.
+    pub fn add_loopback(&mut self, output: Address, input: Address) -> Result<()> {
+        let out_type = self.modules.get(output.module).ok_or(Error::Address)?;
+        if output.channel >= out_type.channel_count() {
+            return Err(Error::Address);
+        }
+        if self.offsets[output.module].output.is_none() {
+            return Err(Error::ModuleOffset);
+        }
+        self.input_bit(&input)?;
+        self.loopbacks.push((output, input));
+        Ok(())
+    }
+
+    /// Takes the output image that was written by a master.
+    pub fn write_output(&mut self, data: &[u16]) -> Result<()> {
+        if data.len() != self.output.len() {
+            return Err(Error::BufferLength);
+        }
+        self.decoder.ingest_output_readback(data)?;
+        self.output = data.to_vec();
+        for (output, input) in self.loopbacks.clone() {
+            let value = match self.decoder.outputs()[output.module][output.channel] {
+                ChannelValue::Bit(v) => v,
+                _ => return Err(Error::ChannelValue),
+            };
+            self.set_input(&input, value)?;
+        }
+        Ok(())
+    }
+
+    /// Runs a cycle of the given coupler against the simulated station.
+    pub fn cycle(&mut self, coupler: &mut Coupler) -> Result<()> {
+        let input = self.faulty_input();
+        let output = coupler.next(&input, &self.output)?;
+        self.write_output(&output)
+    }
+}
+
+/// Creates packed process input images for a station.
+///
+/// This is the inverse of `Coupler::process_input_data`.
+/// All channels start with raw input data of zero.
+#[derive(Debug)]
+pub struct ProcessImageBuilder {
+    modules: Vec<Box<dyn ProcessModbusTcpData>>,
+    offsets: Vec<ModuleOffset>,
+    values: Vec<Vec<ChannelValue>>,
+    /// raw input data of each module
+    data: Vec<Vec<u16>>,
+    len: usize,
+}
+
+impl ProcessImageBuilder {
+    pub fn new(cfg: &CouplerConfig) -> Result<Self> {
+        let len = Coupler::new(cfg)?.process_input_len();
+        let modules = cfg
+            .modules
+            .iter()
+            .zip(&cfg.params)
+            .map(|(t, params)| module_from_parameter_data(t, params))
+            .collect::<Result<Vec<_>>>()?;
+        let values = modules
+            .iter()
+            .map(|m| vec![ChannelValue::None; m.module_type().channel_count()])
+            .collect();
+        let data = modules
+            .iter()
+            .map(|m| vec![0; m.process_input_byte_count().div_ceil(2)])
+            .collect();
+        Ok(ProcessImageBuilder {
+            modules,
+            offsets: offsets_of_process_data(&cfg.offsets),
+            values,
+            data,
+            len,
+        })
+    }
+
+    /// Sets the value of an input channel.
+    ///
+    /// The value is encoded by `ProcessModbusTcpData::process_input_values`,
+    /// modules without an encoding result in `Error::ChannelValue`
+    /// (use `set_raw_input` instead).
+    pub fn set_input(&mut self, addr: &Address, value: ChannelValue) -> Result<&mut Self> {
+        let m = self.input_module(addr.module)?;
+        let mut values = self.values[addr.module].clone();
+        *values.get_mut(addr.channel).ok_or(Error::Address)? = value;
+        let data = m.process_input_values(&values)?;
+        self.values[addr.module] = values;
+        self.data[addr.module] = data;
+        Ok(self)
+    }
+
+    /// Sets the raw input data of a module.
+    pub fn set_raw_input(&mut self, module_nr: usize, data: &[u16]) -> Result<&mut Self> {
+        let m = self.input_module(module_nr)?;
+        if data.len() != m.process_input_byte_count().div_ceil(2) {
+            return Err(Error::BufferLength);
+        }
+        self.values[module_nr] = vec![ChannelValue::None; m.module_type().channel_count()];
+        self.data[module_nr] = data.to_vec();
+        Ok(self)
+    }
+
+    /// Returns the register content of `ADDR_PACKED_PROCESS_INPUT_DATA`.
+    pub fn build(&self) -> Vec<u16> {
+        let mut image = vec![0; self.len];
+        for ((m, offset), data) in self.modules.iter().zip(&self.offsets).zip(&self.data) {
+            if let Some(offset) = offset.input {
+                let (register, bit) = to_register_address(offset);
+                let idx = register.saturating_sub(ADDR_PACKED_PROCESS_INPUT_DATA) as usize;
+                let first = idx * 16 + bit;
+                let cnt = m.process_input_byte_count() * 8;
+                for b in 0..cnt {
+                    set_image_bit(&mut image, first + b, image_bit(data, b));
+                }
+            }
+        }
+        image
+    }
+
+    fn input_module(&self, module_nr: usize) -> Result<&dyn ProcessModbusTcpData> {
+        let m = self.modules.get(module_nr).ok_or(Error::Address)?;
+        if self.offsets[module_nr].input.is_none() {
+            return Err(Error::ModuleOffset);
+        }
+        Ok(m.as_ref())
+    }
+}
+
+/// An in-memory station that emulates the Modbus register map of a coupler.
+///
+/// The module list, the module offsets, the module parameters,
+/// the packed process data, the coupler parameters, the coupler status
+/// and the module diagnosis can be read. The packed process output data
+/// and the coupler parameters can be written.
+///
+/// With the `tcp` feature it acts as a Modbus TCP server stream,
+/// so it can be passed to `tcp::Client::new`.
+#[derive(Debug)]
+pub struct MockCoupler {
+    modules: Vec<ModuleType>,
+    offsets: Vec<u16>,
+    params: Vec<Vec<u16>>,
+    /// used to decode the output image
+    decoder: Coupler,
+    inputs: ProcessImageBuilder,
+    output: Vec<u16>,
+    coupler_params: Vec<u16>,
+    status: u16,
+    diagnosis: Vec<Vec<u16>>,
+    /// pending Modbus TCP response
+    #[cfg(feature = "tcp")]
+    response: Vec<u8>,
+}
+
+impl MockCoupler {
+    pub fn new(cfg: &CouplerConfig) -> Result<Self> {
+        let decoder = Coupler::new(cfg)?;
+        let output = vec![0; decoder.process_output_len()];
+        let diagnosis = diagnosis_addresses_and_register_counts(cfg.modules.len())
+            .into_iter()
+            .map(|(_, cnt)| vec![0; cnt as usize])
+            .collect();
+        Ok(MockCoupler {
+            modules: cfg.modules.clone(),
+            offsets: cfg.offsets.clone(),
+            params: cfg.params.clone(),
+            decoder,
+            inputs: ProcessImageBuilder::new(cfg)?,
+            output,
+            coupler_params: CouplerParameters::default().to_raw_data(),
+            status: 0,
+            diagnosis,
+            #[cfg(feature = "tcp")]
+            response: vec![],
+        })
+    }
+
+    /// The input channels of the station.
+    pub fn inputs_mut(&mut self) -> &mut ProcessImageBuilder {
+        &mut self.inputs
+    }
+
+    /// Decodes the output image that was written by a master.
+    pub fn outputs(&self) -> Result<Vec<Vec<ChannelValue>>> {
+        self.decoder.process_output_data(&self.output)
+    }
+
+    /// Sets the register content of `ADDR_COUPLER_STATUS`.
+    pub fn set_status(&mut self, status: u16) {
+        self.status = status;
+    }
+
+    /// Sets the diagnosis registers of a module.
+    pub fn set_module_diagnosis(&mut self, module_nr: usize, data: &[u16]) -> Result<()> {
+        let d = self.diagnosis.get_mut(module_nr).ok_or(Error::Address)?;
+        if data.len() != d.len() {
+            return Err(Error::BufferLength);
+        }
+        d.copy_from_slice(data);
+        Ok(())
+    }
+
+    pub fn read_holding_registers(&self, addr: u16, count: usize) -> Result<Vec<u16>> {
+        let input = self.inputs.build();
+        (0..count)
+            .map(|i| {
+                let addr = addr.checked_add(i as u16).ok_or(Error::Address)?;
+                self.register(&input, addr).ok_or(Error::Address)
+            })
+            .collect()
+    }
+
+    pub fn write_multiple_registers(&mut self, addr: u16, values: &[u16]) -> Result<()> {
+        let area = [
+            (ADDR_PACKED_PROCESS_OUTPUT_DATA, self.output.len()),
+            (ADDR_COUPLER_PARAMETERS, COUPLER_PARAMETER_REGISTER_COUNT),
+        ]
+        .iter()
+        .position(|&(start, len)| {
+            addr >= start && addr as usize + values.len() <= start as usize + len
+        })
+        .ok_or(Error::Address)?;
+        if area == 0 {
+            let start = (addr - ADDR_PACKED_PROCESS_OUTPUT_DATA) as usize;
+            self.output[start..start + values.len()].copy_from_slice(values);
+        } else {
+            let mut params = self.coupler_params.clone();
+            let start = (addr - ADDR_COUPLER_PARAMETERS) as usize;
+            params[start..start + values.len()].copy_from_slice(values);
+            CouplerParameters::from_raw_data(&params)?;
+            self.coupler_params = params;
+        }
+        Ok(())
+    }
+
+    fn register(&self, input: &[u16], addr: u16) -> Option<u16> {
+        let area = |start: u16, data: &[u16]| {
+            addr.checked_sub(start)
+                .and_then(|i| data.get(i as usize).cloned())
+        };
+        let list: Vec<u16> = self
+            .modules
+            .iter()
+            .flat_map(|m| {
+                let id = m.to_u32().unwrap_or(0);
+                vec![(id >> 16) as u16, id as u16]
+            })
+            .collect();
+        let params = param_addresses_and_register_counts(&self.modules);
+        let diagnosis = diagnosis_addresses_and_register_counts(self.modules.len());
+        area(ADDR_PACKED_PROCESS_INPUT_DATA, input)
+            .or_else(|| area(ADDR_PACKED_PROCESS_OUTPUT_DATA, &self.output))
+            .or_else(|| area(ADDR_COUPLER_STATUS, &[self.status]))
+            .or_else(|| area(ADDR_COUPLER_PARAMETERS, &self.coupler_params))
+            .or_else(|| area(ADDR_CURRENT_MODULE_COUNT, &[self.modules.len() as u16]))
+            .or_else(|| area(ADDR_CURRENT_MODULE_LIST, &list))
+            .or_else(|| area(ADDR_MODULE_OFFSETS, &self.offsets))
+            .or_else(|| {
+                params
+                    .iter()
+                    .zip(&self.params)
+                    .find_map(|(&(start, _), data)| area(start, data))
+            })
+            .or_else(|| {
+                diagnosis
+                    .iter()
+                    .zip(&self.diagnosis)
+                    .find_map(|(&(start, _), data)| area(start, data))
+            })
+    }
+
+    /// Processes a Modbus TCP request and returns the response PDU.
+    #[cfg(feature = "tcp")]
+    fn modbus_response(&mut self, req: &[u8]) -> Vec<u8> {
+        use crate::tcp::{READ_HOLDING_REGISTERS, WRITE_MULTIPLE_REGISTERS};
+
+        const ILLEGAL_FUNCTION: u8 = 0x01;
+        const ILLEGAL_DATA_ADDRESS: u8 = 0x02;
+
+        let function = req[7];
+        let addr = u16::from_be_bytes([req[8], req[9]]);
+        let cnt = u16::from_be_bytes([req[10], req[11]]);
+        let res = match function {
+            READ_HOLDING_REGISTERS => self.read_holding_registers(addr, cnt as usize).map(|data| {
+                let mut pdu = vec![function, (cnt * 2) as u8];
+                for v in data {
+                    pdu.extend_from_slice(&v.to_be_bytes());
+                }
+                pdu
+            }),
+            WRITE_MULTIPLE_REGISTERS => {
+                let values: Vec<_> = req[13..]
+                    .chunks(2)
+                    .map(|x| u16::from_be_bytes([x[0], x[1]]))
+                    .collect();
+                self.write_multiple_registers(addr, &values).map(|_| {
+                    let mut pdu = vec![function];
+                    pdu.extend_from_slice(&req[8..12]);
+                    pdu
+                })
+            }
+            _ => return vec![function | 0x80, ILLEGAL_FUNCTION],
+        };
+        res.unwrap_or_else(|_| vec![function | 0x80, ILLEGAL_DATA_ADDRESS])
+    }
+}
+
+#[cfg(feature = "tcp")]
+impl Write for MockCoupler {
+    /// Takes a complete Modbus TCP request.
+    fn write(&mut self, req: &[u8]) -> io::Result<usize> {
+        if req.len() < 12 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "incomplete Modbus TCP request",
+            ));
+        }
+        let pdu = self.modbus_response(req);
+        self.response = req[0..4].to_vec();
+        self.response
+            .extend_from_slice(&(pdu.len() as u16 + 1).to_be_bytes());
+        self.response.push(req[6]);
+        self.response.extend(pdu);
+        Ok(req.len())
+    }
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(feature = "tcp")]
+impl Read for MockCoupler {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let len = cmp::min(buf.len(), self.response.len());
+        buf[..len].copy_from_slice(&self.response[..len]);
+        self.response.drain(..len);
+        Ok(len)
+    }
+}
+
+fn image_bit(image: &[u16], bit: usize) -> bool {
+    image
+        .get(bit / 16)
+        .map(|r| r & (1 << (bit % 16)) != 0)
+        .unwrap_or(false)
+}
+
+fn set_image_bit(image: &mut [u16], bit: usize, value: bool) {
+    if let Some(r) = image.get_mut(bit / 16) {
+        if value {
+            *r |= 1 << (bit % 16);
+        } else {
+            *r &= !(1 << (bit % 16));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::ur20_fbc_mod_tcp::ToModbusParameterData;
+
+    fn config() -> CouplerConfig {
+        CouplerConfig {
+            modules: vec![
+                ModuleType::UR20_4DI_P,
+                ModuleType::UR20_4DO_P,
+                ModuleType::UR20_4DI_P,
+            ],
+            offsets: vec![0xFFFF, 0x0000, 0x8000, 0xFFFF, 0xFFFF, 0x0008],
+            params: vec![vec![0; 4], vec![0; 4], vec![0; 4]],
+        }
+    }
+
+    #[test]
+    fn set_inputs() {
+        let mut sim = SimulatedCoupler::new(&config()).unwrap();
+        assert_eq!(sim.process_input(), &[0]);
+        assert_eq!(sim.process_output(), &[0]);
+        sim.set_input(
+            &Address {
+                module: 2,
+                channel: 1,
+            },
+            true,
+        )
+        .unwrap();
+        assert_eq!(sim.process_input(), &[0x0200]);
+        assert!(sim
+            .set_input(
+                &Address {
+                    module: 1,
+                    channel: 1
+                },
+                true
+            )
+            .is_err());
+        assert!(sim
+            .set_input(
+                &Address {
+                    module: 2,
+                    channel: 4
+                },
+                true
+            )
+            .is_err());
+    }
+
+    #[test]
+    fn loopback_outputs_to_inputs() {
+        let cfg = config();
+        let mut sim = SimulatedCoupler::new(&cfg).unwrap();
+        let mut c = Coupler::new(&cfg).unwrap();
+        for ch in 0..4 {
+            let output = Address {
+                module: 1,
+                channel: ch,
+            };
+            let input = Address {
+                module: 0,
+                channel: ch,
+            };
+            sim.add_loopback(output, input).unwrap();
+        }
+        assert!(sim
+            .add_loopback(
+                Address {
+                    module: 0,
+                    channel: 0
+                },
+                Address {
+                    module: 2,
+                    channel: 0
+                }
+            )
+            .is_err());
+        assert!(sim
+            .add_loopback(
+                Address {
+                    module: 1,
+                    channel: 0
+                },
+                Address {
+                    module: 1,
+                    channel: 0
+                }
+            )
+            .is_err());
+
+        c.set_output(
+            &Address {
+                module: 1,
+                channel: 2,
+            },
+            ChannelValue::Bit(true),
+        )
+        .unwrap();
+        sim.cycle(&mut c).unwrap();
+        assert_eq!(sim.process_output(), &[0b0100]);
+        assert_eq!(sim.process_input(), &[0b0100]);
+        sim.cycle(&mut c).unwrap();
+        assert_eq!(c.inputs()[0][2], ChannelValue::Bit(true));
+        assert_eq!(c.inputs()[0][1], ChannelValue::Bit(false));
+    }
+
+    #[test]
+    fn build_input_images() {
+        let cfg = CouplerConfig {
+            modules: vec![
+                ModuleType::UR20_4DI_P,
+                ModuleType::UR20_8DI_P_2W,
+                ModuleType::UR20_8AI_I_16_HD,
+                ModuleType::UR20_4AI_RTD_DIAG,
+                ModuleType::UR20_4DO_P,
+            ],
+            offsets: vec![
+                0xFFFF, 0x0000, 0xFFFF, 0x0008, 0xFFFF, 0x0010, 0xFFFF, 0x0090, 0x8000, 0xFFFF,
+            ],
+            params: vec![
+                vec![0; 4],
+                vec![0; 8],
+                vec![0; 17],
+                ur20_4ai_rtd_diag::Mod::default().to_modbus_parameter_data(),
+                vec![0; 4],
+            ],
+        };
+        let c = Coupler::new(&cfg).unwrap();
+        let addr = |module, channel| Address { module, channel };
+        let mut b = ProcessImageBuilder::new(&cfg).unwrap();
+        assert_eq!(b.build(), vec![0; 13]);
+        b.set_input(&addr(0, 3), ChannelValue::Bit(true))
+            .unwrap()
+            .set_input(&addr(1, 0), ChannelValue::Bit(true))
+            .unwrap()
+            .set_input(&addr(1, 7), ChannelValue::Bit(true))
+            .unwrap()
+            .set_input(&addr(2, 1), ChannelValue::Decimal32(10.0))
+            .unwrap()
+            .set_raw_input(3, &[0, 0, 0, 0x7D00])
+            .unwrap();
+        let input = b.build();
+        assert_eq!(input[0], 0x8108);
+        assert_eq!(input[2], 0x2000);
+        assert_eq!(input[12], 0x7D00);
+
+        let values = c.process_input_data(&input).unwrap();
+        assert_eq!(values[0][3], ChannelValue::Bit(true));
+        assert_eq!(values[0][2], ChannelValue::Bit(false));
+        assert_eq!(values[1][0], ChannelValue::Bit(true));
+        assert_eq!(values[1][7], ChannelValue::Bit(true));
+        assert_eq!(values[2][1], ChannelValue::Decimal32(10.0));
+        assert_eq!(values[2][0], ChannelValue::Decimal32(0.0));
+
+        assert_eq!(
+            b.set_input(&addr(0, 0), ChannelValue::Decimal32(1.0)).err(),
+            Some(Error::ChannelValue)
+        );
+        assert_eq!(
+            b.set_input(&addr(3, 0), ChannelValue::Decimal32(1.0)).err(),
+            Some(Error::ChannelValue)
+        );
+        assert_eq!(
+            b.set_input(&addr(4, 0), ChannelValue::Bit(true)).err(),
+            Some(Error::ModuleOffset)
+        );
+        assert_eq!(
+            b.set_input(&addr(0, 4), ChannelValue::Bit(true)).err(),
+            Some(Error::Address)
+        );
+        assert_eq!(b.set_raw_input(3, &[0]).err(), Some(Error::BufferLength));
+        assert_eq!(b.build(), input);
+    }
+
+    #[test]
+    fn emulate_register_map() {
+        let mut mock = MockCoupler::new(&config()).unwrap();
+        assert_eq!(
+            mock.read_holding_registers(ADDR_CURRENT_MODULE_COUNT, 1)
+                .unwrap(),
+            vec![3]
+        );
+        let list = mock
+            .read_holding_registers(ADDR_CURRENT_MODULE_LIST, 6)
+            .unwrap();
+        assert_eq!(module_list_from_registers(&list).unwrap(), config().modules);
+        assert_eq!(
+            mock.read_holding_registers(ADDR_MODULE_OFFSETS, 6).unwrap(),
+            config().offsets
+        );
+        assert_eq!(
+            mock.read_holding_registers(ADDR_MODULE_PARAMETERS + 256, 4)
+                .unwrap(),
+            vec![0; 4]
+        );
+        assert_eq!(
+            mock.read_holding_registers(ADDR_MODULE_PARAMETERS + 256, 5),
+            Err(Error::Address)
+        );
+
+        mock.inputs_mut()
+            .set_input(
+                &Address {
+                    module: 2,
+                    channel: 0,
+                },
+                ChannelValue::Bit(true),
+            )
+            .unwrap();
+        assert_eq!(
+            mock.read_holding_registers(ADDR_PACKED_PROCESS_INPUT_DATA, 1)
+                .unwrap(),
+            vec![0x0100]
+        );
+        mock.write_multiple_registers(ADDR_PACKED_PROCESS_OUTPUT_DATA, &[0b0010])
+            .unwrap();
+        assert_eq!(mock.outputs().unwrap()[1][1], ChannelValue::Bit(true));
+        assert_eq!(
+            mock.write_multiple_registers(ADDR_PACKED_PROCESS_OUTPUT_DATA, &[0, 0]),
+            Err(Error::Address)
+        );
+        assert_eq!(
+            mock.write_multiple_registers(ADDR_MODULE_OFFSETS, &[0]),
+            Err(Error::Address)
+        );
+
+        mock.write_multiple_registers(ADDR_COUPLER_PARAMETERS + 3, &[100])
+            .unwrap();
+        let params = mock
+            .read_holding_registers(ADDR_COUPLER_PARAMETERS, COUPLER_PARAMETER_REGISTER_COUNT)
+            .unwrap();
+        assert_eq!(
+            CouplerParameters::from_raw_data(&params)
+                .unwrap()
+                .watchdog_timeout,
+            100
+        );
+        assert!(mock
+            .write_multiple_registers(ADDR_COUPLER_PARAMETERS, &[9])
+            .is_err());
+
+        mock.set_status(0x0001);
+        mock.set_module_diagnosis(1, &[1, 0, 0, 0, 0, 0, 0, 0])
+            .unwrap();
+        assert_eq!(
+            mock.read_holding_registers(ADDR_COUPLER_STATUS, 1).unwrap(),
+            vec![1]
+        );
+        let (addr, _) = diagnosis_addresses_and_register_counts(3)[1];
+        assert_eq!(mock.read_holding_registers(addr, 1).unwrap(), vec![1]);
+        assert_eq!(mock.set_module_diagnosis(1, &[0]), Err(Error::BufferLength));
+    }
+
+    #[cfg(feature = "tcp")]
+    #[test]
+    fn connect_to_mock_coupler() {
+        use crate::tcp::{Client, ConnectedCoupler};
+
+        let mut mock = MockCoupler::new(&config()).unwrap();
+        mock.inputs_mut()
+            .set_input(
+                &Address {
+                    module: 0,
+                    channel: 3,
+                },
+                ChannelValue::Bit(true),
+            )
+            .unwrap();
+        let mut c = ConnectedCoupler::new(Client::new(mock)).unwrap();
+        c.coupler_mut()
+            .set_output(
+                &Address {
+                    module: 1,
+                    channel: 2,
+                },
+                ChannelValue::Bit(true),
+            )
+            .unwrap();
+        c.cycle().unwrap();
+        assert_eq!(c.coupler().inputs()[0][3], ChannelValue::Bit(true));
+        c.cycle().unwrap();
+        assert_eq!(c.coupler().outputs()[1][2], ChannelValue::Bit(true));
+        assert_eq!(
+            c.read_coupler_parameters().unwrap(),
+            CouplerParameters::default()
+        );
+    }
+
+    fn faulty_cycles(cfg: &FaultConfig, cycles: usize) -> Vec<Vec<Fault>> {
+        let mut sim = SimulatedCoupler::new(&config()).unwrap();
+        let mut c = Coupler::new(&config()).unwrap();
+        sim.set_faults(Some(cfg.clone()));
+        (0..cycles)
+            .map(|_| {
+                sim.cycle(&mut c).unwrap();
+                sim.injected_faults().to_vec()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn inject_faults_deterministically() {
+        let cfg = FaultConfig {
+            seed: 42,
+            bit_flip: 0.5,
+            truncated_read: 0.2,
+            delayed_module: 0.3,
+            module_dropout: 0.3,
+        };
+        let faults = faulty_cycles(&cfg, 50);
+        assert_eq!(faults, faulty_cycles(&cfg, 50));
+        let all: Vec<_> = faults.iter().flatten().collect();
+        assert!(all.iter().any(|f| matches!(f, Fault::BitFlip { .. })));
+        assert!(all.iter().any(|f| matches!(f, Fault::TruncatedRead { .. })));
+        assert!(all.iter().any(|f| matches!(f, Fault::DelayedModule(_))));
+        assert!(all.iter().any(|f| matches!(f, Fault::ModuleDropout(_))));
+        let other = FaultConfig { seed: 7, ..cfg };
+        assert_ne!(faults, faulty_cycles(&other, 50));
+        assert!(faulty_cycles(&FaultConfig::default(), 10)
+            .iter()
+            .all(Vec::is_empty));
+    }
+
+    #[test]
+    fn module_dropout_clears_module_inputs_only() {
+        let mut sim = SimulatedCoupler::new(&config()).unwrap();
+        let mut c = Coupler::new(&config()).unwrap();
+        for ch in 0..4 {
+            for module in [0, 2] {
+                sim.set_input(
+                    &Address {
+                        module,
+                        channel: ch,
+                    },
+                    true,
+                )
+                .unwrap();
+            }
+        }
+        sim.set_faults(Some(FaultConfig {
+            module_dropout: 1.0,
+            ..Default::default()
+        }));
+        sim.cycle(&mut c).unwrap();
+        let nr = match sim.injected_faults() {
+            [Fault::ModuleDropout(nr)] => *nr,
+            f => panic!("unexpected faults {:?}", f),
+        };
+        let other = if nr == 0 { 2 } else { 0 };
+        assert_eq!(c.inputs()[nr], vec![ChannelValue::Bit(false); 4]);
+        assert_eq!(c.inputs()[other], vec![ChannelValue::Bit(true); 4]);
+        assert_eq!(sim.process_input(), &[0x0F0F]);
+        sim.set_faults(None);
+        sim.cycle(&mut c).unwrap();
+        assert!(sim.injected_faults().is_empty());
+        assert_eq!(c.inputs()[nr], vec![ChannelValue::Bit(true); 4]);
+    }
+}
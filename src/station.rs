@@ -0,0 +1,313 @@
+//! Declarative description of a UR20 station.
+
+use super::*;
+use crate::ur20_fbc_mod_tcp::CouplerConfig;
+
+/// Describes all modules of a station.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct StationDescription {
+    /// The modules in the order of their physical position.
+    pub modules: Vec<ModuleDescription>,
+    /// Channels that are calculated from the physical input channels.
+    ///
+    /// They are addressed behind the last module,
+    /// i.e. with the module number `modules.len()`.
+    pub virtual_channels: Vec<VirtualChannel>,
+}
+
+/// Describes a single module of a station.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ModuleDescription {
+    /// The type of the module.
+    pub module_type: ModuleType,
+    /// Raw module parameters (register content of `ADDR_MODULE_PARAMETERS`).
+    pub params: Vec<u16>,
+    /// Optional channel descriptions (indexed by the channel number).
+    pub channels: Vec<ChannelDescription>,
+}
+
+/// Human readable description of a channel.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ChannelDescription {
+    /// A short unique name of the signal (e.g. `pump_1`).
+    pub label: Option<String>,
+    /// A longer description of the signal.
+    pub description: Option<String>,
+    /// Logical inversion of a digital input or output channel
+    /// (e.g. for normally-closed contacts).
+    ///
+    /// The coupler inverts the values of the channel,
+    /// so the application only deals with the logical state.
+    pub invert: bool,
+}
+
+/// A channel that is calculated from physical input channels.
+#[derive(Debug, Clone, PartialEq)]
+pub struct VirtualChannel {
+    pub channel: ChannelDescription,
+    pub expression: Expression,
+}
+
+/// The arithmetic combination of input channels.
+///
+/// All inputs must be decimal values,
+/// otherwise the result is `ChannelValue::None`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expression {
+    Sum(Vec<Address>),
+    /// The first value minus the second value
+    /// (e.g. the difference of two temperatures).
+    Difference(Address, Address),
+    Average(Vec<Address>),
+    Min(Vec<Address>),
+    Max(Vec<Address>),
+}
+
+impl Expression {
+    /// The addresses of all channels the expression depends on.
+    pub fn inputs(&self) -> Vec<Address> {
+        match *self {
+            Expression::Difference(a, b) => vec![a, b],
+            Expression::Sum(ref x)
+            | Expression::Average(ref x)
+            | Expression::Min(ref x)
+            | Expression::Max(ref x) => x.clone(),
+        }
+    }
+
+    /// Calculates the value from the given input values.
+    pub fn evaluate(&self, inputs: &[Vec<ChannelValue>]) -> ChannelValue {
+        let values: Option<Vec<f32>> = self
+            .inputs()
+            .iter()
+            .map(
+                |a| match inputs.get(a.module).and_then(|m| m.get(a.channel)) {
+                    Some(ChannelValue::Decimal32(v)) => Some(*v),
+                    _ => None,
+                },
+            )
+            .collect();
+        let values = match values {
+            Some(ref v) if !v.is_empty() => v,
+            _ => return ChannelValue::None,
+        };
+        let v = match *self {
+            Expression::Sum(_) => values.iter().sum(),
+            Expression::Difference(_, _) => values[0] - values[1],
+            Expression::Average(_) => values.iter().sum::<f32>() / values.len() as f32,
+            Expression::Min(_) => values.iter().cloned().fold(f32::INFINITY, f32::min),
+            Expression::Max(_) => values.iter().cloned().fold(f32::NEG_INFINITY, f32::max),
+        };
+        ChannelValue::Decimal32(v)
+    }
+}
+
+impl ModuleDescription {
+    /// Creates a module description without any channel descriptions.
+    pub fn new(module_type: ModuleType, params: Vec<u16>) -> Self {
+        ModuleDescription {
+            module_type,
+            params,
+            channels: vec![],
+        }
+    }
+}
+
+impl StationDescription {
+    /// Creates the coupler configuration by the given module offsets
+    /// (register content of `ADDR_MODULE_OFFSETS`).
+    pub fn coupler_config(&self, offsets: Vec<u16>) -> CouplerConfig {
+        CouplerConfig {
+            modules: self.modules.iter().map(|m| m.module_type.clone()).collect(),
+            offsets,
+            params: self.modules.iter().map(|m| m.params.clone()).collect(),
+        }
+    }
+
+    /// Returns the description of a channel (if there is one).
+    pub fn channel(&self, addr: &Address) -> Option<&ChannelDescription> {
+        if addr.module == self.modules.len() {
+            return self.virtual_channels.get(addr.channel).map(|v| &v.channel);
+        }
+        self.modules
+            .get(addr.module)
+            .and_then(|m| m.channels.get(addr.channel))
+    }
+
+    /// Finds the address of a channel by its label.
+    pub fn address_of(&self, label: &str) -> Option<Address> {
+        let has_label =
+            |c: &ChannelDescription| c.label.as_ref().map(|l| l == label).unwrap_or(false);
+        self.modules
+            .iter()
+            .enumerate()
+            .find_map(|(module, m)| {
+                m.channels
+                    .iter()
+                    .position(has_label)
+                    .map(|channel| Address { module, channel })
+            })
+            .or_else(|| {
+                self.virtual_channels
+                    .iter()
+                    .position(|v| has_label(&v.channel))
+                    .map(|channel| Address {
+                        module: self.modules.len(),
+                        channel,
+                    })
+            })
+    }
+
+    pub(crate) fn validate(&self) -> Result<()> {
+        for m in &self.modules {
+            if m.channels.len() > m.module_type.channel_count() {
+                return Err(Error::ChannelParameter);
+            }
+            let digital = matches!(
+                ModuleCategory::from(m.module_type.clone()),
+                ModuleCategory::DI | ModuleCategory::DO | ModuleCategory::RO
+            );
+            if !digital && m.channels.iter().any(|c| c.invert) {
+                return Err(Error::ChannelParameter);
+            }
+        }
+        for v in &self.virtual_channels {
+            if v.channel.invert {
+                return Err(Error::ChannelParameter);
+            }
+            for a in v.expression.inputs() {
+                match self.modules.get(a.module) {
+                    Some(m) if a.channel < m.module_type.channel_count() => {}
+                    _ => return Err(Error::Address),
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn labeled(label: &str) -> ChannelDescription {
+        ChannelDescription {
+            label: Some(label.into()),
+            ..Default::default()
+        }
+    }
+
+    fn station() -> StationDescription {
+        StationDescription {
+            modules: vec![
+                ModuleDescription {
+                    module_type: ModuleType::UR20_4DI_P,
+                    params: vec![0; 4],
+                    channels: vec![ChannelDescription::default(), labeled("door_open")],
+                },
+                ModuleDescription::new(ModuleType::UR20_16DO_P, vec![]),
+            ],
+            virtual_channels: vec![],
+        }
+    }
+
+    #[test]
+    fn create_coupler_config() {
+        let cfg = station().coupler_config(vec![0xFFFF, 0x0000, 0x8000, 0xFFFF]);
+        assert_eq!(
+            cfg.modules,
+            vec![ModuleType::UR20_4DI_P, ModuleType::UR20_16DO_P]
+        );
+        assert_eq!(cfg.params, vec![vec![0; 4], vec![]]);
+        assert_eq!(cfg.offsets, vec![0xFFFF, 0x0000, 0x8000, 0xFFFF]);
+    }
+
+    #[test]
+    fn find_channels() {
+        let s = station();
+        let addr = Address {
+            module: 0,
+            channel: 1,
+        };
+        assert_eq!(s.address_of("door_open"), Some(addr));
+        assert_eq!(s.address_of("foo"), None);
+        assert_eq!(s.channel(&addr), Some(&labeled("door_open")));
+        assert_eq!(
+            s.channel(&Address {
+                module: 1,
+                channel: 0
+            }),
+            None
+        );
+    }
+
+    #[test]
+    fn validate_channel_count() {
+        let mut s = station();
+        assert!(s.validate().is_ok());
+        s.modules[0].channels = vec![ChannelDescription::default(); 5];
+        assert!(s.validate().is_err());
+        let inverted = ChannelDescription {
+            invert: true,
+            ..Default::default()
+        };
+        s.modules[0].channels = vec![inverted.clone()];
+        assert!(s.validate().is_ok());
+        s.modules.push(ModuleDescription {
+            module_type: ModuleType::UR20_4AI_UI_16,
+            params: vec![0; 9],
+            channels: vec![inverted],
+        });
+        assert!(s.validate().is_err());
+    }
+
+    #[test]
+    fn evaluate_expressions() {
+        let a = |module, channel| Address { module, channel };
+        let inputs = vec![
+            vec![ChannelValue::Decimal32(20.5), ChannelValue::Decimal32(18.0)],
+            vec![ChannelValue::Decimal32(1.0), ChannelValue::Disabled],
+        ];
+        let diff = Expression::Difference(a(0, 0), a(0, 1));
+        assert_eq!(diff.evaluate(&inputs), ChannelValue::Decimal32(2.5));
+        let sum = Expression::Sum(vec![a(0, 0), a(0, 1), a(1, 0)]);
+        assert_eq!(sum.evaluate(&inputs), ChannelValue::Decimal32(39.5));
+        let avg = Expression::Average(vec![a(0, 0), a(0, 1)]);
+        assert_eq!(avg.evaluate(&inputs), ChannelValue::Decimal32(19.25));
+        let min = Expression::Min(vec![a(0, 0), a(0, 1)]);
+        assert_eq!(min.evaluate(&inputs), ChannelValue::Decimal32(18.0));
+        let max = Expression::Max(vec![a(0, 0), a(1, 0)]);
+        assert_eq!(max.evaluate(&inputs), ChannelValue::Decimal32(20.5));
+        let invalid = Expression::Sum(vec![a(0, 0), a(1, 1)]);
+        assert_eq!(invalid.evaluate(&inputs), ChannelValue::None);
+        assert_eq!(
+            Expression::Sum(vec![]).evaluate(&inputs),
+            ChannelValue::None
+        );
+    }
+
+    #[test]
+    fn find_and_validate_virtual_channels() {
+        let mut s = station();
+        s.virtual_channels.push(VirtualChannel {
+            channel: labeled("any_door"),
+            expression: Expression::Max(vec![Address {
+                module: 0,
+                channel: 1,
+            }]),
+        });
+        let addr = Address {
+            module: 2,
+            channel: 0,
+        };
+        assert_eq!(s.address_of("any_door"), Some(addr));
+        assert_eq!(s.channel(&addr), Some(&labeled("any_door")));
+        assert!(s.validate().is_ok());
+        s.virtual_channels[0].expression = Expression::Sum(vec![Address {
+            module: 0,
+            channel: 4,
+        }]);
+        assert_eq!(s.validate().err().unwrap(), Error::Address);
+    }
+}
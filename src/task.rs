@@ -0,0 +1,89 @@
+//! Scan cycle programming model.
+//!
+//! A `CyclicTask` contains user logic (e.g. an interlock or a small
+//! control loop) that is executed on every call of `Coupler::next`:
+//! after the inputs were decoded and before the outputs are encoded.
+//! Outputs that are set by a task are written within the same cycle.
+
+use super::*;
+
+/// Access to the process values during a scan.
+pub trait IoAccess {
+    /// Returns the current value of an input channel.
+    fn input(&self, addr: &Address) -> Option<&ChannelValue>;
+    /// Returns the value that will be written to an output channel.
+    fn output(&self, addr: &Address) -> Option<&ChannelValue>;
+    /// Sets the value of an output channel.
+    fn set_output(&mut self, addr: &Address, value: ChannelValue) -> Result<()>;
+}
+
+/// User logic that runs once per cycle.
+pub trait CyclicTask: Debug + Send {
+    fn scan(&mut self, io: &mut dyn IoAccess);
+}
+
+/// The process values of a running cycle.
+pub(crate) struct ScanIo<'a> {
+    pub inputs: &'a [Vec<ChannelValue>],
+    pub outputs: &'a mut [Vec<ChannelValue>],
+    /// Modules whose outputs may be set.
+    pub writable: &'a [bool],
+}
+
+impl<'a> IoAccess for ScanIo<'a> {
+    fn input(&self, addr: &Address) -> Option<&ChannelValue> {
+        self.inputs.get(addr.module)?.get(addr.channel)
+    }
+    fn output(&self, addr: &Address) -> Option<&ChannelValue> {
+        self.outputs.get(addr.module)?.get(addr.channel)
+    }
+    fn set_output(&mut self, addr: &Address, value: ChannelValue) -> Result<()> {
+        if !self.writable.get(addr.module).cloned().unwrap_or(false) {
+            return Err(Error::Address);
+        }
+        let v = self
+            .outputs
+            .get_mut(addr.module)
+            .and_then(|m| m.get_mut(addr.channel))
+            .ok_or(Error::Address)?;
+        *v = value;
+        Ok(())
+    }
+}
+
+/// Runs the tasks in the order they were added.
+pub(crate) fn run_tasks(tasks: &mut [Box<dyn CyclicTask>], io: &mut ScanIo) {
+    for t in tasks {
+        t.scan(io);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn access_values() {
+        let inputs = vec![vec![ChannelValue::Bit(true)], vec![]];
+        let mut outputs = vec![vec![], vec![ChannelValue::Bit(false); 2]];
+        let mut io = ScanIo {
+            inputs: &inputs,
+            outputs: &mut outputs,
+            writable: &[false, true],
+        };
+        let addr = |module, channel| Address { module, channel };
+        assert_eq!(io.input(&addr(0, 0)), Some(&ChannelValue::Bit(true)));
+        assert_eq!(io.input(&addr(0, 1)), None);
+        io.set_output(&addr(1, 1), ChannelValue::Bit(true)).unwrap();
+        assert_eq!(io.output(&addr(1, 1)), Some(&ChannelValue::Bit(true)));
+        assert_eq!(
+            io.set_output(&addr(1, 2), ChannelValue::Bit(true)),
+            Err(Error::Address)
+        );
+        assert_eq!(
+            io.set_output(&addr(0, 0), ChannelValue::Bit(true)),
+            Err(Error::Address)
+        );
+    }
+}
@@ -0,0 +1,556 @@
+//! Modbus TCP client for the fieldbus coupler.
+//!
+//! This module is only available with the `tcp` feature.
+//!
+//! ```no_run
+//! let mut c = ur20::tcp::connect("192.168.0.222:502").unwrap();
+//! loop {
+//!     c.cycle().unwrap();
+//!     println!("{:?}", c.coupler().inputs());
+//! }
+//! ```
+
+use super::*;
+use crate::ur20_fbc_mod_tcp::*;
+use std::{
+    cmp,
+    io::{Read, Write},
+    net::{TcpStream, ToSocketAddrs},
+    time::Duration,
+};
+
+pub(crate) const READ_HOLDING_REGISTERS: u8 = 0x03;
+pub(crate) const WRITE_MULTIPLE_REGISTERS: u8 = 0x10;
+
+/// Maximum number of registers per read request.
+const MAX_READ_COUNT: usize = 125;
+/// Maximum number of registers per write request.
+const MAX_WRITE_COUNT: usize = 123;
+
+/// A minimal Modbus TCP client.
+#[derive(Debug)]
+pub struct Client<S = TcpStream> {
+    stream: S,
+    transaction_id: u16,
+    /// The Modbus unit identifier.
+    pub unit_id: u8,
+}
+
+impl Client<TcpStream> {
+    /// Connects to a Modbus TCP server.
+    pub fn connect<A: ToSocketAddrs>(addr: A, timeout: Duration) -> Result<Self> {
+        let stream = TcpStream::connect(addr)?;
+        stream.set_read_timeout(Some(timeout))?;
+        stream.set_write_timeout(Some(timeout))?;
+        stream.set_nodelay(true)?;
+        Ok(Client::new(stream))
+    }
+}
+
+impl<S: Read + Write> Client<S> {
+    pub fn new(stream: S) -> Self {
+        Client {
+            stream,
+            transaction_id: 0,
+            unit_id: 0xFF,
+        }
+    }
+
+    pub fn read_holding_registers(&mut self, addr: u16, count: usize) -> Result<Vec<u16>> {
+        let mut res = vec![];
+        while res.len() < count {
+            let cnt = cmp::min(count - res.len(), MAX_READ_COUNT) as u16;
+            let start = addr.wrapping_add(res.len() as u16);
+            let mut pdu = vec![READ_HOLDING_REGISTERS];
+            pdu.extend_from_slice(&start.to_be_bytes());
+            pdu.extend_from_slice(&cnt.to_be_bytes());
+            let data = self.transaction(&pdu)?;
+            if data.len() != 1 + cnt as usize * 2 || data[0] as usize != cnt as usize * 2 {
+                return Err(Error::DataLength);
+            }
+            res.extend(
+                data[1..]
+                    .chunks(2)
+                    .map(|x| u16::from_be_bytes([x[0], x[1]])),
+            );
+        }
+        Ok(res)
+    }
+
+    pub fn write_multiple_registers(&mut self, addr: u16, values: &[u16]) -> Result<()> {
+        for (i, chunk) in values.chunks(MAX_WRITE_COUNT).enumerate() {
+            let start = addr.wrapping_add((i * MAX_WRITE_COUNT) as u16);
+            let mut pdu = vec![WRITE_MULTIPLE_REGISTERS];
+            pdu.extend_from_slice(&start.to_be_bytes());
+            pdu.extend_from_slice(&(chunk.len() as u16).to_be_bytes());
+            pdu.push((chunk.len() * 2) as u8);
+            for v in chunk {
+                pdu.extend_from_slice(&v.to_be_bytes());
+            }
+            let data = self.transaction(&pdu)?;
+            if data != pdu[1..5] {
+                return Err(Error::DataLength);
+            }
+        }
+        Ok(())
+    }
+
+    /// Sends a request and returns the response data without function code.
+    fn transaction(&mut self, pdu: &[u8]) -> Result<Vec<u8>> {
+        self.transaction_id = self.transaction_id.wrapping_add(1);
+        let mut req = vec![];
+        req.extend_from_slice(&self.transaction_id.to_be_bytes());
+        req.extend_from_slice(&[0, 0]);
+        req.extend_from_slice(&(pdu.len() as u16 + 1).to_be_bytes());
+        req.push(self.unit_id);
+        req.extend_from_slice(pdu);
+        self.stream.write_all(&req)?;
+
+        let mut header = [0; 7];
+        self.stream.read_exact(&mut header)?;
+        let len = u16::from_be_bytes([header[4], header[5]]) as usize;
+        if len < 2 {
+            return Err(Error::DataLength);
+        }
+        let mut res = vec![0; len - 1];
+        self.stream.read_exact(&mut res)?;
+        if header[0..2] != self.transaction_id.to_be_bytes() || res[0] & 0x7F != pdu[0] {
+            return Err(Error::SequenceNumber);
+        }
+        if res[0] & 0x80 != 0 {
+            if res.len() < 2 {
+                return Err(Error::DataLength);
+            }
+            return Err(Error::io(format!("Modbus exception 0x{:02X}", res[1])));
+        }
+        res.remove(0);
+        Ok(res)
+    }
+}
+
+/// A coupler that is connected to a station.
+#[derive(Debug)]
+pub struct ConnectedCoupler<S = TcpStream> {
+    client: Client<S>,
+    coupler: Coupler,
+}
+
+/// Connects to a station and reads its configuration.
+pub fn connect<A: ToSocketAddrs>(addr: A) -> Result<ConnectedCoupler> {
+    let client = Client::connect(addr, Duration::from_secs(1))?;
+    ConnectedCoupler::new(client)
+}
+
+impl<S: Read + Write> ConnectedCoupler<S> {
+    /// Reads the module list, the offsets, the module parameters
+    /// and the current outputs and creates the coupler.
+    ///
+    /// Module IDs that only differ in their revision bits are accepted
+    /// and reported as `CouplerEvent::InexactModuleId`.
+    /// Stations with unsupported modules result in `Error::UnknownModule`.
+    pub fn new(mut client: Client<S>) -> Result<Self> {
+        let count = client.read_holding_registers(ADDR_CURRENT_MODULE_COUNT, 1)?[0] as usize;
+        let list = client.read_holding_registers(ADDR_CURRENT_MODULE_LIST, count * 2)?;
+        let list = module_list_from_registers_tolerant(&list)?;
+        let modules: Vec<_> = list.iter().map(|(m, _)| m.clone()).collect();
+        if modules.iter().any(|m| default_module(m).is_err()) {
+            return Err(Error::UnknownModule);
+        }
+        let offsets = client.read_holding_registers(ADDR_MODULE_OFFSETS, count * 2)?;
+        let mut params = vec![];
+        for (addr, cnt) in param_addresses_and_register_counts(&modules) {
+            if cnt == 0 {
+                params.push(vec![]);
+            } else {
+                params.push(client.read_holding_registers(addr, cnt as usize)?);
+            }
+        }
+        let mut coupler = Coupler::new(&CouplerConfig {
+            modules,
+            offsets,
+            params,
+        })?;
+        for (nr, _) in list.iter().enumerate().filter(|(_, (_, exact))| !exact) {
+            coupler.push_event(CouplerEvent::InexactModuleId(nr));
+        }
+        let mut c = ConnectedCoupler { client, coupler };
+        let output = c.read_outputs()?;
+        c.coupler.ingest_output_readback(&output)?;
+        Ok(c)
+    }
+
+    pub fn coupler(&self) -> &Coupler {
+        &self.coupler
+    }
+
+    pub fn coupler_mut(&mut self) -> &mut Coupler {
+        &mut self.coupler
+    }
+
+    /// Reads the process input data.
+    pub fn read_inputs(&mut self) -> Result<Vec<u16>> {
+        let len = self.coupler.process_input_len();
+        self.client
+            .read_holding_registers(ADDR_PACKED_PROCESS_INPUT_DATA, len)
+    }
+
+    /// Reads back the process output data.
+    pub fn read_outputs(&mut self) -> Result<Vec<u16>> {
+        let len = self.coupler.process_output_len();
+        self.client
+            .read_holding_registers(ADDR_PACKED_PROCESS_OUTPUT_DATA, len)
+    }
+
+    /// Writes the process output data.
+    pub fn write_outputs(&mut self, data: &[u16]) -> Result<()> {
+        self.client
+            .write_multiple_registers(ADDR_PACKED_PROCESS_OUTPUT_DATA, data)
+    }
+
+    /// Writes the registers of the modules that are owned
+    /// by the coupler (see `Coupler::owned_output_ranges`).
+    fn write_owned_outputs(&mut self, data: &[u16]) -> Result<()> {
+        for r in self.coupler.owned_output_ranges() {
+            let end = cmp::min(r.end, data.len());
+            if r.start < end {
+                self.client.write_multiple_registers(
+                    ADDR_PACKED_PROCESS_OUTPUT_DATA + r.start as u16,
+                    &data[r.start..end],
+                )?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Reads the compact digital input image and decodes it
+    /// without touching the state of the coupler.
+    pub fn read_compact_digital_inputs(&mut self) -> Result<Vec<Vec<ChannelValue>>> {
+        let img = self.coupler.compact_digital_image();
+        let data = self
+            .client
+            .read_holding_registers(ADDR_COMPACT_DIGITAL_INPUT_DATA, img.input_len())?;
+        img.decode_inputs(&data)
+    }
+
+    /// Reads the station-level parameters of the coupler.
+    pub fn read_coupler_parameters(&mut self) -> Result<CouplerParameters> {
+        let data = self
+            .client
+            .read_holding_registers(ADDR_COUPLER_PARAMETERS, COUPLER_PARAMETER_REGISTER_COUNT)?;
+        CouplerParameters::from_raw_data(&data)
+    }
+
+    /// Writes the station-level parameters of the coupler.
+    pub fn write_coupler_parameters(&mut self, params: &CouplerParameters) -> Result<()> {
+        self.client
+            .write_multiple_registers(ADDR_COUPLER_PARAMETERS, &params.to_raw_data())
+    }
+
+    /// Writes the shutdown plan (see `Coupler::shutdown_plan`)
+    /// and disables the fieldbus watchdog, so the station
+    /// doesn't report a fieldbus error after disconnecting.
+    pub fn shutdown(&mut self) -> Result<()> {
+        let output = self.coupler.shutdown_plan()?;
+        self.write_owned_outputs(&output)?;
+        let mut params = self.read_coupler_parameters()?;
+        if params.watchdog_timeout != 0 {
+            params.watchdog_timeout = 0;
+            self.write_coupler_parameters(&params)?;
+        }
+        Ok(())
+    }
+
+    /// Reads the inputs and the output readback, processes them
+    /// and writes the outputs.
+    ///
+    /// The result is recorded as driver state of the coupler
+    /// (see `Coupler::health`).
+    pub fn cycle(&mut self) -> Result<()> {
+        let res = self.transfer();
+        self.coupler
+            .set_driver_error(res.as_ref().err().map(ToString::to_string));
+        res
+    }
+
+    fn transfer(&mut self) -> Result<()> {
+        let input = self.read_inputs()?;
+        let readback = self.read_outputs()?;
+        let output = self.coupler.next(&input, &readback)?;
+        self.write_owned_outputs(&output)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use std::{collections::HashMap, io};
+
+    /// A Modbus TCP server with a register memory.
+    #[derive(Debug, Default)]
+    struct Station {
+        registers: HashMap<u16, u16>,
+        response: Vec<u8>,
+    }
+
+    impl Station {
+        fn set(&mut self, addr: u16, values: &[u16]) {
+            for (i, v) in values.iter().enumerate() {
+                self.registers.insert(addr + i as u16, *v);
+            }
+        }
+        fn get(&self, addr: u16) -> u16 {
+            *self.registers.get(&addr).unwrap_or(&0)
+        }
+    }
+
+    impl Write for Station {
+        fn write(&mut self, req: &[u8]) -> io::Result<usize> {
+            let addr = u16::from_be_bytes([req[8], req[9]]);
+            let cnt = u16::from_be_bytes([req[10], req[11]]);
+            let mut pdu = vec![req[7]];
+            match req[7] {
+                READ_HOLDING_REGISTERS => {
+                    pdu.push((cnt * 2) as u8);
+                    for i in 0..cnt {
+                        pdu.extend_from_slice(&self.get(addr + i).to_be_bytes());
+                    }
+                }
+                WRITE_MULTIPLE_REGISTERS => {
+                    let values: Vec<_> = req[13..]
+                        .chunks(2)
+                        .map(|x| u16::from_be_bytes([x[0], x[1]]))
+                        .collect();
+                    self.set(addr, &values);
+                    pdu.extend_from_slice(&req[8..12]);
+                }
+                _ => pdu = vec![req[7] | 0x80, 0x01],
+            }
+            self.response = req[0..4].to_vec();
+            self.response
+                .extend_from_slice(&(pdu.len() as u16 + 1).to_be_bytes());
+            self.response.push(req[6]);
+            self.response.extend(pdu);
+            Ok(req.len())
+        }
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl Read for Station {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let len = cmp::min(buf.len(), self.response.len());
+            buf[..len].copy_from_slice(&self.response[..len]);
+            self.response.drain(..len);
+            Ok(len)
+        }
+    }
+
+    fn station() -> Station {
+        let mut s = Station::default();
+        s.set(ADDR_CURRENT_MODULE_COUNT, &[2]);
+        s.set(ADDR_CURRENT_MODULE_LIST, &[0x0009, 0x1F84, 0x0101, 0x2FA0]);
+        s.set(ADDR_MODULE_OFFSETS, &[0xFFFF, 0x0000, 0x8000, 0xFFFF]);
+        s.set(ADDR_MODULE_PARAMETERS, &[0, 0, 0, 0]);
+        s.set(ADDR_MODULE_PARAMETERS + 256, &[0, 0, 0, 0]);
+        s.set(ADDR_PACKED_PROCESS_INPUT_DATA, &[0b0101]);
+        s
+    }
+
+    #[test]
+    fn read_and_write_registers() {
+        let mut c = Client::new(Station::default());
+        c.write_multiple_registers(0x0800, &[1, 2, 3]).unwrap();
+        assert_eq!(c.read_holding_registers(0x0801, 2).unwrap(), vec![2, 3]);
+        let values: Vec<u16> = (0..300).collect();
+        c.write_multiple_registers(0x1000, &values).unwrap();
+        assert_eq!(c.read_holding_registers(0x1000, 300).unwrap(), values);
+    }
+
+    #[test]
+    fn configure_coupler_and_run_cycles() {
+        let mut c = ConnectedCoupler::new(Client::new(station())).unwrap();
+        assert_eq!(
+            c.coupler().clone_config().modules,
+            vec![ModuleType::UR20_4DI_P, ModuleType::UR20_4DO_P]
+        );
+        c.coupler_mut()
+            .set_output(
+                &Address {
+                    module: 1,
+                    channel: 3,
+                },
+                ChannelValue::Bit(true),
+            )
+            .unwrap();
+        assert!(c.coupler().health() != StationHealth::Ok);
+        c.cycle().unwrap();
+        assert_eq!(c.coupler().health(), StationHealth::Ok);
+        assert_eq!(c.coupler().inputs()[0][2], ChannelValue::Bit(true));
+        assert_eq!(c.client.stream.get(ADDR_PACKED_PROCESS_OUTPUT_DATA), 0b1000);
+    }
+
+    #[test]
+    fn report_inexact_module_ids() {
+        let mut s = station();
+        s.set(ADDR_CURRENT_MODULE_LIST, &[0x0009, 0x1F84, 0x0101, 0x2FA3]);
+        let mut c = ConnectedCoupler::new(Client::new(s)).unwrap();
+        assert_eq!(
+            c.coupler().clone_config().modules,
+            vec![ModuleType::UR20_4DI_P, ModuleType::UR20_4DO_P]
+        );
+        assert_eq!(
+            c.coupler_mut().take_events(),
+            vec![CouplerEvent::InexactModuleId(1)]
+        );
+    }
+
+    #[test]
+    fn reject_unsupported_modules() {
+        let mut s = station();
+        let id = ModuleType::UR20_1CNT_500.to_u32().unwrap();
+        s.set(
+            ADDR_CURRENT_MODULE_LIST,
+            &[0x0009, 0x1F84, (id >> 16) as u16, id as u16],
+        );
+        assert_eq!(
+            ConnectedCoupler::new(Client::new(s)).err().unwrap(),
+            Error::UnknownModule
+        );
+    }
+
+    #[test]
+    fn detect_external_writers() {
+        let mut c = ConnectedCoupler::new(Client::new(station())).unwrap();
+        c.cycle().unwrap();
+        c.cycle().unwrap();
+        assert!(c.coupler_mut().take_events().is_empty());
+        c.client
+            .stream
+            .set(ADDR_PACKED_PROCESS_OUTPUT_DATA, &[0b0001]);
+        c.cycle().unwrap();
+        assert_eq!(
+            c.coupler_mut().take_events(),
+            vec![CouplerEvent::OutputMismatch { modules: vec![1] }]
+        );
+    }
+
+    #[test]
+    fn write_owned_outputs_only() {
+        let mut s = station();
+        s.set(ADDR_CURRENT_MODULE_COUNT, &[3]);
+        s.set(
+            ADDR_CURRENT_MODULE_LIST,
+            &[0x0009, 0x1F84, 0x0101, 0x2FA0, 0x0101, 0x2FA0],
+        );
+        s.set(
+            ADDR_MODULE_OFFSETS,
+            &[0xFFFF, 0x0000, 0x8000, 0xFFFF, 0x8008, 0xFFFF],
+        );
+        s.set(ADDR_MODULE_PARAMETERS + 512, &[0, 0, 0, 0]);
+        let mut c = ConnectedCoupler::new(Client::new(s)).unwrap();
+        c.coupler_mut().set_owned_modules(Some(vec![2])).unwrap();
+        let addr = Address {
+            module: 2,
+            channel: 0,
+        };
+        c.coupler_mut()
+            .set_output(&addr, ChannelValue::Bit(true))
+            .unwrap();
+        c.cycle().unwrap();
+        assert_eq!(c.client.stream.get(ADDR_PACKED_PROCESS_OUTPUT_DATA), 0x0100);
+        // another master switches on an output of module 1
+        c.client
+            .stream
+            .set(ADDR_PACKED_PROCESS_OUTPUT_DATA, &[0x0102]);
+        c.coupler_mut()
+            .set_safe_state(&addr, ChannelValue::Bit(false))
+            .unwrap();
+        c.cycle().unwrap();
+        assert_eq!(c.client.stream.get(ADDR_PACKED_PROCESS_OUTPUT_DATA), 0x0102);
+        c.shutdown().unwrap();
+        assert_eq!(c.client.stream.get(ADDR_PACKED_PROCESS_OUTPUT_DATA), 0x0002);
+    }
+
+    #[test]
+    fn read_compact_digital_inputs() {
+        let mut s = station();
+        s.set(ADDR_COMPACT_DIGITAL_INPUT_DATA, &[0b1000]);
+        let mut c = ConnectedCoupler::new(Client::new(s)).unwrap();
+        let values = c.read_compact_digital_inputs().unwrap();
+        assert_eq!(values[0][3], ChannelValue::Bit(true));
+        assert!(values[1].is_empty());
+    }
+
+    #[test]
+    fn read_and_write_coupler_parameters() {
+        let mut c = ConnectedCoupler::new(Client::new(station())).unwrap();
+        assert_eq!(
+            c.read_coupler_parameters().unwrap(),
+            CouplerParameters::default()
+        );
+        let params = CouplerParameters {
+            fault_behavior: FaultBehavior::HoldLastValue,
+            watchdog_timeout: 100,
+            ..Default::default()
+        };
+        c.write_coupler_parameters(&params).unwrap();
+        assert_eq!(c.client.stream.get(ADDR_COUPLER_PARAMETERS + 2), 1);
+        assert_eq!(c.read_coupler_parameters().unwrap(), params);
+    }
+
+    #[test]
+    fn shutdown_station() {
+        let mut s = station();
+        s.set(ADDR_PACKED_PROCESS_OUTPUT_DATA, &[0b0011]);
+        s.set(ADDR_COUPLER_PARAMETERS, &[0, 0, 0, 100]);
+        let mut c = ConnectedCoupler::new(Client::new(s)).unwrap();
+        c.coupler_mut()
+            .set_safe_state(
+                &Address {
+                    module: 1,
+                    channel: 2,
+                },
+                ChannelValue::Bit(true),
+            )
+            .unwrap();
+        c.shutdown().unwrap();
+        assert_eq!(c.client.stream.get(ADDR_PACKED_PROCESS_OUTPUT_DATA), 0b0100);
+        assert_eq!(c.read_coupler_parameters().unwrap().watchdog(), None);
+    }
+
+    #[test]
+    fn report_exceptions() {
+        let mut c = Client::new(Station::default());
+        assert_eq!(
+            c.transaction(&[0x2B, 0x0E, 0x01, 0x00, 0x00])
+                .err()
+                .unwrap(),
+            Error::io("Modbus exception 0x01")
+        );
+        // exception response without exception code
+        struct Reply(io::Cursor<Vec<u8>>);
+        impl Write for Reply {
+            fn write(&mut self, req: &[u8]) -> io::Result<usize> {
+                Ok(req.len())
+            }
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+        impl Read for Reply {
+            fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+                self.0.read(buf)
+            }
+        }
+        let reply = vec![0, 1, 0, 0, 0, 2, 0xFF, 0x83];
+        let mut c = Client::new(Reply(io::Cursor::new(reply)));
+        assert_eq!(
+            c.transaction(&[READ_HOLDING_REGISTERS, 0, 0, 0, 1])
+                .err()
+                .unwrap(),
+            Error::DataLength
+        );
+    }
+}
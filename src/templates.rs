@@ -0,0 +1,137 @@
+//! Station descriptions of common rack layouts.
+//!
+//! The templates use reasonable default parameters
+//! (e.g. `0..10V` in S7 format for analog channels).
+//! Adjust the module parameters as needed
+//! and create the coupler configuration with
+//! `StationDescription::coupler_config`.
+
+use super::*;
+use crate::station::{ModuleDescription, StationDescription};
+use num_traits::ToPrimitive;
+
+/// 8 DI, 8 DO, 4 AI and 4 AO.
+///
+/// Modules: 2 x UR20-4DI-P, 2 x UR20-4DO-P,
+/// UR20-4AI-UI-16-DIAG and UR20-4AO-UI-16-DIAG.
+pub fn starter_rack() -> StationDescription {
+    StationDescription {
+        modules: vec![
+            digital_input(),
+            digital_input(),
+            digital_output(),
+            digital_output(),
+            analog_input(),
+            analog_output(),
+        ],
+        virtual_channels: vec![],
+    }
+}
+
+/// 16 DI and 16 DO.
+///
+/// Modules: 4 x UR20-4DI-P and UR20-16DO-P.
+pub fn digital_rack() -> StationDescription {
+    StationDescription {
+        modules: vec![
+            digital_input(),
+            digital_input(),
+            digital_input(),
+            digital_input(),
+            ModuleDescription::new(ModuleType::UR20_16DO_P, vec![]),
+        ],
+        virtual_channels: vec![],
+    }
+}
+
+/// 8 AI and 8 AO.
+///
+/// Modules: 2 x UR20-4AI-UI-16-DIAG and 2 x UR20-4AO-UI-16-DIAG.
+pub fn analog_rack() -> StationDescription {
+    StationDescription {
+        modules: vec![
+            analog_input(),
+            analog_input(),
+            analog_output(),
+            analog_output(),
+        ],
+        virtual_channels: vec![],
+    }
+}
+
+/// UR20-4DI-P with an input delay of 3ms.
+fn digital_input() -> ModuleDescription {
+    let delay = InputDelay::ms3.to_u16().unwrap();
+    ModuleDescription::new(ModuleType::UR20_4DI_P, vec![delay; 4])
+}
+
+/// UR20-4DO-P without substitute values.
+fn digital_output() -> ModuleDescription {
+    ModuleDescription::new(ModuleType::UR20_4DO_P, vec![0; 4])
+}
+
+/// UR20-4AI-UI-16-DIAG with `0..10V` (S7) on all channels.
+fn analog_input() -> ModuleDescription {
+    let mut params = vec![FrequencySuppression::Disabled.to_u16().unwrap()];
+    for _ in 0..4 {
+        params.extend_from_slice(&[
+            0, // channel diagnostics
+            0, // diagnostics short circuit
+            0, // diagnostics line break
+            DataFormat::S7.to_u16().unwrap(),
+            AnalogUIRange::V0To10.to_u16().unwrap(),
+        ]);
+    }
+    ModuleDescription::new(ModuleType::UR20_4AI_UI_16_DIAG, params)
+}
+
+/// UR20-4AO-UI-16-DIAG with `0..10V` (S7) on all channels.
+fn analog_output() -> ModuleDescription {
+    let mut params = vec![];
+    for _ in 0..4 {
+        params.extend_from_slice(&[
+            DataFormat::S7.to_u16().unwrap(),
+            AnalogUIRange::V0To10.to_u16().unwrap(),
+            0, // substitute value
+            0, // channel diagnostics
+        ]);
+    }
+    ModuleDescription::new(ModuleType::UR20_4AO_UI_16_DIAG, params)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::ur20_fbc_mod_tcp::{Coupler, ModbusParameterRegisterCount};
+
+    #[test]
+    fn create_couplers_from_templates() {
+        for t in &[starter_rack(), digital_rack(), analog_rack()] {
+            for m in &t.modules {
+                assert_eq!(
+                    m.params.len(),
+                    m.module_type.param_register_count() as usize
+                );
+            }
+            let offsets = vec![0xFFFF; t.modules.len() * 2];
+            assert!(Coupler::new(&t.coupler_config(offsets)).is_ok());
+        }
+    }
+
+    #[test]
+    fn channel_counts_of_starter_rack() {
+        let rack = starter_rack();
+        let count = |cat: ModuleCategory| -> usize {
+            rack.modules
+                .iter()
+                .filter(|m| ModuleCategory::from(m.module_type.clone()) == cat)
+                .map(|m| m.module_type.channel_count())
+                .sum()
+        };
+        assert_eq!(count(ModuleCategory::DI), 8);
+        assert_eq!(count(ModuleCategory::DO), 8);
+        assert_eq!(count(ModuleCategory::AI), 4);
+        assert_eq!(count(ModuleCategory::AO), 4);
+    }
+}
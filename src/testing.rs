@@ -0,0 +1,180 @@
+//! Test doubles for code that works with modules.
+//!
+//! ```rust
+//! # use ur20::{testing::*, ur20_fbc_mod_tcp::ProcessModbusTcpData, ChannelValue, ModuleType};
+//! let m = MockModule::new(ModuleType::UR20_4DI_P, 1, 0);
+//! m.push_input(Ok(vec![ChannelValue::Bit(true); 4]));
+//!
+//! let module: &dyn ProcessModbusTcpData = &m;
+//! assert_eq!(module.process_input_data(&[1]).unwrap()[0], ChannelValue::Bit(true));
+//! assert_eq!(m.calls(), vec![MockCall::ProcessInputData(vec![1])]);
+//! ```
+
+use super::*;
+use crate::ur20_fbc_mod_tcp::{ProcessModbusTcpData, ToModbusParameterData};
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex},
+};
+
+/// A recorded call of a `MockModule`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum MockCall {
+    ProcessInputData(Vec<u16>),
+    ProcessOutputData(Vec<u16>),
+    ProcessOutputValues(Vec<ChannelValue>),
+}
+
+#[derive(Debug, Default)]
+struct MockState {
+    inputs: VecDeque<Result<Vec<ChannelValue>>>,
+    outputs: VecDeque<Result<Vec<ChannelValue>>>,
+    output_values: VecDeque<Result<Vec<u16>>>,
+    calls: Vec<MockCall>,
+}
+
+/// A module with scripted results that records all calls.
+///
+/// Each call returns the next queued result of its kind.
+/// If the queue is empty, a neutral result is returned
+/// (`ChannelValue::None` for each channel or zeroed registers).
+///
+/// Clones share the same state, so a clone can be kept
+/// to inspect the calls after the module was handed over.
+#[derive(Debug, Clone)]
+pub struct MockModule {
+    module_type: ModuleType,
+    input_byte_count: usize,
+    output_byte_count: usize,
+    state: Arc<Mutex<MockState>>,
+}
+
+impl MockModule {
+    pub fn new(module_type: ModuleType, input_byte_count: usize, output_byte_count: usize) -> Self {
+        MockModule {
+            module_type,
+            input_byte_count,
+            output_byte_count,
+            state: Arc::new(Mutex::new(MockState::default())),
+        }
+    }
+
+    /// Queues a result of `process_input_data`.
+    pub fn push_input(&self, res: Result<Vec<ChannelValue>>) {
+        self.state().inputs.push_back(res);
+    }
+
+    /// Queues a result of `process_output_data`.
+    pub fn push_output(&self, res: Result<Vec<ChannelValue>>) {
+        self.state().outputs.push_back(res);
+    }
+
+    /// Queues a result of `process_output_values`.
+    pub fn push_output_values(&self, res: Result<Vec<u16>>) {
+        self.state().output_values.push_back(res);
+    }
+
+    /// Returns all calls in the order they were made.
+    pub fn calls(&self) -> Vec<MockCall> {
+        self.state().calls.clone()
+    }
+
+    fn state(&self) -> std::sync::MutexGuard<'_, MockState> {
+        // A panicking test must not hide the recorded calls.
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn none(&self) -> Vec<ChannelValue> {
+        vec![ChannelValue::None; self.module_type.channel_count()]
+    }
+}
+
+impl Module for MockModule {
+    fn module_type(&self) -> ModuleType {
+        self.module_type.clone()
+    }
+}
+
+/// Mock modules have no parameters.
+impl ToModbusParameterData for MockModule {
+    fn to_modbus_parameter_data(&self) -> Vec<u16> {
+        vec![]
+    }
+}
+
+impl ProcessModbusTcpData for MockModule {
+    fn process_input_byte_count(&self) -> usize {
+        self.input_byte_count
+    }
+    fn process_output_byte_count(&self) -> usize {
+        self.output_byte_count
+    }
+    fn process_input_data(&self, data: &[u16]) -> Result<Vec<ChannelValue>> {
+        let mut s = self.state();
+        s.calls.push(MockCall::ProcessInputData(data.to_vec()));
+        s.inputs.pop_front().unwrap_or_else(|| Ok(self.none()))
+    }
+    fn process_output_data(&self, data: &[u16]) -> Result<Vec<ChannelValue>> {
+        let mut s = self.state();
+        s.calls.push(MockCall::ProcessOutputData(data.to_vec()));
+        s.outputs.pop_front().unwrap_or_else(|| Ok(self.none()))
+    }
+    fn process_output_values(&self, values: &[ChannelValue]) -> Result<Vec<u16>> {
+        let mut s = self.state();
+        s.calls.push(MockCall::ProcessOutputValues(values.to_vec()));
+        s.output_values
+            .pop_front()
+            .unwrap_or_else(|| Ok(vec![0; self.output_byte_count.div_ceil(2)]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn return_queued_results_in_order() {
+        let m = MockModule::new(ModuleType::UR20_4DO_P, 0, 1);
+        m.push_output_values(Ok(vec![0b0101]));
+        m.push_output_values(Err(Error::ChannelValue));
+        let values = vec![ChannelValue::Bit(true); 4];
+        assert_eq!(m.process_output_values(&values).unwrap(), vec![0b0101]);
+        assert_eq!(
+            m.process_output_values(&values).err(),
+            Some(Error::ChannelValue)
+        );
+        assert_eq!(m.process_output_values(&values).unwrap(), vec![0]);
+    }
+
+    #[test]
+    fn return_neutral_results_without_queued_ones() {
+        let m = MockModule::new(ModuleType::UR20_4DI_P, 1, 0);
+        assert_eq!(
+            m.process_input_data(&[0]).unwrap(),
+            vec![ChannelValue::None; 4]
+        );
+        assert_eq!(
+            m.process_output_data(&[]).unwrap(),
+            vec![ChannelValue::None; 4]
+        );
+    }
+
+    #[test]
+    fn record_calls_of_all_clones() {
+        let m = MockModule::new(ModuleType::UR20_4DI_P, 1, 0);
+        let boxed: Box<dyn ProcessModbusTcpData> = Box::new(m.clone());
+        m.push_output(Ok(vec![]));
+        boxed.process_input_data(&[3]).unwrap();
+        boxed.process_output_data(&[]).unwrap();
+        assert_eq!(
+            m.calls(),
+            vec![
+                MockCall::ProcessInputData(vec![3]),
+                MockCall::ProcessOutputData(vec![]),
+            ]
+        );
+        assert_eq!(boxed.process_input_byte_count(), 1);
+        assert_eq!(boxed.module_type(), ModuleType::UR20_4DI_P);
+    }
+}
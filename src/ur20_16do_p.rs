@@ -1,17 +1,34 @@
 //! Digital output module UR20-16DO-P
+//!
+//! The module is implemented by `ur20_do_generic`;
+//! this type is kept for backward compatibility.
 
 use super::*;
-use crate::ur20_fbc_mod_tcp::{FromModbusParameterData, ProcessModbusTcpData};
-use crate::util::*;
+use crate::ur20_fbc_mod_tcp::{
+    FromModbusParameterData, ProcessDataLayout, ProcessModbusTcpData, ToModbusParameterData,
+};
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Mod;
 
+impl Mod {
+    fn generic(&self) -> ur20_do_generic::Mod {
+        ur20_do_generic::Mod {
+            module_type: ModuleType::UR20_16DO_P,
+            ch_params: vec![],
+        }
+    }
+}
+
+impl From<Mod> for ur20_do_generic::Mod {
+    fn from(m: Mod) -> Self {
+        m.generic()
+    }
+}
+
 impl FromModbusParameterData for Mod {
     fn from_modbus_parameter_data(data: &[u16]) -> Result<Mod> {
-        if !data.is_empty() {
-            return Err(Error::BufferLength);
-        }
+        ur20_do_generic::Mod::from_modbus_parameter_data(ModuleType::UR20_16DO_P, data)?;
         Ok(Mod)
     }
 }
@@ -28,6 +45,12 @@ impl Module for Mod {
     }
 }
 
+impl ToModbusParameterData for Mod {
+    fn to_modbus_parameter_data(&self) -> Vec<u16> {
+        vec![]
+    }
+}
+
 impl ProcessModbusTcpData for Mod {
     fn process_input_byte_count(&self) -> usize {
         0
@@ -35,36 +58,14 @@ impl ProcessModbusTcpData for Mod {
     fn process_output_byte_count(&self) -> usize {
         2
     }
+    fn layout(&self) -> ProcessDataLayout {
+        self.generic().layout()
+    }
     fn process_output_data(&self, data: &[u16]) -> Result<Vec<ChannelValue>> {
-        if data.len() != 1 {
-            return Err(Error::BufferLength);
-        }
-        Ok((0..16)
-            .map(|i| test_bit_16(data[0], i))
-            .map(ChannelValue::Bit)
-            .collect())
+        self.generic().process_output_data(data)
     }
     fn process_output_values(&self, values: &[ChannelValue]) -> Result<Vec<u16>> {
-        if values.len() != 16 {
-            return Err(Error::ChannelValue);
-        }
-        let mut res = 0;
-        for (i, v) in values.iter().enumerate() {
-            match *v {
-                ChannelValue::Bit(state) => {
-                    if state {
-                        res = set_bit_16(res, i);
-                    }
-                }
-                ChannelValue::Disabled => {
-                    // do nothing
-                }
-                _ => {
-                    return Err(Error::ChannelValue);
-                }
-            }
-        }
-        Ok(vec![res])
+        self.generic().process_output_values(values)
     }
 }
 
@@ -76,22 +77,20 @@ mod tests {
 
     #[test]
     fn test_process_output_values_with_invalid_channel_len() {
-        let m = Mod::default();
-        assert!(m.process_output_values(&[]).is_err());
+        let m = Mod;
+        assert_eq!(m.process_output_values(&[]).unwrap(), vec![0]);
         assert!(m.process_output_values(&vec![Bit(true); 15]).is_err());
         assert!(m.process_output_values(&vec![Bit(true); 16]).is_ok());
     }
 
     #[test]
     fn test_process_output_data() {
-        let m = Mod::default();
+        let m = Mod;
         assert_eq!(
-            m.process_output_data(&vec![0xFFFF]).unwrap(),
+            m.process_output_data(&[0xFFFF]).unwrap(),
             vec![ChannelValue::Bit(true); 16]
         );
-        let res = m
-            .process_output_data(&vec![0b_0010_0001_0010_0101])
-            .unwrap();
+        let res = m.process_output_data(&[0b_0010_0001_0010_0101]).unwrap();
         assert_eq!(res[0], ChannelValue::Bit(true));
         assert_eq!(res[1], ChannelValue::Bit(false));
         assert_eq!(res[5], ChannelValue::Bit(true));
@@ -101,19 +100,19 @@ mod tests {
 
     #[test]
     fn test_process_output_data_with_invalid_buffer_size() {
-        let m = Mod::default();
-        assert!(m.process_output_data(&vec![0; 2]).is_err());
+        let m = Mod;
+        assert!(m.process_output_data(&[0; 2]).is_err());
     }
 
     #[test]
     fn test_process_output_values_with_invalid_channel_values() {
-        let m = Mod::default();
+        let m = Mod;
         assert!(m.process_output_values(&vec![Decimal32(0.0); 16]).is_err());
     }
 
     #[test]
     fn test_process_output_values() {
-        let m = Mod::default();
+        let m = Mod;
         let mut vals = vec![Bit(false); 16];
         vals[0] = Bit(true);
         vals[2] = Bit(true);
@@ -136,7 +135,7 @@ mod tests {
 
     #[test]
     fn module_type() {
-        let m = Mod::default();
+        let m = Mod;
         assert_eq!(m.module_type(), ModuleType::UR20_16DO_P);
     }
 
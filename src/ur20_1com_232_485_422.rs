@@ -2,22 +2,27 @@
 
 use super::*;
 use crate::{
-    ur20_fbc_mod_tcp::{FromModbusParameterData, ProcessModbusTcpData},
+    ur20_fbc_mod_tcp::{
+        FromModbusParameterData, ProcessDataLayout, ProcessModbusTcpData, ToModbusParameterData,
+    },
     util::*,
 };
-use num_traits::cast::FromPrimitive;
+use num_traits::cast::{FromPrimitive, ToPrimitive};
 use std::{
     cmp,
     io::{self, Read, Write},
+    iter,
+    ops::Range,
+    time::Instant,
 };
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Mod {
     pub mod_params: ModuleParameters,
     pub ch_params: Vec<ChannelParameters>,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
 pub struct ProcessInput {
     /// Indicates if there is a telegramm in the receive buffer or not.
     pub data_available: bool,
@@ -39,7 +44,7 @@ pub struct ProcessInput {
     pub data: Vec<u8>,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
 pub struct ProcessOutput {
     /// This flag controls whether the receive buffer will be cleared
     /// or not.
@@ -50,10 +55,10 @@ pub struct ProcessOutput {
     /// This flag controls the hardware transmit buffer:
     ///
     /// - `false`:  The hardware transmit buffer is released.
-    ///             A character will be sent as soon as it reaches the buffer.
+    ///   A character will be sent as soon as it reaches the buffer.
     /// - `true`:   The hardware transmit buffer is locked.
-    ///             Characters will only be sent, when the flag is set to
-    ///             `false` again.
+    ///   Characters will only be sent, when the flag is set to
+    ///   `false` again.
     pub disable_tx_hw_buffer: bool,
     /// The transmitting sequence number.
     /// The sequence is: 0,1,2,3,0,...
@@ -70,13 +75,13 @@ pub struct ProcessOutput {
 }
 
 #[allow(non_snake_case)]
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct ModuleParameters {
     pub process_data_len: ProcessDataLength,
 }
 
 #[allow(non_snake_case)]
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct ChannelParameters {
     pub operating_mode: OperatingMode,
     pub data_bits: DataBits,
@@ -90,7 +95,7 @@ pub struct ChannelParameters {
 }
 
 #[allow(non_camel_case_types)]
-#[derive(Debug, Clone, PartialEq, Eq, FromPrimitive, ToPrimitive)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, FromPrimitive, ToPrimitive)]
 pub enum OperatingMode {
     Disabled = 0,
     RS232 = 1,
@@ -98,7 +103,7 @@ pub enum OperatingMode {
     RS422 = 3,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, FromPrimitive, ToPrimitive)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, FromPrimitive, ToPrimitive)]
 pub enum DataBits {
     SevenBits = 0,
     EightBits = 1,
@@ -106,7 +111,7 @@ pub enum DataBits {
 
 #[rustfmt::skip]
 #[allow(non_camel_case_types)]
-#[derive(Debug, Clone, PartialEq, Eq,FromPrimitive, ToPrimitive)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, FromPrimitive, ToPrimitive)]
 pub enum BaudRate {
     Baud_300    = 0,
     Baud_600    = 1,
@@ -123,14 +128,14 @@ pub enum BaudRate {
 }
 
 #[rustfmt::skip]
-#[derive(Debug, Clone, PartialEq,Eq,FromPrimitive, ToPrimitive)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, FromPrimitive, ToPrimitive)]
 pub enum StopBit {
     OneBit  = 0,
     TwoBits = 1,
 }
 
 #[rustfmt::skip]
-#[derive(Debug, Clone, PartialEq, Eq, FromPrimitive, ToPrimitive)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, FromPrimitive, ToPrimitive)]
 pub enum Parity {
     None = 0,
     Even = 1,
@@ -139,7 +144,7 @@ pub enum Parity {
 
 #[rustfmt::skip]
 #[allow(non_camel_case_types)]
-#[derive(Debug, Clone, PartialEq,Eq,FromPrimitive, ToPrimitive)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, FromPrimitive, ToPrimitive)]
 pub enum FlowControl {
     None     = 0,
     CTS_RTS  = 1,
@@ -147,7 +152,7 @@ pub enum FlowControl {
 }
 
 #[rustfmt::skip]
-#[derive(Debug, Clone, PartialEq, Eq, FromPrimitive, ToPrimitive)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, FromPrimitive, ToPrimitive)]
 pub enum ProcessDataLength {
     EightBytes   = 0,
     SixteenBytes = 1,
@@ -163,6 +168,13 @@ impl FromModbusParameterData for Mod {
     }
 }
 
+impl ToModbusParameterData for Mod {
+    fn to_modbus_parameter_data(&self) -> Vec<u16> {
+        let p = self.ch_params.first().cloned().unwrap_or_default();
+        parameters_to_raw_data(&self.mod_params, &p)
+    }
+}
+
 impl ProcessInput {
     pub fn try_from_byte_message(bytes: &[u8]) -> Result<Self> {
         if bytes.len() < 2 {
@@ -189,33 +201,6 @@ impl ProcessInput {
     }
 }
 
-impl Default for ProcessInput {
-    fn default() -> Self {
-        ProcessInput {
-            data_available: false,
-            buffer_nearly_full: false,
-            rx_cnt: 0,
-            tx_cnt_ack: 0,
-            ready: false,
-            data: vec![],
-        }
-    }
-}
-
-impl Default for ProcessOutput {
-    fn default() -> Self {
-        ProcessOutput {
-            rx_buf_flush: false,
-            tx_buf_flush: false,
-            disable_tx_hw_buffer: false,
-            tx_cnt: 0,
-            rx_cnt_ack: 0,
-            reset: false,
-            data: vec![],
-        }
-    }
-}
-
 impl ProcessDataLength {
     pub fn user_data_len(&self) -> usize {
         use self::ProcessDataLength::*;
@@ -352,6 +337,25 @@ impl ProcessModbusTcpData for Mod {
             ProcessDataLength::SixteenBytes => 16,
         }
     }
+    fn layout(&self) -> ProcessDataLayout {
+        let words = |first: &str| {
+            let mut res = vec![format!("{} (low byte), data length (high byte)", first)];
+            for i in 1..self.process_input_byte_count() / 2 {
+                res.push(format!("data bytes {} - {}", i * 2 - 2, i * 2 - 1));
+            }
+            res
+        };
+        ProcessDataLayout {
+            input: words("status"),
+            output: words("control"),
+        }
+    }
+    fn process_input_payload(&self) -> Vec<Range<usize>> {
+        iter::once(2..self.process_input_byte_count()).collect()
+    }
+    fn process_output_payload(&self) -> Vec<Range<usize>> {
+        iter::once(2..self.process_output_byte_count()).collect()
+    }
     fn process_input_data(&self, data: &[u16]) -> Result<Vec<ChannelValue>> {
         let buf: Vec<u8> = data.iter().fold(vec![], |mut x, elem| {
             x.push((elem & 0xff) as u8);
@@ -371,9 +375,7 @@ impl ProcessModbusTcpData for Mod {
         Ok(vec![ChannelValue::ComRsOut(current_output)])
     }
     fn process_output_values(&self, values: &[ChannelValue]) -> Result<Vec<u16>> {
-        if values.len() != 1 {
-            return Err(Error::ChannelValue);
-        }
+        let values = util::output_values(self, values)?;
         match values[0] {
             ChannelValue::ComRsOut(ref current_output) => {
                 let count = self.mod_params.process_data_len.user_data_len();
@@ -384,6 +386,9 @@ impl ProcessModbusTcpData for Mod {
                     current_output.try_into_byte_message(&self.mod_params.process_data_len)?;
                 Ok(u8_to_u16(&msg))
             }
+            ChannelValue::Disabled | ChannelValue::None => {
+                Ok(vec![0; self.process_output_byte_count() / 2])
+            }
             _ => Err(Error::ChannelValue),
         }
     }
@@ -410,6 +415,21 @@ fn cnt_ack_to_status_byte(cnt: usize, mut byte: u8) -> u8 {
     byte
 }
 
+/// A source of the current time.
+pub trait Clock: Debug {
+    fn now(&self) -> Instant;
+}
+
+/// The system clock.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+}
+
 #[derive(Debug)]
 pub struct MessageProcessor {
     init_state: InitState,
@@ -592,6 +612,21 @@ fn parameters_from_raw_data(data: &[u16]) -> Result<(ModuleParameters, ChannelPa
     Ok((mod_params, p))
 }
 
+fn parameters_to_raw_data(mod_params: &ModuleParameters, p: &ChannelParameters) -> Vec<u16> {
+    vec![
+        mod_params.process_data_len.to_u16().unwrap(),
+        p.operating_mode.to_u16().unwrap(),
+        p.baud_rate.to_u16().unwrap(),
+        p.stop_bit.to_u16().unwrap(),
+        p.parity.to_u16().unwrap(),
+        p.flow_control.to_u16().unwrap(),
+        p.data_bits.to_u16().unwrap(),
+        u16::from(p.terminating_resistor),
+        p.XON_char as u16,
+        p.XOFF_char as u16,
+    ]
+}
+
 #[cfg(test)]
 mod tests {
 
@@ -601,21 +636,21 @@ mod tests {
     fn try_process_input_data_data_from_empty_byte_message() {
         let byte_msg = vec![0, 0];
         let msg = ProcessInput::try_from_byte_message(&byte_msg).unwrap();
-        assert_eq!(msg.data_available, false);
-        assert_eq!(msg.buffer_nearly_full, false);
+        assert!(!msg.data_available);
+        assert!(!msg.buffer_nearly_full);
         assert_eq!(msg.rx_cnt, 0);
         assert_eq!(msg.tx_cnt_ack, 0);
-        assert_eq!(msg.ready, false);
-        assert_eq!(msg.data, vec![]);
+        assert!(!msg.ready);
+        assert_eq!(msg.data, Vec::<u8>::new());
     }
 
     #[test]
     fn try_process_input_data_data_from_invalid_byte_message() {
-        let too_small_err = ProcessInput::try_from_byte_message(&vec![0]).err().unwrap();
-        let missmatched_len_err = ProcessInput::try_from_byte_message(&vec![0, 5, 0])
+        let too_small_err = ProcessInput::try_from_byte_message(&[0]).err().unwrap();
+        let missmatched_len_err = ProcessInput::try_from_byte_message(&[0, 5, 0])
             .err()
             .unwrap();
-        let ok_res = ProcessInput::try_from_byte_message(&vec![0, 5, 0, 0, 0, 0, 0]);
+        let ok_res = ProcessInput::try_from_byte_message(&[0, 5, 0, 0, 0, 0, 0]);
         assert_eq!(too_small_err, Error::BufferLength);
         assert_eq!(missmatched_len_err, Error::BufferLength);
         assert!(ok_res.is_ok());
@@ -625,11 +660,11 @@ mod tests {
     fn try_process_input_data_data_from_valid_byte_message() {
         let byte_msg = vec![0b_1111_0001, 3, 0x0, 0xf, 0x5];
         let msg = ProcessInput::try_from_byte_message(&byte_msg).unwrap();
-        assert_eq!(msg.data_available, true);
-        assert_eq!(msg.buffer_nearly_full, false);
+        assert!(msg.data_available);
+        assert!(!msg.buffer_nearly_full);
         assert_eq!(msg.rx_cnt, 2);
         assert_eq!(msg.tx_cnt_ack, 3);
-        assert_eq!(msg.ready, true);
+        assert!(msg.ready);
         assert_eq!(msg.data, vec![0, 15, 5]);
     }
 
@@ -724,19 +759,19 @@ mod tests {
     fn try_process_output_from_valid_byte_message() {
         let byte_msg = vec![0b01011010, 3, 0x0, 0xe, 0x7];
         let msg = ProcessOutput::try_from_byte_message(&byte_msg).unwrap();
-        assert_eq!(msg.rx_buf_flush, false);
-        assert_eq!(msg.tx_buf_flush, true);
-        assert_eq!(msg.disable_tx_hw_buffer, false);
+        assert!(!msg.rx_buf_flush);
+        assert!(msg.tx_buf_flush);
+        assert!(!msg.disable_tx_hw_buffer);
         assert_eq!(msg.tx_cnt, 3);
         assert_eq!(msg.rx_cnt_ack, 2);
-        assert_eq!(msg.reset, false);
+        assert!(!msg.reset);
         assert_eq!(msg.data, vec![0, 14, 7]);
     }
 
     #[test]
     fn test_process_input_data_with_empty_buffer() {
         let m = Mod::default();
-        assert!(m.process_input_data(&vec![]).is_err());
+        assert!(m.process_input_data(&[]).is_err());
     }
 
     #[test]
@@ -746,12 +781,12 @@ mod tests {
         let values = m.process_output_data(&data).unwrap();
         assert_eq!(values.len(), 1);
         if let ChannelValue::ComRsOut(ref out) = values[0] {
-            assert_eq!(out.rx_buf_flush, false);
-            assert_eq!(out.tx_buf_flush, true);
-            assert_eq!(out.disable_tx_hw_buffer, false);
+            assert!(!out.rx_buf_flush);
+            assert!(out.tx_buf_flush);
+            assert!(!out.disable_tx_hw_buffer);
             assert_eq!(out.tx_cnt, 3);
             assert_eq!(out.rx_cnt_ack, 2);
-            assert_eq!(out.reset, false);
+            assert!(!out.reset);
             assert_eq!(out.data, vec![0, 14, 7]);
         } else {
             panic!("wrong channel data");
@@ -761,13 +796,13 @@ mod tests {
     #[test]
     fn test_process_output_data_with_empty_buffer() {
         let m = Mod::default();
-        assert!(m.process_output_data(&vec![]).is_err());
+        assert!(m.process_output_data(&[]).is_err());
     }
 
     #[test]
     fn test_process_input_data_with_valid_input_data() {
         let m = Mod::default();
-        let result = m.process_input_data(&vec![0x0600, 0, 0xABCD, 0]).unwrap();
+        let result = m.process_input_data(&[0x0600, 0, 0xABCD, 0]).unwrap();
         if let ChannelValue::ComRsIn(ref msg) = result[0] {
             assert_eq!(msg.data, vec![0, 0, 0xCD, 0xAB, 0, 0]);
         } else {
@@ -778,11 +813,14 @@ mod tests {
     #[test]
     fn test_process_output_values_with_invalid_input_len() {
         let m = Mod::default();
-        assert!(m.process_output_values(&vec![]).is_err());
+        assert_eq!(
+            m.process_output_values(&[]).unwrap(),
+            vec![0; m.process_output_byte_count() / 2]
+        );
         assert!(m
-            .process_output_values(&vec![
-                ChannelValue::ComRsIn(ProcessInput::default()),
+            .process_output_values(&[
                 ChannelValue::ComRsIn(ProcessInput::default()),
+                ChannelValue::ComRsIn(ProcessInput::default())
             ])
             .is_err());
     }
@@ -791,7 +829,7 @@ mod tests {
     fn test_process_output_values_with_invalid_channel_data() {
         let m = Mod::default();
         assert!(m
-            .process_output_values(&vec![ChannelValue::Decimal32(0.0)])
+            .process_output_values(&[ChannelValue::Decimal32(0.0)])
             .is_err());
     }
 
@@ -814,28 +852,28 @@ mod tests {
         five.data = vec![0, 5];
 
         assert!(m
-            .process_output_values(&vec![ChannelValue::ComRsOut(five)])
+            .process_output_values(&[ChannelValue::ComRsOut(five)])
             .is_ok());
 
         assert!(m
-            .process_output_values(&vec![ChannelValue::ComRsOut(fourteen.clone())])
+            .process_output_values(&[ChannelValue::ComRsOut(fourteen.clone())])
             .is_ok());
 
         assert!(m
-            .process_output_values(&vec![ChannelValue::ComRsOut(fifteen.clone())])
+            .process_output_values(&[ChannelValue::ComRsOut(fifteen.clone())])
             .is_err());
 
         m.mod_params.process_data_len = ProcessDataLength::EightBytes;
         assert!(m
-            .process_output_values(&vec![ChannelValue::ComRsOut(fourteen)])
+            .process_output_values(&[ChannelValue::ComRsOut(fourteen)])
             .is_err());
 
         assert!(m
-            .process_output_values(&vec![ChannelValue::ComRsOut(seven.clone())])
+            .process_output_values(&[ChannelValue::ComRsOut(seven.clone())])
             .is_err());
 
         assert!(m
-            .process_output_values(&vec![ChannelValue::ComRsOut(six.clone())])
+            .process_output_values(&[ChannelValue::ComRsOut(six.clone())])
             .is_ok());
     }
 
@@ -846,7 +884,7 @@ mod tests {
         let mut out = ProcessOutput::default();
         out.data = vec![0x0A, 0x0B, 0, 0x0C];
         let res = m
-            .process_output_values(&vec![ChannelValue::ComRsOut(out)])
+            .process_output_values(&[ChannelValue::ComRsOut(out)])
             .unwrap();
         assert_eq!(res.len(), 8);
         assert_eq!(res, vec![0x0400, 0x0B0A, 0x0C00, 0, 0, 0, 0, 0]);
@@ -862,7 +900,7 @@ mod tests {
 
         // 2. first read
         input.ready = true;
-        assert_eq!(input.data_available, false);
+        assert!(!input.data_available);
 
         // 3. first write
         // There is no data to send, and nothing to receive
@@ -870,13 +908,13 @@ mod tests {
         assert_eq!(p.next(&input, &output), output);
 
         // 4. write data to processor buffer
-        p.write(b"This msg is >6 bytes").unwrap();
+        p.write_all(b"This msg is >6 bytes").unwrap();
 
         // 5. read
         // We assume that there is still no data to receive
         // and nothing was send.
         assert_eq!(input.tx_cnt_ack, 0);
-        assert_eq!(input.data_available, false);
+        assert!(!input.data_available);
 
         // 6. write
         // Now that there is data to transmit the transmission
@@ -936,7 +974,7 @@ mod tests {
         let mut output = ProcessOutput::default();
 
         input.ready = true;
-        p.write(b"This msg is >14 bytes").unwrap();
+        p.write_all(b"This msg is >14 bytes").unwrap();
         output = p.next(&input, &output);
         assert_eq!(output.data, b"This msg is >1");
         assert_eq!(output.tx_cnt, 1);
@@ -952,24 +990,24 @@ mod tests {
         let mut output = ProcessOutput::default();
 
         input.ready = true;
-        p.write(b"This msg is >14 bytes").unwrap();
+        p.write_all(b"This msg is >14 bytes").unwrap();
 
         assert_eq!(p.init_state, InitState::ClearBuffers);
 
         output = p.next(&input, &output);
 
-        assert_eq!(output.rx_buf_flush, true);
-        assert_eq!(output.tx_buf_flush, true);
-        assert_eq!(output.reset, false);
+        assert!(output.rx_buf_flush);
+        assert!(output.tx_buf_flush);
+        assert!(!output.reset);
         assert_eq!(p.init_state, InitState::Reset);
 
         output = p.next(&input, &output);
-        assert_eq!(output.rx_buf_flush, false);
-        assert_eq!(output.tx_buf_flush, false);
-        assert_eq!(output.reset, true);
+        assert!(!output.rx_buf_flush);
+        assert!(!output.tx_buf_flush);
+        assert!(output.reset);
         assert_eq!(p.init_state, InitState::Done);
         assert_eq!(p.last_rx_cnt, 4);
-        assert_eq!(output.data, vec![]);
+        assert_eq!(output.data, Vec::<u8>::new());
         assert_eq!(output.tx_cnt, 0);
 
         output = p.next(&input, &output);
@@ -986,7 +1024,7 @@ mod tests {
         let mut buf = vec![0; 11];
 
         input.ready = true;
-        assert_eq!(input.data_available, false);
+        assert!(!input.data_available);
         output = p.next(&input, &output);
         assert_eq!(p.read(&mut buf).unwrap(), 0);
         assert_eq!(buf, vec![0; 11]);
@@ -1057,7 +1095,7 @@ mod tests {
             output.tx_cnt = cnt;
             output = p.next(&input, &output);
             assert_eq!(output.tx_cnt, cnt_next);
-            assert_eq!(output.data.len() > 0, data);
+            assert_eq!(!output.data.is_empty(), data);
         };
 
         test(0, 0, 1, true);
@@ -1115,7 +1153,7 @@ mod tests {
         assert_eq!(p.parity, Parity::Odd);
         assert_eq!(p.flow_control, FlowControl::XON_XOFF);
         assert_eq!(p.data_bits, DataBits::EightBits);
-        assert_eq!(p.terminating_resistor, true);
+        assert!(p.terminating_resistor);
         assert_eq!(p.XON_char, '!');
         assert_eq!(p.XOFF_char, '#');
     }
@@ -1183,4 +1221,19 @@ mod tests {
         let data = vec![1, 0, 5, 0, 0, 0, 1, 0, 17, 19];
         assert!(Mod::from_modbus_parameter_data(&data).is_ok());
     }
+
+    #[test]
+    fn test_parameters_to_raw_data() {
+        let mut m = Mod::default();
+        m.mod_params.process_data_len = ProcessDataLength::SixteenBytes;
+        m.ch_params[0].operating_mode = OperatingMode::RS485;
+        m.ch_params[0].terminating_resistor = true;
+        let data = m.to_modbus_parameter_data();
+        assert_eq!(data.len(), 10);
+        assert_eq!(data[..2], [1, 2]);
+        assert_eq!(data[7], 1);
+        let (mod_params, ch_params) = parameters_from_raw_data(&data).unwrap();
+        assert_eq!(mod_params, m.mod_params);
+        assert_eq!(ch_params, m.ch_params[0]);
+    }
 }
@@ -0,0 +1,264 @@
+//! SSI encoder module UR20-1SSI
+
+use super::*;
+use crate::ur20_fbc_mod_tcp::{
+    FromModbusParameterData, ProcessDataLayout, ProcessModbusTcpData, ToModbusParameterData,
+};
+use num_traits::cast::{FromPrimitive, ToPrimitive};
+
+#[derive(Debug, Clone)]
+pub struct Mod {
+    pub ch_params: Vec<ChannelParameters>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ChannelParameters {
+    /// Number of data bits of an SSI frame (`1 ... 32`).
+    pub bit_count: u8,
+    pub baud_rate: BaudRate,
+    pub coding: Coding,
+}
+
+/// SSI clock rate.
+#[rustfmt::skip]
+#[allow(non_camel_case_types)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, FromPrimitive, ToPrimitive)]
+pub enum BaudRate {
+    kHz125 = 0,
+    kHz250 = 1,
+    kHz500 = 2,
+    MHz1   = 3,
+    MHz2   = 4,
+}
+
+/// Coding of the encoder value.
+#[rustfmt::skip]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, FromPrimitive, ToPrimitive)]
+pub enum Coding {
+    Binary = 0,
+    Gray   = 1,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProcessInput {
+    /// Position value of the encoder
+    pub position: u32,
+    /// The last SSI frame could not be read (e.g. wire break).
+    pub error: bool,
+    /// The encoder supply is overloaded or short circuited.
+    pub supply_error: bool,
+}
+
+impl Default for ChannelParameters {
+    fn default() -> Self {
+        ChannelParameters {
+            bit_count: 25,
+            baud_rate: BaudRate::kHz125,
+            coding: Coding::Gray,
+        }
+    }
+}
+
+impl From<ProcessInput> for ChannelValue {
+    fn from(i: ProcessInput) -> Self {
+        ChannelValue::SsiIn(i)
+    }
+}
+
+impl Default for Mod {
+    fn default() -> Self {
+        Mod {
+            ch_params: vec![ChannelParameters::default()],
+        }
+    }
+}
+
+impl Module for Mod {
+    fn module_type(&self) -> ModuleType {
+        ModuleType::UR20_1SSI
+    }
+}
+
+impl FromModbusParameterData for Mod {
+    fn from_modbus_parameter_data(data: &[u16]) -> Result<Mod> {
+        let ch_params = vec![parameters_from_raw_data(data)?];
+        Ok(Mod { ch_params })
+    }
+}
+
+impl ToModbusParameterData for Mod {
+    fn to_modbus_parameter_data(&self) -> Vec<u16> {
+        self.ch_params
+            .first()
+            .map(parameters_to_raw_data)
+            .unwrap_or_default()
+    }
+}
+
+impl ProcessModbusTcpData for Mod {
+    fn process_input_byte_count(&self) -> usize {
+        8
+    }
+    fn process_output_byte_count(&self) -> usize {
+        0
+    }
+    fn layout(&self) -> ProcessDataLayout {
+        ProcessDataLayout {
+            input: vec![
+                "position (high word)".into(),
+                "position (low word)".into(),
+                "status".into(),
+                "reserved".into(),
+            ],
+            output: vec![],
+        }
+    }
+    fn process_input_data(&self, data: &[u16]) -> Result<Vec<ChannelValue>> {
+        if data.len() != 4 {
+            return Err(Error::BufferLength);
+        }
+        util::check_channel_count(self, &self.ch_params)?;
+        let p = &self.ch_params[0];
+        let mask = if p.bit_count >= 32 {
+            u32::MAX
+        } else {
+            (1 << p.bit_count) - 1
+        };
+        let position = (u32::from(data[0]) << 16 | u32::from(data[1])) & mask;
+        Ok(vec![ProcessInput {
+            position,
+            error: util::test_bit_16(data[2], 0),
+            supply_error: util::test_bit_16(data[2], 1),
+        }
+        .into()])
+    }
+}
+
+fn parameters_from_raw_data(data: &[u16]) -> Result<ChannelParameters> {
+    if data.len() < 3 {
+        return Err(Error::BufferLength);
+    }
+
+    let mut p = ChannelParameters::default();
+
+    p.bit_count = match data[0] {
+        1..=32 => data[0] as u8,
+        _ => {
+            return Err(Error::ChannelParameter);
+        }
+    };
+
+    p.baud_rate = match FromPrimitive::from_u16(data[1]) {
+        Some(x) => x,
+        _ => {
+            return Err(Error::ChannelParameter);
+        }
+    };
+
+    p.coding = match FromPrimitive::from_u16(data[2]) {
+        Some(x) => x,
+        _ => {
+            return Err(Error::ChannelParameter);
+        }
+    };
+
+    Ok(p)
+}
+
+fn parameters_to_raw_data(p: &ChannelParameters) -> Vec<u16> {
+    vec![
+        u16::from(p.bit_count),
+        p.baud_rate.to_u16().unwrap(),
+        p.coding.to_u16().unwrap(),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_process_input_data() {
+        let m = Mod::default();
+        assert!(m.process_input_data(&[0; 3]).is_err());
+        assert_eq!(
+            m.process_input_data(&[0x0123, 0x4567, 0b10, 0]).unwrap(),
+            vec![ChannelValue::SsiIn(ProcessInput {
+                position: 0x0123_4567,
+                error: false,
+                supply_error: true,
+            })]
+        );
+    }
+
+    #[test]
+    fn test_process_input_data_with_bit_count() {
+        let mut m = Mod::default();
+        m.ch_params[0].bit_count = 12;
+        assert_eq!(
+            m.process_input_data(&[0xFFFF, 0xFFFF, 0b01, 0]).unwrap(),
+            vec![ChannelValue::SsiIn(ProcessInput {
+                position: 0x0FFF,
+                error: true,
+                supply_error: false,
+            })]
+        );
+        m.ch_params[0].bit_count = 32;
+        match m.process_input_data(&[0xFFFF, 0xFFFF, 0, 0]).unwrap()[0] {
+            ChannelValue::SsiIn(ref i) => assert_eq!(i.position, u32::MAX),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn test_process_input_data_with_missing_channel_parameters() {
+        let mut m = Mod::default();
+        m.ch_params = vec![];
+        assert!(m.process_input_data(&[0; 4]).is_err());
+    }
+
+    #[test]
+    fn test_process_output_data() {
+        let m = Mod::default();
+        assert_eq!(
+            m.process_output_data(&[]).unwrap(),
+            vec![ChannelValue::None]
+        );
+        assert_eq!(m.process_output_values(&[]).unwrap(), Vec::<u16>::new());
+    }
+
+    #[test]
+    fn test_channel_parameters_from_raw_data() {
+        let p = parameters_from_raw_data(&[13, 3, 0]).unwrap();
+        assert_eq!(p.bit_count, 13);
+        assert_eq!(p.baud_rate, BaudRate::MHz1);
+        assert_eq!(p.coding, Coding::Binary);
+    }
+
+    #[test]
+    fn test_parameters_from_invalid_raw_data() {
+        assert!(parameters_from_raw_data(&[25, 0]).is_err());
+        assert!(parameters_from_raw_data(&[0, 0, 0]).is_err());
+        assert!(parameters_from_raw_data(&[33, 0, 0]).is_err());
+        assert!(parameters_from_raw_data(&[25, 5, 0]).is_err());
+        assert!(parameters_from_raw_data(&[25, 0, 2]).is_err());
+    }
+
+    #[test]
+    fn create_module_from_modbus_parameter_data() {
+        let m = Mod::from_modbus_parameter_data(&[25, 1, 1]).unwrap();
+        assert_eq!(m.ch_params[0].baud_rate, BaudRate::kHz250);
+        assert_eq!(m.ch_params[0].coding, Coding::Gray);
+    }
+
+    #[test]
+    fn test_parameters_to_raw_data() {
+        let mut m = Mod::default();
+        m.ch_params[0].bit_count = 13;
+        m.ch_params[0].baud_rate = BaudRate::MHz1;
+        let data = m.to_modbus_parameter_data();
+        assert_eq!(data, vec![13, 3, 1]);
+        assert_eq!(parameters_from_raw_data(&data).unwrap(), m.ch_params[0]);
+    }
+}
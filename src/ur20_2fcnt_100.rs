@@ -1,8 +1,10 @@
 //! Digital frequency counter module UR20-2FCNT-100
 
 use super::*;
-use crate::ur20_fbc_mod_tcp::{FromModbusParameterData, ProcessModbusTcpData};
-use num_traits::cast::FromPrimitive;
+use crate::ur20_fbc_mod_tcp::{
+    FromModbusParameterData, ProcessDataLayout, ProcessModbusTcpData, ToModbusParameterData,
+};
+use num_traits::cast::{FromPrimitive, ToPrimitive};
 use std::time::Duration;
 
 lazy_static! {
@@ -31,15 +33,10 @@ pub struct ProcessInput {
 impl ProcessInput {
     /// Calculate the frequency in Hz.
     pub fn hertz(&self) -> Option<f32> {
-        if let Some(d) = self.duration {
-            //TODO: check overflow!
-            Some(
-                self.count as f32
-                    / (d.as_secs() as f32 + d.subsec_nanos() as f32 / NANOS_PER_SEC as f32),
-            )
-        } else {
-            None
-        }
+        self.duration.map(|d| {
+            self.count as f32
+                / (d.as_secs() as f32 + d.subsec_nanos() as f32 / NANOS_PER_SEC as f32)
+        })
     }
 }
 
@@ -60,7 +57,7 @@ pub struct ProcessOutput {
     pub command: Option<Command>,
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct ChannelParameters {
     /// Signal filter
     pub input_filter: InputFilter,
@@ -115,6 +112,12 @@ impl FromModbusParameterData for Mod {
     }
 }
 
+impl ToModbusParameterData for Mod {
+    fn to_modbus_parameter_data(&self) -> Vec<u16> {
+        parameters_to_raw_data(&self.ch_params)
+    }
+}
+
 impl ProcessModbusTcpData for Mod {
     fn process_input_byte_count(&self) -> usize {
         20
@@ -122,14 +125,31 @@ impl ProcessModbusTcpData for Mod {
     fn process_output_byte_count(&self) -> usize {
         12
     }
+    fn layout(&self) -> ProcessDataLayout {
+        let mut input = vec![];
+        for ch in 0..2 {
+            input.push(format!("channel {} measurement period (high word)", ch));
+            input.push(format!("channel {} measurement period (low word)", ch));
+            input.push(format!("channel {} counter value (high word)", ch));
+            input.push(format!("channel {} counter value (low word)", ch));
+        }
+        input.push("channel 0 status".into());
+        input.push("channel 1 status".into());
+        let mut output = vec![];
+        for ch in 0..2 {
+            output.push(format!("channel {} measurement duration (high word)", ch));
+            output.push(format!("channel {} measurement duration (low word)", ch));
+        }
+        output.push("channel 0 control".into());
+        output.push("channel 1 control".into());
+        ProcessDataLayout { input, output }
+    }
     fn process_input_data(&self, data: &[u16]) -> Result<Vec<ChannelValue>> {
         if data.len() != 10 {
             return Err(Error::BufferLength);
         }
 
-        if self.ch_params.len() != 2 {
-            return Err(Error::ChannelParameter);
-        }
+        util::check_channel_count(self, &self.ch_params)?;
 
         let res = (0..2)
             .map(|i| {
@@ -193,13 +213,8 @@ impl ProcessModbusTcpData for Mod {
         Ok(res)
     }
     fn process_output_values(&self, values: &[ChannelValue]) -> Result<Vec<u16>> {
-        let cnt = self.module_type().channel_count();
-        if values.len() != cnt {
-            return Err(Error::ChannelValue);
-        }
-        if self.ch_params.len() != cnt {
-            return Err(Error::ChannelParameter);
-        }
+        let values = util::output_values(self, values)?;
+        util::check_channel_count(self, &self.ch_params)?;
         let mut out = vec![0; 6];
 
         for (i, v) in values.iter().enumerate() {
@@ -227,7 +242,7 @@ impl ProcessModbusTcpData for Mod {
                         }
                     }
                 }
-                ChannelValue::Disabled => { /* ignore */ }
+                ChannelValue::Disabled | ChannelValue::None => { /* ignore */ }
                 _ => {
                     return Err(Error::ChannelValue);
                 }
@@ -256,7 +271,14 @@ fn parameters_from_raw_data(data: &[u16]) -> Result<Vec<ChannelParameters>> {
             Ok(p)
         })
         .collect();
-    Ok(channel_parameters?)
+    channel_parameters
+}
+
+fn parameters_to_raw_data(ch_params: &[ChannelParameters]) -> Vec<u16> {
+    ch_params
+        .iter()
+        .map(|p| p.input_filter.to_u16().unwrap())
+        .collect()
 }
 
 #[cfg(test)]
@@ -303,17 +325,17 @@ mod tests {
     #[test]
     fn test_process_input_data_with_invalid_buffer_size() {
         let m = Mod::default();
-        assert!(m.process_input_data(&vec![]).is_err());
-        assert!(m.process_input_data(&vec![0; 2]).is_err());
-        assert!(m.process_input_data(&vec![0; 9]).is_err());
-        assert!(m.process_input_data(&vec![0; 10]).is_ok());
+        assert!(m.process_input_data(&[]).is_err());
+        assert!(m.process_input_data(&[0; 2]).is_err());
+        assert!(m.process_input_data(&[0; 9]).is_err());
+        assert!(m.process_input_data(&[0; 10]).is_ok());
     }
 
     #[test]
     fn test_process_input_data_with_missing_channel_parameters() {
         let mut m = Mod::default();
         m.ch_params = vec![];
-        assert!(m.process_input_data(&vec![0; 10]).is_err());
+        assert!(m.process_input_data(&[0; 10]).is_err());
     }
 
     #[test]
@@ -452,7 +474,7 @@ mod tests {
     fn test_process_output_values_with_invalid_channel_len() {
         let m = Mod::default();
         let out = ProcessOutput::default();
-        assert!(m.process_output_values(&[]).is_err());
+        assert_eq!(m.process_output_values(&[]).unwrap(), vec![0; 6]);
         assert!(m.process_output_values(&vec![out.into(); 1]).is_err());
         assert!(m.process_output_values(&vec![out.into(); 3]).is_err());
         assert!(m.process_output_values(&vec![out.into(); 2]).is_ok());
@@ -538,7 +560,7 @@ mod tests {
         };
         assert_eq!(input.hertz().unwrap(), 25000.0);
         let input = ProcessInput {
-            count: ::std::u32::MAX,
+            count: u32::MAX,
             active: true,
             duration: Some(Duration::new(0, 1_000)),
         };
@@ -550,4 +572,13 @@ mod tests {
         };
         assert_eq!(input.hertz(), None);
     }
+
+    #[test]
+    fn test_parameters_to_raw_data() {
+        let mut m = Mod::default();
+        m.ch_params[1].input_filter = InputFilter::us333;
+        let data = m.to_modbus_parameter_data();
+        assert_eq!(data, vec![0, 6]);
+        assert_eq!(parameters_from_raw_data(&data).unwrap(), m.ch_params);
+    }
 }
@@ -0,0 +1,360 @@
+//! Pulse-width modulation modules UR20-2PWM-PN-0.5A and UR20-2PWM-PN-2A
+//!
+//! Both modules only differ in their maximum output current,
+//! so they share the same implementation.
+
+use super::*;
+use crate::ur20_fbc_mod_tcp::{ProcessDataLayout, ProcessModbusTcpData, ToModbusParameterData};
+use num_traits::cast::{FromPrimitive, ToPrimitive};
+use std::time::Duration;
+
+/// Maximum PWM frequency in Hz.
+pub const MAX_FREQUENCY: u16 = 20_000;
+
+/// Resolution of the duty cycle (0.1 %).
+const DUTY_CYCLE_STEPS: u16 = 1000;
+
+#[derive(Debug, Clone)]
+pub struct Mod {
+    pub module_type: ModuleType,
+    pub ch_params: Vec<ChannelParameters>,
+}
+
+/// Output behavior on a communication loss.
+#[rustfmt::skip]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, FromPrimitive, ToPrimitive)]
+pub enum SubstituteBehavior {
+    /// Switch the output off.
+    Off           = 0,
+    /// Keep the last duty cycle.
+    HoldLastValue = 1,
+    /// Output the substitute duty cycle.
+    Substitute    = 2,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ChannelParameters {
+    /// PWM frequency in Hz (`1 ... 20000`)
+    pub frequency: u16,
+    pub substitute_behavior: SubstituteBehavior,
+    /// Substitute duty cycle in 0.1 % (`0 ... 1000`)
+    pub substitute_duty_cycle: u16,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ProcessOutput {
+    /// Duty cycle (`0.0 ... 1.0`)
+    pub duty_cycle: f32,
+    /// Frequency in Hz that overrides the parameterized frequency.
+    pub frequency: Option<u16>,
+}
+
+impl ProcessOutput {
+    /// Period of the PWM signal (if it overrides the parameterized frequency).
+    pub fn period(&self) -> Option<Duration> {
+        self.frequency
+            .filter(|f| *f > 0)
+            .map(|f| Duration::from_secs_f64(1.0 / f64::from(f)))
+    }
+}
+
+impl Default for ChannelParameters {
+    fn default() -> Self {
+        ChannelParameters {
+            frequency: 100,
+            substitute_behavior: SubstituteBehavior::Off,
+            substitute_duty_cycle: 0,
+        }
+    }
+}
+
+impl Default for ProcessOutput {
+    fn default() -> Self {
+        ProcessOutput {
+            duty_cycle: 0.0,
+            frequency: None,
+        }
+    }
+}
+
+impl From<ProcessOutput> for ChannelValue {
+    fn from(o: ProcessOutput) -> Self {
+        ChannelValue::PwmOut(o)
+    }
+}
+
+impl Mod {
+    /// Creates a module with default parameters.
+    pub fn new(module_type: ModuleType) -> Result<Mod> {
+        check_module_type(&module_type)?;
+        let ch_params = (0..2).map(|_| ChannelParameters::default()).collect();
+        Ok(Mod {
+            module_type,
+            ch_params,
+        })
+    }
+
+    /// Creates a module instance from its parameter register data.
+    pub fn from_modbus_parameter_data(module_type: ModuleType, data: &[u16]) -> Result<Mod> {
+        check_module_type(&module_type)?;
+        let ch_params = parameters_from_raw_data(data)?;
+        Ok(Mod {
+            module_type,
+            ch_params,
+        })
+    }
+}
+
+impl Module for Mod {
+    fn module_type(&self) -> ModuleType {
+        self.module_type.clone()
+    }
+}
+
+impl ToModbusParameterData for Mod {
+    fn to_modbus_parameter_data(&self) -> Vec<u16> {
+        parameters_to_raw_data(&self.ch_params)
+    }
+}
+
+impl ProcessModbusTcpData for Mod {
+    fn process_input_byte_count(&self) -> usize {
+        0
+    }
+    fn process_output_byte_count(&self) -> usize {
+        8
+    }
+    fn layout(&self) -> ProcessDataLayout {
+        ProcessDataLayout {
+            input: vec![],
+            output: (0..2)
+                .flat_map(|i| {
+                    vec![
+                        format!("channel {} duty cycle", i),
+                        format!("channel {} frequency", i),
+                    ]
+                })
+                .collect(),
+        }
+    }
+    fn process_output_data(&self, data: &[u16]) -> Result<Vec<ChannelValue>> {
+        if data.len() != 4 {
+            return Err(Error::BufferLength);
+        }
+        Ok((0..2)
+            .map(|i| {
+                let idx = i * 2;
+                ProcessOutput {
+                    duty_cycle: f32::from(data[idx]) / f32::from(DUTY_CYCLE_STEPS),
+                    frequency: match data[idx + 1] {
+                        0 => None,
+                        f => Some(f),
+                    },
+                }
+                .into()
+            })
+            .collect())
+    }
+    fn process_output_values(&self, values: &[ChannelValue]) -> Result<Vec<u16>> {
+        let values = util::output_values(self, values)?;
+        let mut out = vec![0; 4];
+        for (i, v) in values.iter().enumerate() {
+            match *v {
+                ChannelValue::PwmOut(ref v) => {
+                    if !(0.0..=1.0).contains(&v.duty_cycle) {
+                        return Err(Error::ChannelValue);
+                    }
+                    if let Some(f) = v.frequency {
+                        if f == 0 || f > MAX_FREQUENCY {
+                            return Err(Error::ChannelValue);
+                        }
+                    }
+                    let idx = i * 2;
+                    out[idx] = (v.duty_cycle * f32::from(DUTY_CYCLE_STEPS)).round() as u16;
+                    out[idx + 1] = v.frequency.unwrap_or(0);
+                }
+                ChannelValue::Disabled | ChannelValue::None => {}
+                _ => {
+                    return Err(Error::ChannelValue);
+                }
+            }
+        }
+        Ok(out)
+    }
+}
+
+fn check_module_type(module_type: &ModuleType) -> Result<()> {
+    match *module_type {
+        ModuleType::UR20_2PWM_PN_0_5A | ModuleType::UR20_2PWM_PN_2A => Ok(()),
+        _ => Err(Error::UnknownModule),
+    }
+}
+
+fn parameters_from_raw_data(data: &[u16]) -> Result<Vec<ChannelParameters>> {
+    if data.len() < 6 {
+        return Err(Error::BufferLength);
+    }
+
+    let channel_parameters: Result<Vec<_>> = (0..2)
+        .map(|i| {
+            let mut p = ChannelParameters::default();
+            let idx = i * 3;
+
+            p.frequency = match data[idx] {
+                1..=MAX_FREQUENCY => data[idx],
+                _ => {
+                    return Err(Error::ChannelParameter);
+                }
+            };
+
+            p.substitute_behavior = match FromPrimitive::from_u16(data[idx + 1]) {
+                Some(x) => x,
+                _ => {
+                    return Err(Error::ChannelParameter);
+                }
+            };
+
+            p.substitute_duty_cycle = match data[idx + 2] {
+                0..=DUTY_CYCLE_STEPS => data[idx + 2],
+                _ => {
+                    return Err(Error::ChannelParameter);
+                }
+            };
+
+            Ok(p)
+        })
+        .collect();
+    channel_parameters
+}
+
+fn parameters_to_raw_data(ch_params: &[ChannelParameters]) -> Vec<u16> {
+    ch_params
+        .iter()
+        .flat_map(|p| {
+            vec![
+                p.frequency,
+                p.substitute_behavior.to_u16().unwrap(),
+                p.substitute_duty_cycle,
+            ]
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::ChannelValue::*;
+
+    fn out(duty_cycle: f32, frequency: Option<u16>) -> ChannelValue {
+        ProcessOutput {
+            duty_cycle,
+            frequency,
+        }
+        .into()
+    }
+
+    #[test]
+    fn create_new_module() {
+        assert!(Mod::new(ModuleType::UR20_2PWM_PN_0_5A).is_ok());
+        assert!(Mod::new(ModuleType::UR20_2PWM_PN_2A).is_ok());
+        assert_eq!(
+            Mod::new(ModuleType::UR20_4DO_P).err(),
+            Some(Error::UnknownModule)
+        );
+    }
+
+    #[test]
+    fn test_process_output_values() {
+        let m = Mod::new(ModuleType::UR20_2PWM_PN_2A).unwrap();
+        assert_eq!(
+            m.process_output_values(&[out(0.25, Option::None), out(1.0, Some(500))])
+                .unwrap(),
+            vec![250, 0, 1000, 500]
+        );
+        assert_eq!(
+            m.process_output_values(&[Disabled, out(0.0005, Option::None)])
+                .unwrap(),
+            vec![0, 0, 1, 0]
+        );
+        assert!(m
+            .process_output_values(&[out(1.1, Option::None), None])
+            .is_err());
+        assert!(m
+            .process_output_values(&[out(-0.1, Option::None), None])
+            .is_err());
+        assert!(m
+            .process_output_values(&[out(f32::NAN, Option::None), None])
+            .is_err());
+        assert!(m.process_output_values(&[out(0.5, Some(0)), None]).is_err());
+        assert!(m
+            .process_output_values(&[out(0.5, Some(20_001)), None])
+            .is_err());
+        assert!(m.process_output_values(&[Bit(true), None]).is_err());
+    }
+
+    #[test]
+    fn test_process_output_data() {
+        let m = Mod::new(ModuleType::UR20_2PWM_PN_0_5A).unwrap();
+        assert!(m.process_output_data(&[0; 3]).is_err());
+        assert_eq!(
+            m.process_output_data(&[500, 0, 1000, 2000]).unwrap(),
+            vec![out(0.5, Option::None), out(1.0, Some(2000))]
+        );
+    }
+
+    #[test]
+    fn period_of_process_output() {
+        let o = ProcessOutput {
+            duty_cycle: 0.5,
+            frequency: Some(250),
+        };
+        assert_eq!(o.period(), Some(Duration::from_millis(4)));
+        assert_eq!(ProcessOutput::default().period(), Option::None);
+    }
+
+    #[test]
+    fn test_channel_parameters_from_raw_data() {
+        #[rustfmt::skip]
+        let data = vec![
+            1000, 2, 750, // CH 0
+            1,    1, 0,   // CH 1
+        ];
+        let p = parameters_from_raw_data(&data).unwrap();
+        assert_eq!(p[0].frequency, 1000);
+        assert_eq!(p[0].substitute_behavior, SubstituteBehavior::Substitute);
+        assert_eq!(p[0].substitute_duty_cycle, 750);
+        assert_eq!(p[1].frequency, 1);
+        assert_eq!(p[1].substitute_behavior, SubstituteBehavior::HoldLastValue);
+    }
+
+    #[test]
+    fn test_parameters_from_invalid_raw_data() {
+        assert!(parameters_from_raw_data(&[100, 0, 0, 100, 0]).is_err());
+        assert!(parameters_from_raw_data(&[0, 0, 0, 100, 0, 0]).is_err());
+        assert!(parameters_from_raw_data(&[20_001, 0, 0, 100, 0, 0]).is_err());
+        assert!(parameters_from_raw_data(&[100, 3, 0, 100, 0, 0]).is_err());
+        assert!(parameters_from_raw_data(&[100, 0, 1001, 100, 0, 0]).is_err());
+    }
+
+    #[test]
+    fn create_module_from_modbus_parameter_data() {
+        let m =
+            Mod::from_modbus_parameter_data(ModuleType::UR20_2PWM_PN_0_5A, &[100, 0, 0, 200, 0, 0])
+                .unwrap();
+        assert_eq!(m.module_type(), ModuleType::UR20_2PWM_PN_0_5A);
+        assert_eq!(m.ch_params[1].frequency, 200);
+        assert!(Mod::from_modbus_parameter_data(ModuleType::UR20_4DO_P, &[100; 6]).is_err());
+    }
+
+    #[test]
+    fn test_parameters_to_raw_data() {
+        let mut m = Mod::new(ModuleType::UR20_2PWM_PN_2A).unwrap();
+        m.ch_params[1].frequency = 2000;
+        m.ch_params[1].substitute_behavior = SubstituteBehavior::Substitute;
+        m.ch_params[1].substitute_duty_cycle = 500;
+        let data = m.to_modbus_parameter_data();
+        assert_eq!(data, vec![100, 0, 0, 2000, 2, 500]);
+        assert_eq!(parameters_from_raw_data(&data).unwrap(), m.ch_params);
+    }
+}
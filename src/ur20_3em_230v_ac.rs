@@ -0,0 +1,345 @@
+//! Energy metering module UR20-3EM-230V-AC
+//!
+//! Each channel represents one phase (L1, L2, L3).
+
+use super::*;
+use crate::ur20_fbc_mod_tcp::{
+    FromModbusParameterData, ProcessDataLayout, ProcessModbusTcpData, ToModbusParameterData,
+};
+use num_traits::cast::{FromPrimitive, ToPrimitive};
+
+/// Number of registers of the process input data of each phase.
+const PHASE_REGISTER_COUNT: usize = 8;
+
+#[derive(Debug, Clone)]
+pub struct Mod {
+    pub mod_params: ModuleParameters,
+    pub ch_params: Vec<ChannelParameters>,
+}
+
+/// Wiring of the measured network.
+#[rustfmt::skip]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, FromPrimitive, ToPrimitive)]
+pub enum Connection {
+    /// Three phases with neutral conductor
+    ThreePhaseFourWire  = 0,
+    /// Three phases without neutral conductor
+    ThreePhaseThreeWire = 1,
+    /// Only L1 and the neutral conductor
+    SinglePhase         = 2,
+}
+
+/// Rated secondary current of the current transformers.
+#[rustfmt::skip]
+#[allow(non_camel_case_types)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, FromPrimitive, ToPrimitive)]
+pub enum SecondaryCurrent {
+    A1 = 0,
+    A5 = 1,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ModuleParameters {
+    pub connection: Connection,
+    /// Rated primary current of the current transformers in A (`1 ... 10000`)
+    pub primary_current: u16,
+    pub secondary_current: SecondaryCurrent,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub struct ChannelParameters {
+    pub channel_diagnostics: bool,
+}
+
+/// The measured values of a phase.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProcessInput {
+    /// RMS voltage in V
+    pub voltage: f32,
+    /// RMS current in A
+    pub current: f32,
+    /// Active power in W (negative if energy is fed in)
+    pub power: f32,
+    /// Active energy counter in Wh
+    pub energy: u32,
+    /// Network frequency in Hz
+    pub frequency: f32,
+    /// The voltage of the phase is missing.
+    pub phase_failure: bool,
+    /// The current exceeds the measurement range.
+    pub overcurrent: bool,
+}
+
+impl Default for ModuleParameters {
+    fn default() -> Self {
+        ModuleParameters {
+            connection: Connection::ThreePhaseFourWire,
+            primary_current: 5,
+            secondary_current: SecondaryCurrent::A5,
+        }
+    }
+}
+
+impl Default for Mod {
+    fn default() -> Self {
+        Mod {
+            mod_params: ModuleParameters::default(),
+            ch_params: (0..3).map(|_| ChannelParameters::default()).collect(),
+        }
+    }
+}
+
+impl From<ProcessInput> for ChannelValue {
+    fn from(i: ProcessInput) -> Self {
+        ChannelValue::EmIn(i)
+    }
+}
+
+impl Module for Mod {
+    fn module_type(&self) -> ModuleType {
+        ModuleType::UR20_3EM_230V_AC
+    }
+}
+
+impl FromModbusParameterData for Mod {
+    fn from_modbus_parameter_data(data: &[u16]) -> Result<Mod> {
+        let (mod_params, ch_params) = parameters_from_raw_data(data)?;
+        Ok(Mod {
+            mod_params,
+            ch_params,
+        })
+    }
+}
+
+impl ToModbusParameterData for Mod {
+    fn to_modbus_parameter_data(&self) -> Vec<u16> {
+        parameters_to_raw_data(&self.mod_params, &self.ch_params)
+    }
+}
+
+impl ProcessModbusTcpData for Mod {
+    fn process_input_byte_count(&self) -> usize {
+        3 * PHASE_REGISTER_COUNT * 2
+    }
+    fn process_output_byte_count(&self) -> usize {
+        0
+    }
+    fn layout(&self) -> ProcessDataLayout {
+        let input = (1..=3)
+            .flat_map(|l| {
+                [
+                    "voltage",
+                    "current",
+                    "power (high word)",
+                    "power (low word)",
+                    "energy (high word)",
+                    "energy (low word)",
+                    "frequency",
+                    "status",
+                ]
+                .iter()
+                .map(move |x| format!("L{} {}", l, x))
+            })
+            .collect();
+        ProcessDataLayout {
+            input,
+            output: vec![],
+        }
+    }
+    fn process_input_data(&self, data: &[u16]) -> Result<Vec<ChannelValue>> {
+        if data.len() * 2 != self.process_input_byte_count() {
+            return Err(Error::BufferLength);
+        }
+        util::check_channel_count(self, &self.ch_params)?;
+        let res = data
+            .chunks(PHASE_REGISTER_COUNT)
+            .enumerate()
+            .map(|(i, d)| {
+                if i > 0 && self.mod_params.connection == Connection::SinglePhase {
+                    return ChannelValue::Disabled;
+                }
+                let power = (u32::from(d[2]) << 16 | u32::from(d[3])) as i32;
+                ProcessInput {
+                    voltage: f32::from(d[0]) / 10.0,
+                    current: f32::from(d[1]) / 1000.0,
+                    power: power as f32 / 10.0,
+                    energy: u32::from(d[4]) << 16 | u32::from(d[5]),
+                    frequency: f32::from(d[6]) / 100.0,
+                    phase_failure: util::test_bit_16(d[7], 0),
+                    overcurrent: util::test_bit_16(d[7], 1),
+                }
+                .into()
+            })
+            .collect();
+        Ok(res)
+    }
+}
+
+fn parameters_from_raw_data(data: &[u16]) -> Result<(ModuleParameters, Vec<ChannelParameters>)> {
+    if data.len() < 6 {
+        return Err(Error::BufferLength);
+    }
+
+    let mut module_parameters = ModuleParameters::default();
+
+    module_parameters.connection = match FromPrimitive::from_u16(data[0]) {
+        Some(x) => x,
+        _ => {
+            return Err(Error::ChannelParameter);
+        }
+    };
+
+    module_parameters.primary_current = match data[1] {
+        1..=10_000 => data[1],
+        _ => {
+            return Err(Error::ChannelParameter);
+        }
+    };
+
+    module_parameters.secondary_current = match FromPrimitive::from_u16(data[2]) {
+        Some(x) => x,
+        _ => {
+            return Err(Error::ChannelParameter);
+        }
+    };
+
+    let channel_parameters: Result<Vec<_>> = (0..3)
+        .map(|i| {
+            let mut p = ChannelParameters::default();
+            p.channel_diagnostics = match data[i + 3] {
+                0 => false,
+                1 => true,
+                _ => {
+                    return Err(Error::ChannelParameter);
+                }
+            };
+            Ok(p)
+        })
+        .collect();
+    Ok((module_parameters, channel_parameters?))
+}
+
+fn parameters_to_raw_data(
+    mod_params: &ModuleParameters,
+    ch_params: &[ChannelParameters],
+) -> Vec<u16> {
+    let mut data = vec![
+        mod_params.connection.to_u16().unwrap(),
+        mod_params.primary_current,
+        mod_params.secondary_current.to_u16().unwrap(),
+    ];
+    data.extend(ch_params.iter().map(|p| u16::from(p.channel_diagnostics)));
+    data
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[rustfmt::skip]
+    fn phase(voltage: u16, current: u16, power: i32, energy: u32, status: u16) -> Vec<u16> {
+        let power = power as u32;
+        vec![
+            voltage, current,
+            (power >> 16) as u16, power as u16,
+            (energy >> 16) as u16, energy as u16,
+            5000, status,
+        ]
+    }
+
+    #[test]
+    fn test_process_input_data() {
+        let m = Mod::default();
+        assert!(m.process_input_data(&[0; 23]).is_err());
+        let mut data = phase(2301, 1500, 3450, 0x0001_0000, 0);
+        data.extend(phase(2299, 0, -125, 42, 0b10));
+        data.extend(phase(0, 0, 0, 0, 0b01));
+        let values = m.process_input_data(&data).unwrap();
+        assert_eq!(values.len(), 3);
+        assert_eq!(
+            values[0],
+            ChannelValue::EmIn(ProcessInput {
+                voltage: 230.1,
+                current: 1.5,
+                power: 345.0,
+                energy: 65536,
+                frequency: 50.0,
+                phase_failure: false,
+                overcurrent: false,
+            })
+        );
+        match values[1] {
+            ChannelValue::EmIn(ref v) => {
+                assert_eq!(v.power, -12.5);
+                assert!(v.overcurrent);
+            }
+            _ => panic!(),
+        }
+        match values[2] {
+            ChannelValue::EmIn(ref v) => assert!(v.phase_failure),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn test_process_input_data_of_single_phase() {
+        let mut m = Mod::default();
+        m.mod_params.connection = Connection::SinglePhase;
+        let values = m.process_input_data(&[0; 24]).unwrap();
+        assert!(matches!(values[0], ChannelValue::EmIn(_)));
+        assert_eq!(values[1], ChannelValue::Disabled);
+        assert_eq!(values[2], ChannelValue::Disabled);
+    }
+
+    #[test]
+    fn test_process_input_data_with_missing_channel_parameters() {
+        let mut m = Mod::default();
+        m.ch_params = vec![];
+        assert!(m.process_input_data(&[0; 24]).is_err());
+    }
+
+    #[test]
+    fn test_process_output_data() {
+        let m = Mod::default();
+        assert_eq!(
+            m.process_output_data(&[]).unwrap(),
+            vec![ChannelValue::None; 3]
+        );
+        assert_eq!(m.process_output_values(&[]).unwrap(), Vec::<u16>::new());
+    }
+
+    #[test]
+    fn test_parameters_from_raw_data() {
+        let (m, ch) = parameters_from_raw_data(&[1, 100, 0, 0, 1, 0]).unwrap();
+        assert_eq!(m.connection, Connection::ThreePhaseThreeWire);
+        assert_eq!(m.primary_current, 100);
+        assert_eq!(m.secondary_current, SecondaryCurrent::A1);
+        assert!(!ch[0].channel_diagnostics);
+        assert!(ch[1].channel_diagnostics);
+    }
+
+    #[test]
+    fn test_parameters_from_invalid_raw_data() {
+        assert!(parameters_from_raw_data(&[0, 5, 1, 0, 0]).is_err());
+        assert!(parameters_from_raw_data(&[3, 5, 1, 0, 0, 0]).is_err());
+        assert!(parameters_from_raw_data(&[0, 0, 1, 0, 0, 0]).is_err());
+        assert!(parameters_from_raw_data(&[0, 10_001, 1, 0, 0, 0]).is_err());
+        assert!(parameters_from_raw_data(&[0, 5, 2, 0, 0, 0]).is_err());
+        assert!(parameters_from_raw_data(&[0, 5, 1, 0, 0, 2]).is_err());
+    }
+
+    #[test]
+    fn test_parameters_to_raw_data() {
+        let mut m = Mod::default();
+        m.mod_params.connection = Connection::SinglePhase;
+        m.mod_params.primary_current = 250;
+        m.mod_params.secondary_current = SecondaryCurrent::A1;
+        m.ch_params[0].channel_diagnostics = true;
+        let data = m.to_modbus_parameter_data();
+        assert_eq!(data, vec![2, 250, 0, 1, 0, 0]);
+        let (mod_params, ch_params) = parameters_from_raw_data(&data).unwrap();
+        assert_eq!(mod_params, m.mod_params);
+        assert_eq!(ch_params, m.ch_params);
+    }
+}
@@ -1,21 +1,28 @@
 //! Analog input module UR20-4AI-RTD-DIAG
 
 use super::*;
-use crate::ur20_fbc_mod_tcp::{FromModbusParameterData, ProcessModbusTcpData};
-use num_traits::cast::FromPrimitive;
-
-#[derive(Debug)]
+use crate::diagnosis::{
+    filter_channel_diagnostics, ChannelDiagnosis, ChannelDiagnostics, Diagnostics,
+};
+use crate::ur20_fbc_mod_tcp::{
+    FromModbusParameterData, ProcessDataLayout, ProcessModbusTcpData, ToModbusParameterData,
+};
+use num_traits::cast::{FromPrimitive, ToPrimitive};
+
+#[derive(Debug, Clone)]
 pub struct Mod {
     pub mod_params: ModuleParameters,
     pub ch_params: Vec<ChannelParameters>,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(default))]
 pub struct ModuleParameters {
     pub temperature_unit: TemperatureUnit,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(default))]
 pub struct ChannelParameters {
     pub measurement_range: RtdRange,
     pub connection_type: ConnectionType,
@@ -38,6 +45,12 @@ impl FromModbusParameterData for Mod {
     }
 }
 
+impl ToModbusParameterData for Mod {
+    fn to_modbus_parameter_data(&self) -> Vec<u16> {
+        parameters_to_raw_data(&self.mod_params, &self.ch_params)
+    }
+}
+
 impl Default for ModuleParameters {
     fn default() -> Self {
         ModuleParameters {
@@ -73,10 +86,62 @@ impl Default for Mod {
     }
 }
 
+impl Mod {
+    /// Decodes the input data (see `process_input_data`) together
+    /// with the limit value status of each channel.
+    ///
+    /// The status is `None` for disabled channels and
+    /// channels without limit value monitoring.
+    pub fn process_input_data_with_status(
+        &self,
+        data: &[u16],
+    ) -> Result<(Vec<ChannelValue>, Vec<Option<LimitStatus>>)> {
+        let values = self.process_input_data(data)?;
+        let status = values
+            .iter()
+            .zip(data)
+            .zip(&self.ch_params)
+            .map(|((v, raw), p)| {
+                if !p.limit_value_monitoring || *v == ChannelValue::Disabled {
+                    return None;
+                }
+                Some(LimitStatus::from_raw_value(
+                    *raw as i16,
+                    p.low_limit_value,
+                    p.high_limit_value,
+                ))
+            })
+            .collect();
+        Ok((values, status))
+    }
+}
+
 impl Module for Mod {
     fn module_type(&self) -> ModuleType {
         ModuleType::UR20_4AI_RTD_DIAG
     }
+    fn channel_info(&self) -> Vec<ChannelInfo> {
+        self.ch_params
+            .iter()
+            .map(|p| ChannelInfo {
+                unit: p.measurement_range.unit(&self.mod_params.temperature_unit),
+                range: Some(format!("{:?}", p.measurement_range)),
+                ..Default::default()
+            })
+            .collect()
+    }
+}
+
+impl Diagnostics for Mod {
+    /// Drops the causes that are not monitored.
+    fn process_diagnostic_data(&self, data: &[u16]) -> Result<Vec<ChannelDiagnostics>> {
+        filter_channel_diagnostics(&self.module_type(), data, |ch, cause| {
+            self.ch_params.get(ch).map_or(true, |p| match cause {
+                ChannelDiagnosis::LimitExceeded => p.limit_value_monitoring,
+                _ => p.channel_diagnostics,
+            })
+        })
+    }
 }
 
 impl ProcessModbusTcpData for Mod {
@@ -86,17 +151,24 @@ impl ProcessModbusTcpData for Mod {
     fn process_output_byte_count(&self) -> usize {
         0
     }
+    fn diagnostics(&self) -> Option<&dyn Diagnostics> {
+        Some(self)
+    }
+    fn layout(&self) -> ProcessDataLayout {
+        ProcessDataLayout {
+            input: util::channel_layout(4),
+            output: vec![],
+        }
+    }
     fn process_input_data(&self, data: &[u16]) -> Result<Vec<ChannelValue>> {
         if data.len() != 4 {
             return Err(Error::BufferLength);
         }
 
-        if self.ch_params.len() != 4 {
-            return Err(Error::ChannelParameter);
-        }
+        util::check_channel_count(self, &self.ch_params)?;
         let res = (0..4)
             .map(|i| (data[i], &self.ch_params[i].measurement_range))
-            .map(|(val, range)| match util::u16_to_rtd_value(val, range) {
+            .map(|(val, range)| match codec::u16_to_rtd_value(val, range) {
                 Some(v) => ChannelValue::Decimal32(v),
                 None => ChannelValue::Disabled,
             })
@@ -169,30 +241,61 @@ fn parameters_from_raw_data(data: &[u16]) -> Result<(ModuleParameters, Vec<Chann
     Ok((module_parameters, channel_parameters?))
 }
 
+fn parameters_to_raw_data(
+    mod_params: &ModuleParameters,
+    ch_params: &[ChannelParameters],
+) -> Vec<u16> {
+    let mut data = vec![mod_params.temperature_unit.to_u16().unwrap()];
+    for p in ch_params {
+        data.push(p.measurement_range.to_u16().unwrap());
+        data.push(p.connection_type.to_u16().unwrap());
+        data.push(p.conversion_time.to_u16().unwrap());
+        data.push(u16::from(p.channel_diagnostics));
+        data.push(u16::from(p.limit_value_monitoring));
+        data.push(p.high_limit_value as u16);
+        data.push(p.low_limit_value as u16);
+    }
+    data
+}
+
 #[cfg(test)]
 mod tests {
 
     use super::*;
     use crate::ChannelValue::*;
 
+    #[test]
+    fn channel_info() {
+        let mut m = Mod::default();
+        m.mod_params.temperature_unit = TemperatureUnit::Fahrenheit;
+        m.ch_params[0].measurement_range = RtdRange::PT100;
+        m.ch_params[1].measurement_range = RtdRange::R150;
+        let info = m.channel_info();
+        assert_eq!(info.len(), 4);
+        assert_eq!(info[0].unit, Some(Unit::Fahrenheit));
+        assert_eq!(info[1].unit, Some(Unit::Ohm));
+        assert!(info[2].unit.is_none());
+        assert_eq!(info[0].range.as_deref(), Some("PT100"));
+    }
+
     #[test]
     fn test_process_input_data_with_empty_buffer() {
         let m = Mod::default();
-        assert!(m.process_input_data(&vec![]).is_err());
+        assert!(m.process_input_data(&[]).is_err());
     }
 
     #[test]
     fn test_process_input_data_with_missing_channel_parameters() {
         let mut m = Mod::default();
         m.ch_params = vec![];
-        assert!(m.process_input_data(&vec![0, 0, 0, 0]).is_err());
+        assert!(m.process_input_data(&[0, 0, 0, 0]).is_err());
     }
 
     #[test]
     fn test_process_input_data_with_disabled_channels() {
         let m = Mod::default();
         assert_eq!(
-            m.process_input_data(&vec![5, 0, 7, 8]).unwrap(),
+            m.process_input_data(&[5, 0, 7, 8]).unwrap(),
             vec![Disabled, Disabled, Disabled, Disabled]
         );
     }
@@ -207,7 +310,7 @@ mod tests {
         m.ch_params[3].measurement_range = RtdRange::PT1000;
 
         assert_eq!(
-            m.process_input_data(&vec![0x6C00, 0x7EFF, 55, 99]).unwrap(),
+            m.process_input_data(&[0x6C00, 0x7EFF, 55, 99]).unwrap(),
             vec![
                 Decimal32(40.0),
                 Decimal32(47.03559),
@@ -224,7 +327,7 @@ mod tests {
         m.ch_params[1].measurement_range = RtdRange::Cu10;
 
         assert_eq!(
-            m.process_input_data(&vec![0xF830, 0xFF38, 0, 0]).unwrap(),
+            m.process_input_data(&[0xF830, 0xFF38, 0, 0]).unwrap(),
             vec![Decimal32(-200.0), Decimal32(-20.0), Disabled, Disabled]
         );
     }
@@ -237,7 +340,7 @@ mod tests {
         m.ch_params[1].measurement_range = RtdRange::NI1000;
 
         let input = m
-            .process_input_data(&vec![(-2040_i16 as u16), (-640_i16 as u16), 0, 0])
+            .process_input_data(&[(-2040_i16 as u16), (-640_i16 as u16), 0, 0])
             .unwrap();
 
         if let ChannelValue::Decimal32(v) = input[0] {
@@ -256,7 +359,7 @@ mod tests {
     #[test]
     fn test_process_output_data() {
         let m = Mod::default();
-        assert!(m.process_output_data(&vec![0; 4]).is_err());
+        assert!(m.process_output_data(&[0; 4]).is_err());
         assert_eq!(
             m.process_output_data(&[]).unwrap(),
             vec![ChannelValue::None; 4]
@@ -269,11 +372,11 @@ mod tests {
         assert!(m
             .process_output_values(&[ChannelValue::Decimal32(0.0)])
             .is_err());
-        assert_eq!(m.process_output_values(&[]).unwrap(), &[]);
+        assert_eq!(m.process_output_values(&[]).unwrap(), Vec::<u16>::new());
         assert_eq!(
             m.process_output_values(&vec![ChannelValue::None; 4])
                 .unwrap(),
-            &[]
+            Vec::<u16>::new()
         );
     }
 
@@ -336,23 +439,17 @@ mod tests {
             ConversionTime::ms130
         );
 
-        assert_eq!(
-            parameters_from_raw_data(&data).unwrap().1[3].channel_diagnostics,
-            true
-        );
+        assert!(parameters_from_raw_data(&data).unwrap().1[3].channel_diagnostics);
 
-        assert_eq!(
-            parameters_from_raw_data(&data).unwrap().1[3].limit_value_monitoring,
-            true
-        );
+        assert!(parameters_from_raw_data(&data).unwrap().1[3].limit_value_monitoring);
         assert_eq!(
             parameters_from_raw_data(&data).unwrap().1[3].high_limit_value,
-            ::std::i16::MAX
+            i16::MAX
         );
 
         assert_eq!(
             parameters_from_raw_data(&data).unwrap().1[3].low_limit_value,
-            ::std::i16::MIN
+            i16::MIN
         );
     }
 
@@ -402,4 +499,79 @@ mod tests {
         assert_eq!(module.ch_params[0].measurement_range, RtdRange::PT200);
         assert_eq!(module.ch_params[1].measurement_range, RtdRange::Disabled);
     }
+
+    #[test]
+    fn test_parameters_to_raw_data() {
+        let mut m = Mod::default();
+        m.mod_params.temperature_unit = TemperatureUnit::Fahrenheit;
+        m.ch_params[1].measurement_range = RtdRange::PT100;
+        m.ch_params[1].limit_value_monitoring = true;
+        m.ch_params[1].high_limit_value = 1000;
+        m.ch_params[1].low_limit_value = -200;
+        let data = m.to_modbus_parameter_data();
+        assert_eq!(data.len(), 29);
+        assert_eq!(data[0], 1);
+        assert_eq!(data[13..15], [1000, 0xFF38]);
+        let (mod_params, ch_params) = parameters_from_raw_data(&data).unwrap();
+        assert_eq!(mod_params, m.mod_params);
+        assert_eq!(ch_params, m.ch_params);
+    }
+
+    #[test]
+    fn filter_unmonitored_diagnosis() {
+        let mut data = vec![0; 8];
+        data[2] = 0b0001;
+        data[4] = 0b1100_0000;
+        let mut m = Mod::default();
+        assert_eq!(m.process_diagnostic_data(&data).unwrap()[0], vec![]);
+        m.ch_params[0].channel_diagnostics = true;
+        assert_eq!(
+            m.process_diagnostic_data(&data).unwrap()[0],
+            vec![ChannelDiagnosis::WireBreak]
+        );
+        m.ch_params[0].limit_value_monitoring = true;
+        assert_eq!(
+            m.process_diagnostic_data(&data).unwrap()[0],
+            vec![ChannelDiagnosis::WireBreak, ChannelDiagnosis::LimitExceeded]
+        );
+    }
+
+    #[test]
+    fn test_process_input_data_with_status() {
+        let mut m = Mod::default();
+        for p in &mut m.ch_params {
+            p.measurement_range = RtdRange::PT100;
+            p.high_limit_value = 1000;
+            p.low_limit_value = -200;
+        }
+        m.ch_params[0].limit_value_monitoring = true;
+        m.ch_params[1].limit_value_monitoring = true;
+        m.ch_params[2].limit_value_monitoring = true;
+        m.ch_params[3].measurement_range = RtdRange::Disabled;
+        let (values, status) = m
+            .process_input_data_with_status(&[1001, 0xFF37, 1000, 1001])
+            .unwrap();
+        assert_eq!(
+            values,
+            vec![
+                Decimal32(100.1),
+                Decimal32(-20.1),
+                Decimal32(100.0),
+                Disabled
+            ]
+        );
+        assert_eq!(
+            status,
+            vec![
+                Some(LimitStatus::AboveHigh),
+                Some(LimitStatus::BelowLow),
+                Some(LimitStatus::Ok),
+                Option::None,
+            ]
+        );
+        m.ch_params[0].limit_value_monitoring = false;
+        let (_, status) = m.process_input_data_with_status(&[1001, 0, 0, 0]).unwrap();
+        assert_eq!(status[0], Option::None);
+        assert!(m.process_input_data_with_status(&[0; 3]).is_err());
+    }
 }
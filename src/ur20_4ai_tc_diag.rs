@@ -0,0 +1,528 @@
+//! Analog input module UR20-4AI-TC-DIAG
+//!
+//! If a thermocouple is measured with a voltage range,
+//! the readings can optionally be checked against the
+//! thermoelectric voltage limits of the connected thermocouple type
+//! (see `Mod::plausibility_checks`).
+
+use super::*;
+use crate::diagnosis::{
+    filter_channel_diagnostics, ChannelDiagnosis, ChannelDiagnostics, Diagnostics,
+};
+use crate::ur20_fbc_mod_tcp::{
+    FromModbusParameterData, ProcessDataLayout, ProcessModbusTcpData, ToModbusParameterData,
+};
+use num_traits::cast::{FromPrimitive, ToPrimitive};
+
+#[derive(Debug, Clone)]
+pub struct Mod {
+    pub mod_params: ModuleParameters,
+    pub ch_params: Vec<ChannelParameters>,
+    /// The thermocouple type that is connected to a channel
+    /// with a voltage range (`None` disables the check).
+    pub plausibility_checks: Vec<Option<TcRange>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(default))]
+pub struct ModuleParameters {
+    pub temperature_unit: TemperatureUnit,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(default))]
+pub struct ChannelParameters {
+    pub measurement_range: TcRange,
+    pub cold_junction_compensation: ColdJunctionCompensation,
+    pub conversion_time: ConversionTime,
+    pub channel_diagnostics: bool,
+    pub limit_value_monitoring: bool,
+    //-32768 ... 32767
+    pub high_limit_value: i16,
+    //-32768 ... 32767
+    pub low_limit_value: i16,
+}
+
+/// A voltage reading that is not possible
+/// for the expected thermocouple type.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChannelWarning {
+    pub channel: usize,
+    /// Measured voltage in mV
+    pub voltage: f32,
+    /// The expected thermocouple type.
+    pub tc_type: TcRange,
+}
+
+impl FromModbusParameterData for Mod {
+    fn from_modbus_parameter_data(data: &[u16]) -> Result<Mod> {
+        let (mod_params, ch_params) = parameters_from_raw_data(data)?;
+        Ok(Mod {
+            mod_params,
+            ch_params,
+            plausibility_checks: vec![None; 4],
+        })
+    }
+}
+
+impl ToModbusParameterData for Mod {
+    fn to_modbus_parameter_data(&self) -> Vec<u16> {
+        parameters_to_raw_data(&self.mod_params, &self.ch_params)
+    }
+}
+
+impl Default for ModuleParameters {
+    fn default() -> Self {
+        ModuleParameters {
+            temperature_unit: TemperatureUnit::Celsius,
+        }
+    }
+}
+
+impl Default for ChannelParameters {
+    fn default() -> Self {
+        ChannelParameters {
+            measurement_range: TcRange::Disabled,
+            cold_junction_compensation: ColdJunctionCompensation::Internal,
+            conversion_time: ConversionTime::ms80,
+            channel_diagnostics: false,
+            limit_value_monitoring: false,
+            high_limit_value: 0,
+            low_limit_value: 0,
+        }
+    }
+}
+
+impl Default for Mod {
+    fn default() -> Self {
+        let ch_params = (0..4).map(|_| ChannelParameters::default()).collect();
+
+        let mod_params = ModuleParameters::default();
+
+        Mod {
+            mod_params,
+            ch_params,
+            plausibility_checks: vec![None; 4],
+        }
+    }
+}
+
+impl Mod {
+    /// Checks voltage readings against the limits
+    /// of the expected thermocouple types.
+    ///
+    /// Only channels with a voltage range and an expected
+    /// thermocouple type are checked. A reading outside of the
+    /// limits usually means that the wrong range is configured
+    /// or that the wrong thermocouple is connected.
+    pub fn plausibility_warnings(&self, values: &[ChannelValue]) -> Vec<ChannelWarning> {
+        values
+            .iter()
+            .zip(&self.ch_params)
+            .zip(&self.plausibility_checks)
+            .enumerate()
+            .filter_map(|(channel, ((v, p), tc_type))| {
+                let tc_type = tc_type.as_ref()?;
+                if !is_voltage_range(&p.measurement_range) {
+                    return None;
+                }
+                let (min, max) = emf_limits(tc_type)?;
+                let voltage = match *v {
+                    ChannelValue::Decimal32(v) => v * 1000.0,
+                    _ => return None,
+                };
+                if voltage < min || voltage > max {
+                    Some(ChannelWarning {
+                        channel,
+                        voltage,
+                        tc_type: tc_type.clone(),
+                    })
+                } else {
+                    None
+                }
+            })
+            .collect()
+    }
+
+    /// Decodes the input data (see `process_input_data`) together
+    /// with the limit value status of each channel.
+    ///
+    /// The status is `None` for disabled channels and
+    /// channels without limit value monitoring.
+    pub fn process_input_data_with_status(
+        &self,
+        data: &[u16],
+    ) -> Result<(Vec<ChannelValue>, Vec<Option<LimitStatus>>)> {
+        let values = self.process_input_data(data)?;
+        let status = values
+            .iter()
+            .zip(data)
+            .zip(&self.ch_params)
+            .map(|((v, raw), p)| {
+                if !p.limit_value_monitoring || *v == ChannelValue::Disabled {
+                    return None;
+                }
+                Some(LimitStatus::from_raw_value(
+                    *raw as i16,
+                    p.low_limit_value,
+                    p.high_limit_value,
+                ))
+            })
+            .collect();
+        Ok((values, status))
+    }
+}
+
+impl Module for Mod {
+    fn module_type(&self) -> ModuleType {
+        ModuleType::UR20_4AI_TC_DIAG
+    }
+    fn channel_info(&self) -> Vec<ChannelInfo> {
+        self.ch_params
+            .iter()
+            .map(|p| ChannelInfo {
+                unit: p.measurement_range.unit(&self.mod_params.temperature_unit),
+                range: Some(format!("{:?}", p.measurement_range)),
+                ..Default::default()
+            })
+            .collect()
+    }
+}
+
+impl Diagnostics for Mod {
+    /// Drops the causes that are not monitored.
+    fn process_diagnostic_data(&self, data: &[u16]) -> Result<Vec<ChannelDiagnostics>> {
+        filter_channel_diagnostics(&self.module_type(), data, |ch, cause| {
+            self.ch_params.get(ch).map_or(true, |p| match cause {
+                ChannelDiagnosis::LimitExceeded => p.limit_value_monitoring,
+                _ => p.channel_diagnostics,
+            })
+        })
+    }
+}
+
+impl ProcessModbusTcpData for Mod {
+    fn process_input_byte_count(&self) -> usize {
+        8
+    }
+    fn process_output_byte_count(&self) -> usize {
+        0
+    }
+    fn diagnostics(&self) -> Option<&dyn Diagnostics> {
+        Some(self)
+    }
+    fn layout(&self) -> ProcessDataLayout {
+        ProcessDataLayout {
+            input: util::channel_layout(4),
+            output: vec![],
+        }
+    }
+    fn process_input_data(&self, data: &[u16]) -> Result<Vec<ChannelValue>> {
+        if data.len() != 4 {
+            return Err(Error::BufferLength);
+        }
+
+        util::check_channel_count(self, &self.ch_params)?;
+        let res = (0..4)
+            .map(|i| (data[i], &self.ch_params[i].measurement_range))
+            .map(|(val, range)| match codec::u16_to_tc_value(val, range) {
+                Some(v) => ChannelValue::Decimal32(v),
+                None => ChannelValue::Disabled,
+            })
+            .collect();
+        Ok(res)
+    }
+}
+
+fn parameters_from_raw_data(data: &[u16]) -> Result<(ModuleParameters, Vec<ChannelParameters>)> {
+    if data.len() < 29 {
+        return Err(Error::BufferLength);
+    }
+    let mut module_parameters = ModuleParameters::default();
+
+    module_parameters.temperature_unit = match FromPrimitive::from_u16(data[0]) {
+        Some(x) => x,
+        _ => {
+            return Err(Error::ChannelParameter);
+        }
+    };
+
+    let channel_parameters: Result<Vec<_>> = (0..4)
+        .map(|i| {
+            let mut p = ChannelParameters::default();
+            let idx = i * 7;
+
+            p.measurement_range = match FromPrimitive::from_u16(data[idx + 1]) {
+                Some(x) => x,
+                _ => {
+                    return Err(Error::ChannelParameter);
+                }
+            };
+
+            p.cold_junction_compensation = match FromPrimitive::from_u16(data[idx + 2]) {
+                Some(x) => x,
+                _ => {
+                    return Err(Error::ChannelParameter);
+                }
+            };
+
+            p.conversion_time = match FromPrimitive::from_u16(data[idx + 3]) {
+                Some(x) => x,
+                _ => {
+                    return Err(Error::ChannelParameter);
+                }
+            };
+
+            p.channel_diagnostics = match data[idx + 4] {
+                0 => false,
+                1 => true,
+                _ => {
+                    return Err(Error::ChannelParameter);
+                }
+            };
+
+            p.limit_value_monitoring = match data[idx + 5] {
+                0 => false,
+                1 => true,
+                _ => {
+                    return Err(Error::ChannelParameter);
+                }
+            };
+
+            p.high_limit_value = data[idx + 6] as i16;
+            p.low_limit_value = data[idx + 7] as i16;
+
+            Ok(p)
+        })
+        .collect();
+    Ok((module_parameters, channel_parameters?))
+}
+
+fn is_voltage_range(range: &TcRange) -> bool {
+    use crate::TcRange::*;
+    matches!(*range, mV15 | mV31 | mV62 | mV125 | mV250 | mV500 | mV1000)
+}
+
+/// Thermoelectric voltage (mV) at the lower and upper temperature limit
+/// of a thermocouple type (reference junction at 0 °C).
+///
+/// The values are taken from the IEC 60584-1 (ITS-90) tables,
+/// DIN 43710 (types L and U) and ASTM E988 (type C).
+#[rustfmt::skip]
+fn emf_limits(tc_type: &TcRange) -> Option<(f32, f32)> {
+    use crate::TcRange::*;
+    let limits = match *tc_type {
+        J => (-8.095, 69.553),
+        K => (-6.458, 54.886),
+        N => (-4.345, 47.513),
+        R => (-0.226, 21.103),
+        S => (-0.236, 18.693),
+        T => (-6.258, 20.872),
+        B => (-0.003, 13.820),
+        C => ( 0.000, 37.066),
+        E => (-9.835, 76.373),
+        L => (-8.150, 53.140),
+        U => (-5.700, 34.310),
+        _ => return None,
+    };
+    Some(limits)
+}
+
+fn parameters_to_raw_data(
+    mod_params: &ModuleParameters,
+    ch_params: &[ChannelParameters],
+) -> Vec<u16> {
+    let mut data = vec![mod_params.temperature_unit.to_u16().unwrap()];
+    for p in ch_params {
+        data.push(p.measurement_range.to_u16().unwrap());
+        data.push(p.cold_junction_compensation.to_u16().unwrap());
+        data.push(p.conversion_time.to_u16().unwrap());
+        data.push(u16::from(p.channel_diagnostics));
+        data.push(u16::from(p.limit_value_monitoring));
+        data.push(p.high_limit_value as u16);
+        data.push(p.low_limit_value as u16);
+    }
+    data
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::ChannelValue::*;
+
+    #[test]
+    fn channel_info() {
+        let mut m = Mod::default();
+        m.mod_params.temperature_unit = TemperatureUnit::Kelvin;
+        m.ch_params[0].measurement_range = TcRange::K;
+        m.ch_params[1].measurement_range = TcRange::mV62;
+        let info = m.channel_info();
+        assert_eq!(info.len(), 4);
+        assert_eq!(info[0].unit, Some(Unit::Kelvin));
+        assert_eq!(info[1].unit, Some(Unit::Volt));
+        assert!(info[2].unit.is_none());
+    }
+
+    #[test]
+    fn test_process_input_data_with_empty_buffer() {
+        let m = Mod::default();
+        assert!(m.process_input_data(&[]).is_err());
+    }
+
+    #[test]
+    fn test_process_input_data_with_missing_channel_parameters() {
+        let mut m = Mod::default();
+        m.ch_params = vec![];
+        assert!(m.process_input_data(&[0, 0, 0, 0]).is_err());
+    }
+
+    #[test]
+    fn test_process_input_data() {
+        let mut m = Mod::default();
+
+        m.ch_params[0].measurement_range = TcRange::J;
+        m.ch_params[1].measurement_range = TcRange::K;
+        m.ch_params[2].measurement_range = TcRange::mV1000;
+
+        assert_eq!(
+            m.process_input_data(&[0xFF38, 2505, 0x3600, 99]).unwrap(),
+            vec![Decimal32(-20.0), Decimal32(250.5), Decimal32(0.5), Disabled]
+        );
+    }
+
+    #[test]
+    fn test_process_output_data() {
+        let m = Mod::default();
+        assert!(m.process_output_data(&[0; 4]).is_err());
+        assert_eq!(
+            m.process_output_data(&[]).unwrap(),
+            vec![ChannelValue::None; 4]
+        );
+    }
+
+    #[test]
+    fn test_channel_parameters_from_raw_data() {
+        #[rustfmt::skip]
+        let data = vec![
+            1,                               // Module
+            18, 0, 2, 0, 0, 0, 0,            // CH 0
+            7,  1, 0, 0, 0, 0, 0,            // CH 1
+            17, 0, 1, 0, 0, 0, 0,            // CH 2
+            18, 0, 2, 1, 1, 0x7FFF, 0x8000,  // CH 3
+        ];
+
+        let (m, ch) = parameters_from_raw_data(&data).unwrap();
+        assert_eq!(m.temperature_unit, TemperatureUnit::Fahrenheit);
+        assert_eq!(ch.len(), 4);
+        assert_eq!(ch[0], ChannelParameters::default());
+        assert_eq!(ch[1].measurement_range, TcRange::C);
+        assert_eq!(
+            ch[1].cold_junction_compensation,
+            ColdJunctionCompensation::External
+        );
+        assert_eq!(ch[2].measurement_range, TcRange::mV1000);
+        assert_eq!(ch[2].conversion_time, ConversionTime::ms130);
+        assert!(ch[3].channel_diagnostics);
+        assert!(ch[3].limit_value_monitoring);
+        assert_eq!(ch[3].high_limit_value, i16::MAX);
+        assert_eq!(ch[3].low_limit_value, i16::MIN);
+    }
+
+    #[test]
+    fn test_parameters_from_invalid_raw_data() {
+        let mut data = vec![0; 29];
+        data[0] = 3; // should be max '2'
+        assert!(parameters_from_raw_data(&data).is_err());
+
+        data[0] = 0;
+        data[1] = 19; // should be max '18'
+        assert!(parameters_from_raw_data(&data).is_err());
+
+        data[1] = 0;
+        data[2] = 2; // should be max '1'
+        assert!(parameters_from_raw_data(&data).is_err());
+
+        data[2] = 0;
+        data[4] = 2;
+        assert!(parameters_from_raw_data(&data).is_err());
+    }
+
+    #[test]
+    fn test_parameters_from_invalid_data_buffer_size() {
+        assert!(parameters_from_raw_data(&[0; 28]).is_err());
+        assert!(parameters_from_raw_data(&[0; 29]).is_ok());
+    }
+
+    #[test]
+    fn test_parameters_to_raw_data() {
+        let mut m = Mod::default();
+        m.mod_params.temperature_unit = TemperatureUnit::Fahrenheit;
+        m.ch_params[1].measurement_range = TcRange::K;
+        m.ch_params[1].limit_value_monitoring = true;
+        m.ch_params[1].high_limit_value = 1000;
+        m.ch_params[1].low_limit_value = -200;
+        let data = m.to_modbus_parameter_data();
+        assert_eq!(data.len(), 29);
+        assert_eq!(data[0], 1);
+        assert_eq!(data[13..15], [1000, 0xFF38]);
+        let (mod_params, ch_params) = parameters_from_raw_data(&data).unwrap();
+        assert_eq!(mod_params, m.mod_params);
+        assert_eq!(ch_params, m.ch_params);
+    }
+
+    #[test]
+    fn check_plausibility_of_voltage_readings() {
+        let mut m = Mod::default();
+        m.ch_params[0].measurement_range = TcRange::mV62;
+        m.ch_params[1].measurement_range = TcRange::mV125;
+        m.ch_params[2].measurement_range = TcRange::K;
+        m.plausibility_checks = vec![
+            Some(TcRange::K),
+            Some(TcRange::S),
+            Some(TcRange::K),
+            Option::None,
+        ];
+        let values = vec![
+            Decimal32(0.0412),
+            Decimal32(0.0150),
+            Decimal32(900.0),
+            Decimal32(0.1),
+        ];
+        assert!(m.plausibility_warnings(&values).is_empty());
+
+        let values = vec![
+            Decimal32(0.0600),
+            Decimal32(-0.0010),
+            Decimal32(900.0),
+            Decimal32(0.1),
+        ];
+        let warnings = m.plausibility_warnings(&values);
+        assert_eq!(warnings.len(), 2);
+        assert_eq!(warnings[0].channel, 0);
+        assert_eq!(warnings[0].tc_type, TcRange::K);
+        assert_eq!(warnings[1].channel, 1);
+        assert!((warnings[1].voltage + 1.0).abs() < 1e-3);
+
+        m.plausibility_checks = vec![Option::None; 4];
+        assert!(m.plausibility_warnings(&values).is_empty());
+        assert!(m.plausibility_warnings(&vec![Disabled; 4]).is_empty());
+    }
+
+    #[test]
+    fn test_process_input_data_with_status() {
+        let mut m = Mod::default();
+        m.ch_params[0].measurement_range = TcRange::K;
+        m.ch_params[0].limit_value_monitoring = true;
+        m.ch_params[0].high_limit_value = 500;
+        m.ch_params[0].low_limit_value = 0;
+        let (values, status) = m.process_input_data_with_status(&[501, 0, 0, 0]).unwrap();
+        assert_eq!(values[0], Decimal32(50.1));
+        assert_eq!(status[0], Some(LimitStatus::AboveHigh));
+        assert_eq!(status[1], Option::None);
+        let (_, status) = m
+            .process_input_data_with_status(&[0xFFFF, 0, 0, 0])
+            .unwrap();
+        assert_eq!(status[0], Some(LimitStatus::BelowLow));
+    }
+}
@@ -1,21 +1,24 @@
 //! Analog input module UR20-4AI-UI-12
 
 use super::*;
-use crate::ur20_fbc_mod_tcp::{FromModbusParameterData, ProcessModbusTcpData};
-use num_traits::cast::FromPrimitive;
+use crate::codec::AnalogScaling;
+use crate::ur20_fbc_mod_tcp::{
+    FromModbusParameterData, ProcessDataLayout, ProcessModbusTcpData, ToModbusParameterData,
+};
+use num_traits::cast::{FromPrimitive, ToPrimitive};
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Mod {
     pub mod_params: ModuleParameters,
     pub ch_params: Vec<ChannelParameters>,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct ModuleParameters {
     pub frequency_suppression: FrequencySuppression,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct ChannelParameters {
     pub data_format: DataFormat,
     pub measurement_range: AnalogUIRange,
@@ -31,6 +34,12 @@ impl FromModbusParameterData for Mod {
     }
 }
 
+impl ToModbusParameterData for Mod {
+    fn to_modbus_parameter_data(&self) -> Vec<u16> {
+        parameters_to_raw_data(&self.mod_params, &self.ch_params)
+    }
+}
+
 impl Default for ModuleParameters {
     fn default() -> Self {
         ModuleParameters {
@@ -63,6 +72,16 @@ impl Module for Mod {
     fn module_type(&self) -> ModuleType {
         ModuleType::UR20_4AI_UI_12
     }
+    fn channel_info(&self) -> Vec<ChannelInfo> {
+        self.ch_params
+            .iter()
+            .map(|p| ChannelInfo {
+                unit: p.measurement_range.unit(),
+                range: Some(format!("{:?}", p.measurement_range)),
+                ..Default::default()
+            })
+            .collect()
+    }
 }
 
 impl ProcessModbusTcpData for Mod {
@@ -72,14 +91,24 @@ impl ProcessModbusTcpData for Mod {
     fn process_output_byte_count(&self) -> usize {
         0
     }
+    fn analog_scalings(&self) -> Vec<Option<AnalogScaling>> {
+        self.ch_params
+            .iter()
+            .map(|p| AnalogScaling::ui(&p.measurement_range, &p.data_format))
+            .collect()
+    }
+    fn layout(&self) -> ProcessDataLayout {
+        ProcessDataLayout {
+            input: util::channel_layout(4),
+            output: vec![],
+        }
+    }
     fn process_input_data(&self, data: &[u16]) -> Result<Vec<ChannelValue>> {
         if data.len() != 4 {
             return Err(Error::BufferLength);
         }
 
-        if self.ch_params.len() != 4 {
-            return Err(Error::ChannelParameter);
-        }
+        util::check_channel_count(self, &self.ch_params)?;
 
         let res = (0..4)
             .map(|i| {
@@ -90,7 +119,7 @@ impl ProcessModbusTcpData for Mod {
                 )
             })
             .map(
-                |(val, range, format)| match util::u16_to_analog_ui_value(val, range, format) {
+                |(val, range, format)| match codec::u16_to_analog_ui_value(val, range, format) {
                     Some(v) => ChannelValue::Decimal32(v),
                     None => ChannelValue::Disabled,
                 },
@@ -111,8 +140,7 @@ fn parameters_from_raw_data(data: &[u16]) -> Result<(ModuleParameters, Vec<Chann
         return Err(Error::BufferLength);
     }
 
-    let frequency_suppression =
-        FromPrimitive::from_u16(data[0]).ok_or_else(|| Error::ChannelParameter)?;
+    let frequency_suppression = FromPrimitive::from_u16(data[0]).ok_or(Error::ChannelParameter)?;
 
     let module_parameters = ModuleParameters {
         frequency_suppression,
@@ -123,15 +151,27 @@ fn parameters_from_raw_data(data: &[u16]) -> Result<(ModuleParameters, Vec<Chann
             let mut p = ChannelParameters::default();
             let idx = i * 2;
             p.data_format =
-                FromPrimitive::from_u16(data[idx + 1]).ok_or_else(|| Error::ChannelParameter)?;
+                FromPrimitive::from_u16(data[idx + 1]).ok_or(Error::ChannelParameter)?;
             p.measurement_range =
-                FromPrimitive::from_u16(data[idx + 2]).ok_or_else(|| Error::ChannelParameter)?;
+                FromPrimitive::from_u16(data[idx + 2]).ok_or(Error::ChannelParameter)?;
             Ok(p)
         })
         .collect();
     Ok((module_parameters, channel_parameters?))
 }
 
+fn parameters_to_raw_data(
+    mod_params: &ModuleParameters,
+    ch_params: &[ChannelParameters],
+) -> Vec<u16> {
+    let mut data = vec![mod_params.frequency_suppression.to_u16().unwrap()];
+    for p in ch_params {
+        data.push(p.data_format.to_u16().unwrap());
+        data.push(p.measurement_range.to_u16().unwrap());
+    }
+    data
+}
+
 #[cfg(test)]
 mod tests {
 
@@ -141,14 +181,14 @@ mod tests {
     #[test]
     fn test_process_input_data_with_empty_buffer() {
         let m = Mod::default();
-        assert!(m.process_input_data(&vec![]).is_err());
+        assert!(m.process_input_data(&[]).is_err());
     }
 
     #[test]
     fn test_process_input_data_with_missing_channel_parameters() {
         let mut m = Mod::default();
         m.ch_params = vec![];
-        assert!(m.process_input_data(&vec![0; 4]).is_err());
+        assert!(m.process_input_data(&[0; 4]).is_err());
     }
 
     #[test]
@@ -159,7 +199,7 @@ mod tests {
         assert_eq!(m.ch_params[2].measurement_range, AnalogUIRange::Disabled);
         assert_eq!(m.ch_params[3].measurement_range, AnalogUIRange::Disabled);
         assert_eq!(
-            m.process_input_data(&vec![5, 0, 7, 8]).unwrap(),
+            m.process_input_data(&[5, 0, 7, 8]).unwrap(),
             vec![Disabled; 4]
         );
 
@@ -171,7 +211,7 @@ mod tests {
         m.ch_params[2].data_format = DataFormat::S5;
 
         assert_eq!(
-            m.process_input_data(&vec![0x6C00, 0x3600, 0x4000, 0x6C00])
+            m.process_input_data(&[0x6C00, 0x3600, 0x4000, 0x6C00])
                 .unwrap(),
             vec![
                 Decimal32(20.0),
@@ -192,7 +232,7 @@ mod tests {
         m.ch_params[1].measurement_range = AnalogUIRange::mA4To20;
         m.ch_params[1].data_format = DataFormat::S5;
 
-        let input = m.process_input_data(&vec![0xED00, 0x0F333, 0, 0]).unwrap();
+        let input = m.process_input_data(&[0xED00, 0x0F333, 0, 0]).unwrap();
 
         if let ChannelValue::Decimal32(v) = input[0] {
             assert!((v - 1.19).abs() < 0.01);
@@ -209,7 +249,7 @@ mod tests {
     #[test]
     fn test_process_output_data() {
         let m = Mod::default();
-        assert!(m.process_output_data(&vec![0; 4]).is_err());
+        assert!(m.process_output_data(&[0; 4]).is_err());
         assert_eq!(
             m.process_output_data(&[]).unwrap(),
             vec![ChannelValue::None; 4]
@@ -222,11 +262,11 @@ mod tests {
         assert!(m
             .process_output_values(&[ChannelValue::Decimal32(0.0)])
             .is_err());
-        assert_eq!(m.process_output_values(&[]).unwrap(), &[]);
+        assert_eq!(m.process_output_values(&[]).unwrap(), Vec::<u16>::new());
         assert_eq!(
             m.process_output_values(&vec![ChannelValue::None; 4])
                 .unwrap(),
-            &[]
+            Vec::<u16>::new()
         );
     }
 
@@ -330,4 +370,17 @@ mod tests {
             AnalogUIRange::Disabled
         );
     }
+
+    #[test]
+    fn test_parameters_to_raw_data() {
+        let mut m = Mod::default();
+        m.mod_params.frequency_suppression = FrequencySuppression::Hz60;
+        m.ch_params[1].data_format = DataFormat::S5;
+        m.ch_params[1].measurement_range = AnalogUIRange::V1To5;
+        let data = m.to_modbus_parameter_data();
+        assert_eq!(data, vec![2, 1, 8, 0, 6, 1, 8, 1, 8]);
+        let (mod_params, ch_params) = parameters_from_raw_data(&data).unwrap();
+        assert_eq!(mod_params, m.mod_params);
+        assert_eq!(ch_params, m.ch_params);
+    }
 }
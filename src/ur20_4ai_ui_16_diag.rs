@@ -1,21 +1,27 @@
 //! Analog input module UR20-4AI-UI-16-DIAG
 
 use super::*;
-use crate::ur20_fbc_mod_tcp::{FromModbusParameterData, ProcessModbusTcpData};
-use num_traits::cast::FromPrimitive;
-
-#[derive(Debug)]
+use crate::codec::AnalogScaling;
+use crate::diagnosis::{
+    filter_channel_diagnostics, ChannelDiagnosis, ChannelDiagnostics, Diagnostics,
+};
+use crate::ur20_fbc_mod_tcp::{
+    FromModbusParameterData, ProcessDataLayout, ProcessModbusTcpData, ToModbusParameterData,
+};
+use num_traits::cast::{FromPrimitive, ToPrimitive};
+
+#[derive(Debug, Clone)]
 pub struct Mod {
     pub mod_params: ModuleParameters,
     pub ch_params: Vec<ChannelParameters>,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct ModuleParameters {
     pub frequency_suppression: FrequencySuppression,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct ChannelParameters {
     pub channel_diagnostics: bool,
     pub diag_short_circuit: bool,
@@ -34,6 +40,12 @@ impl FromModbusParameterData for Mod {
     }
 }
 
+impl ToModbusParameterData for Mod {
+    fn to_modbus_parameter_data(&self) -> Vec<u16> {
+        parameters_to_raw_data(&self.mod_params, &self.ch_params)
+    }
+}
+
 impl Default for ModuleParameters {
     fn default() -> Self {
         ModuleParameters {
@@ -65,10 +77,56 @@ impl Default for Mod {
     }
 }
 
+impl Mod {
+    /// Decodes the input data like `process_input_data`
+    /// but reports values outside of the measurement range.
+    ///
+    /// Disabled channels are `None`.
+    pub fn analog_input_values(&self, data: &[u16]) -> Result<Vec<Option<AnalogValue>>> {
+        if data.len() != 4 {
+            return Err(Error::BufferLength);
+        }
+        util::check_channel_count(self, &self.ch_params)?;
+        Ok(data
+            .iter()
+            .zip(&self.ch_params)
+            .map(|(v, p)| {
+                codec::u16_to_checked_analog_ui_value(*v, &p.measurement_range, &p.data_format)
+            })
+            .collect())
+    }
+}
+
 impl Module for Mod {
     fn module_type(&self) -> ModuleType {
         ModuleType::UR20_4AI_UI_16_DIAG
     }
+    fn channel_info(&self) -> Vec<ChannelInfo> {
+        self.ch_params
+            .iter()
+            .map(|p| ChannelInfo {
+                unit: p.measurement_range.unit(),
+                range: Some(format!("{:?}", p.measurement_range)),
+                ..Default::default()
+            })
+            .collect()
+    }
+}
+
+impl Diagnostics for Mod {
+    /// Drops the causes that are not monitored.
+    fn process_diagnostic_data(&self, data: &[u16]) -> Result<Vec<ChannelDiagnostics>> {
+        filter_channel_diagnostics(&self.module_type(), data, |ch, cause| {
+            self.ch_params.get(ch).map_or(true, |p| {
+                p.channel_diagnostics
+                    && match cause {
+                        ChannelDiagnosis::ShortCircuit => p.diag_short_circuit,
+                        ChannelDiagnosis::WireBreak => p.diag_line_break,
+                        _ => true,
+                    }
+            })
+        })
+    }
 }
 
 impl ProcessModbusTcpData for Mod {
@@ -78,14 +136,27 @@ impl ProcessModbusTcpData for Mod {
     fn process_output_byte_count(&self) -> usize {
         0
     }
+    fn analog_scalings(&self) -> Vec<Option<AnalogScaling>> {
+        self.ch_params
+            .iter()
+            .map(|p| AnalogScaling::ui(&p.measurement_range, &p.data_format))
+            .collect()
+    }
+    fn diagnostics(&self) -> Option<&dyn Diagnostics> {
+        Some(self)
+    }
+    fn layout(&self) -> ProcessDataLayout {
+        ProcessDataLayout {
+            input: util::channel_layout(4),
+            output: vec![],
+        }
+    }
     fn process_input_data(&self, data: &[u16]) -> Result<Vec<ChannelValue>> {
         if data.len() != 4 {
             return Err(Error::BufferLength);
         }
 
-        if self.ch_params.len() != 4 {
-            return Err(Error::ChannelParameter);
-        }
+        util::check_channel_count(self, &self.ch_params)?;
 
         let res = (0..4)
             .map(|i| {
@@ -96,7 +167,7 @@ impl ProcessModbusTcpData for Mod {
                 )
             })
             .map(
-                |(val, range, format)| match util::u16_to_analog_ui_value(val, range, format) {
+                |(val, range, format)| match codec::u16_to_analog_ui_value(val, range, format) {
                     Some(v) => ChannelValue::Decimal32(v),
                     None => ChannelValue::Disabled,
                 },
@@ -117,8 +188,7 @@ fn parameters_from_raw_data(data: &[u16]) -> Result<(ModuleParameters, Vec<Chann
         return Err(Error::BufferLength);
     }
 
-    let frequency_suppression =
-        FromPrimitive::from_u16(data[0]).ok_or_else(|| Error::ChannelParameter)?;
+    let frequency_suppression = FromPrimitive::from_u16(data[0]).ok_or(Error::ChannelParameter)?;
 
     let module_parameters = ModuleParameters {
         frequency_suppression,
@@ -154,10 +224,10 @@ fn parameters_from_raw_data(data: &[u16]) -> Result<(ModuleParameters, Vec<Chann
             };
 
             p.data_format =
-                FromPrimitive::from_u16(data[idx + 4]).ok_or_else(|| Error::ChannelParameter)?;
+                FromPrimitive::from_u16(data[idx + 4]).ok_or(Error::ChannelParameter)?;
 
             p.measurement_range =
-                FromPrimitive::from_u16(data[idx + 5]).ok_or_else(|| Error::ChannelParameter)?;
+                FromPrimitive::from_u16(data[idx + 5]).ok_or(Error::ChannelParameter)?;
 
             Ok(p)
         })
@@ -165,6 +235,21 @@ fn parameters_from_raw_data(data: &[u16]) -> Result<(ModuleParameters, Vec<Chann
     Ok((module_parameters, channel_parameters?))
 }
 
+fn parameters_to_raw_data(
+    mod_params: &ModuleParameters,
+    ch_params: &[ChannelParameters],
+) -> Vec<u16> {
+    let mut data = vec![mod_params.frequency_suppression.to_u16().unwrap()];
+    for p in ch_params {
+        data.push(u16::from(p.channel_diagnostics));
+        data.push(u16::from(p.diag_short_circuit));
+        data.push(u16::from(p.diag_line_break));
+        data.push(p.data_format.to_u16().unwrap());
+        data.push(p.measurement_range.to_u16().unwrap());
+    }
+    data
+}
+
 #[cfg(test)]
 mod tests {
 
@@ -174,14 +259,14 @@ mod tests {
     #[test]
     fn test_process_input_data_with_empty_buffer() {
         let m = Mod::default();
-        assert!(m.process_input_data(&vec![]).is_err());
+        assert!(m.process_input_data(&[]).is_err());
     }
 
     #[test]
     fn test_process_input_data_with_missing_channel_parameters() {
         let mut m = Mod::default();
         m.ch_params = vec![];
-        assert!(m.process_input_data(&vec![0; 4]).is_err());
+        assert!(m.process_input_data(&[0; 4]).is_err());
     }
 
     #[test]
@@ -192,7 +277,7 @@ mod tests {
         assert_eq!(m.ch_params[2].measurement_range, AnalogUIRange::Disabled);
         assert_eq!(m.ch_params[3].measurement_range, AnalogUIRange::Disabled);
         assert_eq!(
-            m.process_input_data(&vec![5, 0, 7, 8]).unwrap(),
+            m.process_input_data(&[5, 0, 7, 8]).unwrap(),
             vec![Disabled; 4]
         );
 
@@ -204,7 +289,7 @@ mod tests {
         m.ch_params[2].data_format = DataFormat::S5;
 
         assert_eq!(
-            m.process_input_data(&vec![0x6C00, 0x3600, 0x4000, 0x6C00])
+            m.process_input_data(&[0x6C00, 0x3600, 0x4000, 0x6C00])
                 .unwrap(),
             vec![
                 Decimal32(20.0),
@@ -225,7 +310,7 @@ mod tests {
         m.ch_params[1].measurement_range = AnalogUIRange::mA4To20;
         m.ch_params[1].data_format = DataFormat::S5;
 
-        let input = m.process_input_data(&vec![0xED00, 0x0F333, 0, 0]).unwrap();
+        let input = m.process_input_data(&[0xED00, 0x0F333, 0, 0]).unwrap();
 
         if let ChannelValue::Decimal32(v) = input[0] {
             assert!((v - 1.19).abs() < 0.01);
@@ -242,7 +327,7 @@ mod tests {
     #[test]
     fn test_process_output_data() {
         let m = Mod::default();
-        assert!(m.process_output_data(&vec![0; 4]).is_err());
+        assert!(m.process_output_data(&[0; 4]).is_err());
         assert_eq!(
             m.process_output_data(&[]).unwrap(),
             vec![ChannelValue::None; 4]
@@ -255,11 +340,11 @@ mod tests {
         assert!(m
             .process_output_values(&[ChannelValue::Decimal32(0.0)])
             .is_err());
-        assert_eq!(m.process_output_values(&[]).unwrap(), &[]);
+        assert_eq!(m.process_output_values(&[]).unwrap(), Vec::<u16>::new());
         assert_eq!(
             m.process_output_values(&vec![ChannelValue::None; 4])
                 .unwrap(),
-            &[]
+            Vec::<u16>::new()
         );
     }
 
@@ -301,20 +386,11 @@ mod tests {
             ChannelParameters::default()
         );
 
-        assert_eq!(
-            parameters_from_raw_data(&data).unwrap().1[1].channel_diagnostics,
-            true
-        );
+        assert!(parameters_from_raw_data(&data).unwrap().1[1].channel_diagnostics);
 
-        assert_eq!(
-            parameters_from_raw_data(&data).unwrap().1[1].diag_short_circuit,
-            false
-        );
+        assert!(!parameters_from_raw_data(&data).unwrap().1[1].diag_short_circuit);
 
-        assert_eq!(
-            parameters_from_raw_data(&data).unwrap().1[1].diag_line_break,
-            false
-        );
+        assert!(!parameters_from_raw_data(&data).unwrap().1[1].diag_line_break);
 
         assert_eq!(
             parameters_from_raw_data(&data).unwrap().1[1].data_format,
@@ -326,14 +402,8 @@ mod tests {
             AnalogUIRange::VMinus5To5
         );
 
-        assert_eq!(
-            parameters_from_raw_data(&data).unwrap().1[2].diag_short_circuit,
-            true
-        );
-        assert_eq!(
-            parameters_from_raw_data(&data).unwrap().1[3].diag_line_break,
-            true
-        );
+        assert!(parameters_from_raw_data(&data).unwrap().1[2].diag_short_circuit);
+        assert!(parameters_from_raw_data(&data).unwrap().1[3].diag_line_break);
         assert_eq!(
             parameters_from_raw_data(&data).unwrap().1[3].measurement_range,
             AnalogUIRange::mA0To20
@@ -397,6 +467,37 @@ mod tests {
             module.ch_params[1].measurement_range,
             AnalogUIRange::Disabled
         );
-        assert_eq!(module.ch_params[2].channel_diagnostics, true);
+        assert!(module.ch_params[2].channel_diagnostics);
+    }
+
+    #[test]
+    fn deduplicate_channel_parameters() {
+        use std::collections::HashSet;
+
+        #[rustfmt::skip]
+        let data = vec![
+            0,             // Module
+            0, 0, 0, 0, 1, // CH 0
+            0, 0, 0, 1, 8, // CH 1
+            0, 0, 0, 0, 1, // CH 2
+            0, 0, 0, 1, 8, // CH 3
+        ];
+        let module = Mod::from_modbus_parameter_data(&data).unwrap();
+        let set: HashSet<_> = module.ch_params.iter().collect();
+        assert_eq!(set.len(), 2);
+        assert_eq!(module.ch_params[0], module.ch_params[2]);
+    }
+
+    #[test]
+    fn test_parameters_to_raw_data() {
+        let mut m = Mod::default();
+        m.ch_params[3].diag_line_break = true;
+        m.ch_params[3].measurement_range = AnalogUIRange::mA4To20;
+        let data = m.to_modbus_parameter_data();
+        assert_eq!(data.len(), 21);
+        assert_eq!(data[16..], [0, 0, 1, 1, 1]);
+        let (mod_params, ch_params) = parameters_from_raw_data(&data).unwrap();
+        assert_eq!(mod_params, m.mod_params);
+        assert_eq!(ch_params, m.ch_params);
     }
 }
@@ -1,10 +1,13 @@
 //! Analog output module UR20-4AO-UI-16
 
 use super::*;
-use crate::ur20_fbc_mod_tcp::{FromModbusParameterData, ProcessModbusTcpData};
-use num_traits::cast::FromPrimitive;
+use crate::codec::AnalogScaling;
+use crate::ur20_fbc_mod_tcp::{
+    FromModbusParameterData, ProcessDataLayout, ProcessModbusTcpData, ToModbusParameterData,
+};
+use num_traits::cast::{FromPrimitive, ToPrimitive};
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Mod {
     pub ch_params: Vec<ChannelParameters>,
 }
@@ -23,6 +26,12 @@ impl FromModbusParameterData for Mod {
     }
 }
 
+impl ToModbusParameterData for Mod {
+    fn to_modbus_parameter_data(&self) -> Vec<u16> {
+        parameters_to_raw_data(&self.ch_params)
+    }
+}
+
 impl Default for ChannelParameters {
     fn default() -> Self {
         ChannelParameters {
@@ -44,6 +53,16 @@ impl Module for Mod {
     fn module_type(&self) -> ModuleType {
         ModuleType::UR20_4AO_UI_16
     }
+    fn channel_info(&self) -> Vec<ChannelInfo> {
+        self.ch_params
+            .iter()
+            .map(|p| ChannelInfo {
+                unit: p.output_range.unit(),
+                range: Some(format!("{:?}", p.output_range)),
+                ..Default::default()
+            })
+            .collect()
+    }
 }
 
 impl ProcessModbusTcpData for Mod {
@@ -53,10 +72,23 @@ impl ProcessModbusTcpData for Mod {
     fn process_output_byte_count(&self) -> usize {
         8
     }
+    fn analog_scalings(&self) -> Vec<Option<AnalogScaling>> {
+        self.ch_params
+            .iter()
+            .map(|p| AnalogScaling::ui(&p.output_range, &p.data_format))
+            .collect()
+    }
+    fn layout(&self) -> ProcessDataLayout {
+        ProcessDataLayout {
+            input: vec![],
+            output: util::channel_layout(4),
+        }
+    }
     fn process_output_data(&self, data: &[u16]) -> Result<Vec<ChannelValue>> {
         if data.len() != 4 {
             return Err(Error::BufferLength);
         }
+        util::check_channel_count(self, &self.ch_params)?;
         Ok(data
             .iter()
             .enumerate()
@@ -68,7 +100,7 @@ impl ProcessModbusTcpData for Mod {
                 )
             })
             .map(
-                |(v, range, factor)| match util::u16_to_analog_ui_value(*v, range, factor) {
+                |(v, range, factor)| match codec::u16_to_analog_ui_value(*v, range, factor) {
                     Some(v) => ChannelValue::Decimal32(v),
                     None => ChannelValue::Disabled,
                 },
@@ -76,12 +108,8 @@ impl ProcessModbusTcpData for Mod {
             .collect())
     }
     fn process_output_values(&self, values: &[ChannelValue]) -> Result<Vec<u16>> {
-        if values.len() != 4 {
-            return Err(Error::ChannelValue);
-        }
-        if self.ch_params.len() != 4 {
-            return Err(Error::ChannelParameter);
-        }
+        let values = util::output_values(self, values)?;
+        util::check_channel_count(self, &self.ch_params)?;
         values
             .iter()
             .enumerate()
@@ -99,8 +127,8 @@ impl ProcessModbusTcpData for Mod {
 
 fn value_to_u16(v: &ChannelValue, range: &AnalogUIRange, format: &DataFormat) -> Result<u16> {
     match *v {
-        ChannelValue::Decimal32(v) => Ok(util::analog_ui_value_to_u16(v, range, format)),
-        ChannelValue::Disabled => Ok(0),
+        ChannelValue::Decimal32(v) => Ok(codec::analog_ui_value_to_u16(v, range, format)),
+        ChannelValue::Disabled | ChannelValue::None => Ok(0),
         _ => Err(Error::ChannelValue),
     }
 }
@@ -130,7 +158,7 @@ fn parameters_from_raw_data(data: &[u16]) -> Result<Vec<ChannelParameters>> {
             };
 
             if let Some(v) =
-                util::u16_to_analog_ui_value(data[idx + 2], &p.output_range, &p.data_format)
+                codec::u16_to_analog_ui_value(data[idx + 2], &p.output_range, &p.data_format)
             {
                 p.substitute_value = v;
             }
@@ -138,7 +166,20 @@ fn parameters_from_raw_data(data: &[u16]) -> Result<Vec<ChannelParameters>> {
             Ok(p)
         })
         .collect();
-    Ok(channel_parameters?)
+    channel_parameters
+}
+
+fn parameters_to_raw_data(ch_params: &[ChannelParameters]) -> Vec<u16> {
+    ch_params
+        .iter()
+        .flat_map(|p| {
+            vec![
+                p.data_format.to_u16().unwrap(),
+                p.output_range.to_u16().unwrap(),
+                codec::analog_ui_value_to_u16(p.substitute_value, &p.output_range, &p.data_format),
+            ]
+        })
+        .collect()
 }
 
 #[cfg(test)]
@@ -166,7 +207,7 @@ mod tests {
     fn test_process_output_data() {
         let mut m = Mod::default();
         assert_eq!(
-            m.process_output_data(&vec![123, 456, 789, 0]).unwrap(),
+            m.process_output_data(&[123, 456, 789, 0]).unwrap(),
             &[
                 ChannelValue::Disabled,
                 ChannelValue::Disabled,
@@ -179,8 +220,7 @@ mod tests {
         m.ch_params[2].output_range = AnalogUIRange::mA0To20;
         m.ch_params[3].output_range = AnalogUIRange::mA0To20;
         assert_eq!(
-            m.process_output_data(&vec![0x0, 0x6C00, 0x3600, 0x0])
-                .unwrap(),
+            m.process_output_data(&[0x0, 0x6C00, 0x3600, 0x0]).unwrap(),
             &[
                 Decimal32(0.0),
                 Decimal32(20.0),
@@ -193,16 +233,16 @@ mod tests {
     #[test]
     fn test_process_output_data_with_invalid_buffer_size() {
         let m = Mod::default();
-        assert!(m.process_output_data(&vec![]).is_err());
-        assert!(m.process_output_data(&vec![0; 3]).is_err());
-        assert!(m.process_output_data(&vec![0; 5]).is_err());
-        assert!(m.process_output_data(&vec![0; 4]).is_ok());
+        assert!(m.process_output_data(&[]).is_err());
+        assert!(m.process_output_data(&[0; 3]).is_err());
+        assert!(m.process_output_data(&[0; 5]).is_err());
+        assert!(m.process_output_data(&[0; 4]).is_ok());
     }
 
     #[test]
     fn test_process_output_values_with_invalid_channel_len() {
         let m = Mod::default();
-        assert!(m.process_output_values(&[]).is_err());
+        assert_eq!(m.process_output_values(&[]).unwrap(), vec![0; 4]);
         assert!(m
             .process_output_values(&[Decimal32(0.0), Decimal32(0.0), Decimal32(0.0)])
             .is_err());
@@ -360,4 +400,38 @@ mod tests {
         assert_eq!(module.ch_params[0].data_format, DataFormat::S7);
         assert_eq!(module.ch_params[1].output_range, AnalogUIRange::Disabled);
     }
+
+    #[test]
+    fn test_parameters_to_raw_data() {
+        let mut m = Mod::default();
+        m.ch_params[0].output_range = AnalogUIRange::mA0To20;
+        m.ch_params[0].substitute_value = 10.0;
+        m.ch_params[3].data_format = DataFormat::S5;
+        let data = m.to_modbus_parameter_data();
+        #[rustfmt::skip]
+        assert_eq!(data, vec![
+            1, 0, 0x3600, // CH 0
+            1, 8, 0,      // CH 1
+            1, 8, 0,      // CH 2
+            0, 8, 0,      // CH 3
+        ]);
+        let module = Mod::from_modbus_parameter_data(&data).unwrap();
+        assert_eq!(module.ch_params, m.ch_params);
+    }
+
+    #[test]
+    fn test_process_data_with_missing_channel_parameters() {
+        let mut m = Mod::default();
+        m.ch_params.pop();
+        assert_eq!(
+            m.process_output_data(&[0; 4]).err().unwrap(),
+            Error::ChannelParameter
+        );
+        assert_eq!(
+            m.process_output_values(&vec![ChannelValue::Disabled; 4])
+                .err()
+                .unwrap(),
+            Error::ChannelParameter
+        );
+    }
 }
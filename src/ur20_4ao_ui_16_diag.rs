@@ -1,10 +1,14 @@
 //! Analog output module UR20-4AO-UI-16-DIAG
 
 use super::*;
-use crate::ur20_fbc_mod_tcp::{FromModbusParameterData, ProcessModbusTcpData};
-use num_traits::cast::FromPrimitive;
-
-#[derive(Debug)]
+use crate::codec::AnalogScaling;
+use crate::diagnosis::{filter_channel_diagnostics, ChannelDiagnostics, Diagnostics};
+use crate::ur20_fbc_mod_tcp::{
+    FromModbusParameterData, ProcessDataLayout, ProcessModbusTcpData, ToModbusParameterData,
+};
+use num_traits::cast::{FromPrimitive, ToPrimitive};
+
+#[derive(Debug, Clone)]
 pub struct Mod {
     pub ch_params: Vec<ChannelParameters>,
 }
@@ -24,6 +28,12 @@ impl FromModbusParameterData for Mod {
     }
 }
 
+impl ToModbusParameterData for Mod {
+    fn to_modbus_parameter_data(&self) -> Vec<u16> {
+        parameters_to_raw_data(&self.ch_params)
+    }
+}
+
 impl Default for ChannelParameters {
     fn default() -> Self {
         ChannelParameters {
@@ -46,6 +56,27 @@ impl Module for Mod {
     fn module_type(&self) -> ModuleType {
         ModuleType::UR20_4AO_UI_16_DIAG
     }
+    fn channel_info(&self) -> Vec<ChannelInfo> {
+        self.ch_params
+            .iter()
+            .map(|p| ChannelInfo {
+                unit: p.output_range.unit(),
+                range: Some(format!("{:?}", p.output_range)),
+                ..Default::default()
+            })
+            .collect()
+    }
+}
+
+impl Diagnostics for Mod {
+    /// Drops the causes that are not monitored.
+    fn process_diagnostic_data(&self, data: &[u16]) -> Result<Vec<ChannelDiagnostics>> {
+        filter_channel_diagnostics(&self.module_type(), data, |ch, _| {
+            self.ch_params
+                .get(ch)
+                .map_or(true, |p| p.channel_diagnostics)
+        })
+    }
 }
 
 impl ProcessModbusTcpData for Mod {
@@ -55,10 +86,26 @@ impl ProcessModbusTcpData for Mod {
     fn process_output_byte_count(&self) -> usize {
         8
     }
+    fn analog_scalings(&self) -> Vec<Option<AnalogScaling>> {
+        self.ch_params
+            .iter()
+            .map(|p| AnalogScaling::ui(&p.output_range, &p.data_format))
+            .collect()
+    }
+    fn diagnostics(&self) -> Option<&dyn Diagnostics> {
+        Some(self)
+    }
+    fn layout(&self) -> ProcessDataLayout {
+        ProcessDataLayout {
+            input: vec![],
+            output: util::channel_layout(4),
+        }
+    }
     fn process_output_data(&self, data: &[u16]) -> Result<Vec<ChannelValue>> {
         if data.len() != 4 {
             return Err(Error::BufferLength);
         }
+        util::check_channel_count(self, &self.ch_params)?;
         Ok(data
             .iter()
             .enumerate()
@@ -70,7 +117,7 @@ impl ProcessModbusTcpData for Mod {
                 )
             })
             .map(
-                |(v, range, factor)| match util::u16_to_analog_ui_value(*v, range, factor) {
+                |(v, range, factor)| match codec::u16_to_analog_ui_value(*v, range, factor) {
                     Some(v) => ChannelValue::Decimal32(v),
                     None => ChannelValue::Disabled,
                 },
@@ -78,12 +125,8 @@ impl ProcessModbusTcpData for Mod {
             .collect())
     }
     fn process_output_values(&self, values: &[ChannelValue]) -> Result<Vec<u16>> {
-        if values.len() != 4 {
-            return Err(Error::ChannelValue);
-        }
-        if self.ch_params.len() != 4 {
-            return Err(Error::ChannelParameter);
-        }
+        let values = util::output_values(self, values)?;
+        util::check_channel_count(self, &self.ch_params)?;
         values
             .iter()
             .enumerate()
@@ -101,8 +144,8 @@ impl ProcessModbusTcpData for Mod {
 
 fn value_to_u16(v: &ChannelValue, range: &AnalogUIRange, format: &DataFormat) -> Result<u16> {
     match *v {
-        ChannelValue::Decimal32(v) => Ok(util::analog_ui_value_to_u16(v, range, format)),
-        ChannelValue::Disabled => Ok(0),
+        ChannelValue::Decimal32(v) => Ok(codec::analog_ui_value_to_u16(v, range, format)),
+        ChannelValue::Disabled | ChannelValue::None => Ok(0),
         _ => Err(Error::ChannelValue),
     }
 }
@@ -117,14 +160,13 @@ fn parameters_from_raw_data(data: &[u16]) -> Result<Vec<ChannelParameters>> {
             let mut p = ChannelParameters::default();
             let idx = i * 4;
 
-            p.data_format =
-                FromPrimitive::from_u16(data[idx]).ok_or_else(|| Error::ChannelParameter)?;
+            p.data_format = FromPrimitive::from_u16(data[idx]).ok_or(Error::ChannelParameter)?;
 
             p.output_range =
-                FromPrimitive::from_u16(data[idx + 1]).ok_or_else(|| Error::ChannelParameter)?;
+                FromPrimitive::from_u16(data[idx + 1]).ok_or(Error::ChannelParameter)?;
 
             if let Some(v) =
-                util::u16_to_analog_ui_value(data[idx + 2], &p.output_range, &p.data_format)
+                codec::u16_to_analog_ui_value(data[idx + 2], &p.output_range, &p.data_format)
             {
                 p.substitute_value = v;
             }
@@ -138,7 +180,21 @@ fn parameters_from_raw_data(data: &[u16]) -> Result<Vec<ChannelParameters>> {
             Ok(p)
         })
         .collect();
-    Ok(channel_parameters?)
+    channel_parameters
+}
+
+fn parameters_to_raw_data(ch_params: &[ChannelParameters]) -> Vec<u16> {
+    ch_params
+        .iter()
+        .flat_map(|p| {
+            vec![
+                p.data_format.to_u16().unwrap(),
+                p.output_range.to_u16().unwrap(),
+                codec::analog_ui_value_to_u16(p.substitute_value, &p.output_range, &p.data_format),
+                u16::from(p.channel_diagnostics),
+            ]
+        })
+        .collect()
 }
 
 #[cfg(test)]
@@ -166,7 +222,7 @@ mod tests {
     fn test_process_output_data() {
         let mut m = Mod::default();
         assert_eq!(
-            m.process_output_data(&vec![123, 456, 789, 0]).unwrap(),
+            m.process_output_data(&[123, 456, 789, 0]).unwrap(),
             &[
                 ChannelValue::Disabled,
                 ChannelValue::Disabled,
@@ -179,8 +235,7 @@ mod tests {
         m.ch_params[2].output_range = AnalogUIRange::mA0To20;
         m.ch_params[3].output_range = AnalogUIRange::mA0To20;
         assert_eq!(
-            m.process_output_data(&vec![0x0, 0x6C00, 0x3600, 0x0])
-                .unwrap(),
+            m.process_output_data(&[0x0, 0x6C00, 0x3600, 0x0]).unwrap(),
             &[
                 Decimal32(0.0),
                 Decimal32(20.0),
@@ -193,16 +248,16 @@ mod tests {
     #[test]
     fn test_process_output_data_with_invalid_buffer_size() {
         let m = Mod::default();
-        assert!(m.process_output_data(&vec![]).is_err());
-        assert!(m.process_output_data(&vec![0; 3]).is_err());
-        assert!(m.process_output_data(&vec![0; 5]).is_err());
-        assert!(m.process_output_data(&vec![0; 4]).is_ok());
+        assert!(m.process_output_data(&[]).is_err());
+        assert!(m.process_output_data(&[0; 3]).is_err());
+        assert!(m.process_output_data(&[0; 5]).is_err());
+        assert!(m.process_output_data(&[0; 4]).is_ok());
     }
 
     #[test]
     fn test_process_output_values_with_invalid_channel_len() {
         let m = Mod::default();
-        assert!(m.process_output_values(&[]).is_err());
+        assert_eq!(m.process_output_values(&[]).unwrap(), vec![0; 4]);
         assert!(m
             .process_output_values(&[Decimal32(0.0), Decimal32(0.0), Decimal32(0.0)])
             .is_err());
@@ -309,10 +364,7 @@ mod tests {
             DataFormat::S7
         );
 
-        assert_eq!(
-            parameters_from_raw_data(&data).unwrap()[1].channel_diagnostics,
-            true
-        );
+        assert!(parameters_from_raw_data(&data).unwrap()[1].channel_diagnostics);
 
         assert_eq!(
             parameters_from_raw_data(&data).unwrap()[2].output_range,
@@ -369,4 +421,17 @@ mod tests {
         assert_eq!(module.ch_params[0].data_format, DataFormat::S7);
         assert_eq!(module.ch_params[1].output_range, AnalogUIRange::Disabled);
     }
+
+    #[test]
+    fn test_parameters_to_raw_data() {
+        let mut m = Mod::default();
+        m.ch_params[2].output_range = AnalogUIRange::V0To10;
+        m.ch_params[2].substitute_value = 5.0;
+        m.ch_params[2].channel_diagnostics = true;
+        let data = m.to_modbus_parameter_data();
+        assert_eq!(data.len(), 16);
+        assert_eq!(data[8..12], [1, 2, 0x3600, 1]);
+        let module = Mod::from_modbus_parameter_data(&data).unwrap();
+        assert_eq!(module.ch_params, m.ch_params);
+    }
 }
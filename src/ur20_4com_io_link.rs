@@ -0,0 +1,590 @@
+//! IO-Link master module UR20-4COM-IO-LINK
+//!
+//! Each port has 8 bytes of process input and output data.
+//! On-request data (ISDU) is exchanged through a mailbox
+//! at the beginning of the process data that is handled
+//! by an `IsduProcessor`.
+
+use super::*;
+use crate::{
+    ur20_fbc_mod_tcp::{
+        FromModbusParameterData, ProcessDataLayout, ProcessModbusTcpData, ToModbusParameterData,
+    },
+    util::*,
+};
+use num_traits::cast::{FromPrimitive, ToPrimitive};
+use std::{cmp, collections::VecDeque, ops::Range};
+
+/// Number of process data bytes of each port.
+pub const PORT_DATA_LEN: usize = 8;
+/// Number of bytes of the ISDU mailbox.
+pub const MAILBOX_LEN: usize = 16;
+/// Maximum number of bytes that can be written with one request.
+pub const MAX_REQUEST_DATA_LEN: usize = MAILBOX_LEN - 6;
+/// Maximum number of bytes of a response.
+pub const MAX_RESPONSE_DATA_LEN: usize = MAILBOX_LEN - 2;
+
+const PORT_COUNT: usize = 4;
+const STATUS_LEN: usize = 4;
+
+#[derive(Debug, Clone)]
+pub struct Mod {
+    pub ch_params: Vec<ChannelParameters>,
+}
+
+/// Operating mode of a port.
+#[rustfmt::skip]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, FromPrimitive, ToPrimitive)]
+pub enum PortMode {
+    Inactive = 0,
+    DigitalInput = 1,
+    DigitalOutput = 2,
+    IoLink = 3,
+}
+
+/// Check of the connected device.
+#[rustfmt::skip]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, FromPrimitive, ToPrimitive)]
+pub enum Validation {
+    /// Any device is accepted.
+    NoCheck    = 0,
+    /// The device has to be compatible (same vendor and device ID).
+    Compatible = 1,
+    /// The device has to be identical (including the serial number).
+    Identical  = 2,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ChannelParameters {
+    pub port_mode: PortMode,
+    pub validation: Validation,
+    /// Cycle time in multiples of 0.1 ms (`0` = as fast as possible).
+    pub cycle_time: u16,
+    pub vendor_id: u16,
+    pub device_id: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PortInput {
+    /// A device is connected and the communication is established.
+    pub connected: bool,
+    /// The process data of the device is valid.
+    pub valid: bool,
+    /// The device or the port reports a diagnosis.
+    pub diagnosis: bool,
+    /// Process input data of the device
+    pub data: Vec<u8>,
+}
+
+impl Default for ChannelParameters {
+    fn default() -> Self {
+        ChannelParameters {
+            port_mode: PortMode::Inactive,
+            validation: Validation::NoCheck,
+            cycle_time: 0,
+            vendor_id: 0,
+            device_id: 0,
+        }
+    }
+}
+
+impl Default for Mod {
+    fn default() -> Self {
+        let ch_params = (0..PORT_COUNT)
+            .map(|_| ChannelParameters::default())
+            .collect();
+        Mod { ch_params }
+    }
+}
+
+impl From<PortInput> for ChannelValue {
+    fn from(i: PortInput) -> Self {
+        ChannelValue::IoLinkIn(i)
+    }
+}
+
+impl Module for Mod {
+    fn module_type(&self) -> ModuleType {
+        ModuleType::UR20_4COM_IO_LINK
+    }
+}
+
+impl FromModbusParameterData for Mod {
+    fn from_modbus_parameter_data(data: &[u16]) -> Result<Mod> {
+        let ch_params = parameters_from_raw_data(data)?;
+        Ok(Mod { ch_params })
+    }
+}
+
+impl ToModbusParameterData for Mod {
+    fn to_modbus_parameter_data(&self) -> Vec<u16> {
+        parameters_to_raw_data(&self.ch_params)
+    }
+}
+
+impl ProcessModbusTcpData for Mod {
+    fn process_input_byte_count(&self) -> usize {
+        STATUS_LEN + MAILBOX_LEN + PORT_COUNT * PORT_DATA_LEN
+    }
+    fn process_output_byte_count(&self) -> usize {
+        MAILBOX_LEN + PORT_COUNT * PORT_DATA_LEN
+    }
+    fn layout(&self) -> ProcessDataLayout {
+        let ports = || {
+            (0..PORT_COUNT * PORT_DATA_LEN / 2).map(|i| {
+                let port = i * 2 / PORT_DATA_LEN;
+                let byte = i * 2 % PORT_DATA_LEN;
+                format!("port {} data bytes {} - {}", port, byte, byte + 1)
+            })
+        };
+        let mut input = vec![
+            "port 0 status (low byte), port 1 status (high byte)".to_string(),
+            "port 2 status (low byte), port 3 status (high byte)".to_string(),
+            "ISDU status (low byte), data length (high byte)".to_string(),
+        ];
+        input.extend(
+            (1..MAILBOX_LEN / 2).map(|i| format!("ISDU data bytes {} - {}", i * 2 - 2, i * 2 - 1)),
+        );
+        input.extend(ports());
+        let mut output = vec![
+            "ISDU control (low byte), port (high byte)".to_string(),
+            "ISDU index".to_string(),
+            "ISDU subindex (low byte), data length (high byte)".to_string(),
+        ];
+        output.extend(
+            (3..MAILBOX_LEN / 2).map(|i| format!("ISDU data bytes {} - {}", i * 2 - 6, i * 2 - 5)),
+        );
+        output.extend(ports());
+        ProcessDataLayout { input, output }
+    }
+    fn process_input_payload(&self) -> Vec<Range<usize>> {
+        vec![
+            STATUS_LEN + 2..STATUS_LEN + MAILBOX_LEN,
+            STATUS_LEN + MAILBOX_LEN..self.process_input_byte_count(),
+        ]
+    }
+    fn process_output_payload(&self) -> Vec<Range<usize>> {
+        vec![
+            6..MAILBOX_LEN,
+            MAILBOX_LEN..self.process_output_byte_count(),
+        ]
+    }
+    fn process_input_data(&self, data: &[u16]) -> Result<Vec<ChannelValue>> {
+        if data.len() * 2 != self.process_input_byte_count() {
+            return Err(Error::BufferLength);
+        }
+        util::check_channel_count(self, &self.ch_params)?;
+        let bytes = u16_to_u8(data);
+        let res = self
+            .ch_params
+            .iter()
+            .enumerate()
+            .map(|(i, p)| {
+                let status = bytes[i];
+                let start = STATUS_LEN + MAILBOX_LEN + i * PORT_DATA_LEN;
+                let data = &bytes[start..start + PORT_DATA_LEN];
+                match p.port_mode {
+                    PortMode::Inactive | PortMode::DigitalOutput => ChannelValue::Disabled,
+                    PortMode::DigitalInput => ChannelValue::Bit(test_bit(data[0], 0)),
+                    PortMode::IoLink => PortInput {
+                        connected: test_bit(status, 0),
+                        valid: test_bit(status, 1),
+                        diagnosis: test_bit(status, 2),
+                        data: data.to_vec(),
+                    }
+                    .into(),
+                }
+            })
+            .collect();
+        Ok(res)
+    }
+    fn process_output_data(&self, data: &[u16]) -> Result<Vec<ChannelValue>> {
+        if data.len() * 2 != self.process_output_byte_count() {
+            return Err(Error::BufferLength);
+        }
+        util::check_channel_count(self, &self.ch_params)?;
+        let bytes = u16_to_u8(data);
+        let res = self
+            .ch_params
+            .iter()
+            .enumerate()
+            .map(|(i, p)| {
+                let start = MAILBOX_LEN + i * PORT_DATA_LEN;
+                let data = &bytes[start..start + PORT_DATA_LEN];
+                match p.port_mode {
+                    PortMode::Inactive | PortMode::DigitalInput => ChannelValue::Disabled,
+                    PortMode::DigitalOutput => ChannelValue::Bit(test_bit(data[0], 0)),
+                    PortMode::IoLink => ChannelValue::Bytes(data.to_vec()),
+                }
+            })
+            .collect();
+        Ok(res)
+    }
+    fn process_output_values(&self, values: &[ChannelValue]) -> Result<Vec<u16>> {
+        let values = util::output_values(self, values)?;
+        util::check_channel_count(self, &self.ch_params)?;
+        let mut bytes = vec![0; self.process_output_byte_count()];
+        for (i, (v, p)) in values.iter().zip(&self.ch_params).enumerate() {
+            let start = MAILBOX_LEN + i * PORT_DATA_LEN;
+            match (v, &p.port_mode) {
+                (ChannelValue::Bit(on), PortMode::DigitalOutput) => {
+                    bytes[start] = u8::from(*on);
+                }
+                (ChannelValue::Bytes(data), PortMode::IoLink) => {
+                    if data.len() > PORT_DATA_LEN {
+                        return Err(Error::BufferLength);
+                    }
+                    bytes[start..start + data.len()].copy_from_slice(data);
+                }
+                (ChannelValue::Disabled, _) | (ChannelValue::None, _) => {}
+                _ => {
+                    return Err(Error::ChannelValue);
+                }
+            }
+        }
+        Ok(u8_to_u16(&bytes))
+    }
+}
+
+/// A request to read or write on-request data of a device.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IsduRequest {
+    /// Port number (`0 ... 3`)
+    pub port: usize,
+    pub index: u16,
+    pub subindex: u8,
+    /// Data to write (`None` to read).
+    pub data: Option<Vec<u8>>,
+}
+
+/// The answer to an `IsduRequest`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IsduResponse {
+    pub request: IsduRequest,
+    /// The data that was read or the error code of the device.
+    pub result: result::Result<Vec<u8>, u8>,
+}
+
+/// Exchanges ISDU requests and responses through the mailbox.
+///
+/// The request mailbox contains a sequence number (`1 ... 3`) that
+/// is echoed within the response mailbox as soon as the request
+/// was processed. Only one request is processed at a time.
+#[derive(Debug, Default)]
+pub struct IsduProcessor {
+    queue: VecDeque<IsduRequest>,
+    pending: Option<(u8, IsduRequest)>,
+    last_cnt: u8,
+    responses: Vec<IsduResponse>,
+}
+
+impl IsduProcessor {
+    pub fn new() -> Self {
+        IsduProcessor::default()
+    }
+
+    /// Queues a request.
+    pub fn request(&mut self, req: IsduRequest) -> Result<()> {
+        if req.port >= PORT_COUNT {
+            return Err(Error::Address);
+        }
+        if req.data.as_ref().map(Vec::len).unwrap_or(0) > MAX_REQUEST_DATA_LEN {
+            return Err(Error::BufferLength);
+        }
+        self.queue.push_back(req);
+        Ok(())
+    }
+
+    /// Takes the received responses.
+    pub fn take_responses(&mut self) -> Vec<IsduResponse> {
+        std::mem::take(&mut self.responses)
+    }
+
+    /// Returns `true` if there are requests without response.
+    pub fn is_busy(&self) -> bool {
+        self.pending.is_some() || !self.queue.is_empty()
+    }
+
+    /// Processes the input mailbox and returns the next output mailbox.
+    pub fn next(&mut self, input: &[u8]) -> Result<Vec<u8>> {
+        if input.len() < MAILBOX_LEN {
+            return Err(Error::BufferLength);
+        }
+        if let Some((cnt, _)) = self.pending {
+            if input[0] & 0b11 == cnt {
+                let (_, request) = self.pending.take().unwrap();
+                let len = cmp::min(input[1] as usize, MAX_RESPONSE_DATA_LEN);
+                let result = if test_bit(input[0], 7) {
+                    Err(input[2])
+                } else {
+                    Ok(input[2..2 + len].to_vec())
+                };
+                self.responses.push(IsduResponse { request, result });
+            }
+        }
+        if self.pending.is_none() {
+            if let Some(req) = self.queue.pop_front() {
+                self.last_cnt = self.last_cnt % 3 + 1;
+                self.pending = Some((self.last_cnt, req));
+            }
+        }
+        let mut out = vec![0; MAILBOX_LEN];
+        if let Some((cnt, ref req)) = self.pending {
+            out[0] = cnt;
+            out[1] = req.port as u8;
+            out[2..4].copy_from_slice(&req.index.to_le_bytes());
+            out[4] = req.subindex;
+            if let Some(ref data) = req.data {
+                out[0] = set_bit(out[0], 2);
+                out[5] = data.len() as u8;
+                out[6..6 + data.len()].copy_from_slice(data);
+            }
+        }
+        Ok(out)
+    }
+}
+
+/// Returns the ISDU mailbox of the module input data.
+pub fn input_mailbox(data: &[u16]) -> Result<Vec<u8>> {
+    let bytes = u16_to_u8(data);
+    bytes
+        .get(STATUS_LEN..STATUS_LEN + MAILBOX_LEN)
+        .map(<[u8]>::to_vec)
+        .ok_or(Error::BufferLength)
+}
+
+fn parameters_from_raw_data(data: &[u16]) -> Result<Vec<ChannelParameters>> {
+    if data.len() < PORT_COUNT * 6 {
+        return Err(Error::BufferLength);
+    }
+    (0..PORT_COUNT)
+        .map(|i| {
+            let idx = i * 6;
+            let mut p = ChannelParameters::default();
+
+            p.port_mode = match FromPrimitive::from_u16(data[idx]) {
+                Some(x) => x,
+                _ => {
+                    return Err(Error::ChannelParameter);
+                }
+            };
+
+            p.validation = match FromPrimitive::from_u16(data[idx + 1]) {
+                Some(x) => x,
+                _ => {
+                    return Err(Error::ChannelParameter);
+                }
+            };
+
+            p.cycle_time = data[idx + 2];
+            p.vendor_id = data[idx + 3];
+            p.device_id = u32::from(data[idx + 4]) << 16 | u32::from(data[idx + 5]);
+
+            Ok(p)
+        })
+        .collect()
+}
+
+fn parameters_to_raw_data(ch_params: &[ChannelParameters]) -> Vec<u16> {
+    ch_params
+        .iter()
+        .flat_map(|p| {
+            vec![
+                p.port_mode.to_u16().unwrap(),
+                p.validation.to_u16().unwrap(),
+                p.cycle_time,
+                p.vendor_id,
+                (p.device_id >> 16) as u16,
+                p.device_id as u16,
+            ]
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn io_link_mod() -> Mod {
+        let mut m = Mod::default();
+        m.ch_params[0].port_mode = PortMode::IoLink;
+        m.ch_params[1].port_mode = PortMode::DigitalInput;
+        m.ch_params[2].port_mode = PortMode::DigitalOutput;
+        m
+    }
+
+    #[test]
+    fn test_process_input_data() {
+        let m = io_link_mod();
+        assert!(m.process_input_data(&[0; 25]).is_err());
+        let mut bytes = vec![0; 52];
+        bytes[0] = 0b011;
+        bytes[20..23].copy_from_slice(&[1, 2, 3]);
+        bytes[28] = 1;
+        let values = m.process_input_data(&u8_to_u16(&bytes)).unwrap();
+        assert_eq!(
+            values[0],
+            ChannelValue::IoLinkIn(PortInput {
+                connected: true,
+                valid: true,
+                diagnosis: false,
+                data: vec![1, 2, 3, 0, 0, 0, 0, 0],
+            })
+        );
+        assert_eq!(values[1], ChannelValue::Bit(true));
+        assert_eq!(values[2], ChannelValue::Disabled);
+        assert_eq!(values[3], ChannelValue::Disabled);
+    }
+
+    #[test]
+    fn test_process_output_values() {
+        let m = io_link_mod();
+        let out = m
+            .process_output_values(&[
+                ChannelValue::Bytes(vec![0xAB, 0xCD]),
+                ChannelValue::None,
+                ChannelValue::Bit(true),
+                ChannelValue::Disabled,
+            ])
+            .unwrap();
+        assert_eq!(out.len(), 24);
+        assert_eq!(out[8], 0xCDAB);
+        assert_eq!(out[16], 0x0001);
+        assert_eq!(
+            m.process_output_data(&out).unwrap(),
+            vec![
+                ChannelValue::Bytes(vec![0xAB, 0xCD, 0, 0, 0, 0, 0, 0]),
+                ChannelValue::Disabled,
+                ChannelValue::Bit(true),
+                ChannelValue::Disabled,
+            ]
+        );
+        assert!(m
+            .process_output_values(&[
+                ChannelValue::Bytes(vec![0; 9]),
+                ChannelValue::None,
+                ChannelValue::None,
+                ChannelValue::None,
+            ])
+            .is_err());
+        assert!(m
+            .process_output_values(&[
+                ChannelValue::Bit(true),
+                ChannelValue::None,
+                ChannelValue::None,
+                ChannelValue::None,
+            ])
+            .is_err());
+    }
+
+    #[test]
+    fn test_layout() {
+        let l = io_link_mod().layout();
+        assert_eq!(l.input.len(), 26);
+        assert_eq!(l.output.len(), 24);
+        assert_eq!(l.input[10], "port 0 data bytes 0 - 1");
+        assert_eq!(l.output[23], "port 3 data bytes 6 - 7");
+    }
+
+    #[test]
+    fn test_parameters_to_raw_data() {
+        let mut m = Mod::default();
+        m.ch_params[2].port_mode = PortMode::IoLink;
+        m.ch_params[2].validation = Validation::Compatible;
+        m.ch_params[2].cycle_time = 20;
+        m.ch_params[2].vendor_id = 0x0123;
+        m.ch_params[2].device_id = 0x0004_5678;
+        let data = m.to_modbus_parameter_data();
+        assert_eq!(data.len(), 24);
+        assert_eq!(data[12..18], [3, 1, 20, 0x0123, 0x0004, 0x5678]);
+        assert_eq!(parameters_from_raw_data(&data).unwrap(), m.ch_params);
+    }
+
+    #[test]
+    fn test_parameters_from_raw_data() {
+        #[rustfmt::skip]
+        let data = vec![
+            3, 1, 20, 0x0123, 0x0004, 0x5678, // Port 0
+            1, 0, 0, 0, 0, 0,                 // Port 1
+            2, 0, 0, 0, 0, 0,                 // Port 2
+            0, 0, 0, 0, 0, 0,                 // Port 3
+        ];
+        let p = parameters_from_raw_data(&data).unwrap();
+        assert_eq!(p[0].port_mode, PortMode::IoLink);
+        assert_eq!(p[0].validation, Validation::Compatible);
+        assert_eq!(p[0].cycle_time, 20);
+        assert_eq!(p[0].vendor_id, 0x0123);
+        assert_eq!(p[0].device_id, 0x0004_5678);
+        assert_eq!(p[1].port_mode, PortMode::DigitalInput);
+        assert_eq!(p[3], ChannelParameters::default());
+        assert!(parameters_from_raw_data(&data[..23]).is_err());
+        let mut invalid = data.clone();
+        invalid[6] = 4;
+        assert!(parameters_from_raw_data(&invalid).is_err());
+        invalid[6] = 0;
+        invalid[7] = 3;
+        assert!(parameters_from_raw_data(&invalid).is_err());
+    }
+
+    #[test]
+    fn exchange_isdu_requests() {
+        let mut p = IsduProcessor::new();
+        assert_eq!(p.next(&[0; 16]).unwrap(), vec![0; 16]);
+        p.request(IsduRequest {
+            port: 1,
+            index: 0x0010,
+            subindex: 0,
+            data: None,
+        })
+        .unwrap();
+        p.request(IsduRequest {
+            port: 2,
+            index: 0x0102,
+            subindex: 3,
+            data: Some(vec![7, 8]),
+        })
+        .unwrap();
+        assert!(p
+            .request(IsduRequest {
+                port: 4,
+                index: 0,
+                subindex: 0,
+                data: None,
+            })
+            .is_err());
+        assert!(p
+            .request(IsduRequest {
+                port: 0,
+                index: 0,
+                subindex: 0,
+                data: Some(vec![0; 11]),
+            })
+            .is_err());
+
+        let out = p.next(&[0; 16]).unwrap();
+        assert_eq!(&out[..6], &[1, 1, 0x10, 0x00, 0, 0]);
+        // still waiting for the response
+        assert_eq!(p.next(&[0; 16]).unwrap(), out);
+        assert!(p.take_responses().is_empty());
+
+        let mut input = vec![0; 16];
+        input[0] = 1;
+        input[1] = 3;
+        input[2..5].copy_from_slice(b"abc");
+        let out = p.next(&input).unwrap();
+        assert_eq!(&out[..8], &[0b110, 2, 0x02, 0x01, 3, 2, 7, 8]);
+        let res = p.take_responses();
+        assert_eq!(res.len(), 1);
+        assert_eq!(res[0].request.index, 0x0010);
+        assert_eq!(res[0].result, Ok(b"abc".to_vec()));
+
+        input[0] = 0b1000_0010;
+        input[2] = 0x80;
+        assert_eq!(p.next(&input).unwrap(), vec![0; 16]);
+        assert!(!p.is_busy());
+        let res = p.take_responses();
+        assert_eq!(res[0].request.port, 2);
+        assert_eq!(res[0].result, Err(0x80));
+    }
+}
@@ -1,18 +1,33 @@
 //! Digital input module UR20-4DI-P
+//!
+//! The module is implemented by `ur20_di_generic`;
+//! this type is kept for backward compatibility.
 
-use super::util::test_bit_16;
 use super::*;
-use crate::ur20_fbc_mod_tcp::{FromModbusParameterData, ProcessModbusTcpData};
-use num_traits::cast::FromPrimitive;
+use crate::ur20_fbc_mod_tcp::{
+    FromModbusParameterData, ProcessDataLayout, ProcessModbusTcpData, ToModbusParameterData,
+};
 
-#[derive(Debug)]
+pub use crate::ur20_di_generic::ChannelParameters;
+
+#[derive(Debug, Clone)]
 pub struct Mod {
     pub ch_params: Vec<ChannelParameters>,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
-pub struct ChannelParameters {
-    pub input_delay: InputDelay,
+impl Mod {
+    fn generic(&self) -> ur20_di_generic::Mod {
+        ur20_di_generic::Mod {
+            module_type: ModuleType::UR20_4DI_P,
+            ch_params: self.ch_params.clone(),
+        }
+    }
+}
+
+impl From<Mod> for ur20_di_generic::Mod {
+    fn from(m: Mod) -> Self {
+        m.generic()
+    }
 }
 
 impl FromModbusParameterData for Mod {
@@ -22,14 +37,6 @@ impl FromModbusParameterData for Mod {
     }
 }
 
-impl Default for ChannelParameters {
-    fn default() -> Self {
-        ChannelParameters {
-            input_delay: InputDelay::ms3,
-        }
-    }
-}
-
 impl Default for Mod {
     fn default() -> Self {
         let ch_params = (0..4).map(|_| ChannelParameters::default()).collect();
@@ -43,6 +50,12 @@ impl Module for Mod {
     }
 }
 
+impl ToModbusParameterData for Mod {
+    fn to_modbus_parameter_data(&self) -> Vec<u16> {
+        self.generic().to_modbus_parameter_data()
+    }
+}
+
 impl ProcessModbusTcpData for Mod {
     fn process_input_byte_count(&self) -> usize {
         1
@@ -50,36 +63,20 @@ impl ProcessModbusTcpData for Mod {
     fn process_output_byte_count(&self) -> usize {
         0
     }
+    fn layout(&self) -> ProcessDataLayout {
+        self.generic().layout()
+    }
     fn process_input_data(&self, data: &[u16]) -> Result<Vec<ChannelValue>> {
-        if data.len() != 1 {
-            return Err(Error::BufferLength);
-        }
-        let bits = data[0];
-        let res = (0..4)
-            .map(|i| ChannelValue::Bit(test_bit_16(bits, i)))
-            .collect();
-        Ok(res)
+        self.generic().process_input_data(data)
+    }
+    fn process_input_values(&self, values: &[ChannelValue]) -> Result<Vec<u16>> {
+        self.generic().process_input_values(values)
     }
 }
 
 fn parameters_from_raw_data(data: &[u16]) -> Result<Vec<ChannelParameters>> {
-    if data.len() < 4 {
-        return Err(Error::BufferLength);
-    }
-
-    let channel_parameters: Result<Vec<_>> = (0..4)
-        .map(|i| {
-            let mut p = ChannelParameters::default();
-            p.input_delay = match FromPrimitive::from_u16(data[i]) {
-                Some(x) => x,
-                _ => {
-                    return Err(Error::ChannelParameter);
-                }
-            };
-            Ok(p)
-        })
-        .collect();
-    Ok(channel_parameters?)
+    ur20_di_generic::Mod::from_modbus_parameter_data(ModuleType::UR20_4DI_P, data)
+        .map(|m| m.ch_params)
 }
 
 #[cfg(test)]
@@ -91,7 +88,7 @@ mod tests {
     #[test]
     fn test_process_input_data() {
         let m = Mod::default();
-        assert!(m.process_input_data(&vec![]).is_err());
+        assert!(m.process_input_data(&[]).is_err());
         let data = vec![0b0100];
         assert_eq!(
             m.process_input_data(&data).unwrap(),
@@ -113,11 +110,11 @@ mod tests {
     fn test_process_output_values() {
         let m = Mod::default();
         assert!(m.process_output_values(&[ChannelValue::Bit(true)]).is_err());
-        assert_eq!(m.process_output_values(&[]).unwrap(), &[]);
+        assert_eq!(m.process_output_values(&[]).unwrap(), Vec::<u16>::new());
         assert_eq!(
             m.process_output_values(&vec![ChannelValue::None; 4])
                 .unwrap(),
-            &[]
+            Vec::<u16>::new()
         );
     }
 
@@ -1,17 +1,33 @@
 //! Digital output module UR20-4DO-P
+//!
+//! The module is implemented by `ur20_do_generic`;
+//! this type is kept for backward compatibility.
 
 use super::*;
-use crate::ur20_fbc_mod_tcp::{FromModbusParameterData, ProcessModbusTcpData};
-use crate::util::*;
+use crate::ur20_fbc_mod_tcp::{
+    FromModbusParameterData, ProcessDataLayout, ProcessModbusTcpData, ToModbusParameterData,
+};
 
-#[derive(Debug)]
+pub use crate::ur20_do_generic::ChannelParameters;
+
+#[derive(Debug, Clone)]
 pub struct Mod {
     pub ch_params: Vec<ChannelParameters>,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
-pub struct ChannelParameters {
-    pub substitute_value: bool,
+impl Mod {
+    fn generic(&self) -> ur20_do_generic::Mod {
+        ur20_do_generic::Mod {
+            module_type: ModuleType::UR20_4DO_P,
+            ch_params: self.ch_params.clone(),
+        }
+    }
+}
+
+impl From<Mod> for ur20_do_generic::Mod {
+    fn from(m: Mod) -> Self {
+        m.generic()
+    }
 }
 
 impl FromModbusParameterData for Mod {
@@ -21,14 +37,6 @@ impl FromModbusParameterData for Mod {
     }
 }
 
-impl Default for ChannelParameters {
-    fn default() -> Self {
-        ChannelParameters {
-            substitute_value: false,
-        }
-    }
-}
-
 impl Default for Mod {
     fn default() -> Self {
         let ch_params = (0..4).map(|_| ChannelParameters::default()).collect();
@@ -41,6 +49,13 @@ impl Module for Mod {
         ModuleType::UR20_4DO_P
     }
 }
+
+impl ToModbusParameterData for Mod {
+    fn to_modbus_parameter_data(&self) -> Vec<u16> {
+        self.generic().to_modbus_parameter_data()
+    }
+}
+
 impl ProcessModbusTcpData for Mod {
     fn process_input_byte_count(&self) -> usize {
         0
@@ -48,58 +63,20 @@ impl ProcessModbusTcpData for Mod {
     fn process_output_byte_count(&self) -> usize {
         1
     }
+    fn layout(&self) -> ProcessDataLayout {
+        self.generic().layout()
+    }
     fn process_output_data(&self, data: &[u16]) -> Result<Vec<ChannelValue>> {
-        if data.len() != 1 {
-            return Err(Error::BufferLength);
-        }
-        Ok((0..4)
-            .map(|i| test_bit_16(data[0], i))
-            .map(ChannelValue::Bit)
-            .collect())
+        self.generic().process_output_data(data)
     }
     fn process_output_values(&self, values: &[ChannelValue]) -> Result<Vec<u16>> {
-        if values.len() != 4 {
-            return Err(Error::ChannelValue);
-        }
-        let mut res = 0;
-        for (i, v) in values.iter().enumerate() {
-            match *v {
-                ChannelValue::Bit(state) => {
-                    if state {
-                        res = set_bit_16(res, i);
-                    }
-                }
-                ChannelValue::Disabled => {
-                    // do nothing
-                }
-                _ => {
-                    return Err(Error::ChannelValue);
-                }
-            }
-        }
-        Ok(vec![res])
+        self.generic().process_output_values(values)
     }
 }
 
 fn parameters_from_raw_data(data: &[u16]) -> Result<Vec<ChannelParameters>> {
-    if data.len() < 4 {
-        return Err(Error::BufferLength);
-    }
-
-    let channel_parameters: Result<Vec<_>> = (0..4)
-        .map(|i| {
-            let mut p = ChannelParameters::default();
-            p.substitute_value = match data[i] {
-                0 => false,
-                1 => true,
-                _ => {
-                    return Err(Error::ChannelParameter);
-                }
-            };
-            Ok(p)
-        })
-        .collect();
-    Ok(channel_parameters?)
+    ur20_do_generic::Mod::from_modbus_parameter_data(ModuleType::UR20_4DO_P, data)
+        .map(|m| m.ch_params)
 }
 
 #[cfg(test)]
@@ -111,7 +88,7 @@ mod tests {
     #[test]
     fn test_process_output_values_with_invalid_channel_len() {
         let m = Mod::default();
-        assert!(m.process_output_values(&[]).is_err());
+        assert_eq!(m.process_output_values(&[]).unwrap(), vec![0]);
         assert!(m
             .process_output_values(&[Bit(true), Bit(false), Bit(true)])
             .is_err());
@@ -120,11 +97,21 @@ mod tests {
             .is_ok());
     }
 
+    #[test]
+    fn test_process_output_values_with_none_and_disabled() {
+        let m = Mod::default();
+        assert_eq!(
+            m.process_output_values(&[None, Bit(true), Disabled, Bit(true)])
+                .unwrap(),
+            vec![0b1010]
+        );
+    }
+
     #[test]
     fn test_process_output_data() {
         let m = Mod::default();
         assert_eq!(
-            m.process_output_data(&vec![0x0F]).unwrap(),
+            m.process_output_data(&[0x0F]).unwrap(),
             &[
                 ChannelValue::Bit(true),
                 ChannelValue::Bit(true),
@@ -133,7 +120,7 @@ mod tests {
             ]
         );
         assert_eq!(
-            m.process_output_data(&vec![0b000_0101]).unwrap(),
+            m.process_output_data(&[0b000_0101]).unwrap(),
             &[
                 ChannelValue::Bit(true),
                 ChannelValue::Bit(false),
@@ -146,7 +133,7 @@ mod tests {
     #[test]
     fn test_process_output_data_with_invalid_buffer_size() {
         let m = Mod::default();
-        assert!(m.process_output_data(&vec![0; 2]).is_err());
+        assert!(m.process_output_data(&[0; 2]).is_err());
     }
 
     #[test]
@@ -194,20 +181,11 @@ mod tests {
             ChannelParameters::default()
         );
 
-        assert_eq!(
-            parameters_from_raw_data(&data).unwrap()[1].substitute_value,
-            true
-        );
+        assert!(parameters_from_raw_data(&data).unwrap()[1].substitute_value);
 
-        assert_eq!(
-            parameters_from_raw_data(&data).unwrap()[2].substitute_value,
-            false
-        );
+        assert!(!parameters_from_raw_data(&data).unwrap()[2].substitute_value);
 
-        assert_eq!(
-            parameters_from_raw_data(&data).unwrap()[3].substitute_value,
-            true
-        );
+        assert!(parameters_from_raw_data(&data).unwrap()[3].substitute_value);
     }
 
     #[test]
@@ -241,7 +219,7 @@ mod tests {
             0, // CH 3
         ];
         let module = Mod::from_modbus_parameter_data(&data).unwrap();
-        assert_eq!(module.ch_params[0].substitute_value, true);
-        assert_eq!(module.ch_params[3].substitute_value, false);
+        assert!(module.ch_params[0].substitute_value);
+        assert!(!module.ch_params[3].substitute_value);
     }
 }
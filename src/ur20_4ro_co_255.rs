@@ -1,15 +1,17 @@
 //! Relay output module UR20-4RO-CO-255
 
 use super::*;
-use crate::ur20_fbc_mod_tcp::{FromModbusParameterData, ProcessModbusTcpData};
+use crate::ur20_fbc_mod_tcp::{
+    FromModbusParameterData, ProcessDataLayout, ProcessModbusTcpData, ToModbusParameterData,
+};
 use crate::util::*;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Mod {
     pub ch_params: Vec<ChannelParameters>,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
 pub struct ChannelParameters {
     pub substitute_value: bool,
 }
@@ -21,11 +23,9 @@ impl FromModbusParameterData for Mod {
     }
 }
 
-impl Default for ChannelParameters {
-    fn default() -> Self {
-        ChannelParameters {
-            substitute_value: false,
-        }
+impl ToModbusParameterData for Mod {
+    fn to_modbus_parameter_data(&self) -> Vec<u16> {
+        parameters_to_raw_data(&self.ch_params)
     }
 }
 
@@ -49,6 +49,12 @@ impl ProcessModbusTcpData for Mod {
     fn process_output_byte_count(&self) -> usize {
         1
     }
+    fn layout(&self) -> ProcessDataLayout {
+        ProcessDataLayout {
+            input: vec![],
+            output: util::bit_layout(4),
+        }
+    }
     fn process_output_data(&self, data: &[u16]) -> Result<Vec<ChannelValue>> {
         if data.len() != 1 {
             return Err(Error::BufferLength);
@@ -59,9 +65,7 @@ impl ProcessModbusTcpData for Mod {
             .collect())
     }
     fn process_output_values(&self, values: &[ChannelValue]) -> Result<Vec<u16>> {
-        if values.len() != 4 {
-            return Err(Error::ChannelValue);
-        }
+        let values = util::output_values(self, values)?;
         let mut res = 0;
         for (i, v) in values.iter().enumerate() {
             match *v {
@@ -70,7 +74,7 @@ impl ProcessModbusTcpData for Mod {
                         res = set_bit_16(res, i);
                     }
                 }
-                ChannelValue::Disabled => {
+                ChannelValue::Disabled | ChannelValue::None => {
                     // do nothing
                 }
                 _ => {
@@ -100,7 +104,14 @@ fn parameters_from_raw_data(data: &[u16]) -> Result<Vec<ChannelParameters>> {
             Ok(p)
         })
         .collect();
-    Ok(channel_parameters?)
+    channel_parameters
+}
+
+fn parameters_to_raw_data(ch_params: &[ChannelParameters]) -> Vec<u16> {
+    ch_params
+        .iter()
+        .map(|p| u16::from(p.substitute_value))
+        .collect()
 }
 
 #[cfg(test)]
@@ -112,7 +123,7 @@ mod tests {
     #[test]
     fn test_process_output_values_with_invalid_channel_len() {
         let m = Mod::default();
-        assert!(m.process_output_values(&[]).is_err());
+        assert_eq!(m.process_output_values(&[]).unwrap(), vec![0]);
         assert!(m
             .process_output_values(&[Bit(true), Bit(false), Bit(true)])
             .is_err());
@@ -125,7 +136,7 @@ mod tests {
     fn test_process_output_data() {
         let m = Mod::default();
         assert_eq!(
-            m.process_output_data(&vec![0x0F]).unwrap(),
+            m.process_output_data(&[0x0F]).unwrap(),
             &[
                 ChannelValue::Bit(true),
                 ChannelValue::Bit(true),
@@ -134,7 +145,7 @@ mod tests {
             ]
         );
         assert_eq!(
-            m.process_output_data(&vec![0b000_0101]).unwrap(),
+            m.process_output_data(&[0b000_0101]).unwrap(),
             &[
                 ChannelValue::Bit(true),
                 ChannelValue::Bit(false),
@@ -147,7 +158,7 @@ mod tests {
     #[test]
     fn test_process_output_data_with_invalid_buffer_size() {
         let m = Mod::default();
-        assert!(m.process_output_data(&vec![0; 2]).is_err());
+        assert!(m.process_output_data(&[0; 2]).is_err());
     }
 
     #[test]
@@ -196,20 +207,11 @@ mod tests {
             ChannelParameters::default()
         );
 
-        assert_eq!(
-            parameters_from_raw_data(&data).unwrap()[1].substitute_value,
-            true
-        );
+        assert!(parameters_from_raw_data(&data).unwrap()[1].substitute_value);
 
-        assert_eq!(
-            parameters_from_raw_data(&data).unwrap()[2].substitute_value,
-            false
-        );
+        assert!(!parameters_from_raw_data(&data).unwrap()[2].substitute_value);
 
-        assert_eq!(
-            parameters_from_raw_data(&data).unwrap()[3].substitute_value,
-            true
-        );
+        assert!(parameters_from_raw_data(&data).unwrap()[3].substitute_value);
     }
 
     #[test]
@@ -243,7 +245,14 @@ mod tests {
             0, // CH 3
         ];
         let module = Mod::from_modbus_parameter_data(&data).unwrap();
-        assert_eq!(module.ch_params[0].substitute_value, true);
-        assert_eq!(module.ch_params[3].substitute_value, false);
+        assert!(module.ch_params[0].substitute_value);
+        assert!(!module.ch_params[3].substitute_value);
+    }
+
+    #[test]
+    fn test_parameters_to_raw_data() {
+        let mut m = Mod::default();
+        m.ch_params[1].substitute_value = true;
+        assert_eq!(m.to_modbus_parameter_data(), vec![0, 1, 0, 0]);
     }
 }
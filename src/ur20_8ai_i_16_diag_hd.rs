@@ -1,21 +1,27 @@
 //! Analog input module UR20-8AI-I-16-DIAG-HD
 
 use super::*;
-use crate::ur20_fbc_mod_tcp::{FromModbusParameterData, ProcessModbusTcpData};
-use num_traits::cast::FromPrimitive;
-
-#[derive(Debug)]
+use crate::codec::AnalogScaling;
+use crate::diagnosis::{
+    filter_channel_diagnostics, ChannelDiagnosis, ChannelDiagnostics, Diagnostics,
+};
+use crate::ur20_fbc_mod_tcp::{
+    FromModbusParameterData, ProcessDataLayout, ProcessModbusTcpData, ToModbusParameterData,
+};
+use num_traits::cast::{FromPrimitive, ToPrimitive};
+
+#[derive(Debug, Clone)]
 pub struct Mod {
     pub mod_params: ModuleParameters,
     pub ch_params: Vec<ChannelParameters>,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct ModuleParameters {
     pub frequency_suppression: FrequencySuppression,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct ChannelParameters {
     pub channel_diagnostics: bool,
     pub diag_short_circuit: bool,
@@ -33,6 +39,12 @@ impl FromModbusParameterData for Mod {
     }
 }
 
+impl ToModbusParameterData for Mod {
+    fn to_modbus_parameter_data(&self) -> Vec<u16> {
+        parameters_to_raw_data(&self.mod_params, &self.ch_params)
+    }
+}
+
 impl Default for ModuleParameters {
     fn default() -> Self {
         ModuleParameters {
@@ -65,10 +77,55 @@ impl Default for Mod {
     }
 }
 
+impl Mod {
+    /// Decodes the input data like `process_input_data`
+    /// but reports values outside of the measurement range.
+    ///
+    /// Disabled channels are `None`.
+    pub fn analog_input_values(&self, data: &[u16]) -> Result<Vec<Option<AnalogValue>>> {
+        if data.len() != 8 {
+            return Err(Error::BufferLength);
+        }
+        util::check_channel_count(self, &self.ch_params)?;
+        Ok(data
+            .iter()
+            .zip(&self.ch_params)
+            .map(|(v, p)| {
+                codec::u16_to_checked_analog_i_value(*v, &p.measurement_range, &p.data_format)
+            })
+            .collect())
+    }
+}
+
 impl Module for Mod {
     fn module_type(&self) -> ModuleType {
         ModuleType::UR20_8AI_I_16_DIAG_HD
     }
+    fn channel_info(&self) -> Vec<ChannelInfo> {
+        self.ch_params
+            .iter()
+            .map(|p| ChannelInfo {
+                unit: p.measurement_range.unit(),
+                range: Some(format!("{:?}", p.measurement_range)),
+                ..Default::default()
+            })
+            .collect()
+    }
+}
+
+impl Diagnostics for Mod {
+    /// Drops the causes that are not monitored.
+    fn process_diagnostic_data(&self, data: &[u16]) -> Result<Vec<ChannelDiagnostics>> {
+        filter_channel_diagnostics(&self.module_type(), data, |ch, cause| {
+            self.ch_params.get(ch).map_or(true, |p| {
+                p.channel_diagnostics
+                    && match cause {
+                        ChannelDiagnosis::ShortCircuit => p.diag_short_circuit,
+                        _ => true,
+                    }
+            })
+        })
+    }
 }
 
 impl ProcessModbusTcpData for Mod {
@@ -78,33 +135,42 @@ impl ProcessModbusTcpData for Mod {
     fn process_output_byte_count(&self) -> usize {
         0
     }
+    fn analog_scalings(&self) -> Vec<Option<AnalogScaling>> {
+        self.ch_params
+            .iter()
+            .map(|p| AnalogScaling::i(&p.measurement_range, &p.data_format))
+            .collect()
+    }
+    fn diagnostics(&self) -> Option<&dyn Diagnostics> {
+        Some(self)
+    }
+    fn layout(&self) -> ProcessDataLayout {
+        ProcessDataLayout {
+            input: util::channel_layout(8),
+            output: vec![],
+        }
+    }
     fn process_input_data(&self, data: &[u16]) -> Result<Vec<ChannelValue>> {
-        use crate::AnalogIRange::*;
-
         if data.len() != 8 {
             return Err(Error::BufferLength);
         }
 
-        if self.ch_params.len() != 8 {
-            return Err(Error::ChannelParameter);
-        }
+        util::check_channel_count(self, &self.ch_params)?;
 
         let res = (0..8)
             .map(|i| {
                 (
-                    f32::from(data[i] as i16),
+                    data[i],
                     &self.ch_params[i].measurement_range,
                     &self.ch_params[i].data_format,
                 )
             })
-            .map(|(val, range, format)| {
-                let factor = format.factor();
-                match *range {
-                    mA0To20 => ChannelValue::Decimal32(val * 20.0 / factor),
-                    mA4To20 => ChannelValue::Decimal32(val * 16.0 / factor + 4.0),
-                    Disabled => ChannelValue::Disabled,
-                }
-            })
+            .map(
+                |(val, range, format)| match codec::u16_to_analog_i_value(val, range, format) {
+                    Some(v) => ChannelValue::Decimal32(v),
+                    None => ChannelValue::Disabled,
+                },
+            )
             .collect();
         Ok(res)
     }
@@ -161,6 +227,20 @@ fn parameters_from_raw_data(data: &[u16]) -> Result<(ModuleParameters, Vec<Chann
     Ok((module_parameters, channel_parameters?))
 }
 
+fn parameters_to_raw_data(
+    mod_params: &ModuleParameters,
+    ch_params: &[ChannelParameters],
+) -> Vec<u16> {
+    let mut data = vec![mod_params.frequency_suppression.to_u16().unwrap()];
+    for p in ch_params {
+        data.push(u16::from(p.channel_diagnostics));
+        data.push(u16::from(p.diag_short_circuit));
+        data.push(p.data_format.to_u16().unwrap());
+        data.push(p.measurement_range.to_u16().unwrap());
+    }
+    data
+}
+
 #[cfg(test)]
 mod tests {
 
@@ -170,21 +250,21 @@ mod tests {
     #[test]
     fn test_process_input_data_with_empty_buffer() {
         let m = Mod::default();
-        assert!(m.process_input_data(&vec![]).is_err());
+        assert!(m.process_input_data(&[]).is_err());
     }
 
     #[test]
     fn test_process_input_data_with_missing_channel_parameters() {
         let mut m = Mod::default();
         m.ch_params = vec![];
-        assert!(m.process_input_data(&vec![0; 8]).is_err());
+        assert!(m.process_input_data(&[0; 8]).is_err());
     }
 
     #[test]
     fn test_process_input_data() {
         let mut m = Mod::default();
         assert_eq!(
-            m.process_input_data(&vec![5, 0, 7, 8, 0, 0, 0, 0]).unwrap(),
+            m.process_input_data(&[5, 0, 7, 8, 0, 0, 0, 0]).unwrap(),
             vec![Disabled; 8]
         );
 
@@ -199,7 +279,7 @@ mod tests {
         m.ch_params[5].data_format = DataFormat::S5;
 
         assert_eq!(
-            m.process_input_data(&vec![0x6C00, 0x3600, 0x4000, 0x6C00, 0x3600, 0x4000, 0, 0])
+            m.process_input_data(&[0x6C00, 0x3600, 0x4000, 0x6C00, 0x3600, 0x4000, 0, 0])
                 .unwrap(),
             vec![
                 Decimal32(20.0),
@@ -225,7 +305,7 @@ mod tests {
         m.ch_params[1].data_format = DataFormat::S5;
 
         let input = m
-            .process_input_data(&vec![0xED00, 0x0F333, 0, 0, 0, 0, 0, 0])
+            .process_input_data(&[0xED00, 0x0F333, 0, 0, 0, 0, 0, 0])
             .unwrap();
 
         if let ChannelValue::Decimal32(v) = input[0] {
@@ -243,7 +323,7 @@ mod tests {
     #[test]
     fn test_process_output_data() {
         let m = Mod::default();
-        assert!(m.process_output_data(&vec![0; 8]).is_err());
+        assert!(m.process_output_data(&[0; 8]).is_err());
         assert_eq!(
             m.process_output_data(&[]).unwrap(),
             vec![ChannelValue::None; 8]
@@ -256,11 +336,11 @@ mod tests {
         assert!(m
             .process_output_values(&[ChannelValue::Decimal32(0.0)])
             .is_err());
-        assert_eq!(m.process_output_values(&[]).unwrap(), &[]);
+        assert_eq!(m.process_output_values(&[]).unwrap(), Vec::<u16>::new());
         assert_eq!(
             m.process_output_values(&vec![ChannelValue::None; 8])
                 .unwrap(),
-            &[]
+            Vec::<u16>::new()
         );
     }
 
@@ -333,14 +413,8 @@ mod tests {
             ChannelParameters::default()
         );
 
-        assert_eq!(
-            parameters_from_raw_data(&data).unwrap().1[1].channel_diagnostics,
-            true
-        );
-        assert_eq!(
-            parameters_from_raw_data(&data).unwrap().1[1].diag_short_circuit,
-            false
-        );
+        assert!(parameters_from_raw_data(&data).unwrap().1[1].channel_diagnostics);
+        assert!(!parameters_from_raw_data(&data).unwrap().1[1].diag_short_circuit);
         assert_eq!(
             parameters_from_raw_data(&data).unwrap().1[1].data_format,
             DataFormat::S5
@@ -350,10 +424,7 @@ mod tests {
             AnalogIRange::Disabled
         );
 
-        assert_eq!(
-            parameters_from_raw_data(&data).unwrap().1[2].diag_short_circuit,
-            true
-        );
+        assert!(parameters_from_raw_data(&data).unwrap().1[2].diag_short_circuit);
         assert_eq!(
             parameters_from_raw_data(&data).unwrap().1[3].data_format,
             DataFormat::S7
@@ -424,6 +495,19 @@ mod tests {
             module.ch_params[1].measurement_range,
             AnalogIRange::Disabled
         );
-        assert_eq!(module.ch_params[2].channel_diagnostics, true);
+        assert!(module.ch_params[2].channel_diagnostics);
+    }
+
+    #[test]
+    fn test_parameters_to_raw_data() {
+        let mut m = Mod::default();
+        m.ch_params[0].channel_diagnostics = true;
+        m.ch_params[0].measurement_range = AnalogIRange::mA0To20;
+        let data = m.to_modbus_parameter_data();
+        assert_eq!(data.len(), 33);
+        assert_eq!(data[..5], [0, 1, 0, 1, 0]);
+        let (mod_params, ch_params) = parameters_from_raw_data(&data).unwrap();
+        assert_eq!(mod_params, m.mod_params);
+        assert_eq!(ch_params, m.ch_params);
     }
 }
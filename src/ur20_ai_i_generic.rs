@@ -0,0 +1,425 @@
+//! Analog input modules with eight current channels
+//!
+//! The modules share the process data format but come with two
+//! different parameter layouts: a short one (data format and
+//! measurement range) and one with additional diagnostic settings.
+
+use super::*;
+use crate::codec::AnalogScaling;
+use crate::diagnosis::{
+    filter_channel_diagnostics, ChannelDiagnosis, ChannelDiagnostics, Diagnostics,
+};
+use crate::ur20_fbc_mod_tcp::{ProcessDataLayout, ProcessModbusTcpData, ToModbusParameterData};
+use num_traits::cast::{FromPrimitive, ToPrimitive};
+
+/// Number of parameter registers of a channel without diagnostic settings.
+const CHANNEL_PARAM_CNT: usize = 2;
+
+/// Number of parameter registers of a channel with diagnostic settings.
+const DIAG_CHANNEL_PARAM_CNT: usize = 4;
+
+#[rustfmt::skip]
+make_variants! {
+    UR20_8AI_I_16_HD      => 1 + 8 * CHANNEL_PARAM_CNT,
+    UR20_8AI_I_16_DIAG_HD => 1 + 8 * DIAG_CHANNEL_PARAM_CNT,
+    UR20_8AI_I_PLC_INT    => 1 + 8 * CHANNEL_PARAM_CNT,
+}
+
+#[derive(Debug, Clone)]
+pub struct Mod {
+    pub module_type: ModuleType,
+    pub mod_params: ModuleParameters,
+    pub ch_params: Vec<ChannelParameters>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(default))]
+pub struct ModuleParameters {
+    pub frequency_suppression: FrequencySuppression,
+}
+
+/// Channel parameters.
+///
+/// The diagnostic settings are always `false`
+/// for modules without diagnostic parameters.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(default))]
+pub struct ChannelParameters {
+    pub channel_diagnostics: bool,
+    pub diag_short_circuit: bool,
+    pub data_format: DataFormat,
+    pub measurement_range: AnalogIRange,
+}
+
+impl Default for ModuleParameters {
+    fn default() -> Self {
+        ModuleParameters {
+            frequency_suppression: FrequencySuppression::Disabled,
+        }
+    }
+}
+
+impl Default for ChannelParameters {
+    fn default() -> Self {
+        ChannelParameters {
+            channel_diagnostics: false,
+            diag_short_circuit: false,
+            data_format: DataFormat::S7,
+            measurement_range: AnalogIRange::Disabled,
+        }
+    }
+}
+
+impl Mod {
+    /// Creates a module with default parameters.
+    pub fn new(module_type: ModuleType) -> Result<Mod> {
+        check_module_type(&module_type)?;
+        let ch_params = (0..8).map(|_| ChannelParameters::default()).collect();
+        Ok(Mod {
+            module_type,
+            mod_params: ModuleParameters::default(),
+            ch_params,
+        })
+    }
+
+    /// Creates a module instance from its parameter register data.
+    pub fn from_modbus_parameter_data(module_type: ModuleType, data: &[u16]) -> Result<Mod> {
+        let param_cnt = check_module_type(&module_type)?;
+        let (mod_params, ch_params) = parameters_from_raw_data(param_cnt, data)?;
+        Ok(Mod {
+            module_type,
+            mod_params,
+            ch_params,
+        })
+    }
+
+    /// Decodes the input data like `process_input_data`
+    /// but reports values outside of the measurement range.
+    ///
+    /// Disabled channels are `None`.
+    pub fn analog_input_values(&self, data: &[u16]) -> Result<Vec<Option<AnalogValue>>> {
+        if data.len() != 8 {
+            return Err(Error::BufferLength);
+        }
+        util::check_channel_count(self, &self.ch_params)?;
+        Ok(data
+            .iter()
+            .zip(&self.ch_params)
+            .map(|(v, p)| {
+                codec::u16_to_checked_analog_i_value(*v, &p.measurement_range, &p.data_format)
+            })
+            .collect())
+    }
+}
+
+impl Module for Mod {
+    fn module_type(&self) -> ModuleType {
+        self.module_type.clone()
+    }
+    fn channel_info(&self) -> Vec<ChannelInfo> {
+        self.ch_params
+            .iter()
+            .map(|p| ChannelInfo {
+                unit: p.measurement_range.unit(),
+                range: Some(format!("{:?}", p.measurement_range)),
+                ..Default::default()
+            })
+            .collect()
+    }
+}
+
+impl ToModbusParameterData for Mod {
+    fn to_modbus_parameter_data(&self) -> Vec<u16> {
+        let param_cnt = param_register_count(&self.module_type).unwrap_or(0);
+        parameters_to_raw_data(param_cnt, &self.mod_params, &self.ch_params)
+    }
+}
+
+impl Diagnostics for Mod {
+    /// Drops the causes that are not monitored.
+    ///
+    /// Modules without diagnostic parameters report all causes.
+    fn process_diagnostic_data(&self, data: &[u16]) -> Result<Vec<ChannelDiagnostics>> {
+        let diag_params =
+            param_register_count(&self.module_type) == Some(1 + 8 * DIAG_CHANNEL_PARAM_CNT);
+        filter_channel_diagnostics(&self.module_type, data, |ch, cause| {
+            !diag_params
+                || self.ch_params.get(ch).map_or(true, |p| {
+                    p.channel_diagnostics
+                        && match cause {
+                            ChannelDiagnosis::ShortCircuit => p.diag_short_circuit,
+                            _ => true,
+                        }
+                })
+        })
+    }
+}
+
+impl ProcessModbusTcpData for Mod {
+    fn process_input_byte_count(&self) -> usize {
+        16
+    }
+    fn process_output_byte_count(&self) -> usize {
+        0
+    }
+    fn analog_scalings(&self) -> Vec<Option<AnalogScaling>> {
+        self.ch_params
+            .iter()
+            .map(|p| AnalogScaling::i(&p.measurement_range, &p.data_format))
+            .collect()
+    }
+    fn diagnostics(&self) -> Option<&dyn Diagnostics> {
+        Some(self)
+    }
+    fn layout(&self) -> ProcessDataLayout {
+        ProcessDataLayout {
+            input: util::channel_layout(8),
+            output: vec![],
+        }
+    }
+    fn process_input_data(&self, data: &[u16]) -> Result<Vec<ChannelValue>> {
+        if data.len() != 8 {
+            return Err(Error::BufferLength);
+        }
+
+        util::check_channel_count(self, &self.ch_params)?;
+
+        let res = data
+            .iter()
+            .zip(&self.ch_params)
+            .map(|(val, p)| {
+                match codec::u16_to_analog_i_value(*val, &p.measurement_range, &p.data_format) {
+                    Some(v) => ChannelValue::Decimal32(v),
+                    None => ChannelValue::Disabled,
+                }
+            })
+            .collect();
+        Ok(res)
+    }
+    fn process_input_values(&self, values: &[ChannelValue]) -> Result<Vec<u16>> {
+        let values = util::output_values(self, values)?;
+        util::check_channel_count(self, &self.ch_params)?;
+        values
+            .iter()
+            .zip(&self.ch_params)
+            .map(|(v, p)| match *v {
+                ChannelValue::Decimal32(v) => Ok(codec::analog_i_value_to_u16(
+                    v,
+                    &p.measurement_range,
+                    &p.data_format,
+                )),
+                ChannelValue::Disabled | ChannelValue::None => Ok(0),
+                _ => Err(Error::ChannelValue),
+            })
+            .collect()
+    }
+}
+
+/// Returns the number of parameter registers of a supported module type.
+fn check_module_type(module_type: &ModuleType) -> Result<usize> {
+    param_register_count(module_type).ok_or(Error::UnknownModule)
+}
+
+fn bool_from_raw_data(value: u16) -> Result<bool> {
+    match value {
+        0 => Ok(false),
+        1 => Ok(true),
+        _ => Err(Error::ChannelParameter),
+    }
+}
+
+fn parameters_from_raw_data(
+    param_cnt: usize,
+    data: &[u16],
+) -> Result<(ModuleParameters, Vec<ChannelParameters>)> {
+    if data.len() < param_cnt {
+        return Err(Error::BufferLength);
+    }
+
+    let frequency_suppression = FromPrimitive::from_u16(data[0]).ok_or(Error::ChannelParameter)?;
+
+    let module_parameters = ModuleParameters {
+        frequency_suppression,
+    };
+
+    let ch_param_cnt = (param_cnt - 1) / 8;
+
+    let channel_parameters: Result<Vec<_>> = data[1..param_cnt]
+        .chunks(ch_param_cnt)
+        .map(|d| {
+            let mut p = ChannelParameters::default();
+            if ch_param_cnt == DIAG_CHANNEL_PARAM_CNT {
+                p.channel_diagnostics = bool_from_raw_data(d[0])?;
+                p.diag_short_circuit = bool_from_raw_data(d[1])?;
+            }
+            p.data_format =
+                FromPrimitive::from_u16(d[ch_param_cnt - 2]).ok_or(Error::ChannelParameter)?;
+            p.measurement_range =
+                FromPrimitive::from_u16(d[ch_param_cnt - 1]).ok_or(Error::ChannelParameter)?;
+            Ok(p)
+        })
+        .collect();
+    Ok((module_parameters, channel_parameters?))
+}
+
+fn parameters_to_raw_data(
+    param_cnt: usize,
+    mod_params: &ModuleParameters,
+    ch_params: &[ChannelParameters],
+) -> Vec<u16> {
+    let diagnostics = (param_cnt - 1) / 8 == DIAG_CHANNEL_PARAM_CNT;
+    let mut data = vec![mod_params.frequency_suppression.to_u16().unwrap()];
+    for p in ch_params {
+        if diagnostics {
+            data.push(u16::from(p.channel_diagnostics));
+            data.push(u16::from(p.diag_short_circuit));
+        }
+        data.push(p.data_format.to_u16().unwrap());
+        data.push(p.measurement_range.to_u16().unwrap());
+    }
+    data
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::ur20_fbc_mod_tcp::ModbusParameterRegisterCount;
+    use crate::ChannelValue::*;
+
+    #[test]
+    fn create_new_module() {
+        for t in MODULE_TYPES {
+            let m = Mod::new(t.clone()).unwrap();
+            assert_eq!(m.module_type(), *t);
+            assert_eq!(m.ch_params.len(), 8);
+        }
+        assert_eq!(
+            Mod::new(ModuleType::UR20_4AI_UI_16).err(),
+            Some(Error::UnknownModule)
+        );
+    }
+
+    #[test]
+    fn param_register_count_of_coupler() {
+        for t in MODULE_TYPES {
+            assert_eq!(
+                t.param_register_count() as usize,
+                param_register_count(t).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn test_process_input_data() {
+        let mut m = Mod::new(ModuleType::UR20_8AI_I_PLC_INT).unwrap();
+        assert!(m.process_input_data(&[0; 4]).is_err());
+        assert_eq!(m.process_input_data(&[5; 8]).unwrap(), vec![Disabled; 8]);
+
+        m.ch_params[0].measurement_range = AnalogIRange::mA0To20;
+        m.ch_params[1].measurement_range = AnalogIRange::mA4To20;
+        m.ch_params[1].data_format = DataFormat::S5;
+
+        assert_eq!(
+            m.process_input_data(&[0x3600, 0x4000, 0, 0, 0, 0, 0, 0])
+                .unwrap()[..3],
+            [Decimal32(10.0), Decimal32(20.0), Disabled]
+        );
+
+        m.ch_params.clear();
+        assert!(m.process_input_data(&[0; 8]).is_err());
+    }
+
+    #[test]
+    fn test_process_output_data() {
+        let m = Mod::new(ModuleType::UR20_8AI_I_16_HD).unwrap();
+        assert_eq!(m.process_output_byte_count(), 0);
+        assert_eq!(m.process_output_data(&[]).unwrap(), vec![None; 8]);
+        assert_eq!(m.process_output_values(&[]).unwrap(), Vec::<u16>::new());
+    }
+
+    #[test]
+    fn test_parameters_from_raw_data() {
+        let mut data = vec![0; 17];
+        data[0] = 2;
+        data[3] = 0; // CH 1 data format
+        data[4] = 1; // CH 1 measurement range
+        data[16] = 2; // CH 7 measurement range
+        let (m, ch) = parameters_from_raw_data(17, &data).unwrap();
+        assert_eq!(m.frequency_suppression, FrequencySuppression::Hz60);
+        assert_eq!(ch.len(), 8);
+        assert_eq!(ch[1].data_format, DataFormat::S5);
+        assert_eq!(ch[1].measurement_range, AnalogIRange::mA4To20);
+        assert_eq!(ch[7].measurement_range, AnalogIRange::Disabled);
+        assert!(!ch[1].channel_diagnostics);
+
+        data[16] = 3;
+        assert!(parameters_from_raw_data(17, &data).is_err());
+        assert!(parameters_from_raw_data(17, &[0; 16]).is_err());
+    }
+
+    #[test]
+    fn test_diag_parameters_from_raw_data() {
+        let mut data = vec![0; 33];
+        data[1] = 1; // CH 0 diagnostics
+        data[6] = 1; // CH 1 short circuit
+        data[32] = 1; // CH 7 measurement range
+        let (_, ch) = parameters_from_raw_data(33, &data).unwrap();
+        assert!(ch[0].channel_diagnostics);
+        assert!(!ch[0].diag_short_circuit);
+        assert!(ch[1].diag_short_circuit);
+        assert_eq!(ch[7].measurement_range, AnalogIRange::mA4To20);
+
+        data[2] = 2;
+        assert!(parameters_from_raw_data(33, &data).is_err());
+    }
+
+    #[test]
+    fn create_module_from_modbus_parameter_data() {
+        assert!(Mod::from_modbus_parameter_data(ModuleType::UR20_8AI_I_16_HD, &[0; 17]).is_ok());
+        assert!(
+            Mod::from_modbus_parameter_data(ModuleType::UR20_8AI_I_16_DIAG_HD, &[0; 17]).is_err()
+        );
+        assert!(
+            Mod::from_modbus_parameter_data(ModuleType::UR20_8AI_I_16_DIAG_HD, &[0; 33]).is_ok()
+        );
+        assert!(Mod::from_modbus_parameter_data(ModuleType::UR20_4AI_UI_16, &[0; 17]).is_err());
+    }
+
+    #[test]
+    fn test_parameters_to_raw_data() {
+        let mut m = Mod::new(ModuleType::UR20_8AI_I_16_DIAG_HD).unwrap();
+        m.mod_params.frequency_suppression = FrequencySuppression::Hz50;
+        m.ch_params[7].channel_diagnostics = true;
+        m.ch_params[7].measurement_range = AnalogIRange::mA4To20;
+        let data = m.to_modbus_parameter_data();
+        assert_eq!(data.len(), 33);
+        assert_eq!(data[0], 1);
+        assert_eq!(data[29..], [1, 0, 1, 1]);
+        let (mod_params, ch_params) = parameters_from_raw_data(33, &data).unwrap();
+        assert_eq!(mod_params, m.mod_params);
+        assert_eq!(ch_params, m.ch_params);
+
+        m.module_type = ModuleType::UR20_8AI_I_PLC_INT;
+        assert_eq!(m.to_modbus_parameter_data().len(), 17);
+    }
+
+    #[test]
+    fn test_analog_input_values() {
+        let mut m = Mod::new(ModuleType::UR20_8AI_I_16_HD).unwrap();
+        for p in &mut m.ch_params[..3] {
+            p.measurement_range = AnalogIRange::mA0To20;
+        }
+        assert_eq!(
+            m.analog_input_values(&[0x3600, 0x7F00, 0x8000, 0, 0, 0, 0, 0])
+                .unwrap()[..4],
+            vec![
+                Some(AnalogValue::InRange(10.0)),
+                Some(AnalogValue::Overload),
+                Some(AnalogValue::Underload),
+                Option::None,
+            ][..]
+        );
+        assert!(m.analog_input_values(&[0; 2]).is_err());
+    }
+}
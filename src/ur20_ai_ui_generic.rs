@@ -0,0 +1,467 @@
+//! Analog input modules with four current/voltage channels
+//!
+//! The modules share the process data format but come with two
+//! different parameter layouts: a short one (data format and
+//! measurement range) and one with additional diagnostic settings.
+
+use super::*;
+use crate::codec::AnalogScaling;
+use crate::diagnosis::{
+    filter_channel_diagnostics, ChannelDiagnosis, ChannelDiagnostics, Diagnostics,
+};
+use crate::ur20_fbc_mod_tcp::{ProcessDataLayout, ProcessModbusTcpData, ToModbusParameterData};
+use num_traits::cast::{FromPrimitive, ToPrimitive};
+
+/// Number of parameter registers of a channel without diagnostic settings.
+const CHANNEL_PARAM_CNT: usize = 2;
+
+/// Number of parameter registers of a channel with diagnostic settings.
+const DIAG_CHANNEL_PARAM_CNT: usize = 5;
+
+#[rustfmt::skip]
+make_variants! {
+    UR20_4AI_UI_16          => 1 + 4 * CHANNEL_PARAM_CNT,
+    UR20_4AI_UI_16_HD       => 1 + 4 * CHANNEL_PARAM_CNT,
+    UR20_4AI_UI_16_DIAG_HD  => 1 + 4 * DIAG_CHANNEL_PARAM_CNT,
+    UR20_4AI_UI_DIF_16_DIAG => 1 + 4 * DIAG_CHANNEL_PARAM_CNT,
+}
+
+#[derive(Debug, Clone)]
+pub struct Mod {
+    pub module_type: ModuleType,
+    pub mod_params: ModuleParameters,
+    pub ch_params: Vec<ChannelParameters>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(default))]
+pub struct ModuleParameters {
+    pub frequency_suppression: FrequencySuppression,
+}
+
+/// Channel parameters.
+///
+/// The diagnostic settings are always `false`
+/// for modules without diagnostic parameters.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(default))]
+pub struct ChannelParameters {
+    pub channel_diagnostics: bool,
+    pub diag_short_circuit: bool,
+    pub diag_line_break: bool,
+    pub data_format: DataFormat,
+    pub measurement_range: AnalogUIRange,
+}
+
+impl Default for ModuleParameters {
+    fn default() -> Self {
+        ModuleParameters {
+            frequency_suppression: FrequencySuppression::Disabled,
+        }
+    }
+}
+
+impl Default for ChannelParameters {
+    fn default() -> Self {
+        ChannelParameters {
+            channel_diagnostics: false,
+            diag_short_circuit: false,
+            diag_line_break: false,
+            data_format: DataFormat::S7,
+            measurement_range: AnalogUIRange::Disabled,
+        }
+    }
+}
+
+impl Mod {
+    /// Creates a module with default parameters.
+    pub fn new(module_type: ModuleType) -> Result<Mod> {
+        check_module_type(&module_type)?;
+        let ch_params = (0..4).map(|_| ChannelParameters::default()).collect();
+        Ok(Mod {
+            module_type,
+            mod_params: ModuleParameters::default(),
+            ch_params,
+        })
+    }
+
+    /// Creates a module instance from its parameter register data.
+    pub fn from_modbus_parameter_data(module_type: ModuleType, data: &[u16]) -> Result<Mod> {
+        let param_cnt = check_module_type(&module_type)?;
+        let (mod_params, ch_params) = parameters_from_raw_data(param_cnt, data)?;
+        Ok(Mod {
+            module_type,
+            mod_params,
+            ch_params,
+        })
+    }
+
+    /// Decodes the input data like `process_input_data`
+    /// but reports values outside of the measurement range.
+    ///
+    /// Disabled channels are `None`.
+    pub fn analog_input_values(&self, data: &[u16]) -> Result<Vec<Option<AnalogValue>>> {
+        if data.len() != 4 {
+            return Err(Error::BufferLength);
+        }
+        util::check_channel_count(self, &self.ch_params)?;
+        Ok(data
+            .iter()
+            .zip(&self.ch_params)
+            .map(|(v, p)| {
+                codec::u16_to_checked_analog_ui_value(*v, &p.measurement_range, &p.data_format)
+            })
+            .collect())
+    }
+}
+
+impl Module for Mod {
+    fn module_type(&self) -> ModuleType {
+        self.module_type.clone()
+    }
+    fn channel_info(&self) -> Vec<ChannelInfo> {
+        self.ch_params
+            .iter()
+            .map(|p| ChannelInfo {
+                unit: p.measurement_range.unit(),
+                range: Some(format!("{:?}", p.measurement_range)),
+                ..Default::default()
+            })
+            .collect()
+    }
+}
+
+impl ToModbusParameterData for Mod {
+    fn to_modbus_parameter_data(&self) -> Vec<u16> {
+        let param_cnt = param_register_count(&self.module_type).unwrap_or(0);
+        parameters_to_raw_data(param_cnt, &self.mod_params, &self.ch_params)
+    }
+}
+
+impl Diagnostics for Mod {
+    /// Drops the causes that are not monitored.
+    ///
+    /// Modules without diagnostic parameters report all causes.
+    fn process_diagnostic_data(&self, data: &[u16]) -> Result<Vec<ChannelDiagnostics>> {
+        let diag_params =
+            param_register_count(&self.module_type) == Some(1 + 4 * DIAG_CHANNEL_PARAM_CNT);
+        filter_channel_diagnostics(&self.module_type, data, |ch, cause| {
+            !diag_params
+                || self.ch_params.get(ch).map_or(true, |p| {
+                    p.channel_diagnostics
+                        && match cause {
+                            ChannelDiagnosis::ShortCircuit => p.diag_short_circuit,
+                            ChannelDiagnosis::WireBreak => p.diag_line_break,
+                            _ => true,
+                        }
+                })
+        })
+    }
+}
+
+impl ProcessModbusTcpData for Mod {
+    fn process_input_byte_count(&self) -> usize {
+        8
+    }
+    fn process_output_byte_count(&self) -> usize {
+        0
+    }
+    fn analog_scalings(&self) -> Vec<Option<AnalogScaling>> {
+        self.ch_params
+            .iter()
+            .map(|p| AnalogScaling::ui(&p.measurement_range, &p.data_format))
+            .collect()
+    }
+    fn diagnostics(&self) -> Option<&dyn Diagnostics> {
+        Some(self)
+    }
+    fn layout(&self) -> ProcessDataLayout {
+        ProcessDataLayout {
+            input: util::channel_layout(4),
+            output: vec![],
+        }
+    }
+    fn process_input_data(&self, data: &[u16]) -> Result<Vec<ChannelValue>> {
+        if data.len() != 4 {
+            return Err(Error::BufferLength);
+        }
+
+        util::check_channel_count(self, &self.ch_params)?;
+
+        let res = data
+            .iter()
+            .zip(&self.ch_params)
+            .map(|(val, p)| {
+                match codec::u16_to_analog_ui_value(*val, &p.measurement_range, &p.data_format) {
+                    Some(v) => ChannelValue::Decimal32(v),
+                    None => ChannelValue::Disabled,
+                }
+            })
+            .collect();
+        Ok(res)
+    }
+    fn process_input_values(&self, values: &[ChannelValue]) -> Result<Vec<u16>> {
+        let values = util::output_values(self, values)?;
+        util::check_channel_count(self, &self.ch_params)?;
+        values
+            .iter()
+            .zip(&self.ch_params)
+            .map(|(v, p)| match *v {
+                ChannelValue::Decimal32(v) => Ok(codec::analog_ui_value_to_u16(
+                    v,
+                    &p.measurement_range,
+                    &p.data_format,
+                )),
+                ChannelValue::Disabled | ChannelValue::None => Ok(0),
+                _ => Err(Error::ChannelValue),
+            })
+            .collect()
+    }
+    fn process_output_data(&self, data: &[u16]) -> Result<Vec<ChannelValue>> {
+        if !data.is_empty() {
+            return Err(Error::BufferLength);
+        }
+        Ok((0..4).map(|_| ChannelValue::None).collect())
+    }
+}
+
+/// Returns the number of parameter registers of a supported module type.
+fn check_module_type(module_type: &ModuleType) -> Result<usize> {
+    param_register_count(module_type).ok_or(Error::UnknownModule)
+}
+
+fn bool_from_raw_data(value: u16) -> Result<bool> {
+    match value {
+        0 => Ok(false),
+        1 => Ok(true),
+        _ => Err(Error::ChannelParameter),
+    }
+}
+
+fn parameters_from_raw_data(
+    param_cnt: usize,
+    data: &[u16],
+) -> Result<(ModuleParameters, Vec<ChannelParameters>)> {
+    if data.len() < param_cnt {
+        return Err(Error::BufferLength);
+    }
+
+    let frequency_suppression = FromPrimitive::from_u16(data[0]).ok_or(Error::ChannelParameter)?;
+
+    let module_parameters = ModuleParameters {
+        frequency_suppression,
+    };
+
+    let ch_param_cnt = (param_cnt - 1) / 4;
+
+    let channel_parameters: Result<Vec<_>> = data[1..param_cnt]
+        .chunks(ch_param_cnt)
+        .map(|d| {
+            let mut p = ChannelParameters::default();
+            if ch_param_cnt == DIAG_CHANNEL_PARAM_CNT {
+                p.channel_diagnostics = bool_from_raw_data(d[0])?;
+                p.diag_short_circuit = bool_from_raw_data(d[1])?;
+                p.diag_line_break = bool_from_raw_data(d[2])?;
+            }
+            p.data_format =
+                FromPrimitive::from_u16(d[ch_param_cnt - 2]).ok_or(Error::ChannelParameter)?;
+            p.measurement_range =
+                FromPrimitive::from_u16(d[ch_param_cnt - 1]).ok_or(Error::ChannelParameter)?;
+            Ok(p)
+        })
+        .collect();
+    Ok((module_parameters, channel_parameters?))
+}
+
+fn parameters_to_raw_data(
+    param_cnt: usize,
+    mod_params: &ModuleParameters,
+    ch_params: &[ChannelParameters],
+) -> Vec<u16> {
+    let diagnostics = (param_cnt - 1) / 4 == DIAG_CHANNEL_PARAM_CNT;
+    let mut data = vec![mod_params.frequency_suppression.to_u16().unwrap()];
+    for p in ch_params {
+        if diagnostics {
+            data.push(u16::from(p.channel_diagnostics));
+            data.push(u16::from(p.diag_short_circuit));
+            data.push(u16::from(p.diag_line_break));
+        }
+        data.push(p.data_format.to_u16().unwrap());
+        data.push(p.measurement_range.to_u16().unwrap());
+    }
+    data
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::ur20_fbc_mod_tcp::ModbusParameterRegisterCount;
+    use crate::ChannelValue::*;
+
+    #[test]
+    fn create_new_module() {
+        for t in MODULE_TYPES {
+            let m = Mod::new(t.clone()).unwrap();
+            assert_eq!(m.module_type(), *t);
+            assert_eq!(m.ch_params.len(), 4);
+        }
+        assert_eq!(
+            Mod::new(ModuleType::UR20_4AI_UI_12).err(),
+            Some(Error::UnknownModule)
+        );
+    }
+
+    #[test]
+    fn param_register_count_of_coupler() {
+        for t in MODULE_TYPES {
+            assert_eq!(
+                t.param_register_count() as usize,
+                param_register_count(t).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn test_process_input_data() {
+        let mut m = Mod::new(ModuleType::UR20_4AI_UI_16_HD).unwrap();
+        assert!(m.process_input_data(&[]).is_err());
+        assert_eq!(
+            m.process_input_data(&[5, 0, 7, 8]).unwrap(),
+            vec![Disabled; 4]
+        );
+
+        m.ch_params[0].measurement_range = AnalogUIRange::mA0To20;
+        m.ch_params[1].measurement_range = AnalogUIRange::VMinus5To5;
+        m.ch_params[2].measurement_range = AnalogUIRange::V2To10;
+        m.ch_params[2].data_format = DataFormat::S5;
+
+        assert_eq!(
+            m.process_input_data(&[0x6C00, 0x3600, 0x4000, 0x6C00])
+                .unwrap(),
+            vec![Decimal32(20.0), Decimal32(2.5), Decimal32(10.0), Disabled]
+        );
+
+        m.ch_params.clear();
+        assert!(m.process_input_data(&[0; 4]).is_err());
+    }
+
+    #[test]
+    fn test_process_output_data() {
+        let m = Mod::new(ModuleType::UR20_4AI_UI_16).unwrap();
+        assert!(m.process_output_data(&[0; 4]).is_err());
+        assert_eq!(m.process_output_data(&[]).unwrap(), vec![None; 4]);
+        assert_eq!(m.process_output_values(&[]).unwrap(), Vec::<u16>::new());
+    }
+
+    #[test]
+    fn test_parameters_from_raw_data() {
+        #[rustfmt::skip]
+        let data = vec![
+            3,    // Module
+            1, 8, // CH 0
+            0, 5, // CH 1
+            0, 0, // CH 2
+            0, 1, // CH 3
+        ];
+        let (m, ch) = parameters_from_raw_data(9, &data).unwrap();
+        assert_eq!(m.frequency_suppression, FrequencySuppression::Average16);
+        assert_eq!(ch.len(), 4);
+        assert_eq!(ch[0], ChannelParameters::default());
+        assert_eq!(ch[1].data_format, DataFormat::S5);
+        assert_eq!(ch[1].measurement_range, AnalogUIRange::VMinus5To5);
+        assert_eq!(ch[3].measurement_range, AnalogUIRange::mA4To20);
+        assert!(parameters_from_raw_data(9, &data[..8]).is_err());
+    }
+
+    #[test]
+    fn test_diag_parameters_from_raw_data() {
+        #[rustfmt::skip]
+        let mut data = vec![
+            0,             // Module
+            0, 0, 0, 1, 8, // CH 0
+            1, 0, 0, 0, 5, // CH 1
+            0, 1, 0, 0, 0, // CH 2
+            0, 0, 1, 0, 0, // CH 3
+        ];
+        let (_, ch) = parameters_from_raw_data(21, &data).unwrap();
+        assert_eq!(ch.len(), 4);
+        assert_eq!(ch[0], ChannelParameters::default());
+        assert!(ch[1].channel_diagnostics);
+        assert_eq!(ch[1].measurement_range, AnalogUIRange::VMinus5To5);
+        assert!(ch[2].diag_short_circuit);
+        assert!(ch[3].diag_line_break);
+        assert_eq!(ch[3].measurement_range, AnalogUIRange::mA0To20);
+
+        data[12] = 2;
+        assert!(parameters_from_raw_data(21, &data).is_err());
+        assert!(parameters_from_raw_data(21, &[0; 20]).is_err());
+    }
+
+    #[test]
+    fn create_module_from_modbus_parameter_data() {
+        let mut data = vec![0; 21];
+        data[5] = 1;
+        let m =
+            Mod::from_modbus_parameter_data(ModuleType::UR20_4AI_UI_DIF_16_DIAG, &data).unwrap();
+        assert_eq!(m.ch_params[0].measurement_range, AnalogUIRange::mA4To20);
+        assert!(Mod::from_modbus_parameter_data(ModuleType::UR20_4AI_UI_16, &[0; 8]).is_err());
+        assert!(Mod::from_modbus_parameter_data(ModuleType::UR20_4AI_UI_16, &[0; 9]).is_ok());
+        assert!(Mod::from_modbus_parameter_data(ModuleType::UR20_4DI_P, &[0; 9]).is_err());
+    }
+
+    #[test]
+    fn test_parameters_to_raw_data() {
+        let mut m = Mod::new(ModuleType::UR20_4AI_UI_16).unwrap();
+        m.ch_params[0].measurement_range = AnalogUIRange::V0To5;
+        let data = m.to_modbus_parameter_data();
+        assert_eq!(data, vec![0, 1, 4, 1, 8, 1, 8, 1, 8]);
+
+        m.module_type = ModuleType::UR20_4AI_UI_16_DIAG_HD;
+        m.ch_params[2].diag_short_circuit = true;
+        let data = m.to_modbus_parameter_data();
+        assert_eq!(data.len(), 21);
+        assert_eq!(data[11..16], [0, 1, 0, 1, 8]);
+        let (_, ch_params) = parameters_from_raw_data(21, &data).unwrap();
+        assert_eq!(ch_params, m.ch_params);
+    }
+
+    #[test]
+    fn filter_unmonitored_diagnosis() {
+        let mut data = vec![0; 8];
+        data[2] = 0b0011;
+        data[4] = 0b0100_0000_0000_0010;
+
+        let mut m = Mod::new(ModuleType::UR20_4AI_UI_16).unwrap();
+        let all = m.process_diagnostic_data(&data).unwrap();
+        assert_eq!(all[0], vec![ChannelDiagnosis::ShortCircuit]);
+        assert_eq!(all[1], vec![ChannelDiagnosis::WireBreak]);
+
+        m.module_type = ModuleType::UR20_4AI_UI_16_DIAG_HD;
+        m.ch_params[0].channel_diagnostics = true;
+        m.ch_params[1].channel_diagnostics = true;
+        m.ch_params[1].diag_line_break = true;
+        let filtered = m.process_diagnostic_data(&data).unwrap();
+        assert!(filtered[0].is_empty());
+        assert_eq!(filtered[1], vec![ChannelDiagnosis::WireBreak]);
+        assert!(m.process_diagnostic_data(&[0; 4]).is_err());
+    }
+
+    #[test]
+    fn test_analog_input_values() {
+        let mut m = Mod::new(ModuleType::UR20_4AI_UI_16).unwrap();
+        for p in &mut m.ch_params[..3] {
+            p.measurement_range = AnalogUIRange::V1To5;
+        }
+        assert_eq!(
+            m.analog_input_values(&[0x3600, 0x7FFF, 0x8000, 0]).unwrap()[..4],
+            vec![
+                Some(AnalogValue::InRange(3.0)),
+                Some(AnalogValue::Overload),
+                Some(AnalogValue::WireBreak),
+                Option::None,
+            ][..]
+        );
+        assert!(m.analog_input_values(&[0; 2]).is_err());
+    }
+}
@@ -0,0 +1,417 @@
+//! Analog output modules with four current/voltage channels
+//!
+//! The modules share the process data format but come with two
+//! different parameter layouts: with or without the channel diagnostics.
+
+use super::*;
+use crate::codec::AnalogScaling;
+use crate::diagnosis::{filter_channel_diagnostics, ChannelDiagnostics, Diagnostics};
+use crate::ur20_fbc_mod_tcp::{ProcessDataLayout, ProcessModbusTcpData, ToModbusParameterData};
+use num_traits::cast::{FromPrimitive, ToPrimitive};
+
+/// Number of parameter registers of a channel without diagnostics.
+const CHANNEL_PARAM_CNT: usize = 3;
+
+/// Number of parameter registers of a channel with diagnostics.
+const DIAG_CHANNEL_PARAM_CNT: usize = 4;
+
+#[rustfmt::skip]
+make_variants! {
+    UR20_4AO_UI_16         => 4 * CHANNEL_PARAM_CNT,
+    UR20_4AO_UI_16_M       => 4 * CHANNEL_PARAM_CNT,
+    UR20_4AO_UI_16_HD      => 4 * CHANNEL_PARAM_CNT,
+    UR20_4AO_UI_16_DIAG    => 4 * DIAG_CHANNEL_PARAM_CNT,
+    UR20_4AO_UI_16_M_DIAG  => 4 * DIAG_CHANNEL_PARAM_CNT,
+    UR20_4AO_UI_16_DIAG_HD => 4 * DIAG_CHANNEL_PARAM_CNT,
+}
+
+#[derive(Debug, Clone)]
+pub struct Mod {
+    pub module_type: ModuleType,
+    pub ch_params: Vec<ChannelParameters>,
+}
+
+/// Channel parameters.
+///
+/// `channel_diagnostics` is always `false`
+/// for modules without diagnostics.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(default))]
+pub struct ChannelParameters {
+    pub data_format: DataFormat,
+    pub output_range: AnalogUIRange,
+    /// Output value (mA or V) on a fieldbus failure.
+    pub substitute_value: f32,
+    pub channel_diagnostics: bool,
+}
+
+impl Default for ChannelParameters {
+    fn default() -> Self {
+        ChannelParameters {
+            data_format: DataFormat::S7,
+            output_range: AnalogUIRange::Disabled,
+            substitute_value: 0.0,
+            channel_diagnostics: false,
+        }
+    }
+}
+
+impl Mod {
+    /// Creates a module with default parameters.
+    pub fn new(module_type: ModuleType) -> Result<Mod> {
+        check_module_type(&module_type)?;
+        let ch_params = (0..4).map(|_| ChannelParameters::default()).collect();
+        Ok(Mod {
+            module_type,
+            ch_params,
+        })
+    }
+
+    /// Creates a module instance from its parameter register data.
+    pub fn from_modbus_parameter_data(module_type: ModuleType, data: &[u16]) -> Result<Mod> {
+        let param_cnt = check_module_type(&module_type)?;
+        let ch_params = parameters_from_raw_data(param_cnt, data)?;
+        Ok(Mod {
+            module_type,
+            ch_params,
+        })
+    }
+
+    /// The values the module outputs on a fieldbus failure.
+    ///
+    /// Disabled channels don't output anything.
+    pub fn substitute_values(&self) -> Vec<ChannelValue> {
+        self.ch_params
+            .iter()
+            .map(|p| match p.output_range {
+                AnalogUIRange::Disabled => ChannelValue::Disabled,
+                _ => ChannelValue::Decimal32(p.substitute_value),
+            })
+            .collect()
+    }
+
+    /// Decodes the output data like `process_output_data`
+    /// but reports values outside of the measurement range.
+    ///
+    /// Disabled channels are `None`.
+    pub fn analog_output_values(&self, data: &[u16]) -> Result<Vec<Option<AnalogValue>>> {
+        if data.len() != 4 {
+            return Err(Error::BufferLength);
+        }
+        util::check_channel_count(self, &self.ch_params)?;
+        Ok(data
+            .iter()
+            .zip(&self.ch_params)
+            .map(|(v, p)| {
+                codec::u16_to_checked_analog_ui_value(*v, &p.output_range, &p.data_format)
+            })
+            .collect())
+    }
+}
+
+impl Module for Mod {
+    fn module_type(&self) -> ModuleType {
+        self.module_type.clone()
+    }
+    fn channel_info(&self) -> Vec<ChannelInfo> {
+        self.ch_params
+            .iter()
+            .map(|p| ChannelInfo {
+                unit: p.output_range.unit(),
+                range: Some(format!("{:?}", p.output_range)),
+                ..Default::default()
+            })
+            .collect()
+    }
+}
+
+impl ToModbusParameterData for Mod {
+    fn to_modbus_parameter_data(&self) -> Vec<u16> {
+        let param_cnt = param_register_count(&self.module_type).unwrap_or(0);
+        parameters_to_raw_data(param_cnt, &self.ch_params)
+    }
+}
+
+impl Diagnostics for Mod {
+    /// Drops the causes that are not monitored.
+    ///
+    /// Modules without diagnostic parameters report all causes.
+    fn process_diagnostic_data(&self, data: &[u16]) -> Result<Vec<ChannelDiagnostics>> {
+        let diag_params =
+            param_register_count(&self.module_type) == Some(4 * DIAG_CHANNEL_PARAM_CNT);
+        filter_channel_diagnostics(&self.module_type, data, |ch, _| {
+            !diag_params
+                || self
+                    .ch_params
+                    .get(ch)
+                    .map_or(true, |p| p.channel_diagnostics)
+        })
+    }
+}
+
+impl ProcessModbusTcpData for Mod {
+    fn process_input_byte_count(&self) -> usize {
+        0
+    }
+    fn process_output_byte_count(&self) -> usize {
+        8
+    }
+    fn analog_scalings(&self) -> Vec<Option<AnalogScaling>> {
+        self.ch_params
+            .iter()
+            .map(|p| AnalogScaling::ui(&p.output_range, &p.data_format))
+            .collect()
+    }
+    fn diagnostics(&self) -> Option<&dyn Diagnostics> {
+        Some(self)
+    }
+    fn layout(&self) -> ProcessDataLayout {
+        ProcessDataLayout {
+            input: vec![],
+            output: util::channel_layout(4),
+        }
+    }
+    fn process_output_data(&self, data: &[u16]) -> Result<Vec<ChannelValue>> {
+        if data.len() != 4 {
+            return Err(Error::BufferLength);
+        }
+        util::check_channel_count(self, &self.ch_params)?;
+        Ok(data
+            .iter()
+            .zip(&self.ch_params)
+            .map(|(v, p)| {
+                match codec::u16_to_analog_ui_value(*v, &p.output_range, &p.data_format) {
+                    Some(v) => ChannelValue::Decimal32(v),
+                    None => ChannelValue::Disabled,
+                }
+            })
+            .collect())
+    }
+    fn process_output_values(&self, values: &[ChannelValue]) -> Result<Vec<u16>> {
+        let values = util::output_values(self, values)?;
+        util::check_channel_count(self, &self.ch_params)?;
+        values
+            .iter()
+            .zip(&self.ch_params)
+            .map(|(v, p)| match *v {
+                ChannelValue::Decimal32(v) => Ok(codec::analog_ui_value_to_u16(
+                    v,
+                    &p.output_range,
+                    &p.data_format,
+                )),
+                ChannelValue::Disabled | ChannelValue::None => Ok(0),
+                _ => Err(Error::ChannelValue),
+            })
+            .collect()
+    }
+}
+
+/// Returns the number of parameter registers of a supported module type.
+fn check_module_type(module_type: &ModuleType) -> Result<usize> {
+    param_register_count(module_type).ok_or(Error::UnknownModule)
+}
+
+fn parameters_from_raw_data(param_cnt: usize, data: &[u16]) -> Result<Vec<ChannelParameters>> {
+    if data.len() < param_cnt {
+        return Err(Error::BufferLength);
+    }
+
+    let ch_param_cnt = param_cnt / 4;
+
+    let channel_parameters: Result<Vec<_>> = data[..param_cnt]
+        .chunks(ch_param_cnt)
+        .map(|d| {
+            let mut p = ChannelParameters::default();
+
+            p.data_format = FromPrimitive::from_u16(d[0]).ok_or(Error::ChannelParameter)?;
+            p.output_range = FromPrimitive::from_u16(d[1]).ok_or(Error::ChannelParameter)?;
+
+            if let Some(v) = codec::u16_to_analog_ui_value(d[2], &p.output_range, &p.data_format) {
+                p.substitute_value = v;
+            }
+
+            if ch_param_cnt == DIAG_CHANNEL_PARAM_CNT {
+                p.channel_diagnostics = match d[3] {
+                    0 => false,
+                    1 => true,
+                    _ => {
+                        return Err(Error::ChannelParameter);
+                    }
+                };
+            }
+            Ok(p)
+        })
+        .collect();
+    channel_parameters
+}
+
+fn parameters_to_raw_data(param_cnt: usize, ch_params: &[ChannelParameters]) -> Vec<u16> {
+    let diagnostics = param_cnt / 4 == DIAG_CHANNEL_PARAM_CNT;
+    let mut data = Vec::with_capacity(param_cnt);
+    for p in ch_params {
+        data.push(p.data_format.to_u16().unwrap());
+        data.push(p.output_range.to_u16().unwrap());
+        data.push(codec::analog_ui_value_to_u16(
+            p.substitute_value,
+            &p.output_range,
+            &p.data_format,
+        ));
+        if diagnostics {
+            data.push(u16::from(p.channel_diagnostics));
+        }
+    }
+    data
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::ur20_fbc_mod_tcp::ModbusParameterRegisterCount;
+    use crate::ChannelValue::*;
+
+    #[test]
+    fn create_new_module() {
+        for t in MODULE_TYPES {
+            let m = Mod::new(t.clone()).unwrap();
+            assert_eq!(m.module_type(), *t);
+            assert_eq!(m.ch_params.len(), 4);
+        }
+        assert_eq!(
+            Mod::new(ModuleType::UR20_4AI_UI_16).err(),
+            Some(Error::UnknownModule)
+        );
+    }
+
+    #[test]
+    fn param_register_count_of_coupler() {
+        for t in MODULE_TYPES {
+            assert_eq!(
+                t.param_register_count() as usize,
+                param_register_count(t).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn test_process_output_data() {
+        let mut m = Mod::new(ModuleType::UR20_4AO_UI_16_M).unwrap();
+        assert!(m.process_output_data(&[]).is_err());
+        assert_eq!(m.process_output_data(&[1; 4]).unwrap(), vec![Disabled; 4]);
+        m.ch_params[1].output_range = AnalogUIRange::mA0To20;
+        m.ch_params[2].output_range = AnalogUIRange::V0To10;
+        assert_eq!(
+            m.process_output_data(&[0, 0x6C00, 0x3600, 0]).unwrap(),
+            vec![Disabled, Decimal32(20.0), Decimal32(5.0), Disabled]
+        );
+    }
+
+    #[test]
+    fn test_process_output_values() {
+        let mut m = Mod::new(ModuleType::UR20_4AO_UI_16_HD).unwrap();
+        assert_eq!(m.process_output_values(&[]).unwrap(), vec![0; 4]);
+        assert!(m.process_output_values(&[Decimal32(0.0)]).is_err());
+        assert!(m
+            .process_output_values(&[Bit(true), None, None, None])
+            .is_err());
+
+        m.ch_params[0].output_range = AnalogUIRange::mA4To20;
+        m.ch_params[3].output_range = AnalogUIRange::VMinus10To10;
+        assert_eq!(
+            m.process_output_values(&[Decimal32(12.0), None, Disabled, Decimal32(-10.0)])
+                .unwrap(),
+            vec![0x3600, 0, 0, 0x9400]
+        );
+
+        m.ch_params.pop();
+        assert!(m.process_output_values(&[]).is_err());
+    }
+
+    #[test]
+    fn test_parameters_from_raw_data() {
+        #[rustfmt::skip]
+        let data = vec![
+            1, 8, 0,      // CH 0
+            1, 0, 0x3600, // CH 1
+            0, 2, 0,      // CH 2
+            1, 5, 0xCA00, // CH 3
+        ];
+        let p = parameters_from_raw_data(12, &data).unwrap();
+        assert_eq!(p.len(), 4);
+        assert_eq!(p[0], ChannelParameters::default());
+        assert_eq!(p[1].substitute_value, 10.0);
+        assert_eq!(p[2].data_format, DataFormat::S5);
+        assert_eq!(p[2].output_range, AnalogUIRange::V0To10);
+        assert_eq!(p[3].substitute_value, -2.5);
+        assert!(parameters_from_raw_data(12, &data[..11]).is_err());
+    }
+
+    #[test]
+    fn test_diag_parameters_from_raw_data() {
+        #[rustfmt::skip]
+        let mut data = vec![
+            1, 8, 0,      0, // CH 0
+            1, 0, 0,      1, // CH 1
+            0, 2, 0,      0, // CH 2
+            1, 5, 0xCA00, 0, // CH 3
+        ];
+        let p = parameters_from_raw_data(16, &data).unwrap();
+        assert_eq!(p[0], ChannelParameters::default());
+        assert!(p[1].channel_diagnostics);
+        assert_eq!(p[3].output_range, AnalogUIRange::VMinus5To5);
+        assert_eq!(p[3].substitute_value, -2.5);
+
+        data[7] = 2;
+        assert!(parameters_from_raw_data(16, &data).is_err());
+        data[7] = 0;
+        data[4] = 2;
+        assert!(parameters_from_raw_data(16, &data).is_err());
+        assert!(parameters_from_raw_data(16, &[0; 15]).is_err());
+    }
+
+    #[test]
+    fn test_parameters_to_raw_data() {
+        let mut m = Mod::new(ModuleType::UR20_4AO_UI_16_M).unwrap();
+        m.ch_params[1].output_range = AnalogUIRange::VMinus5To5;
+        m.ch_params[1].substitute_value = -2.5;
+        let data = m.to_modbus_parameter_data();
+        assert_eq!(data.len(), 12);
+        assert_eq!(data[3..6], [1, 5, 0xCA00]);
+        let p = parameters_from_raw_data(12, &data).unwrap();
+        assert_eq!(p, m.ch_params);
+
+        m.module_type = ModuleType::UR20_4AO_UI_16_M_DIAG;
+        m.ch_params[3].channel_diagnostics = true;
+        let data = m.to_modbus_parameter_data();
+        assert_eq!(data.len(), 16);
+        assert_eq!(data[15], 1);
+        let p = parameters_from_raw_data(16, &data).unwrap();
+        assert_eq!(p, m.ch_params);
+    }
+
+    #[test]
+    fn substitute_values() {
+        let mut data = vec![0; 16];
+        data[1] = 8; // CH 0 disabled
+        data[4] = 1; // CH 1 S7
+        data[5] = 0; // CH 1 0 - 20 mA
+        data[6] = 0x3600;
+        let m = Mod::from_modbus_parameter_data(ModuleType::UR20_4AO_UI_16_DIAG_HD, &data).unwrap();
+        assert_eq!(
+            m.substitute_values(),
+            vec![Disabled, Decimal32(10.0), Decimal32(0.0), Decimal32(0.0)]
+        );
+    }
+
+    #[test]
+    fn create_module_from_modbus_parameter_data() {
+        assert!(Mod::from_modbus_parameter_data(ModuleType::UR20_4AO_UI_16_M, &[0; 12]).is_ok());
+        assert!(
+            Mod::from_modbus_parameter_data(ModuleType::UR20_4AO_UI_16_M_DIAG, &[0; 12]).is_err()
+        );
+        assert!(
+            Mod::from_modbus_parameter_data(ModuleType::UR20_4AO_UI_16_M_DIAG, &[0; 16]).is_ok()
+        );
+        assert!(Mod::from_modbus_parameter_data(ModuleType::UR20_4AI_UI_16, &[0; 16]).is_err());
+    }
+}
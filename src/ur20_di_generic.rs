@@ -0,0 +1,267 @@
+//! Digital input modules with one input bit per channel
+//!
+//! The modules only differ in their number of channels
+//! and parameters, so they share the same implementation.
+
+use super::util::{set_bit_16, test_bit_16};
+use super::*;
+use crate::ur20_fbc_mod_tcp::{ProcessDataLayout, ProcessModbusTcpData, ToModbusParameterData};
+use num_traits::cast::{FromPrimitive, ToPrimitive};
+
+#[rustfmt::skip]
+make_variants! {
+    UR20_4DI_P          => 4,
+    UR20_4DI_P_3W       => 4,
+    UR20_8DI_P_2W       => 8,
+    UR20_8DI_P_3W       => 8,
+    UR20_8DI_P_3W_HD    => 8,
+    UR20_16DI_P         => 16,
+    UR20_16DI_P_PLC_INT => 0,
+    UR20_4DI_N          => 4,
+    UR20_8DI_N_3W       => 8,
+    UR20_16DI_N         => 16,
+    UR20_16DI_N_PLC_INT => 0,
+}
+
+#[derive(Debug, Clone)]
+pub struct Mod {
+    pub module_type: ModuleType,
+    pub ch_params: Vec<ChannelParameters>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(default))]
+pub struct ChannelParameters {
+    pub input_delay: InputDelay,
+}
+
+impl Default for ChannelParameters {
+    fn default() -> Self {
+        ChannelParameters {
+            input_delay: InputDelay::ms3,
+        }
+    }
+}
+
+impl Mod {
+    /// Creates a module with default parameters.
+    pub fn new(module_type: ModuleType) -> Result<Mod> {
+        check_module_type(&module_type)?;
+        let ch_params = (0..module_type.channel_count())
+            .map(|_| ChannelParameters::default())
+            .collect();
+        Ok(Mod {
+            module_type,
+            ch_params,
+        })
+    }
+
+    /// Creates a module instance from its parameter register data.
+    ///
+    /// Modules without parameters (PLC interface variants)
+    /// get the default parameters.
+    pub fn from_modbus_parameter_data(module_type: ModuleType, data: &[u16]) -> Result<Mod> {
+        let param_cnt = check_module_type(&module_type)?;
+        if param_cnt == 0 {
+            return Mod::new(module_type);
+        }
+        let ch_params = parameters_from_raw_data(param_cnt, data)?;
+        Ok(Mod {
+            module_type,
+            ch_params,
+        })
+    }
+}
+
+impl Module for Mod {
+    fn module_type(&self) -> ModuleType {
+        self.module_type.clone()
+    }
+}
+
+impl ToModbusParameterData for Mod {
+    fn to_modbus_parameter_data(&self) -> Vec<u16> {
+        let param_cnt = param_register_count(&self.module_type).unwrap_or(0);
+        parameters_to_raw_data(param_cnt, &self.ch_params)
+    }
+}
+
+impl ProcessModbusTcpData for Mod {
+    fn process_input_byte_count(&self) -> usize {
+        self.module_type.channel_count().div_ceil(8)
+    }
+    fn process_output_byte_count(&self) -> usize {
+        0
+    }
+    fn layout(&self) -> ProcessDataLayout {
+        ProcessDataLayout {
+            input: util::bit_layout(self.module_type.channel_count()),
+            output: vec![],
+        }
+    }
+    fn process_input_data(&self, data: &[u16]) -> Result<Vec<ChannelValue>> {
+        if data.len() != 1 {
+            return Err(Error::BufferLength);
+        }
+        let bits = data[0];
+        let res = (0..self.module_type.channel_count())
+            .map(|i| ChannelValue::Bit(test_bit_16(bits, i)))
+            .collect();
+        Ok(res)
+    }
+    fn process_input_values(&self, values: &[ChannelValue]) -> Result<Vec<u16>> {
+        let values = util::output_values(self, values)?;
+        let mut res = 0;
+        for (i, v) in values.iter().enumerate() {
+            match *v {
+                ChannelValue::Bit(state) => {
+                    if state {
+                        res = set_bit_16(res, i);
+                    }
+                }
+                ChannelValue::Disabled | ChannelValue::None => {
+                    // do nothing
+                }
+                _ => {
+                    return Err(Error::ChannelValue);
+                }
+            }
+        }
+        Ok(vec![res])
+    }
+}
+
+/// Returns the number of parameter registers of a supported module type.
+fn check_module_type(module_type: &ModuleType) -> Result<usize> {
+    param_register_count(module_type).ok_or(Error::UnknownModule)
+}
+
+fn parameters_from_raw_data(channel_cnt: usize, data: &[u16]) -> Result<Vec<ChannelParameters>> {
+    if data.len() < channel_cnt {
+        return Err(Error::BufferLength);
+    }
+
+    let channel_parameters: Result<Vec<_>> = (0..channel_cnt)
+        .map(|i| {
+            let mut p = ChannelParameters::default();
+            p.input_delay = match FromPrimitive::from_u16(data[i]) {
+                Some(x) => x,
+                _ => {
+                    return Err(Error::ChannelParameter);
+                }
+            };
+            Ok(p)
+        })
+        .collect();
+    channel_parameters
+}
+
+fn parameters_to_raw_data(channel_cnt: usize, ch_params: &[ChannelParameters]) -> Vec<u16> {
+    ch_params
+        .iter()
+        .take(channel_cnt)
+        .map(|p| p.input_delay.to_u16().unwrap())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::ur20_fbc_mod_tcp::ModbusParameterRegisterCount;
+    use crate::ChannelValue::*;
+
+    #[test]
+    fn create_new_module() {
+        for t in MODULE_TYPES {
+            let m = Mod::new(t.clone()).unwrap();
+            assert_eq!(m.ch_params.len(), t.channel_count());
+        }
+        assert_eq!(
+            Mod::new(ModuleType::UR20_4DO_P).err(),
+            Some(Error::UnknownModule)
+        );
+    }
+
+    #[test]
+    fn param_register_count_of_coupler() {
+        for t in MODULE_TYPES {
+            assert_eq!(
+                t.param_register_count() as usize,
+                param_register_count(t).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn test_process_input_data() {
+        let m = Mod::new(ModuleType::UR20_4DI_N).unwrap();
+        assert_eq!(m.process_input_byte_count(), 1);
+        assert!(m.process_input_data(&[]).is_err());
+        assert_eq!(
+            m.process_input_data(&[0b0100]).unwrap(),
+            vec![Bit(false), Bit(false), Bit(true), Bit(false)]
+        );
+    }
+
+    #[test]
+    fn test_process_input_data_of_16_channels() {
+        let m = Mod::new(ModuleType::UR20_16DI_P).unwrap();
+        assert_eq!(m.process_input_byte_count(), 2);
+        let values = m.process_input_data(&[0x8101]).unwrap();
+        assert_eq!(values.len(), 16);
+        assert_eq!(values[0], Bit(true));
+        assert_eq!(values[1], Bit(false));
+        assert_eq!(values[8], Bit(true));
+        assert_eq!(values[15], Bit(true));
+    }
+
+    #[test]
+    fn test_process_output_values() {
+        let m = Mod::new(ModuleType::UR20_8DI_P_3W).unwrap();
+        assert_eq!(m.process_output_values(&[]).unwrap(), Vec::<u16>::new());
+        assert!(m.process_output_values(&[Bit(true)]).is_err());
+    }
+
+    #[test]
+    fn test_process_output_data() {
+        let m = Mod::new(ModuleType::UR20_4DI_P).unwrap();
+        assert!(m.process_output_data(&[0; 4]).is_err());
+        assert_eq!(
+            m.process_output_data(&[]).unwrap(),
+            vec![ChannelValue::None; 4]
+        );
+    }
+
+    #[test]
+    fn test_parameters_from_raw_data() {
+        let p = parameters_from_raw_data(8, &[0, 1, 2, 3, 4, 5, 0, 0]).unwrap();
+        assert_eq!(p.len(), 8);
+        assert_eq!(p[0].input_delay, InputDelay::no);
+        assert_eq!(p[5].input_delay, InputDelay::ms40);
+        assert!(parameters_from_raw_data(8, &[0; 7]).is_err());
+        assert!(parameters_from_raw_data(4, &[0, 6, 0, 0]).is_err());
+    }
+
+    #[test]
+    fn test_parameters_to_raw_data() {
+        let mut m = Mod::new(ModuleType::UR20_4DI_P_3W).unwrap();
+        m.ch_params[1].input_delay = InputDelay::ms40;
+        let data = m.to_modbus_parameter_data();
+        assert_eq!(data, vec![2, 5, 2, 2]);
+        let m = Mod::from_modbus_parameter_data(ModuleType::UR20_4DI_P_3W, &data).unwrap();
+        assert_eq!(m.ch_params[1].input_delay, InputDelay::ms40);
+        let m = Mod::new(ModuleType::UR20_16DI_N_PLC_INT).unwrap();
+        assert!(m.to_modbus_parameter_data().is_empty());
+    }
+
+    #[test]
+    fn create_module_from_modbus_parameter_data() {
+        let m = Mod::from_modbus_parameter_data(ModuleType::UR20_16DI_N, &[3; 16]).unwrap();
+        assert_eq!(m.module_type(), ModuleType::UR20_16DI_N);
+        assert_eq!(m.ch_params[15].input_delay, InputDelay::ms10);
+        let m = Mod::from_modbus_parameter_data(ModuleType::UR20_16DI_P_PLC_INT, &[]).unwrap();
+        assert_eq!(m.ch_params.len(), 16);
+        assert!(Mod::from_modbus_parameter_data(ModuleType::UR20_4DO_P, &[0; 4]).is_err());
+    }
+}
@@ -0,0 +1,301 @@
+//! Timestamped digital input modules UR20-2DI-P-TS and UR20-4DI-P-TS
+//!
+//! Besides the current input states the modules deliver
+//! a list of records with the time of each detected edge.
+//!
+//! Layout of the process input data:
+//!
+//! | Register     | Content                                             |
+//! |--------------|-----------------------------------------------------|
+//! | 0            | input states (low byte), number of records (high byte) |
+//! | 1 + 4 * n    | sequence number of record `n`                        |
+//! | 2 + 4 * n    | channel (bit 0 - 3), rising edge (bit 8)             |
+//! | 3 + 4 * n    | timestamp in µs (high word)                          |
+//! | 4 + 4 * n    | timestamp in µs (low word)                           |
+
+use super::*;
+use crate::ur20_fbc_mod_tcp::{ProcessDataLayout, ProcessModbusTcpData, ToModbusParameterData};
+use crate::util::test_bit_16;
+use num_traits::cast::{FromPrimitive, ToPrimitive};
+use std::time::Duration;
+
+/// Maximum number of edge records within the process data.
+pub const RECORD_COUNT: usize = 4;
+
+/// Number of registers of an edge record.
+const RECORD_REGISTER_COUNT: usize = 4;
+
+#[derive(Debug, Clone)]
+pub struct Mod {
+    pub module_type: ModuleType,
+    pub ch_params: Vec<ChannelParameters>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ChannelParameters {
+    pub input_delay: InputDelay,
+}
+
+/// A detected edge of an input signal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Edge {
+    /// Sequence number of the record (wraps around).
+    pub sequence: u16,
+    /// `true` for a rising edge, `false` for a falling edge.
+    pub rising: bool,
+    /// Time of the edge (module clock, wraps around).
+    pub timestamp: Duration,
+}
+
+/// The input data of a channel.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ProcessInput {
+    /// Current state of the input.
+    pub state: bool,
+    /// Edges that were reported within this process image
+    /// (in the order of their sequence numbers).
+    pub edges: Vec<Edge>,
+}
+
+impl Default for ChannelParameters {
+    fn default() -> Self {
+        ChannelParameters {
+            input_delay: InputDelay::ms3,
+        }
+    }
+}
+
+impl From<ProcessInput> for ChannelValue {
+    fn from(i: ProcessInput) -> Self {
+        ChannelValue::TsIn(i)
+    }
+}
+
+impl Mod {
+    /// Creates a module with default parameters.
+    pub fn new(module_type: ModuleType) -> Result<Mod> {
+        check_module_type(&module_type)?;
+        let ch_params = (0..module_type.channel_count())
+            .map(|_| ChannelParameters::default())
+            .collect();
+        Ok(Mod {
+            module_type,
+            ch_params,
+        })
+    }
+
+    /// Creates a module instance from its parameter register data.
+    pub fn from_modbus_parameter_data(module_type: ModuleType, data: &[u16]) -> Result<Mod> {
+        check_module_type(&module_type)?;
+        let ch_params = parameters_from_raw_data(module_type.channel_count(), data)?;
+        Ok(Mod {
+            module_type,
+            ch_params,
+        })
+    }
+}
+
+impl Module for Mod {
+    fn module_type(&self) -> ModuleType {
+        self.module_type.clone()
+    }
+}
+
+impl ToModbusParameterData for Mod {
+    fn to_modbus_parameter_data(&self) -> Vec<u16> {
+        parameters_to_raw_data(&self.ch_params)
+    }
+}
+
+impl ProcessModbusTcpData for Mod {
+    fn process_input_byte_count(&self) -> usize {
+        (1 + RECORD_COUNT * RECORD_REGISTER_COUNT) * 2
+    }
+    fn process_output_byte_count(&self) -> usize {
+        0
+    }
+    fn layout(&self) -> ProcessDataLayout {
+        let mut input = vec!["input states, number of records".to_string()];
+        for n in 0..RECORD_COUNT {
+            input.push(format!("record {} sequence number", n));
+            input.push(format!("record {} channel, edge", n));
+            input.push(format!("record {} timestamp (high word)", n));
+            input.push(format!("record {} timestamp (low word)", n));
+        }
+        ProcessDataLayout {
+            input,
+            output: vec![],
+        }
+    }
+    fn process_input_data(&self, data: &[u16]) -> Result<Vec<ChannelValue>> {
+        if data.len() * 2 != self.process_input_byte_count() {
+            return Err(Error::BufferLength);
+        }
+        let channel_cnt = self.module_type.channel_count();
+        let mut inputs: Vec<_> = (0..channel_cnt)
+            .map(|i| ProcessInput {
+                state: test_bit_16(data[0], i),
+                edges: vec![],
+            })
+            .collect();
+        let record_cnt = (data[0] >> 8) as usize;
+        if record_cnt > RECORD_COUNT {
+            return Err(Error::ChannelValue);
+        }
+        for r in data[1..].chunks(RECORD_REGISTER_COUNT).take(record_cnt) {
+            let channel = (r[1] & 0x0F) as usize;
+            let edge = Edge {
+                sequence: r[0],
+                rising: test_bit_16(r[1], 8),
+                timestamp: Duration::from_micros(u64::from(r[2]) << 16 | u64::from(r[3])),
+            };
+            match inputs.get_mut(channel) {
+                Some(i) => i.edges.push(edge),
+                None => return Err(Error::ChannelValue),
+            }
+        }
+        Ok(inputs.into_iter().map(ChannelValue::from).collect())
+    }
+}
+
+fn check_module_type(module_type: &ModuleType) -> Result<()> {
+    match *module_type {
+        ModuleType::UR20_2DI_P_TS | ModuleType::UR20_4DI_P_TS => Ok(()),
+        _ => Err(Error::UnknownModule),
+    }
+}
+
+fn parameters_from_raw_data(channel_cnt: usize, data: &[u16]) -> Result<Vec<ChannelParameters>> {
+    if data.len() < channel_cnt {
+        return Err(Error::BufferLength);
+    }
+
+    let channel_parameters: Result<Vec<_>> = (0..channel_cnt)
+        .map(|i| {
+            let mut p = ChannelParameters::default();
+            p.input_delay = match FromPrimitive::from_u16(data[i]) {
+                Some(x) => x,
+                _ => {
+                    return Err(Error::ChannelParameter);
+                }
+            };
+            Ok(p)
+        })
+        .collect();
+    channel_parameters
+}
+
+fn parameters_to_raw_data(ch_params: &[ChannelParameters]) -> Vec<u16> {
+    ch_params
+        .iter()
+        .map(|p| p.input_delay.to_u16().unwrap())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn record(sequence: u16, channel: u16, rising: bool, micros: u32) -> Vec<u16> {
+        let edge = if rising { 0x0100 } else { 0 };
+        vec![
+            sequence,
+            channel | edge,
+            (micros >> 16) as u16,
+            micros as u16,
+        ]
+    }
+
+    fn input(states: u16, records: &[Vec<u16>]) -> Vec<u16> {
+        let mut data = vec![states | (records.len() as u16) << 8];
+        for r in records {
+            data.extend(r);
+        }
+        data.resize(17, 0);
+        data
+    }
+
+    #[test]
+    fn create_new_module() {
+        assert_eq!(
+            Mod::new(ModuleType::UR20_2DI_P_TS).unwrap().ch_params.len(),
+            2
+        );
+        assert_eq!(
+            Mod::new(ModuleType::UR20_4DI_P_TS).unwrap().ch_params.len(),
+            4
+        );
+        assert_eq!(
+            Mod::new(ModuleType::UR20_4DI_P).err(),
+            Some(Error::UnknownModule)
+        );
+    }
+
+    #[test]
+    fn test_process_input_data() {
+        let m = Mod::new(ModuleType::UR20_4DI_P_TS).unwrap();
+        assert!(m.process_input_data(&[0; 16]).is_err());
+        let data = input(
+            0b0010,
+            &[record(7, 1, true, 70_000), record(8, 3, false, 0x0001_0000)],
+        );
+        let values = m.process_input_data(&data).unwrap();
+        assert_eq!(values.len(), 4);
+        assert_eq!(
+            values[1],
+            ChannelValue::TsIn(ProcessInput {
+                state: true,
+                edges: vec![Edge {
+                    sequence: 7,
+                    rising: true,
+                    timestamp: Duration::from_millis(70),
+                }],
+            })
+        );
+        assert_eq!(values[0], ChannelValue::TsIn(ProcessInput::default()));
+        match values[3] {
+            ChannelValue::TsIn(ref v) => {
+                assert!(!v.state);
+                assert_eq!(v.edges[0].timestamp, Duration::from_micros(65_536));
+                assert!(!v.edges[0].rising);
+            }
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn ignore_unused_records() {
+        let m = Mod::new(ModuleType::UR20_2DI_P_TS).unwrap();
+        let mut data = input(0, &[]);
+        data[2] = 1;
+        let values = m.process_input_data(&data).unwrap();
+        assert_eq!(values[1], ChannelValue::TsIn(ProcessInput::default()));
+    }
+
+    #[test]
+    fn test_process_input_data_with_invalid_records() {
+        let m = Mod::new(ModuleType::UR20_2DI_P_TS).unwrap();
+        let data = input(0, &[record(1, 2, true, 0)]);
+        assert_eq!(m.process_input_data(&data).err(), Some(Error::ChannelValue));
+        let mut data = input(0, &[]);
+        data[0] = 5 << 8;
+        assert_eq!(m.process_input_data(&data).err(), Some(Error::ChannelValue));
+    }
+
+    #[test]
+    fn test_parameters_from_raw_data() {
+        let p = parameters_from_raw_data(2, &[0, 4]).unwrap();
+        assert_eq!(p[0].input_delay, InputDelay::no);
+        assert_eq!(p[1].input_delay, InputDelay::ms20);
+        assert!(parameters_from_raw_data(2, &[0]).is_err());
+        assert!(parameters_from_raw_data(2, &[0, 6]).is_err());
+    }
+
+    #[test]
+    fn test_parameters_to_raw_data() {
+        let mut m = Mod::new(ModuleType::UR20_2DI_P_TS).unwrap();
+        m.ch_params[0].input_delay = InputDelay::us300;
+        assert_eq!(m.to_modbus_parameter_data(), vec![1, 2]);
+    }
+}
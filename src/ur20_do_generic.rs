@@ -0,0 +1,273 @@
+//! Digital output modules with one output bit per channel
+//!
+//! The modules only differ in their number of channels
+//! and parameters, so they share the same implementation.
+
+use super::*;
+use crate::ur20_fbc_mod_tcp::{ProcessDataLayout, ProcessModbusTcpData, ToModbusParameterData};
+use crate::util::*;
+
+#[rustfmt::skip]
+make_variants! {
+    UR20_4DO_P          => 4,
+    UR20_4DO_P_2A       => 4,
+    UR20_4DO_PN_2A      => 4,
+    UR20_8DO_P          => 8,
+    UR20_8DO_P_2W_HD    => 8,
+    UR20_16DO_P         => 0,
+    UR20_16DO_P_PLC_INT => 0,
+    UR20_4DO_N          => 4,
+    UR20_4DO_N_2A       => 4,
+    UR20_8DO_N          => 8,
+    UR20_16DO_N         => 0,
+    UR20_16DO_N_PLC_INT => 0,
+}
+
+#[derive(Debug, Clone)]
+pub struct Mod {
+    pub module_type: ModuleType,
+    /// Channel parameters (empty if the module has no parameters).
+    pub ch_params: Vec<ChannelParameters>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(default))]
+pub struct ChannelParameters {
+    pub substitute_value: bool,
+}
+
+impl Mod {
+    /// Creates a module with default parameters.
+    pub fn new(module_type: ModuleType) -> Result<Mod> {
+        let param_cnt = check_module_type(&module_type)?;
+        let ch_params = (0..param_cnt)
+            .map(|_| ChannelParameters::default())
+            .collect();
+        Ok(Mod {
+            module_type,
+            ch_params,
+        })
+    }
+
+    /// Creates a module instance from its parameter register data.
+    ///
+    /// Modules without parameters don't accept any data.
+    pub fn from_modbus_parameter_data(module_type: ModuleType, data: &[u16]) -> Result<Mod> {
+        let param_cnt = check_module_type(&module_type)?;
+        if param_cnt == 0 && !data.is_empty() {
+            return Err(Error::BufferLength);
+        }
+        let ch_params = parameters_from_raw_data(param_cnt, data)?;
+        Ok(Mod {
+            module_type,
+            ch_params,
+        })
+    }
+}
+
+impl Module for Mod {
+    fn module_type(&self) -> ModuleType {
+        self.module_type.clone()
+    }
+}
+
+impl ToModbusParameterData for Mod {
+    fn to_modbus_parameter_data(&self) -> Vec<u16> {
+        parameters_to_raw_data(&self.ch_params)
+    }
+}
+
+impl ProcessModbusTcpData for Mod {
+    fn process_input_byte_count(&self) -> usize {
+        0
+    }
+    fn process_output_byte_count(&self) -> usize {
+        self.module_type.channel_count().div_ceil(8)
+    }
+    fn layout(&self) -> ProcessDataLayout {
+        ProcessDataLayout {
+            input: vec![],
+            output: util::bit_layout(self.module_type.channel_count()),
+        }
+    }
+    fn process_output_data(&self, data: &[u16]) -> Result<Vec<ChannelValue>> {
+        if data.len() != 1 {
+            return Err(Error::BufferLength);
+        }
+        Ok((0..self.module_type.channel_count())
+            .map(|i| test_bit_16(data[0], i))
+            .map(ChannelValue::Bit)
+            .collect())
+    }
+    fn process_output_values(&self, values: &[ChannelValue]) -> Result<Vec<u16>> {
+        let values = util::output_values(self, values)?;
+        let mut res = 0;
+        for (i, v) in values.iter().enumerate() {
+            match *v {
+                ChannelValue::Bit(state) => {
+                    if state {
+                        res = set_bit_16(res, i);
+                    }
+                }
+                ChannelValue::Disabled | ChannelValue::None => {
+                    // do nothing
+                }
+                _ => {
+                    return Err(Error::ChannelValue);
+                }
+            }
+        }
+        Ok(vec![res])
+    }
+}
+
+/// Returns the number of parameter registers of a supported module type.
+fn check_module_type(module_type: &ModuleType) -> Result<usize> {
+    param_register_count(module_type).ok_or(Error::UnknownModule)
+}
+
+fn parameters_from_raw_data(channel_cnt: usize, data: &[u16]) -> Result<Vec<ChannelParameters>> {
+    if data.len() < channel_cnt {
+        return Err(Error::BufferLength);
+    }
+
+    let channel_parameters: Result<Vec<_>> = (0..channel_cnt)
+        .map(|i| {
+            let mut p = ChannelParameters::default();
+            p.substitute_value = match data[i] {
+                0 => false,
+                1 => true,
+                _ => {
+                    return Err(Error::ChannelParameter);
+                }
+            };
+            Ok(p)
+        })
+        .collect();
+    channel_parameters
+}
+
+fn parameters_to_raw_data(ch_params: &[ChannelParameters]) -> Vec<u16> {
+    ch_params
+        .iter()
+        .map(|p| u16::from(p.substitute_value))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::ur20_fbc_mod_tcp::ModbusParameterRegisterCount;
+    use crate::ChannelValue::*;
+
+    #[test]
+    fn create_new_module() {
+        for t in MODULE_TYPES {
+            assert!(Mod::new(t.clone()).is_ok());
+        }
+        assert_eq!(Mod::new(ModuleType::UR20_8DO_N).unwrap().ch_params.len(), 8);
+        assert!(Mod::new(ModuleType::UR20_16DO_N)
+            .unwrap()
+            .ch_params
+            .is_empty());
+        assert_eq!(
+            Mod::new(ModuleType::UR20_4DI_P).err(),
+            Some(Error::UnknownModule)
+        );
+    }
+
+    #[test]
+    fn param_register_count_of_coupler() {
+        for t in MODULE_TYPES {
+            assert_eq!(
+                t.param_register_count() as usize,
+                param_register_count(t).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn test_process_output_values() {
+        let m = Mod::new(ModuleType::UR20_4DO_N_2A).unwrap();
+        assert_eq!(
+            m.process_output_values(&[Bit(true), Bit(false), None, Bit(true)])
+                .unwrap(),
+            vec![0b1001]
+        );
+        assert_eq!(
+            m.process_output_values(&[Disabled, Bit(true), Disabled, None])
+                .unwrap(),
+            vec![0b0010]
+        );
+        assert_eq!(m.process_output_values(&[]).unwrap(), vec![0]);
+        assert!(m
+            .process_output_values(&[Bit(true), Bit(false), Bit(true)])
+            .is_err());
+        assert!(m
+            .process_output_values(&[Bit(false), Bit(true), Bit(false), Decimal32(0.0)])
+            .is_err());
+    }
+
+    #[test]
+    fn test_process_output_values_of_16_channels() {
+        let m = Mod::new(ModuleType::UR20_16DO_N_PLC_INT).unwrap();
+        assert_eq!(m.process_output_byte_count(), 2);
+        let mut values = vec![Bit(false); 16];
+        values[0] = Bit(true);
+        values[15] = Bit(true);
+        assert_eq!(m.process_output_values(&values).unwrap(), vec![0x8001]);
+    }
+
+    #[test]
+    fn test_process_output_data() {
+        let m = Mod::new(ModuleType::UR20_8DO_P_2W_HD).unwrap();
+        assert_eq!(m.process_output_byte_count(), 1);
+        assert!(m.process_output_data(&[0; 2]).is_err());
+        let values = m.process_output_data(&[0b1000_0010]).unwrap();
+        assert_eq!(values.len(), 8);
+        assert_eq!(values[1], Bit(true));
+        assert_eq!(values[7], Bit(true));
+    }
+
+    #[test]
+    fn test_process_output_data_of_16_channels() {
+        let m = Mod::new(ModuleType::UR20_16DO_N).unwrap();
+        let values = m.process_output_data(&[0xC000]).unwrap();
+        assert_eq!(values.len(), 16);
+        assert_eq!(values[13], Bit(false));
+        assert_eq!(values[14], Bit(true));
+        assert_eq!(values[15], Bit(true));
+    }
+
+    #[test]
+    fn test_parameters_from_raw_data() {
+        let p = parameters_from_raw_data(4, &[0, 1, 0, 1]).unwrap();
+        assert!(!p[0].substitute_value);
+        assert!(p[3].substitute_value);
+        assert!(parameters_from_raw_data(4, &[0; 3]).is_err());
+        assert!(parameters_from_raw_data(4, &[0, 2, 0, 0]).is_err());
+        assert!(parameters_from_raw_data(0, &[]).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_parameters_to_raw_data() {
+        let mut m = Mod::new(ModuleType::UR20_8DO_P).unwrap();
+        m.ch_params[2].substitute_value = true;
+        let data = m.to_modbus_parameter_data();
+        assert_eq!(data, vec![0, 0, 1, 0, 0, 0, 0, 0]);
+        let m = Mod::from_modbus_parameter_data(ModuleType::UR20_8DO_P, &data).unwrap();
+        assert!(m.ch_params[2].substitute_value);
+        let m = Mod::new(ModuleType::UR20_16DO_P).unwrap();
+        assert!(m.to_modbus_parameter_data().is_empty());
+    }
+
+    #[test]
+    fn create_module_from_modbus_parameter_data() {
+        let m = Mod::from_modbus_parameter_data(ModuleType::UR20_4DO_PN_2A, &[1, 0, 0, 1]).unwrap();
+        assert_eq!(m.module_type(), ModuleType::UR20_4DO_PN_2A);
+        assert!(m.ch_params[0].substitute_value);
+        assert!(Mod::from_modbus_parameter_data(ModuleType::UR20_16DO_P_PLC_INT, &[]).is_ok());
+        assert!(Mod::from_modbus_parameter_data(ModuleType::UR20_4DI_P, &[0; 4]).is_err());
+    }
+}
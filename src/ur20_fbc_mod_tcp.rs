@@ -1,29 +1,60 @@
 //! Modbus TCP fieldbus coupler UR20-FBC-MOD-TCP
 
 use super::*;
-use crate::util::*;
+use crate::{
+    bitfield::BitGroup,
+    calibration::{Calibration, ChannelCalibration},
+    codec::AnalogScaling,
+    diagnosis::{
+        DiagnosisEvent, DiagnosisThrottle, Diagnostics, ModuleDiagnosis, DIAGNOSIS_BYTE_COUNT,
+    },
+    heartbeat::Heartbeat,
+    image::{diff_words, ChannelDiff, ImageArea, ProcessImage},
+    station::{ChannelDescription, Expression, StationDescription},
+    task::{run_tasks, CyclicTask, ScanIo},
+    ur20_1com_232_485_422::{Clock, SystemClock},
+    util::*,
+    voting::{VotingGroup, VotingResult},
+};
 use std::{
-    collections::HashMap,
-    io::{Read, Write},
+    cmp,
+    collections::{HashMap, HashSet},
+    io::{self, Read, Write},
+    mem,
+    ops::Range,
+    sync::{Arc, RwLock},
+    time::{Duration, Instant},
 };
 
 type Word = u16;
 type RegisterAddress = u16;
 type BitAddress = u16;
 type BitNr = usize;
+type ModuleValues = Vec<Vec<ChannelValue>>;
+
+/// Number of registers of the coupler parameters.
+pub const COUPLER_PARAMETER_REGISTER_COUNT: usize = 4;
+
+// The register map has no area for the event log of the coupler,
+// so device-side events are only available as module diagnosis
+// (see `Coupler::update_diagnostics`).
+
+pub const ADDR_PACKED_PROCESS_INPUT_DATA: RegisterAddress = 0x0000;
+pub const ADDR_PACKED_PROCESS_OUTPUT_DATA: RegisterAddress = 0x0800;
+pub const ADDR_COMPACT_DIGITAL_INPUT_DATA: RegisterAddress = 0x0400;
+pub const ADDR_COMPACT_DIGITAL_OUTPUT_DATA: RegisterAddress = 0x0C00;
+pub const ADDR_PROCESS_OUTPUT_LEN: RegisterAddress = 0x1010;
+pub const ADDR_PROCESS_INPUT_LEN: RegisterAddress = 0x1011;
+pub const ADDR_COUPLER_ID: RegisterAddress = 0x1000;
+pub const ADDR_COUPLER_STATUS: RegisterAddress = 0x100C;
+pub const ADDR_COUPLER_PARAMETERS: RegisterAddress = 0x1100;
+pub const ADDR_MODULE_DIAGNOSIS: RegisterAddress = 0x2400;
+pub const ADDR_CURRENT_MODULE_COUNT: RegisterAddress = 0x27FE;
+pub const ADDR_CURRENT_MODULE_LIST: RegisterAddress = 0x2A00;
+pub const ADDR_MODULE_OFFSETS: RegisterAddress = 0x2B00;
+pub const ADDR_MODULE_PARAMETERS: RegisterAddress = 0xC000;
 
-pub const ADDR_PACKED_PROCESS_INPUT_DATA  : RegisterAddress = 0x0000;
-pub const ADDR_PACKED_PROCESS_OUTPUT_DATA : RegisterAddress = 0x0800;
-pub const ADDR_PROCESS_OUTPUT_LEN         : RegisterAddress = 0x1010;
-pub const ADDR_PROCESS_INPUT_LEN          : RegisterAddress = 0x1011;
-pub const ADDR_COUPLER_ID                 : RegisterAddress = 0x1000;
-pub const ADDR_COUPLER_STATUS             : RegisterAddress = 0x100C;
-pub const ADDR_CURRENT_MODULE_COUNT       : RegisterAddress = 0x27FE;
-pub const ADDR_CURRENT_MODULE_LIST        : RegisterAddress = 0x2A00;
-pub const ADDR_MODULE_OFFSETS             : RegisterAddress = 0x2B00;
-pub const ADDR_MODULE_PARAMETERS          : RegisterAddress = 0xC000;
-
-pub trait ProcessModbusTcpData: Module + Send {
+pub trait ProcessModbusTcpData: Module + ToModbusParameterData + Send {
     /// Number of bytes within the process input data buffer.
     fn process_input_byte_count(&self) -> usize;
     /// Number of bytes within the process output data buffer.
@@ -45,12 +76,80 @@ pub trait ProcessModbusTcpData: Module + Send {
         Ok(vec![ChannelValue::None; channel_cnt])
     }
     /// Transform channel values into raw module output data.
+    ///
+    /// All modules follow the same contract:
+    ///
+    /// - `values` contains exactly one value per channel.
+    ///   An empty slice is the same as `ChannelValue::None` for each channel.
+    ///   Any other length results in `Error::ChannelValue`.
+    /// - `ChannelValue::None` and `ChannelValue::Disabled` are accepted
+    ///   by every channel and are encoded as zero (e.g. the output is off).
+    /// - Any other value that does not fit the channel results in
+    ///   `Error::ChannelValue`.
+    ///
+    /// Modules without output data return an empty buffer.
     fn process_output_values(&self, values: &[ChannelValue]) -> Result<Vec<u16>> {
-        if !values.is_empty() && values.len() != self.module_type().channel_count() {
+        util::output_values(self, values)?;
+        Ok(vec![])
+    }
+    /// Transform channel values into raw module input data.
+    ///
+    /// This is the inverse of `process_input_data` and is used to
+    /// simulate a module (see `simulator::ProcessImageBuilder`).
+    /// The contract of `values` is the same as for `process_output_values`.
+    ///
+    /// The default implementation only supports modules without input data.
+    fn process_input_values(&self, values: &[ChannelValue]) -> Result<Vec<u16>> {
+        util::output_values(self, values)?;
+        if self.process_input_byte_count() > 0 {
             return Err(Error::ChannelValue);
         }
         Ok(vec![])
     }
+    /// Describe the meaning of the registers within the process data.
+    fn layout(&self) -> ProcessDataLayout {
+        let words = |bytes: usize| {
+            (0..bytes.div_ceil(2))
+                .map(|i| format!("word {}", i))
+                .collect()
+        };
+        ProcessDataLayout {
+            input: words(self.process_input_byte_count()),
+            output: words(self.process_output_byte_count()),
+        }
+    }
+    /// Byte ranges of the process input data that contain user data
+    /// (e.g. the payload of a communication module).
+    ///
+    /// These bytes are cleared by `Reproduction::anonymize`.
+    fn process_input_payload(&self) -> Vec<Range<usize>> {
+        vec![]
+    }
+    /// Byte ranges of the process output data that contain user data.
+    fn process_output_payload(&self) -> Vec<Range<usize>> {
+        vec![]
+    }
+    /// Channel specific diagnosis decoding (see `Coupler::update_diagnostics`).
+    fn diagnostics(&self) -> Option<&dyn Diagnostics> {
+        None
+    }
+    /// Scaling of the analog channels with a data format parameter
+    /// (see `Coupler::set_data_format_override`).
+    ///
+    /// An empty list means that no channel has a data format.
+    fn analog_scalings(&self) -> Vec<Option<AnalogScaling>> {
+        vec![]
+    }
+}
+
+/// The meaning of each register within the process data of a module.
+///
+/// The index of an entry is the register index relative to
+/// the first register of the module data.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ProcessDataLayout {
+    pub input: Vec<String>,
+    pub output: Vec<String>,
 }
 
 pub trait FromModbusParameterData {
@@ -60,6 +159,15 @@ pub trait FromModbusParameterData {
         Self: Sized + ProcessModbusTcpData;
 }
 
+pub trait ToModbusParameterData {
+    /// Create the parameter register data of the module instance.
+    ///
+    /// This is the counterpart of `FromModbusParameterData`:
+    /// the data can be written to `ADDR_MODULE_PARAMETERS`
+    /// to configure the module.
+    fn to_modbus_parameter_data(&self) -> Vec<u16>;
+}
+
 /// The packed process data offset addresses of a module.
 #[derive(Debug, Clone, PartialEq)]
 pub struct ModuleOffset {
@@ -67,13 +175,262 @@ pub struct ModuleOffset {
     pub output: Option<BitAddress>,
 }
 
+/// The decision of an `OutputGuard`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GuardDecision {
+    /// The intended output values may be written.
+    Accept,
+    /// The guard changed some of the intended output values.
+    Modified(String),
+    /// None of the intended output changes may be written.
+    Veto(String),
+}
+
+/// An interlock that checks the intended output values
+/// before they are encoded.
+pub trait OutputGuard: Debug + Send {
+    /// Checks (and maybe modifies) the intended output values of all modules.
+    fn check(&mut self, outputs: &mut [Vec<ChannelValue>]) -> GuardDecision;
+}
+
+/// A parameter block that has to be written to the coupler.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParameterDownload {
+    /// Module position (beginning at `0`)
+    pub module: usize,
+    /// First register of the parameter block.
+    pub address: RegisterAddress,
+    /// Register content.
+    pub data: Vec<u16>,
+}
+
+/// The change of an input channel between two cycles.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ChannelEvent {
+    /// A digital input was switched on.
+    Rising(Address),
+    /// A digital input was switched off.
+    Falling(Address),
+    /// Any other change of an input value.
+    ValueChanged {
+        address: Address,
+        old: ChannelValue,
+        new: ChannelValue,
+    },
+}
+
+/// Something noteworthy that happened during a cycle.
+#[derive(Debug, Clone, PartialEq)]
+pub enum CouplerEvent {
+    /// The process image is shorter than the configuration expects.
+    ImageTruncated {
+        /// `true` for the input image, `false` for the output image.
+        input: bool,
+        /// Expected number of registers.
+        expected: usize,
+        /// Actual number of registers.
+        actual: usize,
+        /// Modules whose data is not (completely) part of the image.
+        modules: Vec<usize>,
+    },
+    /// The time between two cycles exceeded the maximum cycle time
+    /// of the heartbeat.
+    HeartbeatStalled(Duration),
+    /// The output image read back from the coupler differs from the
+    /// image that was commanded in the previous cycle
+    /// (e.g. another Modbus master writes to the same coupler).
+    OutputMismatch {
+        /// Modules with different output values.
+        modules: Vec<usize>,
+    },
+    /// The ID that the coupler reported for a module only matched
+    /// the known ID of its type after ignoring the revision bits.
+    InexactModuleId(usize),
+    /// The parameters of a replaced module have to be downloaded again
+    /// (see `Coupler::reparameterize`).
+    ReparameterizationRequired(usize),
+    /// The output guard modified the intended output values.
+    OutputsModified(String),
+    /// The output guard rejected the intended output changes.
+    OutputsVetoed(String),
+    /// The diagnosis of a module changed
+    /// (see `Coupler::set_diagnosis_event_interval`).
+    Diagnosis(DiagnosisEvent),
+    /// An input value changed (see `Coupler::set_input_events`).
+    Input(ChannelEvent),
+}
+
+/// An output channel whose process output data differs
+/// from the value that was commanded in the previous cycle
+/// (see `Coupler::set_output_verification`).
+#[derive(Debug, Clone, PartialEq)]
+pub struct OutputMismatch {
+    pub address: Address,
+    pub commanded: ChannelValue,
+    pub actual: ChannelValue,
+}
+
+/// Processing time of a module (see `Coupler::set_timing`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ModuleTiming {
+    pub module: usize,
+    pub module_type: ModuleType,
+    /// Number of measured cycles.
+    pub cycles: u64,
+    /// Time to decode the input and output data in the last cycle.
+    pub decode: Duration,
+    /// Time to encode the output data in the last cycle.
+    pub encode: Duration,
+    /// Longest processing time (decoding and encoding) of a cycle.
+    pub max: Duration,
+    /// Processing time of all measured cycles.
+    pub total: Duration,
+}
+
+impl ModuleTiming {
+    fn new(module: usize, module_type: ModuleType) -> Self {
+        ModuleTiming {
+            module,
+            module_type,
+            cycles: 0,
+            decode: Duration::default(),
+            encode: Duration::default(),
+            max: Duration::default(),
+            total: Duration::default(),
+        }
+    }
+
+    fn record(&mut self, decode: Duration, encode: Duration) {
+        self.cycles += 1;
+        self.decode = decode;
+        self.encode = encode;
+        self.max = cmp::max(self.max, decode + encode);
+        self.total += decode + encode;
+    }
+
+    /// Average processing time of a cycle.
+    pub fn mean(&self) -> Duration {
+        match self.cycles {
+            0 => Duration::default(),
+            n => self.total / n as u32,
+        }
+    }
+}
+
+/// The start-up state of a coupler.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InitState {
+    /// No cycle was processed yet.
+    Uninitialized,
+    /// The outputs are held for the given number of cycles
+    /// (see `Coupler::set_startup_hold_off`).
+    HoldOff { remaining: usize },
+    /// The outputs are controlled by the application.
+    Ready,
+}
+
+/// A summary of the station state for supervisory systems.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StationHealth {
+    /// All modules deliver good values and report no errors.
+    Ok,
+    /// The station is running but some values are not reliable.
+    Degraded {
+        /// One line per problem.
+        details: Vec<String>,
+    },
+    /// The station does not deliver any values.
+    Failed { reason: String },
+}
+
+/// The decoded register content of `ADDR_COUPLER_STATUS`.
+///
+/// | Bit | Meaning                                         |
+/// |-----|-------------------------------------------------|
+/// | 0   | at least one module reports a diagnosis         |
+/// | 1   | undervoltage of the system supply               |
+/// | 2   | undervoltage of the input supply                |
+/// | 8   | the module list differs from the configuration  |
+/// | 9   | module bus error                                |
+/// | 10  | force mode is active                            |
+/// | 11  | fieldbus error (e.g. a watchdog timeout)        |
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CouplerStatus {
+    pub module_diagnosis: bool,
+    pub system_supply_undervoltage: bool,
+    pub input_supply_undervoltage: bool,
+    pub configuration_error: bool,
+    pub module_bus_error: bool,
+    pub force_mode: bool,
+    pub fieldbus_error: bool,
+}
+
+impl CouplerStatus {
+    /// Decodes the register content of `ADDR_COUPLER_STATUS`.
+    pub fn from_register(value: u16) -> Self {
+        CouplerStatus {
+            module_diagnosis: util::test_bit_16(value, 0),
+            system_supply_undervoltage: util::test_bit_16(value, 1),
+            input_supply_undervoltage: util::test_bit_16(value, 2),
+            configuration_error: util::test_bit_16(value, 8),
+            module_bus_error: util::test_bit_16(value, 9),
+            force_mode: util::test_bit_16(value, 10),
+            fieldbus_error: util::test_bit_16(value, 11),
+        }
+    }
+}
+
+/// An immutable copy of the coupler state.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Snapshot {
+    pub inputs: Vec<Vec<ChannelValue>>,
+    pub outputs: Vec<Vec<ChannelValue>>,
+    pub diagnosis: Vec<ModuleDiagnosis>,
+}
+
+/// A read-only handle to the latest coupler state
+/// that can be shared with other threads.
+#[derive(Debug, Clone, Default)]
+pub struct Observer {
+    snapshot: Arc<RwLock<Arc<Snapshot>>>,
+}
+
+impl Observer {
+    /// Returns the latest coupler state.
+    pub fn snapshot(&self) -> Arc<Snapshot> {
+        match self.snapshot.read() {
+            Ok(s) => s.clone(),
+            Err(poisoned) => poisoned.into_inner().clone(),
+        }
+    }
+
+    fn publish(&self, snapshot: Snapshot) {
+        let mut s = match self.snapshot.write() {
+            Ok(s) => s,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        *s = Arc::new(snapshot);
+    }
+
+    fn is_observed(&self) -> bool {
+        Arc::strong_count(&self.snapshot) > 1
+    }
+}
+
 /// Modbus TCP coupler implementation.
+///
+/// A `Coupler` is `Send` and can be moved into another thread or task.
+/// It is not `Sync` because an `OutputGuard` is only required to be `Send`;
+/// wrap it into a `Mutex` to share it or use an `Observer`
+/// to read the values from other threads.
 #[derive(Debug)]
 pub struct Coupler {
     /// cached input values
     in_values: Vec<Vec<ChannelValue>>,
     /// cached output values
     out_values: Vec<Vec<ChannelValue>>,
+    /// raw input data and decoded values of the last cycle
+    input_cache: DecodeCache,
     /// buffer write requests
     write: HashMap<Address, ChannelValue>,
     /// stateless modules
@@ -82,12 +439,83 @@ pub struct Coupler {
     offsets: Vec<ModuleOffset>,
     /// statefull message processors
     processors: HashMap<usize, ur20_1com_232_485_422::MessageProcessor>,
+    /// statefull ISDU processors of IO-Link masters
+    io_link: HashMap<usize, ur20_4com_io_link::IsduProcessor>,
     /// Last transmission counter  state
     last_tx_cnt: usize,
+    /// human readable channel descriptions
+    descriptions: Vec<Vec<ChannelDescription>>,
+    /// calculated input channels
+    virtual_channels: Vec<Expression>,
+    /// digital channels with a logical inversion
+    inverted: HashSet<Address>,
+    /// minimal time between two output changes
+    min_change_intervals: HashMap<Address, Duration>,
+    /// time of the last output change
+    last_changes: HashMap<Address, Instant>,
+    /// groups of redundant inputs
+    voting_groups: Vec<VotingGroup>,
+    /// results of the last voting
+    voting_results: Vec<VotingResult>,
+    /// digital channels that represent a number
+    bit_groups: Vec<BitGroup>,
+    /// decoded module diagnosis
+    diagnosis: Vec<ModuleDiagnosis>,
+    /// quality of the module values
+    qualities: Vec<Quality>,
+    /// events that were not taken yet
+    events: Vec<CouplerEvent>,
+    /// field calibration of analog channels
+    calibrations: HashMap<Address, Calibration>,
+    /// data format of all analog channels regardless of their parameters
+    data_format_override: Option<DataFormat>,
+    /// channels that keep their parameterized data format
+    data_format_exceptions: HashSet<Address>,
+    /// interlock logic for the outputs
+    guard: Option<Box<dyn OutputGuard>>,
+    /// user logic that runs on each cycle
+    tasks: Vec<Box<dyn CyclicTask>>,
+    /// heartbeat output
+    heartbeat: Option<Heartbeat>,
+    /// time of the last cycle
+    last_cycle: Option<Instant>,
+    /// shared state for observers
+    observer: Observer,
+    /// output image of the last cycle
+    commanded_output: Option<Vec<u16>>,
+    /// output image that is used by `apply_inputs`
+    output_image: Vec<u16>,
+    /// output image that was read back from the coupler
+    output_readback: Vec<u16>,
+    /// modules whose outputs are controlled by this instance
+    owned_modules: Option<Vec<usize>>,
+    /// last known good module parameters
+    params: Vec<Vec<u16>>,
+    /// last error of the fieldbus driver
+    driver_error: Option<String>,
+    /// last read coupler status
+    status: Option<CouplerStatus>,
+    /// output values on shutdown
+    safe_states: HashMap<Address, ChannelValue>,
+    /// rate limiting of diagnosis events
+    diagnosis_throttle: DiagnosisThrottle,
+    /// source of the current time
+    clock: Box<dyn Clock + Send>,
+    /// number of cycles the outputs are still held
+    hold_off: usize,
+    /// report input changes as events
+    input_events: bool,
+    /// processing time of each module
+    timings: Option<Vec<ModuleTiming>>,
+    /// compare the commanded outputs channel by channel
+    output_verification: bool,
+    /// output channels that differ from the commanded values
+    output_mismatches: Vec<OutputMismatch>,
 }
 
 /// Raw config data to create a coupler instance.
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct CouplerConfig {
     /// Register content of `ADDR_CURRENT_MODULE_LIST`.
     /// Register count: 2 * number of modules
@@ -99,92 +527,271 @@ pub struct CouplerConfig {
     pub params: Vec<Vec<u16>>,
 }
 
+/// Arrangement of the module data within the process images.
+#[rustfmt::skip]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, FromPrimitive, ToPrimitive)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ProcessImageMapping {
+    /// The module data is packed without gaps.
+    Packed      = 0,
+    /// The data of each module begins at a register boundary.
+    WordAligned = 1,
+}
+
+/// Byte order of the process data registers.
+#[rustfmt::skip]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, FromPrimitive, ToPrimitive)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum RegisterFormat {
+    LittleEndian = 0,
+    BigEndian    = 1,
+}
+
+/// Output behavior of the whole station on a fieldbus failure.
+#[rustfmt::skip]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, FromPrimitive, ToPrimitive)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum FaultBehavior {
+    /// Switch all outputs off.
+    SwitchOff     = 0,
+    /// Keep the last output values.
+    HoldLastValue = 1,
+    /// Output the substitute values of the module parameters.
+    Substitute    = 2,
+}
+
+/// Station-level parameters of the coupler
+/// (register content of `ADDR_COUPLER_PARAMETERS`).
+///
+/// `Coupler` expects the process images in the default format
+/// (`Packed` and `LittleEndian`).
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CouplerParameters {
+    pub process_image_mapping: ProcessImageMapping,
+    pub register_format: RegisterFormat,
+    pub fault_behavior: FaultBehavior,
+    /// Fieldbus watchdog timeout in ms (`0` = disabled)
+    pub watchdog_timeout: u16,
+}
+
+impl Default for CouplerParameters {
+    fn default() -> Self {
+        CouplerParameters {
+            process_image_mapping: ProcessImageMapping::Packed,
+            register_format: RegisterFormat::LittleEndian,
+            fault_behavior: FaultBehavior::SwitchOff,
+            watchdog_timeout: 0,
+        }
+    }
+}
+
+impl CouplerParameters {
+    pub fn from_raw_data(data: &[u16]) -> Result<Self> {
+        use num_traits::FromPrimitive;
+
+        if data.len() < COUPLER_PARAMETER_REGISTER_COUNT {
+            return Err(Error::BufferLength);
+        }
+        let process_image_mapping = match FromPrimitive::from_u16(data[0]) {
+            Some(x) => x,
+            _ => {
+                return Err(Error::ChannelParameter);
+            }
+        };
+        let register_format = match FromPrimitive::from_u16(data[1]) {
+            Some(x) => x,
+            _ => {
+                return Err(Error::ChannelParameter);
+            }
+        };
+        let fault_behavior = match FromPrimitive::from_u16(data[2]) {
+            Some(x) => x,
+            _ => {
+                return Err(Error::ChannelParameter);
+            }
+        };
+        Ok(CouplerParameters {
+            process_image_mapping,
+            register_format,
+            fault_behavior,
+            watchdog_timeout: data[3],
+        })
+    }
+
+    pub fn to_raw_data(&self) -> Vec<u16> {
+        vec![
+            self.process_image_mapping.clone() as u16,
+            self.register_format.clone() as u16,
+            self.fault_behavior.clone() as u16,
+            self.watchdog_timeout,
+        ]
+    }
+
+    /// Fieldbus watchdog timeout (`None` if the watchdog is disabled).
+    pub fn watchdog(&self) -> Option<Duration> {
+        match self.watchdog_timeout {
+            0 => None,
+            ms => Some(Duration::from_millis(u64::from(ms))),
+        }
+    }
+}
+
 impl Coupler {
     pub fn new(cfg: &CouplerConfig) -> Result<Self> {
         cfg.validate()?;
+        let mut c = Coupler::from_modules(&cfg.modules, &cfg.params)?;
+        c.set_offsets(&cfg.offsets)?;
+        Ok(c)
+    }
 
-        let offsets = offsets_of_process_data(&cfg.offsets);
+    /// Creates a coupler instance without the module offsets.
+    ///
+    /// The offsets have to be set by `set_offsets`
+    /// before any process data can be processed.
+    pub fn from_modules(module_types: &[ModuleType], params: &[Vec<u16>]) -> Result<Self> {
+        if module_types.len() != params.len() {
+            return Err(Error::BufferLength);
+        }
 
         let mut modules = vec![];
         let mut processors = HashMap::new();
-        for (i, m) in cfg.modules.iter().enumerate() {
-            let param_data = &cfg.params[i];
-            let x: Box<dyn ProcessModbusTcpData> = match *m {
-                ModuleType::UR20_4DI_P => {
-                    let m = ur20_4di_p::Mod::from_modbus_parameter_data(&param_data)?;
-                    Box::new(m)
-                }
-                ModuleType::UR20_4DO_P => {
-                    let m = ur20_4do_p::Mod::from_modbus_parameter_data(&param_data)?;
-                    Box::new(m)
-                }
-                ModuleType::UR20_16DO_P => {
-                    let m = ur20_16do_p::Mod::from_modbus_parameter_data(&param_data)?;
-                    Box::new(m)
-                }
-                ModuleType::UR20_4RO_CO_255 => {
-                    let m = ur20_4ro_co_255::Mod::from_modbus_parameter_data(&param_data)?;
-                    Box::new(m)
-                }
-                ModuleType::UR20_4AO_UI_16 => {
-                    let m = ur20_4ao_ui_16::Mod::from_modbus_parameter_data(&param_data)?;
-                    Box::new(m)
-                }
-                ModuleType::UR20_4AO_UI_16_DIAG => {
-                    let m = ur20_4ao_ui_16_diag::Mod::from_modbus_parameter_data(&param_data)?;
-                    Box::new(m)
-                }
-                ModuleType::UR20_4AI_RTD_DIAG => {
-                    let m = ur20_4ai_rtd_diag::Mod::from_modbus_parameter_data(&param_data)?;
-                    Box::new(m)
-                }
-                ModuleType::UR20_4AI_UI_16_DIAG => {
-                    let m = ur20_4ai_ui_16_diag::Mod::from_modbus_parameter_data(&param_data)?;
-                    Box::new(m)
-                }
-                ModuleType::UR20_4AI_UI_12 => {
-                    let m = ur20_4ai_ui_12::Mod::from_modbus_parameter_data(&param_data)?;
-                    Box::new(m)
-                }
-                ModuleType::UR20_8AI_I_16_DIAG_HD => {
-                    let m = ur20_8ai_i_16_diag_hd::Mod::from_modbus_parameter_data(&param_data)?;
-                    Box::new(m)
-                }
-                ModuleType::UR20_2FCNT_100 => {
-                    let m = ur20_2fcnt_100::Mod::from_modbus_parameter_data(&param_data)?;
-                    Box::new(m)
-                }
-                ModuleType::UR20_1COM_232_485_422 => {
-                    let m = ur20_1com_232_485_422::Mod::from_modbus_parameter_data(&param_data)?;
-                    let processor = ur20_1com_232_485_422::MessageProcessor::new(
-                        m.mod_params.process_data_len.clone(),
-                    );
-                    processors.insert(i, processor);
-                    Box::new(m)
-                }
-                _ => {
-                    panic!("{:?} is not supported", m);
-                }
-            };
-            modules.push(x);
+        let mut io_link = HashMap::new();
+        for (i, m) in module_types.iter().enumerate() {
+            let param_data = &params[i];
+            if *m == ModuleType::UR20_1COM_232_485_422 {
+                let m = ur20_1com_232_485_422::Mod::from_modbus_parameter_data(param_data)?;
+                let processor = ur20_1com_232_485_422::MessageProcessor::new(
+                    m.mod_params.process_data_len.clone(),
+                );
+                processors.insert(i, processor);
+            }
+            if *m == ModuleType::UR20_4COM_IO_LINK {
+                io_link.insert(i, ur20_4com_io_link::IsduProcessor::new());
+            }
+            modules.push(module_from_parameter_data(m, param_data)?);
         }
         Ok(Coupler {
             in_values: vec![],
             out_values: vec![],
+            input_cache: vec![],
             write: HashMap::new(),
             last_tx_cnt: 0,
             modules,
-            offsets,
+            offsets: vec![],
             processors,
+            io_link,
+            descriptions: vec![],
+            virtual_channels: vec![],
+            inverted: HashSet::new(),
+            min_change_intervals: HashMap::new(),
+            last_changes: HashMap::new(),
+            voting_groups: vec![],
+            voting_results: vec![],
+            bit_groups: vec![],
+            diagnosis: vec![],
+            qualities: vec![],
+            events: vec![],
+            calibrations: HashMap::new(),
+            data_format_override: None,
+            data_format_exceptions: HashSet::new(),
+            guard: None,
+            tasks: vec![],
+            heartbeat: None,
+            last_cycle: None,
+            observer: Observer::default(),
+            commanded_output: None,
+            output_image: vec![],
+            output_readback: vec![],
+            owned_modules: None,
+            params: params.to_vec(),
+            driver_error: None,
+            status: None,
+            safe_states: HashMap::new(),
+            diagnosis_throttle: DiagnosisThrottle::default(),
+            clock: Box::new(SystemClock),
+            hold_off: 0,
+            input_events: false,
+            timings: None,
+            output_verification: false,
+            output_mismatches: vec![],
         })
     }
 
+    /// Sets the register content of `ADDR_MODULE_OFFSETS`
+    /// (e.g. of a coupler that was created by `from_modules`).
+    pub fn set_offsets(&mut self, offsets: &[u16]) -> Result<()> {
+        if self.modules.len() * 2 != offsets.len() {
+            return Err(Error::ModuleOffset);
+        }
+        self.offsets = offsets_of_process_data(offsets);
+        self.input_cache.clear();
+        self.commanded_output = None;
+        self.output_image.clear();
+        self.output_readback.clear();
+        Ok(())
+    }
+
+    /// Returns `true` if the module offsets are known.
+    pub fn has_offsets(&self) -> bool {
+        self.offsets.len() == self.modules.len()
+    }
+
+    /// Creates a new coupler instance from a station description and
+    /// the register content of `ADDR_MODULE_OFFSETS`.
+    pub fn from_description(desc: &StationDescription, offsets: &[u16]) -> Result<Self> {
+        desc.validate()?;
+        let mut c = Coupler::new(&desc.coupler_config(offsets.to_vec()))?;
+        c.descriptions = desc.modules.iter().map(|m| m.channels.clone()).collect();
+        for (module, m) in desc.modules.iter().enumerate() {
+            for (channel, d) in m.channels.iter().enumerate() {
+                if d.invert {
+                    c.inverted.insert(Address { module, channel });
+                }
+            }
+        }
+        if !desc.virtual_channels.is_empty() {
+            c.descriptions.push(
+                desc.virtual_channels
+                    .iter()
+                    .map(|v| v.channel.clone())
+                    .collect(),
+            );
+            c.virtual_channels = desc
+                .virtual_channels
+                .iter()
+                .map(|v| v.expression.clone())
+                .collect();
+        }
+        Ok(c)
+    }
+
+    /// Inverts the values of digital channels with the `invert` flag
+    /// of their description.
+    fn invert_channels(&self, values: &mut [Vec<ChannelValue>]) {
+        for addr in &self.inverted {
+            if let Some(ChannelValue::Bit(v)) = values
+                .get_mut(addr.module)
+                .and_then(|m| m.get_mut(addr.channel))
+            {
+                *v = !*v;
+            }
+        }
+    }
+
+    pub(crate) fn module_type(&self, module_nr: usize) -> Option<ModuleType> {
+        self.modules.get(module_nr).map(|m| m.module_type())
+    }
+
     fn is_valid_addr(&self, addr: &Address) -> bool {
         addr.module < self.modules.len()
             && addr.channel < self.modules[addr.module].module_type().channel_count()
     }
 
     /// Returns current coupler input state.
+    ///
+    /// If the station has virtual channels,
+    /// their values follow behind the values of the last module.
     pub fn inputs(&self) -> &Vec<Vec<ChannelValue>> {
         &self.in_values
     }
@@ -194,6 +801,29 @@ impl Coupler {
         &self.out_values
     }
 
+    /// Returns the meta data of a channel.
+    pub fn channel_info(&self, addr: &Address) -> Option<ChannelInfo> {
+        let mut info = if self.is_valid_addr(addr) {
+            self.modules[addr.module]
+                .channel_info()
+                .into_iter()
+                .nth(addr.channel)?
+        } else if addr.module == self.modules.len() && addr.channel < self.virtual_channels.len() {
+            ChannelInfo::default()
+        } else {
+            return None;
+        };
+        if let Some(d) = self
+            .descriptions
+            .get(addr.module)
+            .and_then(|m| m.get(addr.channel))
+        {
+            info.label = d.label.clone();
+            info.description = d.description.clone();
+        }
+        Some(info)
+    }
+
     /// Returns a reader to the underlying communication data buffer.
     pub fn reader(&mut self, module_nr: usize) -> Option<&mut dyn Read> {
         self.processors
@@ -208,990 +838,4946 @@ impl Coupler {
             .map(|r| r as &mut dyn Write)
     }
 
-    pub fn set_output(&mut self, addr: &Address, value: ChannelValue) -> Result<()> {
-        if !self.is_valid_addr(addr) {
-            return Err(Error::Address);
-        }
-        self.write.insert(addr.clone(), value);
-        Ok(())
+    /// Queues an ISDU request for an IO-Link master module.
+    ///
+    /// The request is sent with one of the next cycles;
+    /// the answer can be fetched with `isdu_responses`.
+    pub fn isdu_request(
+        &mut self,
+        module_nr: usize,
+        req: ur20_4com_io_link::IsduRequest,
+    ) -> Result<()> {
+        self.io_link
+            .get_mut(&module_nr)
+            .ok_or(Error::Address)?
+            .request(req)
     }
 
-    pub fn next(&mut self, process_input: &[u16], process_output: &[u16]) -> Result<Vec<u16>> {
-        let infos: Vec<_> = self
-            .modules
-            .iter()
-            .zip(&self.offsets)
-            .map(|(m, o)| (&**m, o))
-            .collect();
-        self.in_values = process_input_data(&*infos, process_input)?;
-        self.out_values = process_output_data(&*infos, process_output)?;
-
-        let mut next_out_values = self.out_values.clone();
-        let mut in_bytes = HashMap::new();
-        let mut out_bytes = HashMap::new();
-
-        for (m_nr, (in_v, out_v)) in self.in_values.iter().zip(&self.out_values).enumerate() {
-            if let Some(p) = self.processors.get_mut(&m_nr) {
-                if let ChannelValue::ComRsIn(ref in_v) = in_v[0] {
-                    if let ChannelValue::ComRsOut(ref out_v) = out_v[0] {
-                        out_bytes.insert(m_nr, ChannelValue::None);
-                        in_bytes.insert(m_nr, ChannelValue::None);
+    /// Takes the received ISDU responses of an IO-Link master module.
+    pub fn isdu_responses(&mut self, module_nr: usize) -> Vec<ur20_4com_io_link::IsduResponse> {
+        self.io_link
+            .get_mut(&module_nr)
+            .map(|p| p.take_responses())
+            .unwrap_or_default()
+    }
 
-                        if !out_v.data.is_empty() && out_v.tx_cnt != self.last_tx_cnt {
-                            out_bytes.insert(m_nr, ChannelValue::Bytes(out_v.data.clone()));
-                        }
-                        self.last_tx_cnt = out_v.tx_cnt;
+    /// Returns the last known good parameters
+    /// (register content of `ADDR_MODULE_PARAMETERS`).
+    pub fn parameters(&self) -> &[Vec<u16>] {
+        &self.params
+    }
 
-                        if let Some(v) = self.write.remove(&Address {
-                            module: m_nr,
-                            channel: 0,
-                        }) {
-                            if let ChannelValue::Bytes(ref data) = v {
-                                p.write_all(data)?;
-                            }
-                        }
+    /// Returns the parameter registers of all modules.
+    ///
+    /// Each entry contains the first parameter register address
+    /// of a module (see `param_addresses_and_register_counts`)
+    /// and the serialized module parameters,
+    /// so the complete configuration can be written to the coupler.
+    pub fn parameter_image(&self) -> Vec<(u16, Vec<u16>)> {
+        let modules: Vec<_> = self.modules.iter().map(|m| m.module_type()).collect();
+        param_addresses_and_register_counts(&modules)
+            .into_iter()
+            .zip(&self.modules)
+            .map(|((addr, _), m)| (addr, m.to_modbus_parameter_data()))
+            .collect()
+    }
 
-                        let rs_out = p.next(in_v, out_v);
-                        next_out_values[m_nr][0] = ChannelValue::ComRsOut(rs_out);
+    /// Returns the process data layout of each module.
+    pub fn layouts(&self) -> Vec<ProcessDataLayout> {
+        self.modules.iter().map(|m| m.layout()).collect()
+    }
 
-                        if in_v.data_available && !in_v.data.is_empty() {
-                            in_bytes.insert(m_nr, ChannelValue::Bytes(in_v.data.clone()));
-                        }
-                    }
+    /// Returns a human readable report of the station
+    /// with the modules, their process data offsets,
+    /// the configured channel ranges and the current values.
+    ///
+    /// ```text
+    /// module 0: UR20_4DI_P (input: 0x0000.0, 1 byte; output: -)
+    ///   channel 0 (start_button): true
+    ///   ...
+    /// module 1: UR20_4AO_UI_16 (input: -; output: 0x0800.0, 8 bytes)
+    ///   channel 0 [mA4To20]: out 12.5 mA
+    ///   ...
+    /// ```
+    pub fn describe(&self) -> String {
+        let fmt_offset = |offset: Option<BitAddress>, cnt: usize| match offset {
+            Some(offset) => {
+                let (register, bit) = to_register_address(offset);
+                let bytes = if cnt == 1 { "byte" } else { "bytes" };
+                format!("0x{:04X}.{}, {} {}", register, bit, cnt, bytes)
+            }
+            None => "-".to_string(),
+        };
+        let fmt_value = |v: Option<&ChannelValue>, info: &ChannelInfo| match v {
+            Some(v) => v.display(info).to_string(),
+            None => "-".to_string(),
+        };
+        let mut lines = vec![];
+        for (nr, (m, offset)) in self.modules.iter().zip(&self.offsets).enumerate() {
+            lines.push(format!(
+                "module {}: {:?} (input: {}; output: {})",
+                nr,
+                m.module_type(),
+                fmt_offset(offset.input, m.process_input_byte_count()),
+                fmt_offset(offset.output, m.process_output_byte_count()),
+            ));
+            for ch in 0..m.module_type().channel_count() {
+                let addr = Address {
+                    module: nr,
+                    channel: ch,
+                };
+                let info = self.channel_info(&addr).unwrap_or_default();
+                let mut line = format!("  channel {}", ch);
+                if let Some(ref label) = info.label {
+                    line.push_str(&format!(" ({})", label));
                 }
-            } else {
-                for (i, _) in out_v.iter().enumerate() {
-                    if let Some(v) = self.write.remove(&Address {
-                        module: m_nr,
-                        channel: i,
-                    }) {
-                        next_out_values[m_nr][i] = v;
-                    }
+                if let Some(ref range) = info.range {
+                    line.push_str(&format!(" [{}]", range));
+                }
+                let value = |values: &Vec<Vec<ChannelValue>>| {
+                    fmt_value(values.get(nr).and_then(|m| m.get(ch)), &info)
+                };
+                match (offset.input, offset.output) {
+                    (Some(_), Some(_)) => line.push_str(&format!(
+                        ": in {}, out {}",
+                        value(&self.in_values),
+                        value(&self.out_values)
+                    )),
+                    (Some(_), None) => line.push_str(&format!(": {}", value(&self.in_values))),
+                    (None, Some(_)) => line.push_str(&format!(": out {}", value(&self.out_values))),
+                    (None, None) => {}
                 }
+                lines.push(line);
             }
         }
-        for (m_nr, v) in in_bytes {
-            self.in_values[m_nr][0] = v;
+        if !self.virtual_channels.is_empty() {
+            lines.push("virtual channels:".to_string());
+            for ch in 0..self.virtual_channels.len() {
+                let addr = Address {
+                    module: self.modules.len(),
+                    channel: ch,
+                };
+                let info = self.channel_info(&addr).unwrap_or_default();
+                let mut line = format!("  channel {}", ch);
+                if let Some(ref label) = info.label {
+                    line.push_str(&format!(" ({})", label));
+                }
+                let v = self.in_values.get(addr.module).and_then(|m| m.get(ch));
+                line.push_str(&format!(": {}", fmt_value(v, &info)));
+                lines.push(line);
+            }
         }
-        for (m_nr, v) in out_bytes {
-            self.out_values[m_nr][0] = v;
+        lines.join("\n")
+    }
+
+    /// Returns the configuration of the station
+    /// (e.g. to create a coupler for a second connection).
+    pub fn clone_config(&self) -> CouplerConfig {
+        CouplerConfig {
+            modules: self.modules.iter().map(|m| m.module_type()).collect(),
+            offsets: self
+                .offsets
+                .iter()
+                .flat_map(|o| vec![o.output.unwrap_or(0xFFFF), o.input.unwrap_or(0xFFFF)])
+                .collect(),
+            params: self.params.clone(),
         }
-        process_output_values(&*infos, &next_out_values)
     }
-}
 
-impl CouplerConfig {
-    fn validate(&self) -> Result<()> {
-        if self.modules.len() != self.params.len() {
+    /// Returns the layout of the compact digital image of the station.
+    pub fn compact_digital_image(&self) -> compact::CompactDigitalImage {
+        let modules: Vec<_> = self.modules.iter().map(|m| m.module_type()).collect();
+        compact::CompactDigitalImage::new(&modules)
+    }
+
+    /// Compares the current parameters of the station with
+    /// the last known good parameters.
+    ///
+    /// Returns the parameter blocks of modules with an unchanged type
+    /// that lost their parameters, i.e. a replaced module that reports
+    /// its default parameters or an all-zero block. Modules whose
+    /// parameters were deliberately changed are left alone.
+    pub fn reparameterize(
+        &mut self,
+        modules: &[ModuleType],
+        params: &[Vec<u16>],
+    ) -> Result<Vec<ParameterDownload>> {
+        if modules.len() != self.modules.len() || params.len() != self.modules.len() {
             return Err(Error::BufferLength);
         }
-        if self.modules.len() * 2 != self.offsets.len() {
-            return Err(Error::ModuleOffset);
+        let mut downloads = vec![];
+        for (nr, m) in self.modules.iter().enumerate() {
+            let current = &params[nr];
+            if m.module_type() != modules[nr] || self.params[nr] == *current {
+                continue;
+            }
+            let defaults = default_module(&modules[nr])
+                .map(|m| m.to_modbus_parameter_data())
+                .ok();
+            if !current.iter().all(|r| *r == 0) && defaults.as_ref() != Some(current) {
+                continue;
+            }
+            downloads.push(ParameterDownload {
+                module: nr,
+                address: ADDR_MODULE_PARAMETERS + (nr * 256) as u16,
+                data: self.params[nr].clone(),
+            });
+            self.events
+                .push(CouplerEvent::ReparameterizationRequired(nr));
         }
-        Ok(())
+        Ok(downloads)
     }
-}
 
-/// Converts the register data into a list of module offsets.
-pub fn offsets_of_process_data(data: &[Word]) -> Vec<ModuleOffset> {
-    let mut offsets = vec![];
-    for i in 0..data.len() / 2 {
-        offsets.push(ModuleOffset {
-            input: word_to_offset(data[i * 2 + 1]),
-            output: word_to_offset(data[i * 2]),
-        });
+    /// Restricts the control of the outputs to the given modules
+    /// (`None` means all modules).
+    ///
+    /// The outputs of all other modules are taken from the output image
+    /// read back from the coupler, so another controller can write them.
+    /// Therefore `next` requires the complete output readback
+    /// and only the registers of `owned_output_ranges` must be written.
+    pub fn set_owned_modules(&mut self, modules: Option<Vec<usize>>) -> Result<()> {
+        if let Some(ref modules) = modules {
+            if modules.iter().any(|m| *m >= self.modules.len()) {
+                return Err(Error::Address);
+            }
+        }
+        self.owned_modules = modules;
+        Ok(())
     }
-    offsets
-}
 
-/// Map the raw input data into values.
-pub fn process_input_data(
-    modules: &[(&dyn ProcessModbusTcpData, &ModuleOffset)],
-    data: &[u16],
-) -> Result<Vec<Vec<ChannelValue>>> {
-    modules
-        .iter()
-        .map(|&(ref m, ref offset)| {
-            if let Some(in_offset) = offset.input {
-                let cnt = m.process_input_byte_count();
-                m.process_input_data(&prepare_raw_data_to_process(
-                    in_offset,
-                    ADDR_PACKED_PROCESS_INPUT_DATA,
-                    cnt,
-                    data,
-                )?)
-            } else {
-                Ok(vec![ChannelValue::None; m.module_type().channel_count()])
+    /// The register ranges of the packed process output image
+    /// (relative to `ADDR_PACKED_PROCESS_OUTPUT_DATA`)
+    /// that contain outputs of owned modules (see `set_owned_modules`).
+    ///
+    /// Registers that are shared with other modules are included.
+    pub fn owned_output_ranges(&self) -> Vec<Range<usize>> {
+        let mut ranges: Vec<Range<usize>> = vec![];
+        for (m_nr, (m, offset)) in self.modules.iter().zip(&self.offsets).enumerate() {
+            let out_offset = match offset.output {
+                Some(o) if self.is_owned(m_nr) => o,
+                _ => continue,
+            };
+            let (start, _) = to_register_address(out_offset);
+            let start = start.saturating_sub(ADDR_PACKED_PROCESS_OUTPUT_DATA) as usize;
+            let end = image_word_count(
+                out_offset,
+                ADDR_PACKED_PROCESS_OUTPUT_DATA,
+                m.process_output_byte_count(),
+            );
+            ranges.push(start..end);
+        }
+        ranges.sort_by_key(|r| r.start);
+        let mut merged: Vec<Range<usize>> = vec![];
+        for r in ranges {
+            match merged.last_mut() {
+                Some(last) if r.start <= last.end => last.end = cmp::max(last.end, r.end),
+                _ => merged.push(r),
             }
-        })
-        .collect()
-}
-
-/// Map the raw output data into values.
-pub fn process_output_data(
-    modules: &[(&dyn ProcessModbusTcpData, &ModuleOffset)],
-    data: &[u16],
-) -> Result<Vec<Vec<ChannelValue>>> {
-    modules
-        .iter()
-        .map(|&(ref m, ref offset)| {
-            if let Some(out_offset) = offset.output {
-                let cnt = m.process_output_byte_count();
-                m.process_output_data(&prepare_raw_data_to_process(
-                    out_offset,
-                    ADDR_PACKED_PROCESS_OUTPUT_DATA,
-                    cnt,
-                    data,
-                )?)
-            } else {
-                Ok(vec![ChannelValue::None; m.module_type().channel_count()])
-            }
-        })
-        .collect()
-}
-
-fn prepare_raw_data_to_process(
-    offset: u16,
-    addr: u16,
-    byte_count: usize,
-    data: &[u16],
-) -> Result<Vec<u16>> {
-    let (start, bit) = to_register_address(offset);
-    let start = (start - addr) as usize;
-    let word_count = {
-        let cnt = byte_count / 2;
-        if cnt == 0 {
-            1
-        } else {
-            cnt
         }
-    };
-    let end = start + word_count;
-    if end > data.len() {
-        return Err(Error::BufferLength);
-    }
-    let output = &data[start..end];
-
-    match bit {
-        0 => Ok(output.to_vec()),
-        8 => Ok(shift_data(&output)),
-        _ => Err(Error::ModuleOffset),
+        merged
     }
-}
 
-/// Map values into raw values.
-pub fn process_output_values(
-    modules: &[(&dyn ProcessModbusTcpData, &ModuleOffset)],
-    values: &[Vec<ChannelValue>],
-) -> Result<Vec<u16>> {
-    if modules.len() != values.len() {
-        return Err(Error::ChannelValue);
+    fn is_owned(&self, module_nr: usize) -> bool {
+        self.owned_modules
+            .as_ref()
+            .map(|m| m.contains(&module_nr))
+            .unwrap_or(true)
     }
 
-    let mut out = vec![];
-
-    for (i, &(ref m, ref offset)) in modules.iter().enumerate() {
-        if let Some(out_offset) = offset.output {
-            let data = m.process_output_values(&values[i])?;
-            let (start, bit) = to_register_address(out_offset);
-            if start < ADDR_PACKED_PROCESS_OUTPUT_DATA {
-                return Err(Error::ModuleOffset);
+    /// Reads exactly `buf.len()` bytes from a communication module.
+    ///
+    /// `cycle` is called to run the next coupler cycle
+    /// (e.g. by calling `next()`) as long as there is not enough data.
+    pub fn read_exact_timeout<C, F>(
+        &mut self,
+        module_nr: usize,
+        buf: &mut [u8],
+        timeout: Duration,
+        clock: &C,
+        mut cycle: F,
+    ) -> Result<()>
+    where
+        C: Clock,
+        F: FnMut(&mut Coupler) -> Result<()>,
+    {
+        let start = clock.now();
+        let mut pos = 0;
+        loop {
+            pos += self
+                .reader(module_nr)
+                .ok_or(Error::Address)?
+                .read(&mut buf[pos..])
+                .module_context(module_nr)?;
+            if pos == buf.len() {
+                return Ok(());
             }
-            let start = (start - ADDR_PACKED_PROCESS_OUTPUT_DATA) as usize;
+            if clock.now().duration_since(start) >= timeout {
+                return Err(io::Error::from(io::ErrorKind::TimedOut)).module_context(module_nr);
+            }
+            cycle(self)?;
+        }
+    }
 
-            match bit {
-                0 => {
-                    if out.len() != start {
-                        return Err(Error::ModuleOffset);
-                    }
-                    out.extend_from_slice(&data);
-                }
-                8 => {
-                    if out.len() != start + 1 {
-                        return Err(Error::ModuleOffset);
-                    }
-                    let shared_low_byte = out[start as usize] & 0x00FF;
-                    let buf = u16_to_u8(&data);
-                    let shared_high_byte = u16::from(buf[0]) << 8;
-                    let word = shared_high_byte | shared_low_byte;
-                    out[start as usize] = word;
-                }
-                _ => {
-                    return Err(Error::ModuleOffset);
+    /// Reads from a communication module until the delimiter was received.
+    ///
+    /// The returned data includes the delimiter.
+    /// `cycle` is called to run the next coupler cycle
+    /// (e.g. by calling `next()`) as long as there is not enough data.
+    pub fn read_until<C, F>(
+        &mut self,
+        module_nr: usize,
+        delimiter: u8,
+        timeout: Duration,
+        clock: &C,
+        mut cycle: F,
+    ) -> Result<Vec<u8>>
+    where
+        C: Clock,
+        F: FnMut(&mut Coupler) -> Result<()>,
+    {
+        let start = clock.now();
+        let mut data = vec![];
+        let mut byte = [0];
+        loop {
+            let reader = self.reader(module_nr).ok_or(Error::Address)?;
+            while reader.read(&mut byte).module_context(module_nr)? == 1 {
+                data.push(byte[0]);
+                if byte[0] == delimiter {
+                    return Ok(data);
                 }
             }
+            if clock.now().duration_since(start) >= timeout {
+                return Err(io::Error::from(io::ErrorKind::TimedOut)).module_context(module_nr);
+            }
+            cycle(self)?;
         }
     }
 
-    Ok(out)
-}
+    /// Requests a new output value that is written with the next cycle.
+    ///
+    /// Channels of modules without outputs result in `Error::NotAnOutput`.
+    pub fn set_output(&mut self, addr: &Address, value: ChannelValue) -> Result<()> {
+        if !self.is_valid_addr(addr) || !self.is_owned(addr.module) {
+            return Err(Error::Address);
+        }
+        let m = &self.modules[addr.module];
+        if m.process_output_byte_count() == 0 {
+            return Err(Error::NotAnOutput {
+                address: *addr,
+                module_type: m.module_type(),
+            });
+        }
+        self.write.insert(*addr, value);
+        Ok(())
+    }
 
-fn word_to_offset(word: Word) -> Option<BitAddress> {
-    if word == 0xFFFF {
-        None
-    } else {
-        Some(word)
+    /// Sets a field of a structured output value
+    /// (e.g. `start` of a frequency counter channel).
+    ///
+    /// The other fields keep the value of a pending write request
+    /// or the current output value.
+    pub fn set_output_field(&mut self, addr: &SubAddress, value: ChannelValue) -> Result<()> {
+        let mut v = match self.write.get(&addr.address) {
+            Some(v) => v.clone(),
+            None => self
+                .out_values
+                .get(addr.address.module)
+                .and_then(|m| m.get(addr.address.channel))
+                .cloned()
+                .ok_or(Error::Address)?,
+        };
+        v.set_field(&addr.sub, value)?;
+        self.set_output(&addr.address, v)
     }
-}
 
-/// Splits a bit address into a register address and a bit number.
-pub fn to_register_address(addr: BitAddress) -> (RegisterAddress, BitNr) {
-    let register = (addr & 0xFFF0) >> 4;
-    let bit = (addr & 0x000F) as usize;
-    (register as u16, bit)
-}
+    /// Returns a field of a structured input value.
+    pub fn input_field(&self, addr: &SubAddress) -> Option<ChannelValue> {
+        self.in_values
+            .get(addr.address.module)?
+            .get(addr.address.channel)?
+            .field(&addr.sub)
+    }
 
-/// Merges a register address and a bit number into a bit address.
-pub fn to_bit_address(addr: RegisterAddress, bit: usize) -> BitAddress {
-    (addr << 4) | (bit as u16)
-}
+    /// Returns the buffered write requests that were not processed yet.
+    pub fn pending_writes(&self) -> &HashMap<Address, ChannelValue> {
+        &self.write
+    }
 
-pub trait ModbusParameterRegisterCount {
-    /// Total number of Modbus registers of module parameters.
-    fn param_register_count(&self) -> u16;
-}
+    /// Cancels a buffered write request.
+    ///
+    /// Data that was already passed to a communication module buffer
+    /// can't be cancelled.
+    pub fn cancel_output(&mut self, addr: &Address) -> Option<ChannelValue> {
+        self.write.remove(addr)
+    }
 
-impl ModbusParameterRegisterCount for ModuleType {
-    fn param_register_count(&self) -> u16 {
-        use super::ModuleType::*;
-        match *self {
-            // Digital input modules
-            UR20_4DI_P | UR20_4DI_P_3W => 0 + 4 * 1,
-            UR20_8DI_P_2W | UR20_8DI_P_3W => 0 + 8 * 1,
+    /// Cancels all buffered write requests.
+    pub fn clear_pending(&mut self) {
+        self.write.clear();
+    }
 
-            // Digital output modules
-            UR20_4DO_P => 0 + 4 * 1,
-            UR20_16DO_P => 0,
-            UR20_4RO_CO_255 => 0 + 4 * 1,
+    /// Sets the value of an output channel on shutdown
+    /// (see `shutdown_plan`).
+    pub fn set_safe_state(&mut self, addr: &Address, value: ChannelValue) -> Result<()> {
+        if !self.is_valid_addr(addr) {
+            return Err(Error::Address);
+        }
+        let m = &self.modules[addr.module];
+        if m.process_output_byte_count() == 0 {
+            return Err(Error::NotAnOutput {
+                address: *addr,
+                module_type: m.module_type(),
+            });
+        }
+        self.safe_states.insert(*addr, value);
+        Ok(())
+    }
 
-            // Analogue input modules
-            UR20_8AI_I_16_DIAG_HD => 1 + 8 * 4,
-            UR20_4AI_UI_16_DIAG => 1 + 4 * 5,
-            UR20_4AI_UI_12 => 1 + 4 * 2,
+    /// Creates the final process output image
+    /// that puts every output channel into its safe state.
+    ///
+    /// Channels without a safe state are switched off.
+    /// Modules that are not owned (see `set_owned_modules`) are left out:
+    /// their registers are taken from the last output readback.
+    pub fn shutdown_plan(&self) -> Result<Vec<u16>> {
+        let infos = self.checked_infos()?;
+        let values: Vec<_> = self
+            .modules
+            .iter()
+            .enumerate()
+            .map(|(module, m)| {
+                let cnt = m.module_type().channel_count();
+                if !self.is_owned(module) || !self.safe_states.keys().any(|a| a.module == module) {
+                    return vec![];
+                }
+                (0..cnt)
+                    .map(|channel| {
+                        self.safe_states
+                            .get(&Address { module, channel })
+                            .cloned()
+                            .unwrap_or(ChannelValue::None)
+                    })
+                    .collect()
+            })
+            .collect();
+        let mut output = encode_output_image(&infos, &values)?;
+        for (m_nr, &(m, offset)) in infos.iter().enumerate() {
+            if let (false, Some(out_offset)) = (self.is_owned(m_nr), offset.output) {
+                copy_module_output(
+                    out_offset,
+                    m.process_output_byte_count(),
+                    &self.output_readback,
+                    &mut output,
+                );
+            }
+        }
+        Ok(output)
+    }
 
-            // Analogue output modul
-            UR20_4AO_UI_16 => 0 + 4 * 3,
-            UR20_4AO_UI_16_DIAG => 0 + 4 * 4,
+    /// Limits how often an output channel may change its value.
+    ///
+    /// A write request that would change the output before the interval
+    /// since the last change has elapsed stays pending until a later cycle.
+    /// Use `None` to remove the limit.
+    pub fn set_min_change_interval(
+        &mut self,
+        addr: &Address,
+        interval: Option<Duration>,
+    ) -> Result<()> {
+        if !self.is_valid_addr(addr) {
+            return Err(Error::Address);
+        }
+        match interval {
+            Some(i) => {
+                self.min_change_intervals.insert(*addr, i);
+            }
+            None => {
+                self.min_change_intervals.remove(addr);
+                self.last_changes.remove(addr);
+            }
+        }
+        Ok(())
+    }
 
-            // Analogue input modules DIAG
-            UR20_4AI_RTD_DIAG => 1 + 4 * 7,
+    /// Sets the source of the current time (`SystemClock` by default).
+    ///
+    /// It is used for the minimal time between output changes,
+    /// the heartbeat and the diagnosis events.
+    pub fn set_clock(&mut self, clock: Box<dyn Clock + Send>) {
+        self.clock = clock;
+    }
 
-            // Counter modules
-            UR20_2FCNT_100 => 0 + 2 * 1,
+    /// Sets the guard that checks the intended output values on each cycle.
+    pub fn set_output_guard(&mut self, guard: Option<Box<dyn OutputGuard>>) {
+        self.guard = guard;
+    }
 
-            // Communication modules
-            UR20_1COM_232_485_422 => 10,
+    /// Adds user logic that runs on each cycle.
+    ///
+    /// The tasks run in the order they were added,
+    /// after the inputs were decoded and before the outputs are encoded.
+    /// Outputs of communication modules can't be set by a task.
+    pub fn add_task(&mut self, task: Box<dyn CyclicTask>) {
+        self.tasks.push(task);
+    }
 
-            // Not yet supported
-            _ => {
-                panic!("{:?} is not supported", self);
+    /// Removes all tasks.
+    pub fn clear_tasks(&mut self) {
+        self.tasks.clear();
+    }
+
+    /// Sets the output channel that signals each cycle to downstream monitors.
+    ///
+    /// The channel must be an output of an owned module
+    /// that is not a communication module.
+    pub fn set_heartbeat(&mut self, heartbeat: Option<Heartbeat>) -> Result<()> {
+        if let Some(ref hb) = heartbeat {
+            let addr = &hb.address;
+            if !self.is_valid_addr(addr) || !self.is_owned(addr.module) {
+                return Err(Error::Address);
+            }
+            let m = &self.modules[addr.module];
+            if m.process_output_byte_count() == 0 || self.processors.contains_key(&addr.module) {
+                return Err(Error::NotAnOutput {
+                    address: *addr,
+                    module_type: m.module_type(),
+                });
             }
         }
+        self.heartbeat = heartbeat;
+        Ok(())
     }
-}
-
-/// Calculate the parameter addresses and the number of registers by a given list of modules.
-pub fn param_addresses_and_register_counts(modules: &[ModuleType]) -> Vec<(u16, u16)> {
-    modules
-        .iter()
-        .enumerate()
-        .map(|(idx, m)| {
-            (
-                ADDR_MODULE_PARAMETERS + (idx * 256) as u16,
-                m.param_register_count(),
-            )
-        })
-        .collect()
-}
 
-/// Converts the raw coupler register data into a list of module types.
-pub fn module_list_from_registers(registers: &[u16]) -> Result<Vec<ModuleType>> {
-    if registers.is_empty() || registers.len() % 2 != 0 {
-        return Err(Error::RegisterCount);
+    /// Holds the outputs for the given number of cycles.
+    ///
+    /// During that time `next()` returns the output image as it was read
+    /// from the coupler: pending writes stay queued and the values of
+    /// tasks and the heartbeat are discarded.
+    /// This prevents spurious actuator motion while the station and
+    /// the application are still settling.
+    pub fn set_startup_hold_off(&mut self, cycles: usize) {
+        self.hold_off = cycles;
     }
-    let mut list = vec![];
-    for i in 0..registers.len() / 2 {
-        let idx = i as usize;
-        let hi = u32::from(registers[idx * 2]);
-        let lo = u32::from(registers[idx * 2 + 1]);
-        let id = (hi << 16) + lo;
-        let m = ModuleType::try_from_u32(id)?;
-        list.push(m);
+
+    /// Ends the start-up hold-off before all cycles have passed.
+    pub fn release_hold_off(&mut self) {
+        self.hold_off = 0;
     }
-    Ok(list)
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    /// Returns the start-up state.
+    pub fn init_state(&self) -> InitState {
+        if self.last_cycle.is_none() {
+            InitState::Uninitialized
+        } else if self.hold_off > 0 {
+            InitState::HoldOff {
+                remaining: self.hold_off,
+            }
+        } else {
+            InitState::Ready
+        }
+    }
 
-    #[test]
-    fn test_offsets_of_process_data() {
-        assert_eq!(offsets_of_process_data(&vec![]), vec![]);
-        assert_eq!(
-            offsets_of_process_data(&vec![0xFFFF, 0x0000, 0x8000, 0x0040, 0x8050, 0xFFFF]),
-            vec![
-                ModuleOffset {
-                    output: None,
-                    input: Some(0x0000),
-                },
-                ModuleOffset {
-                    output: Some(0x8000),
-                    input: Some(0x0040),
-                },
-                ModuleOffset {
-                    output: Some(0x8050),
-                    input: None,
-                },
-            ]
-        );
+    /// Returns `true` if `next()` was not called within
+    /// the maximum cycle time of the heartbeat.
+    pub fn heartbeat_stalled(&self) -> bool {
+        match (&self.heartbeat, self.last_cycle) {
+            (Some(hb), Some(last)) => self.clock.now().duration_since(last) > hb.max_cycle_time,
+            _ => false,
+        }
     }
 
-    #[test]
-    fn test_to_regsiter_address() {
-        assert_eq!(to_register_address(0x80AB), (0x080A, 11));
+    /// Sets the field calibration of an analog channel.
+    ///
+    /// Input and output values of the channel are calibrated
+    /// (`calibrated = raw * gain + offset`) and values passed to
+    /// `set_output` are expected to be calibrated values.
+    ///
+    /// Only analog channels can be calibrated,
+    /// other channels result in `Error::ChannelType`.
+    pub fn set_calibration(&mut self, addr: &Address, offset: f32, gain: f32) -> Result<()> {
+        self.check_analog_channel(addr)?;
+        self.calibrations
+            .insert(*addr, Calibration::new(offset, gain)?);
+        Ok(())
     }
 
-    #[test]
-    fn test_to_bit_address() {
-        assert_eq!(to_bit_address(0x080A, 11), 0x080AB);
+    /// Removes the field calibration of a channel.
+    pub fn remove_calibration(&mut self, addr: &Address) -> Option<Calibration> {
+        self.calibrations.remove(addr)
     }
 
-    #[test]
-    fn test_process_input_data() {
-        let m0 = super::ur20_4ao_ui_16::Mod::default();
-        let mut m1 = super::ur20_4ai_rtd_diag::Mod::default();
-        let m2 = super::ur20_4di_p::Mod::default();
-        let m3 = super::ur20_4di_p::Mod::default();
+    /// Returns all field calibrations (e.g. to save them).
+    pub fn calibrations(&self) -> Vec<ChannelCalibration> {
+        let mut list: Vec<_> = self
+            .calibrations
+            .iter()
+            .map(|(address, calibration)| ChannelCalibration {
+                address: *address,
+                calibration: *calibration,
+            })
+            .collect();
+        list.sort_by_key(|c| (c.address.module, c.address.channel));
+        list
+    }
 
-        #[rustfmt::skip]
-        let data = &[
-            0,33,0,0,             // UR20-4AI-P
-            0b0000_0001_0000_0010 // UR20-4DI-P + UR20-4DI-P
-        ];
+    /// Restores previously saved field calibrations.
+    pub fn restore_calibrations(&mut self, list: &[ChannelCalibration]) -> Result<()> {
+        let mut calibrations = HashMap::new();
+        for c in list {
+            self.check_analog_channel(&c.address)?;
+            let cal = Calibration::new(c.calibration.offset, c.calibration.gain)?;
+            calibrations.insert(c.address, cal);
+        }
+        self.calibrations = calibrations;
+        Ok(())
+    }
 
-        m1.ch_params[1].measurement_range = RtdRange::PT100;
+    fn check_analog_channel(&self, addr: &Address) -> Result<()> {
+        if !self.is_valid_addr(addr) {
+            return Err(Error::Address);
+        }
+        match ModuleCategory::from(self.modules[addr.module].module_type()) {
+            ModuleCategory::AI | ModuleCategory::AO | ModuleCategory::RTD | ModuleCategory::TC => {
+                Ok(())
+            }
+            _ => Err(Error::ChannelType),
+        }
+    }
 
-        let mod0: &dyn ProcessModbusTcpData = &m0;
-        let mod1: &dyn ProcessModbusTcpData = &m1;
-        let mod2: &dyn ProcessModbusTcpData = &m2;
-        let mod3: &dyn ProcessModbusTcpData = &m3;
+    /// Interprets the values of all analog current and voltage channels
+    /// in the given data format regardless of their module parameters
+    /// (`None` removes the override).
+    ///
+    /// The `exceptions` keep their parameterized data format.
+    /// Input values are converted before and output values after
+    /// the field calibration.
+    pub fn set_data_format_override(
+        &mut self,
+        format: Option<DataFormat>,
+        exceptions: &[Address],
+    ) -> Result<()> {
+        if exceptions.iter().any(|a| !self.is_valid_addr(a)) {
+            return Err(Error::Address);
+        }
+        self.data_format_override = format;
+        self.data_format_exceptions = exceptions.iter().cloned().collect();
+        Ok(())
+    }
 
-        let addr_out_0 = to_bit_address(ADDR_PACKED_PROCESS_OUTPUT_DATA, 0);
-        let addr_in_1 = to_bit_address(ADDR_PACKED_PROCESS_INPUT_DATA, 0);
-        let addr_in_2 = to_bit_address(ADDR_PACKED_PROCESS_INPUT_DATA + 4, 0);
-        let addr_in_3 = to_bit_address(ADDR_PACKED_PROCESS_INPUT_DATA + 4, 8);
+    /// Returns the data format override (see `set_data_format_override`).
+    pub fn data_format_override(&self) -> Option<&DataFormat> {
+        self.data_format_override.as_ref()
+    }
 
-        let o0 = ModuleOffset {
-            input: None,
-            output: Some(addr_out_0),
-        };
-        let o1 = ModuleOffset {
-            input: Some(addr_in_1),
-            output: None,
+    /// Scalings of the analog channels that are affected
+    /// by the data format override.
+    fn overridden_scalings(&self) -> Vec<(Address, AnalogScaling, DataFormat)> {
+        let format = match self.data_format_override {
+            Some(ref f) => f,
+            None => return vec![],
         };
-        let o2 = ModuleOffset {
-            input: Some(addr_in_2),
-            output: None,
-        };
-        let o3 = ModuleOffset {
-            input: Some(addr_in_3),
-            output: None,
-        };
-
-        let modules = vec![(mod0, &o0), (mod1, &o1), (mod2, &o2), (mod3, &o3)];
+        let mut scalings = vec![];
+        for (module, m) in self.modules.iter().enumerate() {
+            for (channel, s) in m.analog_scalings().into_iter().enumerate() {
+                let addr = Address { module, channel };
+                if let Some(s) = s {
+                    if s.format != *format && !self.data_format_exceptions.contains(&addr) {
+                        scalings.push((addr, s, format.clone()));
+                    }
+                }
+            }
+        }
+        scalings
+    }
 
-        let res = process_input_data(&modules, data).unwrap();
-        assert_eq!(res.len(), 4);
-        assert_eq!(res[0].len(), 4);
-        assert_eq!(res[1].len(), 4);
-        assert_eq!(res[2].len(), 4);
-        assert_eq!(res[3].len(), 4);
-        assert_eq!(res[1][1], ChannelValue::Decimal32(3.3));
-        assert_eq!(res[2][1], ChannelValue::Bit(true));
-        assert_eq!(res[3][0], ChannelValue::Bit(true));
+    /// Adds a group of redundant inputs that is evaluated on each cycle.
+    ///
+    /// All channels of the group have to be digital inputs.
+    ///
+    /// Returns the index of the group within the voting results.
+    pub fn add_voting_group(&mut self, group: VotingGroup) -> Result<usize> {
+        group.validate()?;
+        if group.inputs.iter().any(|a| !self.is_valid_addr(a)) {
+            return Err(Error::Address);
+        }
+        if group.inputs.iter().any(|a| {
+            ModuleCategory::from(self.modules[a.module].module_type()) != ModuleCategory::DI
+        }) {
+            return Err(Error::ChannelType);
+        }
+        self.voting_groups.push(group);
+        Ok(self.voting_groups.len() - 1)
     }
 
-    #[test]
-    fn test_process_input_data_with_invalid_offset() {
-        let m0 = super::ur20_4ai_rtd_diag::Mod::default();
-        let data = &[0, 33, 0, 0];
-        let mod0: &dyn ProcessModbusTcpData = &m0;
-        let bit = 3; // should not work
-        let addr_in_0 = to_bit_address(ADDR_PACKED_PROCESS_INPUT_DATA, bit);
-        let o0 = ModuleOffset {
-            input: Some(addr_in_0),
-            output: None,
-        };
-        let modules = vec![(mod0, &o0)];
-        assert!(process_input_data(&modules, data).is_err());
+    /// Returns the voting results of the last cycle.
+    pub fn voting_results(&self) -> &[VotingResult] {
+        &self.voting_results
     }
 
-    #[test]
-    fn test_process_input_data_with_invalid_data() {
-        let m0 = super::ur20_4ai_rtd_diag::Mod::default();
-        let m1 = super::ur20_4ai_rtd_diag::Mod::default();
-        let data = &[0, 33, 0, 0];
-        let mod0: &dyn ProcessModbusTcpData = &m0;
-        let mod1: &dyn ProcessModbusTcpData = &m1;
-        let addr_in_0 = to_bit_address(ADDR_PACKED_PROCESS_INPUT_DATA, 0);
-        let addr_in_1 = to_bit_address(ADDR_PACKED_PROCESS_INPUT_DATA + 4, 0);
-        let o0 = ModuleOffset {
-            input: Some(addr_in_0),
-            output: None,
-        };
-        let o1 = ModuleOffset {
-            input: Some(addr_in_1),
-            output: None,
-        };
-        let modules = vec![(mod0, &o0), (mod1, &o1)];
-        assert!(process_input_data(&modules, data).is_err());
+    /// Adds a named group of digital channels that represents a number.
+    ///
+    /// The channels either belong to digital inputs
+    /// or to digital and relay outputs.
+    pub fn add_bit_group(&mut self, group: BitGroup) -> Result<()> {
+        group.validate()?;
+        if group.channels.iter().any(|a| !self.is_valid_addr(a)) {
+            return Err(Error::Address);
+        }
+        if self.bit_groups.iter().any(|g| g.name == group.name) {
+            return Err(Error::ChannelParameter);
+        }
+        let categories: HashSet<_> = group
+            .channels
+            .iter()
+            .map(
+                |a| match ModuleCategory::from(self.modules[a.module].module_type()) {
+                    ModuleCategory::RO => ModuleCategory::DO,
+                    c => c,
+                },
+            )
+            .collect();
+        match categories.into_iter().collect::<Vec<_>>()[..] {
+            [ModuleCategory::DI] | [ModuleCategory::DO] => {}
+            _ => return Err(Error::ChannelType),
+        }
+        self.bit_groups.push(group);
+        Ok(())
     }
 
-    #[test]
-    fn test_process_output_data() {
-        let mut m0 = super::ur20_4ao_ui_16::Mod::default();
-        let m1 = super::ur20_4ai_rtd_diag::Mod::default();
-        let m2 = super::ur20_4do_p::Mod::default();
-        let m3 = super::ur20_4do_p::Mod::default();
+    fn bit_group(&self, name: &str) -> Option<&BitGroup> {
+        self.bit_groups.iter().find(|g| g.name == name)
+    }
 
-        #[rustfmt::skip]
-        let data = &[
-            0,0x3600,0,0,         // UR20-4AO-P
-            0b0000_0001_0000_0010 // UR20-4DO-P + UR20-4DO-P
-        ];
+    /// Returns the number of a group.
+    ///
+    /// Input groups are decoded from the inputs of the last cycle,
+    /// output groups from the current output state.
+    pub fn bit_group_value(&self, name: &str) -> Option<u32> {
+        let g = self.bit_group(name)?;
+        let input = g.channels.iter().all(|a| {
+            ModuleCategory::from(self.modules[a.module].module_type()) == ModuleCategory::DI
+        });
+        g.decode(if input {
+            &self.in_values
+        } else {
+            &self.out_values
+        })
+    }
 
-        m0.ch_params[1].output_range = AnalogUIRange::VMinus5To5;
+    /// Sets the outputs of a group on the next cycle.
+    pub fn set_bit_group_value(&mut self, name: &str, value: u32) -> Result<()> {
+        let states = self.bit_group(name).ok_or(Error::Address)?.encode(value)?;
+        if states.iter().any(|(a, _)| !self.is_owned(a.module)) {
+            return Err(Error::Address);
+        }
+        for (addr, state) in states {
+            self.set_output(&addr, ChannelValue::Bit(state))?;
+        }
+        Ok(())
+    }
 
-        let mod0: &dyn ProcessModbusTcpData = &m0;
-        let mod1: &dyn ProcessModbusTcpData = &m1;
-        let mod2: &dyn ProcessModbusTcpData = &m2;
-        let mod3: &dyn ProcessModbusTcpData = &m3;
+    /// Decodes the register content of `ADDR_MODULE_DIAGNOSIS`
+    /// (one block per module, see `diagnosis_addresses_and_register_counts`).
+    ///
+    /// The channel error causes of modules with diagnostic settings
+    /// are decoded by the module (see `Diagnostics`).
+    pub fn update_diagnostics(&mut self, data: &[Vec<u16>]) -> Result<()> {
+        if data.len() != self.modules.len() {
+            return Err(Error::RegisterCount);
+        }
+        let diagnosis: Vec<_> = self
+            .modules
+            .iter()
+            .zip(data)
+            .map(|(m, d)| {
+                let mut diag =
+                    ModuleDiagnosis::from_raw_data(&u16_to_u8(d), m.module_type().channel_count())?;
+                if let Some(x) = m.diagnostics() {
+                    diag.channels = x.process_diagnostic_data(d)?;
+                }
+                Ok(diag)
+            })
+            .collect::<Result<_>>()?;
+        let now = self.clock.now();
+        for (nr, d) in diagnosis.iter().enumerate() {
+            let changed = match self.diagnosis.get(nr) {
+                Some(prev) => prev != d,
+                None => !d.is_ok(),
+            };
+            if changed {
+                self.diagnosis_throttle.push(nr, d.clone(), now);
+            }
+        }
+        self.diagnosis = diagnosis;
+        self.flush_diagnosis_events(now);
+        self.publish();
+        Ok(())
+    }
 
-        let addr_out_0 = to_bit_address(ADDR_PACKED_PROCESS_OUTPUT_DATA, 0);
-        let addr_in_1 = to_bit_address(ADDR_PACKED_PROCESS_INPUT_DATA, 0);
-        let addr_out_2 = to_bit_address(ADDR_PACKED_PROCESS_OUTPUT_DATA + 4, 0);
-        let addr_out_3 = to_bit_address(ADDR_PACKED_PROCESS_OUTPUT_DATA + 4, 8);
+    /// Sets the minimum time between two diagnosis events of a module.
+    ///
+    /// Changes within that time are coalesced into one event.
+    /// By default each change results in an event.
+    pub fn set_diagnosis_event_interval(&mut self, interval: Option<Duration>) {
+        self.diagnosis_throttle.set_interval(interval);
+    }
 
-        let o0 = ModuleOffset {
-            input: None,
-            output: Some(addr_out_0),
-        };
-        let o1 = ModuleOffset {
-            input: Some(addr_in_1),
-            output: None,
-        };
-        let o2 = ModuleOffset {
-            input: None,
-            output: Some(addr_out_2),
-        };
-        let o3 = ModuleOffset {
-            input: None,
-            output: Some(addr_out_3),
+    /// Enables or disables the measurement of the processing time
+    /// of each module (see `timing_report`).
+    ///
+    /// Enabling resets the measured values.
+    pub fn set_timing(&mut self, enabled: bool) {
+        self.timings = if enabled {
+            Some(
+                self.modules
+                    .iter()
+                    .enumerate()
+                    .map(|(nr, m)| ModuleTiming::new(nr, m.module_type()))
+                    .collect(),
+            )
+        } else {
+            None
         };
+    }
 
-        let modules = vec![(mod0, &o0), (mod1, &o1), (mod2, &o2), (mod3, &o3)];
+    /// Returns the measured processing times
+    /// with the slowest modules (longest cycle) first.
+    ///
+    /// The report is empty if the measurement is disabled.
+    pub fn timing_report(&self) -> Vec<ModuleTiming> {
+        let mut report = self.timings.clone().unwrap_or_default();
+        report.sort_by_key(|t| (cmp::Reverse(t.max), t.module));
+        report
+    }
 
-        let res = process_output_data(&modules, data).unwrap();
-        assert_eq!(res.len(), 4);
-        assert_eq!(res[0].len(), 4);
-        assert_eq!(res[1].len(), 4);
-        assert_eq!(res[2].len(), 4);
-        assert_eq!(res[3].len(), 4);
-        assert_eq!(res[0][1], ChannelValue::Decimal32(2.5));
-        assert_eq!(res[1][0], ChannelValue::None);
-        assert_eq!(res[2][1], ChannelValue::Bit(true));
-        assert_eq!(res[3][0], ChannelValue::Bit(true));
+    /// Enables or disables the verification of the outputs.
+    ///
+    /// On each cycle the echoed process output data is compared
+    /// with the output values of the previous cycle channel by channel
+    /// (e.g. to detect active substitute values after a fieldbus fault).
+    /// Modules that are not owned by this coupler are skipped.
+    pub fn set_output_verification(&mut self, enabled: bool) {
+        self.output_verification = enabled;
+        if !enabled {
+            self.output_mismatches.clear();
+        }
     }
 
-    #[test]
-    fn test_process_output_data_with_invalid_offset() {
-        let m0 = super::ur20_4ao_ui_16::Mod::default();
-        let data = &[0, 33, 0, 0];
-        let mod0: &dyn ProcessModbusTcpData = &m0;
-        let bit = 3; // should not work
-        let addr_out_0 = to_bit_address(ADDR_PACKED_PROCESS_OUTPUT_DATA, bit);
-        let o0 = ModuleOffset {
-            input: None,
-            output: Some(addr_out_0),
-        };
-        let modules = vec![(mod0, &o0)];
-        assert!(process_output_data(&modules, data).is_err());
+    /// Output channels that differed from the commanded values in the
+    /// last cycle (see `set_output_verification`).
+    pub fn output_mismatches(&self) -> &[OutputMismatch] {
+        &self.output_mismatches
     }
 
-    #[test]
-    fn test_process_output_data_with_invalid_data() {
-        let m0 = super::ur20_4ao_ui_16::Mod::default();
-        let m1 = super::ur20_4ao_ui_16::Mod::default();
-        let data = &[0, 33, 0, 0];
-        let mod0: &dyn ProcessModbusTcpData = &m0;
-        let mod1: &dyn ProcessModbusTcpData = &m1;
-        let addr_out_0 = to_bit_address(ADDR_PACKED_PROCESS_OUTPUT_DATA, 0);
-        let addr_out_1 = to_bit_address(ADDR_PACKED_PROCESS_OUTPUT_DATA + 4, 0);
-        let o0 = ModuleOffset {
-            input: None,
-            output: Some(addr_out_0),
-        };
-        let o1 = ModuleOffset {
-            input: None,
-            output: Some(addr_out_1),
-        };
-        let modules = vec![(mod0, &o0), (mod1, &o1)];
-        assert!(process_output_data(&modules, data).is_err());
+    /// Enables or disables events of changed input values.
+    ///
+    /// The inputs of each cycle are compared with the inputs of
+    /// the previous cycle (including virtual channels).
+    /// Digital inputs result in `Rising` and `Falling` events,
+    /// all other channels in `ValueChanged` events.
+    pub fn set_input_events(&mut self, enabled: bool) {
+        self.input_events = enabled;
     }
 
-    #[test]
-    fn test_process_output_values_with_invalid_len() {
-        let m0 = super::ur20_4ao_ui_16::Mod::default();
-        let m1 = super::ur20_4ai_rtd_diag::Mod::default();
+    fn flush_diagnosis_events(&mut self, now: Instant) {
+        self.events.extend(
+            self.diagnosis_throttle
+                .flush(now)
+                .into_iter()
+                .map(CouplerEvent::Diagnosis),
+        );
+    }
 
-        let values = vec![vec![
-            ChannelValue::Decimal32(15.0),
-            ChannelValue::Decimal32(20.0),
-            ChannelValue::Decimal32(20.0),
-            ChannelValue::Decimal32(10.0),
-        ]];
+    /// Returns the last decoded module diagnosis.
+    pub fn diagnosis(&self) -> &[ModuleDiagnosis] {
+        &self.diagnosis
+    }
 
-        let mod0: &dyn ProcessModbusTcpData = &m0;
-        let mod1: &dyn ProcessModbusTcpData = &m1;
+    /// Number of registers of the packed process input image.
+    pub fn process_input_len(&self) -> usize {
+        image_len(&self.infos(), true)
+    }
 
-        let addr_out_0 = to_bit_address(ADDR_PACKED_PROCESS_OUTPUT_DATA, 0);
-        let addr_in_1 = to_bit_address(ADDR_PACKED_PROCESS_INPUT_DATA, 0);
+    /// Number of registers of the packed process output image.
+    ///
+    /// This is the number of registers to read back
+    /// from `ADDR_PACKED_PROCESS_OUTPUT_DATA`.
+    pub fn process_output_len(&self) -> usize {
+        image_len(&self.infos(), false)
+    }
 
-        let o0 = ModuleOffset {
-            input: None,
-            output: Some(addr_out_0),
-        };
-        let o1 = ModuleOffset {
-            input: Some(addr_in_1),
-            output: None,
-        };
+    /// Updates the output state by the register content of
+    /// `ADDR_PACKED_PROCESS_OUTPUT_DATA` that was read back from the coupler.
+    ///
+    /// Modules that share a register are reconstructed from
+    /// the low or the high byte of that register.
+    pub fn ingest_output_readback(&mut self, data: &[u16]) -> Result<()> {
+        let mut values = decode_output_image(&self.checked_infos()?, data)?;
+        for m_nr in self.processors.keys() {
+            values[*m_nr][0] = ChannelValue::None;
+        }
+        self.invert_channels(&mut values);
+        self.out_values = values;
+        self.output_image = data.to_vec();
+        self.output_readback = data.to_vec();
+        self.publish();
+        Ok(())
+    }
 
-        let modules = vec![(mod0, &o0), (mod1, &o1)];
+    /// Maps the packed process input image into the values of all modules.
+    ///
+    /// Unlike `next` this does not change the state of the coupler.
+    pub fn process_input_data(&self, data: &[u16]) -> Result<Vec<Vec<ChannelValue>>> {
+        decode_input_image(&self.checked_infos()?, data)
+    }
 
-        assert!(process_output_values(&modules, &values).is_err());
+    /// Maps the packed process output image into the values of all modules.
+    pub fn process_output_data(&self, data: &[u16]) -> Result<Vec<Vec<ChannelValue>>> {
+        decode_output_image(&self.checked_infos()?, data)
     }
 
-    #[test]
-    fn test_process_output_values_with_invalid_offset_a() {
-        let m0 = super::ur20_4ao_ui_16::Mod::default();
-        let m1 = super::ur20_4ai_rtd_diag::Mod::default();
+    /// Maps the values of all modules into the packed process output image.
+    pub fn process_output_values(&self, values: &[Vec<ChannelValue>]) -> Result<Vec<u16>> {
+        encode_output_image(&self.checked_infos()?, values)
+    }
 
-        let values = vec![
-            vec![
-                ChannelValue::Decimal32(15.0),
-                ChannelValue::Decimal32(20.0),
-                ChannelValue::Decimal32(20.0),
-                ChannelValue::Decimal32(10.0),
-            ],
-            vec![],
-        ];
+    /// Compares two process images of the station channel by channel.
+    ///
+    /// Registers that do not belong to a module are compared word by word.
+    /// This helps to analyze recordings and to verify that
+    /// changes of the encoding don't change the output data.
+    pub fn diff_images(&self, a: &ProcessImage, b: &ProcessImage) -> Result<Vec<ChannelDiff>> {
+        let infos = self.checked_infos()?;
+        let mut diffs = diff_values(
+            ImageArea::Input,
+            &decode_input_image(&infos, &a.input)?,
+            &decode_input_image(&infos, &b.input)?,
+        );
+        diffs.extend(diff_values(
+            ImageArea::Output,
+            &decode_output_image(&infos, &a.output)?,
+            &decode_output_image(&infos, &b.output)?,
+        ));
+        for (area, a, b) in &[
+            (ImageArea::Input, &a.input, &b.input),
+            (ImageArea::Output, &a.output, &b.output),
+        ] {
+            let words = module_words(&infos, *area);
+            diffs.extend(diff_words(*area, a, b).into_iter().filter(|d| match d {
+                ChannelDiff::Word { index, .. } => !words.contains(index),
+                _ => true,
+            }));
+        }
+        Ok(diffs)
+    }
 
-        let mod0: &dyn ProcessModbusTcpData = &m0;
-        let mod1: &dyn ProcessModbusTcpData = &m1;
+    fn checked_infos(&self) -> Result<Vec<(&dyn ProcessModbusTcpData, &ModuleOffset)>> {
+        if !self.has_offsets() {
+            return Err(Error::ModuleOffset);
+        }
+        Ok(self.infos())
+    }
 
-        let addr_out_0 = to_bit_address(ADDR_PACKED_PROCESS_OUTPUT_DATA + 10, 0);
-        let addr_in_1 = to_bit_address(ADDR_PACKED_PROCESS_INPUT_DATA, 0);
+    fn infos(&self) -> Vec<(&dyn ProcessModbusTcpData, &ModuleOffset)> {
+        self.modules
+            .iter()
+            .zip(&self.offsets)
+            .map(|(m, o)| (&**m, o))
+            .collect()
+    }
 
-        let o0 = ModuleOffset {
-            input: None,
-            output: Some(addr_out_0),
-        };
-        let o1 = ModuleOffset {
-            input: Some(addr_in_1),
-            output: None,
-        };
+    /// Returns a read-only handle to the coupler state
+    /// that is updated on each cycle.
+    pub fn observer(&self) -> Observer {
+        let o = self.observer.clone();
+        self.publish();
+        o
+    }
 
-        let modules = vec![(mod0, &o0), (mod1, &o1)];
-        assert!(process_output_values(&modules, &values).is_err());
+    fn publish(&self) {
+        if self.observer.is_observed() {
+            self.observer.publish(Snapshot {
+                inputs: self.in_values.clone(),
+                outputs: self.out_values.clone(),
+                diagnosis: self.diagnosis.clone(),
+            });
+        }
     }
 
-    #[test]
-    fn test_process_output_values_with_invalid_offset_b() {
-        let m0 = super::ur20_4ao_ui_16::Mod::default();
-        let m1 = super::ur20_4ai_rtd_diag::Mod::default();
-        let m2 = super::ur20_4do_p::Mod::default();
+    /// Returns the quality of the values of a module.
+    pub fn quality(&self, module_nr: usize) -> Option<Quality> {
+        self.qualities.get(module_nr).cloned()
+    }
 
-        let values = vec![
-            vec![
-                ChannelValue::Decimal32(15.0),
-                ChannelValue::Decimal32(20.0),
-                ChannelValue::Decimal32(20.0),
-                ChannelValue::Decimal32(10.0),
+    /// Records the result of the last fieldbus transfer.
+    ///
+    /// Drivers pass the error of a failed transfer
+    /// and `None` after a successful one.
+    pub fn set_driver_error(&mut self, error: Option<String>) {
+        self.driver_error = error;
+    }
+
+    /// Sets the register content of `ADDR_COUPLER_STATUS`.
+    pub fn set_status(&mut self, register: u16) {
+        self.status = Some(CouplerStatus::from_register(register));
+    }
+
+    /// Returns the last coupler status
+    /// (`None` if no status was set yet).
+    pub fn status(&self) -> Option<CouplerStatus> {
+        self.status
+    }
+
+    /// Summarizes the driver state, the module qualities
+    /// and the module diagnosis into a single value.
+    pub fn health(&self) -> StationHealth {
+        if let Some(ref e) = self.driver_error {
+            return StationHealth::Failed {
+                reason: format!("driver error: {}", e),
+            };
+        }
+        if self.last_cycle.is_none() {
+            return StationHealth::Failed {
+                reason: "no cycle was processed yet".into(),
+            };
+        }
+        if !self.qualities.is_empty() && self.qualities.iter().all(|q| *q == Quality::Unavailable) {
+            return StationHealth::Failed {
+                reason: "no module data is available".into(),
+            };
+        }
+        let mut details = vec![];
+        if self.heartbeat_stalled() {
+            details.push("heartbeat stalled".to_string());
+        }
+        for (nr, q) in self.qualities.iter().enumerate() {
+            if *q == Quality::Unavailable {
+                details.push(format!("module {}: data unavailable", nr));
+            }
+        }
+        for (nr, d) in self.diagnosis.iter().enumerate() {
+            if !d.is_ok() {
+                details.push(format!("module {}: diagnosis reports an error", nr));
+            }
+        }
+        if details.is_empty() {
+            StationHealth::Ok
+        } else {
+            StationHealth::Degraded { details }
+        }
+    }
+
+    #[cfg(feature = "tcp")]
+    pub(crate) fn push_event(&mut self, ev: CouplerEvent) {
+        self.events.push(ev);
+    }
+
+    /// Returns and removes the events of the past cycles.
+    ///
+    /// Coalesced diagnosis events are included
+    /// as soon as their interval has elapsed.
+    pub fn take_events(&mut self) -> Vec<CouplerEvent> {
+        let now = self.clock.now();
+        self.flush_diagnosis_events(now);
+        self.events.drain(..).collect()
+    }
+
+    pub fn next(&mut self, process_input: &[u16], process_output: &[u16]) -> Result<Vec<u16>> {
+        if !self.has_offsets() {
+            return Err(Error::ModuleOffset);
+        }
+        if self.owned_modules.is_some() && process_output.len() < self.process_output_len() {
+            return Err(Error::BufferLength);
+        }
+        self.output_readback = process_output.to_vec();
+        let mut decode_times = self.timings.as_ref().map(|_| vec![]);
+        let (in_values, out_values) =
+            self.decode_images(process_input, process_output, decode_times.as_mut())?;
+        let scalings = self.overridden_scalings();
+        let previous_inputs = self.update_values(in_values, out_values, &scalings);
+        let now = self.clock.now();
+        self.check_heartbeat(now);
+        let hold = self.hold_off > 0;
+        let mut next_out_values = self.apply_writes(now, hold)?;
+        self.evaluate_inputs(&previous_inputs);
+        self.scan_tasks(&mut next_out_values);
+        self.check_outputs(&mut next_out_values, hold);
+        self.revert_output_values(&mut next_out_values, &scalings);
+        self.publish();
+        let mut output = self.encode_image(&next_out_values, decode_times)?;
+        self.exchange_mailboxes(process_input, &mut output)?;
+        self.keep_foreign_outputs(process_output, &mut output);
+        self.commanded_output = Some(output.clone());
+        self.output_image = output.clone();
+        Ok(output)
+    }
+
+    /// Decodes the process images of a cycle,
+    /// verifies the outputs and updates the qualities.
+    fn decode_images(
+        &mut self,
+        process_input: &[u16],
+        process_output: &[u16],
+        durations: Option<&mut Vec<Duration>>,
+    ) -> Result<(ModuleValues, ModuleValues)> {
+        let infos: Vec<_> = self
+            .modules
+            .iter()
+            .zip(&self.offsets)
+            .map(|(m, o)| (&**m, o))
+            .collect();
+        let mut in_times = durations.as_ref().map(|_| vec![]);
+        let mut out_times = durations.as_ref().map(|_| vec![]);
+        let (in_values, missing_in) = process_truncated_data(
+            &infos,
+            process_input,
+            true,
+            Some(&mut self.input_cache),
+            in_times.as_mut(),
+        )?;
+        let (out_values, missing_out) =
+            process_truncated_data(&infos, process_output, false, None, out_times.as_mut())?;
+        if let (Some(d), Some(i), Some(o)) = (durations, in_times, out_times) {
+            d.extend(i.into_iter().zip(o).map(|(i, o)| i + o));
+        }
+        self.verify_outputs(process_output, &out_values, &missing_out)?;
+        self.update_qualities(process_input, process_output, missing_in, missing_out);
+        Ok((in_values, out_values))
+    }
+
+    /// Compares the output readback with the output image of the last cycle.
+    fn verify_outputs(
+        &mut self,
+        process_output: &[u16],
+        out_values: &[Vec<ChannelValue>],
+        missing_out: &[usize],
+    ) -> Result<()> {
+        self.output_mismatches.clear();
+        let commanded = match self.commanded_output {
+            Some(ref c) if c.len() == process_output.len() => {
+                process_truncated_data(&self.infos(), c, false, None, None)?.0
+            }
+            _ => return Ok(()),
+        };
+        let modules: Vec<_> = (0..commanded.len())
+            .filter(|nr| {
+                self.is_owned(*nr) && !missing_out.contains(nr) && commanded[*nr] != out_values[*nr]
+            })
+            .collect();
+        if self.output_verification {
+            for &module in &modules {
+                let pairs = commanded[module].iter().zip(&out_values[module]);
+                for (channel, (c, a)) in pairs.enumerate() {
+                    if c != a {
+                        self.output_mismatches.push(OutputMismatch {
+                            address: Address { module, channel },
+                            commanded: c.clone(),
+                            actual: a.clone(),
+                        });
+                    }
+                }
+            }
+        }
+        if !modules.is_empty() {
+            self.events.push(CouplerEvent::OutputMismatch { modules });
+        }
+        Ok(())
+    }
+
+    fn update_qualities(
+        &mut self,
+        process_input: &[u16],
+        process_output: &[u16],
+        missing_in: Vec<usize>,
+        missing_out: Vec<usize>,
+    ) {
+        let qualities: Vec<_> = (0..self.modules.len())
+            .map(|nr| {
+                if missing_in.contains(&nr) || missing_out.contains(&nr) {
+                    Quality::Unavailable
+                } else {
+                    Quality::Good
+                }
+            })
+            .collect();
+        if qualities == self.qualities {
+            return;
+        }
+        for (input, missing, data) in [
+            (true, missing_in, process_input),
+            (false, missing_out, process_output),
+        ] {
+            if !missing.is_empty() {
+                let expected = image_len(&self.infos(), input);
+                self.events.push(CouplerEvent::ImageTruncated {
+                    input,
+                    expected,
+                    actual: data.len(),
+                    modules: missing,
+                });
+            }
+        }
+        self.qualities = qualities;
+    }
+
+    /// Applies the data format overrides, calibrations and inversions
+    /// to the decoded values and returns the inputs of the previous cycle.
+    fn update_values(
+        &mut self,
+        mut in_values: ModuleValues,
+        mut out_values: ModuleValues,
+        scalings: &[(Address, AnalogScaling, DataFormat)],
+    ) -> ModuleValues {
+        for (addr, s, format) in scalings {
+            for values in [&mut in_values, &mut out_values] {
+                if let Some(ChannelValue::Decimal32(v)) = values
+                    .get_mut(addr.module)
+                    .and_then(|m| m.get_mut(addr.channel))
+                {
+                    *v = s.convert(*v, format);
+                }
+            }
+        }
+        for (addr, c) in &self.calibrations {
+            for values in [&mut in_values, &mut out_values] {
+                if let Some(v) = values
+                    .get_mut(addr.module)
+                    .and_then(|m| m.get_mut(addr.channel))
+                {
+                    *v = c.apply(v);
+                }
+            }
+        }
+        self.invert_channels(&mut in_values);
+        self.invert_channels(&mut out_values);
+        self.out_values = out_values;
+        mem::replace(&mut self.in_values, in_values)
+    }
+
+    fn check_heartbeat(&mut self, now: Instant) {
+        if let (Some(hb), Some(last)) = (&self.heartbeat, self.last_cycle) {
+            let elapsed = now.duration_since(last);
+            if elapsed > hb.max_cycle_time {
+                self.events.push(CouplerEvent::HeartbeatStalled(elapsed));
+            }
+        }
+        self.last_cycle = Some(now);
+    }
+
+    /// Runs the message processors of the communication modules
+    /// and applies the pending writes (unless the outputs are held).
+    ///
+    /// Returns the next output values.
+    fn apply_writes(&mut self, now: Instant, hold: bool) -> Result<ModuleValues> {
+        let mut next_out_values = self.out_values.clone();
+        let mut in_bytes = HashMap::new();
+        let mut out_bytes = HashMap::new();
+
+        for (m_nr, (in_v, out_v)) in self.in_values.iter().zip(&self.out_values).enumerate() {
+            if let Some(p) = self.processors.get_mut(&m_nr) {
+                if let ChannelValue::ComRsIn(ref in_v) = in_v[0] {
+                    if let ChannelValue::ComRsOut(ref out_v) = out_v[0] {
+                        out_bytes.insert(m_nr, ChannelValue::None);
+                        in_bytes.insert(m_nr, ChannelValue::None);
+
+                        if !out_v.data.is_empty() && out_v.tx_cnt != self.last_tx_cnt {
+                            out_bytes.insert(m_nr, ChannelValue::Bytes(out_v.data.clone()));
+                        }
+                        self.last_tx_cnt = out_v.tx_cnt;
+
+                        if let Some(ChannelValue::Bytes(ref data)) = self.write.remove(&Address {
+                            module: m_nr,
+                            channel: 0,
+                        }) {
+                            p.write_all(data).module_context(m_nr)?;
+                        }
+
+                        let rs_out = p.next(in_v, out_v);
+                        next_out_values[m_nr][0] = ChannelValue::ComRsOut(rs_out);
+
+                        if in_v.data_available && !in_v.data.is_empty() {
+                            in_bytes.insert(m_nr, ChannelValue::Bytes(in_v.data.clone()));
+                        }
+                    }
+                }
+            } else if !hold {
+                for (i, current) in out_v.iter().enumerate() {
+                    let addr = Address {
+                        module: m_nr,
+                        channel: i,
+                    };
+                    let v = match self.write.get(&addr) {
+                        Some(v) => v,
+                        None => continue,
+                    };
+                    if v != current {
+                        if let Some(min) = self.min_change_intervals.get(&addr) {
+                            if let Some(last) = self.last_changes.get(&addr) {
+                                if now.duration_since(*last) < *min {
+                                    continue;
+                                }
+                            }
+                            self.last_changes.insert(addr, now);
+                        }
+                    }
+                    if let Some(v) = self.write.remove(&addr) {
+                        next_out_values[m_nr][i] = v;
+                    }
+                }
+            }
+        }
+        for (m_nr, v) in in_bytes {
+            self.in_values[m_nr][0] = v;
+        }
+        for (m_nr, v) in out_bytes {
+            self.out_values[m_nr][0] = v;
+        }
+        Ok(next_out_values)
+    }
+
+    /// Evaluates the virtual channels, the input changes and the voting groups.
+    fn evaluate_inputs(&mut self, previous_inputs: &[Vec<ChannelValue>]) {
+        if !self.virtual_channels.is_empty() {
+            let values = self
+                .virtual_channels
+                .iter()
+                .map(|e| e.evaluate(&self.in_values))
+                .collect();
+            self.in_values.push(values);
+        }
+        if self.input_events {
+            self.events.extend(
+                input_changes(previous_inputs, &self.in_values)
+                    .into_iter()
+                    .map(CouplerEvent::Input),
+            );
+        }
+        self.voting_results = self
+            .voting_groups
+            .iter()
+            .map(|g| g.evaluate(&self.in_values))
+            .collect();
+    }
+
+    /// Runs the tasks and toggles the heartbeat.
+    fn scan_tasks(&mut self, next_out_values: &mut [Vec<ChannelValue>]) {
+        if !self.tasks.is_empty() {
+            let writable: Vec<_> = (0..self.modules.len())
+                .map(|nr| self.is_owned(nr) && !self.processors.contains_key(&nr))
+                .collect();
+            let mut io = ScanIo {
+                inputs: &self.in_values,
+                outputs: next_out_values,
+                writable: &writable,
+            };
+            run_tasks(&mut self.tasks, &mut io);
+        }
+        if let Some(ref mut hb) = self.heartbeat {
+            next_out_values[hb.address.module][hb.address.channel] = hb.next_value();
+        }
+    }
+
+    /// Holds the outputs during the start-up or lets the guard check them.
+    fn check_outputs(&mut self, next_out_values: &mut [Vec<ChannelValue>], hold: bool) {
+        if hold {
+            self.hold_off -= 1;
+            self.keep_output_values(next_out_values);
+        } else if let Some(ref mut guard) = self.guard {
+            match guard.check(next_out_values) {
+                GuardDecision::Accept => {}
+                GuardDecision::Modified(reason) => {
+                    self.events.push(CouplerEvent::OutputsModified(reason));
+                }
+                GuardDecision::Veto(reason) => {
+                    self.keep_output_values(next_out_values);
+                    self.events.push(CouplerEvent::OutputsVetoed(reason));
+                }
+            }
+        }
+    }
+
+    /// Resets the next output values (except those of the
+    /// communication modules) to the current values.
+    fn keep_output_values(&self, next_out_values: &mut [Vec<ChannelValue>]) {
+        for (m_nr, values) in next_out_values.iter_mut().enumerate() {
+            if !self.processors.contains_key(&m_nr) {
+                *values = self.out_values[m_nr].clone();
+            }
+        }
+    }
+
+    /// Converts the output values back into raw module values.
+    fn revert_output_values(
+        &self,
+        next_out_values: &mut [Vec<ChannelValue>],
+        scalings: &[(Address, AnalogScaling, DataFormat)],
+    ) {
+        self.invert_channels(next_out_values);
+        for (addr, c) in &self.calibrations {
+            if let Some(v) = next_out_values
+                .get_mut(addr.module)
+                .and_then(|m| m.get_mut(addr.channel))
+            {
+                *v = c.revert(v);
+            }
+        }
+        for (addr, s, format) in scalings {
+            if let Some(ChannelValue::Decimal32(v)) = next_out_values
+                .get_mut(addr.module)
+                .and_then(|m| m.get_mut(addr.channel))
+            {
+                *v = s.revert(*v, format);
+            }
+        }
+    }
+
+    /// Encodes the output image and records the processing times.
+    fn encode_image(
+        &mut self,
+        next_out_values: &[Vec<ChannelValue>],
+        decode_times: Option<Vec<Duration>>,
+    ) -> Result<Vec<u16>> {
+        let infos: Vec<_> = self
+            .modules
+            .iter()
+            .zip(&self.offsets)
+            .map(|(m, o)| (&**m, o))
+            .collect();
+        let mut encode_times = self.timings.as_ref().map(|_| vec![]);
+        let output = encode_timed_output_image(&infos, next_out_values, encode_times.as_mut())?;
+        if let (Some(timings), Some(decode), Some(encode)) =
+            (self.timings.as_mut(), decode_times, encode_times)
+        {
+            for (t, (d, e)) in timings.iter_mut().zip(decode.into_iter().zip(encode)) {
+                t.record(d, e);
+            }
+        }
+        Ok(output)
+    }
+
+    /// Writes the ISDU mailboxes of the IO-Link masters into the output image.
+    fn exchange_mailboxes(&mut self, process_input: &[u16], output: &mut [u16]) -> Result<()> {
+        for (m_nr, p) in &mut self.io_link {
+            let (m, offset) = (&self.modules[*m_nr], &self.offsets[*m_nr]);
+            if let (Some(in_offset), Some(out_offset)) = (offset.input, offset.output) {
+                // Skip the mailbox if the input image is truncated.
+                let data = match prepare_raw_data_to_process(
+                    in_offset,
+                    ADDR_PACKED_PROCESS_INPUT_DATA,
+                    m.process_input_byte_count(),
+                    process_input,
+                ) {
+                    Ok(data) => data,
+                    Err(_) => continue,
+                };
+                let mailbox = p.next(&ur20_4com_io_link::input_mailbox(&data)?)?;
+                write_module_output_bytes(out_offset, &mailbox, output);
+            }
+        }
+        Ok(())
+    }
+
+    /// Copies the outputs of the modules that are not owned
+    /// from the readback into the output image.
+    fn keep_foreign_outputs(&self, process_output: &[u16], output: &mut [u16]) {
+        if let Some(ref owned) = self.owned_modules {
+            for (m_nr, (m, offset)) in self.modules.iter().zip(&self.offsets).enumerate() {
+                if owned.contains(&m_nr) {
+                    continue;
+                }
+                if let Some(out_offset) = offset.output {
+                    copy_module_output(
+                        out_offset,
+                        m.process_output_byte_count(),
+                        process_output,
+                        output,
+                    );
+                }
+            }
+        }
+    }
+
+    /// Processes the packed process input image of a cycle.
+    ///
+    /// Unlike `next` the output image doesn't have to be read back:
+    /// the output image of the previous cycle is used instead.
+    /// Before the first cycle it is initialized by
+    /// `ingest_output_readback`, otherwise all outputs start with zero.
+    ///
+    /// Without a real readback neither external writers
+    /// (`CouplerEvent::OutputMismatch`) nor deviating outputs
+    /// (`set_output_verification`) can be detected, and the outputs of
+    /// modules that are not owned are unknown. Therefore
+    /// `Error::ReadbackRequired` is returned if the output verification
+    /// or the output ownership is enabled.
+    ///
+    /// The new output image is returned by `output_image`.
+    pub fn apply_inputs(&mut self, process_input: &[u16]) -> Result<()> {
+        if self.output_verification || self.owned_modules.is_some() {
+            return Err(Error::ReadbackRequired);
+        }
+        let len = self.process_output_len();
+        let output = if self.output_image.len() == len {
+            self.output_image.clone()
+        } else {
+            vec![0; len]
+        };
+        self.next(process_input, &output)?;
+        Ok(())
+    }
+
+    /// The output image of the last cycle that has to be written
+    /// to `ADDR_PACKED_PROCESS_OUTPUT_DATA`.
+    pub fn output_image(&self) -> &[u16] {
+        &self.output_image
+    }
+}
+
+impl CouplerConfig {
+    fn validate(&self) -> Result<()> {
+        if self.modules.len() != self.params.len() {
+            return Err(Error::BufferLength);
+        }
+        if self.modules.len() * 2 != self.offsets.len() {
+            return Err(Error::ModuleOffset);
+        }
+        Ok(())
+    }
+}
+
+/// Compares the input values of two cycles.
+fn input_changes(
+    previous: &[Vec<ChannelValue>],
+    current: &[Vec<ChannelValue>],
+) -> Vec<ChannelEvent> {
+    let mut events = vec![];
+    for (module, (prev, cur)) in previous.iter().zip(current).enumerate() {
+        for (channel, (old, new)) in prev.iter().zip(cur).enumerate() {
+            if old == new {
+                continue;
+            }
+            let address = Address { module, channel };
+            events.push(match (old, new) {
+                (ChannelValue::Bit(false), ChannelValue::Bit(true)) => {
+                    ChannelEvent::Rising(address)
+                }
+                (ChannelValue::Bit(true), ChannelValue::Bit(false)) => {
+                    ChannelEvent::Falling(address)
+                }
+                _ => ChannelEvent::ValueChanged {
+                    address,
+                    old: old.clone(),
+                    new: new.clone(),
+                },
+            });
+        }
+    }
+    events
+}
+
+/// Creates a module instance from its parameter register data.
+///
+/// This is the per-module construction that is used by `Coupler::new`,
+/// so alternative coupler implementations can reuse it.
+/// Returns `Error::UnknownModule` for unsupported module types.
+///
+/// ```rust
+/// # use ur20::{ModuleType, ur20_fbc_mod_tcp::module_from_parameter_data};
+/// let m = module_from_parameter_data(&ModuleType::UR20_4DI_P, &[0; 4]).unwrap();
+/// assert_eq!(m.process_input_byte_count(), 1);
+/// ```
+pub fn module_from_parameter_data(
+    module_type: &ModuleType,
+    data: &[u16],
+) -> Result<Box<dyn ProcessModbusTcpData>> {
+    create_module(module_type, Some(data))
+}
+
+/// Creates a module with its default parameters.
+///
+/// Returns `Error::UnknownModule` for unsupported module types.
+pub fn default_module(module_type: &ModuleType) -> Result<Box<dyn ProcessModbusTcpData>> {
+    create_module(module_type, None)
+}
+
+/// Creates a module from its parameter register data
+/// or with its default parameters if there is no data.
+fn create_module(
+    module_type: &ModuleType,
+    data: Option<&[u16]>,
+) -> Result<Box<dyn ProcessModbusTcpData>> {
+    // modules that know their module type
+    macro_rules! variant {
+        ($module:ident) => {
+            match data {
+                Some(data) => Box::new($module::Mod::from_modbus_parameter_data(
+                    module_type.clone(),
+                    data,
+                )?),
+                None => Box::new($module::Mod::new(module_type.clone())?),
+            }
+        };
+    }
+    // modules of a single module type
+    macro_rules! single {
+        ($module:ident) => {
+            match data {
+                Some(data) => Box::new($module::Mod::from_modbus_parameter_data(data)?),
+                None => Box::new($module::Mod::default()),
+            }
+        };
+    }
+    let m: Box<dyn ProcessModbusTcpData> = match *module_type {
+        ref t if ur20_di_generic::MODULE_TYPES.contains(t) => variant!(ur20_di_generic),
+        ref t if ur20_do_generic::MODULE_TYPES.contains(t) => variant!(ur20_do_generic),
+        ref t if ur20_ai_i_generic::MODULE_TYPES.contains(t) => variant!(ur20_ai_i_generic),
+        ref t if ur20_ai_ui_generic::MODULE_TYPES.contains(t) => variant!(ur20_ai_ui_generic),
+        ref t if ur20_ao_ui_generic::MODULE_TYPES.contains(t) => variant!(ur20_ao_ui_generic),
+        ref t if ur20_pf::MODULE_TYPES.contains(t) => Box::new(ur20_pf::Mod::new(t.clone())?),
+        ModuleType::UR20_2DI_P_TS | ModuleType::UR20_4DI_P_TS => variant!(ur20_di_ts),
+        ModuleType::UR20_4RO_SSR_255 | ModuleType::UR20_4RO_CO_255 => variant!(ur20_ro_generic),
+        ModuleType::UR20_4AI_RTD_DIAG => single!(ur20_4ai_rtd_diag),
+        ModuleType::UR20_4AI_TC_DIAG => single!(ur20_4ai_tc_diag),
+        ModuleType::UR20_1SSI => single!(ur20_1ssi),
+        ModuleType::UR20_3EM_230V_AC => single!(ur20_3em_230v_ac),
+        ModuleType::UR20_2PWM_PN_0_5A | ModuleType::UR20_2PWM_PN_2A => variant!(ur20_2pwm_pn),
+        ModuleType::UR20_4COM_IO_LINK => single!(ur20_4com_io_link),
+        ModuleType::UR20_4AI_UI_16_DIAG => single!(ur20_4ai_ui_16_diag),
+        ModuleType::UR20_4AI_UI_12 => single!(ur20_4ai_ui_12),
+        ModuleType::UR20_2FCNT_100 => single!(ur20_2fcnt_100),
+        ModuleType::UR20_1COM_232_485_422 => single!(ur20_1com_232_485_422),
+        _ => {
+            return Err(Error::UnknownModule);
+        }
+    };
+    Ok(m)
+}
+
+/// Decodes sample process input data of a module with the given parameters
+/// (register content of `ADDR_MODULE_PARAMETERS`).
+///
+/// This is useful to show how a register value will be displayed
+/// with a specific parameterization.
+pub fn simulate_decode(
+    module_type: &ModuleType,
+    params: &[u16],
+    raw_words: &[u16],
+) -> Result<Vec<ChannelValue>> {
+    module_from_parameter_data(module_type, params)?.process_input_data(raw_words)
+}
+
+/// Calculates the module offsets of the packed process images
+/// (`ProcessImageMapping::Packed`) like the coupler does.
+///
+/// The data of a module with a single byte follows directly
+/// behind the previous module, so it might share a register
+/// with it. The data of all other modules begins at a register
+/// boundary and occupies whole registers.
+/// Modules without input or output data have no offset.
+pub fn calculate_offsets(modules: &[&dyn ProcessModbusTcpData]) -> Vec<ModuleOffset> {
+    let mut in_byte = 0;
+    let mut out_byte = 0;
+    let next = |byte: &mut usize, cnt: usize, start: BitAddress| {
+        if cnt == 0 {
+            return None;
+        }
+        if cnt > 1 {
+            *byte = byte.next_multiple_of(2);
+        }
+        let offset = start + (*byte * 8) as BitAddress;
+        *byte += if cnt > 1 {
+            cnt.next_multiple_of(2)
+        } else {
+            cnt
+        };
+        Some(offset)
+    };
+    modules
+        .iter()
+        .map(|m| ModuleOffset {
+            input: next(&mut in_byte, m.process_input_byte_count(), 0x0000),
+            output: next(&mut out_byte, m.process_output_byte_count(), 0x8000),
+        })
+        .collect()
+}
+
+/// Compares the module offsets that were read from the coupler
+/// with the calculated offsets (see `calculate_offsets`).
+///
+/// Returns the numbers of the modules with unexpected offsets.
+/// Additional or missing offsets are reported as well.
+pub fn offset_mismatches(
+    modules: &[&dyn ProcessModbusTcpData],
+    offsets: &[ModuleOffset],
+) -> Vec<usize> {
+    let expected = calculate_offsets(modules);
+    (0..cmp::max(expected.len(), offsets.len()))
+        .filter(|&nr| expected.get(nr) != offsets.get(nr))
+        .collect()
+}
+
+/// Converts a list of module offsets into the register data
+/// of `ADDR_MODULE_OFFSETS` (see `offsets_of_process_data`).
+pub fn offsets_to_process_data(offsets: &[ModuleOffset]) -> Vec<Word> {
+    offsets
+        .iter()
+        .flat_map(|o| vec![o.output.unwrap_or(0xFFFF), o.input.unwrap_or(0xFFFF)])
+        .collect()
+}
+
+/// Converts the register data into a list of module offsets.
+pub fn offsets_of_process_data(data: &[Word]) -> Vec<ModuleOffset> {
+    let mut offsets = vec![];
+    for i in 0..data.len() / 2 {
+        offsets.push(ModuleOffset {
+            input: word_to_offset(data[i * 2 + 1]),
+            output: word_to_offset(data[i * 2]),
+        });
+    }
+    offsets
+}
+
+/// Map the raw input data into values.
+#[deprecated(note = "use `Coupler::process_input_data` instead")]
+pub fn process_input_data(
+    modules: &[(&dyn ProcessModbusTcpData, &ModuleOffset)],
+    data: &[u16],
+) -> Result<Vec<Vec<ChannelValue>>> {
+    decode_input_image(modules, data)
+}
+
+/// Map the raw output data into values.
+#[deprecated(note = "use `Coupler::process_output_data` instead")]
+pub fn process_output_data(
+    modules: &[(&dyn ProcessModbusTcpData, &ModuleOffset)],
+    data: &[u16],
+) -> Result<Vec<Vec<ChannelValue>>> {
+    decode_output_image(modules, data)
+}
+
+/// Map values into raw values.
+#[deprecated(note = "use `Coupler::process_output_values` instead")]
+pub fn process_output_values(
+    modules: &[(&dyn ProcessModbusTcpData, &ModuleOffset)],
+    values: &[Vec<ChannelValue>],
+) -> Result<Vec<u16>> {
+    encode_output_image(modules, values)
+}
+
+fn decode_input_image(
+    modules: &[(&dyn ProcessModbusTcpData, &ModuleOffset)],
+    data: &[u16],
+) -> Result<Vec<Vec<ChannelValue>>> {
+    modules
+        .iter()
+        .map(|&(m, offset)| {
+            if let Some(in_offset) = offset.input {
+                let cnt = m.process_input_byte_count();
+                m.process_input_data(&prepare_raw_data_to_process(
+                    in_offset,
+                    ADDR_PACKED_PROCESS_INPUT_DATA,
+                    cnt,
+                    data,
+                )?)
+            } else {
+                Ok(vec![ChannelValue::None; m.module_type().channel_count()])
+            }
+        })
+        .collect()
+}
+
+fn decode_output_image(
+    modules: &[(&dyn ProcessModbusTcpData, &ModuleOffset)],
+    data: &[u16],
+) -> Result<Vec<Vec<ChannelValue>>> {
+    modules
+        .iter()
+        .map(|&(m, offset)| {
+            if let Some(out_offset) = offset.output {
+                let cnt = m.process_output_byte_count();
+                m.process_output_data(&prepare_raw_data_to_process(
+                    out_offset,
+                    ADDR_PACKED_PROCESS_OUTPUT_DATA,
+                    cnt,
+                    data,
+                )?)
+            } else {
+                Ok(vec![ChannelValue::None; m.module_type().channel_count()])
+            }
+        })
+        .collect()
+}
+
+/// Number of registers of the input or output image.
+fn image_len(modules: &[(&dyn ProcessModbusTcpData, &ModuleOffset)], input: bool) -> usize {
+    modules
+        .iter()
+        .filter_map(|&(m, o)| {
+            if input {
+                o.input.map(|offset| {
+                    image_word_count(
+                        offset,
+                        ADDR_PACKED_PROCESS_INPUT_DATA,
+                        m.process_input_byte_count(),
+                    )
+                })
+            } else {
+                o.output.map(|offset| {
+                    image_word_count(
+                        offset,
+                        ADDR_PACKED_PROCESS_OUTPUT_DATA,
+                        m.process_output_byte_count(),
+                    )
+                })
+            }
+        })
+        .max()
+        .unwrap_or(0)
+}
+
+/// Raw data and decoded values of each module.
+type DecodeCache = Vec<Option<(Vec<u16>, Vec<ChannelValue>)>>;
+
+/// Map the raw input or output data into values.
+///
+/// Modules that are not completely part of the data
+/// get `ChannelValue::None` and their numbers are returned.
+fn process_truncated_data(
+    modules: &[(&dyn ProcessModbusTcpData, &ModuleOffset)],
+    data: &[u16],
+    input: bool,
+    mut cache: Option<&mut DecodeCache>,
+    mut durations: Option<&mut Vec<Duration>>,
+) -> Result<(Vec<Vec<ChannelValue>>, Vec<usize>)> {
+    if let Some(ref mut cache) = cache {
+        cache.resize(modules.len(), None);
+    }
+    let mut missing = vec![];
+    let mut values = vec![];
+    for (nr, &(m, offset)) in modules.iter().enumerate() {
+        let start = durations.as_ref().map(|_| Instant::now());
+        let (offset, base, cnt) = if input {
+            (
+                offset.input,
+                ADDR_PACKED_PROCESS_INPUT_DATA,
+                m.process_input_byte_count(),
+            )
+        } else {
+            (
+                offset.output,
+                ADDR_PACKED_PROCESS_OUTPUT_DATA,
+                m.process_output_byte_count(),
+            )
+        };
+        let v = match offset {
+            Some(offset) if image_word_count(offset, base, cnt) > data.len() => {
+                missing.push(nr);
+                vec![ChannelValue::None; m.module_type().channel_count()]
+            }
+            Some(offset) => {
+                let raw = prepare_raw_data_to_process(offset, base, cnt, data)?;
+                let cached = cache.as_mut().map(|c| &mut c[nr]);
+                match cached {
+                    // The decoding only depends on the raw data,
+                    // so unchanged data results in the same values.
+                    Some(Some((ref last_raw, ref values))) if *last_raw == raw => values.clone(),
+                    cached => {
+                        let values = if input {
+                            m.process_input_data(&raw)?
+                        } else {
+                            m.process_output_data(&raw)?
+                        };
+                        if let Some(entry) = cached {
+                            *entry = Some((raw, values.clone()));
+                        }
+                        values
+                    }
+                }
+            }
+            None => vec![ChannelValue::None; m.module_type().channel_count()],
+        };
+        if let (Some(d), Some(start)) = (durations.as_mut(), start) {
+            d.push(start.elapsed());
+        }
+        values.push(v);
+    }
+    Ok((values, missing))
+}
+
+/// Number of registers an image needs to contain the module data.
+fn image_word_count(offset: u16, addr: u16, byte_count: usize) -> usize {
+    let (start, _) = to_register_address(offset);
+    let start = start.saturating_sub(addr) as usize;
+    start + cmp::max(byte_count / 2, 1)
+}
+
+/// Returns the indices of the image registers that belong to a module.
+fn module_words(
+    modules: &[(&dyn ProcessModbusTcpData, &ModuleOffset)],
+    area: ImageArea,
+) -> HashSet<usize> {
+    let mut words = HashSet::new();
+    for &(m, offset) in modules {
+        let (offset, addr, cnt) = match area {
+            ImageArea::Input => (
+                offset.input,
+                ADDR_PACKED_PROCESS_INPUT_DATA,
+                m.process_input_byte_count(),
+            ),
+            ImageArea::Output => (
+                offset.output,
+                ADDR_PACKED_PROCESS_OUTPUT_DATA,
+                m.process_output_byte_count(),
+            ),
+        };
+        if let Some(offset) = offset {
+            let (start, _) = to_register_address(offset);
+            let start = start.saturating_sub(addr) as usize;
+            words.extend(start..image_word_count(offset, addr, cnt));
+        }
+    }
+    words
+}
+
+fn diff_values(
+    area: ImageArea,
+    a: &[Vec<ChannelValue>],
+    b: &[Vec<ChannelValue>],
+) -> Vec<ChannelDiff> {
+    a.iter()
+        .zip(b)
+        .enumerate()
+        .flat_map(|(module, (a, b))| {
+            a.iter()
+                .zip(b)
+                .enumerate()
+                .filter(|(_, (a, b))| a != b)
+                .map(move |(channel, (a, b))| ChannelDiff::Channel {
+                    area,
+                    address: Address { module, channel },
+                    a: a.clone(),
+                    b: b.clone(),
+                })
+        })
+        .collect()
+}
+
+fn prepare_raw_data_to_process(
+    offset: u16,
+    addr: u16,
+    byte_count: usize,
+    data: &[u16],
+) -> Result<Vec<u16>> {
+    let (start, bit) = to_register_address(offset);
+    let start = (start - addr) as usize;
+    let end = image_word_count(offset, addr, byte_count);
+    if end > data.len() {
+        return Err(Error::BufferLength);
+    }
+    let output = &data[start..end];
+
+    match bit {
+        0 => Ok(output.to_vec()),
+        8 => Ok(shift_data(output)),
+        _ => Err(Error::ModuleOffset),
+    }
+}
+
+fn encode_output_image(
+    modules: &[(&dyn ProcessModbusTcpData, &ModuleOffset)],
+    values: &[Vec<ChannelValue>],
+) -> Result<Vec<u16>> {
+    encode_timed_output_image(modules, values, None)
+}
+
+/// Encodes the output image and records the encoding time of each module.
+fn encode_timed_output_image(
+    modules: &[(&dyn ProcessModbusTcpData, &ModuleOffset)],
+    values: &[Vec<ChannelValue>],
+    mut durations: Option<&mut Vec<Duration>>,
+) -> Result<Vec<u16>> {
+    if modules.len() != values.len() {
+        return Err(Error::ChannelValue);
+    }
+
+    let mut out = vec![];
+
+    for (i, &(m, offset)) in modules.iter().enumerate() {
+        let start = durations.as_ref().map(|_| Instant::now());
+        let encoded = offset
+            .output
+            .map(|o| m.process_output_values(&values[i]).map(|data| (o, data)))
+            .transpose()?;
+        if let (Some(d), Some(start)) = (durations.as_mut(), start) {
+            d.push(start.elapsed());
+        }
+        if let Some((out_offset, data)) = encoded {
+            let (start, bit) = to_register_address(out_offset);
+            if start < ADDR_PACKED_PROCESS_OUTPUT_DATA {
+                return Err(Error::ModuleOffset);
+            }
+            let start = (start - ADDR_PACKED_PROCESS_OUTPUT_DATA) as usize;
+
+            match bit {
+                0 => {
+                    if out.len() != start {
+                        return Err(Error::ModuleOffset);
+                    }
+                    out.extend_from_slice(&data);
+                }
+                8 => {
+                    if out.len() != start + 1 {
+                        return Err(Error::ModuleOffset);
+                    }
+                    let shared_low_byte = out[start] & 0x00FF;
+                    let buf = u16_to_u8(&data);
+                    let shared_high_byte = u16::from(buf[0]) << 8;
+                    let word = shared_high_byte | shared_low_byte;
+                    out[start] = word;
+                }
+                _ => {
+                    return Err(Error::ModuleOffset);
+                }
+            }
+        }
+    }
+
+    Ok(out)
+}
+
+/// Copies the raw output data of a module from one image to another
+/// and leaves the bytes of other modules in shared registers untouched.
+fn copy_module_output(offset: BitAddress, byte_count: usize, src: &[u16], dst: &mut [u16]) {
+    let (start, bit) = to_register_address(offset);
+    let start = start.saturating_sub(ADDR_PACKED_PROCESS_OUTPUT_DATA) as usize;
+    let end = image_word_count(offset, ADDR_PACKED_PROCESS_OUTPUT_DATA, byte_count);
+    let mask = match (bit, byte_count) {
+        (8, _) => 0xFF00,
+        (_, 1) => 0x00FF,
+        _ => 0xFFFF,
+    };
+    for i in start..cmp::min(end, cmp::min(src.len(), dst.len())) {
+        dst[i] = (dst[i] & !mask) | (src[i] & mask);
+    }
+}
+
+/// Overwrites the first bytes of the raw output data of a module.
+fn write_module_output_bytes(offset: BitAddress, bytes: &[u8], dst: &mut [u16]) {
+    let (start, bit) = to_register_address(offset);
+    let start = start.saturating_sub(ADDR_PACKED_PROCESS_OUTPUT_DATA) as usize;
+    let first = start * 2 + bit / 8;
+    for (i, b) in bytes.iter().enumerate() {
+        let idx = first + i;
+        if let Some(word) = dst.get_mut(idx / 2) {
+            *word = if idx % 2 == 0 {
+                (*word & 0xFF00) | u16::from(*b)
+            } else {
+                (*word & 0x00FF) | u16::from(*b) << 8
+            };
+        }
+    }
+}
+
+fn word_to_offset(word: Word) -> Option<BitAddress> {
+    if word == 0xFFFF {
+        None
+    } else {
+        Some(word)
+    }
+}
+
+/// Splits a bit address into a register address and a bit number.
+pub fn to_register_address(addr: BitAddress) -> (RegisterAddress, BitNr) {
+    let register = (addr & 0xFFF0) >> 4;
+    let bit = (addr & 0x000F) as usize;
+    (register, bit)
+}
+
+/// Merges a register address and a bit number into a bit address.
+pub fn to_bit_address(addr: RegisterAddress, bit: usize) -> BitAddress {
+    (addr << 4) | (bit as u16)
+}
+
+pub trait ModbusParameterRegisterCount {
+    /// Total number of Modbus registers of module parameters.
+    fn param_register_count(&self) -> u16;
+}
+
+impl ModbusParameterRegisterCount for ModuleType {
+    fn param_register_count(&self) -> u16 {
+        use super::ModuleType::*;
+        match *self {
+            // Digital input modules
+            UR20_4DI_P | UR20_4DI_P_3W | UR20_4DI_N => 4,
+            UR20_8DI_P_2W | UR20_8DI_P_3W | UR20_8DI_P_3W_HD | UR20_8DI_N_3W => 8,
+            UR20_16DI_P | UR20_16DI_N => 16,
+            UR20_16DI_P_PLC_INT | UR20_16DI_N_PLC_INT => 0,
+            UR20_2DI_P_TS => 2,
+            UR20_4DI_P_TS => 4,
+
+            // Digital output modules
+            UR20_4DO_P | UR20_4DO_P_2A | UR20_4DO_PN_2A | UR20_4DO_N | UR20_4DO_N_2A => 4,
+            UR20_8DO_P | UR20_8DO_P_2W_HD | UR20_8DO_N => 8,
+            UR20_16DO_P | UR20_16DO_P_PLC_INT | UR20_16DO_N | UR20_16DO_N_PLC_INT => 0,
+            UR20_4RO_SSR_255 | UR20_4RO_CO_255 => 4,
+
+            // Analogue input modules
+            UR20_8AI_I_16_DIAG_HD => 1 + 8 * 4,
+            UR20_8AI_I_16_HD | UR20_8AI_I_PLC_INT => 1 + 8 * 2,
+            UR20_4AI_UI_16_DIAG => 1 + 4 * 5,
+            UR20_4AI_UI_12 | UR20_4AI_UI_16 | UR20_4AI_UI_16_HD => 1 + 4 * 2,
+            UR20_4AI_UI_16_DIAG_HD | UR20_4AI_UI_DIF_16_DIAG => 1 + 4 * 5,
+
+            // Analogue output modul
+            UR20_4AO_UI_16 | UR20_4AO_UI_16_M | UR20_4AO_UI_16_HD => 4 * 3,
+            UR20_4AO_UI_16_DIAG | UR20_4AO_UI_16_M_DIAG | UR20_4AO_UI_16_DIAG_HD => 4 * 4,
+
+            // Analogue input modules DIAG
+            UR20_4AI_RTD_DIAG => 1 + 4 * 7,
+            UR20_4AI_TC_DIAG => 1 + 4 * 7,
+
+            // Counter modules
+            UR20_2FCNT_100 => 2,
+
+            // Energy metering modules
+            UR20_3EM_230V_AC => 3 + 3,
+
+            // PWM modules
+            UR20_2PWM_PN_0_5A | UR20_2PWM_PN_2A => 2 * 3,
+
+            // Communication modules
+            UR20_1COM_232_485_422 => 10,
+            UR20_1SSI => 3,
+            UR20_4COM_IO_LINK => 4 * 6,
+
+            // Power feed modules
+            UR20_PF_I
+            | UR20_PF_O
+            | UR20_PF_O_1DI_SIL
+            | UR20_PF_O_2DI_SIL
+            | UR20_PF_O_2DI_DELAY_SIL => 0,
+
+            // Not yet supported
+            _ => {
+                panic!("{:?} is not supported", self);
+            }
+        }
+    }
+}
+
+/// Calculate the parameter addresses and the number of registers by a given list of modules.
+pub fn param_addresses_and_register_counts(modules: &[ModuleType]) -> Vec<(u16, u16)> {
+    modules
+        .iter()
+        .enumerate()
+        .map(|(idx, m)| {
+            (
+                ADDR_MODULE_PARAMETERS + (idx * 256) as u16,
+                m.param_register_count(),
+            )
+        })
+        .collect()
+}
+
+/// Calculate the diagnosis addresses and the number of registers by a given number of modules.
+pub fn diagnosis_addresses_and_register_counts(module_count: usize) -> Vec<(u16, u16)> {
+    let cnt = (DIAGNOSIS_BYTE_COUNT / 2) as u16;
+    (0..module_count)
+        .map(|idx| (ADDR_MODULE_DIAGNOSIS + idx as u16 * cnt, cnt))
+        .collect()
+}
+
+/// Converts the raw coupler register data into a list of module types.
+pub fn module_list_from_registers(registers: &[u16]) -> Result<Vec<ModuleType>> {
+    module_ids_from_registers(registers)?
+        .into_iter()
+        .map(ModuleType::try_from_u32)
+        .collect()
+}
+
+/// Like `module_list_from_registers` but tolerates IDs that only differ
+/// in their revision bits (see `ModuleType::try_from_u32_tolerant`).
+///
+/// The flag of each module is `true` if its ID matched exactly.
+pub fn module_list_from_registers_tolerant(registers: &[u16]) -> Result<Vec<(ModuleType, bool)>> {
+    module_ids_from_registers(registers)?
+        .into_iter()
+        .map(ModuleType::try_from_u32_tolerant)
+        .collect()
+}
+
+fn module_ids_from_registers(registers: &[u16]) -> Result<Vec<u32>> {
+    if registers.is_empty() || registers.len() % 2 != 0 {
+        return Err(Error::RegisterCount);
+    }
+    Ok(registers
+        .chunks(2)
+        .map(|r| (u32::from(r[0]) << 16) + u32::from(r[1]))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    /// A clock that only advances on request.
+    #[derive(Debug, Clone)]
+    struct FakeClock(Arc<Mutex<Instant>>);
+
+    impl FakeClock {
+        fn new() -> Self {
+            FakeClock(Arc::new(Mutex::new(Instant::now())))
+        }
+        fn advance(&self, d: Duration) {
+            *self.0.lock().unwrap() += d;
+        }
+    }
+
+    impl Clock for FakeClock {
+        fn now(&self) -> Instant {
+            *self.0.lock().unwrap()
+        }
+    }
+
+    #[test]
+    fn calculate_packed_offsets() {
+        let cfg = CouplerConfig {
+            modules: vec![
+                ModuleType::UR20_4DO_P,
+                ModuleType::UR20_4AO_UI_16,
+                ModuleType::UR20_1COM_232_485_422,
+                ModuleType::UR20_4DI_P,
+                ModuleType::UR20_8DI_P_2W,
+                ModuleType::UR20_4DO_P,
+                ModuleType::UR20_PF_O_1DI_SIL,
+            ],
+            offsets: vec![],
+            params: vec![
+                vec![0; 4],
+                vec![0; 12],
+                vec![0; 10],
+                vec![0; 4],
+                vec![0; 8],
+                vec![0; 4],
+                vec![],
+            ],
+        };
+        let modules: Vec<_> = cfg
+            .modules
+            .iter()
+            .zip(&cfg.params)
+            .map(|(t, p)| module_from_parameter_data(t, p).unwrap())
+            .collect();
+        let modules: Vec<_> = modules.iter().map(|m| &**m).collect();
+        let offsets = calculate_offsets(&modules);
+        let registers = offsets_to_process_data(&offsets);
+        assert_eq!(
+            registers,
+            vec![
+                0x8000, 0xFFFF, 0x8010, 0xFFFF, 0x8050, 0x0000, 0xFFFF, 0x0040, 0xFFFF, 0x0048,
+                0x8090, 0xFFFF, 0xFFFF, 0xFFFF,
+            ]
+        );
+        assert_eq!(offsets_of_process_data(&registers), offsets);
+        assert!(offset_mismatches(&modules, &offsets).is_empty());
+
+        let mut read = offsets.clone();
+        read[4].input = Some(0x0050);
+        read.pop();
+        assert_eq!(offset_mismatches(&modules, &read), vec![4, 6]);
+
+        let c = Coupler::new(&CouplerConfig {
+            offsets: registers,
+            ..cfg
+        })
+        .unwrap();
+        assert_eq!(c.process_input_len(), 5);
+        assert_eq!(c.process_output_len(), 10);
+    }
+
+    #[test]
+    fn test_offsets_of_process_data() {
+        assert_eq!(offsets_of_process_data(&[]), vec![]);
+        assert_eq!(
+            offsets_of_process_data(&[0xFFFF, 0x0000, 0x8000, 0x0040, 0x8050, 0xFFFF]),
+            vec![
+                ModuleOffset {
+                    output: None,
+                    input: Some(0x0000),
+                },
+                ModuleOffset {
+                    output: Some(0x8000),
+                    input: Some(0x0040),
+                },
+                ModuleOffset {
+                    output: Some(0x8050),
+                    input: None,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn coupler_parameters_from_and_to_raw_data() {
+        assert_eq!(
+            CouplerParameters::from_raw_data(&[0, 0, 0, 0]).unwrap(),
+            CouplerParameters::default()
+        );
+        let p = CouplerParameters::from_raw_data(&[1, 1, 2, 500]).unwrap();
+        assert_eq!(p.process_image_mapping, ProcessImageMapping::WordAligned);
+        assert_eq!(p.register_format, RegisterFormat::BigEndian);
+        assert_eq!(p.fault_behavior, FaultBehavior::Substitute);
+        assert_eq!(p.watchdog(), Some(Duration::from_millis(500)));
+        assert_eq!(p.to_raw_data(), vec![1, 1, 2, 500]);
+        assert_eq!(CouplerParameters::default().watchdog(), None);
+    }
+
+    #[test]
+    fn coupler_parameters_from_invalid_raw_data() {
+        assert_eq!(
+            CouplerParameters::from_raw_data(&[0, 0, 0]).err(),
+            Some(Error::BufferLength)
+        );
+        assert!(CouplerParameters::from_raw_data(&[2, 0, 0, 0]).is_err());
+        assert!(CouplerParameters::from_raw_data(&[0, 2, 0, 0]).is_err());
+        assert!(CouplerParameters::from_raw_data(&[0, 0, 3, 0]).is_err());
+    }
+
+    #[test]
+    fn test_to_regsiter_address() {
+        assert_eq!(to_register_address(0x80AB), (0x080A, 11));
+    }
+
+    #[test]
+    fn test_to_bit_address() {
+        assert_eq!(to_bit_address(0x080A, 11), 0x080AB);
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn test_process_input_data() {
+        let m0 = super::ur20_4ao_ui_16::Mod::default();
+        let mut m1 = super::ur20_4ai_rtd_diag::Mod::default();
+        let m2 = super::ur20_4di_p::Mod::default();
+        let m3 = super::ur20_4di_p::Mod::default();
+
+        #[rustfmt::skip]
+        let data = &[
+            0,33,0,0,             // UR20-4AI-P
+            0b0000_0001_0000_0010 // UR20-4DI-P + UR20-4DI-P
+        ];
+
+        m1.ch_params[1].measurement_range = RtdRange::PT100;
+
+        let mod0: &dyn ProcessModbusTcpData = &m0;
+        let mod1: &dyn ProcessModbusTcpData = &m1;
+        let mod2: &dyn ProcessModbusTcpData = &m2;
+        let mod3: &dyn ProcessModbusTcpData = &m3;
+
+        let addr_out_0 = to_bit_address(ADDR_PACKED_PROCESS_OUTPUT_DATA, 0);
+        let addr_in_1 = to_bit_address(ADDR_PACKED_PROCESS_INPUT_DATA, 0);
+        let addr_in_2 = to_bit_address(ADDR_PACKED_PROCESS_INPUT_DATA + 4, 0);
+        let addr_in_3 = to_bit_address(ADDR_PACKED_PROCESS_INPUT_DATA + 4, 8);
+
+        let o0 = ModuleOffset {
+            input: None,
+            output: Some(addr_out_0),
+        };
+        let o1 = ModuleOffset {
+            input: Some(addr_in_1),
+            output: None,
+        };
+        let o2 = ModuleOffset {
+            input: Some(addr_in_2),
+            output: None,
+        };
+        let o3 = ModuleOffset {
+            input: Some(addr_in_3),
+            output: None,
+        };
+
+        let modules = vec![(mod0, &o0), (mod1, &o1), (mod2, &o2), (mod3, &o3)];
+
+        let res = process_input_data(&modules, data).unwrap();
+        assert_eq!(res.len(), 4);
+        assert_eq!(res[0].len(), 4);
+        assert_eq!(res[1].len(), 4);
+        assert_eq!(res[2].len(), 4);
+        assert_eq!(res[3].len(), 4);
+        assert_eq!(res[1][1], ChannelValue::Decimal32(3.3));
+        assert_eq!(res[2][1], ChannelValue::Bit(true));
+        assert_eq!(res[3][0], ChannelValue::Bit(true));
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn test_process_input_data_with_invalid_offset() {
+        let m0 = super::ur20_4ai_rtd_diag::Mod::default();
+        let data = &[0, 33, 0, 0];
+        let mod0: &dyn ProcessModbusTcpData = &m0;
+        let bit = 3; // should not work
+        let addr_in_0 = to_bit_address(ADDR_PACKED_PROCESS_INPUT_DATA, bit);
+        let o0 = ModuleOffset {
+            input: Some(addr_in_0),
+            output: None,
+        };
+        let modules = vec![(mod0, &o0)];
+        assert!(process_input_data(&modules, data).is_err());
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn test_process_input_data_with_invalid_data() {
+        let m0 = super::ur20_4ai_rtd_diag::Mod::default();
+        let m1 = super::ur20_4ai_rtd_diag::Mod::default();
+        let data = &[0, 33, 0, 0];
+        let mod0: &dyn ProcessModbusTcpData = &m0;
+        let mod1: &dyn ProcessModbusTcpData = &m1;
+        let addr_in_0 = to_bit_address(ADDR_PACKED_PROCESS_INPUT_DATA, 0);
+        let addr_in_1 = to_bit_address(ADDR_PACKED_PROCESS_INPUT_DATA + 4, 0);
+        let o0 = ModuleOffset {
+            input: Some(addr_in_0),
+            output: None,
+        };
+        let o1 = ModuleOffset {
+            input: Some(addr_in_1),
+            output: None,
+        };
+        let modules = vec![(mod0, &o0), (mod1, &o1)];
+        assert!(process_input_data(&modules, data).is_err());
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn test_process_output_data() {
+        let mut m0 = super::ur20_4ao_ui_16::Mod::default();
+        let m1 = super::ur20_4ai_rtd_diag::Mod::default();
+        let m2 = super::ur20_4do_p::Mod::default();
+        let m3 = super::ur20_4do_p::Mod::default();
+
+        #[rustfmt::skip]
+        let data = &[
+            0,0x3600,0,0,         // UR20-4AO-P
+            0b0000_0001_0000_0010 // UR20-4DO-P + UR20-4DO-P
+        ];
+
+        m0.ch_params[1].output_range = AnalogUIRange::VMinus5To5;
+
+        let mod0: &dyn ProcessModbusTcpData = &m0;
+        let mod1: &dyn ProcessModbusTcpData = &m1;
+        let mod2: &dyn ProcessModbusTcpData = &m2;
+        let mod3: &dyn ProcessModbusTcpData = &m3;
+
+        let addr_out_0 = to_bit_address(ADDR_PACKED_PROCESS_OUTPUT_DATA, 0);
+        let addr_in_1 = to_bit_address(ADDR_PACKED_PROCESS_INPUT_DATA, 0);
+        let addr_out_2 = to_bit_address(ADDR_PACKED_PROCESS_OUTPUT_DATA + 4, 0);
+        let addr_out_3 = to_bit_address(ADDR_PACKED_PROCESS_OUTPUT_DATA + 4, 8);
+
+        let o0 = ModuleOffset {
+            input: None,
+            output: Some(addr_out_0),
+        };
+        let o1 = ModuleOffset {
+            input: Some(addr_in_1),
+            output: None,
+        };
+        let o2 = ModuleOffset {
+            input: None,
+            output: Some(addr_out_2),
+        };
+        let o3 = ModuleOffset {
+            input: None,
+            output: Some(addr_out_3),
+        };
+
+        let modules = vec![(mod0, &o0), (mod1, &o1), (mod2, &o2), (mod3, &o3)];
+
+        let res = process_output_data(&modules, data).unwrap();
+        assert_eq!(res.len(), 4);
+        assert_eq!(res[0].len(), 4);
+        assert_eq!(res[1].len(), 4);
+        assert_eq!(res[2].len(), 4);
+        assert_eq!(res[3].len(), 4);
+        assert_eq!(res[0][1], ChannelValue::Decimal32(2.5));
+        assert_eq!(res[1][0], ChannelValue::None);
+        assert_eq!(res[2][1], ChannelValue::Bit(true));
+        assert_eq!(res[3][0], ChannelValue::Bit(true));
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn test_process_output_data_with_invalid_offset() {
+        let m0 = super::ur20_4ao_ui_16::Mod::default();
+        let data = &[0, 33, 0, 0];
+        let mod0: &dyn ProcessModbusTcpData = &m0;
+        let bit = 3; // should not work
+        let addr_out_0 = to_bit_address(ADDR_PACKED_PROCESS_OUTPUT_DATA, bit);
+        let o0 = ModuleOffset {
+            input: None,
+            output: Some(addr_out_0),
+        };
+        let modules = vec![(mod0, &o0)];
+        assert!(process_output_data(&modules, data).is_err());
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn test_process_output_data_with_invalid_data() {
+        let m0 = super::ur20_4ao_ui_16::Mod::default();
+        let m1 = super::ur20_4ao_ui_16::Mod::default();
+        let data = &[0, 33, 0, 0];
+        let mod0: &dyn ProcessModbusTcpData = &m0;
+        let mod1: &dyn ProcessModbusTcpData = &m1;
+        let addr_out_0 = to_bit_address(ADDR_PACKED_PROCESS_OUTPUT_DATA, 0);
+        let addr_out_1 = to_bit_address(ADDR_PACKED_PROCESS_OUTPUT_DATA + 4, 0);
+        let o0 = ModuleOffset {
+            input: None,
+            output: Some(addr_out_0),
+        };
+        let o1 = ModuleOffset {
+            input: None,
+            output: Some(addr_out_1),
+        };
+        let modules = vec![(mod0, &o0), (mod1, &o1)];
+        assert!(process_output_data(&modules, data).is_err());
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn test_process_output_values_with_invalid_len() {
+        let m0 = super::ur20_4ao_ui_16::Mod::default();
+        let m1 = super::ur20_4ai_rtd_diag::Mod::default();
+
+        let values = vec![vec![
+            ChannelValue::Decimal32(15.0),
+            ChannelValue::Decimal32(20.0),
+            ChannelValue::Decimal32(20.0),
+            ChannelValue::Decimal32(10.0),
+        ]];
+
+        let mod0: &dyn ProcessModbusTcpData = &m0;
+        let mod1: &dyn ProcessModbusTcpData = &m1;
+
+        let addr_out_0 = to_bit_address(ADDR_PACKED_PROCESS_OUTPUT_DATA, 0);
+        let addr_in_1 = to_bit_address(ADDR_PACKED_PROCESS_INPUT_DATA, 0);
+
+        let o0 = ModuleOffset {
+            input: None,
+            output: Some(addr_out_0),
+        };
+        let o1 = ModuleOffset {
+            input: Some(addr_in_1),
+            output: None,
+        };
+
+        let modules = vec![(mod0, &o0), (mod1, &o1)];
+
+        assert!(process_output_values(&modules, &values).is_err());
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn test_process_output_values_with_invalid_offset_a() {
+        let m0 = super::ur20_4ao_ui_16::Mod::default();
+        let m1 = super::ur20_4ai_rtd_diag::Mod::default();
+
+        let values = vec![
+            vec![
+                ChannelValue::Decimal32(15.0),
+                ChannelValue::Decimal32(20.0),
+                ChannelValue::Decimal32(20.0),
+                ChannelValue::Decimal32(10.0),
+            ],
+            vec![],
+        ];
+
+        let mod0: &dyn ProcessModbusTcpData = &m0;
+        let mod1: &dyn ProcessModbusTcpData = &m1;
+
+        let addr_out_0 = to_bit_address(ADDR_PACKED_PROCESS_OUTPUT_DATA + 10, 0);
+        let addr_in_1 = to_bit_address(ADDR_PACKED_PROCESS_INPUT_DATA, 0);
+
+        let o0 = ModuleOffset {
+            input: None,
+            output: Some(addr_out_0),
+        };
+        let o1 = ModuleOffset {
+            input: Some(addr_in_1),
+            output: None,
+        };
+
+        let modules = vec![(mod0, &o0), (mod1, &o1)];
+        assert!(process_output_values(&modules, &values).is_err());
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn test_process_output_values_with_invalid_offset_b() {
+        let m0 = super::ur20_4ao_ui_16::Mod::default();
+        let m1 = super::ur20_4ai_rtd_diag::Mod::default();
+        let m2 = super::ur20_4do_p::Mod::default();
+
+        let values = vec![
+            vec![
+                ChannelValue::Decimal32(15.0),
+                ChannelValue::Decimal32(20.0),
+                ChannelValue::Decimal32(20.0),
+                ChannelValue::Decimal32(10.0),
+            ],
+            vec![],
+            vec![
+                ChannelValue::Bit(false),
+                ChannelValue::Bit(false),
+                ChannelValue::Bit(false),
+                ChannelValue::Bit(false),
+            ],
+        ];
+
+        let mod0: &dyn ProcessModbusTcpData = &m0;
+        let mod1: &dyn ProcessModbusTcpData = &m1;
+        let mod2: &dyn ProcessModbusTcpData = &m2;
+
+        let addr_out_0 = to_bit_address(ADDR_PACKED_PROCESS_OUTPUT_DATA, 0);
+        let addr_in_1 = to_bit_address(ADDR_PACKED_PROCESS_INPUT_DATA, 0);
+        let addr_out_2 = to_bit_address(ADDR_PACKED_PROCESS_OUTPUT_DATA + 1, 8);
+
+        let o0 = ModuleOffset {
+            input: None,
+            output: Some(addr_out_0),
+        };
+        let o1 = ModuleOffset {
+            input: Some(addr_in_1),
+            output: None,
+        };
+        let o2 = ModuleOffset {
+            input: None,
+            output: Some(addr_out_2),
+        };
+
+        let modules = vec![(mod0, &o0), (mod1, &o1), (mod2, &o2)];
+        assert!(process_output_values(&modules, &values).is_err());
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn test_process_output_values_with_invalid_offset_c() {
+        let m0 = super::ur20_4ao_ui_16::Mod::default();
+        let values = vec![vec![
+            ChannelValue::Decimal32(15.0),
+            ChannelValue::Decimal32(20.0),
+            ChannelValue::Decimal32(20.0),
+            ChannelValue::Decimal32(10.0),
+        ]];
+        let mod0: &dyn ProcessModbusTcpData = &m0;
+        let addr_out_0 = to_bit_address(0, 0);
+        let o0 = ModuleOffset {
+            input: None,
+            output: Some(addr_out_0),
+        };
+        let modules = vec![(mod0, &o0)];
+        assert!(process_output_values(&modules, &values).is_err());
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn test_process_output_values() {
+        let mut m0 = super::ur20_4ao_ui_16::Mod::default();
+        let m1 = super::ur20_4ai_rtd_diag::Mod::default();
+        let m2 = super::ur20_4do_p::Mod::default();
+        let m3 = super::ur20_4do_p::Mod::default();
+
+        let values = vec![
+            vec![
+                ChannelValue::Decimal32(15.0),
+                ChannelValue::Decimal32(20.0),
+                ChannelValue::Decimal32(20.0),
+                ChannelValue::Decimal32(10.0),
+            ],
+            vec![],
+            vec![
+                ChannelValue::Bit(false),
+                ChannelValue::Bit(true),
+                ChannelValue::Bit(false),
+                ChannelValue::Bit(false),
+            ],
+            vec![
+                ChannelValue::Bit(false),
+                ChannelValue::Bit(false),
+                ChannelValue::Bit(true),
+                ChannelValue::Bit(true),
+            ],
+        ];
+
+        m0.ch_params[1].output_range = AnalogUIRange::mA0To20;
+        m0.ch_params[3].output_range = AnalogUIRange::mA0To20;
+
+        let mod0: &dyn ProcessModbusTcpData = &m0;
+        let mod1: &dyn ProcessModbusTcpData = &m1;
+        let mod2: &dyn ProcessModbusTcpData = &m2;
+        let mod3: &dyn ProcessModbusTcpData = &m3;
+
+        let addr_out_0 = to_bit_address(ADDR_PACKED_PROCESS_OUTPUT_DATA, 0);
+        let addr_in_1 = to_bit_address(ADDR_PACKED_PROCESS_INPUT_DATA, 0);
+        let addr_out_2 = to_bit_address(ADDR_PACKED_PROCESS_OUTPUT_DATA + 4, 0);
+        let addr_out_3 = to_bit_address(ADDR_PACKED_PROCESS_OUTPUT_DATA + 4, 8);
+
+        let o0 = ModuleOffset {
+            input: None,
+            output: Some(addr_out_0),
+        };
+        let o1 = ModuleOffset {
+            input: Some(addr_in_1),
+            output: None,
+        };
+        let o2 = ModuleOffset {
+            input: None,
+            output: Some(addr_out_2),
+        };
+        let o3 = ModuleOffset {
+            input: None,
+            output: Some(addr_out_3),
+        };
+
+        let modules = vec![(mod0, &o0), (mod1, &o1), (mod2, &o2), (mod3, &o3)];
+
+        let res = process_output_values(&modules, &values).unwrap();
+        assert_eq!(res.len(), 5);
+        assert_eq!(res[0], 0x0); // channel is disabled
+        assert_eq!(res[1], 0x6C00);
+        assert_eq!(res[2], 0x0); // channel is disabled
+        assert_eq!(res[3], 0x3600);
+        assert_eq!(res[4], 0b_0000_1100_0000_0010);
+    }
+
+    #[test]
+    fn map_process_images_with_coupler_methods() {
+        let cfg = CouplerConfig {
+            modules: vec![ModuleType::UR20_4DI_P, ModuleType::UR20_4DO_P],
+            offsets: vec![0xFFFF, 0x0000, 0x8000, 0xFFFF],
+            params: vec![vec![0; 4], vec![0; 4]],
+        };
+        let c = Coupler::new(&cfg).unwrap();
+        let inputs = c.process_input_data(&[0b0101]).unwrap();
+        assert_eq!(inputs[0][2], ChannelValue::Bit(true));
+        assert_eq!(inputs[1], vec![ChannelValue::None; 4]);
+        let outputs = c.process_output_data(&[0b0010]).unwrap();
+        assert_eq!(outputs[1][1], ChannelValue::Bit(true));
+        assert_eq!(c.process_output_values(&outputs).unwrap(), vec![0b0010]);
+        assert!(c.process_output_values(&outputs[..1]).is_err());
+        assert!(c.inputs().is_empty());
+    }
+
+    #[test]
+    fn test_param_addresses_and_register_counts() {
+        assert_eq!(param_addresses_and_register_counts(&[]), vec![]);
+        assert_eq!(
+            param_addresses_and_register_counts(&[ModuleType::UR20_4DI_P]),
+            vec![(0xC000, 4)]
+        );
+        assert_eq!(
+            param_addresses_and_register_counts(&[
+                ModuleType::UR20_4DI_P,
+                ModuleType::UR20_4DO_P,
+                ModuleType::UR20_4AI_RTD_DIAG,
+            ]),
+            vec![(0xC000, 4), (0xC100, 4), (0xC200, 29)]
+        );
+    }
+
+    #[test]
+    fn validate_coupler_config_data() {
+        assert!(CouplerConfig {
+            modules: vec![],
+            offsets: vec![],
+            params: vec![],
+        }
+        .validate()
+        .is_ok());
+        assert!(CouplerConfig {
+            modules: vec![ModuleType::UR20_4DI_P],
+            offsets: vec![0xFFFF, 0x0000],
+            params: vec![vec![0; 4]],
+        }
+        .validate()
+        .is_ok());
+        assert!(CouplerConfig {
+            modules: vec![ModuleType::UR20_4DI_P],
+            offsets: vec![0xFFFF, 0x0000],
+            params: vec![],
+        }
+        .validate()
+        .is_err());
+        assert!(CouplerConfig {
+            modules: vec![ModuleType::UR20_4DI_P],
+            offsets: vec![],
+            params: vec![vec![0; 4]],
+        }
+        .validate()
+        .is_err());
+        assert!(CouplerConfig {
+            modules: vec![ModuleType::UR20_4DI_P],
+            offsets: vec![0xFFFF],
+            params: vec![],
+        }
+        .validate()
+        .is_err());
+    }
+
+    #[test]
+    fn create_new_coupler_instance() {
+        let cfg = CouplerConfig {
+            modules: vec![ModuleType::UR20_4DI_P, ModuleType::UR20_1COM_232_485_422],
+            offsets: vec![0xFFFF, 0x0000, 0x8000, 0x0008],
+            params: vec![vec![0; 4], vec![0; 10]],
+        };
+
+        let mut invalid_cfg = cfg.clone();
+        invalid_cfg.params = vec![];
+        let c = Coupler::new(&cfg).unwrap();
+
+        assert!(Coupler::new(&invalid_cfg).is_err());
+        assert_eq!(c.modules.len(), 2);
+        assert_eq!(c.processors.len(), 1);
+        assert_eq!(c.offsets.len(), 2);
+        assert_eq!(c.in_values.len(), 0);
+        assert_eq!(c.out_values.len(), 0);
+        assert_eq!(c.write.len(), 0);
+    }
+
+    #[test]
+    fn cycle_without_output_readback() {
+        let cfg = CouplerConfig {
+            modules: vec![ModuleType::UR20_4DI_P, ModuleType::UR20_4DO_P],
+            offsets: vec![0xFFFF, 0x0000, 0x8000, 0xFFFF],
+            params: vec![vec![0; 4], vec![0; 4]],
+        };
+        let addr = |module, channel| Address { module, channel };
+        let mut c = Coupler::new(&cfg).unwrap();
+        assert!(c.output_image().is_empty());
+        c.apply_inputs(&[0b0001]).unwrap();
+        assert_eq!(c.output_image(), &[0]);
+        assert_eq!(c.inputs()[0][0], ChannelValue::Bit(true));
+
+        c.set_output(&addr(1, 2), ChannelValue::Bit(true)).unwrap();
+        c.apply_inputs(&[0b0001]).unwrap();
+        assert_eq!(c.output_image(), &[0b0100]);
+        c.apply_inputs(&[0b0000]).unwrap();
+        assert_eq!(c.output_image(), &[0b0100]);
+        assert_eq!(c.outputs()[1][2], ChannelValue::Bit(true));
+        assert!(c.take_events().is_empty());
+
+        let mut c = Coupler::new(&cfg).unwrap();
+        c.ingest_output_readback(&[0b1000]).unwrap();
+        c.apply_inputs(&[0b0000]).unwrap();
+        assert_eq!(c.output_image(), &[0b1000]);
+        assert!(c.apply_inputs(&[]).is_ok());
+        let out = c.next(&[0b0000], &[0b0011]).unwrap();
+        assert_eq!(c.output_image(), &out[..]);
+
+        c.set_output_verification(true);
+        assert_eq!(c.apply_inputs(&[0]).err(), Some(Error::ReadbackRequired));
+        c.set_output_verification(false);
+        c.set_owned_modules(Some(vec![1])).unwrap();
+        assert_eq!(c.apply_inputs(&[0]).err(), Some(Error::ReadbackRequired));
+    }
+
+    #[test]
+    fn set_offsets_after_creation() {
+        let modules = [ModuleType::UR20_4DI_P, ModuleType::UR20_4DO_P];
+        let params = [vec![0; 4], vec![0; 4]];
+        assert!(Coupler::from_modules(&modules, &params[..1]).is_err());
+        let mut c = Coupler::from_modules(&modules, &params).unwrap();
+        assert!(!c.has_offsets());
+        assert_eq!(c.next(&[0], &[0]).err(), Some(Error::ModuleOffset));
+        assert_eq!(c.process_input_data(&[0]).err(), Some(Error::ModuleOffset));
+
+        assert_eq!(
+            c.set_offsets(&[0xFFFF, 0x0000]).err(),
+            Some(Error::ModuleOffset)
+        );
+        c.set_offsets(&[0xFFFF, 0x0000, 0x8000, 0xFFFF]).unwrap();
+        assert!(c.has_offsets());
+        assert_eq!(c.process_input_len(), 1);
+        c.next(&[0b0001], &[0]).unwrap();
+        assert_eq!(c.inputs()[0][0], ChannelValue::Bit(true));
+    }
+
+    #[test]
+    fn reuse_decoded_values_of_unchanged_input_data() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        #[derive(Debug)]
+        struct CountingMod(ur20_4di_p::Mod, Arc<AtomicUsize>);
+
+        impl Module for CountingMod {
+            fn module_type(&self) -> ModuleType {
+                self.0.module_type()
+            }
+        }
+
+        impl ToModbusParameterData for CountingMod {
+            fn to_modbus_parameter_data(&self) -> Vec<u16> {
+                self.0.to_modbus_parameter_data()
+            }
+        }
+
+        impl ProcessModbusTcpData for CountingMod {
+            fn process_input_byte_count(&self) -> usize {
+                self.0.process_input_byte_count()
+            }
+            fn process_output_byte_count(&self) -> usize {
+                self.0.process_output_byte_count()
+            }
+            fn process_input_data(&self, data: &[u16]) -> Result<Vec<ChannelValue>> {
+                self.1.fetch_add(1, Ordering::SeqCst);
+                self.0.process_input_data(data)
+            }
+        }
+
+        let cfg = CouplerConfig {
+            modules: vec![ModuleType::UR20_4DI_P, ModuleType::UR20_4DI_P],
+            offsets: vec![0xFFFF, 0x0000, 0xFFFF, 0x0010],
+            params: vec![vec![0; 4], vec![0; 4]],
+        };
+        let mut c = Coupler::new(&cfg).unwrap();
+        let decoded = Arc::new(AtomicUsize::new(0));
+        c.modules[1] = Box::new(CountingMod(ur20_4di_p::Mod::default(), decoded.clone()));
+
+        c.next(&[0b0001, 0b0010], &[]).unwrap();
+        c.next(&[0b0011, 0b0010], &[]).unwrap();
+        assert_eq!(decoded.load(Ordering::SeqCst), 1);
+        assert_eq!(c.inputs()[0][1], ChannelValue::Bit(true));
+        assert_eq!(c.inputs()[1][1], ChannelValue::Bit(true));
+
+        c.next(&[0b0011, 0b0100], &[]).unwrap();
+        assert_eq!(decoded.load(Ordering::SeqCst), 2);
+        assert_eq!(c.inputs()[1][1], ChannelValue::Bit(false));
+        assert_eq!(c.inputs()[1][2], ChannelValue::Bit(true));
+    }
+
+    #[test]
+    fn describe_station() {
+        #[rustfmt::skip]
+        let cfg = CouplerConfig {
+            modules: vec![ModuleType::UR20_4DI_P, ModuleType::UR20_4AO_UI_16],
+            offsets: vec![0xFFFF, 0x0000, 0x8000, 0xFFFF],
+            params: vec![
+                vec![0; 4],
+                vec![
+                    1, 1, 0,
+                    1, 2, 0,
+                    1, 0, 0,
+                    1, 0, 0,
+                ],
+            ],
+        };
+        let mut c = Coupler::new(&cfg).unwrap();
+        let report = c.describe();
+        assert!(report.starts_with(
+            "module 0: UR20_4DI_P (input: 0x0000.0, 1 byte; output: -)\n  channel 0: -\n"
+        ));
+        c.set_output(
+            &Address {
+                module: 1,
+                channel: 0,
+            },
+            ChannelValue::Decimal32(12.0),
+        )
+        .unwrap();
+        let out = c.next(&[0b0001], &[0; 4]).unwrap();
+        c.next(&[0b0001], &out).unwrap();
+        let report = c.describe();
+        let lines: Vec<_> = report.lines().collect();
+        assert_eq!(lines.len(), 10);
+        assert_eq!(lines[1], "  channel 0: true");
+        assert_eq!(lines[2], "  channel 1: false");
+        assert_eq!(
+            lines[5],
+            "module 1: UR20_4AO_UI_16 (input: -; output: 0x0800.0, 8 bytes)"
+        );
+        assert_eq!(lines[6], "  channel 0 [mA4To20]: out 12.0 mA");
+        assert_eq!(lines[7], "  channel 1 [V0To10]: out 0.0 V");
+    }
+
+    #[test]
+    fn channel_info_of_coupler_modules() {
+        #[rustfmt::skip]
+        let cfg = CouplerConfig {
+            modules: vec![ModuleType::UR20_4DI_P, ModuleType::UR20_4AO_UI_16],
+            offsets: vec![0xFFFF, 0x0000, 0x8000, 0xFFFF],
+            params: vec![
+                vec![0; 4],
+                vec![
+                    1, 0, 0, // CH 0
+                    1, 2, 0, // CH 1
+                    1, 8, 0, // CH 2
+                    1, 8, 0, // CH 3
+                ],
+            ],
+        };
+        let c = Coupler::new(&cfg).unwrap();
+        let addr = |module, channel| Address { module, channel };
+        assert_eq!(c.channel_info(&addr(0, 0)), Some(ChannelInfo::default()));
+        assert_eq!(
+            c.channel_info(&addr(1, 0)).unwrap().unit,
+            Some(Unit::MilliAmpere)
+        );
+        assert_eq!(c.channel_info(&addr(1, 1)).unwrap().unit, Some(Unit::Volt));
+        assert_eq!(c.channel_info(&addr(1, 2)).unwrap().unit, None);
+        assert!(c.channel_info(&addr(1, 4)).is_none());
+        assert!(c.channel_info(&addr(2, 0)).is_none());
+    }
+
+    #[test]
+    fn channel_info_with_labels() {
+        use crate::station::ModuleDescription;
+
+        let desc = StationDescription {
+            modules: vec![ModuleDescription {
+                module_type: ModuleType::UR20_4DI_P,
+                params: vec![0; 4],
+                channels: vec![
+                    ChannelDescription::default(),
+                    ChannelDescription {
+                        label: Some("door_open".into()),
+                        description: Some("Door contact of cabinet 3".into()),
+                        ..Default::default()
+                    },
+                ],
+            }],
+            virtual_channels: vec![],
+        };
+        let c = Coupler::from_description(&desc, &[0xFFFF, 0x0000]).unwrap();
+        let addr = |module, channel| Address { module, channel };
+        let info = c.channel_info(&addr(0, 1)).unwrap();
+        assert_eq!(info.label.unwrap(), "door_open");
+        assert_eq!(info.description.unwrap(), "Door contact of cabinet 3");
+        assert_eq!(c.channel_info(&addr(0, 0)), Some(ChannelInfo::default()));
+        assert_eq!(c.channel_info(&addr(0, 3)), Some(ChannelInfo::default()));
+        assert!(Coupler::from_description(&desc, &[]).is_err());
+    }
+
+    #[test]
+    fn invert_digital_channels() {
+        use crate::station::ModuleDescription;
+
+        let inverted = ChannelDescription {
+            invert: true,
+            ..Default::default()
+        };
+        let desc = StationDescription {
+            modules: vec![
+                ModuleDescription {
+                    module_type: ModuleType::UR20_4DI_P,
+                    params: vec![0; 4],
+                    channels: vec![ChannelDescription::default(), inverted.clone()],
+                },
+                ModuleDescription {
+                    module_type: ModuleType::UR20_4DO_P,
+                    params: vec![0; 4],
+                    channels: vec![inverted],
+                },
+            ],
+            virtual_channels: vec![],
+        };
+        let mut c = Coupler::from_description(&desc, &[0xFFFF, 0x0000, 0x8000, 0xFFFF]).unwrap();
+        let addr = |module, channel| Address { module, channel };
+
+        let out = c.next(&[0b0011], &[0b0000]).unwrap();
+        assert_eq!(out, vec![0b0000]);
+        assert_eq!(c.inputs()[0][0], ChannelValue::Bit(true));
+        assert_eq!(c.inputs()[0][1], ChannelValue::Bit(false));
+        assert_eq!(c.inputs()[0][2], ChannelValue::Bit(false));
+        assert_eq!(c.outputs()[1][0], ChannelValue::Bit(true));
+        assert_eq!(c.outputs()[1][1], ChannelValue::Bit(false));
+
+        c.set_output(&addr(1, 0), ChannelValue::Bit(true)).unwrap();
+        c.set_output(&addr(1, 1), ChannelValue::Bit(true)).unwrap();
+        let out = c.next(&[0b0001], &out).unwrap();
+        assert_eq!(out, vec![0b0010]);
+        assert_eq!(c.inputs()[0][1], ChannelValue::Bit(true));
+        c.next(&[0b0001], &out).unwrap();
+        assert_eq!(c.outputs()[1][0], ChannelValue::Bit(true));
+        assert_eq!(c.outputs()[1][1], ChannelValue::Bit(true));
+
+        c.ingest_output_readback(&[0b0001]).unwrap();
+        assert_eq!(c.outputs()[1][0], ChannelValue::Bit(false));
+    }
+
+    #[test]
+    fn evaluate_virtual_channels() {
+        use crate::station::{ModuleDescription, VirtualChannel};
+
+        #[rustfmt::skip]
+        let params = vec![
+            0,
+            0, 0, 0, 1, 2,
+            0, 0, 0, 1, 2,
+            0, 0, 0, 1, 2,
+            0, 0, 0, 1, 2,
+        ];
+        let addr = |module, channel| Address { module, channel };
+        let desc = StationDescription {
+            modules: vec![ModuleDescription::new(
+                ModuleType::UR20_4AI_UI_16_DIAG,
+                params,
+            )],
+            virtual_channels: vec![VirtualChannel {
+                channel: ChannelDescription {
+                    label: Some("delta".into()),
+                    ..Default::default()
+                },
+                expression: Expression::Difference(addr(0, 0), addr(0, 1)),
+            }],
+        };
+        let mut c = Coupler::from_description(&desc, &[0xFFFF, 0x0000]).unwrap();
+        c.next(&[0x6C00, 0x3600, 0, 0], &[]).unwrap();
+        assert_eq!(c.inputs().len(), 2);
+        assert_eq!(c.inputs()[1], vec![ChannelValue::Decimal32(5.0)]);
+        assert_eq!(c.channel_info(&addr(1, 0)).unwrap().label.unwrap(), "delta");
+        assert!(c.channel_info(&addr(1, 1)).is_none());
+        assert!(c
+            .set_output(&addr(1, 0), ChannelValue::Decimal32(0.0))
+            .is_err());
+    }
+
+    #[test]
+    fn process_in_out_data_with_coupler() {
+        use crate::ur20_1com_232_485_422::*;
+        use num_traits::ToPrimitive;
+
+        #[rustfmt::skip]
+        let cfg = CouplerConfig {
+            modules: vec![
+                ModuleType::UR20_4DI_P,
+                ModuleType::UR20_4DO_P,
+                ModuleType::UR20_1COM_232_485_422,
+            ],
+            offsets: vec![
+                0xFFFF,
+                0x0000,
+                0x8000,
+                0xFFFF,
+                to_bit_address(0x0801, 0),
+                to_bit_address(0x0001, 0),
+            ],
+            params: vec![
+                vec![0; 4],
+                vec![0; 4],
+                vec![
+                    ProcessDataLength::EightBytes.to_u16().unwrap(),
+                    OperatingMode::RS232.to_u16().unwrap(),
+                    0, 0, 0, 0, 0, 0, 0, 0,
+                ],
+            ],
+        };
+        let mut c = Coupler::new(&cfg).unwrap();
+        let process_input_data = vec![
+            0b_0101,                // module input for DI_P
+            0b_0000_0100_1111_0001, // len & status
+            0,                      // data
+            0xABCD,                 // data
+            0,
+        ];
+        let process_output_data = vec![0b_11_00, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+
+        // make sure the initialization process evolves
+        let process_output_data = c.next(&process_input_data, &process_output_data).unwrap();
+        let process_output_data = c.next(&process_input_data, &process_output_data).unwrap();
+        let process_output_data = c.next(&process_input_data, &process_output_data).unwrap();
+
+        {
+            let inputs = c.inputs();
+            let outputs = c.outputs();
+
+            assert_eq!(inputs.len(), 3);
+            assert_eq!(outputs.len(), 3);
+
+            assert_eq!(inputs[0].len(), 4);
+            assert_eq!(outputs[0].len(), 4);
+
+            assert_eq!(inputs[0][0], ChannelValue::Bit(true));
+            assert_eq!(inputs[0][1], ChannelValue::Bit(false));
+            assert_eq!(inputs[0][2], ChannelValue::Bit(true));
+            assert_eq!(inputs[0][3], ChannelValue::Bit(false));
+
+            assert_eq!(outputs[1][0], ChannelValue::Bit(false));
+            assert_eq!(outputs[1][1], ChannelValue::Bit(false));
+            assert_eq!(outputs[1][2], ChannelValue::Bit(true));
+            assert_eq!(outputs[1][3], ChannelValue::Bit(true));
+
+            assert_eq!(outputs[0], vec![ChannelValue::None; 4]);
+            assert_eq!(inputs[1], vec![ChannelValue::None; 4]);
+
+            assert_eq!(inputs[2], vec![ChannelValue::Bytes(vec![0, 0, 0xCD, 0xAB])]);
+            assert_eq!(outputs[2], vec![ChannelValue::None]);
+        }
+
+        c.set_output(
+            &Address {
+                module: 2,
+                channel: 0,
+            },
+            ChannelValue::Bytes(b"Hello modbus coupler!".to_vec()),
+        )
+        .unwrap();
+        c.set_output(
+            &Address {
+                module: 1,
+                channel: 1,
+            },
+            ChannelValue::Bit(true),
+        )
+        .unwrap();
+        assert!(c
+            .set_output(
+                &Address {
+                    module: 3,
+                    channel: 0,
+                },
+                ChannelValue::Bit(true)
+            )
+            .is_err());
+        assert!(c
+            .set_output(
+                &Address {
+                    module: 2,
+                    channel: 1,
+                },
+                ChannelValue::Bit(true)
+            )
+            .is_err());
+        let di = Address {
+            module: 0,
+            channel: 3,
+        };
+        assert_eq!(
+            c.set_output(&di, ChannelValue::Bit(true)).err(),
+            Some(Error::NotAnOutput {
+                address: di,
+                module_type: ModuleType::UR20_4DI_P,
+            })
+        );
+
+        assert_eq!(c.write.len(), 2);
+
+        let process_input_data = vec![0b_0101, 0, 0, 0, 0];
+        let process_output_data = c.next(&process_input_data, &process_output_data).unwrap();
+        assert_eq!(c.write.len(), 0);
+        {
+            let inputs = c.inputs();
+            let outputs = c.outputs();
+            assert_eq!(outputs[1][1], ChannelValue::Bit(false));
+            assert_eq!(inputs[2][0], ChannelValue::None);
+            assert_eq!(outputs[2][0], ChannelValue::None);
+        }
+        let process_output_data = c.next(&process_input_data, &process_output_data).unwrap();
+        {
+            let inputs = c.inputs();
+            let outputs = c.outputs();
+            assert_eq!(outputs[1][1], ChannelValue::Bit(true));
+            assert_eq!(inputs[2][0], ChannelValue::None);
+            assert_eq!(outputs[2][0], ChannelValue::Bytes(b"Hello ".to_vec()));
+        }
+        let process_output_data = c.next(&process_input_data, &process_output_data).unwrap();
+        {
+            let outputs = c.outputs();
+            assert_eq!(outputs[2][0], ChannelValue::None);
+        }
+
+        let process_input_data = vec![
+            0b_0101,                // module input for DI_P
+            0b_0000_0101_1111_1001, // len & status (bit 3&4: RX_CNT , bit 5&6: TX_CNT_ACK)
+            0xDDEE,                 // data
+            0xFFFF,                 // data
+            0x00AA,                 // data
+        ];
+        let _process_output_data = c.next(&process_input_data, &process_output_data).unwrap();
+
+        assert!(c.reader(0).is_none());
+        assert!(c.reader(1).is_none());
+        assert!(c.reader(2).is_some());
+        assert!(c.writer(0).is_none());
+        assert!(c.writer(1).is_none());
+        assert!(c.writer(2).is_some());
+        let mut buf = [0; 20];
+        let reader = c.reader(2).unwrap();
+        let len_0 = reader.read(&mut buf).unwrap();
+        let len_1 = reader.read(&mut buf).unwrap();
+        assert_eq!(len_0, 9);
+        assert_eq!(len_1, 0);
+        assert_eq!(
+            &buf[0..9],
+            &[0, 0, 0xCD, 0xAB, 0xEE, 0xDD, 0xFF, 0xFF, 0xAA]
+        );
+    }
+
+    #[test]
+    fn exchange_isdu_requests_with_io_link_master() {
+        use crate::ur20_4com_io_link::*;
+
+        let mut params = vec![0; 24];
+        params[0] = PortMode::IoLink as u16;
+        let cfg = CouplerConfig {
+            modules: vec![ModuleType::UR20_4DO_P, ModuleType::UR20_4COM_IO_LINK],
+            offsets: vec![0x8000, 0xFFFF, 0x8010, 0x0000],
+            params: vec![vec![0; 4], params],
+        };
+        let mut c = Coupler::new(&cfg).unwrap();
+        assert!(c
+            .isdu_request(
+                0,
+                IsduRequest {
+                    port: 0,
+                    index: 0x10,
+                    subindex: 0,
+                    data: None
+                }
+            )
+            .is_err());
+        c.isdu_request(
+            1,
+            IsduRequest {
+                port: 0,
+                index: 0x10,
+                subindex: 0,
+                data: None,
+            },
+        )
+        .unwrap();
+        c.set_output(
+            &Address {
+                module: 1,
+                channel: 0,
+            },
+            ChannelValue::Bytes(vec![0xAA]),
+        )
+        .unwrap();
+
+        let mut input = vec![0; 26];
+        let out = c.next(&input, &[0; 25]).unwrap();
+        assert_eq!(out.len(), 25);
+        assert_eq!(out[0], 0);
+        // request counter and port
+        assert_eq!(out[1], 0x0001);
+        // index
+        assert_eq!(out[2], 0x0010);
+        // port 0 data
+        assert_eq!(out[9], 0x00AA);
+        assert!(c.isdu_responses(1).is_empty());
+
+        input[2] = 0x0201; // counter 1, 2 bytes
+        input[3] = 0x5678;
+        let out = c.next(&input, &out).unwrap();
+        assert_eq!(out[1], 0);
+        assert_eq!(out[9], 0x00AA);
+        let res = c.isdu_responses(1);
+        assert_eq!(res.len(), 1);
+        assert_eq!(res[0].result, Ok(vec![0x78, 0x56]));
+        assert!(c.isdu_responses(0).is_empty());
+    }
+
+    #[test]
+    fn read_from_com_module_over_cycles() {
+        use crate::ur20_1com_232_485_422::*;
+        use num_traits::ToPrimitive;
+
+        #[rustfmt::skip]
+        let cfg = CouplerConfig {
+            modules: vec![ModuleType::UR20_1COM_232_485_422],
+            offsets: vec![0x8000, 0x0000],
+            params: vec![vec![
+                ProcessDataLength::EightBytes.to_u16().unwrap(),
+                OperatingMode::RS232.to_u16().unwrap(),
+                0, 0, 0, 0, 0, 0, 0, 0,
+            ]],
+        };
+        let mut c = Coupler::new(&cfg).unwrap();
+        let frame = |rx_cnt: u16, data: &[u8]| {
+            let mut words = vec![((data.len() as u16) << 8) | 0b1110_0001 | (rx_cnt << 3)];
+            words.extend(u8_to_u16(data));
+            words.resize(4, 0);
+            words
+        };
+        let clock = FakeClock::new();
+        let mut out = vec![0; 4];
+        let mut cnt = 0;
+        let mut cycle = |c: &mut Coupler| {
+            let input = if cnt < 3 {
+                frame(2, b"ab\ncd")
+            } else {
+                frame(3, b"ef")
+            };
+            cnt += 1;
+            clock.advance(Duration::from_millis(10));
+            out = c.next(&input, &out)?;
+            Ok(())
+        };
+        let timeout = Duration::from_secs(1);
+        assert_eq!(
+            c.read_until(0, b'\n', timeout, &clock, &mut cycle).unwrap(),
+            b"ab\n"
+        );
+        let mut buf = [0; 4];
+        let timeout = Duration::from_millis(50);
+        c.read_exact_timeout(0, &mut buf, timeout, &clock, &mut cycle)
+            .unwrap();
+        assert_eq!(&buf, b"cdef");
+        let err = c
+            .read_exact_timeout(0, &mut buf[..1], timeout, &clock, &mut cycle)
+            .err()
+            .unwrap();
+        assert_eq!(
+            err,
+            Error::Io {
+                module: Some(0),
+                source: io::Error::from(io::ErrorKind::TimedOut).to_string(),
+            }
+        );
+        assert!(err.to_string().starts_with("I/O error of module 0: "));
+        assert!(c
+            .read_exact_timeout(1, &mut buf, timeout, &clock, &mut cycle)
+            .is_err());
+
+        let start = clock.now();
+        assert_eq!(
+            c.read_until(0, b'\n', timeout, &clock, &mut cycle)
+                .err()
+                .unwrap(),
+            Error::Io {
+                module: Some(0),
+                source: io::Error::from(io::ErrorKind::TimedOut).to_string(),
+            }
+        );
+        assert_eq!(clock.now().duration_since(start), Duration::from_millis(50));
+    }
+
+    #[test]
+    fn cancel_pending_writes() {
+        let cfg = CouplerConfig {
+            modules: vec![ModuleType::UR20_4DO_P],
+            offsets: vec![0x8000, 0xFFFF],
+            params: vec![vec![0; 4]],
+        };
+        let mut c = Coupler::new(&cfg).unwrap();
+        let addr = |channel| Address { module: 0, channel };
+        c.set_output(&addr(0), ChannelValue::Bit(true)).unwrap();
+        c.set_output(&addr(1), ChannelValue::Bit(true)).unwrap();
+        c.set_output(&addr(2), ChannelValue::Bit(true)).unwrap();
+        assert_eq!(c.pending_writes().len(), 3);
+        assert_eq!(
+            c.pending_writes().get(&addr(1)),
+            Some(&ChannelValue::Bit(true))
+        );
+
+        assert_eq!(c.cancel_output(&addr(1)), Some(ChannelValue::Bit(true)));
+        assert_eq!(c.cancel_output(&addr(1)), None);
+        assert_eq!(c.pending_writes().len(), 2);
+
+        let out = c.next(&[], &[0]).unwrap();
+        assert_eq!(out, vec![0b0101]);
+        assert!(c.pending_writes().is_empty());
+
+        c.set_output(&addr(3), ChannelValue::Bit(true)).unwrap();
+        c.clear_pending();
+        assert!(c.pending_writes().is_empty());
+        let out = c.next(&[], &out).unwrap();
+        assert_eq!(out, vec![0b0101]);
+    }
+
+    #[test]
+    fn limit_output_changes() {
+        let cfg = CouplerConfig {
+            modules: vec![ModuleType::UR20_4DO_P],
+            offsets: vec![0x8000, 0xFFFF],
+            params: vec![vec![0; 4]],
+        };
+        let mut c = Coupler::new(&cfg).unwrap();
+        let clock = FakeClock::new();
+        c.set_clock(Box::new(clock.clone()));
+        let addr = |channel| Address { module: 0, channel };
+        let hour = Some(Duration::from_secs(3600));
+        c.set_min_change_interval(&addr(0), hour).unwrap();
+        c.set_min_change_interval(&addr(1), hour).unwrap();
+        assert!(c.set_min_change_interval(&addr(4), hour).is_err());
+
+        c.set_output(&addr(0), ChannelValue::Bit(true)).unwrap();
+        c.set_output(&addr(2), ChannelValue::Bit(true)).unwrap();
+        let out = c.next(&[], &[0]).unwrap();
+        assert_eq!(out, vec![0b0101]);
+
+        // The interval since the last change is not elapsed yet.
+        c.set_output(&addr(0), ChannelValue::Bit(false)).unwrap();
+        c.set_output(&addr(2), ChannelValue::Bit(false)).unwrap();
+        let out = c.next(&[], &out).unwrap();
+        assert_eq!(out, vec![0b0001]);
+        assert_eq!(c.pending_writes().len(), 1);
+
+        // Writing the current value is not a change.
+        c.set_output(&addr(0), ChannelValue::Bit(true)).unwrap();
+        c.set_output(&addr(1), ChannelValue::Bit(false)).unwrap();
+        let out = c.next(&[], &out).unwrap();
+        assert_eq!(out, vec![0b0001]);
+        assert!(c.pending_writes().is_empty());
+
+        c.set_output(&addr(0), ChannelValue::Bit(false)).unwrap();
+        clock.advance(Duration::from_secs(3599));
+        let out = c.next(&[], &out).unwrap();
+        assert_eq!(out, vec![0b0001]);
+        clock.advance(Duration::from_secs(1));
+        let out = c.next(&[], &out).unwrap();
+        assert_eq!(out, vec![0b0000]);
+
+        c.set_output(&addr(0), ChannelValue::Bit(true)).unwrap();
+        let out = c.next(&[], &out).unwrap();
+        assert_eq!(out, vec![0b0000]);
+        c.set_min_change_interval(&addr(0), None).unwrap();
+        let out = c.next(&[], &out).unwrap();
+        assert_eq!(out, vec![0b0001]);
+    }
+
+    #[test]
+    fn evaluate_voting_groups() {
+        let cfg = CouplerConfig {
+            modules: vec![ModuleType::UR20_4DI_P, ModuleType::UR20_4DO_P],
+            offsets: vec![0xFFFF, 0x0000, 0x8000, 0xFFFF],
+            params: vec![vec![0; 4], vec![0; 4]],
+        };
+        let mut c = Coupler::new(&cfg).unwrap();
+        use crate::voting::VotingLogic;
+
+        let addr = |channel| Address { module: 0, channel };
+        let group = VotingGroup::two_out_of_three(addr(0), addr(1), addr(2));
+        assert_eq!(c.add_voting_group(group).unwrap(), 0);
+        let invalid = VotingGroup::two_out_of_three(addr(0), addr(1), addr(4));
+        assert!(c.add_voting_group(invalid).is_err());
+        let unsafe_group = VotingGroup {
+            inputs: vec![addr(0), addr(1)],
+            logic: VotingLogic::MOutOfN(0),
+        };
+        assert_eq!(
+            c.add_voting_group(unsafe_group).err().unwrap(),
+            Error::ChannelParameter
+        );
+        let unsafe_group = VotingGroup {
+            inputs: vec![addr(0), addr(1)],
+            logic: VotingLogic::MOutOfN(3),
+        };
+        assert_eq!(
+            c.add_voting_group(unsafe_group).err().unwrap(),
+            Error::ChannelParameter
+        );
+        let output = VotingGroup::two_out_of_three(
+            addr(0),
+            addr(1),
+            Address {
+                module: 1,
+                channel: 0,
+            },
+        );
+        assert_eq!(
+            c.add_voting_group(output).err().unwrap(),
+            Error::ChannelType
+        );
+        assert!(c.voting_results().is_empty());
+
+        c.next(&[0b0011], &[0]).unwrap();
+        assert_eq!(
+            c.voting_results(),
+            &[VotingResult {
+                value: Some(true),
+                discrepancy: true,
+            }]
+        );
+        c.next(&[0b1000], &[0]).unwrap();
+        assert_eq!(
+            c.voting_results(),
+            &[VotingResult {
+                value: Some(false),
+                discrepancy: false,
+            }]
+        );
+    }
+
+    #[test]
+    fn read_and_write_bit_groups() {
+        use crate::bitfield::{BitEncoding, BitGroup};
+
+        let cfg = CouplerConfig {
+            modules: vec![
+                ModuleType::UR20_4DI_P,
+                ModuleType::UR20_8DO_P,
+                ModuleType::UR20_4RO_CO_255,
             ],
-            vec![],
+            offsets: vec![0xFFFF, 0x0000, 0x8000, 0xFFFF, 0x8008, 0xFFFF],
+            params: vec![vec![0; 4], vec![0; 8], vec![0; 4]],
+        };
+        let mut c = Coupler::new(&cfg).unwrap();
+        let addr = |module, channel| Address { module, channel };
+        let channels = |module, n| (0..n).map(|ch| addr(module, ch)).collect::<Vec<_>>();
+
+        let selector = BitGroup::new("selector", channels(0, 4), BitEncoding::Bcd);
+        c.add_bit_group(selector.clone()).unwrap();
+        assert_eq!(c.add_bit_group(selector), Err(Error::ChannelParameter));
+        let mut display = channels(1, 8);
+        display.extend(channels(2, 4));
+        c.add_bit_group(BitGroup::new("display", display, BitEncoding::Binary))
+            .unwrap();
+        let mixed = vec![addr(0, 0), addr(1, 0)];
+        assert_eq!(
+            c.add_bit_group(BitGroup::new("mixed", mixed, BitEncoding::Binary)),
+            Err(Error::ChannelType)
+        );
+        let invalid = vec![addr(0, 4)];
+        assert_eq!(
+            c.add_bit_group(BitGroup::new("invalid", invalid, BitEncoding::Binary)),
+            Err(Error::Address)
+        );
+
+        assert_eq!(c.bit_group_value("selector"), None);
+        let out = c.next(&[0b0111], &[0, 0]).unwrap();
+        assert_eq!(c.bit_group_value("selector"), Some(7));
+        assert_eq!(c.bit_group_value("display"), Some(0));
+        assert_eq!(c.bit_group_value("foo"), None);
+
+        c.set_bit_group_value("display", 0x5A3).unwrap();
+        assert!(c.set_bit_group_value("display", 0x1000).is_err());
+        assert!(c.set_bit_group_value("selector", 1).is_err());
+        assert_eq!(c.set_bit_group_value("foo", 1), Err(Error::Address));
+        let out = c.next(&[0b0111], &out).unwrap();
+        assert_eq!(out, vec![0x05A3]);
+        c.next(&[0b0111], &out).unwrap();
+        assert_eq!(c.bit_group_value("display"), Some(0x5A3));
+    }
+
+    #[test]
+    fn test_diagnosis_addresses_and_register_counts() {
+        assert_eq!(
+            diagnosis_addresses_and_register_counts(3),
+            vec![(0x2400, 8), (0x2408, 8), (0x2410, 8)]
+        );
+    }
+
+    #[test]
+    fn decode_module_diagnosis() {
+        let cfg = CouplerConfig {
+            modules: vec![ModuleType::UR20_4DI_P, ModuleType::UR20_16DO_P],
+            offsets: vec![0xFFFF, 0x0000, 0x8000, 0xFFFF],
+            params: vec![vec![0; 4], vec![]],
+        };
+        let mut c = Coupler::new(&cfg).unwrap();
+        assert!(c.diagnosis().is_empty());
+        assert!(c.update_diagnostics(&[vec![0; 8]]).is_err());
+        let mut data = vec![vec![0; 8]; 2];
+        data[1][0] = 0b1001;
+        data[1][2] = 0b0010;
+        data[1][4] = 0b0010 << 8;
+        c.update_diagnostics(&data).unwrap();
+        assert!(c.diagnosis()[0].is_ok());
+        let d = &c.diagnosis()[1];
+        assert!(d.channel_error);
+        assert_eq!(d.channels.len(), 16);
+        assert_eq!(
+            d.channels[1],
+            vec![crate::diagnosis::ChannelDiagnosis::ShortCircuit]
+        );
+    }
+
+    #[test]
+    fn decode_module_specific_diagnosis() {
+        use crate::diagnosis::ChannelDiagnosis;
+        let mut params = vec![0; 21];
+        params[1] = 1; // CH 0 diagnostics
+        params[3] = 1; // CH 0 line break
+        let cfg = CouplerConfig {
+            modules: vec![ModuleType::UR20_4AI_UI_16_DIAG],
+            offsets: vec![0xFFFF, 0x0000],
+            params: vec![params],
+        };
+        let mut c = Coupler::new(&cfg).unwrap();
+        let mut data = vec![0; 8];
+        data[0] = 0b1001;
+        data[2] = 0b0011;
+        data[4] = 0b0100_0000_0100_0010;
+        c.update_diagnostics(&[data]).unwrap();
+        let d = &c.diagnosis()[0];
+        assert!(d.channel_error);
+        assert_eq!(
+            d.channels,
+            vec![vec![ChannelDiagnosis::WireBreak], vec![], vec![], vec![]]
+        );
+    }
+
+    #[test]
+    fn compare_process_images() {
+        let cfg = CouplerConfig {
+            modules: vec![ModuleType::UR20_4DI_P, ModuleType::UR20_16DO_P],
+            offsets: vec![0xFFFF, 0x0000, 0x8000, 0xFFFF],
+            params: vec![vec![0; 4], vec![]],
+        };
+        let c = Coupler::new(&cfg).unwrap();
+        let a = ProcessImage {
+            input: vec![0b0001, 7],
+            output: vec![0],
+        };
+        let mut b = ProcessImage {
+            input: vec![0b0011, 9],
+            output: vec![0b0100],
+        };
+        assert!(c.diff_images(&a, &a).unwrap().is_empty());
+        assert_eq!(
+            c.diff_images(&a, &b).unwrap(),
             vec![
-                ChannelValue::Bit(false),
-                ChannelValue::Bit(false),
-                ChannelValue::Bit(false),
-                ChannelValue::Bit(false),
+                ChannelDiff::Channel {
+                    area: ImageArea::Input,
+                    address: Address {
+                        module: 0,
+                        channel: 1
+                    },
+                    a: ChannelValue::Bit(false),
+                    b: ChannelValue::Bit(true),
+                },
+                ChannelDiff::Channel {
+                    area: ImageArea::Output,
+                    address: Address {
+                        module: 1,
+                        channel: 2
+                    },
+                    a: ChannelValue::Bit(false),
+                    b: ChannelValue::Bit(true),
+                },
+                ChannelDiff::Word {
+                    area: ImageArea::Input,
+                    index: 1,
+                    a: Some(7),
+                    b: Some(9),
+                },
+            ]
+        );
+        b.output.clear();
+        assert!(c.diff_images(&a, &b).is_err());
+    }
+
+    #[test]
+    fn create_shutdown_plan() {
+        let cfg = CouplerConfig {
+            modules: vec![
+                ModuleType::UR20_4DI_P,
+                ModuleType::UR20_4DO_P,
+                ModuleType::UR20_4AO_UI_16,
             ],
-        ];
+            offsets: vec![0xFFFF, 0x0000, 0x8000, 0xFFFF, 0x8010, 0xFFFF],
+            params: vec![
+                vec![0; 4],
+                vec![0; 4],
+                vec![1, 0, 0, 1, 0, 0, 1, 0, 0, 1, 0, 0],
+            ],
+        };
+        let mut c = Coupler::new(&cfg).unwrap();
+        assert_eq!(c.shutdown_plan().unwrap(), vec![0; 5]);
+        let addr = |module, channel| Address { module, channel };
+        c.set_safe_state(&addr(1, 2), ChannelValue::Bit(true))
+            .unwrap();
+        c.set_safe_state(&addr(2, 1), ChannelValue::Decimal32(10.0))
+            .unwrap();
+        assert_eq!(c.shutdown_plan().unwrap(), vec![0b0100, 0, 0x3600, 0, 0]);
+        assert!(c
+            .set_safe_state(&addr(0, 0), ChannelValue::Bit(true))
+            .is_err());
+        assert!(c
+            .set_safe_state(&addr(1, 4), ChannelValue::Bit(true))
+            .is_err());
+        c.set_safe_state(&addr(1, 0), ChannelValue::Decimal32(1.0))
+            .unwrap();
+        assert!(c.shutdown_plan().is_err());
+    }
 
-        let mod0: &dyn ProcessModbusTcpData = &m0;
-        let mod1: &dyn ProcessModbusTcpData = &m1;
-        let mod2: &dyn ProcessModbusTcpData = &m2;
+    #[test]
+    fn hold_outputs_during_startup() {
+        let cfg = CouplerConfig {
+            modules: vec![ModuleType::UR20_4DO_P],
+            offsets: vec![0x8000, 0xFFFF],
+            params: vec![vec![0; 4]],
+        };
+        let mut c = Coupler::new(&cfg).unwrap();
+        let addr = Address {
+            module: 0,
+            channel: 1,
+        };
+        c.set_startup_hold_off(2);
+        assert_eq!(c.init_state(), InitState::Uninitialized);
+        c.set_output(&addr, ChannelValue::Bit(true)).unwrap();
 
-        let addr_out_0 = to_bit_address(ADDR_PACKED_PROCESS_OUTPUT_DATA + 0, 0);
-        let addr_in_1 = to_bit_address(ADDR_PACKED_PROCESS_INPUT_DATA, 0);
-        let addr_out_2 = to_bit_address(ADDR_PACKED_PROCESS_OUTPUT_DATA + 1, 8);
+        assert_eq!(c.next(&[], &[0b0100]).unwrap(), vec![0b0100]);
+        assert_eq!(c.init_state(), InitState::HoldOff { remaining: 1 });
+        assert_eq!(c.next(&[], &[0b0100]).unwrap(), vec![0b0100]);
+        assert_eq!(c.init_state(), InitState::Ready);
+        assert_eq!(c.next(&[], &[0b0100]).unwrap(), vec![0b0110]);
+
+        c.set_startup_hold_off(5);
+        c.set_output(&addr, ChannelValue::Bit(false)).unwrap();
+        assert_eq!(c.next(&[], &[0b0110]).unwrap(), vec![0b0110]);
+        c.release_hold_off();
+        assert_eq!(c.init_state(), InitState::Ready);
+        assert_eq!(c.next(&[], &[0b0110]).unwrap(), vec![0b0100]);
+    }
+
+    #[test]
+    fn throttle_diagnosis_events() {
+        let cfg = CouplerConfig {
+            modules: vec![ModuleType::UR20_4DI_P, ModuleType::UR20_4DO_P],
+            offsets: vec![0xFFFF, 0x0000, 0x8000, 0xFFFF],
+            params: vec![vec![0; 4], vec![0; 4]],
+        };
+        let mut c = Coupler::new(&cfg).unwrap();
+        let clock = FakeClock::new();
+        c.set_clock(Box::new(clock.clone()));
+        let ok = vec![vec![0; 8]; 2];
+        let mut fault = ok.clone();
+        fault[1][0] = 0x0001;
+
+        c.update_diagnostics(&ok).unwrap();
+        assert!(c.take_events().is_empty());
+        c.update_diagnostics(&fault).unwrap();
+        c.update_diagnostics(&ok).unwrap();
+        assert_eq!(c.take_events().len(), 2);
+
+        let interval = Duration::from_millis(50);
+        c.set_diagnosis_event_interval(Some(interval));
+        for _ in 0..5 {
+            c.update_diagnostics(&fault).unwrap();
+            c.update_diagnostics(&ok).unwrap();
+        }
+        c.update_diagnostics(&fault).unwrap();
+        assert!(c.take_events().is_empty());
+
+        clock.advance(interval);
+        match c.take_events()[..] {
+            [CouplerEvent::Diagnosis(ref ev)] => {
+                assert_eq!(ev.module, 1);
+                assert_eq!(ev.count, 11);
+                assert!(ev.diagnosis.module_error);
+                assert_eq!(ev.last, ev.first);
+            }
+            ref x => panic!("unexpected events: {:?}", x),
+        }
+    }
+
+    #[test]
+    fn detect_input_changes() {
+        let cfg = CouplerConfig {
+            modules: vec![ModuleType::UR20_4DI_P, ModuleType::UR20_4AI_UI_16_DIAG],
+            offsets: vec![0xFFFF, 0x0000, 0xFFFF, 0x0010],
+            params: vec![vec![0; 4], vec![0; 21]],
+        };
+        let mut c = Coupler::new(&cfg).unwrap();
+        let addr = |module, channel| Address { module, channel };
+        c.next(&[0b0001, 0, 0, 0, 0], &[]).unwrap();
+        c.next(&[0b0010, 0, 0, 0, 0], &[]).unwrap();
+        assert!(c.take_events().is_empty());
+
+        c.set_input_events(true);
+        c.next(&[0b0010, 0, 0, 0, 0], &[]).unwrap();
+        assert!(c.take_events().is_empty());
+        c.next(&[0b0100, 0, 0x2000, 0, 0], &[]).unwrap();
+        assert_eq!(
+            c.take_events(),
+            vec![
+                CouplerEvent::Input(ChannelEvent::Falling(addr(0, 1))),
+                CouplerEvent::Input(ChannelEvent::Rising(addr(0, 2))),
+                CouplerEvent::Input(ChannelEvent::ValueChanged {
+                    address: addr(1, 1),
+                    old: ChannelValue::Decimal32(0.0),
+                    new: ChannelValue::Decimal32(10.0),
+                }),
+            ]
+        );
+    }
+
+    #[test]
+    fn measure_module_timing() {
+        let cfg = CouplerConfig {
+            modules: vec![ModuleType::UR20_4DI_P, ModuleType::UR20_4DO_P],
+            offsets: vec![0xFFFF, 0x0000, 0x8000, 0xFFFF],
+            params: vec![vec![0; 4], vec![0; 4]],
+        };
+        let mut c = Coupler::new(&cfg).unwrap();
+        c.next(&[0], &[0]).unwrap();
+        assert!(c.timing_report().is_empty());
+
+        c.set_timing(true);
+        for i in 0..3 {
+            c.next(&[i], &[0]).unwrap();
+        }
+        let report = c.timing_report();
+        assert_eq!(report.len(), 2);
+        assert!(report[0].max >= report[1].max);
+        for t in &report {
+            assert_eq!(t.cycles, 3);
+            assert!(t.total >= t.max);
+            assert!(t.mean() <= t.max);
+            assert!(t.decode + t.encode <= t.max);
+        }
+        let out = report.iter().find(|t| t.module == 1).unwrap();
+        assert_eq!(out.module_type, ModuleType::UR20_4DO_P);
+
+        c.set_timing(false);
+        assert!(c.timing_report().is_empty());
+    }
+
+    #[test]
+    fn degrade_on_truncated_image() {
+        let cfg = CouplerConfig {
+            modules: vec![ModuleType::UR20_4DI_P, ModuleType::UR20_4DI_P],
+            offsets: vec![0xFFFF, 0x0000, 0xFFFF, 0x0010],
+            params: vec![vec![0; 4], vec![0; 4]],
+        };
+        let mut c = Coupler::new(&cfg).unwrap();
+        c.next(&[0b0101], &[]).unwrap();
+        assert_eq!(c.quality(0), Some(Quality::Good));
+        assert_eq!(c.quality(1), Some(Quality::Unavailable));
+        assert_eq!(c.quality(2), None);
+        assert_eq!(c.inputs()[0][0], ChannelValue::Bit(true));
+        assert_eq!(c.inputs()[1], vec![ChannelValue::None; 4]);
+        assert_eq!(
+            c.take_events(),
+            vec![CouplerEvent::ImageTruncated {
+                input: true,
+                expected: 2,
+                actual: 1,
+                modules: vec![1],
+            }]
+        );
+        c.next(&[0b0101], &[]).unwrap();
+        assert!(c.take_events().is_empty());
+        c.next(&[0b0101, 0b0001], &[]).unwrap();
+        assert_eq!(c.quality(1), Some(Quality::Good));
+        assert_eq!(c.inputs()[1][0], ChannelValue::Bit(true));
+    }
+
+    #[test]
+    fn clone_coupler_config() {
+        let cfg = CouplerConfig {
+            modules: vec![
+                ModuleType::UR20_4DI_P,
+                ModuleType::UR20_4DO_P,
+                ModuleType::UR20_4AO_UI_16,
+            ],
+            offsets: vec![0xFFFF, 0x0000, 0x8000, 0xFFFF, 0x8010, 0xFFFF],
+            params: vec![vec![0; 4], vec![1, 0, 0, 1], vec![0; 12]],
+        };
+        let c = Coupler::new(&cfg).unwrap();
+        let clone = c.clone_config();
+        assert_eq!(clone.modules, cfg.modules);
+        assert_eq!(clone.offsets, cfg.offsets);
+        assert_eq!(clone.params, cfg.params);
+        assert!(Coupler::new(&clone).is_ok());
+    }
+
+    #[test]
+    fn ingest_output_readback_with_shared_register() {
+        let cfg = CouplerConfig {
+            modules: vec![
+                ModuleType::UR20_4DO_P,
+                ModuleType::UR20_4DO_P,
+                ModuleType::UR20_4AO_UI_16,
+            ],
+            offsets: vec![0x8000, 0xFFFF, 0x8008, 0xFFFF, 0x8010, 0xFFFF],
+            params: vec![vec![0; 4], vec![0; 4], vec![0; 12]],
+        };
+        let mut c = Coupler::new(&cfg).unwrap();
+        assert_eq!(c.process_input_len(), 0);
+        assert_eq!(c.process_output_len(), 5);
+        assert!(c.ingest_output_readback(&[0x0501]).is_err());
+        c.ingest_output_readback(&[0x0501, 0, 0, 0, 0]).unwrap();
+        use crate::ChannelValue::*;
+        assert_eq!(
+            c.outputs()[0],
+            vec![Bit(true), Bit(false), Bit(false), Bit(false)]
+        );
+        assert_eq!(
+            c.outputs()[1],
+            vec![Bit(true), Bit(false), Bit(true), Bit(false)]
+        );
+        assert_eq!(c.outputs()[2].len(), 4);
+    }
+
+    #[test]
+    fn calibrate_analog_channels() {
+        let cfg = CouplerConfig {
+            modules: vec![ModuleType::UR20_4AI_UI_16_DIAG, ModuleType::UR20_4AO_UI_16],
+            offsets: vec![0xFFFF, 0x0000, 0x8000, 0xFFFF],
+            params: vec![vec![0; 21], vec![0; 12]],
+        };
+        let mut c = Coupler::new(&cfg).unwrap();
+        let ai = Address {
+            module: 0,
+            channel: 0,
+        };
+        let ao = Address {
+            module: 1,
+            channel: 0,
+        };
+        assert!(c.set_calibration(&ai, 0.5, 0.0).is_err());
+        assert!(c
+            .set_calibration(
+                &Address {
+                    module: 2,
+                    channel: 0
+                },
+                0.5,
+                1.0
+            )
+            .is_err());
+        let input = [0x3600, 0, 0, 0];
+        c.set_output(&ao, ChannelValue::Decimal32(5.0)).unwrap();
+        let raw_output = c.next(&input, &[0; 4]).unwrap();
+        let raw_input = match c.inputs()[0][0] {
+            ChannelValue::Decimal32(v) => v,
+            _ => panic!("unexpected value"),
+        };
+
+        c.set_calibration(&ai, 0.5, 2.0).unwrap();
+        c.set_calibration(&ao, 1.0, 2.0).unwrap();
+        c.set_output(&ao, ChannelValue::Decimal32(11.0)).unwrap();
+        assert_eq!(c.next(&input, &[0; 4]).unwrap(), raw_output);
+        assert_eq!(
+            c.inputs()[0][0],
+            ChannelValue::Decimal32(raw_input * 2.0 + 0.5)
+        );
+        assert_eq!(c.outputs()[1][0], ChannelValue::Decimal32(1.0));
+
+        let saved = c.calibrations();
+        assert_eq!(saved.len(), 2);
+        assert_eq!(saved[0].address, ai);
+        c.remove_calibration(&ai);
+        assert_eq!(c.calibrations().len(), 1);
+        c.restore_calibrations(&saved).unwrap();
+        assert_eq!(c.calibrations(), saved);
+
+        let cfg = CouplerConfig {
+            modules: vec![ModuleType::UR20_4DI_P],
+            offsets: vec![0xFFFF, 0x0000],
+            params: vec![vec![0; 4]],
+        };
+        let mut c = Coupler::new(&cfg).unwrap();
+        assert_eq!(
+            c.set_calibration(&ai, 0.5, 2.0).err(),
+            Some(Error::ChannelType)
+        );
+        assert_eq!(
+            c.restore_calibrations(&saved[..1]).err(),
+            Some(Error::ChannelType)
+        );
+        assert!(c.calibrations().is_empty());
+    }
 
-        let o0 = ModuleOffset {
-            input: None,
-            output: Some(addr_out_0),
+    #[test]
+    fn override_data_format() {
+        // All channels are parameterized as S5 (0...20mA).
+        let cfg = CouplerConfig {
+            modules: vec![ModuleType::UR20_4AI_UI_16_DIAG, ModuleType::UR20_4AO_UI_16],
+            offsets: vec![0xFFFF, 0x0000, 0x8000, 0xFFFF],
+            params: vec![vec![0; 21], vec![0; 12]],
         };
-        let o1 = ModuleOffset {
-            input: Some(addr_in_1),
-            output: None,
+        let mut c = Coupler::new(&cfg).unwrap();
+        let ai_1 = Address {
+            module: 0,
+            channel: 1,
         };
-        let o2 = ModuleOffset {
-            input: None,
-            output: Some(addr_out_2),
+        let ao = Address {
+            module: 1,
+            channel: 0,
+        };
+        let input = [0x3600, 0x3600, 0, 0];
+        let invalid = Address {
+            module: 2,
+            channel: 0,
         };
+        assert_eq!(
+            c.set_data_format_override(Some(DataFormat::S7), &[invalid]),
+            Err(Error::Address)
+        );
+        assert_eq!(c.data_format_override(), Option::None);
+        c.set_data_format_override(Some(DataFormat::S7), &[ai_1])
+            .unwrap();
+        assert_eq!(c.data_format_override(), Some(&DataFormat::S7));
 
-        let modules = vec![(mod0, &o0), (mod1, &o1), (mod2, &o2)];
-        assert!(process_output_values(&modules, &values).is_err());
+        c.set_output(&ao, ChannelValue::Decimal32(10.0)).unwrap();
+        let output = c.next(&input, &[0; 4]).unwrap();
+        assert_eq!(output[0], 0x3600);
+        assert_eq!(c.inputs()[0][0], ChannelValue::Decimal32(10.0));
+        assert_eq!(c.inputs()[0][1], ChannelValue::Decimal32(16.875));
+
+        c.set_data_format_override(None, &[]).unwrap();
+        c.next(&input, &output).unwrap();
+        assert_eq!(c.inputs()[0][0], ChannelValue::Decimal32(16.875));
+        assert_eq!(c.outputs()[1][0], ChannelValue::Decimal32(16.875));
+    }
+
+    #[derive(Debug)]
+    struct Interlock;
+
+    impl OutputGuard for Interlock {
+        fn check(&mut self, outputs: &mut [Vec<ChannelValue>]) -> GuardDecision {
+            use crate::ChannelValue::*;
+            if outputs[0][0] == Bit(true) && outputs[0][1] == Bit(true) {
+                return GuardDecision::Veto("valve A and B are open".into());
+            }
+            if outputs[0][3] == Bit(true) {
+                outputs[0][3] = Bit(false);
+                return GuardDecision::Modified("channel 3 is locked".into());
+            }
+            GuardDecision::Accept
+        }
     }
 
     #[test]
-    fn test_process_output_values_with_invalid_offset_c() {
-        let m0 = super::ur20_4ao_ui_16::Mod::default();
-        let values = vec![vec![
-            ChannelValue::Decimal32(15.0),
-            ChannelValue::Decimal32(20.0),
-            ChannelValue::Decimal32(20.0),
-            ChannelValue::Decimal32(10.0),
-        ]];
-        let mod0: &dyn ProcessModbusTcpData = &m0;
-        let addr_out_0 = to_bit_address(0, 0);
-        let o0 = ModuleOffset {
-            input: None,
-            output: Some(addr_out_0),
+    fn guard_outputs() {
+        let cfg = CouplerConfig {
+            modules: vec![ModuleType::UR20_4DO_P],
+            offsets: vec![0x8000, 0xFFFF],
+            params: vec![vec![0; 4]],
         };
-        let modules = vec![(mod0, &o0)];
-        assert!(process_output_values(&modules, &values).is_err());
+        let mut c = Coupler::new(&cfg).unwrap();
+        c.set_output_guard(Some(Box::new(Interlock)));
+        let addr = |channel| Address { module: 0, channel };
+
+        c.set_output(&addr(0), ChannelValue::Bit(true)).unwrap();
+        assert_eq!(c.next(&[], &[0]).unwrap(), vec![0b0001]);
+        assert!(c.take_events().is_empty());
+
+        c.set_output(&addr(1), ChannelValue::Bit(true)).unwrap();
+        c.set_output(&addr(2), ChannelValue::Bit(true)).unwrap();
+        assert_eq!(c.next(&[], &[0b0001]).unwrap(), vec![0b0001]);
+        assert_eq!(
+            c.take_events(),
+            vec![CouplerEvent::OutputsVetoed("valve A and B are open".into())]
+        );
+
+        c.set_output(&addr(3), ChannelValue::Bit(true)).unwrap();
+        assert_eq!(c.next(&[], &[0b0001]).unwrap(), vec![0b0001]);
+        assert_eq!(
+            c.take_events(),
+            vec![CouplerEvent::OutputsModified("channel 3 is locked".into())]
+        );
+
+        c.set_output_guard(None);
+        c.set_output(&addr(3), ChannelValue::Bit(true)).unwrap();
+        assert_eq!(c.next(&[], &[0b0001]).unwrap(), vec![0b1001]);
+    }
+
+    /// Switches output 0 on while input 0 is on
+    /// and counts the scans on output 1.
+    #[derive(Debug, Default)]
+    struct Follower {
+        scans: usize,
+    }
+
+    impl CyclicTask for Follower {
+        fn scan(&mut self, io: &mut dyn crate::task::IoAccess) {
+            self.scans += 1;
+            let input = io.input(&Address {
+                module: 0,
+                channel: 0,
+            });
+            let on = input == Some(&ChannelValue::Bit(true));
+            let out = |channel| Address { module: 1, channel };
+            io.set_output(&out(0), ChannelValue::Bit(on)).unwrap();
+            io.set_output(&out(1), ChannelValue::Bit(self.scans % 2 == 1))
+                .unwrap();
+            assert!(io.set_output(&out(4), ChannelValue::Bit(true)).is_err());
+        }
     }
 
     #[test]
-    fn test_process_output_values() {
-        let mut m0 = super::ur20_4ao_ui_16::Mod::default();
-        let m1 = super::ur20_4ai_rtd_diag::Mod::default();
-        let m2 = super::ur20_4do_p::Mod::default();
-        let m3 = super::ur20_4do_p::Mod::default();
+    fn run_cyclic_tasks() {
+        let cfg = CouplerConfig {
+            modules: vec![ModuleType::UR20_4DI_P, ModuleType::UR20_4DO_P],
+            offsets: vec![0xFFFF, 0x0000, 0x8000, 0xFFFF],
+            params: vec![vec![0; 4], vec![0; 4]],
+        };
+        let mut c = Coupler::new(&cfg).unwrap();
+        c.add_task(Box::new(Follower::default()));
+        assert_eq!(c.next(&[0b0001], &[0]).unwrap(), vec![0b0011]);
+        assert_eq!(c.next(&[0b0000], &[0b0011]).unwrap(), vec![0b0000]);
+        c.clear_tasks();
+        assert_eq!(c.next(&[0b0001], &[0b0010]).unwrap(), vec![0b0010]);
+    }
 
-        let values = vec![
-            vec![
-                ChannelValue::Decimal32(15.0),
-                ChannelValue::Decimal32(20.0),
-                ChannelValue::Decimal32(20.0),
-                ChannelValue::Decimal32(10.0),
+    #[test]
+    fn reject_heartbeat_on_non_output_channels() {
+        use crate::heartbeat::HeartbeatMode;
+        use crate::ur20_1com_232_485_422::*;
+        use num_traits::ToPrimitive;
+
+        #[rustfmt::skip]
+        let cfg = CouplerConfig {
+            modules: vec![
+                ModuleType::UR20_4DI_P,
+                ModuleType::UR20_4DO_P,
+                ModuleType::UR20_1COM_232_485_422,
             ],
-            vec![],
-            vec![
-                ChannelValue::Bit(false),
-                ChannelValue::Bit(true),
-                ChannelValue::Bit(false),
-                ChannelValue::Bit(false),
+            offsets: vec![
+                0xFFFF,
+                0x0000,
+                0x8000,
+                0xFFFF,
+                to_bit_address(0x0801, 0),
+                to_bit_address(0x0001, 0),
             ],
-            vec![
-                ChannelValue::Bit(false),
-                ChannelValue::Bit(false),
-                ChannelValue::Bit(true),
-                ChannelValue::Bit(true),
+            params: vec![
+                vec![0; 4],
+                vec![0; 4],
+                vec![
+                    ProcessDataLength::EightBytes.to_u16().unwrap(),
+                    OperatingMode::RS232.to_u16().unwrap(),
+                    0, 0, 0, 0, 0, 0, 0, 0,
+                ],
             ],
-        ];
-
-        m0.ch_params[1].output_range = AnalogUIRange::mA0To20;
-        m0.ch_params[3].output_range = AnalogUIRange::mA0To20;
-
-        let mod0: &dyn ProcessModbusTcpData = &m0;
-        let mod1: &dyn ProcessModbusTcpData = &m1;
-        let mod2: &dyn ProcessModbusTcpData = &m2;
-        let mod3: &dyn ProcessModbusTcpData = &m3;
-
-        let addr_out_0 = to_bit_address(ADDR_PACKED_PROCESS_OUTPUT_DATA, 0);
-        let addr_in_1 = to_bit_address(ADDR_PACKED_PROCESS_INPUT_DATA, 0);
-        let addr_out_2 = to_bit_address(ADDR_PACKED_PROCESS_OUTPUT_DATA + 4, 0);
-        let addr_out_3 = to_bit_address(ADDR_PACKED_PROCESS_OUTPUT_DATA + 4, 8);
-
-        let o0 = ModuleOffset {
-            input: None,
-            output: Some(addr_out_0),
         };
-        let o1 = ModuleOffset {
-            input: Some(addr_in_1),
-            output: None,
+        let mut c = Coupler::new(&cfg).unwrap();
+        let hb = |module| {
+            let addr = Address { module, channel: 0 };
+            Some(Heartbeat::new(
+                addr,
+                HeartbeatMode::Toggle,
+                Duration::from_millis(20),
+            ))
         };
-        let o2 = ModuleOffset {
-            input: None,
-            output: Some(addr_out_2),
+        let di = Address {
+            module: 0,
+            channel: 0,
         };
-        let o3 = ModuleOffset {
-            input: None,
-            output: Some(addr_out_3),
+        let com = Address {
+            module: 2,
+            channel: 0,
         };
+        assert_eq!(
+            c.set_heartbeat(hb(0)).err(),
+            Some(Error::NotAnOutput {
+                address: di,
+                module_type: ModuleType::UR20_4DI_P,
+            })
+        );
+        assert_eq!(
+            c.set_heartbeat(hb(2)).err(),
+            Some(Error::NotAnOutput {
+                address: com,
+                module_type: ModuleType::UR20_1COM_232_485_422,
+            })
+        );
+        c.set_owned_modules(Some(vec![2])).unwrap();
+        assert_eq!(c.set_heartbeat(hb(1)).err(), Some(Error::Address));
+        c.set_owned_modules(None).unwrap();
+        c.set_heartbeat(hb(1)).unwrap();
+    }
 
-        let modules = vec![(mod0, &o0), (mod1, &o1), (mod2, &o2), (mod3, &o3)];
+    #[test]
+    fn toggle_heartbeat() {
+        use crate::heartbeat::HeartbeatMode;
+        let cfg = CouplerConfig {
+            modules: vec![ModuleType::UR20_4DO_P],
+            offsets: vec![0x8000, 0xFFFF],
+            params: vec![vec![0; 4]],
+        };
+        let mut c = Coupler::new(&cfg).unwrap();
+        let clock = FakeClock::new();
+        c.set_clock(Box::new(clock.clone()));
+        let addr = Address {
+            module: 0,
+            channel: 3,
+        };
+        let invalid = Address {
+            module: 0,
+            channel: 4,
+        };
+        let timeout = Duration::from_millis(20);
+        let hb = Heartbeat::new(invalid, HeartbeatMode::Toggle, timeout);
+        assert!(c.set_heartbeat(Some(hb)).is_err());
+        let hb = Heartbeat::new(addr, HeartbeatMode::Toggle, timeout);
+        c.set_heartbeat(Some(hb)).unwrap();
+        assert!(!c.heartbeat_stalled());
 
-        let res = process_output_values(&modules, &values).unwrap();
-        assert_eq!(res.len(), 5);
-        assert_eq!(res[0], 0x0); // channel is disabled
-        assert_eq!(res[1], 0x6C00);
-        assert_eq!(res[2], 0x0); // channel is disabled
-        assert_eq!(res[3], 0x3600);
-        assert_eq!(res[4], 0b_0000_1100_0000_0010);
+        assert_eq!(c.next(&[], &[0]).unwrap(), vec![0b1000]);
+        assert_eq!(c.next(&[], &[0b1000]).unwrap(), vec![0b0000]);
+        assert!(!c.heartbeat_stalled());
+        assert!(c.take_events().is_empty());
+
+        clock.advance(timeout);
+        assert!(!c.heartbeat_stalled());
+        clock.advance(Duration::from_millis(10));
+        assert!(c.heartbeat_stalled());
+        assert_eq!(c.next(&[], &[0]).unwrap(), vec![0b1000]);
+        assert!(!c.heartbeat_stalled());
+        match c.take_events()[..] {
+            [CouplerEvent::HeartbeatStalled(d)] => assert_eq!(d, Duration::from_millis(30)),
+            ref x => panic!("unexpected events: {:?}", x),
+        }
     }
 
     #[test]
-    fn test_param_addresses_and_register_counts() {
-        assert_eq!(param_addresses_and_register_counts(&[]), vec![]);
+    fn summarize_station_health() {
+        let cfg = CouplerConfig {
+            modules: vec![ModuleType::UR20_4DI_P, ModuleType::UR20_4DO_P],
+            offsets: vec![0xFFFF, 0x0000, 0x8000, 0xFFFF],
+            params: vec![vec![0; 4], vec![0; 4]],
+        };
+        let mut c = Coupler::new(&cfg).unwrap();
+        match c.health() {
+            StationHealth::Failed { .. } => {}
+            x => panic!("unexpected health: {:?}", x),
+        }
+        c.next(&[0], &[0]).unwrap();
+        assert_eq!(c.health(), StationHealth::Ok);
+
+        c.next(&[0], &[]).unwrap();
         assert_eq!(
-            param_addresses_and_register_counts(&[ModuleType::UR20_4DI_P]),
-            vec![(0xC000, 4)]
+            c.health(),
+            StationHealth::Degraded {
+                details: vec!["module 1: data unavailable".into()]
+            }
         );
+        c.next(&[], &[]).unwrap();
         assert_eq!(
-            param_addresses_and_register_counts(&[
+            c.health(),
+            StationHealth::Failed {
+                reason: "no module data is available".into()
+            }
+        );
+
+        c.next(&[0], &[0]).unwrap();
+        let mut diagnosis = vec![vec![0; 8]; 2];
+        diagnosis[0][0] = 0x0001;
+        c.update_diagnostics(&diagnosis).unwrap();
+        assert_eq!(
+            c.health(),
+            StationHealth::Degraded {
+                details: vec!["module 0: diagnosis reports an error".into()]
+            }
+        );
+
+        c.set_driver_error(Some("timeout".into()));
+        assert_eq!(
+            c.health(),
+            StationHealth::Failed {
+                reason: "driver error: timeout".into()
+            }
+        );
+    }
+
+    #[test]
+    fn create_coupler_with_power_feed_modules() {
+        let cfg = CouplerConfig {
+            modules: vec![
+                ModuleType::UR20_PF_I,
                 ModuleType::UR20_4DI_P,
-                ModuleType::UR20_4DO_P,
-                ModuleType::UR20_4AI_RTD_DIAG,
-            ]),
-            vec![(0xC000, 4), (0xC100, 4), (0xC200, 29)]
+                ModuleType::UR20_PF_O,
+            ],
+            offsets: vec![0xFFFF, 0xFFFF, 0xFFFF, 0x0000, 0xFFFF, 0xFFFF],
+            params: vec![vec![], vec![0; 4], vec![]],
+        };
+        let mut c = Coupler::new(&cfg).unwrap();
+        assert_eq!(c.process_input_len(), 1);
+        c.next(&[0b0001], &[]).unwrap();
+        assert!(c.inputs()[0].is_empty());
+        assert_eq!(c.inputs()[1][0], ChannelValue::Bit(true));
+        assert_eq!(c.health(), StationHealth::Ok);
+
+        let mut diagnosis = vec![vec![0; 8]; 3];
+        diagnosis[2][0] = 0b0001_0000;
+        c.update_diagnostics(&diagnosis).unwrap();
+        assert!(c.diagnosis()[2].supply_voltage_missing);
+        assert_eq!(
+            c.health(),
+            StationHealth::Degraded {
+                details: vec!["module 2: diagnosis reports an error".into()]
+            }
         );
     }
 
     #[test]
-    fn validate_coupler_config_data() {
-        assert!(CouplerConfig {
-            modules: vec![],
-            offsets: vec![],
-            params: vec![],
-        }
-        .validate()
-        .is_ok());
-        assert!(CouplerConfig {
-            modules: vec![ModuleType::UR20_4DI_P],
-            offsets: vec![0xFFFF, 0x0000],
-            params: vec![vec![0; 4]],
-        }
-        .validate()
-        .is_ok());
-        assert!(CouplerConfig {
+    fn observe_coupler_state() {
+        let cfg = CouplerConfig {
             modules: vec![ModuleType::UR20_4DI_P],
             offsets: vec![0xFFFF, 0x0000],
-            params: vec![],
-        }
-        .validate()
-        .is_err());
-        assert!(CouplerConfig {
-            modules: vec![ModuleType::UR20_4DI_P],
-            offsets: vec![],
             params: vec![vec![0; 4]],
-        }
-        .validate()
-        .is_err());
-        assert!(CouplerConfig {
-            modules: vec![ModuleType::UR20_4DI_P],
-            offsets: vec![0xFFFF],
-            params: vec![],
-        }
-        .validate()
-        .is_err());
+        };
+        let mut c = Coupler::new(&cfg).unwrap();
+        c.next(&[0b0001], &[]).unwrap();
+        let o = c.observer();
+        let first = o.snapshot();
+        assert_eq!(first.inputs[0][0], ChannelValue::Bit(true));
+
+        let handle = o.clone();
+        c.next(&[0b0010], &[]).unwrap();
+        let second = std::thread::spawn(move || handle.snapshot())
+            .join()
+            .unwrap();
+        assert_eq!(second.inputs[0][0], ChannelValue::Bit(false));
+        assert_eq!(second.inputs[0][1], ChannelValue::Bit(true));
+        assert_eq!(first.inputs[0][0], ChannelValue::Bit(true));
+
+        c.update_diagnostics(&[vec![0; 8]]).unwrap();
+        assert_eq!(o.snapshot().diagnosis.len(), 1);
+    }
+
+    #[test]
+    fn detect_external_writer() {
+        let cfg = CouplerConfig {
+            modules: vec![ModuleType::UR20_4DO_P, ModuleType::UR20_4DO_P],
+            offsets: vec![0x8000, 0xFFFF, 0x8008, 0xFFFF],
+            params: vec![vec![0; 4], vec![0; 4]],
+        };
+        let mut c = Coupler::new(&cfg).unwrap();
+        c.set_output(
+            &Address {
+                module: 1,
+                channel: 0,
+            },
+            ChannelValue::Bit(true),
+        )
+        .unwrap();
+        let out = c.next(&[], &[0]).unwrap();
+        assert_eq!(out, vec![0x0100]);
+        assert!(c.take_events().is_empty());
+        let out = c.next(&[], &out).unwrap();
+        assert!(c.take_events().is_empty());
+        c.next(&[], &[out[0] | 0x0001]).unwrap();
+        assert_eq!(
+            c.take_events(),
+            vec![CouplerEvent::OutputMismatch { modules: vec![0] }]
+        );
+        assert!(c.output_mismatches().is_empty());
+    }
+
+    #[test]
+    fn verify_outputs() {
+        let cfg = CouplerConfig {
+            modules: vec![ModuleType::UR20_4DO_P, ModuleType::UR20_4DO_P],
+            offsets: vec![0x8000, 0xFFFF, 0x8008, 0xFFFF],
+            params: vec![vec![0; 4], vec![0; 4]],
+        };
+        let mut c = Coupler::new(&cfg).unwrap();
+        c.set_output_verification(true);
+        let addr = |module, channel| Address { module, channel };
+        c.set_output(&addr(1, 2), ChannelValue::Bit(true)).unwrap();
+        let out = c.next(&[], &[0]).unwrap();
+        assert!(c.output_mismatches().is_empty());
+        c.next(&[], &out).unwrap();
+        assert!(c.output_mismatches().is_empty());
+
+        // substitute values are active
+        c.next(&[], &[0x0001]).unwrap();
+        assert_eq!(
+            c.output_mismatches(),
+            &[
+                OutputMismatch {
+                    address: addr(0, 0),
+                    commanded: ChannelValue::Bit(false),
+                    actual: ChannelValue::Bit(true),
+                },
+                OutputMismatch {
+                    address: addr(1, 2),
+                    commanded: ChannelValue::Bit(true),
+                    actual: ChannelValue::Bit(false),
+                },
+            ]
+        );
+        c.set_output_verification(false);
+        assert!(c.output_mismatches().is_empty());
     }
 
     #[test]
-    fn create_new_coupler_instance() {
+    fn leave_outputs_of_other_masters_untouched() {
         let cfg = CouplerConfig {
-            modules: vec![ModuleType::UR20_4DI_P, ModuleType::UR20_1COM_232_485_422],
-            offsets: vec![0xFFFF, 0x0000, 0x8000, 0x0008],
-            params: vec![vec![0; 4], vec![0; 10]],
+            modules: vec![
+                ModuleType::UR20_4DO_P,
+                ModuleType::UR20_4DO_P,
+                ModuleType::UR20_4AO_UI_16,
+            ],
+            offsets: vec![0x8000, 0xFFFF, 0x8008, 0xFFFF, 0x8010, 0xFFFF],
+            params: vec![vec![0; 4], vec![0; 4], vec![0; 12]],
         };
+        let mut c = Coupler::new(&cfg).unwrap();
+        assert!(c.set_owned_modules(Some(vec![3])).is_err());
+        c.set_owned_modules(Some(vec![0])).unwrap();
+        let addr = |module| Address { module, channel: 0 };
+        assert!(c.set_output(&addr(1), ChannelValue::Bit(true)).is_err());
+        c.set_output(&addr(0), ChannelValue::Bit(true)).unwrap();
+        assert_eq!(c.owned_output_ranges(), vec![0..1]);
+        assert_eq!(c.next(&[], &[0x0500]).err(), Some(Error::BufferLength));
+        let readback = [0x0500, 1, 2, 3, 4];
+        assert_eq!(c.next(&[], &readback).unwrap(), vec![0x0501, 1, 2, 3, 4]);
+        c.next(&[], &[0x0001, 1, 2, 3, 5]).unwrap();
+        assert!(c.take_events().is_empty());
+        c.set_safe_state(&addr(0), ChannelValue::Bit(true)).unwrap();
+        assert_eq!(c.shutdown_plan().unwrap(), vec![0x0001, 1, 2, 3, 5]);
 
-        let mut invalid_cfg = cfg.clone();
-        invalid_cfg.params = vec![];
-        let c = Coupler::new(&cfg).unwrap();
+        c.set_owned_modules(Some(vec![2])).unwrap();
+        assert_eq!(c.owned_output_ranges(), vec![1..5]);
+        c.set_owned_modules(Some(vec![1, 2])).unwrap();
+        assert_eq!(c.owned_output_ranges(), vec![0..5]);
+        assert_eq!(c.shutdown_plan().unwrap(), vec![0x0001, 0, 0, 0, 0]);
 
-        assert!(Coupler::new(&invalid_cfg).is_err());
-        assert_eq!(c.modules.len(), 2);
-        assert_eq!(c.processors.len(), 1);
-        assert_eq!(c.offsets.len(), 2);
-        assert_eq!(c.in_values.len(), 0);
-        assert_eq!(c.out_values.len(), 0);
-        assert_eq!(c.write.len(), 0);
+        c.set_owned_modules(None).unwrap();
+        c.set_output(&addr(1), ChannelValue::Bit(false)).unwrap();
+        assert_eq!(c.next(&[], &readback).unwrap()[0], 0x0400);
     }
 
     #[test]
-    fn process_in_out_data_with_coupler() {
-        use crate::ur20_1com_232_485_422::*;
-        use num_traits::ToPrimitive;
+    fn reparameterize_replaced_modules() {
+        let cfg = CouplerConfig {
+            modules: vec![ModuleType::UR20_4DI_P, ModuleType::UR20_4DO_P],
+            offsets: vec![0xFFFF, 0x0000, 0x8000, 0xFFFF],
+            params: vec![vec![1, 0, 0, 3], vec![1, 1, 0, 0]],
+        };
+        let mut c = Coupler::new(&cfg).unwrap();
+        assert_eq!(c.parameters(), &cfg.params[..]);
+        assert!(c.reparameterize(&cfg.modules, &[]).is_err());
+        assert!(c
+            .reparameterize(&cfg.modules, &cfg.params)
+            .unwrap()
+            .is_empty());
+
+        let modules = vec![ModuleType::UR20_4DI_P, ModuleType::UR20_4RO_CO_255];
+        let params = vec![vec![0; 4], vec![0; 4]];
+        assert_eq!(
+            c.reparameterize(&modules, &params).unwrap(),
+            vec![ParameterDownload {
+                module: 0,
+                address: 0xC000,
+                data: vec![1, 0, 0, 3],
+            }]
+        );
+        assert_eq!(
+            c.take_events(),
+            vec![CouplerEvent::ReparameterizationRequired(0)]
+        );
+
+        let defaults = default_module(&ModuleType::UR20_4DI_P)
+            .unwrap()
+            .to_modbus_parameter_data();
+        assert!(defaults.iter().any(|r| *r != 0));
+        let params = vec![defaults, vec![1, 1, 0, 0]];
+        assert_eq!(
+            c.reparameterize(&cfg.modules, &params).unwrap(),
+            vec![ParameterDownload {
+                module: 0,
+                address: 0xC000,
+                data: vec![1, 0, 0, 3],
+            }]
+        );
+    }
+
+    #[test]
+    fn keep_changed_parameters_when_reparameterizing() {
+        let cfg = CouplerConfig {
+            modules: vec![ModuleType::UR20_4DI_P, ModuleType::UR20_4DO_P],
+            offsets: vec![0xFFFF, 0x0000, 0x8000, 0xFFFF],
+            params: vec![vec![1, 0, 0, 3], vec![1, 1, 0, 0]],
+        };
+        let mut c = Coupler::new(&cfg).unwrap();
+        let params = vec![vec![4, 4, 0, 3], vec![0, 1, 0, 0]];
+        assert!(c.reparameterize(&cfg.modules, &params).unwrap().is_empty());
+        assert!(c.take_events().is_empty());
+    }
+
+    #[test]
+    fn decode_coupler_status() {
+        assert_eq!(CouplerStatus::from_register(0), CouplerStatus::default());
+        let s = CouplerStatus::from_register(0b0000_1100_0000_0001);
+        assert!(s.module_diagnosis);
+        assert!(s.force_mode);
+        assert!(s.fieldbus_error);
+        assert!(!s.module_bus_error);
+        assert!(!s.system_supply_undervoltage);
+        let s = CouplerStatus::from_register(0b0000_0011_0000_0110);
+        assert!(s.system_supply_undervoltage);
+        assert!(s.input_supply_undervoltage);
+        assert!(s.configuration_error);
+        assert!(s.module_bus_error);
+        assert!(!s.force_mode);
+
+        let mut c = Coupler::new(&CouplerConfig {
+            modules: vec![],
+            offsets: vec![],
+            params: vec![],
+        })
+        .unwrap();
+        assert_eq!(c.status(), None);
+        c.set_status(0x0400);
+        assert!(c.status().unwrap().force_mode);
+    }
 
+    #[test]
+    fn create_parameter_image() {
         let cfg = CouplerConfig {
             modules: vec![
                 ModuleType::UR20_4DI_P,
+                ModuleType::UR20_PF_O,
                 ModuleType::UR20_4DO_P,
-                ModuleType::UR20_1COM_232_485_422,
-            ],
-            offsets: vec![
-                0xFFFF,
-                0x0000,
-                0x8000,
-                0xFFFF,
-                to_bit_address(0x0801, 0),
-                to_bit_address(0x0001, 0),
-            ],
-            params: vec![
-                vec![0; 4],
-                vec![0; 4],
-                #[cfg_attr(rustfmt, rustfmt_skip)]
-                vec![
-                    ProcessDataLength::EightBytes.to_u16().unwrap(),
-                    OperatingMode::RS232.to_u16().unwrap(),
-                    0, 0, 0, 0, 0, 0, 0, 0,
-                ],
             ],
+            offsets: vec![0xFFFF, 0x0000, 0xFFFF, 0xFFFF, 0x8000, 0xFFFF],
+            params: vec![vec![1, 0, 0, 3], vec![], vec![1, 1, 0, 0]],
         };
-        let mut c = Coupler::new(&cfg).unwrap();
-        let process_input_data = vec![
-            0b_0101,               // module input for DI_P
-            0b_00000100_1111_0001, // len & status
-            0,                     // data
-            0xABCD,                // data
-            0,
-        ];
-        let process_output_data = vec![0b_11_00, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+        let c = Coupler::new(&cfg).unwrap();
+        assert_eq!(
+            c.parameter_image(),
+            vec![
+                (0xC000, vec![1, 0, 0, 3]),
+                (0xC100, vec![]),
+                (0xC200, vec![1, 1, 0, 0]),
+            ]
+        );
+    }
 
-        // make sure the initialization process evolves
-        let process_output_data = c.next(&process_input_data, &process_output_data).unwrap();
-        let process_output_data = c.next(&process_input_data, &process_output_data).unwrap();
-        let process_output_data = c.next(&process_input_data, &process_output_data).unwrap();
+    #[test]
+    fn simulate_decoding_of_sample_data() {
+        #[rustfmt::skip]
+        let params = [
+            0,             // frequency suppression
+            0, 0, 0, 1, 2, // CH 0: S7, 0..10V
+            0, 0, 0, 1, 2, // CH 1: S7, 0..10V
+            0, 0, 0, 1, 8, // CH 2: disabled
+            0, 0, 0, 1, 2, // CH 3: S7, 0..10V
+        ];
+        let values = simulate_decode(
+            &ModuleType::UR20_4AI_UI_16_DIAG,
+            &params,
+            &[0x6C00, 0x3600, 0, 0],
+        )
+        .unwrap();
+        assert_eq!(values[0], ChannelValue::Decimal32(10.0));
+        assert_eq!(values[1], ChannelValue::Decimal32(5.0));
+        assert_eq!(values[2], ChannelValue::Disabled);
+        assert!(simulate_decode(&ModuleType::UR20_4AI_UI_16_DIAG, &params, &[0]).is_err());
+        assert_eq!(
+            simulate_decode(&ModuleType::UR20_1COM_SAI_PRO, &[], &[])
+                .err()
+                .unwrap(),
+            Error::UnknownModule
+        );
+    }
 
-        {
-            let inputs = c.inputs();
-            let outputs = c.outputs();
+    #[test]
+    fn test_module_list_from_registers() {
+        assert_eq!(
+            module_list_from_registers(&[]).err().unwrap(),
+            Error::RegisterCount
+        );
+        assert_eq!(
+            module_list_from_registers(&[0xAB0C]).err().unwrap(),
+            Error::RegisterCount
+        );
+        assert_eq!(
+            module_list_from_registers(&[0x0101, 0x2FA0]).unwrap(),
+            vec![ModuleType::UR20_4DO_P]
+        );
+        assert_eq!(
+            module_list_from_registers(&[0x0101, 0x2FA3]).err().unwrap(),
+            Error::UnknownModule
+        );
+    }
 
-            assert_eq!(inputs.len(), 3);
-            assert_eq!(outputs.len(), 3);
+    #[test]
+    fn test_module_list_from_registers_tolerant() {
+        assert_eq!(
+            module_list_from_registers_tolerant(&[0xAB0C])
+                .err()
+                .unwrap(),
+            Error::RegisterCount
+        );
+        assert_eq!(
+            module_list_from_registers_tolerant(&[0x0101, 0x2FA0, 0x0101, 0x2FA3]).unwrap(),
+            vec![
+                (ModuleType::UR20_4DO_P, true),
+                (ModuleType::UR20_4DO_P, false)
+            ]
+        );
+        assert_eq!(
+            module_list_from_registers_tolerant(&[0xAB0C, 0x0000])
+                .err()
+                .unwrap(),
+            Error::UnknownModule
+        );
+    }
 
-            assert_eq!(inputs[0].len(), 4);
-            assert_eq!(outputs[0].len(), 4);
+    /// All module types that are supported by the coupler.
+    const SUPPORTED_MODULES: &[ModuleType] = &[
+        ModuleType::UR20_4DI_P,
+        ModuleType::UR20_4DI_P_3W,
+        ModuleType::UR20_8DI_P_2W,
+        ModuleType::UR20_8DI_P_3W,
+        ModuleType::UR20_8DI_P_3W_HD,
+        ModuleType::UR20_16DI_P,
+        ModuleType::UR20_16DI_P_PLC_INT,
+        ModuleType::UR20_4DI_N,
+        ModuleType::UR20_8DI_N_3W,
+        ModuleType::UR20_16DI_N,
+        ModuleType::UR20_16DI_N_PLC_INT,
+        ModuleType::UR20_2DI_P_TS,
+        ModuleType::UR20_4DI_P_TS,
+        ModuleType::UR20_4DO_P,
+        ModuleType::UR20_16DO_P,
+        ModuleType::UR20_4DO_P_2A,
+        ModuleType::UR20_4DO_PN_2A,
+        ModuleType::UR20_8DO_P,
+        ModuleType::UR20_8DO_P_2W_HD,
+        ModuleType::UR20_16DO_P_PLC_INT,
+        ModuleType::UR20_4DO_N,
+        ModuleType::UR20_4DO_N_2A,
+        ModuleType::UR20_8DO_N,
+        ModuleType::UR20_16DO_N,
+        ModuleType::UR20_16DO_N_PLC_INT,
+        ModuleType::UR20_4RO_SSR_255,
+        ModuleType::UR20_4RO_CO_255,
+        ModuleType::UR20_4AO_UI_16,
+        ModuleType::UR20_4AO_UI_16_DIAG,
+        ModuleType::UR20_4AO_UI_16_M,
+        ModuleType::UR20_4AO_UI_16_M_DIAG,
+        ModuleType::UR20_4AO_UI_16_HD,
+        ModuleType::UR20_4AO_UI_16_DIAG_HD,
+        ModuleType::UR20_4AI_RTD_DIAG,
+        ModuleType::UR20_4AI_TC_DIAG,
+        ModuleType::UR20_4AI_UI_16_DIAG,
+        ModuleType::UR20_4AI_UI_12,
+        ModuleType::UR20_4AI_UI_16,
+        ModuleType::UR20_4AI_UI_16_HD,
+        ModuleType::UR20_4AI_UI_16_DIAG_HD,
+        ModuleType::UR20_4AI_UI_DIF_16_DIAG,
+        ModuleType::UR20_8AI_I_16_DIAG_HD,
+        ModuleType::UR20_8AI_I_16_HD,
+        ModuleType::UR20_8AI_I_PLC_INT,
+        ModuleType::UR20_2FCNT_100,
+        ModuleType::UR20_1COM_232_485_422,
+        ModuleType::UR20_1SSI,
+        ModuleType::UR20_4COM_IO_LINK,
+        ModuleType::UR20_2PWM_PN_0_5A,
+        ModuleType::UR20_2PWM_PN_2A,
+        ModuleType::UR20_3EM_230V_AC,
+        ModuleType::UR20_PF_I,
+        ModuleType::UR20_PF_O,
+        ModuleType::UR20_PF_O_1DI_SIL,
+        ModuleType::UR20_PF_O_2DI_SIL,
+        ModuleType::UR20_PF_O_2DI_DELAY_SIL,
+    ];
 
-            assert_eq!(inputs[0][0], ChannelValue::Bit(true));
-            assert_eq!(inputs[0][1], ChannelValue::Bit(false));
-            assert_eq!(inputs[0][2], ChannelValue::Bit(true));
-            assert_eq!(inputs[0][3], ChannelValue::Bit(false));
+    /// Parameters with mostly zero values that are valid for the module.
+    fn valid_params(t: &ModuleType) -> Vec<u16> {
+        let mut params = vec![0; t.param_register_count() as usize];
+        if *t == ModuleType::UR20_1SSI {
+            params[0] = 25; // bit count
+        }
+        if *t == ModuleType::UR20_2PWM_PN_0_5A || *t == ModuleType::UR20_2PWM_PN_2A {
+            params[0] = 100; // frequency
+            params[3] = 100;
+        }
+        if *t == ModuleType::UR20_3EM_230V_AC {
+            params[1] = 5; // primary current
+        }
+        params
+    }
 
-            assert_eq!(outputs[1][0], ChannelValue::Bit(false));
-            assert_eq!(outputs[1][1], ChannelValue::Bit(false));
-            assert_eq!(outputs[1][2], ChannelValue::Bit(true));
-            assert_eq!(outputs[1][3], ChannelValue::Bit(true));
+    /// Places the process data of the modules one after another.
+    fn generated_offsets(modules: &[Box<dyn ProcessModbusTcpData>]) -> Vec<u16> {
+        let mut input = ADDR_PACKED_PROCESS_INPUT_DATA;
+        let mut output = ADDR_PACKED_PROCESS_OUTPUT_DATA;
+        let mut offsets = vec![];
+        for m in modules {
+            for (cnt, addr) in [
+                (m.process_output_byte_count(), &mut output),
+                (m.process_input_byte_count(), &mut input),
+            ] {
+                if cnt == 0 {
+                    offsets.push(0xFFFF);
+                } else {
+                    offsets.push(to_bit_address(*addr, 0));
+                    *addr += cmp::max(cnt / 2, 1) as u16;
+                }
+            }
+        }
+        offsets
+    }
 
-            assert_eq!(outputs[0], vec![ChannelValue::None; 4]);
-            assert_eq!(inputs[1], vec![ChannelValue::None; 4]);
+    #[test]
+    fn only_supported_modules_can_be_created() {
+        for (_, t) in crate::MODULE_IDS {
+            if SUPPORTED_MODULES.contains(t) {
+                continue;
+            }
+            assert_eq!(
+                module_from_parameter_data(t, &[0; 64]).err(),
+                Some(Error::UnknownModule),
+                "{:?} is supported but not part of the test matrix",
+                t
+            );
+        }
+    }
 
-            assert_eq!(inputs[2], vec![ChannelValue::Bytes(vec![0, 0, 0xCD, 0xAB])]);
-            assert_eq!(outputs[2], vec![ChannelValue::None]);
+    #[test]
+    fn create_default_modules() {
+        for t in SUPPORTED_MODULES {
+            let m = default_module(t).unwrap();
+            assert_eq!(m.module_type(), *t);
+            let params = m.to_modbus_parameter_data();
+            assert_eq!(params.len(), t.param_register_count() as usize);
+            assert!(module_from_parameter_data(t, &params).is_ok());
         }
+    }
 
-        c.set_output(
-            &Address {
-                module: 2,
-                channel: 0,
-            },
-            ChannelValue::Bytes(b"Hello modbus coupler!".to_vec()),
-        )
-        .unwrap();
-        c.set_output(
-            &Address {
-                module: 1,
-                channel: 1,
-            },
-            ChannelValue::Bit(true),
-        )
-        .unwrap();
-        assert!(c
-            .set_output(
-                &Address {
-                    module: 3,
-                    channel: 0,
-                },
-                ChannelValue::Bit(true)
-            )
-            .is_err());
-        assert!(c
-            .set_output(
-                &Address {
-                    module: 2,
-                    channel: 1,
-                },
-                ChannelValue::Bit(true)
-            )
-            .is_err());
+    #[test]
+    fn process_data_of_all_supported_modules() {
+        let params: Vec<_> = SUPPORTED_MODULES.iter().map(valid_params).collect();
+        let modules: Vec<_> = SUPPORTED_MODULES
+            .iter()
+            .zip(&params)
+            .map(|(t, p)| module_from_parameter_data(t, p).unwrap())
+            .collect();
+        let cfg = CouplerConfig {
+            modules: SUPPORTED_MODULES.to_vec(),
+            offsets: generated_offsets(&modules),
+            params,
+        };
+        let mut c = Coupler::new(&cfg).unwrap();
 
-        assert_eq!(c.write.len(), 2);
+        let mut expected = HashMap::new();
+        for (nr, t) in SUPPORTED_MODULES.iter().enumerate() {
+            for ch in 0..t.channel_count() {
+                let v = match ModuleCategory::from(t.clone()) {
+                    ModuleCategory::DO => ChannelValue::Bit(ch % 2 == 0),
+                    ModuleCategory::AO => ChannelValue::Decimal32((ch as f32 + 1.0) * 5.0),
+                    _ => continue,
+                };
+                let addr = Address {
+                    module: nr,
+                    channel: ch,
+                };
+                c.set_output(&addr, v.clone()).unwrap();
+                expected.insert(addr, v);
+            }
+        }
+        assert!(!expected.is_empty());
 
-        let process_input_data = vec![0b_0101, 0, 0, 0, 0];
-        let process_output_data = c.next(&process_input_data, &process_output_data).unwrap();
-        assert_eq!(c.write.len(), 0);
-        {
-            let inputs = c.inputs();
-            let outputs = c.outputs();
-            assert_eq!(outputs[1][1], ChannelValue::Bit(false));
-            assert_eq!(inputs[2][0], ChannelValue::None);
-            assert_eq!(outputs[2][0], ChannelValue::None);
+        let input = vec![0x0001; c.process_input_len()];
+        let mut output = vec![0; c.process_output_len()];
+        for _ in 0..3 {
+            output = c.next(&input, &output).unwrap();
+            assert_eq!(output.len(), c.process_output_len());
         }
-        let process_output_data = c.next(&process_input_data, &process_output_data).unwrap();
-        {
-            let inputs = c.inputs();
-            let outputs = c.outputs();
-            assert_eq!(outputs[1][1], ChannelValue::Bit(true));
-            assert_eq!(inputs[2][0], ChannelValue::None);
-            assert_eq!(outputs[2][0], ChannelValue::Bytes(b"Hello ".to_vec()));
+        assert_eq!(c.inputs().len(), SUPPORTED_MODULES.len());
+        for (nr, t) in SUPPORTED_MODULES.iter().enumerate() {
+            assert_eq!(c.inputs()[nr].len(), t.channel_count(), "{:?}", t);
+            assert_eq!(c.outputs()[nr].len(), t.channel_count(), "{:?}", t);
         }
-        let process_output_data = c.next(&process_input_data, &process_output_data).unwrap();
-        {
-            let outputs = c.outputs();
-            assert_eq!(outputs[2][0], ChannelValue::None);
+        for (addr, v) in expected {
+            assert_eq!(
+                c.outputs()[addr.module][addr.channel],
+                v,
+                "{:?}",
+                SUPPORTED_MODULES[addr.module]
+            );
         }
+    }
 
-        let process_input_data = vec![
-            0b_0101,               // module input for DI_P
-            0b_00000101_1111_1001, // len & status (bit 3&4: RX_CNT , bit 5&6: TX_CNT_ACK)
-            0xDDEE,                // data
-            0xFFFF,                // data
-            0x00AA,                // data
-        ];
-        let _process_output_data = c.next(&process_input_data, &process_output_data).unwrap();
+    #[test]
+    fn output_values_of_all_supported_modules() {
+        for t in SUPPORTED_MODULES {
+            let m = module_from_parameter_data(t, &valid_params(t)).unwrap();
+            let cnt = t.channel_count();
+            let empty = m.process_output_values(&[]).unwrap();
+            assert_eq!(empty.len(), m.process_output_byte_count().div_ceil(2));
+            assert!(empty.iter().all(|w| *w == 0), "{:?}", t);
+            for v in [ChannelValue::None, ChannelValue::Disabled] {
+                assert_eq!(
+                    m.process_output_values(&vec![v; cnt]).unwrap(),
+                    empty,
+                    "{:?}",
+                    t
+                );
+            }
+            assert_eq!(
+                m.process_output_values(&vec![ChannelValue::None; cnt + 1])
+                    .err(),
+                Some(Error::ChannelValue),
+                "{:?}",
+                t
+            );
+        }
+    }
 
-        assert!(c.reader(0).is_none());
-        assert!(c.reader(1).is_none());
-        assert!(c.reader(2).is_some());
-        assert!(c.writer(0).is_none());
-        assert!(c.writer(1).is_none());
-        assert!(c.writer(2).is_some());
-        let mut buf = [0; 20];
-        let reader = c.reader(2).unwrap();
-        let len_0 = reader.read(&mut buf).unwrap();
-        let len_1 = reader.read(&mut buf).unwrap();
-        assert_eq!(len_0, 9);
-        assert_eq!(len_1, 0);
+    #[test]
+    fn layouts_of_all_supported_modules() {
+        for t in SUPPORTED_MODULES {
+            let m = module_from_parameter_data(t, &valid_params(t)).unwrap();
+            let layout = m.layout();
+            assert_eq!(
+                layout.input.len(),
+                m.process_input_byte_count().div_ceil(2),
+                "{:?}",
+                t
+            );
+            assert_eq!(
+                layout.output.len(),
+                m.process_output_byte_count().div_ceil(2),
+                "{:?}",
+                t
+            );
+        }
+        let cfg = CouplerConfig {
+            modules: vec![ModuleType::UR20_4DI_P, ModuleType::UR20_1COM_232_485_422],
+            offsets: vec![0xFFFF, 0x0000, 0x8000, 0x0010],
+            params: vec![vec![0; 4], vec![0; 10]],
+        };
+        let layouts = Coupler::new(&cfg).unwrap().layouts();
+        assert_eq!(layouts[0].input, vec!["channel 0 - 3 (bit 0 - 3)"]);
+        assert!(layouts[0].output.is_empty());
         assert_eq!(
-            &buf[0..9],
-            &[0, 0, 0xCD, 0xAB, 0xEE, 0xDD, 0xFF, 0xFF, 0xAA]
+            layouts[1].output[0],
+            "control (low byte), data length (high byte)"
         );
+        assert_eq!(layouts[1].input[3], "data bytes 4 - 5");
     }
 
     #[test]
-    fn test_module_list_from_registers() {
+    fn access_fields_of_structured_values() {
+        let cfg = CouplerConfig {
+            modules: vec![ModuleType::UR20_2FCNT_100],
+            offsets: vec![0x8000, 0x0000],
+            params: vec![vec![0; 2]],
+        };
+        let mut c = Coupler::new(&cfg).unwrap();
+        let ch0 = Address {
+            module: 0,
+            channel: 0,
+        };
+        c.next(&[0; 10], &[0; 6]).unwrap();
+        c.set_output_field(&ch0.sub("duration"), ChannelValue::Decimal32(0.5))
+            .unwrap();
+        c.set_output_field(&ch0.sub("start"), ChannelValue::Bit(true))
+            .unwrap();
         assert_eq!(
-            module_list_from_registers(&vec![]).err().unwrap(),
-            Error::RegisterCount
+            c.set_output_field(&ch0.sub("foo"), ChannelValue::Bit(true)),
+            Err(Error::Address)
         );
         assert_eq!(
-            module_list_from_registers(&vec![0xAB0C]).err().unwrap(),
-            Error::RegisterCount
+            c.set_output_field(&ch0.sub("start"), ChannelValue::Decimal32(1.0)),
+            Err(Error::ChannelValue)
         );
+
+        // 1s period (125ns ticks) with 50 rising edges
+        let input = [0x007A, 0x1200, 0x0000, 50, 0, 0, 0, 0, 0x0100, 0];
+        let output = c.next(&input, &[0; 6]).unwrap();
+        assert_eq!(output, vec![0x0007, 0xA120, 0, 0, 0x0100, 0]);
         assert_eq!(
-            module_list_from_registers(&vec![0x0101, 0x2FA0]).unwrap(),
-            vec![ModuleType::UR20_4DO_P]
+            c.input_field(&ch0.sub("frequency")),
+            Some(ChannelValue::Decimal32(50.0))
+        );
+        assert_eq!(
+            c.input_field(&ch0.sub("active")),
+            Some(ChannelValue::Bit(true))
         );
+        assert_eq!(c.input_field(&ch0.sub("foo")), None);
     }
 }
@@ -0,0 +1,93 @@
+//! Power feed modules
+//!
+//! The modules occupy a slot but have neither parameters
+//! nor process data. The state of the supply voltage is reported
+//! within the module diagnosis (`ModuleDiagnosis::supply_voltage_missing`).
+
+use super::*;
+use crate::ur20_fbc_mod_tcp::{ProcessModbusTcpData, ToModbusParameterData};
+
+#[rustfmt::skip]
+make_variants! {
+    UR20_PF_I               => 0,
+    UR20_PF_O               => 0,
+    UR20_PF_O_1DI_SIL       => 0,
+    UR20_PF_O_2DI_SIL       => 0,
+    UR20_PF_O_2DI_DELAY_SIL => 0,
+}
+
+#[derive(Debug, Clone)]
+pub struct Mod {
+    pub module_type: ModuleType,
+}
+
+impl Mod {
+    /// Creates a module instance.
+    pub fn new(module_type: ModuleType) -> Result<Mod> {
+        param_register_count(&module_type).ok_or(Error::UnknownModule)?;
+        Ok(Mod { module_type })
+    }
+}
+
+impl Module for Mod {
+    fn module_type(&self) -> ModuleType {
+        self.module_type.clone()
+    }
+}
+
+impl ToModbusParameterData for Mod {
+    fn to_modbus_parameter_data(&self) -> Vec<u16> {
+        vec![]
+    }
+}
+
+impl ProcessModbusTcpData for Mod {
+    fn process_input_byte_count(&self) -> usize {
+        0
+    }
+    fn process_output_byte_count(&self) -> usize {
+        0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::diagnosis::ModuleDiagnosis;
+    use crate::ur20_fbc_mod_tcp::ModbusParameterRegisterCount;
+
+    #[test]
+    fn create_new_module() {
+        for t in MODULE_TYPES {
+            let m = Mod::new(t.clone()).unwrap();
+            assert_eq!(m.module_type(), *t);
+            assert_eq!(t.param_register_count(), 0);
+            assert!(m.channel_info().is_empty());
+            assert!(m.to_modbus_parameter_data().is_empty());
+        }
+        assert_eq!(
+            Mod::new(ModuleType::UR20_4DI_P).err(),
+            Some(Error::UnknownModule)
+        );
+    }
+
+    #[test]
+    fn test_process_data() {
+        let m = Mod::new(ModuleType::UR20_PF_O).unwrap();
+        assert!(m.process_input_data(&[]).unwrap().is_empty());
+        assert!(m.process_output_data(&[]).unwrap().is_empty());
+        assert!(m.process_output_values(&[]).unwrap().is_empty());
+        assert!(m.process_input_data(&[0]).is_err());
+    }
+
+    #[test]
+    fn decode_diagnosis() {
+        let m = Mod::new(ModuleType::UR20_PF_I).unwrap();
+        let mut data = [0; 16];
+        data[0] = 0b0001_0001;
+        let d = ModuleDiagnosis::from_raw_data(&data, m.module_type().channel_count()).unwrap();
+        assert!(d.supply_voltage_missing);
+        assert!(d.channels.is_empty());
+    }
+}
@@ -0,0 +1,211 @@
+//! Relay output modules UR20-4RO-SSR-255 and UR20-4RO-CO-255
+//!
+//! Both modules have one output bit per channel and
+//! a substitute value for each channel, so they share
+//! the same implementation.
+
+use super::*;
+use crate::ur20_fbc_mod_tcp::{ProcessDataLayout, ProcessModbusTcpData, ToModbusParameterData};
+use crate::util::*;
+
+#[derive(Debug, Clone)]
+pub struct Mod {
+    pub module_type: ModuleType,
+    pub ch_params: Vec<ChannelParameters>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub struct ChannelParameters {
+    pub substitute_value: bool,
+}
+
+impl Mod {
+    /// Creates a module with default parameters.
+    pub fn new(module_type: ModuleType) -> Result<Mod> {
+        check_module_type(&module_type)?;
+        let ch_params = (0..module_type.channel_count())
+            .map(|_| ChannelParameters::default())
+            .collect();
+        Ok(Mod {
+            module_type,
+            ch_params,
+        })
+    }
+
+    /// Creates a module instance from its parameter register data.
+    pub fn from_modbus_parameter_data(module_type: ModuleType, data: &[u16]) -> Result<Mod> {
+        check_module_type(&module_type)?;
+        let ch_params = parameters_from_raw_data(module_type.channel_count(), data)?;
+        Ok(Mod {
+            module_type,
+            ch_params,
+        })
+    }
+}
+
+impl Module for Mod {
+    fn module_type(&self) -> ModuleType {
+        self.module_type.clone()
+    }
+}
+
+impl ToModbusParameterData for Mod {
+    fn to_modbus_parameter_data(&self) -> Vec<u16> {
+        parameters_to_raw_data(&self.ch_params)
+    }
+}
+
+impl ProcessModbusTcpData for Mod {
+    fn process_input_byte_count(&self) -> usize {
+        0
+    }
+    fn process_output_byte_count(&self) -> usize {
+        1
+    }
+    fn layout(&self) -> ProcessDataLayout {
+        ProcessDataLayout {
+            input: vec![],
+            output: util::bit_layout(self.module_type.channel_count()),
+        }
+    }
+    fn process_output_data(&self, data: &[u16]) -> Result<Vec<ChannelValue>> {
+        if data.len() != 1 {
+            return Err(Error::BufferLength);
+        }
+        Ok((0..self.module_type.channel_count())
+            .map(|i| test_bit_16(data[0], i))
+            .map(ChannelValue::Bit)
+            .collect())
+    }
+    fn process_output_values(&self, values: &[ChannelValue]) -> Result<Vec<u16>> {
+        let values = util::output_values(self, values)?;
+        let mut res = 0;
+        for (i, v) in values.iter().enumerate() {
+            match *v {
+                ChannelValue::Bit(state) => {
+                    if state {
+                        res = set_bit_16(res, i);
+                    }
+                }
+                ChannelValue::Disabled | ChannelValue::None => {
+                    // do nothing
+                }
+                _ => {
+                    return Err(Error::ChannelValue);
+                }
+            }
+        }
+        Ok(vec![res])
+    }
+}
+
+fn check_module_type(module_type: &ModuleType) -> Result<()> {
+    match *module_type {
+        ModuleType::UR20_4RO_SSR_255 | ModuleType::UR20_4RO_CO_255 => Ok(()),
+        _ => Err(Error::UnknownModule),
+    }
+}
+
+fn parameters_from_raw_data(channel_cnt: usize, data: &[u16]) -> Result<Vec<ChannelParameters>> {
+    if data.len() < channel_cnt {
+        return Err(Error::BufferLength);
+    }
+
+    let channel_parameters: Result<Vec<_>> = (0..channel_cnt)
+        .map(|i| {
+            let mut p = ChannelParameters::default();
+            p.substitute_value = match data[i] {
+                0 => false,
+                1 => true,
+                _ => {
+                    return Err(Error::ChannelParameter);
+                }
+            };
+            Ok(p)
+        })
+        .collect();
+    channel_parameters
+}
+
+fn parameters_to_raw_data(ch_params: &[ChannelParameters]) -> Vec<u16> {
+    ch_params
+        .iter()
+        .map(|p| u16::from(p.substitute_value))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::ChannelValue::*;
+
+    #[test]
+    fn create_new_module() {
+        assert!(Mod::new(ModuleType::UR20_4RO_SSR_255).is_ok());
+        assert!(Mod::new(ModuleType::UR20_4RO_CO_255).is_ok());
+        assert_eq!(
+            Mod::new(ModuleType::UR20_4DO_P).err(),
+            Some(Error::UnknownModule)
+        );
+    }
+
+    #[test]
+    fn test_process_output_values() {
+        let m = Mod::new(ModuleType::UR20_4RO_SSR_255).unwrap();
+        assert_eq!(
+            m.process_output_values(&[Bit(true), Bit(false), Bit(true), Bit(true)])
+                .unwrap(),
+            vec![0b1101]
+        );
+        assert_eq!(
+            m.process_output_values(&[Disabled, Bit(true), None, Bit(false)])
+                .unwrap(),
+            vec![0b0010]
+        );
+        assert_eq!(m.process_output_values(&[]).unwrap(), vec![0]);
+        assert!(m
+            .process_output_values(&[Bit(true), Bit(true), Bit(true)])
+            .is_err());
+        assert!(m
+            .process_output_values(&[Bit(false), Bit(true), Bit(false), Decimal32(0.0)])
+            .is_err());
+    }
+
+    #[test]
+    fn test_process_output_data() {
+        let m = Mod::new(ModuleType::UR20_4RO_SSR_255).unwrap();
+        assert!(m.process_output_data(&[0; 2]).is_err());
+        assert_eq!(
+            m.process_output_data(&[0b0101]).unwrap(),
+            vec![Bit(true), Bit(false), Bit(true), Bit(false)]
+        );
+    }
+
+    #[test]
+    fn test_parameters_from_raw_data() {
+        let p = parameters_from_raw_data(4, &[0, 1, 0, 1]).unwrap();
+        assert_eq!(p.len(), 4);
+        assert!(!p[0].substitute_value);
+        assert!(p[1].substitute_value);
+        assert!(parameters_from_raw_data(4, &[0; 3]).is_err());
+        assert!(parameters_from_raw_data(4, &[0, 2, 0, 0]).is_err());
+    }
+
+    #[test]
+    fn test_parameters_to_raw_data() {
+        let mut m = Mod::new(ModuleType::UR20_4RO_CO_255).unwrap();
+        m.ch_params[3].substitute_value = true;
+        assert_eq!(m.to_modbus_parameter_data(), vec![0, 0, 0, 1]);
+    }
+
+    #[test]
+    fn create_module_from_modbus_parameter_data() {
+        let m =
+            Mod::from_modbus_parameter_data(ModuleType::UR20_4RO_SSR_255, &[1, 0, 0, 1]).unwrap();
+        assert_eq!(m.module_type(), ModuleType::UR20_4RO_SSR_255);
+        assert!(m.ch_params[0].substitute_value);
+        assert!(m.ch_params[3].substitute_value);
+        assert!(Mod::from_modbus_parameter_data(ModuleType::UR20_4DI_P, &[0; 4]).is_err());
+    }
+}
@@ -1,5 +1,6 @@
 use super::*;
 use byteorder::{ByteOrder, LittleEndian};
+use std::{borrow::Cow, cmp};
 
 pub fn set_bit(mut val: u8, bit_nr: usize) -> u8 {
     val |= bit_mask(bit_nr) as u8;
@@ -20,7 +21,7 @@ pub fn test_bit_16(val: u16, bit_nr: usize) -> bool {
 }
 
 fn bit_mask(bit: usize) -> usize {
-    (1 << bit)
+    1 << bit
 }
 
 pub fn u16_to_u8(words: &[u16]) -> Vec<u8> {
@@ -52,91 +53,76 @@ pub fn shift_data(data: &[u16]) -> Vec<u16> {
     u8_to_u16(&shifted)
 }
 
-pub fn analog_ui_value_to_u16(v: f32, range: &AnalogUIRange, format: &DataFormat) -> u16 {
-    let factor = format.factor();
-    use crate::AnalogUIRange::*;
+/// Layout of a register with one bit per channel.
+pub fn bit_layout(channel_cnt: usize) -> Vec<String> {
+    vec![format!(
+        "channel 0 - {} (bit 0 - {})",
+        channel_cnt - 1,
+        channel_cnt - 1
+    )]
+}
 
-    #[rustfmt::skip]
-    let v = match *range {
-        mA0To20       => (factor * v / 20.0),
-        mA4To20       => (factor * (v - 4.0) / 16.0),
-        V0To10        |
-        VMinus10To10  => (factor * v / 10.0),
-        V0To5         |
-        VMinus5To5    => (factor * v / 5.0),
-        V1To5         => (factor * (v - 1.0) / 4.0),
-        V2To10        => (factor * (v - 2.0) / 8.0),
-        Disabled      => 0.0,
-    };
-    v as u16
+/// Layout of registers with one register per channel.
+pub fn channel_layout(channel_cnt: usize) -> Vec<String> {
+    (0..channel_cnt).map(|i| format!("channel {}", i)).collect()
 }
 
-pub fn u16_to_analog_ui_value(
-    data: u16,
-    range: &AnalogUIRange,
-    format: &DataFormat,
-) -> Option<f32> {
-    let factor = format.factor();
-    use crate::AnalogUIRange::*;
-    let data = f32::from(data as i16);
+/// Levenshtein distance of two strings.
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut prev = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let cost = if ca == *cb { prev } else { prev + 1 };
+            prev = row[j + 1];
+            row[j + 1] = cmp::min(cost, cmp::min(row[j], row[j + 1]) + 1);
+        }
+    }
+    row[b.len()]
+}
 
-    #[cfg_attr(rustfmt, rustfmt_skip)]
-    match *range {
-        mA0To20         => Some(data * 20.0 / factor),
-        mA4To20         => Some(data * 16.0 / factor + 4.0),
-        V0To10          |
-        VMinus10To10    => Some(data * 10.0 / factor),
-        V0To5           |
-        VMinus5To5      => Some(data * 5.0 / factor),
-        V1To5           => Some(data * 4.0 / factor + 1.0),
-        V2To10          => Some(data * 8.0 / factor + 2.0),
-        Disabled        => None,
+/// Checks that there are parameters for each channel of the module.
+pub fn check_channel_count<T>(m: &dyn Module, ch_params: &[T]) -> Result<()> {
+    if ch_params.len() != m.module_type().channel_count() {
+        return Err(Error::ChannelParameter);
     }
+    Ok(())
 }
 
-pub fn u16_to_rtd_value(data: u16, range: &RtdRange) -> Option<f32> {
-    use crate::RtdRange::*;
+/// Checks the number of output values of a module.
+///
+/// An empty slice is expanded to `ChannelValue::None` for each channel
+/// (see `ProcessModbusTcpData::process_output_values`).
+pub fn output_values<'a, M: Module + ?Sized>(
+    m: &M,
+    values: &'a [ChannelValue],
+) -> Result<Cow<'a, [ChannelValue]>> {
+    let cnt = m.module_type().channel_count();
+    if values.is_empty() {
+        return Ok(Cow::Owned(vec![ChannelValue::None; cnt]));
+    }
+    if values.len() != cnt {
+        return Err(Error::ChannelValue);
+    }
+    Ok(Cow::Borrowed(values))
+}
 
-    #[cfg_attr(rustfmt, rustfmt_skip)]
-    match *range {
-        PT100  |
-        PT200  |
-        PT500  |
-        PT1000 |
-        NI100  |
-        NI120  |
-        NI200  |
-        NI500  |
-        NI1000 |
-        Cu10   => {
-            Some(f32::from(data as i16) / 10.0)
-        }
-        R40   |
-        R80   |
-        R150  |
-        R300  |
-        R500  |
-        R1000 |
-        R2000 |
-        R4000 => {
-            let n = match *range {
-                R40   => 40.0,
-                R80   => 80.0,
-                R150  => 150.0,
-                R300  => 300.0,
-                R500  => 500.0,
-                R1000 => 1000.0,
-                R2000 => 2000.0,
-                R4000 => 4000.0,
-                _ => {
-                    unreachable!()
-                }
-            };
-            let d = n * u32::from(data) as f32 / 0x6C00 as f32;
-            Some(d)
+/// Calculates the Modbus RTU CRC.
+pub fn crc16(data: &[u8]) -> u16 {
+    let mut crc = 0xFFFF;
+    for b in data {
+        crc ^= u16::from(*b);
+        for _ in 0..8 {
+            if crc & 1 != 0 {
+                crc = (crc >> 1) ^ 0xA001;
+            } else {
+                crc >>= 1;
+            }
         }
-        Disabled => None
     }
+    crc
 }
 
 #[cfg(test)]
@@ -144,8 +130,8 @@ mod tests {
 
     #[test]
     fn test_bit() {
-        assert_eq!(super::test_bit(0b10, 0), false);
-        assert_eq!(super::test_bit(0b10, 1), true);
+        assert!(!super::test_bit(0b10, 0));
+        assert!(super::test_bit(0b10, 1));
     }
 
     #[test]
@@ -156,7 +142,7 @@ mod tests {
 
     #[test]
     fn u16_to_u8() {
-        assert_eq!(super::u16_to_u8(&[]), vec![]);
+        assert_eq!(super::u16_to_u8(&[]), Vec::<u8>::new());
         assert_eq!(super::u16_to_u8(&[0xABCD]), vec![0xCD, 0xAB]);
         assert_eq!(
             super::u16_to_u8(&[0xAB, 0xCD]),
@@ -166,111 +152,24 @@ mod tests {
 
     #[test]
     fn u8_to_u16() {
-        assert_eq!(super::u8_to_u16(&[]), vec![]);
+        assert_eq!(super::u8_to_u16(&[]), Vec::<u16>::new());
         assert_eq!(super::u8_to_u16(&[0xAB]), vec![0xAB]);
         assert_eq!(super::u8_to_u16(&[0xA, 0xB]), vec![0x0B0A]);
         assert_eq!(super::u8_to_u16(&[0xA, 0xB, 0xC]), vec![0x0B0A, 0xC]);
     }
 
     #[test]
-    fn shift_data() {
-        assert_eq!(super::shift_data(&vec![0xABCD]), vec![0x00AB]);
-    }
-
-    #[test]
-    fn test_u16_to_analog_ui_value() {
-        use super::*;
-        assert_eq!(
-            u16_to_analog_ui_value(0x3600, &AnalogUIRange::mA0To20, &DataFormat::S7),
-            Some(10.0)
-        );
-        assert_eq!(
-            u16_to_analog_ui_value(0x2000, &AnalogUIRange::mA0To20, &DataFormat::S5),
-            Some(10.0)
-        );
-
-        assert_eq!(
-            u16_to_analog_ui_value(0x3600, &AnalogUIRange::mA4To20, &DataFormat::S7),
-            Some(12.0)
-        );
-        assert_eq!(
-            u16_to_analog_ui_value(0x2000, &AnalogUIRange::mA4To20, &DataFormat::S5),
-            Some(12.0)
-        );
-
-        assert_eq!(
-            u16_to_analog_ui_value(0x3600, &AnalogUIRange::V0To10, &DataFormat::S7),
-            Some(5.0)
-        );
-        assert_eq!(
-            u16_to_analog_ui_value(0x2000, &AnalogUIRange::V0To10, &DataFormat::S5),
-            Some(5.0)
-        );
-
-        assert_eq!(
-            u16_to_analog_ui_value(0x3600, &AnalogUIRange::VMinus10To10, &DataFormat::S7),
-            Some(5.0)
-        );
-        assert_eq!(
-            u16_to_analog_ui_value(0x2000, &AnalogUIRange::VMinus10To10, &DataFormat::S5),
-            Some(5.0)
-        );
-
-        assert_eq!(
-            u16_to_analog_ui_value(0x3600, &AnalogUIRange::V2To10, &DataFormat::S7),
-            Some(6.0)
-        );
-        assert_eq!(
-            u16_to_analog_ui_value(0x2000, &AnalogUIRange::V2To10, &DataFormat::S5),
-            Some(6.0)
-        );
-
-        assert_eq!(
-            u16_to_analog_ui_value(0x3600, &AnalogUIRange::V1To5, &DataFormat::S7),
-            Some(3.0)
-        );
-        assert_eq!(
-            u16_to_analog_ui_value(0x2000, &AnalogUIRange::V1To5, &DataFormat::S5),
-            Some(3.0)
-        );
-
-        assert_eq!(
-            u16_to_analog_ui_value(0x3600, &AnalogUIRange::V0To5, &DataFormat::S7),
-            Some(2.5)
-        );
-        assert_eq!(
-            u16_to_analog_ui_value(0x2000, &AnalogUIRange::V0To5, &DataFormat::S5),
-            Some(2.5)
-        );
-
-        assert_eq!(
-            u16_to_analog_ui_value(0x3600, &AnalogUIRange::VMinus5To5, &DataFormat::S7),
-            Some(2.5)
-        );
-        assert_eq!(
-            u16_to_analog_ui_value(0xCA00, &AnalogUIRange::VMinus5To5, &DataFormat::S7),
-            Some(-2.5)
-        );
-        assert_eq!(
-            u16_to_analog_ui_value(0x2000, &AnalogUIRange::VMinus5To5, &DataFormat::S5),
-            Some(2.5)
-        );
-        assert_eq!(
-            u16_to_analog_ui_value(0xE000, &AnalogUIRange::VMinus5To5, &DataFormat::S5),
-            Some(-2.5)
-        );
-        assert_eq!(
-            u16_to_analog_ui_value(0xE000, &AnalogUIRange::Disabled, &DataFormat::S5),
-            None
-        );
+    fn edit_distance() {
+        assert_eq!(super::edit_distance("", ""), 0);
+        assert_eq!(super::edit_distance("abc", ""), 3);
+        assert_eq!(super::edit_distance("", "abc"), 3);
+        assert_eq!(super::edit_distance("kitten", "sitting"), 3);
+        assert_eq!(super::edit_distance("UR20_4DI_P", "UR20_4DI_P"), 0);
+        assert_eq!(super::edit_distance("UR20_4DI_PP", "UR20_4DI_P"), 1);
     }
 
     #[test]
-    fn test_analog_ui_value_to_u16() {
-        use super::*;
-        assert_eq!(
-            analog_ui_value_to_u16(10.0, &AnalogUIRange::mA0To20, &DataFormat::S7),
-            0x3600
-        );
+    fn shift_data() {
+        assert_eq!(super::shift_data(&[0xABCD]), vec![0x00AB]);
     }
 }
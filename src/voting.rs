@@ -0,0 +1,170 @@
+//! Voting of redundant digital inputs.
+
+use super::*;
+
+/// The logic used to combine the inputs of a group.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VotingLogic {
+    /// All inputs need to be `true`.
+    And,
+    /// At least one input needs to be `true`.
+    Or,
+    /// At least `m` inputs need to be `true`
+    /// (e.g. `MOutOfN(2)` with three inputs is a 2oo3 voting).
+    MOutOfN(usize),
+}
+
+/// A group of redundant input channels.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VotingGroup {
+    /// The addresses of the input channels.
+    pub inputs: Vec<Address>,
+    /// How the inputs are combined.
+    pub logic: VotingLogic,
+}
+
+/// The result of a voting.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VotingResult {
+    /// The resulting value.
+    /// It is `None` if the result depends on inputs
+    /// that have no valid value.
+    pub value: Option<bool>,
+    /// The inputs don't agree or at least one input has no valid value.
+    pub discrepancy: bool,
+}
+
+impl VotingGroup {
+    /// Creates a 2oo3 voting group.
+    pub fn two_out_of_three(a: Address, b: Address, c: Address) -> Self {
+        VotingGroup {
+            inputs: vec![a, b, c],
+            logic: VotingLogic::MOutOfN(2),
+        }
+    }
+
+    fn required(&self) -> usize {
+        match self.logic {
+            VotingLogic::And => self.inputs.len(),
+            VotingLogic::Or => 1,
+            VotingLogic::MOutOfN(m) => m,
+        }
+    }
+
+    pub(crate) fn validate(&self) -> Result<()> {
+        if self.inputs.is_empty() {
+            return Err(Error::Address);
+        }
+        match self.logic {
+            VotingLogic::MOutOfN(m) if m == 0 || m > self.inputs.len() => {
+                Err(Error::ChannelParameter)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Evaluates the group based on the channel values of all modules.
+    pub fn evaluate(&self, values: &[Vec<ChannelValue>]) -> VotingResult {
+        let mut active = 0;
+        let mut inactive = 0;
+        let mut unknown = 0;
+        for addr in &self.inputs {
+            match values.get(addr.module).and_then(|m| m.get(addr.channel)) {
+                Some(ChannelValue::Bit(true)) => active += 1,
+                Some(ChannelValue::Bit(false)) => inactive += 1,
+                _ => unknown += 1,
+            }
+        }
+        let required = self.required();
+        let value = if active >= required {
+            Some(true)
+        } else if active + unknown < required {
+            Some(false)
+        } else {
+            None
+        };
+        VotingResult {
+            value,
+            discrepancy: unknown > 0 || (active > 0 && inactive > 0),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::ChannelValue::*;
+
+    fn addr(channel: usize) -> Address {
+        Address { module: 0, channel }
+    }
+
+    #[test]
+    fn evaluate_and_or() {
+        let mut g = VotingGroup {
+            inputs: vec![addr(0), addr(1)],
+            logic: VotingLogic::And,
+        };
+        let values = vec![vec![Bit(true), Bit(false)]];
+        let res = g.evaluate(&values);
+        assert_eq!(res.value, Some(false));
+        assert!(res.discrepancy);
+
+        g.logic = VotingLogic::Or;
+        let res = g.evaluate(&values);
+        assert_eq!(res.value, Some(true));
+        assert!(res.discrepancy);
+
+        let res = g.evaluate(&[vec![Bit(true), Bit(true)]]);
+        assert_eq!(res.value, Some(true));
+        assert!(!res.discrepancy);
+    }
+
+    #[test]
+    fn evaluate_two_out_of_three() {
+        let g = VotingGroup::two_out_of_three(addr(0), addr(1), addr(2));
+        let res = g.evaluate(&[vec![Bit(true), Bit(false), Bit(true)]]);
+        assert_eq!(res.value, Some(true));
+        assert!(res.discrepancy);
+
+        let res = g.evaluate(&[vec![Bit(false), Bit(false), Bit(true)]]);
+        assert_eq!(res.value, Some(false));
+        assert!(res.discrepancy);
+
+        let res = g.evaluate(&[vec![Bit(false), Bit(false), Bit(false)]]);
+        assert_eq!(res.value, Some(false));
+        assert!(!res.discrepancy);
+    }
+
+    #[test]
+    fn validate_groups() {
+        let mut g = VotingGroup::two_out_of_three(addr(0), addr(1), addr(2));
+        assert!(g.validate().is_ok());
+        g.logic = VotingLogic::MOutOfN(3);
+        assert!(g.validate().is_ok());
+        g.logic = VotingLogic::MOutOfN(0);
+        assert_eq!(g.validate(), Err(Error::ChannelParameter));
+        g.logic = VotingLogic::MOutOfN(4);
+        assert_eq!(g.validate(), Err(Error::ChannelParameter));
+        g.inputs.clear();
+        g.logic = VotingLogic::Or;
+        assert_eq!(g.validate(), Err(Error::Address));
+    }
+
+    #[test]
+    fn evaluate_with_invalid_inputs() {
+        let g = VotingGroup::two_out_of_three(addr(0), addr(1), addr(2));
+        let res = g.evaluate(&[vec![Bit(true), Bit(true), Disabled]]);
+        assert_eq!(res.value, Some(true));
+        assert!(res.discrepancy);
+
+        let res = g.evaluate(&[vec![Bit(true), Bit(false), None]]);
+        assert_eq!(res.value, Option::None);
+        assert!(res.discrepancy);
+
+        let res = g.evaluate(&[]);
+        assert_eq!(res.value, Option::None);
+        assert!(res.discrepancy);
+    }
+}